@@ -1,41 +1,130 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
 use serde::Serialize;
 
-use crate::changeset::{FileChange, MultiFileChangeset, OpKind, TransactionMode};
+use crate::changeset::{FileChange, MultiFileChangeset, OpKind, TransactionMode, TransactionSpec};
 use crate::error::IdenteditError;
-use crate::execution_context::ExecutionContext;
-
+use crate::path_style::render_path;
+use crate::project_config::{ChangeGuards, ProtectedRegionRule};
+use crate::provider::{ProviderOverrides, ProviderRegistry};
+
+mod byte_span;
+mod dependencies;
+mod groups;
+mod guards;
 mod io;
 mod move_ops;
+mod normalize;
+mod optional;
+mod partial;
 mod preflight;
+mod protected;
 mod replacements;
-
+mod secrets;
+mod stats;
+mod verify;
+mod whole_file;
+
+pub use byte_span::write_byte_span;
+use dependencies::resolve_declared_dependencies;
+use groups::summarize_committed_groups;
+pub use groups::GroupSummary;
+pub(crate) use io::STREAMING_APPLY_MIN_BYTES;
+pub use io::Durability;
+pub use normalize::Normalization;
 use move_ops::{
     commit_move_plans, preflight_move_plans, rollback_committed_moves,
     validate_move_operation_constraints,
 };
+use optional::drop_missing_optional_files;
+use partial::apply_changesets_continue_on_error;
+pub use partial::{
+    ApplyOperationOutcomes, FailedOperation, OperationLabels, OperationRef, SkippedOperation,
+};
 use preflight::{
-    commit_preflight_batch, preflight_changesets_in_order, prepare_commit_batch,
+    PreflightFilePlan, commit_preflight_batch, preflight_changesets_in_order, prepare_commit_batch,
     rollback_committed_files,
 };
+pub use stats::{ChangeStats, FileChangeStats};
+pub use verify::{
+    OperationStatus, VerifiedOperation, VerifyResponse, VerifySummary, verify_multi_file_changeset,
+};
+pub use whole_file::{WholeFilePrecondition, write_whole_file};
 
 #[cfg(test)]
 use io::{
-    ApplyGuardState, AtomicWritePhase, acquire_apply_lock, capture_path_fingerprint,
-    verify_apply_guard_state, write_text_atomically_with_hook,
-    write_text_atomically_with_hook_and_rename,
+    ApplyGuardState, AtomicWritePhase, PlannedText, Splice, acquire_apply_lock,
+    capture_path_fingerprint, verify_apply_guard_state, write_planned_atomically,
+    write_text_atomically_with_hook, write_text_atomically_with_hook_and_rename,
 };
 #[cfg(test)]
 use move_ops::commit_move_plan_with_rename;
 #[cfg(test)]
 use preflight::FileRollbackSnapshot;
 #[cfg(test)]
-use replacements::{ResolvedReplacement, apply_replacements_to_text, ensure_non_overlapping};
+use replacements::{
+    ResolvedReplacement, apply_replacements_to_text, ensure_non_overlapping,
+    splices_for_replacements,
+};
+
+/// What the caller of an apply decides rather than the plan: carried on
+/// [`TransactionSpec::settings`], never read from or written to plan JSON.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ApplySettings {
+    /// What atomic rewrites fsync.
+    pub durability: Durability,
+    /// Limits on how much one apply may delete.
+    pub guards: ChangeGuards,
+    /// Refuse writes whose new text looks like it holds a credential.
+    pub secret_scan: bool,
+    /// Config line ranges no write may touch, besides the marked ones.
+    pub protected_regions: Vec<ProtectedRegionRule>,
+    /// Directory the `protected_regions` paths are relative to; the current directory
+    /// when empty.
+    pub project_root: PathBuf,
+    /// Session the apply acts for: its own leases do not block it.
+    pub session: Option<String>,
+    /// Provider overrides to resolve targets with, matching the ones the plan was built with.
+    pub providers: ProviderOverrides,
+    /// List `invalidated_identities` in the stats, parsing each file before and after.
+    pub invalidated_identities: bool,
+}
+
+impl ApplySettings {
+    /// The registry targets resolve against under these settings.
+    pub fn provider_registry(&self) -> Result<ProviderRegistry, IdenteditError> {
+        ProviderRegistry::with_overrides(self.providers.clone())
+    }
+}
 
 #[derive(Debug, Clone, Serialize)]
 pub struct ApplyResponse {
     pub applied: Vec<ApplyFileResult>,
     pub summary: ApplySummary,
     pub transaction: ApplyTransaction,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operations: Option<ApplyOperationOutcomes>,
+    /// Optional operations left undone because their target node, line, or file is gone;
+    /// they count neither as applied nor as failed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub targets_missing_ignored: Vec<OperationRef>,
+}
+
+impl ApplyResponse {
+    /// A committed response for a request that leaves every file as it is.
+    pub fn unchanged() -> Self {
+        ApplyResponse {
+            applied: Vec::new(),
+            summary: summarize_apply_results(&[]),
+            transaction: ApplyTransaction {
+                mode: TransactionMode::AllOrNothing,
+                status: TransactionStatus::Committed,
+            },
+            operations: None,
+            targets_missing_ignored: Vec::new(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -44,6 +133,15 @@ pub struct ApplyFileResult {
     pub operations_applied: usize,
     pub operations_total: usize,
     pub status: ApplyFileStatus,
+    /// Ownership or extended attributes that could not be carried over to the rewritten file.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub metadata_warnings: Vec<String>,
+    /// What the transaction's whitespace policy changed beyond the operations themselves.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub normalized: Option<Normalization>,
+    /// Reported through `ApplySummary::files`; absent for moves and failed files.
+    #[serde(skip)]
+    pub stats: Option<ChangeStats>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -51,12 +149,21 @@ pub struct ApplySummary {
     pub files_modified: usize,
     pub operations_applied: usize,
     pub operations_failed: usize,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    pub bytes_delta: i64,
+    /// Line, byte, and identity stats for each rewritten file.
+    pub files: Vec<FileChangeStats>,
+    /// Operation counts per `group`, for plans whose operations name one.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub groups: BTreeMap<String, GroupSummary>,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum ApplyFileStatus {
     Applied,
+    Failed,
     RolledBack,
     RollbackFailed,
 }
@@ -65,6 +172,7 @@ pub enum ApplyFileStatus {
 #[serde(rename_all = "snake_case")]
 pub enum TransactionStatus {
     Committed,
+    Partial,
     DryRun,
     RolledBack,
     RollbackFailed,
@@ -95,11 +203,26 @@ fn summarize_apply_results(applied: &[ApplyFileResult]) -> ApplySummary {
                 .saturating_sub(result.operations_applied)
         })
         .sum();
+    let files = applied
+        .iter()
+        .filter(|result| result.operations_applied > 0)
+        .filter_map(|result| {
+            result.stats.clone().map(|stats| FileChangeStats {
+                file: result.file.clone(),
+                stats,
+            })
+        })
+        .collect::<Vec<_>>();
 
     ApplySummary {
         files_modified,
         operations_applied,
         operations_failed,
+        lines_added: files.iter().map(|file| file.stats.lines_added).sum(),
+        lines_removed: files.iter().map(|file| file.stats.lines_removed).sum(),
+        bytes_delta: files.iter().map(|file| file.stats.bytes_delta).sum(),
+        files,
+        groups: BTreeMap::new(),
     }
 }
 
@@ -110,49 +233,65 @@ pub fn apply_changeset(changeset: &FileChange) -> Result<ApplyResponse, Identedi
 pub fn apply_multi_file_changeset(
     changeset: &MultiFileChangeset,
 ) -> Result<ApplyResponse, IdenteditError> {
+    if changeset.transaction.mode == TransactionMode::ContinueOnError {
+        return apply_changesets_continue_on_error(changeset, false);
+    }
     apply_multi_file_changeset_with_injection(changeset, None)
 }
 
 pub fn dry_run_multi_file_changeset(
     changeset: &MultiFileChangeset,
 ) -> Result<ApplyResponse, IdenteditError> {
+    if changeset.transaction.mode == TransactionMode::ContinueOnError {
+        return apply_changesets_continue_on_error(changeset, true);
+    }
     if changeset.files.is_empty() {
         return Err(IdenteditError::InvalidRequest {
             message: "changeset.files must contain at least one file".to_string(),
         });
     }
+    resolve_declared_dependencies(changeset)?;
 
-    let move_execution_order = validate_move_operation_constraints(&changeset.files)?;
-    let edit_changesets = changeset
-        .files
+    let (files, mut targets_missing_ignored) = drop_missing_optional_files(&changeset.files);
+    let move_execution_order = validate_move_operation_constraints(&files)?;
+    let edit_changesets = files
         .iter()
         .filter(|changeset| !changeset_has_move(changeset))
         .cloned()
         .collect::<Vec<_>>();
 
-    let context = ExecutionContext::new();
-    let preflight_plans = preflight_changesets_in_order(&edit_changesets, context.registry())?;
-    let move_plans = preflight_move_plans(&move_execution_order)?;
+    let registry = changeset.transaction.settings.provider_registry()?;
+    let mut preflight_plans =
+        preflight_changesets_in_order(&edit_changesets, &changeset.transaction, &registry)?;
+    take_ignored_operations(&mut preflight_plans, &mut targets_missing_ignored);
+    let move_plans = preflight_move_plans(&move_execution_order, &changeset.transaction.settings)?;
 
     let mut applied = Vec::with_capacity(preflight_plans.len() + move_plans.len());
     for plan in preflight_plans {
         applied.push(ApplyFileResult {
-            file: plan.file.display().to_string(),
+            file: render_path(&plan.file),
             operations_applied: plan.operations_total,
             operations_total: plan.operations_total,
             status: ApplyFileStatus::Applied,
+            metadata_warnings: Vec::new(),
+            normalized: plan.normalized,
+            stats: Some(plan.stats),
         });
     }
     for plan in move_plans {
         applied.push(ApplyFileResult {
-            file: plan.source.display().to_string(),
+            file: render_path(&plan.source),
             operations_applied: plan.operations_total,
             operations_total: plan.operations_total,
             status: ApplyFileStatus::Applied,
+            metadata_warnings: Vec::new(),
+            normalized: None,
+            stats: None,
         });
     }
 
-    let summary = summarize_apply_results(&applied);
+    let mut summary = summarize_apply_results(&applied);
+    summary.groups = summarize_committed_groups(&changeset.files, &targets_missing_ignored);
     let transaction = ApplyTransaction {
         mode: TransactionMode::AllOrNothing,
         status: TransactionStatus::DryRun,
@@ -162,6 +301,8 @@ pub fn dry_run_multi_file_changeset(
         applied,
         summary,
         transaction,
+        operations: None,
+        targets_missing_ignored,
     })
 }
 
@@ -174,10 +315,12 @@ pub(crate) fn apply_multi_file_changeset_with_injection(
             message: "changeset.files must contain at least one file".to_string(),
         });
     }
+    resolve_declared_dependencies(changeset)?;
 
     let mut committed_writes = 0usize;
     apply_changesets_with_hooks(
         &changeset.files,
+        &changeset.transaction,
         || Ok(()),
         || {
             if let Some(injection) = failure_injection {
@@ -219,6 +362,7 @@ where
 {
     apply_changesets_with_hooks(
         std::slice::from_ref(changeset),
+        &TransactionSpec::default(),
         before_write_hook,
         after_verify_hook,
     )
@@ -226,6 +370,7 @@ where
 
 fn apply_changesets_with_hooks<Before, After>(
     changesets: &[FileChange],
+    transaction: &TransactionSpec,
     mut before_write_hook: Before,
     mut after_verify_hook: After,
 ) -> Result<ApplyResponse, IdenteditError>
@@ -233,18 +378,22 @@ where
     Before: FnMut() -> Result<(), IdenteditError>,
     After: FnMut() -> Result<(), IdenteditError>,
 {
-    let move_execution_order = validate_move_operation_constraints(changesets)?;
+    let plan_files = changesets;
+    let (changesets, mut targets_missing_ignored) = drop_missing_optional_files(changesets);
+    let move_execution_order = validate_move_operation_constraints(&changesets)?;
     let edit_changesets = changesets
         .iter()
         .filter(|changeset| !changeset_has_move(changeset))
         .cloned()
         .collect::<Vec<_>>();
 
-    let context = ExecutionContext::new();
-    let preflight_plans = preflight_changesets_in_order(&edit_changesets, context.registry())?;
+    let registry = transaction.settings.provider_registry()?;
+    let mut preflight_plans =
+        preflight_changesets_in_order(&edit_changesets, transaction, &registry)?;
+    take_ignored_operations(&mut preflight_plans, &mut targets_missing_ignored);
     let commit_batch = prepare_commit_batch(preflight_plans);
     let edit_rollback_snapshots = commit_batch.rollback_snapshots.clone();
-    let move_plans = preflight_move_plans(&move_execution_order)?;
+    let move_plans = preflight_move_plans(&move_execution_order, &transaction.settings)?;
 
     let mut applied = if commit_batch.preflight_plans.is_empty() {
         if !move_plans.is_empty() {
@@ -284,7 +433,8 @@ where
         }
     }
 
-    let summary = summarize_apply_results(&applied);
+    let mut summary = summarize_apply_results(&applied);
+    summary.groups = summarize_committed_groups(plan_files, &targets_missing_ignored);
     let transaction = ApplyTransaction {
         mode: TransactionMode::AllOrNothing,
         status: TransactionStatus::Committed,
@@ -294,9 +444,21 @@ where
         applied,
         summary,
         transaction,
+        operations: None,
+        targets_missing_ignored,
     })
 }
 
+/// Moves the ignored operations of `plans` to `ignored`, and drops the plans of files whose
+/// every operation was ignored so they are left as they are.
+fn take_ignored_operations(plans: &mut Vec<PreflightFilePlan>, ignored: &mut Vec<OperationRef>) {
+    plans.retain_mut(|plan| {
+        let all_ignored = plan.operations_total == 0 && !plan.ignored.is_empty();
+        ignored.append(&mut plan.ignored);
+        !all_ignored
+    });
+}
+
 fn changeset_has_move(changeset: &FileChange) -> bool {
     changeset
         .operations
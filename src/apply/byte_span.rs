@@ -0,0 +1,98 @@
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use crate::changeset::{TransactionMode, TransactionSpec};
+use crate::error::IdenteditError;
+use crate::hash::hash_bytes;
+use crate::path_style::render_path;
+
+use super::guards::{check_fraction_changed, line_count};
+use super::io::{
+    acquire_apply_lock, capture_apply_guard_state, prepare_write_target, write_bytes_atomically,
+};
+use super::protected::{check_protected_rewrite, protected_regions};
+use super::secrets::check_new_secrets;
+use super::stats::ChangeStats;
+use super::{
+    ApplyFileResult, ApplyFileStatus, ApplyResponse, ApplySettings, ApplyTransaction,
+    TransactionStatus, summarize_apply_results,
+};
+
+/// Replaces bytes `span` of `path` with `replacement`, for files that need not be UTF-8 or
+/// parse at all. The bytes must still hash to `expected_hash`, or the write fails with
+/// `precondition_failed`. The write takes the apply lock and the same write guards as a
+/// changeset apply, and protected regions, change guards, and the secret scan see the
+/// file's text with invalid UTF-8 replaced.
+pub fn write_byte_span(
+    path: &Path,
+    span: Range<usize>,
+    expected_hash: &str,
+    replacement: &[u8],
+    settings: &ApplySettings,
+) -> Result<ApplyResponse, IdenteditError> {
+    prepare_write_target(path, &TransactionSpec::default())?;
+    let _lock = acquire_apply_lock(path, settings.session.as_deref())?;
+    let guard_state = capture_apply_guard_state(path)?;
+    let original = fs::read(path).map_err(|error| IdenteditError::io(path, error))?;
+    if span.start > span.end || span.end > original.len() {
+        return Err(IdenteditError::InvalidRequest {
+            message: format!(
+                "Byte span {}:{} is outside '{}', which has {} bytes",
+                span.start,
+                span.end,
+                path.display(),
+                original.len()
+            ),
+        });
+    }
+    let actual_hash = hash_bytes(&original[span.clone()]);
+    if actual_hash != expected_hash {
+        return Err(IdenteditError::PreconditionFailed {
+            expected_hash: expected_hash.to_string(),
+            actual_hash,
+        });
+    }
+
+    let mut updated = Vec::with_capacity(original.len() - span.len() + replacement.len());
+    updated.extend_from_slice(&original[..span.start]);
+    updated.extend_from_slice(replacement);
+    updated.extend_from_slice(&original[span.end..]);
+
+    let original_text = String::from_utf8_lossy(&original);
+    let updated_text = String::from_utf8_lossy(&updated);
+    let protected = protected_regions(path, &original_text, settings)?;
+    check_protected_rewrite(path, &protected, &original_text, &updated_text)?;
+    let stats = ChangeStats::between(&original_text, &updated_text);
+    check_fraction_changed(
+        path,
+        line_count(&original_text),
+        stats.lines_removed,
+        &settings.guards,
+    )?;
+    if settings.secret_scan {
+        check_new_secrets(path, &original_text, &updated_text)?;
+    }
+    let metadata_warnings =
+        write_bytes_atomically(path, &updated, Some(&guard_state), settings.durability)?;
+
+    let applied = vec![ApplyFileResult {
+        file: render_path(path),
+        operations_applied: 1,
+        operations_total: 1,
+        status: ApplyFileStatus::Applied,
+        metadata_warnings,
+        normalized: None,
+        stats: Some(stats),
+    }];
+    Ok(ApplyResponse {
+        summary: summarize_apply_results(&applied),
+        applied,
+        transaction: ApplyTransaction {
+            mode: TransactionMode::AllOrNothing,
+            status: TransactionStatus::Committed,
+        },
+        operations: None,
+        targets_missing_ignored: Vec::new(),
+    })
+}
@@ -0,0 +1,171 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::changeset::MultiFileChangeset;
+use crate::error::IdenteditError;
+
+/// Position of an operation in the plan: (index into `files`, index into `operations`).
+pub(super) type OperationKey = (usize, usize);
+
+/// Declared prerequisites of every operation that lists `depends_on`.
+pub(super) type DeclaredDependencies = BTreeMap<OperationKey, Vec<OperationKey>>;
+
+/// Resolves `depends_on` ids to plan positions, rejecting duplicate or empty ids, unknown
+/// references, and dependency cycles.
+pub(super) fn resolve_declared_dependencies(
+    changeset: &MultiFileChangeset,
+) -> Result<DeclaredDependencies, IdenteditError> {
+    let mut ids = HashMap::<&str, OperationKey>::new();
+    for (file_index, file_change) in changeset.files.iter().enumerate() {
+        for (operation_index, operation) in file_change.operations.iter().enumerate() {
+            let Some(id) = operation.id.as_deref() else {
+                continue;
+            };
+            if id.is_empty() {
+                return Err(invalid_dependency(format!(
+                    "{} has an empty id",
+                    operation_path((file_index, operation_index))
+                )));
+            }
+            if let Some(first) = ids.insert(id, (file_index, operation_index)) {
+                return Err(invalid_dependency(format!(
+                    "Duplicate operation id '{id}' in {} and {}",
+                    operation_path(first),
+                    operation_path((file_index, operation_index))
+                )));
+            }
+        }
+    }
+
+    let mut declared = DeclaredDependencies::new();
+    for (file_index, file_change) in changeset.files.iter().enumerate() {
+        for (operation_index, operation) in file_change.operations.iter().enumerate() {
+            let key = (file_index, operation_index);
+            for dependency in &operation.depends_on {
+                let Some(prerequisite) = ids.get(dependency.as_str()).copied() else {
+                    return Err(invalid_dependency(format!(
+                        "{} depends on unknown operation id '{dependency}'",
+                        operation_path(key)
+                    )));
+                };
+                let prerequisites = declared.entry(key).or_default();
+                if !prerequisites.contains(&prerequisite) {
+                    prerequisites.push(prerequisite);
+                }
+            }
+        }
+    }
+
+    reject_dependency_cycles(changeset, &declared)?;
+    Ok(declared)
+}
+
+fn reject_dependency_cycles(
+    changeset: &MultiFileChangeset,
+    declared: &DeclaredDependencies,
+) -> Result<(), IdenteditError> {
+    let mut finished = BTreeSet::new();
+    for &start in declared.keys() {
+        if finished.contains(&start) {
+            continue;
+        }
+        let mut path = vec![start];
+        let mut pending = vec![prerequisites_of(declared, start).iter()];
+        while let Some(next) = pending.last_mut().map(Iterator::next) {
+            match next {
+                Some(&prerequisite) if path.contains(&prerequisite) => {
+                    let cycle_start = path
+                        .iter()
+                        .position(|key| *key == prerequisite)
+                        .unwrap_or_default();
+                    let mut cycle = path[cycle_start..]
+                        .iter()
+                        .map(|key| operation_label(changeset, *key))
+                        .collect::<Vec<_>>();
+                    cycle.push(operation_label(changeset, prerequisite));
+                    return Err(invalid_dependency(format!(
+                        "Operation dependencies form a cycle: {}",
+                        cycle.join(" -> ")
+                    )));
+                }
+                Some(&prerequisite) if !finished.contains(&prerequisite) => {
+                    path.push(prerequisite);
+                    pending.push(prerequisites_of(declared, prerequisite).iter());
+                }
+                Some(_) => {}
+                None => {
+                    pending.pop();
+                    if let Some(key) = path.pop() {
+                        finished.insert(key);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Orders file indices so that files holding prerequisites come before their dependents.
+///
+/// Ties keep plan order. Dependencies can still run both ways between two files without an
+/// operation-level cycle; the remaining files then fall back to plan order.
+pub(super) fn order_files_by_dependencies(
+    file_count: usize,
+    declared: &DeclaredDependencies,
+) -> Vec<usize> {
+    let mut prerequisite_files = vec![BTreeSet::new(); file_count];
+    for (&(file_index, _), prerequisites) in declared {
+        for &(prerequisite_file, _) in prerequisites {
+            if prerequisite_file != file_index {
+                prerequisite_files[file_index].insert(prerequisite_file);
+            }
+        }
+    }
+
+    let mut ordered = Vec::with_capacity(file_count);
+    let mut remaining = (0..file_count).collect::<BTreeSet<_>>();
+    while let Some(&fallback) = remaining.first() {
+        let next = remaining
+            .iter()
+            .copied()
+            .find(|file_index| prerequisite_files[*file_index].is_disjoint(&remaining))
+            .unwrap_or(fallback);
+        remaining.remove(&next);
+        ordered.push(next);
+    }
+    ordered
+}
+
+fn prerequisites_of(declared: &DeclaredDependencies, key: OperationKey) -> &[OperationKey] {
+    declared.get(&key).map(Vec::as_slice).unwrap_or_default()
+}
+
+fn operation_label(changeset: &MultiFileChangeset, key: OperationKey) -> String {
+    changeset.files[key.0].operations[key.1]
+        .id
+        .as_ref()
+        .map(|id| format!("'{id}'"))
+        .unwrap_or_else(|| operation_path(key))
+}
+
+fn operation_path((file_index, operation_index): OperationKey) -> String {
+    format!("files[{file_index}].operations[{operation_index}]")
+}
+
+fn invalid_dependency(message: String) -> IdenteditError {
+    IdenteditError::InvalidRequest { message }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DeclaredDependencies, order_files_by_dependencies};
+
+    #[test]
+    fn files_holding_prerequisites_are_ordered_first() {
+        let declared = DeclaredDependencies::from([((0, 0), vec![(2, 1)]), ((2, 0), vec![(1, 0)])]);
+        assert_eq!(order_files_by_dependencies(3, &declared), vec![1, 2, 0]);
+
+        let crossing = DeclaredDependencies::from([((0, 0), vec![(1, 0)]), ((1, 1), vec![(0, 1)])]);
+        assert_eq!(order_files_by_dependencies(3, &crossing), vec![2, 0, 1]);
+    }
+}
@@ -0,0 +1,66 @@
+//! Per-group counts for `ApplySummary::groups`: operations that name a `group` are tallied
+//! under it, so a composite change can be reported, and retried with `apply --group`, one
+//! part at a time.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::changeset::FileChange;
+
+use super::partial::{ApplyOperationOutcomes, OperationRef};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct GroupSummary {
+    pub applied: usize,
+    pub failed: usize,
+    /// Operations not attempted because one they depend on failed.
+    pub skipped: usize,
+}
+
+/// Counts for an all-or-nothing apply that went through: every operation of `files` was
+/// applied except the `ignored` ones, whose optional target was missing.
+pub(super) fn summarize_committed_groups(
+    files: &[FileChange],
+    ignored: &[OperationRef],
+) -> BTreeMap<String, GroupSummary> {
+    let mut groups = BTreeMap::<String, GroupSummary>::new();
+    for group in files
+        .iter()
+        .flat_map(|file_change| &file_change.operations)
+        .filter_map(|operation| operation.group.as_ref())
+    {
+        groups.entry(group.clone()).or_default().applied += 1;
+    }
+    for group in ignored
+        .iter()
+        .filter_map(|operation| operation.labels.group.as_ref())
+    {
+        if let Some(summary) = groups.get_mut(group) {
+            summary.applied = summary.applied.saturating_sub(1);
+        }
+    }
+    groups
+}
+
+/// Counts for a `continue_on_error` apply, from its per-operation outcomes.
+pub(super) fn summarize_outcome_groups(
+    outcomes: &ApplyOperationOutcomes,
+) -> BTreeMap<String, GroupSummary> {
+    let mut groups = BTreeMap::<String, GroupSummary>::new();
+    let mut count = |group: &Option<String>, field: fn(&mut GroupSummary) -> &mut usize| {
+        if let Some(group) = group {
+            *field(groups.entry(group.clone()).or_default()) += 1;
+        }
+    };
+    for operation in &outcomes.applied {
+        count(&operation.labels.group, |summary| &mut summary.applied);
+    }
+    for operation in &outcomes.failed {
+        count(&operation.labels.group, |summary| &mut summary.failed);
+    }
+    for operation in &outcomes.skipped {
+        count(&operation.labels.group, |summary| &mut summary.skipped);
+    }
+    groups
+}
@@ -0,0 +1,100 @@
+//! Destructive-change guards: limits on how much one apply may delete, so that a truncated
+//! or malformed replacement is stopped instead of wiping out most of a file.
+//!
+//! The limits come from `guards` in the project config; `--force` lifts them.
+
+use std::path::Path;
+
+use crate::changeset::OpKind;
+use crate::error::IdenteditError;
+use crate::project_config::ChangeGuards;
+use crate::transform::MatchedChange;
+
+/// Files shorter than this are exempt from `max_file_fraction_changed`: any real edit to
+/// them changes a large share of their lines.
+const FRACTION_GUARD_MIN_LINES: usize = 20;
+
+/// Fails with `destructive_change` when `matched` deletes more lines, net of the lines it
+/// writes, than `max_deleted_lines_per_op` allows.
+pub(super) fn check_deleted_lines(
+    file: &Path,
+    matched: &MatchedChange,
+    guards: &ChangeGuards,
+) -> Result<(), IdenteditError> {
+    let Some(max_deleted) = guards.max_deleted_lines_per_op else {
+        return Ok(());
+    };
+    let written = match &matched.op {
+        OpKind::Replace { new_text } => new_text.as_str(),
+        OpKind::Delete => "",
+        _ => return Ok(()),
+    };
+    let deleted = line_count(&matched.old_text).saturating_sub(line_count(written));
+    if deleted > max_deleted {
+        return Err(IdenteditError::DestructiveChange {
+            file: file.display().to_string(),
+            message: format!(
+                "operation {} deletes {deleted} lines; max_deleted_lines_per_op is {max_deleted}",
+                matched.index
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Fails with `destructive_change` when an apply removes or changes a larger share of the
+/// `original_lines` lines of `file` than `max_file_fraction_changed` allows.
+pub(super) fn check_fraction_changed(
+    file: &Path,
+    original_lines: usize,
+    lines_removed: usize,
+    guards: &ChangeGuards,
+) -> Result<(), IdenteditError> {
+    let Some(max_fraction) = guards.max_file_fraction_changed else {
+        return Ok(());
+    };
+    if original_lines < FRACTION_GUARD_MIN_LINES {
+        return Ok(());
+    }
+    let fraction = lines_removed as f64 / original_lines as f64;
+    if fraction > max_fraction {
+        return Err(IdenteditError::DestructiveChange {
+            file: file.display().to_string(),
+            message: format!(
+                "the edit removes or changes {lines_removed} of {original_lines} lines ({:.0}%); max_file_fraction_changed is {max_fraction}",
+                fraction * 100.0
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Lines in `text`, counting a last line without a line break.
+pub(super) fn line_count(text: &str) -> usize {
+    text.bytes().filter(|byte| *byte == b'\n').count()
+        + usize::from(!text.is_empty() && !text.ends_with('\n'))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{ChangeGuards, check_fraction_changed};
+
+    #[test]
+    fn fraction_guard_follows_the_guards_it_is_given() {
+        let file = Path::new("module.py");
+        let strict = ChangeGuards {
+            max_deleted_lines_per_op: None,
+            max_file_fraction_changed: Some(0.5),
+        };
+        let off = ChangeGuards {
+            max_deleted_lines_per_op: None,
+            max_file_fraction_changed: None,
+        };
+
+        assert!(check_fraction_changed(file, 40, 30, &strict).is_err());
+        assert!(check_fraction_changed(file, 40, 30, &off).is_ok());
+        assert!(check_fraction_changed(file, 10, 10, &strict).is_ok());
+    }
+}
@@ -1,18 +1,59 @@
+#[cfg(unix)]
+use std::ffi::OsString;
 use std::fs::{self, File, OpenOptions};
-use std::io::Write;
+use std::io::{BufReader, BufWriter, Read, Write};
 #[cfg(unix)]
-use std::os::unix::fs::MetadataExt;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use clap::ValueEnum;
 use fs2::FileExt;
 
-use crate::changeset::hash_bytes;
+use crate::changeset::TransactionSpec;
 use crate::error::IdenteditError;
+use crate::hash::hash_reader;
 
 static TEMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
 
+/// Files at least this large are rewritten by splicing edits into a streamed copy instead of
+/// being rebuilt in memory.
+pub(crate) const STREAMING_APPLY_MIN_BYTES: usize = 16 * 1024 * 1024;
+
+/// Read and write buffer size for streamed rewrites and hashing.
+const STREAM_WINDOW_BYTES: usize = 256 * 1024;
+
+/// Bytes `start..end` of the file on disk are replaced by `text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Splice {
+    pub(super) start: usize,
+    pub(super) end: usize,
+    pub(super) text: String,
+}
+
+/// The contents a commit or rollback writes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum PlannedText {
+    /// The whole file, built in memory.
+    Full(String),
+    /// Sorted, non-overlapping splices against the file currently on disk.
+    Spliced(Vec<Splice>),
+}
+
+/// How much an atomic rewrite fsyncs, trading crash consistency for speed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum Durability {
+    /// No fsync; the kernel flushes whenever it likes.
+    None,
+    /// Fsync the temp file before the rename, so the path never names unwritten data.
+    File,
+    /// Also fsync the parent directory after the rename, so the rename itself is durable.
+    #[default]
+    Dir,
+}
+
 #[derive(Debug)]
 pub(super) struct ApplyFileLock {
     _file: File,
@@ -25,6 +66,87 @@ pub(super) enum AtomicWritePhase {
     Renamed,
 }
 
+/// Ownership and extended attributes (SELinux labels included) of a file being rewritten.
+/// Restoring them is best-effort: an unprivileged process cannot give a file away or
+/// relabel it, so failures become warnings instead of failing the write.
+#[derive(Debug, Default)]
+struct PreservedMetadata {
+    #[cfg(unix)]
+    owner: Option<(u32, u32)>,
+    #[cfg(unix)]
+    xattrs: Vec<(OsString, Vec<u8>)>,
+    warnings: Vec<String>,
+}
+
+impl PreservedMetadata {
+    fn capture(path: &Path, metadata: &fs::Metadata) -> Self {
+        let mut preserved = Self::default();
+        #[cfg(unix)]
+        {
+            preserved.owner = Some((metadata.uid(), metadata.gid()));
+            if xattr::SUPPORTED_PLATFORM {
+                match xattr::list(path) {
+                    Ok(names) => {
+                        for name in names {
+                            match xattr::get(path, &name) {
+                                Ok(Some(value)) => preserved.xattrs.push((name, value)),
+                                Ok(None) => {}
+                                Err(error) => preserved.warnings.push(format!(
+                                    "could not read extended attribute {}: {error}",
+                                    name.to_string_lossy()
+                                )),
+                            }
+                        }
+                    }
+                    Err(error) => preserved
+                        .warnings
+                        .push(format!("could not list extended attributes: {error}")),
+                }
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = (path, metadata);
+        preserved
+    }
+
+    /// Runs before permissions are restored, since `chown` clears setuid/setgid bits.
+    fn restore(&mut self, temp_path: &Path) {
+        #[cfg(unix)]
+        {
+            if let Some((uid, gid)) = self.owner
+                && let Ok(current) = fs::metadata(temp_path)
+            {
+                // Group first: a user can often keep the group even when the owner fails.
+                if current.gid() != gid
+                    && let Err(error) = std::os::unix::fs::chown(temp_path, None, Some(gid))
+                {
+                    self.warnings
+                        .push(format!("could not restore group {gid}: {error}"));
+                }
+                if current.uid() != uid
+                    && let Err(error) = std::os::unix::fs::chown(temp_path, Some(uid), None)
+                {
+                    self.warnings
+                        .push(format!("could not restore owner {uid}: {error}"));
+                }
+            }
+            for (name, value) in &self.xattrs {
+                if xattr::get(temp_path, name).ok().flatten().as_ref() == Some(value) {
+                    continue;
+                }
+                if let Err(error) = xattr::set(temp_path, name, value) {
+                    self.warnings.push(format!(
+                        "could not restore extended attribute {}: {error}",
+                        name.to_string_lossy()
+                    ));
+                }
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = temp_path;
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(super) struct ApplyGuardState {
     pub(super) path_fingerprint: PathFingerprint,
@@ -41,13 +163,83 @@ pub(super) struct PathFingerprint {
     modified_nanos: Option<u128>,
 }
 
-pub(super) fn acquire_apply_lock(path: &Path) -> Result<ApplyFileLock, IdenteditError> {
+/// Refuses hard-linked and read-only targets unless the transaction opts in, and returns the
+/// permissions the file has now. Nothing is changed here: a read-only target admitted by
+/// `chmod_writable` is only made writable while [`write_planned_keeping_permissions`] writes it.
+pub(super) fn prepare_write_target(
+    path: &Path,
+    transaction: &TransactionSpec,
+) -> Result<fs::Permissions, IdenteditError> {
+    let metadata = fs::symlink_metadata(path).map_err(|error| IdenteditError::io(path, error))?;
+    if !metadata.is_file() {
+        // The apply guard and the write-open reject symlinks and directories with their own errors.
+        return fs::metadata(path)
+            .map(|metadata| metadata.permissions())
+            .map_err(|error| IdenteditError::io(path, error));
+    }
+
+    #[cfg(unix)]
+    if metadata.nlink() > 1 && !transaction.force_unlink {
+        return Err(IdenteditError::HardLinkedTarget {
+            path: path.display().to_string(),
+            links: metadata.nlink(),
+        });
+    }
+
+    let original_permissions = metadata.permissions();
+    if original_permissions.readonly() && !transaction.chmod_writable {
+        return Err(IdenteditError::ReadOnlyTarget {
+            path: path.display().to_string(),
+            reason: "read-only permissions".to_string(),
+        });
+    }
+
+    Ok(original_permissions)
+}
+
+/// Like [`write_planned_atomically`] for a target that had `original_permissions` at
+/// preflight. A read-only target is made writable just for the write (some platforms refuse
+/// to replace a read-only file) and gets `original_permissions` back afterwards, whether the
+/// write succeeded or not.
+pub(super) fn write_planned_keeping_permissions(
+    path: &Path,
+    contents: &PlannedText,
+    expected_guard: Option<&ApplyGuardState>,
+    original_permissions: &fs::Permissions,
+    durability: Durability,
+) -> Result<Vec<String>, IdenteditError> {
+    if !original_permissions.readonly() {
+        return write_planned_atomically(path, contents, expected_guard, durability);
+    }
+
+    let mut writable = original_permissions.clone();
+    #[cfg(unix)]
+    writable.set_mode(writable.mode() | 0o200);
+    #[cfg(not(unix))]
+    writable.set_readonly(false);
+    fs::set_permissions(path, writable).map_err(|error| write_open_error(path, error))?;
+    let result = write_planned_atomically(path, contents, expected_guard, durability);
+    let restored = fs::set_permissions(path, original_permissions.clone())
+        .map_err(|error| IdenteditError::io(path, error));
+    let metadata_warnings = result?;
+    restored?;
+    Ok(metadata_warnings)
+}
+
+/// Locks `path` for an apply acting for `session`.
+pub(super) fn acquire_apply_lock(
+    path: &Path,
+    session: Option<&str>,
+) -> Result<ApplyFileLock, IdenteditError> {
+    // Read-only targets only get this far with `chmod_writable`; they are locked through a
+    // read handle so their permissions stay untouched until the commit writes them.
+    let read_only = fs::metadata(path).is_ok_and(|metadata| metadata.permissions().readonly());
     let file = OpenOptions::new()
         .truncate(false)
         .read(true)
-        .write(true)
+        .write(!read_only)
         .open(path)
-        .map_err(|error| IdenteditError::io(path, error))?;
+        .map_err(|error| write_open_error(path, error))?;
 
     file.try_lock_exclusive().map_err(|error| {
         if error.kind() == std::io::ErrorKind::WouldBlock {
@@ -58,10 +250,28 @@ pub(super) fn acquire_apply_lock(path: &Path) -> Result<ApplyFileLock, Identedit
             IdenteditError::io(path, error)
         }
     })?;
+    crate::lease::check_lease(path, session)?;
 
     Ok(ApplyFileLock { _file: file })
 }
 
+/// Permission failures on the write-open name the target as unwritable; `EPERM` there means
+/// an immutable or append-only flag rather than the permission bits.
+fn write_open_error(path: &Path, error: std::io::Error) -> IdenteditError {
+    if error.kind() != std::io::ErrorKind::PermissionDenied {
+        return IdenteditError::io(path, error);
+    }
+    let reason = if cfg!(unix) && error.raw_os_error() == Some(1) {
+        "marked immutable or append-only"
+    } else {
+        "not writable by the current user"
+    };
+    IdenteditError::ReadOnlyTarget {
+        path: path.display().to_string(),
+        reason: reason.to_string(),
+    }
+}
+
 pub(super) fn capture_path_fingerprint(path: &Path) -> Result<PathFingerprint, IdenteditError> {
     let metadata = fs::symlink_metadata(path).map_err(|error| IdenteditError::io(path, error))?;
 
@@ -92,13 +302,18 @@ pub(super) fn capture_path_fingerprint(path: &Path) -> Result<PathFingerprint, I
 
 pub(super) fn capture_apply_guard_state(path: &Path) -> Result<ApplyGuardState, IdenteditError> {
     let path_fingerprint = capture_path_fingerprint(path)?;
-    let source_bytes = fs::read(path).map_err(|error| IdenteditError::io(path, error))?;
     Ok(ApplyGuardState {
         path_fingerprint,
-        source_hash: hash_bytes(&source_bytes),
+        source_hash: hash_file(path)?,
     })
 }
 
+fn hash_file(path: &Path) -> Result<String, IdenteditError> {
+    let file = File::open(path).map_err(|error| IdenteditError::io(path, error))?;
+    hash_reader(BufReader::with_capacity(STREAM_WINDOW_BYTES, file))
+        .map_err(|error| IdenteditError::io(path, error))
+}
+
 pub(super) fn verify_apply_guard_state(
     path: &Path,
     expected: &ApplyGuardState,
@@ -110,8 +325,7 @@ pub(super) fn verify_apply_guard_state(
         });
     }
 
-    let current_bytes = fs::read(path).map_err(|error| IdenteditError::io(path, error))?;
-    let current_hash = hash_bytes(&current_bytes);
+    let current_hash = hash_file(path)?;
     if current_hash != expected.source_hash {
         return Err(IdenteditError::PreconditionFailed {
             expected_hash: expected.source_hash.clone(),
@@ -126,8 +340,75 @@ pub(super) fn write_text_atomically(
     path: &Path,
     contents: &str,
     expected_guard: Option<&ApplyGuardState>,
+    durability: Durability,
+) -> Result<Vec<String>, IdenteditError> {
+    write_text_atomically_with_hook_and_guard(
+        path,
+        contents,
+        expected_guard,
+        durability,
+        |_| Ok(()),
+    )
+}
+
+/// Like [`write_text_atomically`] for contents that need not be UTF-8.
+pub(super) fn write_bytes_atomically(
+    path: &Path,
+    contents: &[u8],
+    expected_guard: Option<&ApplyGuardState>,
+    durability: Durability,
+) -> Result<Vec<String>, IdenteditError> {
+    write_atomically_with_hook_guard_and_rename(
+        path,
+        write_bytes(contents),
+        expected_guard,
+        durability,
+        |_| Ok(()),
+        |from, to| fs::rename(from, to),
+    )
+}
+
+pub(super) fn write_planned_atomically(
+    path: &Path,
+    contents: &PlannedText,
+    expected_guard: Option<&ApplyGuardState>,
+    durability: Durability,
+) -> Result<Vec<String>, IdenteditError> {
+    match contents {
+        PlannedText::Full(text) => write_text_atomically(path, text, expected_guard, durability),
+        PlannedText::Spliced(splices) => write_atomically_with_hook_guard_and_rename(
+            path,
+            |temp_file, _| copy_spliced(path, splices, temp_file),
+            expected_guard,
+            durability,
+            |_| Ok(()),
+            |from, to| fs::rename(from, to),
+        ),
+    }
+}
+
+/// Writes `contents` to a new file at `path`, failing with `AlreadyExists` when something is
+/// already there. The text goes to an adjacent temporary file that is hard-linked into place,
+/// so the new file never appears half-written and an existing one is never replaced.
+pub(super) fn create_text_atomically(
+    path: &Path,
+    contents: &str,
+    durability: Durability,
 ) -> Result<(), IdenteditError> {
-    write_text_atomically_with_hook_and_guard(path, contents, expected_guard, |_| Ok(()))
+    let (temp_path, mut temp_file) = create_temp_file_adjacent(path)?;
+    let result = (|| {
+        write_text(contents)(&mut temp_file, &temp_path)?;
+        if durability != Durability::None {
+            temp_file
+                .sync_all()
+                .map_err(|error| IdenteditError::io(&temp_path, error))?;
+        }
+        drop(temp_file);
+        fs::hard_link(&temp_path, path).map_err(|error| IdenteditError::io(path, error))
+    })();
+    let _ = fs::remove_file(&temp_path);
+    result?;
+    sync_parent_directory(path, durability)
 }
 
 #[cfg(test)]
@@ -135,11 +416,17 @@ pub(super) fn write_text_atomically_with_hook<F>(
     path: &Path,
     contents: &str,
     phase_hook: F,
-) -> Result<(), IdenteditError>
+) -> Result<Vec<String>, IdenteditError>
 where
     F: FnMut(AtomicWritePhase) -> std::io::Result<()>,
 {
-    write_text_atomically_with_hook_and_guard(path, contents, None, phase_hook)
+    write_text_atomically_with_hook_and_guard(
+        path,
+        contents,
+        None,
+        Durability::default(),
+        phase_hook,
+    )
 }
 
 #[cfg(test)]
@@ -148,58 +435,140 @@ pub(super) fn write_text_atomically_with_hook_and_rename<F, R>(
     contents: &str,
     phase_hook: F,
     rename_file: R,
-) -> Result<(), IdenteditError>
+) -> Result<Vec<String>, IdenteditError>
 where
     F: FnMut(AtomicWritePhase) -> std::io::Result<()>,
     R: FnMut(&Path, &Path) -> std::io::Result<()>,
 {
-    write_text_atomically_with_hook_guard_and_rename(path, contents, None, phase_hook, rename_file)
+    write_atomically_with_hook_guard_and_rename(
+        path,
+        write_text(contents),
+        None,
+        Durability::default(),
+        phase_hook,
+        rename_file,
+    )
 }
 
 fn write_text_atomically_with_hook_and_guard<F>(
     path: &Path,
     contents: &str,
     expected_guard: Option<&ApplyGuardState>,
+    durability: Durability,
     phase_hook: F,
-) -> Result<(), IdenteditError>
+) -> Result<Vec<String>, IdenteditError>
 where
     F: FnMut(AtomicWritePhase) -> std::io::Result<()>,
 {
-    write_text_atomically_with_hook_guard_and_rename(
+    write_atomically_with_hook_guard_and_rename(
         path,
-        contents,
+        write_text(contents),
         expected_guard,
+        durability,
         phase_hook,
         |from, to| fs::rename(from, to),
     )
 }
 
-fn write_text_atomically_with_hook_guard_and_rename<F, R>(
+fn write_text(contents: &str) -> impl FnOnce(&mut File, &Path) -> Result<(), IdenteditError> {
+    write_bytes(contents.as_bytes())
+}
+
+fn write_bytes(contents: &[u8]) -> impl FnOnce(&mut File, &Path) -> Result<(), IdenteditError> {
+    move |temp_file, temp_path| {
+        temp_file
+            .write_all(contents)
+            .map_err(|error| IdenteditError::io(temp_path, error))
+    }
+}
+
+/// Streams `path` into `output` one window at a time, replacing each splice's byte range with
+/// its text. The write itself holds one window plus the splices; empty splices at the same
+/// offset are written in order.
+fn copy_spliced(path: &Path, splices: &[Splice], output: &mut File) -> Result<(), IdenteditError> {
+    let source = File::open(path).map_err(|error| IdenteditError::io(path, error))?;
+    let mut reader = BufReader::with_capacity(STREAM_WINDOW_BYTES, source);
+    let mut writer = BufWriter::with_capacity(STREAM_WINDOW_BYTES, output);
+    let mut position = 0;
+    for splice in splices {
+        if splice.start < position || splice.end < splice.start {
+            return Err(IdenteditError::InvalidRequest {
+                message: format!(
+                    "Spliced rewrite of '{}' needs sorted, non-overlapping spans, but [{}, {}) follows offset {}",
+                    path.display(),
+                    splice.start,
+                    splice.end,
+                    position,
+                ),
+            });
+        }
+        copy_exactly(path, &mut reader, &mut writer, splice.start - position)?;
+        writer
+            .write_all(splice.text.as_bytes())
+            .map_err(|error| IdenteditError::io(path, error))?;
+        copy_exactly(
+            path,
+            &mut reader,
+            &mut std::io::sink(),
+            splice.end - splice.start,
+        )?;
+        position = splice.end;
+    }
+    std::io::copy(&mut reader, &mut writer).map_err(|error| IdenteditError::io(path, error))?;
+    writer
+        .flush()
+        .map_err(|error| IdenteditError::io(path, error))
+}
+
+fn copy_exactly(
     path: &Path,
-    contents: &str,
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+    length: usize,
+) -> Result<(), IdenteditError> {
+    let length = length as u64;
+    let copied = std::io::copy(&mut reader.by_ref().take(length), writer)
+        .map_err(|error| IdenteditError::io(path, error))?;
+    if copied != length {
+        return Err(IdenteditError::io(
+            path,
+            std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "file ended before an edited span",
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn write_atomically_with_hook_guard_and_rename<W, F, R>(
+    path: &Path,
+    write_contents: W,
     expected_guard: Option<&ApplyGuardState>,
+    durability: Durability,
     mut phase_hook: F,
     mut rename_file: R,
-) -> Result<(), IdenteditError>
+) -> Result<Vec<String>, IdenteditError>
 where
+    W: FnOnce(&mut File, &Path) -> Result<(), IdenteditError>,
     F: FnMut(AtomicWritePhase) -> std::io::Result<()>,
     R: FnMut(&Path, &Path) -> std::io::Result<()>,
 {
-    let target_permissions = fs::metadata(path)
-        .map_err(|error| IdenteditError::io(path, error))?
-        .permissions();
+    let target_metadata = fs::metadata(path).map_err(|error| IdenteditError::io(path, error))?;
+    let target_permissions = target_metadata.permissions();
+    let mut preserved = PreservedMetadata::capture(path, &target_metadata);
     let (temp_path, mut temp_file) = create_temp_file_adjacent(path)?;
 
     let result = (|| {
-        temp_file
-            .write_all(contents.as_bytes())
-            .map_err(|error| IdenteditError::io(&temp_path, error))?;
+        write_contents(&mut temp_file, &temp_path)?;
         phase_hook(AtomicWritePhase::TempWritten)
             .map_err(|error| IdenteditError::io(path, error))?;
 
-        temp_file
-            .sync_all()
-            .map_err(|error| IdenteditError::io(&temp_path, error))?;
+        if durability != Durability::None {
+            temp_file
+                .sync_all()
+                .map_err(|error| IdenteditError::io(&temp_path, error))?;
+        }
         phase_hook(AtomicWritePhase::TempSynced)
             .map_err(|error| IdenteditError::io(path, error))?;
 
@@ -207,6 +576,7 @@ where
             verify_apply_guard_state(path, guard_state)?;
         }
 
+        preserved.restore(&temp_path);
         fs::set_permissions(&temp_path, target_permissions.clone())
             .map_err(|error| IdenteditError::io(&temp_path, error))?;
         drop(temp_file);
@@ -214,14 +584,14 @@ where
         rename_file(&temp_path, path).map_err(|error| IdenteditError::io(path, error))?;
         phase_hook(AtomicWritePhase::Renamed).map_err(|error| IdenteditError::io(path, error))?;
 
-        sync_parent_directory(path)
+        sync_parent_directory(path, durability)
     })();
 
     if result.is_err() {
         let _ = fs::remove_file(&temp_path);
     }
 
-    result
+    result.map(|()| preserved.warnings)
 }
 
 fn create_temp_file_adjacent(path: &Path) -> Result<(PathBuf, File), IdenteditError> {
@@ -265,7 +635,14 @@ fn resolve_parent_directory(path: &Path) -> PathBuf {
     }
 }
 
-pub(super) fn sync_parent_directory(path: &Path) -> Result<(), IdenteditError> {
+pub(super) fn sync_parent_directory(
+    path: &Path,
+    durability: Durability,
+) -> Result<(), IdenteditError> {
+    if durability != Durability::Dir {
+        return Ok(());
+    }
+
     #[cfg(unix)]
     {
         let parent = resolve_parent_directory(path);
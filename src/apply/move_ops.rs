@@ -4,12 +4,13 @@ use std::path::{Component, Path, PathBuf};
 
 use crate::changeset::{ChangeOp, FileChange, OpKind};
 use crate::error::IdenteditError;
+use crate::path_style::render_path;
 
 use super::io::{
-    ApplyFileLock, ApplyGuardState, acquire_apply_lock, capture_apply_guard_state,
+    ApplyFileLock, ApplyGuardState, Durability, acquire_apply_lock, capture_apply_guard_state,
     sync_parent_directory, verify_apply_guard_state,
 };
-use super::{ApplyFileResult, ApplyFileStatus};
+use super::{ApplyFileResult, ApplyFileStatus, ApplySettings};
 
 #[derive(Debug, Clone)]
 struct MoveEdge {
@@ -370,11 +371,13 @@ pub(super) struct MovePreflightPlan {
     pub(super) destination: PathBuf,
     pub(super) operations_total: usize,
     pub(super) guard_state: ApplyGuardState,
+    pub(super) durability: Durability,
     pub(super) _lock_guard: ApplyFileLock,
 }
 
 pub(super) fn preflight_move_plans(
     execution_order: &[NormalizedMoveEdge],
+    settings: &ApplySettings,
 ) -> Result<Vec<MovePreflightPlan>, IdenteditError> {
     if execution_order.is_empty() {
         return Ok(Vec::new());
@@ -385,7 +388,7 @@ pub(super) fn preflight_move_plans(
 
     let mut plans_by_source = BTreeMap::new();
     for edge in lock_order {
-        let lock_guard = acquire_apply_lock(&edge.source)?;
+        let lock_guard = acquire_apply_lock(&edge.source, settings.session.as_deref())?;
         let guard_state = capture_apply_guard_state(&edge.source)?;
         plans_by_source.insert(
             edge.source.clone(),
@@ -394,6 +397,7 @@ pub(super) fn preflight_move_plans(
                 destination: edge.destination,
                 operations_total: 1,
                 guard_state,
+                durability: settings.durability,
                 _lock_guard: lock_guard,
             },
         );
@@ -484,14 +488,17 @@ where
 
     rename_file(&plan.source, &plan.destination)
         .map_err(|error| IdenteditError::io(&plan.source, error))?;
-    sync_parent_directory(&plan.source)?;
-    sync_parent_directory(&plan.destination)?;
+    sync_parent_directory(&plan.source, plan.durability)?;
+    sync_parent_directory(&plan.destination, plan.durability)?;
 
     Ok(ApplyFileResult {
-        file: plan.source.display().to_string(),
+        file: render_path(&plan.source),
         operations_applied: plan.operations_total,
         operations_total: plan.operations_total,
         status: ApplyFileStatus::Applied,
+        metadata_warnings: Vec::new(),
+        normalized: None,
+        stats: None,
     })
 }
 
@@ -510,8 +517,8 @@ pub(super) fn rollback_committed_moves(
 
         fs::rename(&plan.destination, &plan.source)
             .map_err(|error| IdenteditError::io(&plan.destination, error))?;
-        sync_parent_directory(&plan.source)?;
-        sync_parent_directory(&plan.destination)?;
+        sync_parent_directory(&plan.source, plan.durability)?;
+        sync_parent_directory(&plan.destination, plan.durability)?;
     }
 
     Ok(())
@@ -0,0 +1,162 @@
+use std::ops::Range;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::changeset::{TrailingWhitespaceScope, TransactionSpec};
+use crate::error::IdenteditError;
+
+use super::io::PlannedText;
+use super::replacements::ResolvedReplacement;
+
+/// What `ensure_final_newline` and `strip_trailing_whitespace` changed in one file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Normalization {
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub final_newline_added: bool,
+    /// 1-based lines of the written file that lost trailing spaces or tabs.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub trailing_whitespace_stripped: Vec<usize>,
+}
+
+/// Byte ranges of the rewritten text that `replacements` wrote; an empty range marks a
+/// deletion point.
+pub(super) fn edited_ranges(replacements: &[ResolvedReplacement]) -> Vec<Range<usize>> {
+    let mut ordered = replacements.iter().collect::<Vec<_>>();
+    ordered.sort_by_key(|replacement| (replacement.start, replacement.end, replacement.index));
+    let mut shift = 0isize;
+    ordered
+        .into_iter()
+        .map(|replacement| {
+            let start = replacement.start.saturating_add_signed(shift);
+            shift += replacement.new_text.len() as isize
+                - (replacement.end - replacement.start) as isize;
+            start..start + replacement.new_text.len()
+        })
+        .collect()
+}
+
+/// Applies the transaction's whitespace policy to `updated_text` in place. Returns `None`
+/// when no policy is set or nothing needed changing.
+pub(super) fn normalize_planned_text(
+    file: &Path,
+    updated_text: &mut PlannedText,
+    edited: &[Range<usize>],
+    transaction: &TransactionSpec,
+) -> Result<Option<Normalization>, IdenteditError> {
+    if !transaction.ensure_final_newline && transaction.strip_trailing_whitespace.is_none() {
+        return Ok(None);
+    }
+    let PlannedText::Full(text) = updated_text else {
+        return Err(IdenteditError::InvalidRequest {
+            message: format!(
+                "ensure_final_newline/strip_trailing_whitespace cannot be applied to '{}': the file is too large to rewrite in memory",
+                file.display()
+            ),
+        });
+    };
+
+    let (normalized_text, normalization) = normalize_text(text, edited, transaction);
+    if normalization == Normalization::default() {
+        return Ok(None);
+    }
+    *text = normalized_text;
+    Ok(Some(normalization))
+}
+
+fn normalize_text(
+    text: &str,
+    edited: &[Range<usize>],
+    transaction: &TransactionSpec,
+) -> (String, Normalization) {
+    let mut normalization = Normalization::default();
+    let mut normalized = String::with_capacity(text.len() + 2);
+    let mut line_start = 0;
+    for (index, line) in text.split_inclusive('\n').enumerate() {
+        let ending_len = if line.ends_with("\r\n") {
+            2
+        } else if line.ends_with('\n') {
+            1
+        } else {
+            0
+        };
+        let (content, ending) = line.split_at(line.len() - ending_len);
+        let content_end = line_start + content.len();
+        let strip = match transaction.strip_trailing_whitespace {
+            Some(TrailingWhitespaceScope::File) => true,
+            Some(TrailingWhitespaceScope::EditedLines) => edited.iter().any(|range| {
+                if range.is_empty() {
+                    (line_start..=content_end).contains(&range.start)
+                } else {
+                    range.start < line_start + line.len() && line_start < range.end
+                }
+            }),
+            None => false,
+        };
+        let kept = if strip {
+            content.trim_end_matches([' ', '\t'])
+        } else {
+            content
+        };
+        if kept.len() != content.len() {
+            normalization.trailing_whitespace_stripped.push(index + 1);
+        }
+        normalized.push_str(kept);
+        normalized.push_str(ending);
+        line_start += line.len();
+    }
+
+    let content = normalized.strip_prefix('\u{feff}').unwrap_or(&normalized);
+    if transaction.ensure_final_newline && !content.is_empty() && !content.ends_with('\n') {
+        let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+        normalized.push_str(newline);
+        normalization.final_newline_added = true;
+    }
+    (normalized, normalization)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(
+        ensure_final_newline: bool,
+        strip_trailing_whitespace: Option<TrailingWhitespaceScope>,
+    ) -> TransactionSpec {
+        TransactionSpec {
+            ensure_final_newline,
+            strip_trailing_whitespace,
+            ..TransactionSpec::default()
+        }
+    }
+
+    #[test]
+    fn edited_lines_scope_leaves_untouched_lines_alone() {
+        let text = "keep  \nedited \t\r\nnext  ";
+        let edited = [7..16, 7..7];
+        let (normalized, normalization) = normalize_text(
+            text,
+            &edited,
+            &policy(true, Some(TrailingWhitespaceScope::EditedLines)),
+        );
+        assert_eq!(normalized, "keep  \nedited\r\nnext  \r\n");
+        assert_eq!(normalization.trailing_whitespace_stripped, vec![2]);
+        assert!(normalization.final_newline_added);
+
+        let (normalized, normalization) = normalize_text(
+            text,
+            &[],
+            &policy(false, Some(TrailingWhitespaceScope::File)),
+        );
+        assert_eq!(normalized, "keep\nedited\r\nnext");
+        assert_eq!(normalization.trailing_whitespace_stripped, vec![1, 2, 3]);
+        assert!(!normalization.final_newline_added);
+    }
+
+    #[test]
+    fn final_newline_is_not_added_to_empty_files() {
+        let (normalized, normalization) = normalize_text("\u{feff}", &[], &policy(true, None));
+        assert_eq!(normalized, "\u{feff}");
+        assert_eq!(normalization, Normalization::default());
+    }
+}
@@ -0,0 +1,56 @@
+use std::io::ErrorKind;
+
+use crate::changeset::{ChangeOp, FileChange};
+use crate::error::IdenteditError;
+use crate::path_style::render_path;
+
+use super::partial::{OperationLabels, OperationRef};
+
+/// Whether `error` means the target node, line, or file is gone rather than changed.
+pub(super) fn target_is_missing(error: &IdenteditError) -> bool {
+    match error {
+        IdenteditError::TargetMissing { .. } => true,
+        IdenteditError::Io { source, .. } => source.kind() == ErrorKind::NotFound,
+        _ => false,
+    }
+}
+
+/// Whether `operation` is optional and failed to resolve because its target is gone.
+pub(super) fn ignores_missing_target(operation: &ChangeOp, error: &IdenteditError) -> bool {
+    operation.optional && target_is_missing(error)
+}
+
+/// Drops the files that do not exist and whose every operation is optional, returning the
+/// files left and the operations dropped with them.
+pub(super) fn drop_missing_optional_files(
+    changesets: &[FileChange],
+) -> (Vec<FileChange>, Vec<OperationRef>) {
+    let mut kept = Vec::with_capacity(changesets.len());
+    let mut ignored = Vec::new();
+    for changeset in changesets {
+        let missing = std::fs::symlink_metadata(&changeset.file)
+            .is_err_and(|error| error.kind() == ErrorKind::NotFound);
+        if missing
+            && !changeset.operations.is_empty()
+            && changeset
+                .operations
+                .iter()
+                .all(|operation| operation.optional)
+        {
+            ignored.extend(
+                (0..changeset.operations.len()).map(|index| ignored_operation(changeset, index)),
+            );
+        } else {
+            kept.push(changeset.clone());
+        }
+    }
+    (kept, ignored)
+}
+
+pub(super) fn ignored_operation(changeset: &FileChange, index: usize) -> OperationRef {
+    OperationRef {
+        file: render_path(&changeset.file),
+        operation: index,
+        labels: OperationLabels::of(&changeset.operations[index]),
+    }
+}
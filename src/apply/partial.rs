@@ -0,0 +1,575 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::changeset::{
+    ChangeOp, FileChange, MultiFileChangeset, OpKind, TransactionMode, TransactionSpec, hash_text,
+};
+use crate::error::{ErrorBody, IdenteditError};
+use crate::execution_context::ExecutionContext;
+use crate::handle::Span;
+use crate::path_style::render_path;
+use crate::provider::ProviderRegistry;
+use crate::transform::{
+    MatchedChange, parse_handles_for_source, resolve_each_changeset_target_in_handles,
+    validate_change_conflicts,
+};
+
+use super::dependencies::{
+    DeclaredDependencies, OperationKey, order_files_by_dependencies, resolve_declared_dependencies,
+};
+use super::groups::summarize_outcome_groups;
+use super::guards::{check_deleted_lines, check_fraction_changed, line_count};
+use super::io::{
+    ApplyFileLock, ApplyGuardState, PlannedText, acquire_apply_lock, capture_apply_guard_state,
+    prepare_write_target, verify_apply_guard_state, write_planned_keeping_permissions,
+};
+use super::normalize::{Normalization, edited_ranges, normalize_planned_text};
+use super::optional::{ignored_operation, ignores_missing_target};
+use super::preflight::order_changesets_for_preflight;
+use super::protected::{check_protected_regions, protected_regions};
+use super::replacements::{
+    matched_changes_to_replacements, plan_rewrite, validate_preview_consistency,
+};
+use super::secrets::check_written_secrets;
+use super::stats::ChangeStats;
+use super::{
+    ApplyFileResult, ApplyFileStatus, ApplyResponse, ApplyTransaction, TransactionStatus,
+    summarize_apply_results,
+};
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ApplyOperationOutcomes {
+    pub applied: Vec<OperationRef>,
+    pub skipped: Vec<SkippedOperation>,
+    pub failed: Vec<FailedOperation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OperationRef {
+    pub file: String,
+    pub operation: usize,
+    #[serde(flatten)]
+    pub labels: OperationLabels,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedOperation {
+    pub file: String,
+    pub operation: usize,
+    #[serde(flatten)]
+    pub labels: OperationLabels,
+    pub depends_on: OperationRef,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedOperation {
+    pub file: String,
+    pub operation: usize,
+    #[serde(flatten)]
+    pub labels: OperationLabels,
+    pub error: ErrorBody,
+}
+
+/// The caller-supplied `id` and `correlation_id` of a plan operation, echoed in its
+/// per-operation results so callers can match them without counting indexes.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct OperationLabels {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+}
+
+impl OperationLabels {
+    pub fn of(operation: &ChangeOp) -> Self {
+        Self {
+            id: operation.id.clone(),
+            correlation_id: operation.correlation_id.clone(),
+            group: operation.group.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PartialFilePlan {
+    file_index: usize,
+    file: PathBuf,
+    source_text: String,
+    matched_changes: Vec<MatchedChange>,
+    guard_state: ApplyGuardState,
+    original_permissions: std::fs::Permissions,
+    _lock_guard: ApplyFileLock,
+}
+
+/// Applies every operation that still resolves instead of rolling the whole plan back.
+///
+/// Operations whose target no longer matches are reported as failed. Operations that
+/// depend on a failed one are skipped: in the same file that is any operation whose
+/// planned span overlaps it, and across files it is the other half of a cross-file move
+/// (a delete whose text is inserted elsewhere), so a failed insert never loses the moved
+/// code. Operations that declare a failed or skipped operation in `depends_on` are skipped
+/// as well. Each file is then written on its own, prerequisite files first, and a write
+/// failure only affects that file and the operations that depend on it.
+pub(super) fn apply_changesets_continue_on_error(
+    changeset: &MultiFileChangeset,
+    dry_run: bool,
+) -> Result<ApplyResponse, IdenteditError> {
+    if changeset.files.is_empty() {
+        return Err(IdenteditError::InvalidRequest {
+            message: "changeset.files must contain at least one file".to_string(),
+        });
+    }
+    if changeset.files.iter().any(|file_change| {
+        file_change
+            .operations
+            .iter()
+            .any(|operation| matches!(operation.op, OpKind::Move { .. }))
+    }) {
+        return Err(IdenteditError::InvalidRequest {
+            message: "continue_on_error mode does not support file move operations".to_string(),
+        });
+    }
+
+    let declared = resolve_declared_dependencies(changeset)?;
+
+    let mut failed = BTreeMap::<OperationKey, ErrorBody>::new();
+    let mut ignored = BTreeSet::<OperationKey>::new();
+    let mut reachable = Vec::with_capacity(changeset.files.len());
+    let mut reachable_indices = Vec::with_capacity(changeset.files.len());
+    for (file_index, file_change) in changeset.files.iter().enumerate() {
+        match std::fs::canonicalize(&file_change.file) {
+            Ok(_) => {
+                reachable.push(file_change.clone());
+                reachable_indices.push(file_index);
+            }
+            Err(error) => fail_file(
+                file_index,
+                file_change,
+                &IdenteditError::io(&file_change.file, error),
+                &mut failed,
+                &mut ignored,
+            ),
+        }
+    }
+
+    let registry = changeset.transaction.settings.provider_registry()?;
+    let context = ExecutionContext::new(&registry);
+    let mut plans = Vec::with_capacity(reachable.len());
+    for file_change in order_changesets_for_preflight(&reachable)? {
+        let file_index = reachable
+            .iter()
+            .position(|candidate| std::ptr::eq(candidate, file_change))
+            .map(|position| reachable_indices[position])
+            .ok_or_else(|| IdenteditError::InvalidRequest {
+                message: format!(
+                    "Internal preflight ordering error: '{}' is not part of the changeset",
+                    file_change.file.display()
+                ),
+            })?;
+        match preflight_partial_file(
+            file_index,
+            file_change,
+            &changeset.transaction,
+            context.registry(),
+            &context,
+            &mut failed,
+            &mut ignored,
+        ) {
+            Ok(plan) => plans.push(plan),
+            Err(error) => fail_file(file_index, file_change, &error, &mut failed, &mut ignored),
+        }
+    }
+
+    let file_order = order_files_by_dependencies(changeset.files.len(), &declared);
+    plans.sort_by_key(|plan| {
+        file_order
+            .iter()
+            .position(|file_index| *file_index == plan.file_index)
+    });
+
+    let mut skipped = skip_dependent_operations(changeset, &declared, &failed, &BTreeSet::new());
+    let mut applied_keys = BTreeSet::new();
+    let mut operations_applied_by_file = vec![0; changeset.files.len()];
+    let mut metadata_warnings_by_file = vec![Vec::new(); changeset.files.len()];
+    let mut normalized_by_file = vec![None; changeset.files.len()];
+    let mut stats_by_file = vec![None; changeset.files.len()];
+    for plan in plans {
+        let matched_changes = plan
+            .matched_changes
+            .into_iter()
+            .filter(|matched| !skipped.contains_key(&(plan.file_index, matched.index)))
+            .collect::<Vec<_>>();
+        let keys = matched_changes
+            .iter()
+            .map(|matched| (plan.file_index, matched.index))
+            .collect::<Vec<_>>();
+        let file_change = &changeset.files[plan.file_index];
+        let applied_operations = keys
+            .iter()
+            .map(|(_, operation)| &file_change.operations[*operation])
+            .collect::<Vec<_>>();
+        let commit_result = build_updated_text(
+            &plan.file,
+            plan.source_text,
+            matched_changes,
+            &changeset.transaction,
+            context.registry(),
+        )
+        .and_then(|(updated_text, normalized, stats)| {
+            let stats = stats.with_identities(applied_operations);
+            if dry_run || keys.is_empty() {
+                return Ok((Vec::new(), normalized, stats));
+            }
+            verify_apply_guard_state(&plan.file, &plan.guard_state)?;
+            write_planned_keeping_permissions(
+                &plan.file,
+                &updated_text,
+                Some(&plan.guard_state),
+                &plan.original_permissions,
+                changeset.transaction.settings.durability,
+            )
+            .map(|metadata_warnings| (metadata_warnings, normalized, stats))
+        });
+
+        match commit_result {
+            Ok((metadata_warnings, normalized, stats)) => {
+                operations_applied_by_file[plan.file_index] = keys.len();
+                metadata_warnings_by_file[plan.file_index] = metadata_warnings;
+                normalized_by_file[plan.file_index] = normalized;
+                stats_by_file[plan.file_index] = Some(stats);
+                applied_keys.extend(keys);
+            }
+            Err(error) => {
+                // Conflicts between the remaining operations would have rejected the
+                // plan in all-or-nothing mode, so they fail together like write errors.
+                let error_body = error.to_error_response().error;
+                for key in keys {
+                    failed.insert(key, error_body.clone());
+                }
+                skipped = skip_dependent_operations(changeset, &declared, &failed, &applied_keys);
+            }
+        }
+    }
+
+    let mut ignored_by_file = vec![0; changeset.files.len()];
+    for (file_index, _) in &ignored {
+        ignored_by_file[*file_index] += 1;
+    }
+    let applied = changeset
+        .files
+        .iter()
+        .enumerate()
+        .zip(operations_applied_by_file)
+        .zip(metadata_warnings_by_file)
+        .zip(normalized_by_file)
+        .zip(stats_by_file)
+        .map(
+            |(
+                ((((file_index, file_change), operations_applied), metadata_warnings), normalized),
+                stats,
+            )| {
+                let operations_total = file_change.operations.len() - ignored_by_file[file_index];
+                ApplyFileResult {
+                    file: render_path(&file_change.file),
+                    operations_applied,
+                    operations_total,
+                    status: if operations_applied == 0 && operations_total > 0 {
+                        ApplyFileStatus::Failed
+                    } else {
+                        ApplyFileStatus::Applied
+                    },
+                    metadata_warnings,
+                    normalized,
+                    stats,
+                }
+            },
+        )
+        .collect::<Vec<_>>();
+    let mut summary = summarize_apply_results(&applied);
+    let status = if dry_run {
+        TransactionStatus::DryRun
+    } else if summary.operations_failed == 0 {
+        TransactionStatus::Committed
+    } else {
+        TransactionStatus::Partial
+    };
+
+    let operations = ApplyOperationOutcomes {
+        applied: applied_keys
+            .into_iter()
+            .map(|key| operation_ref(changeset, key))
+            .collect(),
+        skipped: skipped
+            .into_iter()
+            .map(|(key, blocker)| {
+                let OperationRef {
+                    file,
+                    operation,
+                    labels,
+                } = operation_ref(changeset, key);
+                SkippedOperation {
+                    file,
+                    operation,
+                    labels,
+                    depends_on: operation_ref(changeset, blocker),
+                }
+            })
+            .collect(),
+        failed: failed
+            .into_iter()
+            .map(|(key, error)| {
+                let OperationRef {
+                    file,
+                    operation,
+                    labels,
+                } = operation_ref(changeset, key);
+                FailedOperation {
+                    file,
+                    operation,
+                    labels,
+                    error,
+                }
+            })
+            .collect(),
+    };
+
+    summary.groups = summarize_outcome_groups(&operations);
+
+    Ok(ApplyResponse {
+        applied,
+        summary,
+        transaction: ApplyTransaction {
+            mode: TransactionMode::ContinueOnError,
+            status,
+        },
+        operations: Some(operations),
+        targets_missing_ignored: ignored
+            .into_iter()
+            .map(|(file_index, operation)| {
+                ignored_operation(&changeset.files[file_index], operation)
+            })
+            .collect(),
+    })
+}
+
+fn operation_ref(
+    changeset: &MultiFileChangeset,
+    (file_index, operation): OperationKey,
+) -> OperationRef {
+    let file_change = &changeset.files[file_index];
+    OperationRef {
+        file: render_path(&file_change.file),
+        operation,
+        labels: OperationLabels::of(&file_change.operations[operation]),
+    }
+}
+
+fn fail_file(
+    file_index: usize,
+    file_change: &FileChange,
+    error: &IdenteditError,
+    failed: &mut BTreeMap<OperationKey, ErrorBody>,
+    ignored: &mut BTreeSet<OperationKey>,
+) {
+    let error_body = error.to_error_response().error;
+    for (operation, change_op) in file_change.operations.iter().enumerate() {
+        if ignores_missing_target(change_op, error) {
+            ignored.insert((file_index, operation));
+        } else {
+            failed.insert((file_index, operation), error_body.clone());
+        }
+    }
+}
+
+fn preflight_partial_file(
+    file_index: usize,
+    changeset: &FileChange,
+    transaction: &TransactionSpec,
+    registry: &ProviderRegistry,
+    context: &ExecutionContext,
+    failed: &mut BTreeMap<OperationKey, ErrorBody>,
+    ignored: &mut BTreeSet<OperationKey>,
+) -> Result<PartialFilePlan, IdenteditError> {
+    let original_permissions = prepare_write_target(&changeset.file, transaction)?;
+    let lock_guard = acquire_apply_lock(&changeset.file, transaction.settings.session.as_deref())?;
+    let guard_state = capture_apply_guard_state(&changeset.file)?;
+    let source_text = context.read_file_utf8(&changeset.file)?;
+    let requires_structure_parse = changeset.operations.is_empty()
+        || changeset
+            .operations
+            .iter()
+            .any(|operation| operation.target.requires_node_resolution());
+    let handles = if requires_structure_parse {
+        parse_handles_for_source(&changeset.file, source_text.as_bytes(), registry)?
+    } else {
+        Vec::new()
+    };
+
+    let protected = protected_regions(&changeset.file, &source_text, &transaction.settings)?;
+    let mut matched_changes = Vec::new();
+    for (index, resolved) in
+        resolve_each_changeset_target_in_handles(changeset, &source_text, &handles)
+            .into_iter()
+            .enumerate()
+    {
+        match resolved.and_then(|matched| {
+            validate_preview_consistency(changeset, std::slice::from_ref(&matched))?;
+            check_protected_regions(&changeset.file, &protected, &matched)?;
+            check_deleted_lines(&changeset.file, &matched, &transaction.settings.guards)?;
+            if transaction.settings.secret_scan {
+                check_written_secrets(&changeset.file, &source_text, &matched)?;
+            }
+            Ok(matched)
+        }) {
+            Ok(matched) => matched_changes.push(matched),
+            Err(error) if ignores_missing_target(&changeset.operations[index], &error) => {
+                ignored.insert((file_index, index));
+            }
+            Err(error) => {
+                failed.insert((file_index, index), error.to_error_response().error);
+            }
+        }
+    }
+
+    Ok(PartialFilePlan {
+        file_index,
+        file: changeset.file.clone(),
+        source_text,
+        matched_changes,
+        guard_state,
+        original_permissions,
+        _lock_guard: lock_guard,
+    })
+}
+
+/// Maps every operation that depends on a failed operation, directly or through another
+/// skipped one, to the operation it was skipped for. Already applied operations stay put.
+fn skip_dependent_operations(
+    changeset: &MultiFileChangeset,
+    declared: &DeclaredDependencies,
+    failed: &BTreeMap<OperationKey, ErrorBody>,
+    applied: &BTreeSet<OperationKey>,
+) -> BTreeMap<OperationKey, OperationKey> {
+    let mut skipped = BTreeMap::new();
+    let mut blocking = failed.keys().copied().collect::<Vec<_>>();
+    let mut settled = blocking
+        .iter()
+        .chain(applied)
+        .copied()
+        .collect::<BTreeSet<_>>();
+    while let Some(blocker) = blocking.pop() {
+        for (file_index, file_change) in changeset.files.iter().enumerate() {
+            for operation in 0..file_change.operations.len() {
+                let key = (file_index, operation);
+                if settled.contains(&key) || !operations_depend(changeset, declared, blocker, key) {
+                    continue;
+                }
+                settled.insert(key);
+                skipped.insert(key, blocker);
+                blocking.push(key);
+            }
+        }
+    }
+    skipped
+}
+
+fn operations_depend(
+    changeset: &MultiFileChangeset,
+    declared: &DeclaredDependencies,
+    blocker: OperationKey,
+    dependent: OperationKey,
+) -> bool {
+    if declared
+        .get(&dependent)
+        .is_some_and(|prerequisites| prerequisites.contains(&blocker))
+    {
+        return true;
+    }
+    let ((left_file, left_index), (right_file, right_index)) = (blocker, dependent);
+    let left = &changeset.files[left_file].operations[left_index];
+    let right = &changeset.files[right_file].operations[right_index];
+    if left_file == right_file {
+        spans_interfere(left.preview.matched_span, right.preview.matched_span)
+    } else {
+        is_cross_file_move_pair(left, right) || is_cross_file_move_pair(right, left)
+    }
+}
+
+/// `edit` compiles a cross-file move into a delete of the node and an insert of its exact
+/// text in the destination file.
+fn is_cross_file_move_pair(deleted: &ChangeOp, inserted: &ChangeOp) -> bool {
+    let OpKind::Delete = deleted.op else {
+        return false;
+    };
+    let (OpKind::InsertBefore { new_text } | OpKind::InsertAfter { new_text }) = &inserted.op
+    else {
+        return false;
+    };
+    let deleted_hash = deleted
+        .preview
+        .old_hash
+        .clone()
+        .or_else(|| deleted.preview.old_text.as_deref().map(hash_text));
+    deleted_hash.is_some_and(|deleted_hash| deleted_hash == hash_text(new_text))
+}
+
+/// Overlapping spans interfere, and so does an insertion point inside or at the edge of
+/// the other span.
+fn spans_interfere(left: Span, right: Span) -> bool {
+    if left.start == left.end || right.start == right.end {
+        left.start <= right.end && right.start <= left.end
+    } else {
+        left.start < right.end && right.start < left.end
+    }
+}
+
+fn build_updated_text(
+    file: &Path,
+    source_text: String,
+    matched_changes: Vec<MatchedChange>,
+    transaction: &TransactionSpec,
+    registry: &ProviderRegistry,
+) -> Result<(PlannedText, Option<Normalization>, ChangeStats), IdenteditError> {
+    validate_change_conflicts(&matched_changes)?;
+    let replacements = matched_changes_to_replacements(matched_changes)?;
+    let edited = edited_ranges(&replacements);
+    let original_lines = line_count(&source_text);
+    let (original_text, mut updated_text) = plan_rewrite(file, source_text, replacements)?;
+    let normalized = normalize_planned_text(file, &mut updated_text, &edited, transaction)?;
+    let stats = ChangeStats::for_rewrite(
+        file,
+        &original_text,
+        &updated_text,
+        transaction.settings.invalidated_identities.then_some(registry),
+    );
+    check_fraction_changed(
+        file,
+        original_lines,
+        stats.lines_removed,
+        &transaction.settings.guards,
+    )?;
+    Ok((updated_text, normalized, stats))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::spans_interfere;
+    use crate::handle::Span;
+
+    fn span(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+
+    #[test]
+    fn spans_interfere_on_overlap_and_insertions_at_edges() {
+        assert!(spans_interfere(span(0, 10), span(5, 15)));
+        assert!(!spans_interfere(span(0, 10), span(10, 20)));
+        assert!(spans_interfere(span(10, 10), span(0, 10)));
+        assert!(spans_interfere(span(0, 0), span(0, 10)));
+        assert!(!spans_interfere(span(11, 11), span(0, 10)));
+    }
+}
@@ -4,44 +4,59 @@ use std::fs;
 use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
 
-use crate::changeset::{FileChange, hash_bytes};
+use crate::changeset::{FileChange, TransactionSpec, hash_bytes};
 use crate::error::IdenteditError;
 use crate::execution_context::ExecutionContext;
+use crate::path_style::render_path;
 use crate::provider::ProviderRegistry;
 use crate::transform::{
-    parse_handles_for_source_with_registry, resolve_changeset_targets_in_handles,
-    validate_change_conflicts,
+    parse_handles_for_source, resolve_each_changeset_target_in_handles, validate_change_conflicts,
 };
 
+use super::guards::{check_deleted_lines, check_fraction_changed, line_count};
 use super::io::{
-    ApplyFileLock, ApplyGuardState, acquire_apply_lock, capture_apply_guard_state,
-    verify_apply_guard_state, write_text_atomically,
+    ApplyFileLock, ApplyGuardState, Durability, PlannedText, acquire_apply_lock,
+    capture_apply_guard_state, prepare_write_target, verify_apply_guard_state,
+    write_planned_keeping_permissions,
 };
+use super::normalize::{Normalization, edited_ranges, normalize_planned_text};
+use super::optional::{ignored_operation, ignores_missing_target};
+use super::partial::OperationRef;
+use super::protected::{check_protected_regions, protected_regions};
 use super::replacements::{
-    apply_replacements_to_text, matched_changes_to_replacements, validate_preview_consistency,
+    matched_changes_to_replacements, plan_rewrite, validate_preview_consistency,
 };
+use super::secrets::check_written_secrets;
+use super::stats::ChangeStats;
 use super::{ApplyFileResult, ApplyFileStatus};
 
 #[derive(Debug)]
 pub(super) struct PreflightFilePlan {
     pub(super) file: PathBuf,
+    /// Operations to apply, leaving out the ignored ones.
     pub(super) operations_total: usize,
-    original_text: String,
+    /// Optional operations whose target is missing.
+    pub(super) ignored: Vec<OperationRef>,
+    original_text: PlannedText,
     original_permissions: std::fs::Permissions,
-    pub(super) updated_text: String,
+    pub(super) updated_text: PlannedText,
+    pub(super) normalized: Option<Normalization>,
+    pub(super) stats: ChangeStats,
     guard_state: ApplyGuardState,
+    durability: Durability,
     _lock_guard: ApplyFileLock,
 }
 
 pub(super) fn preflight_changesets_in_order(
     changesets: &[FileChange],
+    transaction: &TransactionSpec,
     registry: &ProviderRegistry,
 ) -> Result<Vec<PreflightFilePlan>, IdenteditError> {
     let ordered_changesets = order_changesets_for_preflight(changesets)?;
-    let context = ExecutionContext::new();
+    let context = ExecutionContext::new(registry);
     let mut plans = Vec::with_capacity(changesets.len());
     for changeset in ordered_changesets {
-        let plan = preflight_changeset(changeset, registry, &context)?;
+        let plan = preflight_changeset(changeset, transaction, registry, &context)?;
         plans.push(plan);
     }
 
@@ -81,7 +96,7 @@ fn duplicate_key_for_canonical_path(path: &Path) -> Result<ChangesetDuplicateKey
     }
 }
 
-fn order_changesets_for_preflight(
+pub(super) fn order_changesets_for_preflight(
     changesets: &[FileChange],
 ) -> Result<Vec<&FileChange>, IdenteditError> {
     let mut ordered = Vec::with_capacity(changesets.len());
@@ -124,39 +139,81 @@ fn order_changesets_for_preflight(
 
 fn preflight_changeset(
     changeset: &FileChange,
+    transaction: &TransactionSpec,
     registry: &ProviderRegistry,
     context: &ExecutionContext,
 ) -> Result<PreflightFilePlan, IdenteditError> {
-    let lock_guard = acquire_apply_lock(&changeset.file)?;
+    let original_permissions = prepare_write_target(&changeset.file, transaction)?;
+    let lock_guard = acquire_apply_lock(&changeset.file, transaction.settings.session.as_deref())?;
     let guard_state = capture_apply_guard_state(&changeset.file)?;
     let source_text = context.read_file_utf8(&changeset.file)?;
-    let original_permissions = fs::metadata(&changeset.file)
-        .map_err(|error| IdenteditError::io(&changeset.file, error))?
-        .permissions();
     let requires_structure_parse = changeset.operations.is_empty()
         || changeset
             .operations
             .iter()
             .any(|operation| operation.target.requires_node_resolution());
     let handles = if requires_structure_parse {
-        parse_handles_for_source_with_registry(&changeset.file, source_text.as_bytes(), registry)?
+        parse_handles_for_source(&changeset.file, source_text.as_bytes(), registry)?
     } else {
         Vec::new()
     };
-    let matched_changes = resolve_changeset_targets_in_handles(changeset, &source_text, &handles)?;
+    let protected = protected_regions(&changeset.file, &source_text, &transaction.settings)?;
+    let mut matched_changes = Vec::with_capacity(changeset.operations.len());
+    let mut ignored = Vec::new();
+    for (index, resolved) in
+        resolve_each_changeset_target_in_handles(changeset, &source_text, &handles)
+            .into_iter()
+            .enumerate()
+    {
+        match resolved {
+            Ok(matched) => {
+                check_protected_regions(&changeset.file, &protected, &matched)?;
+                check_deleted_lines(&changeset.file, &matched, &transaction.settings.guards)?;
+                if transaction.settings.secret_scan {
+                    check_written_secrets(&changeset.file, &source_text, &matched)?;
+                }
+                matched_changes.push(matched);
+            }
+            Err(error) if ignores_missing_target(&changeset.operations[index], &error) => {
+                ignored.push(ignored_operation(changeset, index));
+            }
+            Err(error) => return Err(error),
+        }
+    }
     validate_change_conflicts(&matched_changes)?;
     validate_preview_consistency(changeset, &matched_changes)?;
     let replacements = matched_changes_to_replacements(matched_changes)?;
-    let original_text = source_text.clone();
-    let updated_text = apply_replacements_to_text(&changeset.file, source_text, replacements)?;
+    let edited = edited_ranges(&replacements);
+    let original_lines = line_count(&source_text);
+    let (original_text, mut updated_text) =
+        plan_rewrite(&changeset.file, source_text, replacements)?;
+    let normalized =
+        normalize_planned_text(&changeset.file, &mut updated_text, &edited, transaction)?;
+    let stats = ChangeStats::for_rewrite(
+        &changeset.file,
+        &original_text,
+        &updated_text,
+        transaction.settings.invalidated_identities.then_some(registry),
+    )
+    .with_identities(&changeset.operations);
+    check_fraction_changed(
+        &changeset.file,
+        original_lines,
+        stats.lines_removed,
+        &transaction.settings.guards,
+    )?;
 
     Ok(PreflightFilePlan {
         file: changeset.file.clone(),
-        operations_total: changeset.operations.len(),
+        operations_total: changeset.operations.len() - ignored.len(),
+        ignored,
         original_text,
         original_permissions,
         updated_text,
+        normalized,
+        stats,
         guard_state,
+        durability: transaction.settings.durability,
         _lock_guard: lock_guard,
     })
 }
@@ -164,8 +221,9 @@ fn preflight_changeset(
 #[derive(Debug, Clone)]
 pub(super) struct FileRollbackSnapshot {
     pub(super) file: PathBuf,
-    pub(super) original_text: String,
+    pub(super) original_text: PlannedText,
     pub(super) original_permissions: std::fs::Permissions,
+    pub(super) durability: Durability,
 }
 
 #[derive(Debug)]
@@ -181,6 +239,7 @@ pub(super) fn prepare_commit_batch(preflight_plans: Vec<PreflightFilePlan>) -> C
             file: plan.file.clone(),
             original_text: plan.original_text.clone(),
             original_permissions: plan.original_permissions.clone(),
+            durability: plan.durability,
         })
         .collect();
 
@@ -213,8 +272,10 @@ fn validate_commit_batch_invariants(batch: &CommitBatch) -> Result<(), Identedit
             });
         }
 
-        let snapshot_hash = hash_bytes(snapshot.original_text.as_bytes());
-        if snapshot_hash != plan.guard_state.source_hash {
+        // Spliced snapshots only hold the edited spans; the guard hash covers the rest.
+        if let PlannedText::Full(original_text) = &snapshot.original_text
+            && hash_bytes(original_text.as_bytes()) != plan.guard_state.source_hash
+        {
             return Err(IdenteditError::InvalidRequest {
                 message: format!(
                     "Internal commit planning error at index {index}: snapshot hash does not match preflight source hash for '{}'",
@@ -283,9 +344,13 @@ pub(super) fn rollback_committed_files(
                         "Internal rollback error: missing snapshot for committed index {index}"
                     ),
                 })?;
-        write_text_atomically(&snapshot.file, &snapshot.original_text, None)?;
-        fs::set_permissions(&snapshot.file, snapshot.original_permissions.clone())
-            .map_err(|error| IdenteditError::io(&snapshot.file, error))?;
+        write_planned_keeping_permissions(
+            &snapshot.file,
+            &snapshot.original_text,
+            None,
+            &snapshot.original_permissions,
+            snapshot.durability,
+        )?;
     }
 
     Ok(())
@@ -300,12 +365,21 @@ where
 {
     verify_apply_guard_state(&plan.file, &plan.guard_state)?;
     after_verify_hook()?;
-    write_text_atomically(&plan.file, &plan.updated_text, Some(&plan.guard_state))?;
+    let metadata_warnings = write_planned_keeping_permissions(
+        &plan.file,
+        &plan.updated_text,
+        Some(&plan.guard_state),
+        &plan.original_permissions,
+        plan.durability,
+    )?;
 
     Ok(ApplyFileResult {
-        file: plan.file.display().to_string(),
+        file: render_path(&plan.file),
         operations_applied: plan.operations_total,
         operations_total: plan.operations_total,
         status: ApplyFileStatus::Applied,
+        metadata_warnings,
+        normalized: plan.normalized,
+        stats: Some(plan.stats),
     })
 }
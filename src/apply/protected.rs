@@ -0,0 +1,283 @@
+//! Protected regions: lines `apply` refuses to modify whatever the plan says.
+//!
+//! A file protects lines by enclosing them in an `identedit:protected-begin` comment and an
+//! `identedit:protected-end` comment; a begin marker without an end protects the rest of
+//! the file. The `protected_regions` rules of the project config protect line ranges of the
+//! files their path pattern matches. An insertion at either edge of a region is allowed.
+
+use std::ops::Range;
+use std::path::{Component, Path};
+
+use glob::{MatchOptions, Pattern};
+
+use crate::error::IdenteditError;
+use crate::project_config::PROJECT_CONFIG_FILE;
+use crate::transform::MatchedChange;
+
+use super::ApplySettings;
+
+const BEGIN_MARKER: &str = "identedit:protected-begin";
+const END_MARKER: &str = "identedit:protected-end";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct ProtectedRegion {
+    /// 1-based first and last line.
+    lines: (usize, usize),
+    span: Range<usize>,
+    origin: String,
+}
+
+/// Fails with `protected_region` when `matched` would write inside one of `regions`.
+pub(super) fn check_protected_regions(
+    file: &Path,
+    regions: &[ProtectedRegion],
+    matched: &MatchedChange,
+) -> Result<(), IdenteditError> {
+    let span = matched.matched_span.start..matched.matched_span.end;
+    check_range(file, regions, &span)?;
+    if let Some(insert_at) = matched.move_insert_at {
+        check_range(file, regions, &(insert_at..insert_at))?;
+    }
+    Ok(())
+}
+
+/// Fails with `protected_region` when rewriting `original` as `contents` changes a line of
+/// one of `regions`.
+pub(super) fn check_protected_rewrite(
+    file: &Path,
+    regions: &[ProtectedRegion],
+    original: &str,
+    contents: &str,
+) -> Result<(), IdenteditError> {
+    if regions.is_empty() || original == contents {
+        return Ok(());
+    }
+    let prefix = original
+        .bytes()
+        .zip(contents.bytes())
+        .take_while(|(left, right)| left == right)
+        .count();
+    let suffix = original[prefix..]
+        .bytes()
+        .rev()
+        .zip(contents[prefix..].bytes().rev())
+        .take_while(|(left, right)| left == right)
+        .count();
+    check_range(file, regions, &(prefix..original.len() - suffix))
+}
+
+fn check_range(
+    file: &Path,
+    regions: &[ProtectedRegion],
+    range: &Range<usize>,
+) -> Result<(), IdenteditError> {
+    let touched = |region: &&ProtectedRegion| {
+        if range.is_empty() {
+            region.span.start < range.start && range.start < region.span.end
+        } else {
+            range.start < region.span.end && region.span.start < range.end
+        }
+    };
+    match regions.iter().find(touched) {
+        Some(region) => Err(IdenteditError::ProtectedRegion {
+            file: file.display().to_string(),
+            start_line: region.lines.0,
+            end_line: region.lines.1,
+            origin: region.origin.clone(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// The protected regions of `file`, whose text is `source`, from its markers and the
+/// config rules of `settings`, in line order per origin.
+pub(super) fn protected_regions(
+    file: &Path,
+    source: &str,
+    settings: &ApplySettings,
+) -> Result<Vec<ProtectedRegion>, IdenteditError> {
+    let rules = settings.protected_regions.as_slice();
+    if rules.is_empty() && !source.contains(BEGIN_MARKER) {
+        return Ok(Vec::new());
+    }
+    let line_spans = source
+        .split_inclusive('\n')
+        .scan(0, |start, line| {
+            let span = *start..*start + line.len();
+            *start = span.end;
+            Some((line, span))
+        })
+        .collect::<Vec<_>>();
+
+    let mut regions = Vec::new();
+    let mut open = None;
+    for (index, (line, span)) in line_spans.iter().enumerate() {
+        match open {
+            None if has_marker(line, BEGIN_MARKER) => open = Some((index, span.start)),
+            Some((begin, start)) if has_marker(line, END_MARKER) => {
+                regions.push(ProtectedRegion {
+                    lines: (begin + 1, index + 1),
+                    span: start..span.end,
+                    origin: format!("an {BEGIN_MARKER} marker"),
+                });
+                open = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some((begin, start)) = open {
+        regions.push(ProtectedRegion {
+            lines: (begin + 1, line_spans.len()),
+            span: start..source.len(),
+            origin: format!("an {BEGIN_MARKER} marker without an end marker"),
+        });
+    }
+
+    let Some(relative) = relative_path(file, &settings.project_root) else {
+        return Ok(regions);
+    };
+    let options = MatchOptions {
+        require_literal_separator: true,
+        ..MatchOptions::default()
+    };
+    for rule in rules {
+        let pattern = Pattern::new(&rule.path).map_err(|error| IdenteditError::InvalidRequest {
+            message: format!(
+                "Invalid protected_regions path '{}' in '{PROJECT_CONFIG_FILE}': {error}",
+                rule.path
+            ),
+        })?;
+        let [first, last] = rule.lines;
+        if first == 0 || last < first {
+            return Err(IdenteditError::InvalidRequest {
+                message: format!(
+                    "protected_regions lines [{first}, {last}] for '{}' in '{PROJECT_CONFIG_FILE}' must be 1-based with the first line not after the last",
+                    rule.path
+                ),
+            });
+        }
+        if !pattern.matches_with(&relative, options) || first > line_spans.len() {
+            continue;
+        }
+        let last = last.min(line_spans.len());
+        regions.push(ProtectedRegion {
+            lines: (first, last),
+            span: line_spans[first - 1].1.start..line_spans[last - 1].1.end,
+            origin: format!("the protected_regions rule for '{}'", rule.path),
+        });
+    }
+    Ok(regions)
+}
+
+/// Whether `line` holds `marker` as a word of its own, so that text merely quoting the
+/// marker, such as "`identedit:protected-begin`", does not protect anything.
+fn has_marker(line: &str, marker: &str) -> bool {
+    line.match_indices(marker).any(|(at, _)| {
+        let before = line[..at].chars().next_back();
+        let after = line[at + marker.len()..].chars().next();
+        before.is_none_or(char::is_whitespace) && after.is_none_or(char::is_whitespace)
+    })
+}
+
+/// `file` relative to `root`, or to the current directory when `root` is empty, with `/`
+/// separators, as config rules name it; `None` for files outside it.
+fn relative_path(file: &Path, root: &Path) -> Option<String> {
+    let relative = if file.is_absolute() || !root.as_os_str().is_empty() {
+        let current = std::env::current_dir().ok()?;
+        let root = current.join(root);
+        let canonical_root = root.canonicalize().unwrap_or_else(|_| root.clone());
+        let file = current.join(file);
+        file.strip_prefix(&root)
+            .or_else(|_| file.strip_prefix(&canonical_root))
+            .ok()?
+            .to_path_buf()
+    } else {
+        file.to_path_buf()
+    };
+    let mut parts = Vec::new();
+    for component in relative.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    Some(parts.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::apply::ApplySettings;
+    use crate::project_config::ProtectedRegionRule;
+
+    use super::{check_range, protected_regions};
+
+    #[test]
+    fn markers_and_rules_protect_lines_but_not_their_edges() {
+        let source =
+            "// license\n# identedit:protected-begin\nkeep()\n# identedit:protected-end\nfree()\n";
+        let settings = ApplySettings {
+            protected_regions: vec![ProtectedRegionRule {
+                path: "src/*.py".to_string(),
+                lines: [1, 1],
+            }],
+            ..ApplySettings::default()
+        };
+        let regions = protected_regions(Path::new("src/main.py"), source, &settings)
+            .expect("regions should resolve");
+        assert_eq!(
+            regions
+                .iter()
+                .map(|region| region.lines)
+                .collect::<Vec<_>>(),
+            vec![(2, 4), (1, 1)]
+        );
+
+        let keep = source.find("keep").expect("keep is in the source");
+        let free = source.find("free").expect("free is in the source");
+        let file = Path::new("src/main.py");
+        assert!(check_range(file, &regions, &(keep..keep + 4)).is_err());
+        assert!(check_range(file, &regions, &(keep..keep)).is_err());
+        assert!(check_range(file, &regions, &(free..free)).is_ok());
+        assert!(check_range(file, &regions, &(free..free + 4)).is_ok());
+        assert!(check_range(file, &regions, &(0..0)).is_ok());
+
+        let regions = protected_regions(Path::new("other/main.py"), source, &settings)
+            .expect("regions should resolve");
+        assert_eq!(regions.len(), 1);
+    }
+
+    #[test]
+    fn quoted_markers_do_not_protect_anything() {
+        let source = "const BEGIN: &str = \"identedit:protected-begin\";\n";
+        let regions = protected_regions(Path::new("lib.rs"), source, &ApplySettings::default())
+            .expect("regions should resolve");
+        assert!(regions.is_empty());
+    }
+
+    #[test]
+    fn rules_match_paths_relative_to_the_project_root() {
+        let source = "// license\nbody()\n";
+        let root = std::env::temp_dir().join("identedit-protected-root");
+        let settings = ApplySettings {
+            protected_regions: vec![ProtectedRegionRule {
+                path: "src/*.py".to_string(),
+                lines: [1, 1],
+            }],
+            project_root: root.clone(),
+            ..ApplySettings::default()
+        };
+        let regions = protected_regions(&root.join("src/main.py"), source, &settings)
+            .expect("regions should resolve");
+        assert_eq!(regions.len(), 1);
+
+        let regions = protected_regions(Path::new("src/main.py"), source, &settings)
+            .expect("regions should resolve");
+        assert!(regions.is_empty());
+        let regions = protected_regions(Path::new("/elsewhere/src/main.py"), source, &settings)
+            .expect("regions should resolve");
+        assert!(regions.is_empty());
+    }
+}
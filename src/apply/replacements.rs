@@ -5,6 +5,8 @@ use crate::error::IdenteditError;
 use crate::handle::Span;
 use crate::transform::MatchedChange;
 
+use super::io::{PlannedText, STREAMING_APPLY_MIN_BYTES, Splice};
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(super) struct ResolvedReplacement {
     pub(super) index: usize,
@@ -92,15 +94,84 @@ pub(super) fn matched_changes_to_replacements(
 pub(super) fn apply_replacements_to_text(
     file: &Path,
     mut source_text: String,
-    mut replacements: Vec<ResolvedReplacement>,
+    replacements: Vec<ResolvedReplacement>,
 ) -> Result<String, IdenteditError> {
+    let replacements = validate_replacements_against_text(file, &source_text, replacements)?;
+    for replacement in replacements.iter().rev() {
+        source_text.replace_range(replacement.start..replacement.end, &replacement.new_text);
+    }
+
+    Ok(source_text)
+}
+
+/// What to write for `source_text` after `replacements`, and what rollback writes back.
+/// Files of at least [`STREAMING_APPLY_MIN_BYTES`] only keep the edited spans: forward splices
+/// for the commit and inverse splices that restore the original over the committed file.
+/// `source_text` itself is dropped here, but the caller has already read (and for node targets
+/// parsed) the whole file, so preflight still peaks at the file size; only the plan held until
+/// commit and rollback is bounded by the edits.
+pub(super) fn plan_rewrite(
+    file: &Path,
+    source_text: String,
+    replacements: Vec<ResolvedReplacement>,
+) -> Result<(PlannedText, PlannedText), IdenteditError> {
+    if source_text.len() < STREAMING_APPLY_MIN_BYTES {
+        let original_text = source_text.clone();
+        let updated_text = apply_replacements_to_text(file, source_text, replacements)?;
+        return Ok((
+            PlannedText::Full(original_text),
+            PlannedText::Full(updated_text),
+        ));
+    }
+
+    let replacements = validate_replacements_against_text(file, &source_text, replacements)?;
+    drop(source_text);
+    let (forward, inverse) = splices_for_replacements(replacements)?;
+    Ok((PlannedText::Spliced(inverse), PlannedText::Spliced(forward)))
+}
+
+/// Forward splices apply `replacements` in position order; inverse splices undo them on the
+/// rewritten file. Rejects the same overlaps as [`apply_replacements_to_text`], including an
+/// insert that touches another edit, whose order would otherwise be ambiguous.
+pub(super) fn splices_for_replacements(
+    mut replacements: Vec<ResolvedReplacement>,
+) -> Result<(Vec<Splice>, Vec<Splice>), IdenteditError> {
+    replacements.sort_by_key(|replacement| (replacement.start, replacement.end, replacement.index));
+    ensure_non_overlapping(&replacements)?;
+    let mut forward = Vec::with_capacity(replacements.len());
+    let mut inverse = Vec::with_capacity(replacements.len());
+    let mut shift = 0isize;
+    for replacement in replacements {
+        let start = replacement.start.saturating_add_signed(shift);
+        inverse.push(Splice {
+            start,
+            end: start + replacement.new_text.len(),
+            text: replacement.old_text,
+        });
+        shift +=
+            replacement.new_text.len() as isize - (replacement.end - replacement.start) as isize;
+        forward.push(Splice {
+            start: replacement.start,
+            end: replacement.end,
+            text: replacement.new_text,
+        });
+    }
+
+    Ok((forward, inverse))
+}
+
+/// Sorts `replacements` by position and checks each against `source_text` without applying it.
+fn validate_replacements_against_text(
+    file: &Path,
+    source_text: &str,
+    mut replacements: Vec<ResolvedReplacement>,
+) -> Result<Vec<ResolvedReplacement>, IdenteditError> {
     replacements.sort_by_key(|replacement| (replacement.start, replacement.end, replacement.index));
     ensure_non_overlapping(&replacements)?;
 
     for replacement in replacements.iter().rev() {
-        let span = replacement.start..replacement.end;
         let current_text = source_text
-            .get(span.clone())
+            .get(replacement.start..replacement.end)
             .ok_or_else(|| IdenteditError::InvalidRequest {
                 message: format!(
                     "Operation {} matched span [{}, {}) is not a valid UTF-8 boundary range for file '{}'",
@@ -118,11 +189,9 @@ pub(super) fn apply_replacements_to_text(
                 actual_hash,
             });
         }
-
-        source_text.replace_range(span, &replacement.new_text);
     }
 
-    Ok(source_text)
+    Ok(replacements)
 }
 
 pub(super) fn validate_preview_consistency(
@@ -0,0 +1,78 @@
+//! Opt-in refusal of writes that introduce credentials, such as an AWS access key or a
+//! private key block pasted into new text.
+//!
+//! Only text an edit writes is scanned, and a match that the file already held is let
+//! through, so editing around an existing fixture key does not fail. `secret_scan` in the
+//! project config turns the scan on; `--allow-secrets` turns it off again. Callers run
+//! these checks only when the apply's `ApplySettings::secret_scan` is set.
+
+use std::path::Path;
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::changeset::OpKind;
+use crate::error::IdenteditError;
+use crate::transform::MatchedChange;
+
+fn secret_patterns() -> &'static [(&'static str, Regex)] {
+    static PATTERNS: OnceLock<Vec<(&'static str, Regex)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            ("an AWS access key id", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b"),
+            (
+                "an AWS secret access key",
+                r#"(?i)aws_secret_access_key\s*[:=]\s*["']?[A-Za-z0-9/+=]{40}\b"#,
+            ),
+            (
+                "a private key block",
+                r"-----BEGIN (?:[A-Z0-9]+ )*PRIVATE KEY-----",
+            ),
+            ("a GitHub token", r"\bgh[pousr]_[A-Za-z0-9]{36}\b"),
+            ("a Slack token", r"\bxox[abposr]-[A-Za-z0-9-]{10,}"),
+        ]
+        .into_iter()
+        .map(|(name, pattern)| {
+            let regex = Regex::new(pattern).expect("secret pattern should compile");
+            (name, regex)
+        })
+        .collect()
+    })
+}
+
+/// Fails with `potential_secret` when the text `matched` writes holds a credential that
+/// `source`, the file as it was, does not.
+pub(super) fn check_written_secrets(
+    file: &Path,
+    source: &str,
+    matched: &MatchedChange,
+) -> Result<(), IdenteditError> {
+    match &matched.op {
+        OpKind::Replace { new_text }
+        | OpKind::InsertBefore { new_text }
+        | OpKind::InsertAfter { new_text }
+        | OpKind::Insert { new_text } => check_new_secrets(file, source, new_text),
+        _ => Ok(()),
+    }
+}
+
+/// Fails with `potential_secret` when `contents` holds a credential that `original` does
+/// not.
+pub(super) fn check_new_secrets(
+    file: &Path,
+    original: &str,
+    contents: &str,
+) -> Result<(), IdenteditError> {
+    for (kind, regex) in secret_patterns() {
+        if regex
+            .find_iter(contents)
+            .any(|found| !original.contains(found.as_str()))
+        {
+            return Err(IdenteditError::PotentialSecret {
+                file: file.display().to_string(),
+                kind,
+            });
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,155 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use serde::Serialize;
+use similar::{DiffTag, TextDiff};
+
+use crate::changeset::{ChangeOp, TransformTarget};
+use crate::provider::ProviderRegistry;
+use crate::transform::parse_handles_for_source;
+
+use super::io::PlannedText;
+
+/// What an apply changed in one file, so callers need not re-diff it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ChangeStats {
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    /// Size of the rewritten file minus the original, in bytes.
+    pub bytes_delta: i64,
+    /// Identities of the node targets the applied operations edited, as they were before
+    /// the edit; sorted and without duplicates.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub identities: Vec<String>,
+    /// Identities of the file's nodes, before the edit, that no longer exist at the same
+    /// span: edited nodes, their ancestors, and nodes the edit shifted. Cached handles with
+    /// these identities are stale. Absent unless requested through
+    /// [`ApplySettings::invalidated_identities`](super::ApplySettings), when either version
+    /// of the file cannot be parsed, or when the file is too large to rewrite in memory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invalidated_identities: Option<Vec<String>>,
+}
+
+/// Per-file stats as listed in the apply summary.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileChangeStats {
+    pub file: String,
+    #[serde(flatten)]
+    pub stats: ChangeStats,
+}
+
+impl ChangeStats {
+    /// Counts the lines a line diff of `old_text` against `new_text` adds and removes; a
+    /// changed line counts once each way.
+    pub fn between(old_text: &str, new_text: &str) -> Self {
+        let mut stats = Self {
+            bytes_delta: new_text.len() as i64 - old_text.len() as i64,
+            ..Self::default()
+        };
+        stats.add_line_counts(old_text, new_text);
+        stats
+    }
+
+    /// Stats for a planned rewrite of `file`, with `invalidated_identities` when given a
+    /// `registry`. Spliced rewrites are too large to diff whole, so each splice is diffed on
+    /// its own, and a splice inside a line counts that line as changed.
+    pub(super) fn for_rewrite(
+        file: &Path,
+        original_text: &PlannedText,
+        updated_text: &PlannedText,
+        registry: Option<&ProviderRegistry>,
+    ) -> Self {
+        match (original_text, updated_text) {
+            (PlannedText::Full(original_text), PlannedText::Full(updated_text)) => {
+                let stats = Self::between(original_text, updated_text);
+                match registry {
+                    Some(registry) => {
+                        stats.with_invalidations(file, original_text, updated_text, registry)
+                    }
+                    None => stats,
+                }
+            }
+            (PlannedText::Spliced(inverse), PlannedText::Spliced(forward)) => {
+                let mut stats = Self::default();
+                for (old, new) in inverse.iter().zip(forward) {
+                    stats.bytes_delta += new.text.len() as i64 - old.text.len() as i64;
+                    stats.add_line_counts(&old.text, &new.text);
+                }
+                stats
+            }
+            _ => Self::default(),
+        }
+    }
+
+    pub(super) fn with_identities<'a>(
+        mut self,
+        operations: impl IntoIterator<Item = &'a ChangeOp>,
+    ) -> Self {
+        self.identities = operations
+            .into_iter()
+            .filter_map(|operation| match &operation.target {
+                TransformTarget::Node { identity, .. } => Some(identity.clone()),
+                _ => None,
+            })
+            .collect();
+        self.identities.sort();
+        self.identities.dedup();
+        self
+    }
+
+    pub(crate) fn with_invalidations(
+        mut self,
+        file: &Path,
+        original_text: &str,
+        updated_text: &str,
+        registry: &ProviderRegistry,
+    ) -> Self {
+        let parse = |text: &str| parse_handles_for_source(file, text.as_bytes(), registry).ok();
+        let (Some(before), Some(after)) = (parse(original_text), parse(updated_text)) else {
+            return self;
+        };
+        let kept = after
+            .iter()
+            .map(|handle| (handle.identity.as_str(), handle.span.start, handle.span.end))
+            .collect::<HashSet<_>>();
+        let mut seen = HashSet::new();
+        self.invalidated_identities = Some(
+            before
+                .iter()
+                .filter(|handle| {
+                    !kept.contains(&(handle.identity.as_str(), handle.span.start, handle.span.end))
+                })
+                .filter(|handle| seen.insert(handle.identity.as_str()))
+                .map(|handle| handle.identity.clone())
+                .collect(),
+        );
+        self
+    }
+
+    fn add_line_counts(&mut self, old_text: &str, new_text: &str) {
+        for operation in TextDiff::from_lines(old_text, new_text).ops() {
+            let (tag, old_range, new_range) = operation.as_tag_tuple();
+            if tag != DiffTag::Equal {
+                self.lines_removed += old_range.len();
+                self.lines_added += new_range.len();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_counts_treat_a_changed_line_as_removed_and_added() {
+        let stats = ChangeStats::between("a\nb\nc\n", "a\nB\nc\nd\n");
+        assert_eq!(stats.lines_removed, 1);
+        assert_eq!(stats.lines_added, 2);
+        assert_eq!(stats.bytes_delta, 2);
+        assert_eq!(
+            ChangeStats::between("same\n", "same\n"),
+            ChangeStats::default()
+        );
+    }
+}
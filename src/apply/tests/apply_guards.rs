@@ -4,12 +4,14 @@ use std::os::unix::fs::PermissionsExt;
 
 use tempfile::tempdir;
 
+use crate::changeset::TransactionSpec;
 use crate::error::IdenteditError;
+use crate::provider::ProviderRegistry;
 use crate::transform::{build_replace_changeset, parse_handles_for_file};
 
 use super::super::{
     ApplyGuardState, apply_changeset, apply_changeset_with_hook, apply_changeset_with_hooks,
-    capture_path_fingerprint, verify_apply_guard_state,
+    apply_changesets_with_hooks, capture_path_fingerprint, verify_apply_guard_state,
 };
 use super::create_python_target;
 
@@ -17,7 +19,8 @@ use super::create_python_target;
 fn apply_detects_path_swap_before_write() {
     let directory = tempdir().expect("tempdir should be created");
     let file_path = create_python_target(directory.path());
-    let handles = parse_handles_for_file(&file_path).expect("handles should parse");
+    let handles = parse_handles_for_file(&file_path, &ProviderRegistry::default())
+        .expect("handles should parse");
     let process_handle = handles
         .iter()
         .find(|handle| handle.name.as_deref() == Some("process_data"))
@@ -27,6 +30,7 @@ fn apply_detects_path_swap_before_write() {
         &file_path,
         &process_handle.identity,
         "def process_data(value):\n    return value * 10".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset should be created");
 
@@ -79,7 +83,8 @@ fn apply_detects_alias_write_race_without_inode_swap() {
         .modified()
         .expect("mtime should be readable");
 
-    let handles = parse_handles_for_file(&file_path).expect("handles should parse");
+    let handles = parse_handles_for_file(&file_path, &ProviderRegistry::default())
+        .expect("handles should parse");
     let process_handle = handles
         .iter()
         .find(|handle| handle.name.as_deref() == Some("process_data"))
@@ -88,6 +93,7 @@ fn apply_detects_alias_write_race_without_inode_swap() {
         &file_path,
         &process_handle.identity,
         "def process_data(value):\n    return value * 10".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset should be created");
 
@@ -104,8 +110,17 @@ fn apply_detects_alias_write_race_without_inode_swap() {
         Ok(())
     };
 
-    let error = apply_changeset_with_hook(&changeset, &mut before_write)
-        .expect_err("alias mutation should be detected as stale content");
+    let force_unlink = TransactionSpec {
+        force_unlink: true,
+        ..TransactionSpec::default()
+    };
+    let error = apply_changesets_with_hooks(
+        std::slice::from_ref(&changeset),
+        &force_unlink,
+        &mut before_write,
+        || Ok(()),
+    )
+    .expect_err("alias mutation should be detected as stale content");
     match error {
         IdenteditError::PreconditionFailed { .. } => {}
         other => panic!("unexpected error variant: {other}"),
@@ -148,7 +163,8 @@ fn apply_detects_symlinked_ancestor_alias_write_race_without_inode_swap() {
         .modified()
         .expect("mtime should be readable");
 
-    let handles = parse_handles_for_file(&alias_path).expect("handles should parse");
+    let handles = parse_handles_for_file(&alias_path, &ProviderRegistry::default())
+        .expect("handles should parse");
     let process_handle = handles
         .iter()
         .find(|handle| handle.name.as_deref() == Some("process_data"))
@@ -157,6 +173,7 @@ fn apply_detects_symlinked_ancestor_alias_write_race_without_inode_swap() {
         &alias_path,
         &process_handle.identity,
         "def process_data(value):\n    return value * 10".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset should be created");
 
@@ -207,7 +224,8 @@ fn apply_detects_post_verify_mutation_before_atomic_write() {
         .modified()
         .expect("mtime should be readable");
 
-    let handles = parse_handles_for_file(&file_path).expect("handles should parse");
+    let handles = parse_handles_for_file(&file_path, &ProviderRegistry::default())
+        .expect("handles should parse");
     let process_handle = handles
         .iter()
         .find(|handle| handle.name.as_deref() == Some("process_data"))
@@ -216,6 +234,7 @@ fn apply_detects_post_verify_mutation_before_atomic_write() {
         &file_path,
         &process_handle.identity,
         "def process_data(value):\n    return value * 10".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset should be created");
 
@@ -263,7 +282,8 @@ fn apply_detects_symlink_swap_after_verify_before_atomic_write() {
     )
     .expect("symlink target fixture should be written");
 
-    let handles = parse_handles_for_file(&file_path).expect("handles should parse");
+    let handles = parse_handles_for_file(&file_path, &ProviderRegistry::default())
+        .expect("handles should parse");
     let process_handle = handles
         .iter()
         .find(|handle| handle.name.as_deref() == Some("process_data"))
@@ -272,6 +292,7 @@ fn apply_detects_symlink_swap_after_verify_before_atomic_write() {
         &file_path,
         &process_handle.identity,
         "def process_data(value):\n    return value * 10".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset should be created");
 
@@ -316,7 +337,8 @@ fn apply_fails_atomically_when_directory_permissions_flip_mid_apply() {
         .expect("parent metadata should be readable")
         .permissions();
 
-    let handles = parse_handles_for_file(&file_path).expect("handles should parse");
+    let handles = parse_handles_for_file(&file_path, &ProviderRegistry::default())
+        .expect("handles should parse");
     let process_handle = handles
         .iter()
         .find(|handle| handle.name.as_deref() == Some("process_data"))
@@ -325,6 +347,7 @@ fn apply_fails_atomically_when_directory_permissions_flip_mid_apply() {
         &file_path,
         &process_handle.identity,
         "def process_data(value):\n    return value * 10".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset should be created");
 
@@ -357,7 +380,8 @@ fn apply_fails_atomically_when_directory_permissions_flip_mid_apply() {
 fn apply_failure_path_releases_file_lock_for_subsequent_retry() {
     let directory = tempdir().expect("tempdir should be created");
     let file_path = create_python_target(directory.path());
-    let handles = parse_handles_for_file(&file_path).expect("handles should parse");
+    let handles = parse_handles_for_file(&file_path, &ProviderRegistry::default())
+        .expect("handles should parse");
     let process_handle = handles
         .iter()
         .find(|handle| handle.name.as_deref() == Some("process_data"))
@@ -366,6 +390,7 @@ fn apply_failure_path_releases_file_lock_for_subsequent_retry() {
         &file_path,
         &process_handle.identity,
         "def process_data(value):\n    return value * 10".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset should be created");
 
@@ -390,7 +415,8 @@ fn apply_failure_path_releases_file_lock_for_subsequent_retry() {
 fn apply_reports_io_error_when_target_is_deleted_mid_flight() {
     let directory = tempdir().expect("tempdir should be created");
     let file_path = create_python_target(directory.path());
-    let handles = parse_handles_for_file(&file_path).expect("handles should parse");
+    let handles = parse_handles_for_file(&file_path, &ProviderRegistry::default())
+        .expect("handles should parse");
     let process_handle = handles
         .iter()
         .find(|handle| handle.name.as_deref() == Some("process_data"))
@@ -399,6 +425,7 @@ fn apply_reports_io_error_when_target_is_deleted_mid_flight() {
         &file_path,
         &process_handle.identity,
         "def process_data(value):\n    return value * 10".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset should be created");
 
@@ -423,7 +450,8 @@ fn apply_reports_io_error_when_target_is_deleted_mid_flight() {
 fn apply_reports_path_changed_when_target_is_swapped_to_directory() {
     let directory = tempdir().expect("tempdir should be created");
     let file_path = create_python_target(directory.path());
-    let handles = parse_handles_for_file(&file_path).expect("handles should parse");
+    let handles = parse_handles_for_file(&file_path, &ProviderRegistry::default())
+        .expect("handles should parse");
     let process_handle = handles
         .iter()
         .find(|handle| handle.name.as_deref() == Some("process_data"))
@@ -432,6 +460,7 @@ fn apply_reports_path_changed_when_target_is_swapped_to_directory() {
         &file_path,
         &process_handle.identity,
         "def process_data(value):\n    return value * 10".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset should be created");
 
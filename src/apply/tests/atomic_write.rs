@@ -6,7 +6,8 @@ use tempfile::tempdir;
 use crate::error::IdenteditError;
 
 use super::super::{
-    AtomicWritePhase, write_text_atomically_with_hook, write_text_atomically_with_hook_and_rename,
+    AtomicWritePhase, Durability, PlannedText, Splice, write_planned_atomically,
+    write_text_atomically_with_hook, write_text_atomically_with_hook_and_rename,
 };
 use super::fail_on_phase;
 
@@ -147,6 +148,52 @@ fn atomic_write_preserves_existing_file_mode_bits() {
     );
 }
 
+#[cfg(unix)]
+#[test]
+fn atomic_write_preserves_extended_attributes() {
+    let directory = tempdir().expect("tempdir should be created");
+    let file_path = directory.path().join("target.txt");
+    std::fs::write(&file_path, "old").expect("fixture write should succeed");
+    if xattr::set(&file_path, "user.identedit.test", b"watched").is_err() {
+        // The filesystem backing the temp dir does not support user xattrs.
+        return;
+    }
+
+    let warnings = write_text_atomically_with_hook(&file_path, "new", |_| Ok(()))
+        .expect("atomic write should succeed");
+
+    assert!(warnings.is_empty(), "unexpected warnings: {warnings:?}");
+    assert_eq!(
+        xattr::get(&file_path, "user.identedit.test").expect("xattr should be readable"),
+        Some(b"watched".to_vec())
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn atomic_write_restores_foreign_ownership() {
+    use std::os::unix::fs::MetadataExt;
+
+    let directory = tempdir().expect("tempdir should be created");
+    let file_path = directory.path().join("target.txt");
+    std::fs::write(&file_path, "old").expect("fixture write should succeed");
+    if std::os::unix::fs::chown(&file_path, Some(4242), Some(4242)).is_err() {
+        // Only root can hand a file to another user, and only then is there anything to restore.
+        return;
+    }
+
+    let warnings = write_text_atomically_with_hook(&file_path, "new", |_| Ok(()))
+        .expect("atomic write should succeed");
+
+    let metadata = std::fs::metadata(&file_path).expect("metadata should be readable");
+    assert!(warnings.is_empty(), "unexpected warnings: {warnings:?}");
+    assert_eq!((metadata.uid(), metadata.gid()), (4242, 4242));
+    assert_eq!(
+        std::fs::read_to_string(&file_path).expect("target should be readable"),
+        "new"
+    );
+}
+
 #[cfg(unix)]
 #[test]
 fn atomic_write_failure_in_read_only_directory_preserves_file_and_cleans_temp() {
@@ -276,3 +323,67 @@ fn atomic_write_exdev_like_rename_failure_preserves_contents_and_cleans_temp_fil
         "EXDEV-like rename failure should clean temporary files: {temp_entries:?}"
     );
 }
+
+#[test]
+fn spliced_write_keeps_coincident_empty_splices_in_order() {
+    let directory = tempdir().expect("tempdir should be created");
+    let file_path = directory.path().join("target.txt");
+    std::fs::write(&file_path, "abc").expect("fixture write should succeed");
+
+    let splices = vec![
+        Splice {
+            start: 1,
+            end: 1,
+            text: "X".to_string(),
+        },
+        Splice {
+            start: 1,
+            end: 1,
+            text: "Y".to_string(),
+        },
+    ];
+    write_planned_atomically(
+        &file_path,
+        &PlannedText::Spliced(splices),
+        None,
+        Durability::Dir,
+    )
+    .expect("spliced write should succeed");
+
+    let actual = std::fs::read_to_string(&file_path).expect("target should be readable");
+    assert_eq!(actual, "aXYbc");
+}
+
+#[test]
+fn spliced_write_rejects_out_of_order_splices_and_preserves_contents() {
+    let directory = tempdir().expect("tempdir should be created");
+    let file_path = directory.path().join("target.txt");
+    std::fs::write(&file_path, "abcdef").expect("fixture write should succeed");
+
+    let splices = vec![
+        Splice {
+            start: 2,
+            end: 4,
+            text: "X".to_string(),
+        },
+        Splice {
+            start: 3,
+            end: 3,
+            text: "Y".to_string(),
+        },
+    ];
+    let error = write_planned_atomically(
+        &file_path,
+        &PlannedText::Spliced(splices),
+        None,
+        Durability::Dir,
+    )
+    .expect_err("overlapping splices should be rejected");
+    assert!(
+        matches!(error, IdenteditError::InvalidRequest { ref message } if message.contains("non-overlapping")),
+        "unexpected error: {error}"
+    );
+
+    let actual = std::fs::read_to_string(&file_path).expect("target should be readable");
+    assert_eq!(actual, "abcdef");
+}
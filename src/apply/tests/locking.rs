@@ -4,14 +4,17 @@ use std::time::Duration;
 
 use tempfile::tempdir;
 
-use crate::changeset::{OpKind, TransformTarget, hash_text};
+use crate::changeset::{OpKind, TransactionSpec, TransformTarget, hash_text};
 use crate::error::IdenteditError;
+use crate::provider::ProviderRegistry;
 use crate::transform::{
     TransformInstruction, build_changeset, build_delete_changeset, build_replace_changeset,
     parse_handles_for_file,
 };
 
-use super::super::{acquire_apply_lock, apply_changeset, apply_changeset_with_hook};
+use super::super::{
+    acquire_apply_lock, apply_changeset, apply_changeset_with_hook, apply_changesets_with_hooks,
+};
 
 #[test]
 fn apply_lock_rejects_second_concurrent_holder_on_same_file() {
@@ -19,8 +22,8 @@ fn apply_lock_rejects_second_concurrent_holder_on_same_file() {
     let file_path = directory.path().join("target.py");
     std::fs::write(&file_path, "def a():\n    return 1").expect("fixture write should succeed");
 
-    let first_lock = acquire_apply_lock(&file_path).expect("first lock should succeed");
-    let second_attempt = acquire_apply_lock(&file_path);
+    let first_lock = acquire_apply_lock(&file_path, None).expect("first lock should succeed");
+    let second_attempt = acquire_apply_lock(&file_path, None);
     let error = second_attempt.expect_err("second lock should fail while first lock is held");
 
     match error {
@@ -32,7 +35,7 @@ fn apply_lock_rejects_second_concurrent_holder_on_same_file() {
 
     drop(first_lock);
 
-    let third_attempt = acquire_apply_lock(&file_path);
+    let third_attempt = acquire_apply_lock(&file_path, None);
     assert!(
         third_attempt.is_ok(),
         "lock should be acquirable after previous holder is dropped"
@@ -49,8 +52,9 @@ fn apply_lock_allows_independent_files_in_parallel() {
     std::fs::write(&second_file, "def second():\n    return 2")
         .expect("second fixture write should succeed");
 
-    let first_lock = acquire_apply_lock(&first_file).expect("first file lock should succeed");
-    let second_lock = acquire_apply_lock(&second_file).expect("second file lock should succeed");
+    let first_lock = acquire_apply_lock(&first_file, None).expect("first file lock should succeed");
+    let second_lock =
+        acquire_apply_lock(&second_file, None).expect("second file lock should succeed");
 
     drop(first_lock);
     drop(second_lock);
@@ -64,8 +68,8 @@ fn apply_lock_rejects_hardlink_alias_for_same_underlying_file() {
     std::fs::write(&canonical, "def value():\n    return 1").expect("fixture write should succeed");
     std::fs::hard_link(&canonical, &alias).expect("hard link should be created");
 
-    let first_lock = acquire_apply_lock(&canonical).expect("first lock should succeed");
-    let second_attempt = acquire_apply_lock(&alias);
+    let first_lock = acquire_apply_lock(&canonical, None).expect("first lock should succeed");
+    let second_attempt = acquire_apply_lock(&alias, None);
     let error = second_attempt.expect_err("hardlink alias should contend for the same file lock");
 
     match error {
@@ -90,7 +94,8 @@ fn apply_on_hardlinked_target_splits_alias_after_atomic_rename() {
     .expect("fixture write should succeed");
     std::fs::hard_link(&canonical, &alias).expect("hard link should be created");
 
-    let handles = parse_handles_for_file(&canonical).expect("handles should parse");
+    let handles = parse_handles_for_file(&canonical, &ProviderRegistry::default())
+        .expect("handles should parse");
     let process_handle = handles
         .iter()
         .find(|handle| handle.name.as_deref() == Some("process_data"))
@@ -99,10 +104,31 @@ fn apply_on_hardlinked_target_splits_alias_after_atomic_rename() {
         &canonical,
         &process_handle.identity,
         "def process_data(value):\n    return value * 10".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset should be created");
 
-    apply_changeset(&changeset).expect("apply should succeed on canonical path");
+    #[cfg(unix)]
+    {
+        let error = apply_changeset(&changeset)
+            .expect_err("hard-linked target should be refused without force_unlink");
+        assert!(
+            matches!(error, IdenteditError::HardLinkedTarget { links: 2, .. }),
+            "unexpected error variant: {error}"
+        );
+    }
+
+    let force_unlink = TransactionSpec {
+        force_unlink: true,
+        ..TransactionSpec::default()
+    };
+    apply_changesets_with_hooks(
+        std::slice::from_ref(&changeset),
+        &force_unlink,
+        || Ok(()),
+        || Ok(()),
+    )
+    .expect("apply should succeed on canonical path with force_unlink");
 
     let canonical_contents =
         std::fs::read_to_string(&canonical).expect("canonical should be readable");
@@ -130,8 +156,8 @@ fn apply_lock_rejects_dot_segment_alias_for_same_file() {
     std::fs::write(&canonical, "def value():\n    return 1").expect("fixture write should succeed");
     let alias = nested.join("..").join("nested").join("target.py");
 
-    let first_lock = acquire_apply_lock(&canonical).expect("first lock should succeed");
-    let second_attempt = acquire_apply_lock(&alias);
+    let first_lock = acquire_apply_lock(&canonical, None).expect("first lock should succeed");
+    let second_attempt = acquire_apply_lock(&alias, None);
     let error =
         second_attempt.expect_err("dot-segment alias should contend for the same file lock");
 
@@ -160,8 +186,8 @@ fn apply_lock_rejects_symlinked_ancestor_alias_for_same_file() {
     let alias = alias_dir.join("target.py");
     std::fs::write(&canonical, "def value():\n    return 1").expect("fixture write should succeed");
 
-    let first_lock = acquire_apply_lock(&canonical).expect("first lock should succeed");
-    let second_attempt = acquire_apply_lock(&alias);
+    let first_lock = acquire_apply_lock(&canonical, None).expect("first lock should succeed");
+    let second_attempt = acquire_apply_lock(&alias, None);
     let error =
         second_attempt.expect_err("symlinked-ancestor alias should contend for the same lock");
 
@@ -185,14 +211,19 @@ fn concurrent_apply_with_delete_and_insert_has_single_winner_and_resource_busy_l
     )
     .expect("fixture write should succeed");
 
-    let handles = parse_handles_for_file(&file_path).expect("handles should parse");
+    let handles = parse_handles_for_file(&file_path, &ProviderRegistry::default())
+        .expect("handles should parse");
     let process_handle = handles
         .iter()
         .find(|handle| handle.name.as_deref() == Some("process_data"))
         .expect("process_data handle should exist");
 
-    let delete_changeset = build_delete_changeset(&file_path, &process_handle.identity)
-        .expect("delete changeset should be built");
+    let delete_changeset = build_delete_changeset(
+        &file_path,
+        &process_handle.identity,
+        &ProviderRegistry::default(),
+    )
+    .expect("delete changeset should be built");
     let insert_changeset = build_changeset(
         &file_path,
         vec![TransformInstruction {
@@ -206,6 +237,7 @@ fn concurrent_apply_with_delete_and_insert_has_single_winner_and_resource_busy_l
                 new_text: "# inserted-by-loser\n".to_string(),
             },
         }],
+        &ProviderRegistry::default(),
     )
     .expect("insert changeset should be built");
 
@@ -265,15 +297,20 @@ fn concurrent_apply_loser_retry_returns_target_missing_after_winner_commits() {
     )
     .expect("fixture write should succeed");
 
-    let handles = parse_handles_for_file(&file_path).expect("handles should parse");
+    let handles = parse_handles_for_file(&file_path, &ProviderRegistry::default())
+        .expect("handles should parse");
     let process_handle = handles
         .iter()
         .find(|handle| handle.name.as_deref() == Some("process_data"))
         .expect("process_data handle should exist");
     let expected_identity = process_handle.identity.clone();
 
-    let delete_changeset = build_delete_changeset(&file_path, &process_handle.identity)
-        .expect("delete changeset should be built");
+    let delete_changeset = build_delete_changeset(
+        &file_path,
+        &process_handle.identity,
+        &ProviderRegistry::default(),
+    )
+    .expect("delete changeset should be built");
     let insert_changeset = build_changeset(
         &file_path,
         vec![TransformInstruction {
@@ -287,6 +324,7 @@ fn concurrent_apply_loser_retry_returns_target_missing_after_winner_commits() {
                 new_text: "# inserted-by-loser\n".to_string(),
             },
         }],
+        &ProviderRegistry::default(),
     )
     .expect("insert changeset should be built");
 
@@ -1,16 +1,18 @@
 use tempfile::tempdir;
 
-use crate::changeset::{ChangeOp, ChangePreview, FileChange, OpKind, TransformTarget};
+use crate::changeset::{
+    ChangeOp, ChangePreview, FileChange, OpKind, TransactionSpec, TransformTarget,
+};
 use crate::error::IdenteditError;
 use crate::handle::Span;
 use crate::provider::ProviderRegistry;
 use crate::transform::{build_replace_changeset, parse_handles_for_file};
 
 use super::super::{
-    ApplyFileStatus, FileRollbackSnapshot, acquire_apply_lock, apply_changesets_with_hooks,
-    commit_move_plan_with_rename, commit_preflight_batch, preflight_changesets_in_order,
-    preflight_move_plans, prepare_commit_batch, rollback_committed_files,
-    validate_move_operation_constraints,
+    ApplyFileStatus, ApplySettings, Durability, FileRollbackSnapshot, PlannedText,
+    acquire_apply_lock, apply_changesets_with_hooks, commit_move_plan_with_rename,
+    commit_preflight_batch, preflight_changesets_in_order, preflight_move_plans,
+    prepare_commit_batch, rollback_committed_files, validate_move_operation_constraints,
 };
 use super::create_python_target;
 use std::fs::FileTimes;
@@ -19,7 +21,8 @@ use std::fs::FileTimes;
 use std::os::unix::fs::symlink;
 
 fn process_identity_for(file_path: &std::path::Path) -> String {
-    let handles = parse_handles_for_file(file_path).expect("handles should parse");
+    let handles = parse_handles_for_file(file_path, &ProviderRegistry::default())
+        .expect("handles should parse");
     handles
         .iter()
         .find(|handle| handle.name.as_deref() == Some("process_data"))
@@ -32,12 +35,17 @@ fn build_move_changeset(source: &std::path::Path, destination: &std::path::Path)
     FileChange {
         file: source.to_path_buf(),
         operations: vec![ChangeOp {
+            id: None,
+            depends_on: Vec::new(),
+            correlation_id: None,
+            group: None,
             target: TransformTarget::node(
                 "move-placeholder".to_string(),
                 "file".to_string(),
                 None,
                 "move-placeholder".to_string(),
             ),
+            optional: false,
             op: OpKind::Move {
                 to: destination.to_path_buf(),
             },
@@ -73,28 +81,34 @@ fn preflight_builds_file_plans_without_mutating_sources() {
         &file_a,
         &process_identity_for(&file_a),
         "def process_data(value):\n    return value * 10".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset_a should be built");
     let changeset_b = build_replace_changeset(
         &file_b,
         &process_identity_for(&file_b),
         "def process_data(value):\n    return value * 11".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset_b should be built");
 
     let registry = ProviderRegistry::default();
-    let plans = preflight_changesets_in_order(&[changeset_a, changeset_b], &registry)
-        .expect("preflight should succeed for both files");
+    let plans = preflight_changesets_in_order(
+        &[changeset_a, changeset_b],
+        &TransactionSpec::default(),
+        &registry,
+    )
+    .expect("preflight should succeed for both files");
 
     assert_eq!(plans.len(), 2);
     assert_eq!(plans[0].file, file_a);
     assert_eq!(plans[1].file, file_b);
     assert!(
-        plans[0].updated_text.contains("return value * 10"),
+        matches!(&plans[0].updated_text, PlannedText::Full(text) if text.contains("return value * 10")),
         "first preflight plan should include replacement"
     );
     assert!(
-        plans[1].updated_text.contains("return value * 11"),
+        matches!(&plans[1].updated_text, PlannedText::Full(text) if text.contains("return value * 11")),
         "second preflight plan should include replacement"
     );
 
@@ -121,12 +135,14 @@ fn preflight_fails_fast_and_preserves_all_file_contents() {
         &file_a,
         &process_identity_for(&file_a),
         "def process_data(value):\n    return value * 10".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset_a should be built");
     let mut changeset_b = build_replace_changeset(
         &file_b,
         &process_identity_for(&file_b),
         "def process_data(value):\n    return value * 11".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset_b should be built");
     let stale_span_hint = match &changeset_b.operations[0].target {
@@ -141,8 +157,12 @@ fn preflight_fails_fast_and_preserves_all_file_contents() {
     );
 
     let registry = ProviderRegistry::default();
-    let error = preflight_changesets_in_order(&[changeset_a, changeset_b], &registry)
-        .expect_err("preflight should fail when one file has an unresolved target");
+    let error = preflight_changesets_in_order(
+        &[changeset_a, changeset_b],
+        &TransactionSpec::default(),
+        &registry,
+    )
+    .expect_err("preflight should fail when one file has an unresolved target");
     match error {
         IdenteditError::PreconditionFailed { .. } => {}
         other => panic!("unexpected error variant: {other}"),
@@ -162,16 +182,17 @@ fn preflight_holds_file_locks_until_plans_are_dropped() {
         &file_path,
         &process_identity_for(&file_path),
         "def process_data(value):\n    return value * 42".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset should be built");
 
     let registry = ProviderRegistry::default();
-    let plans =
-        preflight_changesets_in_order(&[changeset], &registry).expect("preflight should succeed");
+    let plans = preflight_changesets_in_order(&[changeset], &TransactionSpec::default(), &registry)
+        .expect("preflight should succeed");
     assert_eq!(plans.len(), 1);
 
-    let lock_error =
-        acquire_apply_lock(&file_path).expect_err("preflight lock should block concurrent lock");
+    let lock_error = acquire_apply_lock(&file_path, None)
+        .expect_err("preflight lock should block concurrent lock");
     match lock_error {
         IdenteditError::ResourceBusy { path } => {
             assert_eq!(path, file_path.display().to_string());
@@ -181,7 +202,7 @@ fn preflight_holds_file_locks_until_plans_are_dropped() {
 
     drop(plans);
 
-    let post_drop_lock = acquire_apply_lock(&file_path);
+    let post_drop_lock = acquire_apply_lock(&file_path, None);
     assert!(
         post_drop_lock.is_ok(),
         "lock should be acquirable after preflight plans are dropped"
@@ -201,18 +222,24 @@ fn preflight_orders_plans_by_canonical_path_not_input_order() {
         &file_b,
         &process_identity_for(&file_b),
         "def process_data(value):\n    return value * 20".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset_b should be built");
     let changeset_a = build_replace_changeset(
         &file_a,
         &process_identity_for(&file_a),
         "def process_data(value):\n    return value * 21".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset_a should be built");
 
     let registry = ProviderRegistry::default();
-    let plans = preflight_changesets_in_order(&[changeset_b, changeset_a], &registry)
-        .expect("preflight should succeed");
+    let plans = preflight_changesets_in_order(
+        &[changeset_b, changeset_a],
+        &TransactionSpec::default(),
+        &registry,
+    )
+    .expect("preflight should succeed");
     assert_eq!(plans.len(), 2);
     assert_eq!(
         plans[0].file, file_a,
@@ -239,18 +266,24 @@ fn preflight_rejects_duplicate_logical_file_entries_before_locking() {
         &canonical,
         &process_identity_for(&canonical),
         "def process_data(value):\n    return value * 30".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset_a should be built");
     let changeset_b = build_replace_changeset(
         &dot_alias,
         &process_identity_for(&dot_alias),
         "def process_data(value):\n    return value * 31".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset_b should be built");
 
     let registry = ProviderRegistry::default();
-    let error = preflight_changesets_in_order(&[changeset_a, changeset_b], &registry)
-        .expect_err("duplicate logical path entries should be rejected");
+    let error = preflight_changesets_in_order(
+        &[changeset_a, changeset_b],
+        &TransactionSpec::default(),
+        &registry,
+    )
+    .expect_err("duplicate logical path entries should be rejected");
     match error {
         IdenteditError::InvalidRequest { message } => {
             assert!(
@@ -279,18 +312,24 @@ fn preflight_rejects_duplicate_entries_for_hardlink_aliases() {
         &canonical,
         &process_identity_for(&canonical),
         "def process_data(value):\n    return value * 34".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset_a should be built");
     let changeset_b = build_replace_changeset(
         &hardlink_alias,
         &process_identity_for(&hardlink_alias),
         "def process_data(value):\n    return value * 35".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset_b should be built");
 
     let registry = ProviderRegistry::default();
-    let error = preflight_changesets_in_order(&[changeset_a, changeset_b], &registry)
-        .expect_err("hardlink alias entries should be rejected as duplicate file entries");
+    let error = preflight_changesets_in_order(
+        &[changeset_a, changeset_b],
+        &TransactionSpec::default(),
+        &registry,
+    )
+    .expect_err("hardlink alias entries should be rejected as duplicate file entries");
     match error {
         IdenteditError::InvalidRequest { message } => {
             assert!(
@@ -326,18 +365,24 @@ fn preflight_rejects_duplicate_entries_for_symlinked_dot_segment_aliases() {
         &canonical,
         &process_identity_for(&canonical),
         "def process_data(value):\n    return value * 32".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset_a should be built");
     let changeset_b = build_replace_changeset(
         &symlink_dot_alias,
         &process_identity_for(&symlink_dot_alias),
         "def process_data(value):\n    return value * 33".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset_b should be built");
 
     let registry = ProviderRegistry::default();
-    let error = preflight_changesets_in_order(&[changeset_a, changeset_b], &registry)
-        .expect_err("duplicate canonical entries through symlink+dot aliases should be rejected");
+    let error = preflight_changesets_in_order(
+        &[changeset_a, changeset_b],
+        &TransactionSpec::default(),
+        &registry,
+    )
+    .expect_err("duplicate canonical entries through symlink+dot aliases should be rejected");
     match error {
         IdenteditError::InvalidRequest { message } => {
             assert!(
@@ -370,24 +415,28 @@ fn preflight_rejects_non_adjacent_hardlink_alias_entries() {
         &middle,
         &process_identity_for(&middle),
         "def process_data(value):\n    return value * 40".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("middle changeset should be built");
     let changeset_alias = build_replace_changeset(
         &hardlink_alias,
         &process_identity_for(&hardlink_alias),
         "def process_data(value):\n    return value * 41".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("alias changeset should be built");
     let changeset_canonical = build_replace_changeset(
         &canonical,
         &process_identity_for(&canonical),
         "def process_data(value):\n    return value * 42".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("canonical changeset should be built");
 
     let registry = ProviderRegistry::default();
     let error = preflight_changesets_in_order(
         &[changeset_middle, changeset_alias, changeset_canonical],
+        &TransactionSpec::default(),
         &registry,
     )
     .expect_err("non-adjacent hardlink alias entries should be rejected");
@@ -427,25 +476,29 @@ fn preflight_rejects_non_adjacent_hardlink_alias_entries_before_locking_middle_f
         &canonical,
         &process_identity_for(&canonical),
         "def process_data(value):\n    return value * 43".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("canonical changeset should be built");
     let changeset_middle = build_replace_changeset(
         &middle,
         &process_identity_for(&middle),
         "def process_data(value):\n    return value * 44".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("middle changeset should be built");
     let changeset_alias = build_replace_changeset(
         &hardlink_alias,
         &process_identity_for(&hardlink_alias),
         "def process_data(value):\n    return value * 45".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("alias changeset should be built");
 
-    let _middle_lock = acquire_apply_lock(&middle).expect("middle lock should be acquired");
+    let _middle_lock = acquire_apply_lock(&middle, None).expect("middle lock should be acquired");
     let registry = ProviderRegistry::default();
     let error = preflight_changesets_in_order(
         &[changeset_canonical, changeset_middle, changeset_alias],
+        &TransactionSpec::default(),
         &registry,
     )
     .expect_err("duplicate detection should fail before attempting middle lock");
@@ -477,18 +530,21 @@ fn preflight_duplicate_hardlink_diagnostic_is_input_order_independent_with_non_a
         &canonical,
         &process_identity_for(&canonical),
         "def process_data(value):\n    return value * 46".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("canonical changeset should be built");
     let changeset_middle = build_replace_changeset(
         &middle,
         &process_identity_for(&middle),
         "def process_data(value):\n    return value * 47".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("middle changeset should be built");
     let changeset_alias = build_replace_changeset(
         &hardlink_alias,
         &process_identity_for(&hardlink_alias),
         "def process_data(value):\n    return value * 48".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("alias changeset should be built");
 
@@ -499,11 +555,13 @@ fn preflight_duplicate_hardlink_diagnostic_is_input_order_independent_with_non_a
             changeset_alias.clone(),
             changeset_canonical.clone(),
         ],
+        &TransactionSpec::default(),
         &registry,
     )
     .expect_err("first permutation should reject duplicate alias entries");
     let second = preflight_changesets_in_order(
         &[changeset_canonical, changeset_middle, changeset_alias],
+        &TransactionSpec::default(),
         &registry,
     )
     .expect_err("second permutation should reject duplicate alias entries");
@@ -544,24 +602,28 @@ fn preflight_rejects_three_non_adjacent_hardlink_alias_entries() {
         &alias_z,
         &process_identity_for(&alias_z),
         "def process_data(value):\n    return value * 49".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("alias_z changeset should be built");
     let changeset_middle = build_replace_changeset(
         &middle,
         &process_identity_for(&middle),
         "def process_data(value):\n    return value * 50".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("middle changeset should be built");
     let changeset_alias_b = build_replace_changeset(
         &alias_b,
         &process_identity_for(&alias_b),
         "def process_data(value):\n    return value * 51".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("alias_b changeset should be built");
     let changeset_canonical = build_replace_changeset(
         &canonical,
         &process_identity_for(&canonical),
         "def process_data(value):\n    return value * 52".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("canonical changeset should be built");
 
@@ -573,6 +635,7 @@ fn preflight_rejects_three_non_adjacent_hardlink_alias_entries() {
             changeset_alias_b,
             changeset_canonical,
         ],
+        &TransactionSpec::default(),
         &registry,
     )
     .expect_err("multiple non-adjacent hardlink aliases should be rejected");
@@ -603,24 +666,31 @@ fn preflight_allows_three_distinct_files_with_identical_contents() {
         &file_z,
         &process_identity_for(&file_z),
         "def process_data(value):\n    return value * 53".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset_z should be built");
     let changeset_a = build_replace_changeset(
         &file_a,
         &process_identity_for(&file_a),
         "def process_data(value):\n    return value * 54".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset_a should be built");
     let changeset_m = build_replace_changeset(
         &file_m,
         &process_identity_for(&file_m),
         "def process_data(value):\n    return value * 55".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset_m should be built");
 
     let registry = ProviderRegistry::default();
-    let plans = preflight_changesets_in_order(&[changeset_z, changeset_a, changeset_m], &registry)
-        .expect("distinct files should be accepted");
+    let plans = preflight_changesets_in_order(
+        &[changeset_z, changeset_a, changeset_m],
+        &TransactionSpec::default(),
+        &registry,
+    )
+    .expect("distinct files should be accepted");
 
     assert_eq!(plans.len(), 3);
     assert_eq!(plans[0].file, file_a);
@@ -640,18 +710,21 @@ fn prepare_commit_batch_captures_rollback_snapshots_before_write() {
         &file_a,
         &process_identity_for(&file_a),
         "def process_data(value):\n    return value * 88".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset should be built");
     let registry = ProviderRegistry::default();
     let preflight_plans =
-        preflight_changesets_in_order(&[changeset], &registry).expect("preflight should succeed");
+        preflight_changesets_in_order(&[changeset], &TransactionSpec::default(), &registry)
+            .expect("preflight should succeed");
     let commit_batch = prepare_commit_batch(preflight_plans);
 
     assert_eq!(commit_batch.preflight_plans.len(), 1);
     assert_eq!(commit_batch.rollback_snapshots.len(), 1);
     assert_eq!(commit_batch.rollback_snapshots[0].file, file_a);
     assert_eq!(
-        commit_batch.rollback_snapshots[0].original_text, before,
+        commit_batch.rollback_snapshots[0].original_text,
+        PlannedText::Full(before),
         "rollback snapshot text should capture original pre-write content"
     );
 }
@@ -669,18 +742,24 @@ fn commit_preflight_batch_applies_changes_in_deterministic_order() {
         &file_b,
         &process_identity_for(&file_b),
         "def process_data(value):\n    return value * 91".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset_b should be built");
     let changeset_a = build_replace_changeset(
         &file_a,
         &process_identity_for(&file_a),
         "def process_data(value):\n    return value * 90".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset_a should be built");
 
     let registry = ProviderRegistry::default();
-    let preflight_plans = preflight_changesets_in_order(&[changeset_b, changeset_a], &registry)
-        .expect("preflight should succeed");
+    let preflight_plans = preflight_changesets_in_order(
+        &[changeset_b, changeset_a],
+        &TransactionSpec::default(),
+        &registry,
+    )
+    .expect("preflight should succeed");
     let commit_batch = prepare_commit_batch(preflight_plans);
     let applied = commit_preflight_batch(commit_batch, || Ok(()), || Ok(()))
         .expect("commit batch should succeed");
@@ -718,18 +797,24 @@ fn commit_batch_failure_rolls_back_already_written_files() {
         &file_b,
         &process_identity_for(&file_b),
         "def process_data(value):\n    return value * 101".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset_b should be built");
     let changeset_a = build_replace_changeset(
         &file_a,
         &process_identity_for(&file_a),
         "def process_data(value):\n    return value * 100".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset_a should be built");
 
     let registry = ProviderRegistry::default();
-    let preflight_plans = preflight_changesets_in_order(&[changeset_b, changeset_a], &registry)
-        .expect("preflight should succeed");
+    let preflight_plans = preflight_changesets_in_order(
+        &[changeset_b, changeset_a],
+        &TransactionSpec::default(),
+        &registry,
+    )
+    .expect("preflight should succeed");
     let commit_batch = prepare_commit_batch(preflight_plans);
 
     let mut hook_calls = 0usize;
@@ -786,18 +871,24 @@ fn commit_batch_precondition_failure_rolls_back_already_written_files() {
         &file_b,
         &process_identity_for(&file_b),
         "def process_data(value):\n    return value * 121".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset_b should be built");
     let changeset_a = build_replace_changeset(
         &file_a,
         &process_identity_for(&file_a),
         "def process_data(value):\n    return value * 120\n# rollback_probe".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset_a should be built");
 
     let registry = ProviderRegistry::default();
-    let preflight_plans = preflight_changesets_in_order(&[changeset_b, changeset_a], &registry)
-        .expect("preflight should succeed");
+    let preflight_plans = preflight_changesets_in_order(
+        &[changeset_b, changeset_a],
+        &TransactionSpec::default(),
+        &registry,
+    )
+    .expect("preflight should succeed");
     let commit_batch = prepare_commit_batch(preflight_plans);
 
     let file_b_for_hook = file_b.clone();
@@ -864,18 +955,24 @@ fn commit_batch_path_swap_failure_rolls_back_already_written_files() {
         &file_b,
         &process_identity_for(&file_b),
         "def process_data(value):\n    return value * 131".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset_b should be built");
     let changeset_a = build_replace_changeset(
         &file_a,
         &process_identity_for(&file_a),
         "def process_data(value):\n    return value * 130\n# rollback_probe".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset_a should be built");
 
     let registry = ProviderRegistry::default();
-    let preflight_plans = preflight_changesets_in_order(&[changeset_b, changeset_a], &registry)
-        .expect("preflight should succeed");
+    let preflight_plans = preflight_changesets_in_order(
+        &[changeset_b, changeset_a],
+        &TransactionSpec::default(),
+        &registry,
+    )
+    .expect("preflight should succeed");
     let commit_batch = prepare_commit_batch(preflight_plans);
 
     let file_b_for_hook = file_b.clone();
@@ -939,18 +1036,24 @@ fn rollback_failure_surfaces_rollback_failed_error() {
         &file_b,
         &process_identity_for(&file_b),
         "def process_data(value):\n    return value * 111".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset_b should be built");
     let changeset_a = build_replace_changeset(
         &file_a,
         &process_identity_for(&file_a),
         "def process_data(value):\n    return value * 110".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset_a should be built");
 
     let registry = ProviderRegistry::default();
-    let preflight_plans = preflight_changesets_in_order(&[changeset_b, changeset_a], &registry)
-        .expect("preflight should succeed");
+    let preflight_plans = preflight_changesets_in_order(
+        &[changeset_b, changeset_a],
+        &TransactionSpec::default(),
+        &registry,
+    )
+    .expect("preflight should succeed");
     let commit_batch = prepare_commit_batch(preflight_plans);
 
     let mut hook_calls = 0usize;
@@ -1007,11 +1110,13 @@ fn commit_preflight_batch_rejects_rollback_snapshot_count_mismatch() {
         &file,
         &process_identity_for(&file),
         "def process_data(value):\n    return value * 999".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset should be built");
     let registry = ProviderRegistry::default();
     let preflight_plans =
-        preflight_changesets_in_order(&[changeset], &registry).expect("preflight should succeed");
+        preflight_changesets_in_order(&[changeset], &TransactionSpec::default(), &registry)
+            .expect("preflight should succeed");
     let mut commit_batch = prepare_commit_batch(preflight_plans);
     commit_batch.rollback_snapshots.clear();
 
@@ -1043,8 +1148,11 @@ fn rollback_committed_files_reports_missing_snapshot_index_deterministically() {
 
     let snapshots = vec![FileRollbackSnapshot {
         file: file.clone(),
-        original_text: "def process_data(value):\n    return value + 1\n".to_string(),
+        original_text: PlannedText::Full(
+            "def process_data(value):\n    return value + 1\n".to_string(),
+        ),
         original_permissions: permissions,
+        durability: Durability::Dir,
     }];
     let error = rollback_committed_files(&snapshots, &[0, 1])
         .expect_err("out-of-range committed index should be rejected deterministically");
@@ -1078,6 +1186,7 @@ fn move_commit_failure_rolls_back_previously_committed_moves() {
     let mut hook_calls = 0usize;
     let error = apply_changesets_with_hooks(
         &[changeset_a_to_b, changeset_b_to_c],
+        &TransactionSpec::default(),
         || Ok(()),
         || {
             hook_calls += 1;
@@ -1138,6 +1247,7 @@ fn move_rollback_failure_surfaces_rollback_failed_error() {
     let mut hook_calls = 0usize;
     let error = apply_changesets_with_hooks(
         &[changeset_a_to_b, changeset_b_to_c],
+        &TransactionSpec::default(),
         || Ok(()),
         || {
             hook_calls += 1;
@@ -1182,8 +1292,8 @@ fn move_commit_exdev_like_error_does_not_fallback_to_copy() {
     let changeset = build_move_changeset(&source, &destination);
     let execution_order = validate_move_operation_constraints(&[changeset])
         .expect("single move should pass graph validation");
-    let plans =
-        preflight_move_plans(&execution_order).expect("move preflight should produce one plan");
+    let plans = preflight_move_plans(&execution_order, &ApplySettings::default())
+        .expect("move preflight should produce one plan");
     assert_eq!(plans.len(), 1);
 
     let error = commit_move_plan_with_rename(
@@ -1233,6 +1343,7 @@ fn mixed_edit_and_move_failure_rolls_back_committed_edit() {
         &edit_file,
         &process_identity_for(&edit_file),
         "def process_data(value):\n    return value * 777".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("edit changeset should be built");
     let move_changeset = build_move_changeset(&move_source, &move_destination);
@@ -1240,6 +1351,7 @@ fn mixed_edit_and_move_failure_rolls_back_committed_edit() {
     let mut hook_calls = 0usize;
     let error = apply_changesets_with_hooks(
         &[edit_changeset, move_changeset],
+        &TransactionSpec::default(),
         || Ok(()),
         || {
             hook_calls += 1;
@@ -1294,6 +1406,7 @@ fn mixed_edit_and_move_rollback_sabotage_returns_rollback_failed() {
         &edit_file,
         &process_identity_for(&edit_file),
         "def process_data(value):\n    return value * 888".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("edit changeset should be built");
     let move_changeset = build_move_changeset(&move_source, &move_destination);
@@ -1303,6 +1416,7 @@ fn mixed_edit_and_move_rollback_sabotage_returns_rollback_failed() {
     let mut hook_calls = 0usize;
     let error = apply_changesets_with_hooks(
         &[edit_changeset, move_changeset],
+        &TransactionSpec::default(),
         || Ok(()),
         || {
             hook_calls += 1;
@@ -1355,6 +1469,7 @@ fn move_only_batch_invokes_before_write_hook_once() {
     let mut before_calls = 0usize;
     let response = apply_changesets_with_hooks(
         &[changeset_a_to_b, changeset_b_to_c],
+        &TransactionSpec::default(),
         || {
             before_calls += 1;
             Ok(())
@@ -1398,6 +1513,7 @@ fn mixed_edit_move_batch_invokes_before_write_hook_once() {
         &edit_file,
         &process_identity_for(&edit_file),
         "def process_data(value):\n    return value * 321".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("edit changeset should be built");
     let move_changeset = build_move_changeset(&move_source, &move_destination);
@@ -1405,6 +1521,7 @@ fn mixed_edit_move_batch_invokes_before_write_hook_once() {
     let mut before_calls = 0usize;
     let response = apply_changesets_with_hooks(
         &[edit_changeset, move_changeset],
+        &TransactionSpec::default(),
         || {
             before_calls += 1;
             Ok(())
@@ -1446,6 +1563,7 @@ fn move_graph_validation_failure_skips_before_write_hook() {
     let mut before_calls = 0usize;
     let error = apply_changesets_with_hooks(
         &[self_move_changeset],
+        &TransactionSpec::default(),
         || {
             before_calls += 1;
             Ok(())
@@ -1526,13 +1644,18 @@ fn move_destination_exists_validation_preserves_mixed_batch_edit_file() {
         &edit_file,
         &process_identity_for(&edit_file),
         "def process_data(value):\n    return value * 654".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("edit changeset should be built");
     let move_changeset = build_move_changeset(&move_source, &move_destination);
 
-    let error =
-        apply_changesets_with_hooks(&[edit_changeset, move_changeset], || Ok(()), || Ok(()))
-            .expect_err("destination-exists validation should reject mixed batch before commit");
+    let error = apply_changesets_with_hooks(
+        &[edit_changeset, move_changeset],
+        &TransactionSpec::default(),
+        || Ok(()),
+        || Ok(()),
+    )
+    .expect_err("destination-exists validation should reject mixed batch before commit");
     match error {
         IdenteditError::InvalidRequest { message } => {
             assert!(
@@ -1562,13 +1685,18 @@ fn mixed_batch_with_missing_move_source_preserves_edit_file() {
         &edit_file,
         &process_identity_for(&edit_file),
         "def process_data(value):\n    return value * 987".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("edit changeset should be built");
     let move_changeset = build_move_changeset(&missing_move_source, &move_destination);
 
-    let error =
-        apply_changesets_with_hooks(&[edit_changeset, move_changeset], || Ok(()), || Ok(()))
-            .expect_err("missing move source should fail before any commit");
+    let error = apply_changesets_with_hooks(
+        &[edit_changeset, move_changeset],
+        &TransactionSpec::default(),
+        || Ok(()),
+        || Ok(()),
+    )
+    .expect_err("missing move source should fail before any commit");
     match error {
         IdenteditError::Io { path, .. } => {
             assert!(
@@ -1596,6 +1724,7 @@ fn missing_move_source_failure_skips_before_write_hook() {
     let mut before_calls = 0usize;
     let error = apply_changesets_with_hooks(
         &[move_changeset],
+        &TransactionSpec::default(),
         || {
             before_calls += 1;
             Ok(())
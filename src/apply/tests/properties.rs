@@ -8,7 +8,8 @@ use tempfile::tempdir;
 use crate::error::IdenteditError;
 
 use super::super::{
-    AtomicWritePhase, ResolvedReplacement, apply_replacements_to_text, ensure_non_overlapping,
+    AtomicWritePhase, Durability, PlannedText, ResolvedReplacement, apply_replacements_to_text,
+    ensure_non_overlapping, splices_for_replacements, write_planned_atomically,
     write_text_atomically_with_hook,
 };
 use super::fail_on_phase;
@@ -178,6 +179,66 @@ proptest! {
         prop_assert_eq!(forward_output.unwrap(), reverse_output.unwrap());
     }
 
+    #[test]
+    fn prop_spliced_rewrite_matches_in_memory_rewrite_and_inverse_restores_original(
+        source in "[a-z\n]{0,160}",
+        first_start in 0usize..80,
+        first_len in 0usize..16,
+        gap in 0usize..32,
+        second_len in 0usize..16,
+        first_new in "[A-Z\n]{0,24}",
+        second_new in "[A-Z\n]{0,24}",
+    ) {
+        let second_start = first_start + first_len + gap;
+        prop_assume!(second_start + second_len <= source.len());
+        let first_end = first_start + first_len;
+        let second_end = second_start + second_len;
+        let replacements = vec![
+            replacement(
+                0,
+                "hash-a",
+                source[first_start..first_end].to_string(),
+                first_start,
+                first_end,
+                first_new,
+            ),
+            replacement(
+                1,
+                "hash-b",
+                source[second_start..second_end].to_string(),
+                second_start,
+                second_end,
+                second_new,
+            ),
+        ];
+        let expected = apply_replacements_to_text(
+            Path::new("fixture.txt"),
+            source.clone(),
+            replacements.clone(),
+        );
+        let spliced = splices_for_replacements(replacements);
+        let Ok(expected) = expected else {
+            // Inserts touching another edit are rejected in memory; splicing must agree.
+            prop_assert!(spliced.is_err());
+            return Ok(());
+        };
+
+        let directory = tempdir().expect("tempdir should be created");
+        let file_path = directory.path().join("target.txt");
+        std::fs::write(&file_path, &source).expect("fixture write should succeed");
+        let (forward, inverse) = spliced.expect("splices should plan when the rewrite does");
+
+        write_planned_atomically(&file_path, &PlannedText::Spliced(forward), None, Durability::Dir)
+            .expect("spliced rewrite should succeed");
+        let rewritten = std::fs::read_to_string(&file_path).expect("target should be readable");
+        prop_assert_eq!(rewritten, expected);
+
+        write_planned_atomically(&file_path, &PlannedText::Spliced(inverse), None, Durability::Dir)
+            .expect("inverse splices should succeed");
+        let restored = std::fs::read_to_string(&file_path).expect("target should be readable");
+        prop_assert_eq!(restored, source);
+    }
+
     #[test]
     fn prop_atomic_write_failure_never_partially_writes(
         original in "[ -~]{0,80}",
@@ -8,18 +8,27 @@ fn summary_derives_failed_operations_from_per_file_totals() {
             operations_applied: 2,
             operations_total: 3,
             status: ApplyFileStatus::Applied,
+            metadata_warnings: Vec::new(),
+            normalized: None,
+            stats: None,
         },
         ApplyFileResult {
             file: "b.py".to_string(),
             operations_applied: 0,
             operations_total: 2,
             status: ApplyFileStatus::Applied,
+            metadata_warnings: Vec::new(),
+            normalized: None,
+            stats: None,
         },
         ApplyFileResult {
             file: "c.py".to_string(),
             operations_applied: 1,
             operations_total: 1,
             status: ApplyFileStatus::Applied,
+            metadata_warnings: Vec::new(),
+            normalized: None,
+            stats: None,
         },
     ];
 
@@ -36,6 +45,9 @@ fn summary_never_underflows_when_file_counts_are_inconsistent() {
         operations_applied: 5,
         operations_total: 2,
         status: ApplyFileStatus::Applied,
+        metadata_warnings: Vec::new(),
+        normalized: None,
+        stats: None,
     }];
 
     let summary = summarize_apply_results(&applied);
@@ -4,6 +4,7 @@ use std::os::unix::fs::symlink;
 use tempfile::tempdir;
 
 use crate::changeset::MultiFileChangeset;
+use crate::provider::ProviderRegistry;
 use crate::transform::{build_replace_changeset, parse_handles_for_file};
 
 use super::super::{apply_changeset, apply_multi_file_changeset};
@@ -46,7 +47,8 @@ fn apply_allows_symlinked_ancestor_when_target_entry_is_regular_file() {
     .expect("fixture write should succeed");
 
     let linked_path = link_dir.join("target.py");
-    let handles = parse_handles_for_file(&linked_path).expect("handles should parse");
+    let handles = parse_handles_for_file(&linked_path, &ProviderRegistry::default())
+        .expect("handles should parse");
     let process_handle = handles
         .iter()
         .find(|handle| handle.name.as_deref() == Some("process_data"))
@@ -55,6 +57,7 @@ fn apply_allows_symlinked_ancestor_when_target_entry_is_regular_file() {
         &linked_path,
         &process_handle.identity,
         "def process_data(value):\n    return value * 10".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("changeset should be created");
 
@@ -70,7 +73,8 @@ fn apply_multi_file_rejects_symlink_target_without_mutating_regular_siblings() {
     let regular_target = create_python_target(directory.path());
     let regular_before =
         std::fs::read_to_string(&regular_target).expect("regular fixture should be readable");
-    let regular_handles = parse_handles_for_file(&regular_target).expect("handles should parse");
+    let regular_handles = parse_handles_for_file(&regular_target, &ProviderRegistry::default())
+        .expect("handles should parse");
     let regular_process = regular_handles
         .iter()
         .find(|handle| handle.name.as_deref() == Some("process_data"))
@@ -79,6 +83,7 @@ fn apply_multi_file_rejects_symlink_target_without_mutating_regular_siblings() {
         &regular_target,
         &regular_process.identity,
         "def process_data(value):\n    return value * 77".to_string(),
+        &ProviderRegistry::default(),
     )
     .expect("regular changeset should be created");
 
@@ -98,6 +103,7 @@ fn apply_multi_file_rejects_symlink_target_without_mutating_regular_siblings() {
     let multi = MultiFileChangeset {
         files: vec![regular_changeset, symlink_changeset],
         transaction: Default::default(),
+        correlation_id: None,
     };
 
     let error = apply_multi_file_changeset(&multi)
@@ -0,0 +1,311 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::changeset::{ChangeOp, FileChange, MultiFileChangeset, OpKind, TransformTarget};
+use crate::error::{ErrorBody, IdenteditError};
+use crate::execution_context::ExecutionContext;
+use crate::handle::SelectionHandle;
+use crate::path_style::render_path;
+use crate::provider::ProviderRegistry;
+use crate::transform::{
+    MatchedChange, parse_handles_for_source, resolve_each_changeset_target_in_handles,
+};
+
+use super::ApplySettings;
+use super::optional::target_is_missing;
+use super::partial::OperationLabels;
+use super::preflight::order_changesets_for_preflight;
+use super::protected::{check_protected_regions, protected_regions};
+use super::replacements::{
+    ensure_non_overlapping, matched_changes_to_replacements, validate_preview_consistency,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationStatus {
+    /// The target resolves and still matches the plan.
+    Applicable,
+    /// The target or file changed since the plan was built: a hash no longer matches, or
+    /// a node of the target's kind still starts at its `span_hint` with other text.
+    Stale,
+    /// The target node, line, or file is gone.
+    Missing,
+    /// The target is gone, and the operation is optional, so `apply` skips it.
+    TargetMissingIgnored,
+    /// More than one node matches the target.
+    Ambiguous,
+    /// The target resolves, but the edit overlaps another applicable operation's.
+    Conflict,
+    /// The edit would modify a protected region.
+    Protected,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifiedOperation {
+    pub file: String,
+    pub operation: usize,
+    #[serde(flatten)]
+    pub labels: OperationLabels,
+    pub status: OperationStatus,
+    /// Why the operation is not applicable; absent when it is.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorBody>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VerifySummary {
+    pub operations_total: usize,
+    pub applicable: usize,
+    pub stale: usize,
+    pub missing: usize,
+    pub target_missing_ignored: usize,
+    pub ambiguous: usize,
+    pub conflict: usize,
+    pub protected: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyResponse {
+    /// Every operation is applicable or ignored, so `apply` would commit the plan as it
+    /// stands.
+    pub applicable: bool,
+    pub summary: VerifySummary,
+    pub operations: Vec<VerifiedOperation>,
+    /// The plan's `correlation_id`, echoed back.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+}
+
+type OperationKey = (usize, usize);
+
+/// Why an operation is not applicable.
+type Failure = (OperationStatus, ErrorBody);
+
+/// Runs the target resolution, precondition, and overlap checks of an apply against
+/// `changeset` without locking or writing anything, and reports each operation's status.
+/// A plan that is malformed as a whole, such as one listing a file twice, is still an
+/// error.
+pub fn verify_multi_file_changeset(
+    changeset: &MultiFileChangeset,
+) -> Result<VerifyResponse, IdenteditError> {
+    if changeset.files.is_empty() {
+        return Err(IdenteditError::InvalidRequest {
+            message: "changeset.files must contain at least one file".to_string(),
+        });
+    }
+
+    let mut outcomes = BTreeMap::<OperationKey, Result<(), Failure>>::new();
+    let mut reachable = Vec::with_capacity(changeset.files.len());
+    for (file_index, file_change) in changeset.files.iter().enumerate() {
+        match std::fs::canonicalize(&file_change.file) {
+            Ok(_) => reachable.push(file_index),
+            Err(error) => {
+                let failure = failure(&IdenteditError::io(&file_change.file, error));
+                for (operation, change_op) in file_change.operations.iter().enumerate() {
+                    outcomes.insert(
+                        (file_index, operation),
+                        Err(ignore_if_optional(change_op, failure.clone())),
+                    );
+                }
+            }
+        }
+    }
+    let reachable_changes = reachable
+        .iter()
+        .map(|file_index| changeset.files[*file_index].clone())
+        .collect::<Vec<_>>();
+    order_changesets_for_preflight(&reachable_changes)?;
+
+    let registry = changeset.transaction.settings.provider_registry()?;
+    let context = ExecutionContext::new(&registry);
+    for (file_index, file_change) in reachable.into_iter().zip(&reachable_changes) {
+        let outcomes_of_file = verify_file(
+            file_change,
+            &changeset.transaction.settings,
+            context.registry(),
+            &context,
+        );
+        for (operation, outcome) in outcomes_of_file.into_iter().enumerate() {
+            outcomes.insert((file_index, operation), outcome);
+        }
+    }
+
+    let mut summary = VerifySummary::default();
+    let operations = outcomes
+        .into_iter()
+        .map(|((file_index, operation), outcome)| {
+            let (status, error) = match outcome {
+                Ok(()) => (OperationStatus::Applicable, None),
+                Err((status, error)) => (status, Some(error)),
+            };
+            summary.operations_total += 1;
+            *match status {
+                OperationStatus::Applicable => &mut summary.applicable,
+                OperationStatus::Stale => &mut summary.stale,
+                OperationStatus::Missing => &mut summary.missing,
+                OperationStatus::TargetMissingIgnored => &mut summary.target_missing_ignored,
+                OperationStatus::Ambiguous => &mut summary.ambiguous,
+                OperationStatus::Conflict => &mut summary.conflict,
+                OperationStatus::Protected => &mut summary.protected,
+            } += 1;
+            let file_change = &changeset.files[file_index];
+            VerifiedOperation {
+                file: render_path(&file_change.file),
+                operation,
+                labels: OperationLabels::of(&file_change.operations[operation]),
+                status,
+                error,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Ok(VerifyResponse {
+        applicable: summary.applicable + summary.target_missing_ignored == summary.operations_total,
+        summary,
+        operations,
+        correlation_id: changeset.correlation_id.clone(),
+    })
+}
+
+/// One outcome per operation of `changeset`, applied with `settings`.
+fn verify_file(
+    changeset: &FileChange,
+    settings: &ApplySettings,
+    registry: &ProviderRegistry,
+    context: &ExecutionContext,
+) -> Vec<Result<(), Failure>> {
+    let fail_all = |error: IdenteditError| {
+        let failure = failure(&error);
+        changeset
+            .operations
+            .iter()
+            .map(|_| Err(failure.clone()))
+            .collect()
+    };
+    if let [operation] = changeset.operations.as_slice()
+        && let OpKind::Move { to } = &operation.op
+    {
+        return vec![match std::fs::symlink_metadata(to) {
+            Ok(_) => Err(failure(&IdenteditError::InvalidRequest {
+                message: format!("Move destination '{}' already exists", to.display()),
+            })),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(error) => Err(failure(&IdenteditError::io(to, error))),
+        }];
+    }
+
+    let source_text = match context.read_file_utf8(&changeset.file) {
+        Ok(source_text) => source_text,
+        Err(error) => return fail_all(error),
+    };
+    let handles = if changeset
+        .operations
+        .iter()
+        .any(|operation| operation.target.requires_node_resolution())
+    {
+        match parse_handles_for_source(&changeset.file, source_text.as_bytes(), registry) {
+            Ok(handles) => handles,
+            Err(error) => return fail_all(error),
+        }
+    } else {
+        Vec::new()
+    };
+
+    let protected = match protected_regions(&changeset.file, &source_text, settings) {
+        Ok(protected) => protected,
+        Err(error) => return fail_all(error),
+    };
+    let mut outcomes = Vec::with_capacity(changeset.operations.len());
+    let mut matched_changes = Vec::new();
+    let resolutions = resolve_each_changeset_target_in_handles(changeset, &source_text, &handles);
+    for (operation, resolved) in changeset.operations.iter().zip(resolutions) {
+        match resolved.and_then(|matched| {
+            validate_preview_consistency(changeset, std::slice::from_ref(&matched))?;
+            check_protected_regions(&changeset.file, &protected, &matched)?;
+            Ok(matched)
+        }) {
+            Ok(matched) => {
+                outcomes.push(Ok(()));
+                matched_changes.push(matched);
+            }
+            Err(error) => {
+                // `apply` ignores an optional operation whenever its target does not
+                // resolve, even if the node looks changed in place.
+                let mut failure = ignore_if_optional(operation, failure(&error));
+                if failure.0 == OperationStatus::Missing
+                    && node_changed_in_place(&operation.target, &handles)
+                {
+                    failure.0 = OperationStatus::Stale;
+                }
+                outcomes.push(Err(failure));
+            }
+        }
+    }
+    for (index, error) in overlapping_operations(matched_changes) {
+        outcomes[index] = Err((OperationStatus::Conflict, error));
+    }
+    outcomes
+}
+
+/// A node's identity covers its text, so an edited node no longer resolves; a node of the
+/// same kind still starting at the target's `span_hint` marks it as changed rather than gone.
+fn node_changed_in_place(target: &TransformTarget, handles: &[SelectionHandle]) -> bool {
+    let TransformTarget::Node {
+        kind,
+        span_hint: Some(span_hint),
+        ..
+    } = target
+    else {
+        return false;
+    };
+    handles
+        .iter()
+        .any(|handle| handle.kind == *kind && handle.span.start == span_hint.start)
+}
+
+/// Every pair of resolved operations whose edits overlap, as apply would reject them.
+fn overlapping_operations(matched_changes: Vec<MatchedChange>) -> Vec<(usize, ErrorBody)> {
+    let mut replacements = match matched_changes_to_replacements(matched_changes) {
+        Ok(replacements) => replacements,
+        Err(_) => return Vec::new(),
+    };
+    replacements.sort_by_key(|replacement| (replacement.start, replacement.end, replacement.index));
+
+    let mut conflicts = Vec::new();
+    for (position, first) in replacements.iter().enumerate() {
+        for second in &replacements[position + 1..] {
+            if second.start > first.end {
+                break;
+            }
+            if first.index == second.index {
+                continue;
+            }
+            if let Err(error) = ensure_non_overlapping(&[first.clone(), second.clone()]) {
+                let error = error.to_error_response().error;
+                conflicts.push((first.index, error.clone()));
+                conflicts.push((second.index, error));
+            }
+        }
+    }
+    conflicts
+}
+
+fn failure(error: &IdenteditError) -> Failure {
+    let status = match error {
+        error if target_is_missing(error) => OperationStatus::Missing,
+        IdenteditError::AmbiguousTarget { .. } => OperationStatus::Ambiguous,
+        IdenteditError::ProtectedRegion { .. } => OperationStatus::Protected,
+        _ => OperationStatus::Stale,
+    };
+    (status, error.to_error_response().error)
+}
+
+/// `failure` as `apply` treats it: an optional operation whose target is gone is ignored.
+fn ignore_if_optional(operation: &ChangeOp, (status, error): Failure) -> Failure {
+    if operation.optional && status == OperationStatus::Missing {
+        (OperationStatus::TargetMissingIgnored, error)
+    } else {
+        (status, error)
+    }
+}
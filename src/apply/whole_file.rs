@@ -0,0 +1,158 @@
+use std::fs;
+use std::path::Path;
+
+use crate::changeset::{TransactionMode, TransactionSpec};
+use crate::error::IdenteditError;
+use crate::hash::hash_bytes;
+use crate::path_style::render_path;
+
+use super::io::{
+    acquire_apply_lock, capture_apply_guard_state, create_text_atomically, prepare_write_target,
+    write_text_atomically,
+};
+use super::protected::{check_protected_rewrite, protected_regions};
+use super::secrets::check_new_secrets;
+use super::stats::ChangeStats;
+use super::{
+    ApplyFileResult, ApplyFileStatus, ApplyResponse, ApplySettings, ApplyTransaction,
+    TransactionStatus, summarize_apply_results,
+};
+
+/// Stands in for the hash of a file that must not exist yet.
+const ABSENT_FILE_HASH: &str = "absent";
+
+/// What must be on disk before a whole-file write goes ahead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WholeFilePrecondition {
+    /// The file exists and its contents hash to this value.
+    FileHash(String),
+    /// Nothing exists at the path yet.
+    Absent,
+}
+
+/// Replaces or creates `path` with `contents` as one operation. A replacement takes the
+/// apply lock and the same hard-link, read-only, and path-changed guards as a changeset
+/// apply; a creation never overwrites a file that appears in the meantime. Either way a
+/// failed precondition is `precondition_failed`, with `absent` standing in for the hash of
+/// a missing file.
+pub fn write_whole_file(
+    path: &Path,
+    contents: &str,
+    precondition: &WholeFilePrecondition,
+    settings: &ApplySettings,
+) -> Result<ApplyResponse, IdenteditError> {
+    let (metadata_warnings, stats) = match precondition {
+        WholeFilePrecondition::FileHash(expected_hash) => {
+            replace_whole_file(path, contents, expected_hash, settings)?
+        }
+        WholeFilePrecondition::Absent => {
+            create_whole_file(path, contents, settings)?;
+            (Vec::new(), whole_file_stats(path, "", contents, settings)?)
+        }
+    };
+
+    let applied = vec![ApplyFileResult {
+        file: render_path(path),
+        operations_applied: 1,
+        operations_total: 1,
+        status: ApplyFileStatus::Applied,
+        metadata_warnings,
+        normalized: None,
+        stats: Some(stats),
+    }];
+    Ok(ApplyResponse {
+        summary: summarize_apply_results(&applied),
+        applied,
+        transaction: ApplyTransaction {
+            mode: TransactionMode::AllOrNothing,
+            status: TransactionStatus::Committed,
+        },
+        operations: None,
+        targets_missing_ignored: Vec::new(),
+    })
+}
+
+fn replace_whole_file(
+    path: &Path,
+    contents: &str,
+    expected_hash: &str,
+    settings: &ApplySettings,
+) -> Result<(Vec<String>, ChangeStats), IdenteditError> {
+    if !path_exists(path)? {
+        return Err(IdenteditError::PreconditionFailed {
+            expected_hash: expected_hash.to_string(),
+            actual_hash: ABSENT_FILE_HASH.to_string(),
+        });
+    }
+    prepare_write_target(path, &TransactionSpec::default())?;
+    let _lock = acquire_apply_lock(path, settings.session.as_deref())?;
+    let guard_state = capture_apply_guard_state(path)?;
+    if guard_state.source_hash != expected_hash {
+        return Err(IdenteditError::PreconditionFailed {
+            expected_hash: expected_hash.to_string(),
+            actual_hash: guard_state.source_hash,
+        });
+    }
+    // The guard hash matched, so this is the text the write replaces.
+    let original = fs::read(path).map_err(|error| IdenteditError::io(path, error))?;
+    let original = String::from_utf8_lossy(&original);
+    let protected = protected_regions(path, &original, settings)?;
+    check_protected_rewrite(path, &protected, &original, contents)?;
+    // Rewriting the whole file is what the caller asked for, so `max_file_fraction_changed`
+    // does not apply.
+    let stats = whole_file_stats(path, &original, contents, settings)?;
+    if settings.secret_scan {
+        check_new_secrets(path, &original, contents)?;
+    }
+    let metadata_warnings =
+        write_text_atomically(path, contents, Some(&guard_state), settings.durability)?;
+    Ok((metadata_warnings, stats))
+}
+
+fn create_whole_file(
+    path: &Path,
+    contents: &str,
+    settings: &ApplySettings,
+) -> Result<(), IdenteditError> {
+    let existing_file = || IdenteditError::PreconditionFailed {
+        expected_hash: ABSENT_FILE_HASH.to_string(),
+        actual_hash: fs::read(path)
+            .map(|bytes| hash_bytes(&bytes))
+            .unwrap_or_else(|_| "present".to_string()),
+    };
+    if path_exists(path)? {
+        return Err(existing_file());
+    }
+    if settings.secret_scan {
+        check_new_secrets(path, "", contents)?;
+    }
+    match create_text_atomically(path, contents, settings.durability) {
+        Err(IdenteditError::Io { source, .. })
+            if source.kind() == std::io::ErrorKind::AlreadyExists =>
+        {
+            Err(existing_file())
+        }
+        result => result,
+    }
+}
+
+fn whole_file_stats(
+    path: &Path,
+    original: &str,
+    contents: &str,
+    settings: &ApplySettings,
+) -> Result<ChangeStats, IdenteditError> {
+    let stats = ChangeStats::between(original, contents);
+    if !settings.invalidated_identities {
+        return Ok(stats);
+    }
+    Ok(stats.with_invalidations(path, original, contents, &settings.provider_registry()?))
+}
+
+fn path_exists(path: &Path) -> Result<bool, IdenteditError> {
+    match fs::symlink_metadata(path) {
+        Ok(_) => Ok(true),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(error) => Err(IdenteditError::io(path, error)),
+    }
+}
@@ -1,10 +1,14 @@
 use std::path::PathBuf;
 use std::{fmt, result};
 
+use clap::ValueEnum;
 use serde::de::{self, Deserializer, MapAccess, Visitor};
+use serde::ser::{self, Serializer};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-use crate::handle::Span;
+use crate::apply::ApplySettings;
+use crate::handle::{PositionRange, Span};
 pub use crate::hash::HASH_HEX_LEN;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
@@ -15,6 +19,9 @@ pub enum TransformTarget {
         kind: String,
         #[serde(skip_serializing_if = "Option::is_none")]
         span_hint: Option<Span>,
+        /// `span_hint` in line/column coordinates; resolved to bytes against the file.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        position_hint: Option<PositionRange>,
         expected_old_hash: String,
     },
     FileStart {
@@ -51,6 +58,8 @@ struct TransformTargetWire {
     #[serde(default)]
     span_hint: Option<Span>,
     #[serde(default)]
+    position_hint: Option<PositionRange>,
+    #[serde(default)]
     expected_old_hash: Option<String>,
     #[serde(default)]
     expected_file_hash: Option<String>,
@@ -58,6 +67,9 @@ struct TransformTargetWire {
     anchor: Option<String>,
     #[serde(default)]
     end_anchor: Option<String>,
+    /// Only plan operations read it; see [`ChangeOp::optional`].
+    #[serde(default)]
+    optional: Option<bool>,
 }
 
 impl<'de> Deserialize<'de> for TransformTarget {
@@ -66,54 +78,67 @@ impl<'de> Deserialize<'de> for TransformTarget {
         D: Deserializer<'de>,
     {
         let wire = TransformTargetWire::deserialize(deserializer)?;
-        match wire.target_type.unwrap_or(TransformTargetType::Node) {
-            TransformTargetType::Node => {
-                if wire.expected_file_hash.is_some()
-                    || wire.anchor.is_some()
-                    || wire.end_anchor.is_some()
-                {
-                    return Err(de::Error::custom(
-                        "node target does not accept expected_file_hash/anchor/end_anchor",
-                    ));
-                }
-                let identity = wire
-                    .identity
-                    .ok_or_else(|| de::Error::missing_field("identity"))?;
-                let kind = wire.kind.ok_or_else(|| de::Error::missing_field("kind"))?;
-                let expected_old_hash = wire
-                    .expected_old_hash
-                    .ok_or_else(|| de::Error::missing_field("expected_old_hash"))?;
-                Ok(TransformTarget::Node {
-                    identity,
-                    kind,
-                    span_hint: wire.span_hint,
-                    expected_old_hash,
-                })
-            }
-            TransformTargetType::FileStart => {
-                reject_node_or_line_fields_for_file_target(&wire)?;
-                let expected_file_hash = wire
-                    .expected_file_hash
-                    .ok_or_else(|| de::Error::missing_field("expected_file_hash"))?;
-                Ok(TransformTarget::FileStart { expected_file_hash })
-            }
-            TransformTargetType::FileEnd => {
-                reject_node_or_line_fields_for_file_target(&wire)?;
-                let expected_file_hash = wire
-                    .expected_file_hash
-                    .ok_or_else(|| de::Error::missing_field("expected_file_hash"))?;
-                Ok(TransformTarget::FileEnd { expected_file_hash })
-            }
-            TransformTargetType::Line => {
-                reject_node_or_file_fields_for_line_target(&wire)?;
-                let anchor = wire
-                    .anchor
-                    .ok_or_else(|| de::Error::missing_field("anchor"))?;
-                Ok(TransformTarget::Line {
-                    anchor,
-                    end_anchor: wire.end_anchor,
-                })
+        if wire.optional.is_some() {
+            return Err(de::Error::custom(
+                "target optional is only accepted in plan operations",
+            ));
+        }
+        target_from_wire(wire)
+    }
+}
+
+fn target_from_wire<E>(wire: TransformTargetWire) -> result::Result<TransformTarget, E>
+where
+    E: de::Error,
+{
+    match wire.target_type.unwrap_or(TransformTargetType::Node) {
+        TransformTargetType::Node => {
+            if wire.expected_file_hash.is_some()
+                || wire.anchor.is_some()
+                || wire.end_anchor.is_some()
+            {
+                return Err(de::Error::custom(
+                    "node target does not accept expected_file_hash/anchor/end_anchor",
+                ));
             }
+            let identity = wire
+                .identity
+                .ok_or_else(|| de::Error::missing_field("identity"))?;
+            let kind = wire.kind.ok_or_else(|| de::Error::missing_field("kind"))?;
+            let expected_old_hash = wire
+                .expected_old_hash
+                .ok_or_else(|| de::Error::missing_field("expected_old_hash"))?;
+            Ok(TransformTarget::Node {
+                identity,
+                kind,
+                span_hint: wire.span_hint,
+                position_hint: wire.position_hint,
+                expected_old_hash,
+            })
+        }
+        TransformTargetType::FileStart => {
+            reject_node_or_line_fields_for_file_target(&wire)?;
+            let expected_file_hash = wire
+                .expected_file_hash
+                .ok_or_else(|| de::Error::missing_field("expected_file_hash"))?;
+            Ok(TransformTarget::FileStart { expected_file_hash })
+        }
+        TransformTargetType::FileEnd => {
+            reject_node_or_line_fields_for_file_target(&wire)?;
+            let expected_file_hash = wire
+                .expected_file_hash
+                .ok_or_else(|| de::Error::missing_field("expected_file_hash"))?;
+            Ok(TransformTarget::FileEnd { expected_file_hash })
+        }
+        TransformTargetType::Line => {
+            reject_node_or_file_fields_for_line_target(&wire)?;
+            let anchor = wire
+                .anchor
+                .ok_or_else(|| de::Error::missing_field("anchor"))?;
+            Ok(TransformTarget::Line {
+                anchor,
+                end_anchor: wire.end_anchor,
+            })
         }
     }
 }
@@ -134,6 +159,9 @@ where
     if wire.span_hint.is_some() {
         invalid_fields.push("span_hint");
     }
+    if wire.position_hint.is_some() {
+        invalid_fields.push("position_hint");
+    }
     if wire.expected_old_hash.is_some() {
         invalid_fields.push("expected_old_hash");
     }
@@ -169,6 +197,9 @@ where
     if wire.span_hint.is_some() {
         invalid_fields.push("span_hint");
     }
+    if wire.position_hint.is_some() {
+        invalid_fields.push("position_hint");
+    }
     if wire.expected_old_hash.is_some() {
         invalid_fields.push("expected_old_hash");
     }
@@ -196,6 +227,7 @@ impl TransformTarget {
             identity,
             kind,
             span_hint,
+            position_hint: None,
             expected_old_hash,
         }
     }
@@ -223,21 +255,54 @@ impl TransformTarget {
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct FileChange {
+    #[serde(
+        serialize_with = "crate::path_style::serialize_path",
+        deserialize_with = "crate::path_style::deserialize_path"
+    )]
     pub file: PathBuf,
     pub operations: Vec<ChangeOp>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct MultiFileChangeset {
     pub files: Vec<FileChange>,
     #[serde(default)]
     pub transaction: TransactionSpec,
+    /// Caller-supplied tag echoed in the apply or verify response, and in its error.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Default)]
 pub struct TransactionSpec {
     pub mode: TransactionMode,
+    /// Rewrite targets that have other hard links; only this path gets the new contents.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub force_unlink: bool,
+    /// Write read-only targets instead of refusing them; they keep their read-only mode.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub chmod_writable: bool,
+    /// End every rewritten file with a line break.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub ensure_final_newline: bool,
+    /// Remove trailing spaces and tabs from rewritten files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strip_trailing_whitespace: Option<TrailingWhitespaceScope>,
+    /// Set by the caller, not the plan.
+    #[serde(skip)]
+    pub settings: ApplySettings,
+}
+
+/// Which lines `strip_trailing_whitespace` cleans.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum TrailingWhitespaceScope {
+    /// Lines an operation wrote or joined.
+    EditedLines,
+    /// Every line of the file.
+    File,
 }
 
 impl<'de> Deserialize<'de> for TransactionSpec {
@@ -263,11 +328,26 @@ impl<'de> Deserialize<'de> for TransactionSpec {
                 struct TransactionSpecWire {
                     #[serde(default)]
                     mode: TransactionMode,
+                    #[serde(default)]
+                    force_unlink: bool,
+                    #[serde(default)]
+                    chmod_writable: bool,
+                    #[serde(default)]
+                    ensure_final_newline: bool,
+                    #[serde(default)]
+                    strip_trailing_whitespace: Option<TrailingWhitespaceScope>,
                 }
 
                 let wire =
                     TransactionSpecWire::deserialize(de::value::MapAccessDeserializer::new(map))?;
-                Ok(TransactionSpec { mode: wire.mode })
+                Ok(TransactionSpec {
+                    mode: wire.mode,
+                    force_unlink: wire.force_unlink,
+                    chmod_writable: wire.chmod_writable,
+                    ensure_final_newline: wire.ensure_final_newline,
+                    strip_trailing_whitespace: wire.strip_trailing_whitespace,
+                    settings: ApplySettings::default(),
+                })
             }
         }
 
@@ -280,27 +360,143 @@ impl<'de> Deserialize<'de> for TransactionSpec {
 pub enum TransactionMode {
     #[default]
     AllOrNothing,
+    ContinueOnError,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(deny_unknown_fields)]
+#[serde(from = "ChangeOpWire", into = "ChangeOpWire")]
 pub struct ChangeOp {
+    /// Plan-local name other operations can list in `depends_on`.
+    pub id: Option<String>,
+    /// Ids of operations that must be applied before this one.
+    pub depends_on: Vec<String>,
+    /// Caller-supplied tag echoed, along with `id`, in this operation's per-operation
+    /// results and errors.
+    pub correlation_id: Option<String>,
+    /// Free-form name of the part of a composite change this operation belongs to, such as
+    /// "rename" or "update docs"; the apply summary counts results per group.
+    pub group: Option<String>,
     pub target: TransformTarget,
+    /// A missing target node, line, or file makes this operation a recorded no-op
+    /// (`target_missing_ignored`) instead of failing the plan. Written as
+    /// `"optional": true` inside `target`.
+    pub optional: bool,
     pub op: OpKind,
     pub preview: ChangePreview,
 }
 
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ChangeOpWire {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    depends_on: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    correlation_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    group: Option<String>,
+    target: OptionalTarget,
+    op: OpKind,
+    preview: ChangePreview,
+}
+
+impl From<ChangeOpWire> for ChangeOp {
+    fn from(wire: ChangeOpWire) -> Self {
+        Self {
+            id: wire.id,
+            depends_on: wire.depends_on,
+            correlation_id: wire.correlation_id,
+            group: wire.group,
+            target: wire.target.target,
+            optional: wire.target.optional,
+            op: wire.op,
+            preview: wire.preview,
+        }
+    }
+}
+
+impl From<ChangeOp> for ChangeOpWire {
+    fn from(operation: ChangeOp) -> Self {
+        Self {
+            id: operation.id,
+            depends_on: operation.depends_on,
+            correlation_id: operation.correlation_id,
+            group: operation.group,
+            target: OptionalTarget {
+                target: operation.target,
+                optional: operation.optional,
+            },
+            op: operation.op,
+            preview: operation.preview,
+        }
+    }
+}
+
+/// A plan operation's target together with the `optional` flag carried in its object.
+struct OptionalTarget {
+    target: TransformTarget,
+    optional: bool,
+}
+
+impl Serialize for OptionalTarget {
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut value = serde_json::to_value(&self.target).map_err(ser::Error::custom)?;
+        if self.optional
+            && let Some(fields) = value.as_object_mut()
+        {
+            fields.insert("optional".to_string(), Value::Bool(true));
+        }
+        value.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for OptionalTarget {
+    fn deserialize<D>(deserializer: D) -> result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut wire = TransformTargetWire::deserialize(deserializer)?;
+        let optional = wire.optional.take().unwrap_or(false);
+        Ok(Self {
+            target: target_from_wire(wire)?,
+            optional,
+        })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
 pub enum OpKind {
-    Replace { new_text: String },
+    Replace {
+        new_text: String,
+    },
     Delete,
-    InsertBefore { new_text: String },
-    InsertAfter { new_text: String },
-    Insert { new_text: String },
-    MoveBefore { destination: Box<TransformTarget> },
-    MoveAfter { destination: Box<TransformTarget> },
-    Move { to: PathBuf },
+    InsertBefore {
+        new_text: String,
+    },
+    InsertAfter {
+        new_text: String,
+    },
+    Insert {
+        new_text: String,
+    },
+    MoveBefore {
+        destination: Box<TransformTarget>,
+    },
+    MoveAfter {
+        destination: Box<TransformTarget>,
+    },
+    Move {
+        #[serde(
+            serialize_with = "crate::path_style::serialize_path",
+            deserialize_with = "crate::path_style::deserialize_path"
+        )]
+        to: PathBuf,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -321,7 +517,15 @@ pub struct ChangePreview {
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct MovePreview {
+    #[serde(
+        serialize_with = "crate::path_style::serialize_path",
+        deserialize_with = "crate::path_style::deserialize_path"
+    )]
     pub from: PathBuf,
+    #[serde(
+        serialize_with = "crate::path_style::serialize_path",
+        deserialize_with = "crate::path_style::deserialize_path"
+    )]
     pub to: PathBuf,
 }
 
@@ -459,11 +663,13 @@ mod tests {
                 kind,
                 expected_old_hash,
                 span_hint,
+                position_hint,
             } => {
                 assert_eq!(identity, "id-1");
                 assert_eq!(kind, "function_definition");
                 assert_eq!(expected_old_hash, "hash-1");
                 assert!(span_hint.is_none());
+                assert!(position_hint.is_none());
             }
             other => panic!("expected node target, got {other:?}"),
         }
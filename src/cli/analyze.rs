@@ -0,0 +1,394 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::{Args, Subcommand, ValueEnum};
+use serde::Serialize;
+
+use crate::error::{ErrorBody, IdenteditError};
+use crate::patch::annotation::AnnotationMarker;
+use crate::path_style::render_path;
+use crate::provider::ProviderRegistry;
+
+use super::analyze_imports::{Import, extract_imports, supports_imports};
+use super::analyze_markers::{Marker, scan_markers};
+use super::analyze_sarif::{SarifLog, markers_log, unused_log};
+use super::analyze_unused::{UnusedSymbol, find_unused, supports_unused};
+use super::scan::{ScanLimits, collect_files, scan_parallel};
+
+#[derive(Debug, Args)]
+pub struct AnalyzeArgs {
+    #[command(subcommand)]
+    pub command: AnalyzeCommands,
+    #[arg(
+        long,
+        value_enum,
+        global = true,
+        default_value_t = AnalyzeOutput::Json,
+        help = "Output format: json, or sarif (SARIF 2.1.0 for code-scanning uploads; markers and unused only)"
+    )]
+    pub output: AnalyzeOutput,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum AnalyzeOutput {
+    Json,
+    Sarif,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AnalyzeCommands {
+    #[command(
+        about = "List each file's import, use, include, and require statements and the file graph they form"
+    )]
+    Imports(AnalyzeImportsArgs),
+    #[command(
+        about = "List TODO, FIXME, HACK, and XXX comments across a directory with anchors and enclosing handles"
+    )]
+    Markers(AnalyzeMarkersArgs),
+    #[command(
+        about = "List file-private functions, local variables, and parameters that nothing in their file references"
+    )]
+    Unused(AnalyzeUnusedArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct AnalyzeImportsArgs {
+    #[arg(
+        value_name = "DIR",
+        default_value = ".",
+        help = "Directory analyzed recursively; hidden entries and dependency/build directories are skipped"
+    )]
+    pub dir: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct AnalyzeMarkersArgs {
+    #[arg(
+        value_name = "DIR",
+        default_value = ".",
+        help = "Directory analyzed recursively; hidden entries and dependency/build directories are skipped"
+    )]
+    pub dir: PathBuf,
+    #[arg(
+        long = "marker",
+        value_enum,
+        default_values = ["todo", "fixme", "hack", "xxx"],
+        help = "Only list markers of this kind (repeatable; all kinds by default)"
+    )]
+    pub markers: Vec<AnnotationMarker>,
+    #[arg(long, help = "Add the git blame author and commit of each marker line")]
+    pub blame: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct AnalyzeUnusedArgs {
+    #[arg(
+        value_name = "PATH",
+        default_value = ".",
+        help = "Files, or directories analyzed recursively; hidden entries and dependency/build directories are skipped"
+    )]
+    pub paths: Vec<PathBuf>,
+}
+
+pub enum AnalyzeCommandOutput {
+    Imports(ImportGraph),
+    Markers(MarkerInventory),
+    Unused(UnusedReport),
+    Sarif(SarifLog),
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportGraph {
+    #[serde(serialize_with = "crate::path_style::serialize_path")]
+    pub root: PathBuf,
+    pub files: Vec<FileImports>,
+    /// Each file to the files in the tree it imports; files without such imports are left
+    /// out.
+    pub adjacency: BTreeMap<String, Vec<String>>,
+    /// The same edges reversed: each file to the files that import it, which is what an
+    /// edit to it can break.
+    pub imported_by: BTreeMap<String, Vec<String>>,
+    pub summary: ImportSummary,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileImports {
+    /// Relative to the root.
+    #[serde(serialize_with = "crate::path_style::serialize_path")]
+    pub file: PathBuf,
+    pub imports: Vec<Import>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorBody>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportSummary {
+    pub files: usize,
+    pub imports: usize,
+    pub resolved: usize,
+    pub edges: usize,
+    pub errors: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MarkerInventory {
+    pub markers: Vec<Marker>,
+    /// Files that could not be read or parsed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<AnalyzeFileError>,
+    pub summary: MarkerSummary,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnusedReport {
+    pub symbols: Vec<UnusedSymbol>,
+    /// Files that could not be read or parsed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<AnalyzeFileError>,
+    pub summary: UnusedSummary,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct UnusedSummary {
+    pub files_scanned: usize,
+    pub unused: usize,
+    /// Unused symbol counts by category.
+    pub by_category: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnalyzeFileError {
+    #[serde(serialize_with = "crate::path_style::serialize_path")]
+    pub file: PathBuf,
+    pub error: ErrorBody,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct MarkerSummary {
+    pub files_scanned: usize,
+    pub markers: usize,
+    /// Marker counts by word.
+    pub by_marker: BTreeMap<String, usize>,
+}
+
+pub fn run_analyze(
+    args: AnalyzeArgs,
+    limits: ScanLimits,
+    registry: &ProviderRegistry,
+) -> Result<AnalyzeCommandOutput, IdenteditError> {
+    let sarif = args.output == AnalyzeOutput::Sarif;
+    match args.command {
+        AnalyzeCommands::Imports(_) if sarif => Err(IdenteditError::InvalidRequest {
+            message: "analyze imports reports a graph, not findings; --output sarif applies to analyze markers and analyze unused".to_string(),
+        }),
+        AnalyzeCommands::Imports(imports_args) => {
+            analyze_imports(&imports_args.dir, &limits, registry).map(AnalyzeCommandOutput::Imports)
+        }
+        AnalyzeCommands::Markers(markers_args) => {
+            let inventory = analyze_markers(markers_args, &limits, registry)?;
+            Ok(if sarif {
+                AnalyzeCommandOutput::Sarif(markers_log(&inventory))
+            } else {
+                AnalyzeCommandOutput::Markers(inventory)
+            })
+        }
+        AnalyzeCommands::Unused(unused_args) => {
+            let report = analyze_unused(&unused_args.paths, &limits, registry)?;
+            Ok(if sarif {
+                AnalyzeCommandOutput::Sarif(unused_log(&report))
+            } else {
+                AnalyzeCommandOutput::Unused(report)
+            })
+        }
+    }
+}
+
+fn analyze_imports(
+    root: &Path,
+    limits: &ScanLimits,
+    registry: &ProviderRegistry,
+) -> Result<ImportGraph, IdenteditError> {
+    let mut all_files = Vec::new();
+    collect_files(root, &|_| true, limits, &mut all_files)?;
+    let known = all_files
+        .iter()
+        .map(|file| file.strip_prefix(root).unwrap_or(file).to_path_buf())
+        .collect::<HashSet<_>>();
+    let files = all_files
+        .into_iter()
+        .filter(|file| registry.provider_for(file).is_ok_and(supports_imports))
+        .collect::<Vec<_>>();
+
+    let files = scan_parallel(&files, limits, registry, |registry, file| {
+        let relative = file.strip_prefix(root).unwrap_or(file).to_path_buf();
+        let imports = fs::read(file)
+            .map_err(|error| IdenteditError::io(file, error))
+            .and_then(|source| {
+                extract_imports(root, file, &source, registry.provider_for(file)?, &known)
+            });
+        match imports {
+            Ok(imports) => FileImports {
+                file: relative,
+                imports,
+                error: None,
+            },
+            Err(error) => FileImports {
+                file: relative,
+                imports: Vec::new(),
+                error: Some(error.to_error_response().error),
+            },
+        }
+    })?;
+
+    let mut summary = ImportSummary::default();
+    let mut adjacency = BTreeMap::new();
+    let mut imported_by = BTreeMap::<String, Vec<String>>::new();
+    for file in &files {
+        summary.files += 1;
+        summary.imports += file.imports.len();
+        summary.errors += usize::from(file.error.is_some());
+        let targets = file
+            .imports
+            .iter()
+            .filter_map(|import| import.resolved.as_deref())
+            .filter(|target| *target != file.file)
+            .map(render_path)
+            .collect::<BTreeSet<_>>();
+        summary.resolved += file
+            .imports
+            .iter()
+            .filter(|import| import.resolved.is_some())
+            .count();
+        if targets.is_empty() {
+            continue;
+        }
+        let source = render_path(&file.file);
+        summary.edges += targets.len();
+        for target in &targets {
+            imported_by
+                .entry(target.clone())
+                .or_default()
+                .push(source.clone());
+        }
+        adjacency.insert(source, targets.into_iter().collect());
+    }
+    for importers in imported_by.values_mut() {
+        importers.sort();
+    }
+
+    Ok(ImportGraph {
+        root: root.to_path_buf(),
+        files,
+        adjacency,
+        imported_by,
+        summary,
+    })
+}
+
+fn analyze_markers(
+    args: AnalyzeMarkersArgs,
+    limits: &ScanLimits,
+    registry: &ProviderRegistry,
+) -> Result<MarkerInventory, IdenteditError> {
+    let has_syntax_tree = |path: &Path| {
+        registry
+            .provider_for(path)
+            .is_ok_and(|provider| provider.name().starts_with("tree-sitter"))
+    };
+    let mut files = Vec::new();
+    collect_files(&args.dir, &has_syntax_tree, limits, &mut files)?;
+
+    let scanned = scan_parallel(&files, limits, registry, |registry, file| {
+        fs::read(file)
+            .map_err(|error| IdenteditError::io(file, error))
+            .and_then(|source| {
+                let provider = registry.provider_for(file)?;
+                scan_markers(file, &source, provider, &args.markers, args.blame)
+            })
+    })?;
+
+    let mut inventory = MarkerInventory {
+        markers: Vec::new(),
+        errors: Vec::new(),
+        summary: MarkerSummary {
+            files_scanned: files.len(),
+            ..MarkerSummary::default()
+        },
+    };
+    for (file, markers) in files.into_iter().zip(scanned) {
+        match markers {
+            Ok(markers) => inventory.markers.extend(markers),
+            Err(error) => inventory.errors.push(AnalyzeFileError {
+                file,
+                error: error.to_error_response().error,
+            }),
+        }
+    }
+    inventory.summary.markers = inventory.markers.len();
+    for marker in &inventory.markers {
+        *inventory
+            .summary
+            .by_marker
+            .entry(marker.marker().to_string())
+            .or_default() += 1;
+    }
+    Ok(inventory)
+}
+
+fn analyze_unused(
+    paths: &[PathBuf],
+    limits: &ScanLimits,
+    registry: &ProviderRegistry,
+) -> Result<UnusedReport, IdenteditError> {
+    let supported = |path: &Path| registry.provider_for(path).is_ok_and(supports_unused);
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            collect_files(path, &supported, limits, &mut files)?;
+        } else if supported(path) {
+            files.push(path.clone());
+        } else {
+            return Err(IdenteditError::InvalidRequest {
+                message: format!(
+                    "analyze unused does not support '{}'; it covers Python, JavaScript, TypeScript, Rust, C, C++, Java, and C# files",
+                    path.display()
+                ),
+            });
+        }
+    }
+
+    let scanned = scan_parallel(&files, limits, registry, |registry, file| {
+        fs::read(file)
+            .map_err(|error| IdenteditError::io(file, error))
+            .and_then(|source| find_unused(file, &source, registry.provider_for(file)?))
+    })?;
+
+    let mut report = UnusedReport {
+        symbols: Vec::new(),
+        errors: Vec::new(),
+        summary: UnusedSummary {
+            files_scanned: files.len(),
+            ..UnusedSummary::default()
+        },
+    };
+    for (file, symbols) in files.into_iter().zip(scanned) {
+        match symbols {
+            Ok(symbols) => report.symbols.extend(symbols),
+            Err(error) => report.errors.push(AnalyzeFileError {
+                file,
+                error: error.to_error_response().error,
+            }),
+        }
+    }
+    report.summary.unused = report.symbols.len();
+    for symbol in &report.symbols {
+        *report
+            .summary
+            .by_category
+            .entry(symbol.category.as_str().to_string())
+            .or_default() += 1;
+    }
+    Ok(report)
+}
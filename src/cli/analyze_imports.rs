@@ -0,0 +1,505 @@
+//! Import extraction for `analyze imports`: one tree-sitter query per language finds the
+//! import, use, include, and require statements, and a per-language rule maps each module
+//! to a file in the tree when it names one.
+
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use streaming_iterator::StreamingIterator;
+use tree_sitter::{Query, QueryCursor};
+
+use crate::error::IdenteditError;
+use crate::provider::StructureProvider;
+
+/// How a language's module names map to files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ModuleStyle {
+    /// Dotted modules from the root or the importing file's directory; leading dots climb
+    /// packages.
+    Python,
+    /// `./` and `../` specifiers relative to the importing file; bare ones are packages.
+    JavaScript,
+    /// `mod name;` declarations; `use` paths are left unresolved.
+    Rust,
+    /// Quoted includes, relative to the importing file or the root.
+    Include,
+    /// Fully qualified class names, matched against paths ending in `a/b/C.<extension>`.
+    Qualified(&'static str),
+    /// `require_relative` relative to the importing file; `require` from the root or `lib`.
+    Ruby,
+    /// Dotted modules from the root, as `a/b.lua` or `a/b/init.lua`.
+    Lua,
+    /// Left unresolved, as for Go import paths that need `go.mod` to map.
+    Unresolved,
+}
+
+/// Captures: `@module` is the imported name; `@submodule` is a Rust `mod name;`
+/// declaration; `@member` is a name a Python `from` import takes from its module, which
+/// may itself be a module; `@function` only feeds predicates and Ruby's `require_relative`
+/// check.
+const IMPORT_QUERIES: &[(&[&str], ModuleStyle, &str)] = &[
+    (
+        &["tree-sitter-python"],
+        ModuleStyle::Python,
+        r#"
+        (import_statement name: (dotted_name) @module)
+        (import_statement name: (aliased_import name: (dotted_name) @module))
+        (import_from_statement module_name: (_) @module)
+        (import_from_statement module_name: (_) @module name: (dotted_name) @member)
+        (import_from_statement
+          module_name: (_) @module
+          name: (aliased_import name: (dotted_name) @member))
+        "#,
+    ),
+    (
+        &[
+            "tree-sitter-javascript",
+            "tree-sitter-typescript",
+            "tree-sitter-tsx",
+        ],
+        ModuleStyle::JavaScript,
+        r#"
+        (import_statement source: (string) @module)
+        (export_statement source: (string) @module)
+        (call_expression
+          function: (identifier) @function
+          arguments: (arguments . (string) @module)
+          (#eq? @function "require"))
+        (call_expression function: (import) arguments: (arguments . (string) @module))
+        "#,
+    ),
+    (
+        &["tree-sitter-rust"],
+        ModuleStyle::Rust,
+        r#"
+        (use_declaration argument: (_) @module)
+        (mod_item name: (identifier) @submodule !body)
+        (extern_crate_declaration name: (identifier) @module)
+        "#,
+    ),
+    (
+        &["tree-sitter-go"],
+        ModuleStyle::Unresolved,
+        "(import_spec path: (_) @module)",
+    ),
+    (
+        &[
+            "tree-sitter-c",
+            "tree-sitter-cpp",
+            "tree-sitter-c-cpp-header",
+        ],
+        ModuleStyle::Include,
+        "(preproc_include path: (_) @module)",
+    ),
+    (
+        &["tree-sitter-java"],
+        ModuleStyle::Qualified("java"),
+        "(import_declaration [(scoped_identifier) (identifier)] @module)",
+    ),
+    (
+        &["tree-sitter-c-sharp"],
+        ModuleStyle::Unresolved,
+        "(using_directive [(qualified_name) (identifier)] @module)",
+    ),
+    (
+        &["tree-sitter-ruby"],
+        ModuleStyle::Ruby,
+        r#"
+        (call
+          method: (identifier) @function
+          arguments: (argument_list . (string (string_content) @module))
+          (#any-of? @function "require" "require_relative" "load"))
+        "#,
+    ),
+    (
+        &["tree-sitter-lua"],
+        ModuleStyle::Lua,
+        r#"
+        (function_call
+          name: (identifier) @function
+          arguments: (arguments . (string content: (string_content) @module))
+          (#eq? @function "require"))
+        "#,
+    ),
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Import {
+    /// The imported name as written, without quotes.
+    pub module: String,
+    pub line: usize,
+    /// The file in the tree the import names, relative to the root.
+    #[serde(
+        serialize_with = "crate::path_style::serialize_optional_path",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub resolved: Option<PathBuf>,
+}
+
+/// The import query and module style for a provider, when imports are supported for it.
+fn import_query(provider_name: &str) -> Option<(ModuleStyle, &'static str)> {
+    IMPORT_QUERIES
+        .iter()
+        .find(|(providers, _, _)| providers.contains(&provider_name))
+        .map(|(_, style, query)| (*style, *query))
+}
+
+pub(super) fn supports_imports(provider: &dyn StructureProvider) -> bool {
+    import_query(provider.name()).is_some()
+}
+
+/// The imports of `file` (relative to `root`), in source order, with each module resolved
+/// against `known`, the files of the tree relative to `root`.
+pub(super) fn extract_imports(
+    root: &Path,
+    file: &Path,
+    source: &[u8],
+    provider: &dyn StructureProvider,
+    known: &HashSet<PathBuf>,
+) -> Result<Vec<Import>, IdenteditError> {
+    let Some((style, query)) = import_query(provider.name()) else {
+        return Ok(Vec::new());
+    };
+    let Some(tree) = provider.syntax_tree(source) else {
+        return Ok(Vec::new());
+    };
+    let tree = tree?;
+    let query =
+        Query::new(&tree.language(), query).map_err(|error| IdenteditError::LanguageSetup {
+            message: format!("import query for {}: {error}", provider.name()),
+        })?;
+    let names = query.capture_names();
+
+    let mut imports = Vec::new();
+    let mut seen = BTreeSet::new();
+    let mut cursor = QueryCursor::new();
+    let mut matches = cursor.matches(&query, tree.root_node(), source);
+    while let Some(found) = matches.next() {
+        let mut function = None;
+        let mut member = None;
+        for capture in found.captures {
+            match names[capture.index as usize] {
+                "function" => function = capture.node.utf8_text(source).ok(),
+                "member" => member = capture.node.utf8_text(source).ok(),
+                _ => {}
+            }
+        }
+        for capture in found.captures {
+            let submodule = match names[capture.index as usize] {
+                "module" => false,
+                "submodule" => true,
+                _ => continue,
+            };
+            let Ok(text) = capture.node.utf8_text(source) else {
+                continue;
+            };
+            let line = capture.node.start_position().row + 1;
+            let module = unquote(text).to_string();
+            if module.is_empty() {
+                continue;
+            }
+            let relative = file.strip_prefix(root).unwrap_or(file);
+            let resolved =
+                resolve(style, relative, &module, submodule, function, known).or_else(|| {
+                    let member = member?;
+                    let separator = if module.ends_with('.') { "" } else { "." };
+                    let module = format!("{module}{separator}{member}");
+                    resolve(style, relative, &module, submodule, function, known)
+                });
+            if !seen.insert((line, module.clone(), resolved.clone())) {
+                continue;
+            }
+            imports.push(Import {
+                module,
+                line,
+                resolved,
+            });
+        }
+    }
+    // `from . import a, b` is one import per module it names, not also one of `.` itself.
+    let resolved = imports
+        .iter()
+        .filter(|import| import.resolved.is_some())
+        .map(|import| (import.line, import.module.clone()))
+        .collect::<HashSet<_>>();
+    imports.retain(|import| {
+        import.resolved.is_some() || !resolved.contains(&(import.line, import.module.clone()))
+    });
+    imports.sort_by_key(|import| import.line);
+    Ok(imports)
+}
+
+fn unquote(text: &str) -> &str {
+    let text = text.trim();
+    let quoted = |open: char, close: char| {
+        text.len() >= 2 && text.starts_with(open) && text.ends_with(close)
+    };
+    if quoted('"', '"') || quoted('\'', '\'') || quoted('`', '`') || quoted('<', '>') {
+        &text[1..text.len() - 1]
+    } else {
+        text
+    }
+}
+
+fn resolve(
+    style: ModuleStyle,
+    file: &Path,
+    module: &str,
+    submodule: bool,
+    function: Option<&str>,
+    known: &HashSet<PathBuf>,
+) -> Option<PathBuf> {
+    let dir = file.parent().unwrap_or(Path::new(""));
+    let first_known = |candidates: Vec<PathBuf>| {
+        candidates
+            .into_iter()
+            .find(|candidate| known.contains(candidate))
+    };
+    match style {
+        ModuleStyle::Python => {
+            let dots = module
+                .chars()
+                .take_while(|character| *character == '.')
+                .count();
+            let dotted = module[dots..].replace('.', "/");
+            let bases = if dots > 0 {
+                let mut base = dir.to_path_buf();
+                for _ in 1..dots {
+                    base = join(&base, "..")?;
+                }
+                vec![base]
+            } else {
+                vec![PathBuf::new(), dir.to_path_buf()]
+            };
+            first_known(
+                bases
+                    .iter()
+                    .flat_map(|base| {
+                        [
+                            join(base, &format!("{dotted}.py")),
+                            join(base, &format!("{dotted}/__init__.py")),
+                        ]
+                    })
+                    .flatten()
+                    .collect(),
+            )
+        }
+        ModuleStyle::JavaScript => {
+            if !module.starts_with('.') {
+                return None;
+            }
+            let base = join(dir, module)?;
+            let base = base.to_string_lossy();
+            let mut candidates = vec![PathBuf::from(base.as_ref())];
+            for extension in ["ts", "tsx", "d.ts", "js", "jsx", "mjs", "cjs"] {
+                candidates.push(PathBuf::from(format!("{base}.{extension}")));
+            }
+            for extension in ["ts", "tsx", "js", "jsx"] {
+                candidates.push(PathBuf::from(format!("{base}/index.{extension}")));
+            }
+            first_known(candidates)
+        }
+        ModuleStyle::Rust => {
+            if !submodule {
+                return None;
+            }
+            let stem = file
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or("");
+            let module_dir = if ["mod", "lib", "main"].contains(&stem) {
+                dir.to_path_buf()
+            } else {
+                dir.join(stem)
+            };
+            first_known(vec![
+                module_dir.join(format!("{module}.rs")),
+                module_dir.join(module).join("mod.rs"),
+            ])
+        }
+        ModuleStyle::Include => first_known(
+            [join(dir, module), join(Path::new(""), module)]
+                .into_iter()
+                .flatten()
+                .collect(),
+        ),
+        ModuleStyle::Qualified(extension) => {
+            let suffix = PathBuf::from(format!("{}.{extension}", module.replace('.', "/")));
+            let mut matches = known
+                .iter()
+                .filter(|path| path.ends_with(&suffix))
+                .collect::<Vec<_>>();
+            matches.sort();
+            matches.first().map(|path| path.to_path_buf())
+        }
+        ModuleStyle::Ruby => {
+            let module = module.strip_suffix(".rb").unwrap_or(module);
+            let bases = if function == Some("require_relative") {
+                vec![dir.to_path_buf()]
+            } else {
+                vec![PathBuf::new(), PathBuf::from("lib")]
+            };
+            first_known(
+                bases
+                    .iter()
+                    .filter_map(|base| join(base, &format!("{module}.rb")))
+                    .collect(),
+            )
+        }
+        ModuleStyle::Lua => {
+            let module = module.replace('.', "/");
+            first_known(vec![
+                PathBuf::from(format!("{module}.lua")),
+                PathBuf::from(format!("{module}/init.lua")),
+            ])
+        }
+        ModuleStyle::Unresolved => None,
+    }
+}
+
+/// `base` joined with the `/`-separated `relative`, with `.` and `..` folded away; `None`
+/// when it climbs above the root.
+fn join(base: &Path, relative: &str) -> Option<PathBuf> {
+    let mut joined = PathBuf::new();
+    let parts = base
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .chain(relative.split('/').map(str::to_string));
+    for part in parts {
+        match part.as_str() {
+            "" | "." => {}
+            ".." => {
+                if !joined.pop() {
+                    return None;
+                }
+            }
+            part => joined.push(part),
+        }
+    }
+    Some(joined)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::path::{Path, PathBuf};
+
+    use super::extract_imports;
+    use crate::provider::ProviderRegistry;
+
+    fn modules(file: &str, source: &str, known: &[&str]) -> Vec<(String, Option<String>)> {
+        let registry = ProviderRegistry::default();
+        let path = Path::new(file);
+        let provider = registry.provider_for(path).expect("provider should exist");
+        let known = known.iter().map(PathBuf::from).collect::<HashSet<_>>();
+        extract_imports(Path::new(""), path, source.as_bytes(), provider, &known)
+            .expect("imports should be extracted")
+            .into_iter()
+            .map(|import| {
+                (
+                    import.module,
+                    import
+                        .resolved
+                        .map(|path| path.to_string_lossy().replace('\\', "/")),
+                )
+            })
+            .collect()
+    }
+
+    fn some(path: &str) -> Option<String> {
+        Some(path.to_string())
+    }
+
+    #[test]
+    fn every_language_query_finds_its_imports() {
+        assert_eq!(
+            modules(
+                "pkg/app.py",
+                "import os\nimport pkg.util as u\nfrom .models import User\n",
+                &["pkg/util.py", "pkg/models/__init__.py"],
+            ),
+            vec![
+                ("os".to_string(), None),
+                ("pkg.util".to_string(), some("pkg/util.py")),
+                (".models".to_string(), some("pkg/models/__init__.py")),
+            ]
+        );
+        assert_eq!(
+            modules(
+                "src/a.ts",
+                "import x from './b';\nexport * from '../c';\nconst y = require(\"lodash\");\n",
+                &["src/b.ts", "c/index.js"],
+            ),
+            vec![
+                ("./b".to_string(), some("src/b.ts")),
+                ("../c".to_string(), some("c/index.js")),
+                ("lodash".to_string(), None),
+            ]
+        );
+        assert_eq!(
+            modules(
+                "src/lib.rs",
+                "mod parser;\nuse std::fs;\nmod inline {}\n",
+                &["src/parser/mod.rs"],
+            ),
+            vec![
+                ("parser".to_string(), some("src/parser/mod.rs")),
+                ("std::fs".to_string(), None),
+            ]
+        );
+        assert_eq!(
+            modules("main.go", "package main\n\nimport \"fmt\"\n", &[]),
+            vec![("fmt".to_string(), None)]
+        );
+        assert_eq!(
+            modules(
+                "src/main.c",
+                "#include <stdio.h>\n#include \"util.h\"\n",
+                &["src/util.h"],
+            ),
+            vec![
+                ("stdio.h".to_string(), None),
+                ("util.h".to_string(), some("src/util.h")),
+            ]
+        );
+        assert_eq!(
+            modules("include/api.h", "#include \"types.h\"\n", &["types.h"],),
+            vec![("types.h".to_string(), some("types.h"))]
+        );
+        assert_eq!(
+            modules(
+                "src/main/java/app/Main.java",
+                "import app.model.User;\nclass Main {}\n",
+                &["src/main/java/app/model/User.java"],
+            ),
+            vec![(
+                "app.model.User".to_string(),
+                some("src/main/java/app/model/User.java")
+            )]
+        );
+        assert_eq!(
+            modules("Program.cs", "using System.Text;\n", &[]),
+            vec![("System.Text".to_string(), None)]
+        );
+        assert_eq!(
+            modules(
+                "lib/a.rb",
+                "require 'json'\nrequire_relative 'b'\n",
+                &["lib/b.rb"],
+            ),
+            vec![
+                ("json".to_string(), None),
+                ("b".to_string(), some("lib/b.rb")),
+            ]
+        );
+        assert_eq!(
+            modules(
+                "main.lua",
+                "local util = require(\"app.util\")\n",
+                &["app/util.lua"],
+            ),
+            vec![("app.util".to_string(), some("app/util.lua"))]
+        );
+    }
+}
@@ -0,0 +1,191 @@
+//! Marker scanning for `analyze markers`: the `read --annotations` markers of a whole
+//! tree, each with the enclosing handle a cleanup edit needs, and optionally `git blame`.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+use serde::Serialize;
+
+use crate::error::IdenteditError;
+use crate::handle::LineIndex;
+use crate::patch::annotation::AnnotationMarker;
+use crate::provider::StructureProvider;
+
+use super::read::ReadHandle;
+use super::read_annotations::collect_annotations;
+use super::read_window::ReadWindow;
+
+#[derive(Debug, Serialize)]
+pub struct Marker {
+    /// The marker as `read --annotations` reports it, anchor included.
+    #[serde(flatten)]
+    pub annotation: ReadHandle,
+    /// The innermost named node around the marker, such as the function it sits in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub handle: Option<ReadHandle>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blame: Option<BlameLine>,
+}
+
+impl Marker {
+    pub(super) fn marker(&self) -> &str {
+        match &self.annotation {
+            ReadHandle::Annotation { marker, .. } => marker,
+            _ => unreachable!("markers are built from annotation handles"),
+        }
+    }
+}
+
+/// `git blame` for one line.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BlameLine {
+    pub commit: String,
+    pub author: String,
+    pub author_mail: String,
+    /// Seconds since the Unix epoch.
+    pub author_time: i64,
+}
+
+/// The markers in the comments of `file` whose word is one of `markers`, in line order.
+pub(super) fn scan_markers(
+    file: &Path,
+    source: &[u8],
+    provider: &dyn StructureProvider,
+    markers: &[AnnotationMarker],
+    blame: bool,
+) -> Result<Vec<Marker>, IdenteditError> {
+    let handles = provider.parse(file, source)?;
+    let annotations = collect_annotations(file, source, &handles, None)?
+        .into_iter()
+        .filter_map(|annotation| match &annotation {
+            ReadHandle::Annotation { line, marker, .. }
+                if markers.iter().any(|wanted| wanted.as_str() == marker) =>
+            {
+                Some((*line, annotation))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if annotations.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut blame = if blame {
+        blame_lines(file)
+    } else {
+        HashMap::new()
+    };
+    let named = handles
+        .into_iter()
+        .filter(|handle| handle.name.is_some() && !handle.kind.contains("comment"))
+        .collect::<Vec<_>>();
+    let lines = LineIndex::new(source);
+    Ok(annotations
+        .into_iter()
+        .map(|(line, annotation)| Marker {
+            handle: ReadWindow::ContainingLine(line)
+                .filter_nodes(source, named.clone())
+                .into_iter()
+                .next()
+                .map(|handle| ReadHandle::from_selection_handle(handle, &lines, false)),
+            blame: blame.remove(&line),
+            annotation,
+        })
+        .collect())
+}
+
+/// `git blame` of every line of `file`, by line number; empty when the file is not tracked
+/// or git is unavailable.
+fn blame_lines(file: &Path) -> HashMap<usize, BlameLine> {
+    let directory = file
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let Some(name) = file.file_name() else {
+        return HashMap::new();
+    };
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .args(["blame", "--line-porcelain", "--"])
+        .arg(name)
+        .output();
+    let Ok(output) = output else {
+        return HashMap::new();
+    };
+    if !output.status.success() {
+        return HashMap::new();
+    }
+    parse_line_porcelain(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn parse_line_porcelain(porcelain: &str) -> HashMap<usize, BlameLine> {
+    let mut lines = HashMap::new();
+    let mut current: Option<(usize, BlameLine)> = None;
+    for line in porcelain.lines() {
+        if line.starts_with('\t') {
+            if let Some((line_number, blame)) = current.take() {
+                lines.insert(line_number, blame);
+            }
+            continue;
+        }
+        match &mut current {
+            None => {
+                let mut fields = line.split(' ');
+                let commit = fields.next().unwrap_or_default();
+                let Some(line_number) = fields.nth(1).and_then(|field| field.parse().ok()) else {
+                    continue;
+                };
+                current = Some((
+                    line_number,
+                    BlameLine {
+                        commit: commit.to_string(),
+                        ..BlameLine::default()
+                    },
+                ));
+            }
+            Some((_, blame)) => {
+                if let Some(author) = line.strip_prefix("author ") {
+                    blame.author = author.to_string();
+                } else if let Some(mail) = line.strip_prefix("author-mail ") {
+                    blame.author_mail = mail.trim_matches(['<', '>']).to_string();
+                } else if let Some(time) = line.strip_prefix("author-time ") {
+                    blame.author_time = time.parse().unwrap_or_default();
+                }
+            }
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_line_porcelain;
+
+    #[test]
+    fn line_porcelain_yields_the_author_of_each_final_line() {
+        let porcelain = "\
+3f2a 1 1 2
+author Ada
+author-mail <ada@example.com>
+author-time 1700000000
+author-tz +0000
+summary first
+filename a.py
+\tdef a():
+3f2a 2 2
+author Ada
+author-mail <ada@example.com>
+author-time 1700000000
+filename a.py
+\t    # TODO: return more
+";
+        let lines = parse_line_porcelain(porcelain);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[&2].commit, "3f2a");
+        assert_eq!(lines[&2].author, "Ada");
+        assert_eq!(lines[&2].author_mail, "ada@example.com");
+        assert_eq!(lines[&2].author_time, 1_700_000_000);
+    }
+}
@@ -0,0 +1,399 @@
+//! SARIF 2.1.0 export for `analyze --output sarif`, so findings can be uploaded to
+//! code-scanning UIs. Rule ids are `<analyzer>/<finding kind>`, regions come from handle
+//! spans, and handle identities or line anchors become partial fingerprints.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::patch::annotation::AnnotationMarker;
+use crate::path_style::render_path;
+
+use super::analyze::{AnalyzeFileError, MarkerInventory, UnusedReport};
+use super::analyze_unused::SymbolCategory;
+use super::read::ReadHandle;
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+#[derive(Debug, Serialize)]
+pub struct SarifLog {
+    #[serde(rename = "$schema")]
+    pub schema: &'static str,
+    pub version: &'static str,
+    pub runs: Vec<SarifRun>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifRun {
+    pub tool: SarifTool,
+    pub column_kind: &'static str,
+    pub results: Vec<SarifResult>,
+    pub invocations: Vec<SarifInvocation>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SarifTool {
+    pub driver: SarifDriver,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifDriver {
+    pub name: &'static str,
+    pub version: &'static str,
+    pub rules: Vec<SarifRule>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifRule {
+    pub id: String,
+    pub short_description: SarifMessage,
+    pub default_configuration: SarifConfiguration,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SarifConfiguration {
+    pub level: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SarifMessage {
+    pub text: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifResult {
+    pub rule_id: String,
+    pub rule_index: usize,
+    pub level: &'static str,
+    pub message: SarifMessage,
+    pub locations: Vec<SarifLocation>,
+    pub partial_fingerprints: BTreeMap<&'static str, String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifLocation {
+    pub physical_location: SarifPhysicalLocation,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub logical_locations: Vec<SarifLogicalLocation>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifPhysicalLocation {
+    pub artifact_location: SarifArtifactLocation,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<SarifRegion>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifArtifactLocation {
+    pub uri: String,
+    /// `%SRCROOT%` for relative paths, so uploads resolve them against the checkout.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uri_base_id: Option<&'static str>,
+}
+
+/// Lines are 1-based; columns are 1-based UTF-16 code units, end exclusive.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifRegion {
+    pub start_line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_column: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_column: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub byte_offset: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub byte_length: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifLogicalLocation {
+    pub name: String,
+    pub kind: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifInvocation {
+    pub execution_successful: bool,
+    /// Files that could not be read or parsed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tool_execution_notifications: Vec<SarifNotification>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SarifNotification {
+    pub level: &'static str,
+    pub message: SarifMessage,
+    pub locations: Vec<SarifLocation>,
+}
+
+pub(super) fn markers_log(inventory: &MarkerInventory) -> SarifLog {
+    let rules = AnnotationMarker::value_variants()
+        .iter()
+        .map(|marker| {
+            rule(
+                format!("markers/{}", marker.as_str().to_lowercase()),
+                format!("{} comment", marker.as_str()),
+                "note",
+            )
+        })
+        .collect();
+    let results = inventory.markers.iter().filter_map(|marker| {
+        let ReadHandle::Annotation {
+            file,
+            line,
+            anchor,
+            marker: word,
+            message,
+            ..
+        } = &marker.annotation
+        else {
+            return None;
+        };
+        let mut fingerprints = BTreeMap::from([("identeditAnchor/v1", anchor.clone())]);
+        if let Some(ReadHandle::Node { identity, .. }) = &marker.handle {
+            fingerprints.insert("identeditIdentity/v1", identity.clone());
+        }
+        Some(finding(
+            format!("markers/{}", word.to_lowercase()),
+            "note",
+            if message.is_empty() {
+                word.clone()
+            } else {
+                format!("{word}: {message}")
+            },
+            SarifLocation {
+                physical_location: physical_location(
+                    file,
+                    Some(SarifRegion {
+                        start_line: *line,
+                        start_column: None,
+                        end_line: None,
+                        end_column: None,
+                        byte_offset: None,
+                        byte_length: None,
+                    }),
+                ),
+                logical_locations: marker
+                    .handle
+                    .as_ref()
+                    .and_then(logical_location)
+                    .into_iter()
+                    .collect(),
+            },
+            fingerprints,
+        ))
+    });
+    log(rules, results, &inventory.errors)
+}
+
+pub(super) fn unused_log(report: &UnusedReport) -> SarifLog {
+    let rules = [
+        SymbolCategory::Function,
+        SymbolCategory::Variable,
+        SymbolCategory::Parameter,
+    ]
+    .into_iter()
+    .map(|category| {
+        rule(
+            format!("unused/{}", category.as_str()),
+            format!("Unreferenced {}", category.as_str()),
+            "warning",
+        )
+    })
+    .collect();
+    let results = report.symbols.iter().filter_map(|symbol| {
+        let ReadHandle::Node {
+            file,
+            span,
+            start_position,
+            end_position,
+            identity,
+            ..
+        } = &symbol.handle
+        else {
+            return None;
+        };
+        Some(finding(
+            format!("unused/{}", symbol.category.as_str()),
+            "warning",
+            format!(
+                "Unreferenced {} '{}'",
+                symbol.category.as_str(),
+                symbol.name
+            ),
+            SarifLocation {
+                physical_location: physical_location(
+                    file,
+                    Some(SarifRegion {
+                        start_line: start_position.line,
+                        start_column: Some(start_position.utf16_column),
+                        end_line: Some(end_position.line),
+                        end_column: Some(end_position.utf16_column),
+                        byte_offset: Some(span.start),
+                        byte_length: Some(span.end - span.start),
+                    }),
+                ),
+                logical_locations: logical_location(&symbol.handle).into_iter().collect(),
+            },
+            BTreeMap::from([("identeditIdentity/v1", identity.clone())]),
+        ))
+    });
+    log(rules, results, &report.errors)
+}
+
+fn rule(id: String, description: String, level: &'static str) -> SarifRule {
+    SarifRule {
+        id,
+        short_description: SarifMessage { text: description },
+        default_configuration: SarifConfiguration { level },
+    }
+}
+
+/// A result whose `rule_index` is filled in by [`log`].
+fn finding(
+    rule_id: String,
+    level: &'static str,
+    message: String,
+    location: SarifLocation,
+    partial_fingerprints: BTreeMap<&'static str, String>,
+) -> SarifResult {
+    SarifResult {
+        rule_id,
+        rule_index: 0,
+        level,
+        message: SarifMessage { text: message },
+        locations: vec![location],
+        partial_fingerprints,
+    }
+}
+
+fn log(
+    rules: Vec<SarifRule>,
+    results: impl Iterator<Item = SarifResult>,
+    errors: &[AnalyzeFileError],
+) -> SarifLog {
+    let results = results
+        .map(|result| SarifResult {
+            rule_index: rules
+                .iter()
+                .position(|rule| rule.id == result.rule_id)
+                .unwrap_or_default(),
+            ..result
+        })
+        .collect();
+    let notifications = errors
+        .iter()
+        .map(|error| SarifNotification {
+            level: "error",
+            message: SarifMessage {
+                text: error.error.message.clone(),
+            },
+            locations: vec![SarifLocation {
+                physical_location: physical_location(&error.file, None),
+                logical_locations: Vec::new(),
+            }],
+        })
+        .collect();
+    SarifLog {
+        schema: SCHEMA,
+        version: "2.1.0",
+        runs: vec![SarifRun {
+            tool: SarifTool {
+                driver: SarifDriver {
+                    name: "identedit",
+                    version: env!("CARGO_PKG_VERSION"),
+                    rules,
+                },
+            },
+            column_kind: "utf16CodeUnits",
+            results,
+            invocations: vec![SarifInvocation {
+                execution_successful: true,
+                tool_execution_notifications: notifications,
+            }],
+        }],
+    }
+}
+
+fn physical_location(file: &Path, region: Option<SarifRegion>) -> SarifPhysicalLocation {
+    SarifPhysicalLocation {
+        artifact_location: artifact_location(file),
+        region,
+    }
+}
+
+/// Relative paths stay relative to `%SRCROOT%`; absolute ones become `file://` URIs.
+fn artifact_location(file: &Path) -> SarifArtifactLocation {
+    let path = render_path(file).replace('\\', "/");
+    let mut uri = String::with_capacity(path.len());
+    for character in path.chars() {
+        match character {
+            '%' => uri.push_str("%25"),
+            ' ' => uri.push_str("%20"),
+            '#' => uri.push_str("%23"),
+            '?' => uri.push_str("%3F"),
+            _ => uri.push(character),
+        }
+    }
+    if file.is_absolute() {
+        let separator = if uri.starts_with('/') { "" } else { "/" };
+        SarifArtifactLocation {
+            uri: format!("file://{separator}{uri}"),
+            uri_base_id: None,
+        }
+    } else {
+        SarifArtifactLocation {
+            uri: uri.trim_start_matches("./").to_string(),
+            uri_base_id: Some("%SRCROOT%"),
+        }
+    }
+}
+
+fn logical_location(handle: &ReadHandle) -> Option<SarifLogicalLocation> {
+    match handle {
+        ReadHandle::Node {
+            kind,
+            name: Some(name),
+            ..
+        } => Some(SarifLogicalLocation {
+            name: name.clone(),
+            kind: kind.clone(),
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::artifact_location;
+
+    #[test]
+    fn relative_paths_use_the_source_root_and_absolute_ones_file_uris() {
+        let relative = artifact_location(Path::new("./src/my file.rs"));
+        assert_eq!(relative.uri, "src/my%20file.rs");
+        assert_eq!(relative.uri_base_id, Some("%SRCROOT%"));
+
+        if cfg!(unix) {
+            let absolute = artifact_location(Path::new("/repo/a#b.py"));
+            assert_eq!(absolute.uri, "file:///repo/a%23b.py");
+            assert_eq!(absolute.uri_base_id, None);
+        }
+    }
+}
@@ -0,0 +1,592 @@
+//! Unused-symbol hints for `analyze unused`: functions, local variables, and parameters
+//! that no identifier in their file mentions. Only symbols that nothing outside the file
+//! can reach are considered, and matching is by name, so shadowing can hide an unused
+//! symbol but never makes a used one look unused.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Serialize;
+use tree_sitter::Node;
+
+use crate::error::IdenteditError;
+use crate::handle::LineIndex;
+use crate::provider::StructureProvider;
+
+use super::read::ReadHandle;
+
+/// Which functions a language keeps private to their file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Privacy {
+    /// Undecorated functions whose name starts with `_`, dunders aside.
+    Underscore,
+    /// Functions outside `export` statements; methods only when `#private` or `private`.
+    Export,
+    /// Functions without `pub` or attributes, outside trait impls and trait definitions.
+    Visibility,
+    /// `static` top-level functions.
+    Static,
+    /// Methods with a `private` modifier and no annotations or attributes.
+    Modifier,
+}
+
+const LANGUAGES: &[(&[&str], Privacy)] = &[
+    (&["tree-sitter-python"], Privacy::Underscore),
+    (
+        &[
+            "tree-sitter-javascript",
+            "tree-sitter-typescript",
+            "tree-sitter-tsx",
+        ],
+        Privacy::Export,
+    ),
+    (&["tree-sitter-rust"], Privacy::Visibility),
+    (&["tree-sitter-c", "tree-sitter-cpp"], Privacy::Static),
+    (
+        &["tree-sitter-java", "tree-sitter-c-sharp"],
+        Privacy::Modifier,
+    ),
+];
+
+/// Named function definitions; each is also a scope for its variables and parameters.
+const FUNCTION_KINDS: &[&str] = &[
+    "function_definition",
+    "function_declaration",
+    "generator_function_declaration",
+    "function_item",
+    "method_definition",
+    "method_declaration",
+    "local_function_statement",
+];
+
+/// Anonymous functions: scopes for their variables, but their parameters are fixed by
+/// whoever calls them.
+const CLOSURE_KINDS: &[&str] = &[
+    "lambda",
+    "lambda_expression",
+    "arrow_function",
+    "function_expression",
+    "function",
+    "closure_expression",
+];
+
+/// Nodes that bind a local variable, with the field holding the name.
+const VARIABLE_KINDS: &[(&str, &str)] = &[
+    ("assignment", "left"),
+    ("variable_declarator", "name"),
+    ("let_declaration", "pattern"),
+    ("init_declarator", "declarator"),
+    ("declaration", "declarator"),
+];
+
+/// Statements a variable binding is deleted with, when it is their only binding.
+const DECLARATION_KINDS: &[&str] = &[
+    "expression_statement",
+    "lexical_declaration",
+    "variable_declaration",
+    "local_variable_declaration",
+    "local_declaration_statement",
+    "declaration",
+];
+
+/// Fields that lead from a declarator or pattern to the name it binds.
+const BINDING_FIELDS: &[&str] = &["name", "pattern", "left", "declarator"];
+
+/// Parameter and pattern wrappers whose only identifier child is the name they bind.
+const WRAPPER_KINDS: &[&str] = &[
+    "typed_parameter",
+    "list_splat_pattern",
+    "dictionary_splat_pattern",
+    "rest_pattern",
+    "mut_pattern",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymbolCategory {
+    Function,
+    Variable,
+    Parameter,
+}
+
+impl SymbolCategory {
+    pub(super) fn as_str(self) -> &'static str {
+        match self {
+            Self::Function => "function",
+            Self::Variable => "variable",
+            Self::Parameter => "parameter",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnusedSymbol {
+    pub name: String,
+    pub category: SymbolCategory,
+    /// Line of the symbol's name.
+    pub line: usize,
+    /// What a deletion targets: the function, the statement declaring the variable, or
+    /// the parameter.
+    pub handle: ReadHandle,
+}
+
+struct Candidate<'tree> {
+    binding: Node<'tree>,
+    category: SymbolCategory,
+    target: Node<'tree>,
+    /// Where references count; the whole file when `None`.
+    scope: Option<Node<'tree>>,
+    /// Where they do not, such as a function's own body for recursion.
+    exclude: Option<Node<'tree>>,
+}
+
+pub(super) fn supports_unused(provider: &dyn StructureProvider) -> bool {
+    privacy(provider.name()).is_some()
+}
+
+fn privacy(provider: &str) -> Option<Privacy> {
+    LANGUAGES
+        .iter()
+        .find(|(providers, _)| providers.contains(&provider))
+        .map(|(_, privacy)| *privacy)
+}
+
+/// The unreferenced symbols of `file`, in source order.
+pub(super) fn find_unused(
+    file: &Path,
+    source: &[u8],
+    provider: &dyn StructureProvider,
+) -> Result<Vec<UnusedSymbol>, IdenteditError> {
+    let Some(privacy) = privacy(provider.name()) else {
+        return Ok(Vec::new());
+    };
+    let handles = provider.parse(file, source)?;
+    let Some(tree) = provider.syntax_tree(source).transpose()? else {
+        return Ok(Vec::new());
+    };
+    let root = tree.root_node();
+
+    let mut references = HashMap::<&str, Vec<usize>>::new();
+    let mut strings = Vec::new();
+    collect_references(root, source, &mut references, &mut strings);
+    let mut candidates = Vec::new();
+    collect_candidates(root, None, privacy, source, &mut candidates);
+
+    let lines = LineIndex::new(source);
+    let mut unused = Vec::new();
+    for candidate in candidates {
+        let name = text(candidate.binding, source);
+        if ignored_name(name, candidate.category)
+            || is_referenced(name, &candidate, &references, &strings, source)
+        {
+            continue;
+        }
+        let Some(handle) = handles.iter().find(|handle| {
+            handle.span.start == candidate.target.start_byte()
+                && handle.span.end == candidate.target.end_byte()
+                && handle.kind == candidate.target.kind()
+        }) else {
+            continue;
+        };
+        unused.push(UnusedSymbol {
+            name: name.to_string(),
+            category: candidate.category,
+            line: candidate.binding.start_position().row + 1,
+            handle: ReadHandle::from_selection_handle(handle.clone(), &lines, false),
+        });
+    }
+    Ok(unused)
+}
+
+fn text<'source>(node: Node<'_>, source: &'source [u8]) -> &'source str {
+    node.utf8_text(source).unwrap_or_default()
+}
+
+/// `main`, receivers, and `_`-prefixed locals and parameters are meant to go unreferenced.
+fn ignored_name(name: &str, category: SymbolCategory) -> bool {
+    name.is_empty()
+        || matches!(name, "self" | "cls" | "this" | "main")
+        || (category != SymbolCategory::Function && name.starts_with('_'))
+}
+
+fn collect_references<'tree, 'source>(
+    node: Node<'tree>,
+    source: &'source [u8],
+    references: &mut HashMap<&'source str, Vec<usize>>,
+    strings: &mut Vec<Node<'tree>>,
+) {
+    let kind = node.kind();
+    if kind.ends_with("identifier") && node.child_count() == 0 {
+        references
+            .entry(text(node, source))
+            .or_default()
+            .push(node.start_byte());
+        return;
+    }
+    if kind.contains("string") && node.named_child_count() == 0 {
+        strings.push(node);
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_references(child, source, references, strings);
+    }
+}
+
+/// Whether an identifier other than the binding, or a word in a string such as a Rust
+/// format string, names the candidate within its scope.
+fn is_referenced(
+    name: &str,
+    candidate: &Candidate<'_>,
+    references: &HashMap<&str, Vec<usize>>,
+    strings: &[Node<'_>],
+    source: &[u8],
+) -> bool {
+    let counts = |start: usize, end: usize| {
+        candidate
+            .scope
+            .is_none_or(|scope| scope.start_byte() <= start && end <= scope.end_byte())
+            && candidate
+                .exclude
+                .is_none_or(|exclude| end <= exclude.start_byte() || exclude.end_byte() <= start)
+    };
+    let binding = candidate.binding.start_byte();
+    references.get(name).is_some_and(|starts| {
+        starts
+            .iter()
+            .any(|&start| start != binding && counts(start, start + name.len()))
+    }) || strings.iter().any(|string| {
+        counts(string.start_byte(), string.end_byte()) && contains_word(text(*string, source), name)
+    })
+}
+
+fn contains_word(haystack: &str, word: &str) -> bool {
+    let is_word = |character: char| character.is_alphanumeric() || character == '_';
+    haystack.match_indices(word).any(|(start, _)| {
+        !haystack[..start].chars().next_back().is_some_and(is_word)
+            && !haystack[start + word.len()..]
+                .chars()
+                .next()
+                .is_some_and(is_word)
+    })
+}
+
+fn collect_candidates<'tree>(
+    node: Node<'tree>,
+    enclosing: Option<Node<'tree>>,
+    privacy: Privacy,
+    source: &[u8],
+    candidates: &mut Vec<Candidate<'tree>>,
+) {
+    let kind = node.kind();
+    if FUNCTION_KINDS.contains(&kind) {
+        if let Some(name) = function_name(node)
+            && !is_required(node, source)
+            && (enclosing.is_some() || is_file_private(node, name, privacy, source))
+        {
+            candidates.push(Candidate {
+                binding: name,
+                category: SymbolCategory::Function,
+                target: node,
+                scope: None,
+                exclude: Some(node),
+            });
+            // Every call site is in the file, so a dead parameter can go too.
+            if node.child_by_field_name("body").is_some()
+                && let Some(parameters) = node.child_by_field_name("parameters")
+            {
+                let mut cursor = parameters.walk();
+                for parameter in parameters.named_children(&mut cursor) {
+                    for binding in bindings(parameter) {
+                        candidates.push(Candidate {
+                            binding,
+                            category: SymbolCategory::Parameter,
+                            target: parameter,
+                            scope: Some(node),
+                            exclude: None,
+                        });
+                    }
+                }
+            }
+        }
+    } else if let Some(function) = enclosing
+        && let Some((_, field)) = VARIABLE_KINDS
+            .iter()
+            .find(|(variable, _)| *variable == kind)
+    {
+        let binding = node
+            .child_by_field_name(field)
+            .map(|binding| match binding.kind() {
+                "mut_pattern" => bindings(binding).into_iter().next().unwrap_or(binding),
+                _ => binding,
+            });
+        if let Some(binding) = binding.filter(|binding| binding.kind() == "identifier") {
+            candidates.push(Candidate {
+                binding,
+                category: SymbolCategory::Variable,
+                target: declaration_target(node),
+                scope: Some(function),
+                exclude: None,
+            });
+        }
+    }
+
+    let enclosing = if FUNCTION_KINDS.contains(&kind) || CLOSURE_KINDS.contains(&kind) {
+        Some(node)
+    } else {
+        enclosing
+    };
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect_candidates(child, enclosing, privacy, source, candidates);
+    }
+}
+
+/// The identifier naming a function; C and C++ names sit at the end of a declarator
+/// chain, and qualified out-of-class definitions have none.
+fn function_name(node: Node<'_>) -> Option<Node<'_>> {
+    if let Some(name) = node.child_by_field_name("name") {
+        return name.kind().ends_with("identifier").then_some(name);
+    }
+    let mut declarator = node.child_by_field_name("declarator")?;
+    loop {
+        if declarator.kind().ends_with("identifier") && declarator.kind() != "qualified_identifier"
+        {
+            return Some(declarator);
+        }
+        declarator = declarator.child_by_field_name("declarator")?;
+    }
+}
+
+/// The identifiers a parameter or declarator binds: each `name` (Go lists several),
+/// otherwise the end of its pattern, or the only identifier inside a wrapper such as
+/// `mut x` or a typed Python parameter. Destructuring patterns bind nothing here.
+fn bindings(node: Node<'_>) -> Vec<Node<'_>> {
+    if node.kind() == "identifier" {
+        return vec![node];
+    }
+    let mut cursor = node.walk();
+    let names = node
+        .children_by_field_name("name", &mut cursor)
+        .filter(|name| name.kind() == "identifier")
+        .collect::<Vec<_>>();
+    if !names.is_empty() {
+        return names;
+    }
+    for field in &BINDING_FIELDS[1..] {
+        if let Some(child) = node.child_by_field_name(field) {
+            return bindings(child);
+        }
+    }
+    if !WRAPPER_KINDS.contains(&node.kind()) {
+        return Vec::new();
+    }
+    let mut cursor = node.walk();
+    let identifiers = node
+        .named_children(&mut cursor)
+        .filter(|child| child.kind() == "identifier")
+        .collect::<Vec<_>>();
+    if identifiers.len() == 1 {
+        identifiers
+    } else {
+        Vec::new()
+    }
+}
+
+/// The statement a variable binding is deleted with, unless it declares other names too.
+fn declaration_target(node: Node<'_>) -> Node<'_> {
+    let mut target = node;
+    while let Some(parent) = target.parent()
+        && DECLARATION_KINDS.contains(&parent.kind())
+    {
+        let mut cursor = parent.walk();
+        let siblings = parent
+            .named_children(&mut cursor)
+            .filter(|child| child.kind() == target.kind())
+            .count();
+        if siblings > 1 {
+            break;
+        }
+        target = parent;
+    }
+    target
+}
+
+/// Whether something other than a call in the file needs the function: a decorator or
+/// attribute registers it, or a trait, interface, or base class declares it.
+fn is_required(node: Node<'_>, source: &[u8]) -> bool {
+    let mut cursor = node.walk();
+    let annotated = node.children(&mut cursor).any(|child| match child.kind() {
+        "attribute_list" => true,
+        "modifiers" | "modifier" => {
+            let modifiers = text(child, source);
+            modifiers.contains('@') || contains_word(modifiers, "override")
+        }
+        _ => false,
+    });
+    annotated
+        || node
+            .parent()
+            .is_some_and(|parent| parent.kind() == "decorated_definition")
+        || node
+            .prev_named_sibling()
+            .is_some_and(|sibling| sibling.kind() == "attribute_item")
+        || node
+            .parent()
+            .and_then(|list| list.parent())
+            .is_some_and(|item| {
+                item.kind() == "trait_item"
+                    || (item.kind() == "impl_item" && item.child_by_field_name("trait").is_some())
+            })
+}
+
+/// Whether a function outside any other function is out of reach of other files.
+fn is_file_private(node: Node<'_>, name: Node<'_>, privacy: Privacy, source: &[u8]) -> bool {
+    let parent_kind = node.parent().map(|parent| parent.kind());
+    let has_child = |predicate: &dyn Fn(Node<'_>) -> bool| {
+        let mut cursor = node.walk();
+        node.children(&mut cursor).any(predicate)
+    };
+    match privacy {
+        Privacy::Underscore => {
+            let name = text(name, source);
+            name.starts_with('_') && !(name.starts_with("__") && name.ends_with("__"))
+        }
+        Privacy::Export => {
+            if node.kind() == "method_definition" {
+                name.kind() == "private_property_identifier"
+                    || has_child(&|child| {
+                        child.kind() == "accessibility_modifier" && text(child, source) == "private"
+                    })
+            } else {
+                parent_kind != Some("export_statement")
+            }
+        }
+        // Child modules declared with `mod name;` live in other files and see private items.
+        Privacy::Visibility => {
+            !has_child(&|child| child.kind() == "visibility_modifier")
+                && !declares_file_modules(node)
+        }
+        Privacy::Static => {
+            parent_kind == Some("translation_unit")
+                && has_child(&|child| {
+                    child.kind() == "storage_class_specifier" && text(child, source) == "static"
+                })
+        }
+        Privacy::Modifier => has_child(&|child| {
+            matches!(child.kind(), "modifiers" | "modifier")
+                && contains_word(text(child, source), "private")
+        }),
+    }
+}
+
+fn declares_file_modules(node: Node<'_>) -> bool {
+    let mut root = node;
+    while let Some(parent) = root.parent() {
+        root = parent;
+    }
+    let mut cursor = root.walk();
+    root.named_children(&mut cursor)
+        .any(|item| item.kind() == "mod_item" && item.child_by_field_name("body").is_none())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::find_unused;
+    use crate::provider::ProviderRegistry;
+
+    fn unused(file: &str, source: &str) -> Vec<(String, &'static str, usize)> {
+        let registry = ProviderRegistry::default();
+        let path = Path::new(file);
+        let provider = registry.provider_for(path).expect("provider should exist");
+        find_unused(path, source.as_bytes(), provider)
+            .expect("source should parse")
+            .into_iter()
+            .map(|symbol| (symbol.name, symbol.category.as_str(), symbol.line))
+            .collect()
+    }
+
+    #[test]
+    fn python_reports_private_functions_locals_and_parameters() {
+        let source = "\
+def public(a):
+    tmp = a + 1
+    return _helper(a, 0)
+
+
+def _helper(x, _ignored):
+    return x
+
+
+def _dead(value):
+    return 1
+";
+        assert_eq!(
+            unused("a.py", source),
+            vec![
+                ("tmp".to_string(), "variable", 2),
+                ("_dead".to_string(), "function", 10),
+                ("value".to_string(), "parameter", 10),
+            ]
+        );
+    }
+
+    #[test]
+    fn rust_skips_public_attributed_and_trait_functions() {
+        let source = r#"
+pub fn api() {}
+
+fn helper(value: i32, unused: i32) -> i32 {
+    let label = value;
+    println!("{label}");
+    value
+}
+
+fn dead() {}
+
+#[test]
+fn a_test() {}
+
+impl Clone for Thing {
+    fn clone(&self) -> Self {
+        Thing
+    }
+}
+
+fn caller() -> i32 {
+    helper(1, 2)
+}
+"#;
+        assert_eq!(
+            unused("a.rs", source),
+            vec![
+                ("unused".to_string(), "parameter", 4),
+                ("dead".to_string(), "function", 10),
+                ("caller".to_string(), "function", 21),
+            ]
+        );
+    }
+
+    #[test]
+    fn javascript_keeps_exported_functions() {
+        let source = "\
+export function api(x) {
+  const y = 1;
+  return x;
+}
+
+function local(z) {
+  return z;
+}
+";
+        assert_eq!(
+            unused("a.js", source),
+            vec![
+                ("y".to_string(), "variable", 2),
+                ("local".to_string(), "function", 6),
+            ]
+        );
+    }
+}
@@ -0,0 +1,167 @@
+//! Line anchors for recently changed code (`anchors --from-git-diff`).
+//!
+//! Every added line of a `git diff` becomes a hashline anchor into the working tree, checked
+//! against the file as it is now, so a follow-up line patch can target exactly what changed.
+//! Consecutive added lines form one range whose first and last anchors span it.
+
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use serde::Serialize;
+
+use crate::error::IdenteditError;
+use crate::hash::hash_bytes;
+use crate::hashline::{HashedLine, format_line_ref, show_hashed_lines};
+
+use super::diff_plan::{Hunk, LineTag, parse_working_tree_diff};
+
+#[derive(Debug, Args)]
+pub struct AnchorsArgs {
+    #[arg(
+        long = "from-git-diff",
+        value_name = "DIFF",
+        required = true,
+        num_args = 0..=1,
+        default_missing_value = "-",
+        help = "Unified diff of the working tree, as `git diff` prints it; reads stdin without DIFF or with '-'. Paths resolve from the current directory"
+    )]
+    pub from_git_diff: PathBuf,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnchorsResponse {
+    pub files: Vec<ChangedFileAnchors>,
+    pub summary: AnchorsSummary,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangedFileAnchors {
+    #[serde(serialize_with = "crate::path_style::serialize_path")]
+    pub file: PathBuf,
+    pub expected_file_hash: String,
+    pub ranges: Vec<ChangedRange>,
+}
+
+/// A run of consecutive added lines.
+#[derive(Debug, Serialize)]
+pub struct ChangedRange {
+    /// Header of the hunk the lines came from.
+    pub hunk: String,
+    pub start_anchor: String,
+    /// Absent when the range is a single line.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_anchor: Option<String>,
+    pub lines: Vec<AnchoredLine>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnchoredLine {
+    pub line: usize,
+    pub anchor: String,
+    pub text: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct AnchorsSummary {
+    pub files: usize,
+    pub ranges: usize,
+    pub lines: usize,
+}
+
+pub fn run_anchors(args: AnchorsArgs) -> Result<AnchorsResponse, IdenteditError> {
+    let diff_text = if args.from_git_diff.as_os_str() == "-" {
+        let mut buffer = String::new();
+        std::io::stdin()
+            .read_to_string(&mut buffer)
+            .map_err(|source| IdenteditError::StdinRead { source })?;
+        buffer
+    } else {
+        fs::read_to_string(&args.from_git_diff)
+            .map_err(|error| IdenteditError::io(&args.from_git_diff, error))?
+    };
+
+    let mut response = AnchorsResponse {
+        files: Vec::new(),
+        summary: AnchorsSummary::default(),
+    };
+    for file_diff in parse_working_tree_diff(&diff_text)? {
+        let source = fs::read(&file_diff.path)
+            .map_err(|error| IdenteditError::io(&file_diff.path, error))?;
+        let source_text = String::from_utf8(source.clone()).map_err(|error| {
+            IdenteditError::io(
+                &file_diff.path,
+                std::io::Error::new(std::io::ErrorKind::InvalidData, error),
+            )
+        })?;
+        let lines = show_hashed_lines(&source_text);
+        let mut ranges = Vec::new();
+        for hunk in &file_diff.hunks {
+            ranges.extend(hunk_ranges(&file_diff.path, &lines, hunk)?);
+        }
+        if ranges.is_empty() {
+            continue;
+        }
+        response.summary.ranges += ranges.len();
+        response.summary.lines += ranges.iter().map(|range| range.lines.len()).sum::<usize>();
+        response.files.push(ChangedFileAnchors {
+            file: file_diff.path,
+            expected_file_hash: hash_bytes(&source),
+            ranges,
+        });
+    }
+    response.summary.files = response.files.len();
+    Ok(response)
+}
+
+/// The added lines of `hunk` as ranges of anchors into `lines`, failing when the file no
+/// longer has them where the hunk puts them.
+fn hunk_ranges(
+    file: &Path,
+    lines: &[HashedLine],
+    hunk: &Hunk,
+) -> Result<Vec<ChangedRange>, IdenteditError> {
+    let mut ranges: Vec<ChangedRange> = Vec::new();
+    let mut line_number = hunk.new_start;
+    let mut previous_added = false;
+    for hunk_line in &hunk.lines {
+        match hunk_line.tag {
+            LineTag::Removed => continue,
+            LineTag::Context => previous_added = false,
+            LineTag::Added => {
+                let current = line_number
+                    .checked_sub(1)
+                    .and_then(|index| lines.get(index))
+                    .filter(|current| current.content == hunk_line.text)
+                    .ok_or_else(|| IdenteditError::InvalidRequest {
+                        message: format!(
+                            "Hunk '{}' does not match the current contents of '{}' at line {line_number}; re-run git diff",
+                            hunk.header,
+                            file.display()
+                        ),
+                    })?;
+                let anchored = AnchoredLine {
+                    line: current.line,
+                    anchor: format_line_ref(current.line, &current.hash),
+                    text: current.content.clone(),
+                };
+                match ranges.last_mut().filter(|_| previous_added) {
+                    Some(range) => {
+                        range.end_anchor = Some(anchored.anchor.clone());
+                        range.lines.push(anchored);
+                    }
+                    None => ranges.push(ChangedRange {
+                        hunk: hunk.header.clone(),
+                        start_anchor: anchored.anchor.clone(),
+                        end_anchor: None,
+                        lines: vec![anchored],
+                    }),
+                }
+                previous_added = true;
+            }
+        }
+        line_number += 1;
+    }
+    Ok(ranges)
+}
@@ -1,22 +1,33 @@
 use std::env;
 use std::fs;
-use std::io::Read;
 use std::path::{Path, PathBuf};
 
 use clap::Args;
 use serde::{Deserialize, Serialize};
 
 use crate::apply::{
-    ApplyFailureInjection, ApplyFileResult, ApplyResponse, ApplySummary, ApplyTransaction,
-    apply_multi_file_changeset, apply_multi_file_changeset_with_injection,
-    dry_run_multi_file_changeset,
+    ApplyFailureInjection, ApplyFileResult, ApplyOperationOutcomes, ApplyResponse, ApplySettings,
+    ApplySummary, ApplyTransaction, Normalization, OperationRef, apply_multi_file_changeset,
+    apply_multi_file_changeset_with_injection, dry_run_multi_file_changeset,
 };
-use crate::changeset::{FileChange, MultiFileChangeset, TransformTarget, hash_text};
+use crate::changeset::{
+    FileChange, MultiFileChangeset, OpKind, TrailingWhitespaceScope, TransactionMode,
+    TransformTarget, hash_text,
+};
+use crate::compression::{read_plan_file, read_plan_stdin};
 use crate::error::IdenteditError;
 use crate::hashline::{
     HashlineCheckError, HashlineCheckResult, HashlineMismatchStatus, check_hashline_refs,
-    format_line_ref,
+    format_line_ref, parse_line_ref,
 };
+use crate::provider::ProviderRegistry;
+use crate::transform::line_spans;
+
+use super::inverse::InverseBaseline;
+use super::plan_hash::plan_hash;
+use super::preflight_cmd::{PreflightCheck, run_preflight_cmds};
+use super::read::ReadHandle;
+use super::reread::RereadBaseline;
 
 #[derive(Debug, Args)]
 pub struct ApplyArgs {
@@ -29,13 +40,73 @@ pub struct ApplyArgs {
         help = "Enable line-target anchor remap/repair for deterministic stale anchors"
     )]
     pub repair: bool,
+    #[arg(
+        long,
+        help = "Commit operations that still resolve and report failed and skipped ones instead of rolling back (sets transaction mode continue_on_error)"
+    )]
+    pub continue_on_error: bool,
+    #[arg(
+        long,
+        help = "Rewrite targets that have other hard links; only the named path gets the new contents (sets transaction force_unlink)"
+    )]
+    pub force_unlink: bool,
+    #[arg(
+        long,
+        help = "Write read-only targets instead of refusing them, keeping their mode (sets transaction chmod_writable)"
+    )]
+    pub chmod_writable: bool,
+    #[arg(
+        long,
+        help = "End every rewritten file with a line break (sets transaction ensure_final_newline)"
+    )]
+    pub ensure_final_newline: bool,
+    #[arg(
+        long,
+        value_enum,
+        value_name = "SCOPE",
+        help = "Remove trailing spaces and tabs from edited lines or the whole file (sets transaction strip_trailing_whitespace)"
+    )]
+    pub strip_trailing_whitespace: Option<TrailingWhitespaceScope>,
     #[arg(long, help = "Include per-file apply results in output")]
     pub verbose: bool,
+    #[arg(
+        long = "group",
+        value_name = "NAME",
+        help = "Apply only the operations whose group is NAME, e.g. to retry the part of a plan that failed (repeatable)"
+    )]
+    pub groups: Vec<String>,
+    #[arg(
+        long = "reread-after",
+        help = "Include the handles the apply created in the response: new node handles, and fresh anchors around lines changed through line targets"
+    )]
+    pub reread_after: bool,
+    #[arg(
+        long = "invalidated-identities",
+        help = "List in each file's stats the node identities the apply invalidated, parsing the file before and after (implied by --reread-after)"
+    )]
+    pub invalidated_identities: bool,
+    #[arg(
+        long,
+        help = "Include in the response, as inverse, a plan that undoes this apply; run it with identedit revert"
+    )]
+    pub emit_inverse: bool,
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Write a plan that undoes this apply to PATH; run it with identedit revert"
+    )]
+    pub inverse_out: Option<PathBuf>,
+    #[arg(
+        long = "preflight-cmd",
+        value_name = "PROGRAM",
+        help = "Run PROGRAM before writing, with the target files as JSON on stdin; a non-zero exit aborts the apply (repeatable)"
+    )]
+    pub preflight_cmds: Vec<PathBuf>,
     #[arg(long = "inject-failure-after-writes", hide = true, value_name = "N")]
     pub inject_failure_after_writes: Option<usize>,
     #[arg(
         value_name = "PLAN",
-        help = "Path to edit-plan JSON; if omitted, read raw plan JSON from stdin"
+        help = "Path to edit-plan JSON, optionally gzip or zstd compressed; if omitted, read raw plan JSON from stdin"
     )]
     pub input: Option<PathBuf>,
 }
@@ -45,6 +116,9 @@ pub struct ApplyArgs {
 struct StdinApplyRequest {
     command: String,
     changeset: MultiFileChangeset,
+    /// Overrides the changeset's own `correlation_id`.
+    #[serde(default)]
+    correlation_id: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -53,39 +127,160 @@ pub struct ApplyCliResponse {
     pub transaction: ApplyTransaction,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub applied: Option<Vec<ApplyFileResult>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operations: Option<ApplyOperationOutcomes>,
+    /// Optional operations whose target is gone, reported even without `--verbose`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub targets_missing_ignored: Vec<OperationRef>,
+    /// `file: warning` for every file whose ownership or extended attributes could not be
+    /// restored, reported even without `--verbose`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub metadata_warnings: Vec<String>,
+    /// Files the transaction's whitespace policy changed, reported even without `--verbose`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub normalized: Vec<NormalizedFile>,
+    /// Handles read back from the rewritten files; present with `--reread-after`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reread: Option<Vec<ReadHandle>>,
+    /// The plan's `correlation_id`, echoed back.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    /// The `plan hash` digest of the plan as read, before command-line flags adjust it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan_hash: Option<String>,
+    /// What each `--preflight-cmd` reported before the write.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub preflight: Vec<PreflightCheck>,
+    /// The plan that undoes the apply; present with `--emit-inverse`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inverse: Option<MultiFileChangeset>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NormalizedFile {
+    pub file: String,
+    #[serde(flatten)]
+    pub normalization: Normalization,
 }
 
-pub fn run_apply(args: ApplyArgs) -> Result<ApplyCliResponse, IdenteditError> {
+pub fn run_apply(
+    args: ApplyArgs,
+    settings: ApplySettings,
+) -> Result<ApplyCliResponse, IdenteditError> {
     let failure_injection = parse_failure_injection(args.inject_failure_after_writes)?;
     if args.dry_run && failure_injection.is_some() {
         return Err(IdenteditError::InvalidRequest {
             message: "--dry-run cannot be combined with --inject-failure-after-writes".to_string(),
         });
     }
+    if args.dry_run && args.reread_after {
+        return Err(IdenteditError::InvalidRequest {
+            message: "--reread-after has nothing to read back with --dry-run".to_string(),
+        });
+    }
+    let wants_inverse = args.emit_inverse || args.inverse_out.is_some();
+    if args.dry_run && wants_inverse {
+        return Err(IdenteditError::InvalidRequest {
+            message: "--emit-inverse and --inverse-out have nothing to undo with --dry-run"
+                .to_string(),
+        });
+    }
 
-    let mut changeset = if args.json {
+    let changeset = if args.json {
         run_apply_json_mode()?
-    } else if let Some(input_path) = args.input {
-        read_changeset_from_file(&input_path)?
+    } else if let Some(input_path) = &args.input {
+        read_changeset_from_file(input_path)?
     } else {
         read_changeset_from_stdin()?
     };
+    let correlation_id = changeset.correlation_id.clone();
+    apply_plan(&args, changeset, settings, failure_injection)
+        .map_err(|error| error.with_correlation_id(correlation_id.as_deref()))
+}
+
+/// The part of `run_apply` after the plan is read, whose errors echo its `correlation_id`.
+fn apply_plan(
+    args: &ApplyArgs,
+    mut changeset: MultiFileChangeset,
+    settings: ApplySettings,
+    failure_injection: Option<ApplyFailureInjection>,
+) -> Result<ApplyCliResponse, IdenteditError> {
+    let wants_inverse = args.emit_inverse || args.inverse_out.is_some();
+    let plan_hash = plan_hash(&changeset)?;
+    if !args.groups.is_empty() {
+        keep_groups(&mut changeset, &args.groups)?;
+    }
+    if args.continue_on_error {
+        changeset.transaction.mode = TransactionMode::ContinueOnError;
+    }
+    changeset.transaction.force_unlink |= args.force_unlink;
+    changeset.transaction.chmod_writable |= args.chmod_writable;
+    changeset.transaction.ensure_final_newline |= args.ensure_final_newline;
+    if args.strip_trailing_whitespace.is_some() {
+        changeset.transaction.strip_trailing_whitespace = args.strip_trailing_whitespace;
+    }
+    changeset.transaction.settings = ApplySettings {
+        invalidated_identities: args.invalidated_identities || args.reread_after,
+        ..settings
+    };
+    if changeset.transaction.mode == TransactionMode::ContinueOnError && failure_injection.is_some()
+    {
+        return Err(IdenteditError::InvalidRequest {
+            message: "continue_on_error mode cannot be combined with --inject-failure-after-writes"
+                .to_string(),
+        });
+    }
     if args.repair {
         repair_line_targets_in_changeset(&mut changeset)?;
         refresh_line_previews_after_repair(&mut changeset)?;
     }
 
+    let preflight = run_preflight_cmds(
+        &args.preflight_cmds,
+        "apply",
+        changeset.files.iter().flat_map(|file_change| {
+            let destinations =
+                file_change
+                    .operations
+                    .iter()
+                    .filter_map(|operation| match &operation.op {
+                        OpKind::Move { to } => Some(to.as_path()),
+                        _ => None,
+                    });
+            std::iter::once(file_change.file.as_path()).chain(destinations)
+        }),
+    )?;
+    let baseline = args
+        .reread_after
+        .then(|| RereadBaseline::for_changesets(&changeset.files));
+    let inverse_baseline = if wants_inverse {
+        Some(InverseBaseline::capture(&changeset)?)
+    } else {
+        None
+    };
     let response =
         apply_changeset_with_optional_injection(&changeset, failure_injection, args.dry_run)?;
 
-    Ok(shape_apply_response(response, args.verbose))
+    let mut response = shape_apply_response(response, args.verbose);
+    let registry = changeset.transaction.settings.provider_registry()?;
+    response.reread = baseline.map(|baseline| baseline.reread(&registry));
+    if let Some(inverse_baseline) = inverse_baseline {
+        let inverse = inverse_baseline.inverse_plan(changeset.correlation_id.clone(), &registry)?;
+        if let Some(path) = &args.inverse_out {
+            let serialized = serde_json::to_string_pretty(&inverse)
+                .map_err(|source| IdenteditError::ResponseSerialization { source })?;
+            fs::write(path, serialized).map_err(|error| IdenteditError::io(path, error))?;
+        }
+        response.inverse = args.emit_inverse.then_some(inverse);
+    }
+    response.correlation_id = changeset.correlation_id;
+    response.plan_hash = Some(plan_hash);
+    response.preflight = preflight;
+    Ok(response)
 }
 
 fn run_apply_json_mode() -> Result<MultiFileChangeset, IdenteditError> {
-    let mut request_body = String::new();
-    std::io::stdin()
-        .read_to_string(&mut request_body)
-        .map_err(|error| IdenteditError::StdinRead { source: error })?;
+    let request_body = read_plan_stdin()?;
 
     let request: StdinApplyRequest = serde_json::from_str(&request_body)
         .map_err(|error| IdenteditError::InvalidJsonRequest { source: error })?;
@@ -99,10 +294,41 @@ fn run_apply_json_mode() -> Result<MultiFileChangeset, IdenteditError> {
         });
     }
 
-    Ok(request.changeset)
+    let mut changeset = request.changeset;
+    if request.correlation_id.is_some() {
+        changeset.correlation_id = request.correlation_id;
+    }
+    Ok(changeset)
+}
+
+/// Drops the operations outside `groups`, and the files left without any.
+fn keep_groups(
+    changeset: &mut MultiFileChangeset,
+    groups: &[String],
+) -> Result<(), IdenteditError> {
+    for file_change in &mut changeset.files {
+        file_change.operations.retain(|operation| {
+            operation
+                .group
+                .as_ref()
+                .is_some_and(|group| groups.contains(group))
+        });
+    }
+    changeset
+        .files
+        .retain(|file_change| !file_change.operations.is_empty());
+    if changeset.files.is_empty() {
+        return Err(IdenteditError::InvalidRequest {
+            message: format!(
+                "No operation in the plan belongs to group {}",
+                groups.join(", ")
+            ),
+        });
+    }
+    Ok(())
 }
 
-fn apply_changeset_with_optional_injection(
+pub(super) fn apply_changeset_with_optional_injection(
     changeset: &MultiFileChangeset,
     failure_injection: Option<ApplyFailureInjection>,
     dry_run: bool,
@@ -118,6 +344,10 @@ fn apply_changeset_with_optional_injection(
     apply_multi_file_changeset(changeset)
 }
 
+/// Remaps stale line anchors to where their lines moved. An anchor whose hash now sits on
+/// several lines (duplicates, or a truncated-hash collision) is not resolved by its own hash
+/// alone: for a `replace`, each candidate start/end pair is checked against the preview's
+/// old text, so the neighbouring lines in the range decide. Exactly one pair must fit.
 fn repair_line_targets_in_changeset(
     changeset: &mut MultiFileChangeset,
 ) -> Result<(), IdenteditError> {
@@ -146,40 +376,109 @@ fn repair_line_targets_in_changeset(
         if check.ok {
             continue;
         }
-        if !check.mismatches.iter().all(|mismatch| {
-            mismatch.status == HashlineMismatchStatus::Remappable && mismatch.remaps.len() == 1
-        }) {
+        if check
+            .mismatches
+            .iter()
+            .any(|mismatch| mismatch.status == HashlineMismatchStatus::Mismatch)
+        {
             return Err(hashline_precondition_failed_error(check));
         }
 
-        let remapped = check
-            .mismatches
-            .into_iter()
-            .map(|mismatch| {
-                let target = mismatch.remaps.first().expect("validated remap count");
-                (
-                    mismatch.edit_index,
-                    format_line_ref(target.line, &target.hash),
-                )
-            })
-            .collect::<std::collections::HashMap<usize, String>>();
-
-        for (ref_index, (operation_index, is_end_anchor, _)) in target_refs.iter().enumerate() {
-            let Some(new_anchor) = remapped.get(&ref_index) else {
+        let spans = line_spans(&source);
+        let mut candidates = vec![None; refs.len()];
+        for mismatch in &check.mismatches {
+            candidates[mismatch.edit_index] = Some(
+                mismatch
+                    .remaps
+                    .iter()
+                    .map(|target| (target.line, format_line_ref(target.line, &target.hash)))
+                    .collect::<Vec<_>>(),
+            );
+        }
+
+        for (operation_index, operation) in file_change.operations.iter_mut().enumerate() {
+            let ref_indices = target_refs
+                .iter()
+                .enumerate()
+                .filter(|(_, (index, _, _))| *index == operation_index)
+                .map(|(ref_index, _)| ref_index)
+                .collect::<Vec<_>>();
+            if ref_indices
+                .iter()
+                .all(|ref_index| candidates[*ref_index].is_none())
+            {
                 continue;
+            }
+            let candidates_for =
+                |ref_index: usize| -> Result<Vec<(usize, String)>, IdenteditError> {
+                    match &candidates[ref_index] {
+                        Some(remaps) => Ok(remaps.clone()),
+                        None => {
+                            let anchor = &refs[ref_index];
+                            let parsed =
+                                parse_line_ref(anchor).map_err(map_hashline_check_error)?;
+                            Ok(vec![(parsed.line, anchor.clone())])
+                        }
+                    }
+                };
+            let starts = candidates_for(ref_indices[0])?;
+            let ends = match ref_indices.get(1) {
+                Some(end_index) => Some(candidates_for(*end_index)?),
+                None => None,
+            };
+            let preview = &operation.preview;
+            let (expected_old_hash, expected_old_len) = match operation.op {
+                OpKind::Replace { .. } => (
+                    preview
+                        .old_hash
+                        .clone()
+                        .or_else(|| preview.old_text.as_deref().map(hash_text)),
+                    preview
+                        .old_len
+                        .or_else(|| preview.old_text.as_ref().map(String::len)),
+                ),
+                _ => (None, None),
             };
 
-            let operation = file_change
-                .operations
-                .get_mut(*operation_index)
-                .ok_or_else(|| IdenteditError::InvalidRequest {
-                    message: format!(
-                        "Internal apply repair error: missing operation {} for file '{}'",
-                        operation_index,
-                        file_change.file.display()
-                    ),
-                })?;
-            let TransformTarget::Line { anchor, end_anchor } = &mut operation.target else {
+            let mut fits = Vec::new();
+            for (start_line, start_anchor) in &starts {
+                let end_options = match &ends {
+                    Some(ends) => ends
+                        .iter()
+                        .map(|(line, anchor)| (*line, Some(anchor)))
+                        .collect::<Vec<_>>(),
+                    None => vec![(*start_line, None)],
+                };
+                for (end_line, end_anchor) in end_options {
+                    let (Some(start), Some(end)) = (
+                        spans.get(start_line.wrapping_sub(1)),
+                        spans.get(end_line.wrapping_sub(1)),
+                    ) else {
+                        continue;
+                    };
+                    if end_line < *start_line {
+                        continue;
+                    }
+                    let range = start.start..end.end;
+                    if expected_old_len.is_some_and(|len| range.len() != len)
+                        || expected_old_hash
+                            .as_deref()
+                            .is_some_and(|expected| hash_text(&source[range]) != expected)
+                    {
+                        continue;
+                    }
+                    fits.push((start_anchor.clone(), end_anchor.cloned()));
+                }
+            }
+            let [(start_anchor, end_anchor)] = fits.as_slice() else {
+                return Err(hashline_precondition_failed_error(check));
+            };
+
+            let TransformTarget::Line {
+                anchor,
+                end_anchor: target_end_anchor,
+            } = &mut operation.target
+            else {
                 return Err(IdenteditError::InvalidRequest {
                     message: format!(
                         "Internal apply repair error: expected line target at operation {}",
@@ -187,13 +486,9 @@ fn repair_line_targets_in_changeset(
                     ),
                 });
             };
-
-            if *is_end_anchor {
-                if let Some(end_anchor) = end_anchor {
-                    *end_anchor = new_anchor.clone();
-                }
-            } else {
-                *anchor = new_anchor.clone();
+            *anchor = start_anchor.clone();
+            if let Some(end_anchor) = end_anchor {
+                *target_end_anchor = Some(end_anchor.clone());
             }
         }
     }
@@ -204,13 +499,17 @@ fn repair_line_targets_in_changeset(
 fn refresh_line_previews_after_repair(
     changeset: &mut MultiFileChangeset,
 ) -> Result<(), IdenteditError> {
+    let registry = changeset.transaction.settings.provider_registry()?;
     for file_change in &mut changeset.files {
-        refresh_line_operation_previews(file_change)?;
+        refresh_line_operation_previews(file_change, &registry)?;
     }
     Ok(())
 }
 
-fn refresh_line_operation_previews(file_change: &mut FileChange) -> Result<(), IdenteditError> {
+fn refresh_line_operation_previews(
+    file_change: &mut FileChange,
+    registry: &ProviderRegistry,
+) -> Result<(), IdenteditError> {
     let mut original_indices = Vec::new();
     let mut line_operations = Vec::new();
     for (index, operation) in file_change.operations.iter().enumerate() {
@@ -228,7 +527,7 @@ fn refresh_line_operation_previews(file_change: &mut FileChange) -> Result<(), I
         file: file_change.file.clone(),
         operations: line_operations,
     };
-    let resolved = crate::transform::resolve_changeset_targets(&line_only_change)?;
+    let resolved = crate::transform::resolve_changeset_targets(&line_only_change, registry)?;
 
     for (resolved_index, matched_change) in resolved.into_iter().enumerate() {
         let original_index = original_indices
@@ -286,12 +585,44 @@ pub(crate) fn shape_apply_response(response: ApplyResponse, verbose: bool) -> Ap
         applied,
         summary,
         transaction,
+        operations,
+        targets_missing_ignored,
     } = response;
 
+    let metadata_warnings = applied
+        .iter()
+        .flat_map(|result| {
+            result
+                .metadata_warnings
+                .iter()
+                .map(|warning| format!("{}: {warning}", result.file))
+        })
+        .collect();
+    let normalized = applied
+        .iter()
+        .filter_map(|result| {
+            result
+                .normalized
+                .clone()
+                .map(|normalization| NormalizedFile {
+                    file: result.file.clone(),
+                    normalization,
+                })
+        })
+        .collect();
     ApplyCliResponse {
         summary,
         transaction,
         applied: verbose.then_some(applied),
+        operations,
+        targets_missing_ignored,
+        metadata_warnings,
+        normalized,
+        reread: None,
+        correlation_id: None,
+        plan_hash: None,
+        preflight: Vec::new(),
+        inverse: None,
     }
 }
 
@@ -319,18 +650,14 @@ fn parse_failure_injection(
     Ok(Some(ApplyFailureInjection { after_writes }))
 }
 
-fn read_changeset_from_file(path: &Path) -> Result<MultiFileChangeset, IdenteditError> {
-    let content = fs::read_to_string(path).map_err(|error| IdenteditError::io(path, error))?;
+pub(super) fn read_changeset_from_file(path: &Path) -> Result<MultiFileChangeset, IdenteditError> {
+    let content = read_plan_file(path)?;
     serde_json::from_str(&content)
         .map_err(|error| IdenteditError::InvalidJsonRequest { source: error })
 }
 
-fn read_changeset_from_stdin() -> Result<MultiFileChangeset, IdenteditError> {
-    let mut request_body = String::new();
-    std::io::stdin()
-        .read_to_string(&mut request_body)
-        .map_err(|error| IdenteditError::StdinRead { source: error })?;
-
+pub(super) fn read_changeset_from_stdin() -> Result<MultiFileChangeset, IdenteditError> {
+    let request_body = read_plan_stdin()?;
     serde_json::from_str(&request_body)
         .map_err(|error| IdenteditError::InvalidJsonRequest { source: error })
 }
@@ -0,0 +1,188 @@
+use clap::CommandFactory;
+use serde::Serialize;
+
+use super::Cli;
+use crate::apply::STREAMING_APPLY_MIN_BYTES;
+use crate::hash::{HASH_ALGORITHM, HASH_HEX_LEN, hash_text};
+use crate::hashline::HASHLINE_PUBLIC_HEX_LEN;
+use crate::provider::ProviderRegistry;
+
+/// Bumped whenever anything this response describes changes: a hash input, algorithm, or
+/// length, or the name or order of a response field.
+pub const CONTRACT_VERSION: u32 = 1;
+
+/// Bumped whenever a plan written by an older build may no longer apply: a removed or
+/// renamed op, target, or transaction field.
+pub const PLAN_SCHEMA_VERSION: u32 = 1;
+
+/// The `type` tags accepted in a change's `op`, in `OpKind` order.
+const OP_TYPES: &[&str] = &[
+    "replace",
+    "delete",
+    "insert_before",
+    "insert_after",
+    "insert",
+    "move_before",
+    "move_after",
+    "move",
+];
+
+/// The `type` tags accepted in a change's `target`.
+const TARGET_TYPES: &[&str] = &["node", "file_start", "file_end", "line"];
+
+#[derive(Debug, Serialize)]
+pub struct CapabilitiesResponse {
+    pub version: &'static str,
+    pub contract_version: u32,
+    /// Struct fields serialize in declaration order and JSON maps in key order, so equal
+    /// inputs give byte-identical responses.
+    pub field_order: &'static str,
+    pub hashes: HashFormats,
+    pub schema_versions: SchemaVersions,
+    pub features: Features,
+    pub commands: Vec<CommandCapability>,
+    pub op_types: &'static [&'static str],
+    pub target_types: &'static [&'static str],
+    /// In lookup order, including grammars loaded from the dynamic manifest.
+    pub providers: Vec<ProviderCapability>,
+    pub limits: Limits,
+    /// Hash of `providers`, which changes when `grammar install` or an edit to the dynamic
+    /// grammar manifest changes the supported extensions. Every command loads the manifest
+    /// afresh, so a client that sees a new token only needs to re-read `providers`.
+    pub providers_token: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SchemaVersions {
+    pub contract: u32,
+    pub plan: u32,
+}
+
+/// Cargo features this binary was built with.
+#[derive(Debug, Serialize)]
+pub struct Features {
+    pub script: bool,
+    pub wasm: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CommandCapability {
+    pub name: String,
+    pub about: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProviderCapability {
+    pub name: &'static str,
+    /// Empty for the fallback provider, which accepts any file.
+    pub extensions: &'static [&'static str],
+}
+
+/// `null` when the feature that enforces the limit is not built in.
+#[derive(Debug, Serialize)]
+pub struct Limits {
+    pub streaming_apply_min_bytes: usize,
+    pub script_max_operations: Option<u64>,
+    pub wasm_fuel_per_node: Option<u64>,
+    pub wasm_memory_limit_bytes: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HashFormats {
+    pub identity: HashFormat,
+    pub text: HashFormat,
+    pub line: HashFormat,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HashFormat {
+    pub algorithm: &'static str,
+    /// Leading lowercase hex digits kept from the full digest.
+    pub hex_length: usize,
+    pub input: &'static str,
+    pub used_by: &'static [&'static str],
+}
+
+pub fn run_capabilities(registry: &ProviderRegistry) -> CapabilitiesResponse {
+    let providers = registry
+        .providers()
+        .map(|provider| ProviderCapability {
+            name: provider.name(),
+            extensions: provider.supported_extensions(),
+        })
+        .collect::<Vec<_>>();
+    let providers_token = providers_token(&providers);
+    CapabilitiesResponse {
+        version: env!("CARGO_PKG_VERSION"),
+        contract_version: CONTRACT_VERSION,
+        field_order: "stable",
+        hashes: HashFormats {
+            identity: HashFormat {
+                algorithm: HASH_ALGORITHM,
+                hex_length: HASH_HEX_LEN,
+                input: "kind + \"\\n\" + name (empty if none) + \"\\n\" + node text",
+                used_by: &["identity"],
+            },
+            text: HashFormat {
+                algorithm: HASH_ALGORITHM,
+                hex_length: HASH_HEX_LEN,
+                input: "UTF-8 bytes of the text",
+                used_by: &["expected_old_hash", "old_hash", "expected_file_hash"],
+            },
+            line: HashFormat {
+                algorithm: HASH_ALGORITHM,
+                hex_length: HASHLINE_PUBLIC_HEX_LEN,
+                input: "line content without its line terminator",
+                used_by: &["anchor", "end_anchor"],
+            },
+        },
+        schema_versions: SchemaVersions {
+            contract: CONTRACT_VERSION,
+            plan: PLAN_SCHEMA_VERSION,
+        },
+        features: Features {
+            script: cfg!(feature = "script"),
+            wasm: cfg!(feature = "wasm"),
+        },
+        commands: Cli::command()
+            .get_subcommands()
+            .map(|command| CommandCapability {
+                name: command.get_name().to_string(),
+                about: command
+                    .get_about()
+                    .map(ToString::to_string)
+                    .unwrap_or_default(),
+            })
+            .collect(),
+        op_types: OP_TYPES,
+        target_types: TARGET_TYPES,
+        providers,
+        limits: Limits {
+            streaming_apply_min_bytes: STREAMING_APPLY_MIN_BYTES,
+            #[cfg(feature = "script")]
+            script_max_operations: Some(super::script::MAX_OPERATIONS),
+            #[cfg(not(feature = "script"))]
+            script_max_operations: None,
+            #[cfg(feature = "wasm")]
+            wasm_fuel_per_node: Some(super::edit_wasm::FUEL_PER_NODE),
+            #[cfg(not(feature = "wasm"))]
+            wasm_fuel_per_node: None,
+            #[cfg(feature = "wasm")]
+            wasm_memory_limit_bytes: Some(super::edit_wasm::MEMORY_LIMIT_BYTES),
+            #[cfg(not(feature = "wasm"))]
+            wasm_memory_limit_bytes: None,
+        },
+        providers_token,
+    }
+}
+
+fn providers_token(providers: &[ProviderCapability]) -> String {
+    let mut state = String::new();
+    for provider in providers {
+        state.push_str(provider.name);
+        state.push('\t');
+        state.push_str(&provider.extensions.join(","));
+        state.push('\n');
+    }
+    hash_text(&state)
+}
@@ -0,0 +1,22 @@
+use clap::{Args, CommandFactory};
+use clap_complete::Shell;
+
+use super::Cli;
+
+#[derive(Debug, Args)]
+pub struct CompletionsArgs {
+    #[arg(
+        value_enum,
+        value_name = "SHELL",
+        help = "Shell to generate completions for"
+    )]
+    pub shell: Shell,
+}
+
+/// The completion script for `args.shell`, covering every command and flag in the clap tree.
+pub fn run_completions(args: CompletionsArgs) -> String {
+    let mut command = Cli::command();
+    let mut script = Vec::new();
+    clap_complete::generate(args.shell, &mut command, "identedit", &mut script);
+    String::from_utf8_lossy(&script).trim_end().to_string()
+}
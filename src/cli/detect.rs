@@ -0,0 +1,208 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use serde::Serialize;
+
+use crate::error::IdenteditError;
+use crate::provider::{HeaderDecision, ProviderRegistry, ProviderRoute, normalize_extension};
+
+use super::read::header_decision;
+
+/// Bytes inspected for NUL bytes, as git does when it decides a file is binary.
+const BINARY_SNIFF_BYTES: usize = 8000;
+
+/// Lines at each end of a file that editors read modelines from.
+const MODELINE_LINES: usize = 5;
+
+#[derive(Debug, Args)]
+pub struct DetectArgs {
+    #[arg(value_name = "FILE", required = true, help = "Files to inspect")]
+    pub files: Vec<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DetectResponse {
+    pub files: Vec<DetectedFile>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DetectedFile {
+    #[serde(serialize_with = "crate::path_style::serialize_path")]
+    pub file: PathBuf,
+    /// Lowercased extension without the dot, as providers match it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extension: Option<String>,
+    /// Absent when every provider that could take the file is disabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub route: Option<ProviderRoute>,
+    /// A NUL byte within the first 8000 bytes.
+    pub binary: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shebang: Option<Shebang>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modeline: Option<Modeline>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header_language: Option<HeaderDecision>,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct Shebang {
+    pub line: String,
+    /// The program run, looked through `env`, such as `python3`.
+    pub interpreter: String,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct Modeline {
+    /// 1-based.
+    pub line: usize,
+    pub text: String,
+    /// The file type it declares, such as `python` for `vim: ft=python`.
+    pub language: String,
+}
+
+pub fn run_detect(
+    args: DetectArgs,
+    registry: &ProviderRegistry,
+) -> Result<DetectResponse, IdenteditError> {
+    let files = args
+        .files
+        .into_iter()
+        .map(|file| detect_file(registry, file))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(DetectResponse { files })
+}
+
+fn detect_file(registry: &ProviderRegistry, file: PathBuf) -> Result<DetectedFile, IdenteditError> {
+    let source = fs::read(&file).map_err(|error| IdenteditError::io(&file, error))?;
+    let binary = source[..source.len().min(BINARY_SNIFF_BYTES)].contains(&0);
+    let route = registry.provider_route(&file).ok();
+    let header_language = match route {
+        Some((provider, _)) if !binary => header_decision(provider, &source).ok().flatten(),
+        _ => None,
+    };
+    let text = (!binary).then(|| String::from_utf8_lossy(&source));
+    Ok(DetectedFile {
+        extension: extension(&file),
+        provider: route.map(|(provider, _)| provider.name()),
+        route: route.map(|(_, route)| route),
+        binary,
+        shebang: text.as_deref().and_then(shebang),
+        modeline: text.as_deref().and_then(modeline),
+        header_language,
+        file,
+    })
+}
+
+fn extension(file: &Path) -> Option<String> {
+    file.extension()
+        .and_then(|value| value.to_str())
+        .and_then(normalize_extension)
+}
+
+fn shebang(text: &str) -> Option<Shebang> {
+    let line = text.trim_start_matches('\u{feff}').lines().next()?;
+    let command = line.strip_prefix("#!")?;
+    let mut words = command.split_whitespace();
+    let mut program = words.next()?;
+    if program.rsplit('/').next() == Some("env") {
+        program = words.find(|word| !word.starts_with('-') && !word.contains('='))?;
+    }
+    Some(Shebang {
+        line: line.trim_end().to_string(),
+        interpreter: program.rsplit('/').next().unwrap_or(program).to_string(),
+    })
+}
+
+/// The first vim (`vim: ft=python`, `vim: set filetype=python:`) or emacs
+/// (`-*- mode: python -*-`, `-*- python -*-`) modeline in the first or last five lines.
+fn modeline(text: &str) -> Option<Modeline> {
+    let lines = text.lines().collect::<Vec<_>>();
+    let tail_start = lines
+        .len()
+        .saturating_sub(MODELINE_LINES)
+        .max(MODELINE_LINES);
+    (0..lines.len().min(MODELINE_LINES))
+        .chain(tail_start..lines.len())
+        .find_map(|index| {
+            let line = lines[index];
+            let language = emacs_mode(line).or_else(|| vim_filetype(line))?;
+            Some(Modeline {
+                line: index + 1,
+                text: line.trim().to_string(),
+                language,
+            })
+        })
+}
+
+fn emacs_mode(line: &str) -> Option<String> {
+    let (_, rest) = line.split_once("-*-")?;
+    let (variables, _) = rest.split_once("-*-")?;
+    let variables = variables.trim();
+    if !variables.contains(':') {
+        return non_empty(variables);
+    }
+    variables.split(';').find_map(|variable| {
+        let (name, value) = variable.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("mode")
+            .then(|| non_empty(value.trim()))
+            .flatten()
+    })
+}
+
+fn vim_filetype(line: &str) -> Option<String> {
+    let (_, settings) = ["vim:", "vi:", "ex:"]
+        .into_iter()
+        .find_map(|marker| line.split_once(marker))?;
+    let settings = settings.trim_start();
+    let settings = settings
+        .strip_prefix("set ")
+        .or_else(|| settings.strip_prefix("se "))
+        .unwrap_or(settings);
+    settings
+        .split(|character: char| character == ':' || character.is_whitespace())
+        .find_map(|setting| {
+            let (name, value) = setting.split_once('=')?;
+            matches!(name, "ft" | "filetype" | "syntax" | "syn")
+                .then(|| non_empty(value))
+                .flatten()
+        })
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    (!value.is_empty()).then(|| value.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{modeline, shebang};
+
+    #[test]
+    fn shebang_interpreter_looks_through_env() {
+        let found = shebang("#!/usr/bin/env -S python3 -u\nprint(1)\n").expect("shebang");
+        assert_eq!(found.interpreter, "python3");
+        assert_eq!(
+            shebang("#!/bin/bash\n").expect("shebang").interpreter,
+            "bash"
+        );
+        assert_eq!(shebang("echo hi\n#!/bin/sh\n"), None);
+    }
+
+    #[test]
+    fn modelines_are_read_from_either_end() {
+        let emacs = modeline("#!/bin/sh\n# -*- mode: Python; coding: utf-8 -*-\n").expect("emacs");
+        assert_eq!((emacs.line, emacs.language.as_str()), (2, "python"));
+
+        let mut text = "x\n".repeat(20);
+        text.push_str("# vim: set ft=ruby ts=2:\n");
+        let vim = modeline(&text).expect("vim");
+        assert_eq!((vim.line, vim.language.as_str()), (21, "ruby"));
+
+        let middle = format!("{}# vim: ft=ruby\n{}", "x\n".repeat(5), "x\n".repeat(5));
+        assert_eq!(modeline(&middle), None);
+    }
+}
@@ -0,0 +1,169 @@
+//! `identedit diff-handle`: a targeted staleness check for one stored handle.
+//!
+//! An identity covers the node's kind, name, and text, so any edit to the node retires it.
+//! Given the text the handle was read with, the successor is the node whose kind and name,
+//! combined with that old text, hash back to the stored identity; its current text is then
+//! diffed against the old one.
+
+use std::fs;
+use std::path::PathBuf;
+
+use clap::Args;
+use serde::Serialize;
+use similar::TextDiff;
+
+use crate::cli::read::ReadHandle;
+use crate::error::IdenteditError;
+use crate::handle::{LineIndex, SelectionHandle, compute_identity};
+use crate::hash::hash_text;
+use crate::path_style::render_path;
+use crate::provider::ProviderRegistry;
+use crate::transform::parse_handles_for_file;
+
+#[derive(Debug, Args)]
+pub struct DiffHandleArgs {
+    #[arg(
+        long,
+        value_name = "IDENTITY",
+        help = "Stored node identity (from read)"
+    )]
+    pub identity: String,
+    #[arg(
+        long = "expected-hash",
+        value_name = "HASH",
+        help = "Stored expected_old_hash of the node text"
+    )]
+    pub expected_hash: String,
+    #[arg(
+        long = "old-text",
+        value_name = "TEXT",
+        conflicts_with = "old_text_file",
+        help = "Node text the handle was read with; enables the successor lookup and diff"
+    )]
+    pub old_text: Option<String>,
+    #[arg(
+        long = "old-text-file",
+        value_name = "PATH",
+        help = "Read the old node text from PATH instead of --old-text"
+    )]
+    pub old_text_file: Option<PathBuf>,
+    #[arg(value_name = "FILE", help = "File containing the node")]
+    pub file: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HandleChange {
+    /// The identity still matches one node and its text hashes to the expected hash.
+    Unchanged,
+    /// The node is still there, but its text differs from the expected hash.
+    Changed,
+    /// No node has the identity, and no successor was found (none was searched for
+    /// without the old text).
+    Stale,
+    /// Several nodes match the identity or qualify as its successor.
+    Ambiguous,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiffHandleResponse {
+    #[serde(serialize_with = "crate::path_style::serialize_path")]
+    pub file: PathBuf,
+    pub identity: String,
+    pub status: HandleChange,
+    /// The node as it reads now, text included; absent when stale or ambiguous.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current: Option<Box<ReadHandle>>,
+    /// Unified diff of the old text against the current text; only for `changed` with
+    /// the old text supplied.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<String>,
+}
+
+pub fn run_diff_handle(
+    args: DiffHandleArgs,
+    registry: &ProviderRegistry,
+) -> Result<DiffHandleResponse, IdenteditError> {
+    let old_text = match (&args.old_text, &args.old_text_file) {
+        (Some(text), _) => Some(text.clone()),
+        (None, Some(path)) => {
+            Some(fs::read_to_string(path).map_err(|error| IdenteditError::io(path, error))?)
+        }
+        (None, None) => None,
+    };
+    if let Some(old_text) = &old_text
+        && hash_text(old_text) != args.expected_hash
+    {
+        return Err(IdenteditError::InvalidRequest {
+            message: format!(
+                "Old text hashes to '{}', not --expected-hash '{}'; pass the text the handle was read with",
+                hash_text(old_text),
+                args.expected_hash
+            ),
+        });
+    }
+
+    let source = fs::read(&args.file).map_err(|error| IdenteditError::io(&args.file, error))?;
+    let handles = parse_handles_for_file(&args.file, registry)?;
+    let same_identity = handles
+        .iter()
+        .filter(|handle| handle.identity == args.identity)
+        .collect::<Vec<_>>();
+    let (status, current) = match same_identity.as_slice() {
+        [handle] if handle.expected_old_hash == args.expected_hash => {
+            (HandleChange::Unchanged, Some(*handle))
+        }
+        [handle] => (HandleChange::Changed, Some(*handle)),
+        [_, _, ..] => (HandleChange::Ambiguous, None),
+        [] => match &old_text {
+            Some(old_text) => find_successor(&handles, &args.identity, old_text),
+            None => (HandleChange::Stale, None),
+        },
+    };
+
+    let diff = match (status, current, &old_text) {
+        (HandleChange::Changed, Some(handle), Some(old_text)) => {
+            let label = render_path(&args.file);
+            Some(
+                TextDiff::from_lines(old_text.as_str(), handle.text.as_str())
+                    .unified_diff()
+                    .header(&format!("a/{label}"), &format!("b/{label}"))
+                    .to_string(),
+            )
+        }
+        _ => None,
+    };
+    let lines = LineIndex::new(&source);
+    Ok(DiffHandleResponse {
+        file: args.file,
+        identity: args.identity,
+        status,
+        current: current.map(|handle| {
+            Box::new(ReadHandle::from_selection_handle(
+                handle.clone(),
+                &lines,
+                true,
+            ))
+        }),
+        diff,
+    })
+}
+
+/// The one node whose kind and name, hashed with `old_text`, give back `identity`.
+fn find_successor<'a>(
+    handles: &'a [SelectionHandle],
+    identity: &str,
+    old_text: &str,
+) -> (HandleChange, Option<&'a SelectionHandle>) {
+    let successors = handles
+        .iter()
+        .filter(|handle| {
+            compute_identity(&handle.kind, handle.name.as_deref(), old_text) == identity
+        })
+        .collect::<Vec<_>>();
+    match successors.as_slice() {
+        [] => (HandleChange::Stale, None),
+        [handle] => (HandleChange::Changed, Some(*handle)),
+        _ => (HandleChange::Ambiguous, None),
+    }
+}
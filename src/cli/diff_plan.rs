@@ -0,0 +1,456 @@
+//! Unified diff to edit plan conversion (`plan from-diff`).
+//!
+//! Each hunk becomes one line-range `replace` whose anchors are the first and last lines
+//! of the hunk's old side, so the context lines are hash-checked along with the removed
+//! lines when the plan is applied. Hunks without old lines (`-U0` insertions) become an
+//! `insert_after` on the preceding line, or a `file_start` insert at line 0.
+
+use std::path::{Path, PathBuf};
+
+use crate::changeset::{FileChange, MultiFileChangeset, OpKind, TransformTarget, hash_text};
+use crate::error::IdenteditError;
+use crate::hashline::{HashedLine, format_line_ref, show_hashed_lines};
+use crate::provider::ProviderRegistry;
+use crate::transform::{TransformInstruction, build_changeset};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct FileDiff {
+    pub(super) path: PathBuf,
+    pub(super) hunks: Vec<Hunk>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Hunk {
+    pub(super) header: String,
+    pub(super) old_start: usize,
+    pub(super) old_count: usize,
+    pub(super) new_start: usize,
+    pub(super) lines: Vec<HunkLine>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct HunkLine {
+    pub(super) tag: LineTag,
+    pub(super) text: String,
+    /// Cleared by a following `\ No newline at end of file` marker.
+    pub(super) newline: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum LineTag {
+    Context,
+    Removed,
+    Added,
+}
+
+impl Hunk {
+    fn old_lines(&self) -> impl Iterator<Item = &HunkLine> {
+        self.lines.iter().filter(|line| line.tag != LineTag::Added)
+    }
+
+    fn new_text(&self, newline: &str) -> String {
+        let mut text = String::new();
+        for line in self
+            .lines
+            .iter()
+            .filter(|line| line.tag != LineTag::Removed)
+        {
+            text.push_str(&line.text);
+            if line.newline {
+                text.push_str(newline);
+            }
+        }
+        text
+    }
+}
+
+pub(super) fn plan_from_diff(
+    diff_text: &str,
+    registry: &ProviderRegistry,
+) -> Result<MultiFileChangeset, IdenteditError> {
+    let file_diffs = parse_unified_diff(diff_text)?;
+    if file_diffs.is_empty() {
+        return Err(IdenteditError::InvalidRequest {
+            message: "Diff contains no file changes".to_string(),
+        });
+    }
+
+    let files = file_diffs
+        .iter()
+        .map(|file_diff| {
+            let source_text = std::fs::read_to_string(&file_diff.path)
+                .map_err(|error| IdenteditError::io(&file_diff.path, error))?;
+            let instructions = hunk_instructions(&file_diff.path, &source_text, &file_diff.hunks)?;
+            build_changeset(&file_diff.path, instructions, registry)
+        })
+        .collect::<Result<Vec<FileChange>, _>>()?;
+
+    Ok(MultiFileChangeset {
+        files,
+        transaction: Default::default(),
+        correlation_id: None,
+    })
+}
+
+fn hunk_instructions(
+    file: &Path,
+    source_text: &str,
+    hunks: &[Hunk],
+) -> Result<Vec<TransformInstruction>, IdenteditError> {
+    let lines = show_hashed_lines(source_text);
+    let newline = if source_text.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+
+    let mut instructions = Vec::with_capacity(hunks.len());
+    let mut next_free_line = 1;
+    for hunk in hunks {
+        let start = locate_hunk(file, &lines, hunk, next_free_line)?;
+        let new_text = hunk.new_text(newline);
+        let instruction = if hunk.old_count == 0 {
+            match start.checked_sub(1).map(|index| &lines[index]) {
+                Some(previous) => TransformInstruction {
+                    target: TransformTarget::Line {
+                        anchor: format_line_ref(previous.line, &previous.hash),
+                        end_anchor: None,
+                    },
+                    op: OpKind::InsertAfter { new_text },
+                },
+                None => TransformInstruction {
+                    target: TransformTarget::FileStart {
+                        expected_file_hash: hash_text(source_text),
+                    },
+                    op: OpKind::Insert { new_text },
+                },
+            }
+        } else {
+            let first = &lines[start];
+            let last = &lines[start + hunk.old_count - 1];
+            TransformInstruction {
+                target: TransformTarget::Line {
+                    anchor: format_line_ref(first.line, &first.hash),
+                    end_anchor: (hunk.old_count > 1)
+                        .then(|| format_line_ref(last.line, &last.hash)),
+                },
+                op: OpKind::Replace { new_text },
+            }
+        };
+        instructions.push(instruction);
+        next_free_line = start + hunk.old_count + 1;
+    }
+    Ok(instructions)
+}
+
+/// Zero-based index of the first old line of `hunk` in `lines`. The position stated in
+/// the header is tried first, then the nearest offset where every context and removed
+/// line matches, never before `min_line` (the line after the previous hunk).
+fn locate_hunk(
+    file: &Path,
+    lines: &[HashedLine],
+    hunk: &Hunk,
+    min_line: usize,
+) -> Result<usize, IdenteditError> {
+    // For an insertion-only hunk the header names the line it follows.
+    let stated = if hunk.old_count == 0 {
+        hunk.old_start
+    } else {
+        hunk.old_start.saturating_sub(1)
+    };
+    let lowest = min_line.saturating_sub(1);
+    let highest = lines.len().saturating_sub(hunk.old_count);
+    let matches_at = |start: usize| {
+        start + hunk.old_count <= lines.len()
+            && hunk
+                .old_lines()
+                .zip(&lines[start..])
+                .all(|(expected, actual)| expected.text == actual.content)
+    };
+
+    if hunk.old_count == 0 {
+        // Nothing to match against; the line it follows is anchored by hash instead.
+        if (lowest..=lines.len()).contains(&stated) {
+            return Ok(stated);
+        }
+    } else {
+        for distance in 0..=lines.len() {
+            let candidates = [stated.checked_sub(distance), stated.checked_add(distance)];
+            for start in candidates.into_iter().flatten() {
+                if (lowest..=highest).contains(&start) && matches_at(start) {
+                    return Ok(start);
+                }
+            }
+        }
+    }
+
+    Err(IdenteditError::InvalidRequest {
+        message: format!(
+            "Hunk '{}' does not match the current contents of '{}'",
+            hunk.header,
+            file.display()
+        ),
+    })
+}
+
+fn parse_unified_diff(diff_text: &str) -> Result<Vec<FileDiff>, IdenteditError> {
+    parse_unified_diff_with(diff_text, |index, old_header, new_header| {
+        file_path_from_headers(index, old_header, new_header).map(Some)
+    })
+}
+
+/// Files of a `git diff` as they are in the working tree: created files are kept, renamed
+/// files take their new path, and deleted files are dropped.
+pub(super) fn parse_working_tree_diff(diff_text: &str) -> Result<Vec<FileDiff>, IdenteditError> {
+    parse_unified_diff_with(diff_text, |_, old_header, new_header| {
+        let old_path = header_path(old_header);
+        let new_path = header_path(new_header);
+        if new_path == "/dev/null" {
+            return Ok(None);
+        }
+        let prefixed = old_path == "/dev/null" || old_path.starts_with("a/");
+        Ok(Some(PathBuf::from(
+            new_path
+                .strip_prefix("b/")
+                .filter(|_| prefixed)
+                .unwrap_or(new_path),
+        )))
+    })
+}
+
+/// Parses every file of a unified diff whose `---`/`+++` headers `file_path` maps to a
+/// path; hunks of files it maps to `None` are read and dropped.
+fn parse_unified_diff_with(
+    diff_text: &str,
+    file_path: impl Fn(usize, &str, &str) -> Result<Option<PathBuf>, IdenteditError>,
+) -> Result<Vec<FileDiff>, IdenteditError> {
+    let lines = diff_text.lines().collect::<Vec<_>>();
+    let mut files: Vec<FileDiff> = Vec::new();
+    let mut skipping = false;
+    let mut index = 0;
+
+    while index < lines.len() {
+        let line = lines[index];
+        if line.starts_with("Binary files ") || line == "GIT binary patch" {
+            return Err(diff_error(index, "binary diffs are not supported"));
+        }
+        if let Some(old_header) = line.strip_prefix("--- ")
+            && let Some(new_header) = lines
+                .get(index + 1)
+                .and_then(|next| next.strip_prefix("+++ "))
+        {
+            let path = file_path(index, old_header, new_header)?;
+            skipping = path.is_none();
+            files.extend(path.map(|path| FileDiff {
+                path,
+                hunks: Vec::new(),
+            }));
+            index += 2;
+            continue;
+        }
+        if line.starts_with("@@ ") {
+            let (hunk, consumed) = parse_hunk(&lines, index)?;
+            if !skipping {
+                let file = files.last_mut().ok_or_else(|| {
+                    diff_error(index, "hunk appears before any '---'/'+++' header")
+                })?;
+                file.hunks.push(hunk);
+            }
+            index += consumed;
+            continue;
+        }
+        index += 1;
+    }
+
+    Ok(files
+        .into_iter()
+        .filter(|file| !file.hunks.is_empty())
+        .collect())
+}
+
+fn file_path_from_headers(
+    index: usize,
+    old_header: &str,
+    new_header: &str,
+) -> Result<PathBuf, IdenteditError> {
+    let old_path = header_path(old_header);
+    let new_path = header_path(new_header);
+    if old_path == "/dev/null" || new_path == "/dev/null" {
+        return Err(diff_error(
+            index,
+            "creating or deleting files is not supported; diff existing files only",
+        ));
+    }
+
+    let (old_path, new_path) = match (old_path.strip_prefix("a/"), new_path.strip_prefix("b/")) {
+        (Some(old_path), Some(new_path)) => (old_path, new_path),
+        _ => (old_path, new_path),
+    };
+    if old_path != new_path {
+        return Err(diff_error(
+            index,
+            &format!("renames are not supported ('{old_path}' -> '{new_path}')"),
+        ));
+    }
+    Ok(PathBuf::from(new_path))
+}
+
+/// Path part of a `---`/`+++` header, without a trailing tab-separated timestamp.
+fn header_path(header: &str) -> &str {
+    let path = header.split('\t').next().unwrap_or_default().trim_end();
+    path.strip_prefix('"')
+        .and_then(|quoted| quoted.strip_suffix('"'))
+        .unwrap_or(path)
+}
+
+fn parse_hunk(lines: &[&str], start: usize) -> Result<(Hunk, usize), IdenteditError> {
+    let header = lines[start];
+    let (old_start, old_count, new_start, new_count) = parse_hunk_header(header)
+        .ok_or_else(|| diff_error(start, &format!("malformed hunk header '{header}'")))?;
+
+    let mut hunk_lines: Vec<HunkLine> = Vec::new();
+    let (mut old_seen, mut new_seen) = (0, 0);
+    let mut index = start + 1;
+    while old_seen < old_count || new_seen < new_count {
+        let Some(&line) = lines.get(index) else {
+            return Err(diff_error(index, &format!("hunk '{header}' is truncated")));
+        };
+        let (tag, text) = match line.as_bytes().first() {
+            Some(b' ') => (LineTag::Context, &line[1..]),
+            // Some tools drop the space of an empty context line.
+            None => (LineTag::Context, ""),
+            Some(b'-') => (LineTag::Removed, &line[1..]),
+            Some(b'+') => (LineTag::Added, &line[1..]),
+            Some(b'\\') => {
+                clear_last_newline(&mut hunk_lines);
+                index += 1;
+                continue;
+            }
+            _ => {
+                return Err(diff_error(
+                    index,
+                    &format!("unexpected line inside hunk '{header}'"),
+                ));
+            }
+        };
+        if tag != LineTag::Added {
+            old_seen += 1;
+        }
+        if tag != LineTag::Removed {
+            new_seen += 1;
+        }
+        hunk_lines.push(HunkLine {
+            tag,
+            text: text.to_string(),
+            newline: true,
+        });
+        index += 1;
+    }
+    if old_seen != old_count || new_seen != new_count {
+        return Err(diff_error(
+            start,
+            &format!("hunk '{header}' line counts do not match its body"),
+        ));
+    }
+    if lines.get(index).is_some_and(|line| line.starts_with('\\')) {
+        clear_last_newline(&mut hunk_lines);
+        index += 1;
+    }
+
+    Ok((
+        Hunk {
+            header: header.to_string(),
+            old_start,
+            old_count,
+            new_start,
+            lines: hunk_lines,
+        },
+        index - start,
+    ))
+}
+
+fn clear_last_newline(lines: &mut [HunkLine]) {
+    if let Some(last) = lines.last_mut() {
+        last.newline = false;
+    }
+}
+
+/// `@@ -OLD[,COUNT] +NEW[,COUNT] @@ ...` to (old start, old count, new start, new count).
+fn parse_hunk_header(header: &str) -> Option<(usize, usize, usize, usize)> {
+    let ranges = header.strip_prefix("@@ ")?;
+    let (ranges, _) = ranges.split_once(" @@")?;
+    let (old_range, new_range) = ranges.split_once(' ')?;
+    let (old_start, old_count) = parse_hunk_range(old_range.strip_prefix('-')?)?;
+    let (new_start, new_count) = parse_hunk_range(new_range.strip_prefix('+')?)?;
+    Some((old_start, old_count, new_start, new_count))
+}
+
+fn parse_hunk_range(range: &str) -> Option<(usize, usize)> {
+    match range.split_once(',') {
+        Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+fn diff_error(index: usize, detail: &str) -> IdenteditError {
+    IdenteditError::InvalidRequest {
+        message: format!("Invalid unified diff at line {}: {detail}", index + 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{LineTag, parse_hunk_header, parse_unified_diff, parse_working_tree_diff};
+
+    #[test]
+    fn parse_hunk_header_defaults_missing_counts_to_one() {
+        assert_eq!(
+            parse_hunk_header("@@ -3,4 +3,5 @@ fn main"),
+            Some((3, 4, 3, 5))
+        );
+        assert_eq!(parse_hunk_header("@@ -7 +7 @@"), Some((7, 1, 7, 1)));
+        assert_eq!(parse_hunk_header("@@ -5,0 +6,2 @@"), Some((5, 0, 6, 2)));
+        assert_eq!(parse_hunk_header("@@ bogus @@"), None);
+    }
+
+    #[test]
+    fn parse_unified_diff_reads_git_headers_and_no_newline_markers() {
+        let diff = "diff --git a/src/lib.rs b/src/lib.rs\nindex 1..2 100644\n--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1,2 +1,2 @@\n fn a() {}\n-fn b() {}\n\\ No newline at end of file\n+fn c() {}\n";
+        let files = parse_unified_diff(diff).expect("diff should parse");
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, PathBuf::from("src/lib.rs"));
+        let lines = &files[0].hunks[0].lines;
+        assert_eq!(
+            lines.iter().map(|line| line.tag).collect::<Vec<_>>(),
+            vec![LineTag::Context, LineTag::Removed, LineTag::Added]
+        );
+        assert!(!lines[1].newline, "marker applies to the removed line");
+        assert!(lines[2].newline);
+    }
+
+    #[test]
+    fn parse_unified_diff_rejects_file_creation_and_truncated_hunks() {
+        parse_unified_diff("--- /dev/null\n+++ b/new.rs\n@@ -0,0 +1 @@\n+x\n")
+            .expect_err("file creation must fail");
+        parse_unified_diff("--- a.rs\n+++ a.rs\n@@ -1,2 +1,2 @@\n x\n")
+            .expect_err("truncated hunk must fail");
+    }
+
+    #[test]
+    fn working_tree_diff_keeps_created_and_renamed_files_and_drops_deleted_ones() {
+        let diff = "--- /dev/null\n+++ b/new.rs\n@@ -0,0 +1 @@\n+x\n--- a/old.rs\n+++ /dev/null\n@@ -1 +0,0 @@\n-y\n--- a/from.rs\n+++ b/to.rs\n@@ -1 +1 @@\n-a\n+b\n";
+        let files = parse_working_tree_diff(diff).expect("diff should parse");
+
+        assert_eq!(
+            files
+                .iter()
+                .map(|file| file.path.clone())
+                .collect::<Vec<_>>(),
+            vec![PathBuf::from("new.rs"), PathBuf::from("to.rs")]
+        );
+        assert_eq!(files[0].hunks[0].new_start, 1);
+    }
+}
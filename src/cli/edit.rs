@@ -4,6 +4,8 @@ use clap::Args;
 
 use crate::changeset::MultiFileChangeset;
 use crate::error::IdenteditError;
+use crate::provider::ProviderRegistry;
+use crate::text_encoding::TextEncoding;
 
 #[derive(Debug, Args)]
 pub struct EditArgs {
@@ -21,6 +23,57 @@ pub struct EditArgs {
     pub replace: Option<String>,
     #[arg(long, help = "Delete the target node (--identity mode)")]
     pub delete: bool,
+    #[arg(
+        long,
+        value_name = "PROGRAM",
+        help = "Pipe each selected node as a JSON handle to PROGRAM and use its stdout as the replacement"
+    )]
+    pub codemod: Option<PathBuf>,
+    #[arg(
+        long = "codemod-arg",
+        value_name = "ARG",
+        requires = "codemod",
+        allow_hyphen_values = true,
+        help = "Argument passed to the --codemod program (repeatable)"
+    )]
+    pub codemod_args: Vec<String>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with = "codemod",
+        help = "Rhai script that computes each selected node's replacement"
+    )]
+    pub script: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "KIND",
+        help = "Select nodes of this kind for --codemod/--script (repeatable)"
+    )]
+    pub kind: Vec<String>,
+    #[arg(
+        long,
+        value_name = "GLOB",
+        help = "Select nodes whose name matches GLOB for --codemod/--script"
+    )]
+    pub name: Option<String>,
+    #[arg(
+        long = "and",
+        value_name = "SELECTOR",
+        help = "JSON selector every node picked for --codemod/--script must also match (repeatable)"
+    )]
+    pub and: Vec<String>,
+    #[arg(
+        long = "or",
+        value_name = "SELECTOR",
+        help = "JSON selector of which nodes picked for --codemod/--script must match one (repeatable)"
+    )]
+    pub or: Vec<String>,
+    #[arg(
+        long = "not",
+        value_name = "SELECTOR",
+        help = "JSON selector nodes picked for --codemod/--script must not match (repeatable)"
+    )]
+    pub not: Vec<String>,
     #[arg(long, help = "Read edit request JSON from stdin")]
     pub json: bool,
     #[arg(
@@ -28,6 +81,13 @@ pub struct EditArgs {
         help = "Emit verbose preview fields (old_text) instead of compact fields"
     )]
     pub verbose: bool,
+    #[arg(
+        long = "text-encoding",
+        value_enum,
+        default_value_t = TextEncoding::Utf8,
+        help = "Encoding of --replace and of op text fields in --json input (utf8|base64)"
+    )]
+    pub text_encoding: TextEncoding,
     #[arg(
         value_name = "FILE",
         help = "Input file in flag mode; omit when using --json"
@@ -35,13 +95,28 @@ pub struct EditArgs {
     pub file: Option<PathBuf>,
 }
 
-pub fn run_edit(args: EditArgs) -> Result<MultiFileChangeset, IdenteditError> {
-    crate::cli::edit_build::run_edit_build(crate::cli::edit_build::EditBuildArgs {
-        identity: args.identity,
-        replace: args.replace,
-        delete: args.delete,
-        json: args.json,
-        verbose: args.verbose,
-        file: args.file,
-    })
+pub fn run_edit(
+    args: EditArgs,
+    registry: &ProviderRegistry,
+) -> Result<MultiFileChangeset, IdenteditError> {
+    crate::cli::edit_build::run_edit_build(
+        crate::cli::edit_build::EditBuildArgs {
+            identity: args.identity,
+            replace: args.replace,
+            delete: args.delete,
+            codemod: args.codemod,
+            codemod_args: args.codemod_args,
+            script: args.script,
+            kind: args.kind,
+            name: args.name,
+            and: args.and,
+            or: args.or,
+            not: args.not,
+            json: args.json,
+            verbose: args.verbose,
+            text_encoding: args.text_encoding,
+            file: args.file,
+        },
+        registry,
+    )
 }
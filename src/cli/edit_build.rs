@@ -10,11 +10,17 @@ use crate::changeset::{FileChange, MultiFileChangeset, OpKind, TransformTarget,
 use crate::error::IdenteditError;
 use crate::handle::SelectionHandle;
 use crate::handle::Span;
+use crate::provider::ProviderRegistry;
+use crate::selector::{Position, Selector};
+use crate::text_encoding::TextEncoding;
 use crate::transform::{
     TransformInstruction, build_changeset, build_delete_changeset, build_replace_changeset,
     parse_handles_for_file, resolve_target_in_handles,
 };
 
+use super::edit_codemod::{Codemod, build_codemod_changeset};
+use super::edit_fan_out::{FanOutWire, expand_fan_out};
+
 #[derive(Debug, Args)]
 pub struct EditBuildArgs {
     #[arg(
@@ -31,6 +37,57 @@ pub struct EditBuildArgs {
     pub replace: Option<String>,
     #[arg(long, help = "Delete the target node (--identity mode)")]
     pub delete: bool,
+    #[arg(
+        long,
+        value_name = "PROGRAM",
+        help = "Pipe each selected node as a JSON handle to PROGRAM and use its stdout as the replacement"
+    )]
+    pub codemod: Option<PathBuf>,
+    #[arg(
+        long = "codemod-arg",
+        value_name = "ARG",
+        requires = "codemod",
+        allow_hyphen_values = true,
+        help = "Argument passed to the --codemod program (repeatable)"
+    )]
+    pub codemod_args: Vec<String>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with = "codemod",
+        help = "Rhai script that computes each selected node's replacement"
+    )]
+    pub script: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "KIND",
+        help = "Select nodes of this kind for --codemod/--script (repeatable)"
+    )]
+    pub kind: Vec<String>,
+    #[arg(
+        long,
+        value_name = "GLOB",
+        help = "Select nodes whose name matches GLOB for --codemod/--script"
+    )]
+    pub name: Option<String>,
+    #[arg(
+        long = "and",
+        value_name = "SELECTOR",
+        help = "JSON selector every node picked for --codemod/--script must also match (repeatable)"
+    )]
+    pub and: Vec<String>,
+    #[arg(
+        long = "or",
+        value_name = "SELECTOR",
+        help = "JSON selector of which nodes picked for --codemod/--script must match one (repeatable)"
+    )]
+    pub or: Vec<String>,
+    #[arg(
+        long = "not",
+        value_name = "SELECTOR",
+        help = "JSON selector nodes picked for --codemod/--script must not match (repeatable)"
+    )]
+    pub not: Vec<String>,
     #[arg(long, help = "Read edit request JSON from stdin")]
     pub json: bool,
     #[arg(
@@ -38,6 +95,13 @@ pub struct EditBuildArgs {
         help = "Emit verbose preview fields (old_text) instead of compact fields"
     )]
     pub verbose: bool,
+    #[arg(
+        long = "text-encoding",
+        value_enum,
+        default_value_t = TextEncoding::Utf8,
+        help = "Encoding of --replace and of op text fields in --json input (utf8|base64)"
+    )]
+    pub text_encoding: TextEncoding,
     #[arg(
         value_name = "FILE",
         help = "Input file in flag mode; omit when using --json"
@@ -49,7 +113,10 @@ pub struct EditBuildArgs {
 #[serde(deny_unknown_fields)]
 struct StdinEditRequestWire {
     command: String,
-    #[serde(default)]
+    #[serde(
+        default,
+        deserialize_with = "crate::path_style::deserialize_optional_path"
+    )]
     file: Option<PathBuf>,
     #[serde(default)]
     operations: Option<Vec<StdinEditOperationWire>>,
@@ -57,11 +124,17 @@ struct StdinEditRequestWire {
     handle_table: Option<StdinHandleTableWire>,
     #[serde(default)]
     files: Option<Vec<StdinEditFileWire>>,
+    #[serde(default)]
+    fan_out: Option<FanOutWire>,
+    /// Carried into the plan, so the apply and verify responses echo it too.
+    #[serde(default)]
+    correlation_id: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(deny_unknown_fields)]
 struct StdinEditFileWire {
+    #[serde(deserialize_with = "crate::path_style::deserialize_path")]
     file: PathBuf,
     operations: Vec<StdinEditOperationWire>,
     #[serde(default)]
@@ -77,7 +150,7 @@ struct StdinEditFileRequest {
 
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
-enum StdinEditOp {
+pub(super) enum StdinEditOp {
     Replace {
         new_text: String,
     },
@@ -109,10 +182,12 @@ enum StdinEditOp {
         destination: Value,
     },
     MoveToBefore {
+        #[serde(deserialize_with = "crate::path_style::deserialize_path")]
         destination_file: PathBuf,
         destination: Value,
     },
     MoveToAfter {
+        #[serde(deserialize_with = "crate::path_style::deserialize_path")]
         destination_file: PathBuf,
         destination: Value,
     },
@@ -154,6 +229,17 @@ struct HandleRefTargetWire {
     r#ref: String,
 }
 
+/// The first or last node a selector matches, for inserts relative to nodes whose
+/// identity the caller does not know, such as after the last `use` declaration.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PositionTargetWire {
+    #[serde(rename = "type")]
+    _target_type: String,
+    position: Position,
+    selector: Selector,
+}
+
 #[derive(Debug)]
 enum ParsedOperationKind {
     Canonical(OpKind),
@@ -218,12 +304,13 @@ impl NormalizeState {
         &mut self,
         file: &Path,
         target: &TransformTarget,
+        registry: &ProviderRegistry,
     ) -> Result<SelectionHandle, IdenteditError> {
         let key = file.to_path_buf();
         let handles = if let Some(existing) = self.handle_cache.get(&key) {
             existing.clone()
         } else {
-            let parsed = parse_handles_for_file(file)?;
+            let parsed = parse_handles_for_file(file, registry)?;
             self.handle_cache.insert(key.clone(), parsed.clone());
             parsed
         };
@@ -237,6 +324,7 @@ impl NormalizeState {
         destination_file: PathBuf,
         destination_target: TransformTarget,
         insert_before: bool,
+        registry: &ProviderRegistry,
     ) -> Result<(), IdenteditError> {
         if !matches!(source_target, TransformTarget::Node { .. }) {
             return Err(IdenteditError::InvalidRequest {
@@ -254,10 +342,11 @@ impl NormalizeState {
             });
         }
 
-        let source_handle = self.resolve_move_endpoint(source_file, &source_target)?;
+        let source_handle = self.resolve_move_endpoint(source_file, &source_target, registry)?;
         let moved_text = source_handle.text;
 
-        let _ = self.resolve_move_endpoint(destination_file.as_path(), &destination_target)?;
+        let _ =
+            self.resolve_move_endpoint(destination_file.as_path(), &destination_target, registry)?;
 
         self.push_instruction_for_file(
             source_file.to_path_buf(),
@@ -286,14 +375,71 @@ impl NormalizeState {
     }
 }
 
-pub fn run_edit_build(args: EditBuildArgs) -> Result<MultiFileChangeset, IdenteditError> {
+pub fn run_edit_build(
+    args: EditBuildArgs,
+    registry: &ProviderRegistry,
+) -> Result<MultiFileChangeset, IdenteditError> {
     if args.json {
-        return run_edit_json_mode(args.verbose);
+        if args.codemod.is_some() || args.script.is_some() {
+            return Err(IdenteditError::InvalidRequest {
+                message:
+                    "--codemod and --script are flag-mode options and cannot be combined with --json"
+                        .to_string(),
+            });
+        }
+        return run_edit_json_mode(args.verbose, args.text_encoding, registry);
     }
 
     let file = args.file.ok_or_else(|| IdenteditError::InvalidRequest {
         message: "FILE is required unless --json mode is enabled".to_string(),
     })?;
+    if let Some(program) = args.codemod.as_ref().or(args.script.as_ref()) {
+        if args.replace.is_some() || args.delete {
+            return Err(IdenteditError::InvalidRequest {
+                message: "--codemod/--script cannot be combined with --replace or --delete"
+                    .to_string(),
+            });
+        }
+        if args.identity.is_none()
+            && args.kind.is_empty()
+            && args.name.is_none()
+            && args.and.is_empty()
+            && args.or.is_empty()
+        {
+            return Err(IdenteditError::InvalidRequest {
+                message:
+                    "--codemod/--script needs a selector: --identity, --kind, --name, --and, or --or"
+                        .to_string(),
+            });
+        }
+        let selector = selector_from_flags(args.kind, args.name, &args.and, &args.or, &args.not)?;
+        let codemod = Codemod {
+            program,
+            args: &args.codemod_args,
+            script: args.script.is_some(),
+        };
+        let mut changeset = build_codemod_changeset(
+            &file,
+            &codemod,
+            args.identity.as_deref(),
+            &selector,
+            registry,
+        )?;
+        apply_preview_mode(&mut changeset, args.verbose);
+        return Ok(changeset);
+    }
+    if !args.kind.is_empty()
+        || args.name.is_some()
+        || !args.and.is_empty()
+        || !args.or.is_empty()
+        || !args.not.is_empty()
+    {
+        return Err(IdenteditError::InvalidRequest {
+            message:
+                "--kind, --name, --and, --or, and --not select nodes for --codemod or --script"
+                    .to_string(),
+        });
+    }
     let identity = args
         .identity
         .ok_or_else(|| IdenteditError::InvalidRequest {
@@ -307,14 +453,15 @@ pub fn run_edit_build(args: EditBuildArgs) -> Result<MultiFileChangeset, Idented
     }
 
     if let Some(replacement) = args.replace {
-        let file_change = build_replace_changeset(&file, &identity, replacement)?;
+        let replacement = args.text_encoding.decode("--replace", replacement)?;
+        let file_change = build_replace_changeset(&file, &identity, replacement, registry)?;
         let mut changeset = wrap_single_file(file_change);
         apply_preview_mode(&mut changeset, args.verbose);
         return Ok(changeset);
     }
 
     if args.delete {
-        let file_change = build_delete_changeset(&file, &identity)?;
+        let file_change = build_delete_changeset(&file, &identity, registry)?;
         let mut changeset = wrap_single_file(file_change);
         apply_preview_mode(&mut changeset, args.verbose);
         return Ok(changeset);
@@ -325,14 +472,56 @@ pub fn run_edit_build(args: EditBuildArgs) -> Result<MultiFileChangeset, Idented
     })
 }
 
-fn run_edit_json_mode(verbose: bool) -> Result<MultiFileChangeset, IdenteditError> {
+/// Lowers the flag-mode filters into one selector: nodes of a `--kind` named like `--name`
+/// that match every `--and`, at least one `--or`, and none of the `--not` selectors.
+fn selector_from_flags(
+    kinds: Vec<String>,
+    name: Option<String>,
+    and: &[String],
+    or: &[String],
+    not: &[String],
+) -> Result<Selector, IdenteditError> {
+    let mut not = parse_selector_flags("--not", not)?;
+    let not = match not.len() {
+        0 => None,
+        1 => not.pop().map(Box::new),
+        _ => Some(Box::new(Selector {
+            or: not,
+            ..Selector::default()
+        })),
+    };
+    Ok(Selector {
+        kinds,
+        name_pattern: name,
+        and: parse_selector_flags("--and", and)?,
+        or: parse_selector_flags("--or", or)?,
+        not,
+        ..Selector::default()
+    })
+}
+
+fn parse_selector_flags(flag: &str, values: &[String]) -> Result<Vec<Selector>, IdenteditError> {
+    values
+        .iter()
+        .map(|value| {
+            serde_json::from_str(value).map_err(|error| IdenteditError::InvalidRequest {
+                message: format!("{flag} expects a JSON selector: {error}"),
+            })
+        })
+        .collect()
+}
+
+fn run_edit_json_mode(
+    verbose: bool,
+    text_encoding: TextEncoding,
+    registry: &ProviderRegistry,
+) -> Result<MultiFileChangeset, IdenteditError> {
     let mut request_body = String::new();
     std::io::stdin()
         .read_to_string(&mut request_body)
         .map_err(|error| IdenteditError::StdinRead { source: error })?;
 
-    let request: StdinEditRequestWire = serde_json::from_str(&request_body)
-        .map_err(|error| IdenteditError::InvalidJsonRequest { source: error })?;
+    let request: StdinEditRequestWire = text_encoding.parse_request(&request_body)?;
 
     if request.command != "edit" {
         return Err(IdenteditError::InvalidRequest {
@@ -342,9 +531,37 @@ fn run_edit_json_mode(verbose: bool) -> Result<MultiFileChangeset, IdenteditErro
             ),
         });
     }
+    let correlation_id = request.correlation_id.clone();
+    build_edit_plan(request, verbose, registry)
+        .map_err(|error| error.with_correlation_id(correlation_id.as_deref()))
+}
 
-    let file_requests = parse_stdin_edit_shape(request)?;
-    let normalized_buckets = normalize_edit_file_requests(file_requests)?;
+/// The part of `run_edit_json_mode` after the request is parsed, whose errors echo its
+/// `correlation_id`.
+fn build_edit_plan(
+    mut request: StdinEditRequestWire,
+    verbose: bool,
+    registry: &ProviderRegistry,
+) -> Result<MultiFileChangeset, IdenteditError> {
+    let correlation_id = request.correlation_id.clone();
+    let normalized_buckets = match request.fan_out.take() {
+        Some(fan_out) => {
+            if request.file.is_some()
+                || request.operations.is_some()
+                || request.handle_table.is_some()
+                || request.files.is_some()
+            {
+                return Err(IdenteditError::InvalidRequest {
+                    message: "edit JSON field 'fan_out' cannot be combined with 'file', 'operations', 'handle_table', or 'files'".to_string(),
+                });
+            }
+            expand_fan_out(fan_out, registry)?
+                .into_iter()
+                .map(|(file, instructions)| FileInstructionBucket { file, instructions })
+                .collect()
+        }
+        None => normalize_edit_file_requests(parse_stdin_edit_shape(request)?, registry)?,
+    };
     let mut files = Vec::with_capacity(normalized_buckets.len());
     for bucket in normalized_buckets {
         if bucket.instructions.is_empty() {
@@ -353,13 +570,18 @@ fn run_edit_json_mode(verbose: bool) -> Result<MultiFileChangeset, IdenteditErro
                 operations: Vec::new(),
             });
         } else {
-            files.push(build_changeset(&bucket.file, bucket.instructions)?);
+            files.push(build_changeset(
+                &bucket.file,
+                bucket.instructions,
+                registry,
+            )?);
         }
     }
 
     let mut changeset = MultiFileChangeset {
         files,
         transaction: Default::default(),
+        correlation_id,
     };
     apply_preview_mode(&mut changeset, verbose);
     Ok(changeset)
@@ -367,6 +589,7 @@ fn run_edit_json_mode(verbose: bool) -> Result<MultiFileChangeset, IdenteditErro
 
 fn normalize_edit_file_requests(
     file_requests: Vec<StdinEditFileRequest>,
+    registry: &ProviderRegistry,
 ) -> Result<Vec<FileInstructionBucket>, IdenteditError> {
     let mut state = NormalizeState::default();
 
@@ -379,7 +602,8 @@ fn normalize_edit_file_requests(
             continue;
         }
         for operation in file_request.operations {
-            let parsed = parse_edit_operation(operation, handle_table.as_ref())?;
+            let parsed =
+                parse_edit_operation(operation, &source_file, handle_table.as_ref(), registry)?;
             match parsed.op {
                 ParsedOperationKind::Canonical(op) => state.push_instruction_for_file(
                     source_file.clone(),
@@ -397,6 +621,7 @@ fn normalize_edit_file_requests(
                     destination_file,
                     destination,
                     true,
+                    registry,
                 )?,
                 ParsedOperationKind::MoveToAfter {
                     destination_file,
@@ -407,6 +632,7 @@ fn normalize_edit_file_requests(
                     destination_file,
                     destination,
                     false,
+                    registry,
                 )?,
             }
         }
@@ -452,9 +678,8 @@ fn parse_stdin_edit_shape(
         let files = request.files.expect("checked has_batch");
         if files.is_empty() {
             return Err(IdenteditError::InvalidRequest {
-                message:
-                    "edit JSON request field 'files' must contain at least one file entry"
-                        .to_string(),
+                message: "edit JSON request field 'files' must contain at least one file entry"
+                    .to_string(),
             });
         }
         return Ok(files
@@ -494,12 +719,15 @@ fn wrap_single_file(file_change: FileChange) -> MultiFileChangeset {
     MultiFileChangeset {
         files: vec![file_change],
         transaction: Default::default(),
+        correlation_id: None,
     }
 }
 
 fn parse_edit_operation(
     operation: StdinEditOperationWire,
+    file: &Path,
     handle_table: Option<&StdinHandleTableWire>,
+    registry: &ProviderRegistry,
 ) -> Result<ParsedEditInstruction, IdenteditError> {
     if let Some(target_wire) = operation.target {
         if operation.identity.is_some()
@@ -512,10 +740,10 @@ fn parse_edit_operation(
             });
         }
 
-        let target = parse_edit_target_from_wire(target_wire, handle_table)?;
+        let target = parse_edit_target_from_wire(target_wire, file, handle_table, registry)?;
         return Ok(ParsedEditInstruction {
             target,
-            op: parse_stdin_operation_kind(operation.op, handle_table)?,
+            op: parse_stdin_operation_kind(operation.op, file, handle_table, registry)?,
         });
     }
 
@@ -538,13 +766,15 @@ fn parse_edit_operation(
 
     Ok(ParsedEditInstruction {
         target: TransformTarget::node(identity, kind, operation.span_hint, expected_old_hash),
-        op: parse_stdin_operation_kind(operation.op, handle_table)?,
+        op: parse_stdin_operation_kind(operation.op, file, handle_table, registry)?,
     })
 }
 
 fn parse_edit_target_from_wire(
     target_wire: Value,
+    file: &Path,
     handle_table: Option<&StdinHandleTableWire>,
+    registry: &ProviderRegistry,
 ) -> Result<TransformTarget, IdenteditError> {
     let target_type = target_wire.get("type").and_then(Value::as_str);
     if target_type == Some("handle_ref") {
@@ -583,6 +813,30 @@ fn parse_edit_target_from_wire(
         ));
     }
 
+    if target_type == Some("position") {
+        let position: PositionTargetWire =
+            serde_json::from_value(target_wire).map_err(|error| {
+                IdenteditError::InvalidRequest {
+                    message: format!("invalid position target payload: {error}"),
+                }
+            })?;
+        let handle = position
+            .selector
+            .pick(parse_handles_for_file(file, registry)?, position.position)?
+            .ok_or_else(|| IdenteditError::InvalidRequest {
+                message: format!(
+                    "No node in '{}' matches the position target's selector",
+                    file.display()
+                ),
+            })?;
+        return Ok(TransformTarget::node(
+            handle.identity,
+            handle.kind,
+            Some(handle.span),
+            handle.expected_old_hash,
+        ));
+    }
+
     serde_json::from_value::<TransformTarget>(target_wire).map_err(|error| {
         IdenteditError::InvalidRequest {
             message: format!("invalid operation target: {error}"),
@@ -592,7 +846,9 @@ fn parse_edit_target_from_wire(
 
 fn parse_stdin_operation_kind(
     operation: StdinEditOp,
+    file: &Path,
     handle_table: Option<&StdinHandleTableWire>,
+    registry: &ProviderRegistry,
 ) -> Result<ParsedOperationKind, IdenteditError> {
     let parsed = match operation {
         StdinEditOp::Replace { new_text } => {
@@ -619,33 +875,53 @@ fn parse_stdin_operation_kind(
         }
         StdinEditOp::MoveBefore { destination } => {
             ParsedOperationKind::Canonical(OpKind::MoveBefore {
-                destination: Box::new(parse_edit_target_from_wire(destination, handle_table)?),
+                destination: Box::new(parse_edit_target_from_wire(
+                    destination,
+                    file,
+                    handle_table,
+                    registry,
+                )?),
             })
         }
         StdinEditOp::MoveAfter { destination } => {
             ParsedOperationKind::Canonical(OpKind::MoveAfter {
-                destination: Box::new(parse_edit_target_from_wire(destination, handle_table)?),
+                destination: Box::new(parse_edit_target_from_wire(
+                    destination,
+                    file,
+                    handle_table,
+                    registry,
+                )?),
             })
         }
         StdinEditOp::MoveToBefore {
             destination_file,
             destination,
         } => ParsedOperationKind::MoveToBefore {
+            destination: parse_edit_target_from_wire(
+                destination,
+                &destination_file,
+                handle_table,
+                registry,
+            )?,
             destination_file,
-            destination: parse_edit_target_from_wire(destination, handle_table)?,
         },
         StdinEditOp::MoveToAfter {
             destination_file,
             destination,
         } => ParsedOperationKind::MoveToAfter {
+            destination: parse_edit_target_from_wire(
+                destination,
+                &destination_file,
+                handle_table,
+                registry,
+            )?,
             destination_file,
-            destination: parse_edit_target_from_wire(destination, handle_table)?,
         },
     };
     Ok(parsed)
 }
 
-fn apply_preview_mode(changeset: &mut MultiFileChangeset, verbose: bool) {
+pub(super) fn apply_preview_mode(changeset: &mut MultiFileChangeset, verbose: bool) {
     for file in &mut changeset.files {
         for operation in &mut file.operations {
             if verbose {
@@ -0,0 +1,231 @@
+//! `edit --codemod PROGRAM`: external programs as node transforms.
+//!
+//! Each selected node is written to the program's stdin as one JSON handle (the same shape
+//! as a `read --json --verbose` handle) and the program's stdout becomes the node's
+//! replacement text. The replacements are assembled into an ordinary plan, so hashes,
+//! previews, and `apply` work exactly as for hand-written edits.
+//!
+//! A `.wasm` program is instead loaded as a sandboxed module when the `wasm` feature is
+//! enabled; see `edit_wasm`. `edit --script` shares the node selection and runs a Rhai
+//! script in-process instead; see `script`.
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::changeset::{FileChange, MultiFileChangeset, OpKind, TransformTarget};
+use crate::error::IdenteditError;
+use crate::handle::{LineIndex, SelectionHandle};
+use crate::provider::ProviderRegistry;
+use crate::selector::Selector;
+use crate::transform::{TransformInstruction, build_changeset, parse_handles_for_file};
+
+use super::read::ReadHandle;
+use super::script::ReplacementScript;
+
+pub(super) struct Codemod<'a> {
+    pub(super) program: &'a Path,
+    pub(super) args: &'a [String],
+    /// `program` is a `--script` file rather than an executable.
+    pub(super) script: bool,
+}
+
+/// Runs the codemod once per selected node, in source order. Nodes whose output equals
+/// their current text get no operation.
+pub(super) fn build_codemod_changeset(
+    file: &Path,
+    codemod: &Codemod<'_>,
+    identity: Option<&str>,
+    selector: &Selector,
+    registry: &ProviderRegistry,
+) -> Result<MultiFileChangeset, IdenteditError> {
+    let source = std::fs::read(file).map_err(|error| IdenteditError::io(file, error))?;
+    let handles = parse_handles_for_file(file, registry)?;
+    let matched = selector.matches_among(&handles)?;
+    let selected = handles
+        .into_iter()
+        .zip(matched)
+        .filter_map(|(handle, matched)| matched.then_some(handle))
+        .filter(|handle| identity.is_none_or(|identity| handle.identity == identity))
+        .collect::<Vec<_>>();
+    if selected.is_empty() {
+        return Err(match identity {
+            Some(identity) => IdenteditError::TargetMissing {
+                identity: identity.to_string(),
+                file: file.display().to_string(),
+            },
+            None => IdenteditError::InvalidRequest {
+                message: format!(
+                    "No nodes in '{}' matched the codemod selector",
+                    file.display()
+                ),
+            },
+        });
+    }
+
+    let runner = CodemodRunner::new(codemod)?;
+    let lines = LineIndex::new(&source);
+    let mut instructions = Vec::new();
+    for handle in selected {
+        let Some(new_text) = runner.run(codemod, &handle, &lines)? else {
+            continue;
+        };
+        if new_text == handle.text {
+            continue;
+        }
+        instructions.push(TransformInstruction {
+            target: TransformTarget::node(
+                handle.identity,
+                handle.kind,
+                Some(handle.span),
+                handle.expected_old_hash,
+            ),
+            op: OpKind::Replace { new_text },
+        });
+    }
+
+    let file_change = if instructions.is_empty() {
+        FileChange {
+            file: file.to_path_buf(),
+            operations: Vec::new(),
+        }
+    } else {
+        build_changeset(file, instructions, registry)?
+    };
+    Ok(MultiFileChangeset {
+        files: vec![file_change],
+        transaction: Default::default(),
+        correlation_id: None,
+    })
+}
+
+/// `.wasm` codemods run in the embedded sandbox; anything else is an executable.
+enum CodemodRunner {
+    Process,
+    Script(Box<ReplacementScript>),
+    #[cfg(feature = "wasm")]
+    Wasm(super::edit_wasm::WasmCodemod),
+}
+
+impl CodemodRunner {
+    fn new(codemod: &Codemod<'_>) -> Result<Self, IdenteditError> {
+        if codemod.script {
+            return ReplacementScript::load(codemod.program)
+                .map(|script| Self::Script(Box::new(script)));
+        }
+        let is_wasm = codemod
+            .program
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("wasm"));
+        if !is_wasm {
+            return Ok(Self::Process);
+        }
+        #[cfg(feature = "wasm")]
+        {
+            super::edit_wasm::WasmCodemod::load(codemod.program).map(Self::Wasm)
+        }
+        #[cfg(not(feature = "wasm"))]
+        Err(IdenteditError::InvalidRequest {
+            message: format!(
+                "'{}' is a WASM codemod, but this identedit was built without the `wasm` feature",
+                codemod.program.display()
+            ),
+        })
+    }
+
+    /// The node's new text, or `None` to leave it alone.
+    fn run(
+        &self,
+        codemod: &Codemod<'_>,
+        handle: &SelectionHandle,
+        lines: &LineIndex,
+    ) -> Result<Option<String>, IdenteditError> {
+        if let Self::Script(script) = self {
+            return script.replacement(handle, None, None);
+        }
+        let input = ReadHandle::from_selection_handle(handle.clone(), lines, true);
+        let output = match self {
+            Self::Process => run_process(codemod, &input, &handle.text),
+            Self::Script(_) => unreachable!("handled above"),
+            #[cfg(feature = "wasm")]
+            Self::Wasm(module) => module.transform(&input, codemod.args),
+        };
+        output
+            .map(Some)
+            .map_err(|message| IdenteditError::CodemodFailed {
+                program: codemod.program.display().to_string(),
+                message: format!("on node '{}': {message}", handle.identity),
+            })
+    }
+}
+
+fn run_process(
+    codemod: &Codemod<'_>,
+    handle: &ReadHandle,
+    old_text: &str,
+) -> Result<String, String> {
+    let input = serde_json::to_vec(handle).map_err(|error| error.to_string())?;
+    let mut child = Command::new(codemod.program)
+        .args(codemod.args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| format!("could not start: {error}"))?;
+    // Written from a separate thread so a codemod that streams output before draining
+    // stdin cannot deadlock against us. A codemod may also ignore stdin entirely, so a
+    // broken pipe is not an error.
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let writer = std::thread::spawn(move || {
+        let _ = stdin.write_all(&input);
+    });
+    let output = child
+        .wait_with_output()
+        .map_err(|error| format!("could not be waited on: {error}"))?;
+    let _ = writer.join();
+
+    if !output.status.success() {
+        return Err(format!(
+            "{}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let stdout =
+        String::from_utf8(output.stdout).map_err(|_| "stdout is not valid UTF-8".to_string())?;
+    Ok(trim_print_newline(stdout, old_text))
+}
+
+/// Drops the newline `print`/`echo` add after the output, unless the node text itself
+/// ends with one.
+fn trim_print_newline(mut output: String, old_text: &str) -> String {
+    if old_text.ends_with('\n') {
+        return output;
+    }
+    if output.ends_with('\n') {
+        output.pop();
+        if output.ends_with('\r') {
+            output.pop();
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::trim_print_newline;
+
+    #[test]
+    fn one_printed_newline_is_dropped_unless_the_node_ends_with_one() {
+        assert_eq!(trim_print_newline("x + 1\n".to_string(), "x"), "x + 1");
+        assert_eq!(trim_print_newline("x + 1\r\n".to_string(), "x"), "x + 1");
+        assert_eq!(trim_print_newline("a\n\n".to_string(), "x"), "a\n");
+        assert_eq!(
+            trim_print_newline(
+                "def f():\n    pass\n".to_string(),
+                "def f():\n    return 1\n"
+            ),
+            "def f():\n    pass\n"
+        );
+    }
+}
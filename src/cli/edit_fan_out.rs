@@ -0,0 +1,189 @@
+//! `edit --json` with `fan_out`: one operation expanded into one operation per node that a
+//! selector matches across a list of files, such as a decorator added to every `test_*`
+//! function.
+//!
+//! Nodes are selected by the read selector filters, by a tree-sitter query whose `@target`
+//! captures name them, or by both. `expected_target_count` guards the expansion: when the
+//! files hold a different number of matching nodes, no plan is built.
+
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use streaming_iterator::StreamingIterator;
+use tree_sitter::{Query, QueryCursor};
+
+use crate::changeset::{OpKind, TransformTarget};
+use crate::error::IdenteditError;
+use crate::handle::{SelectionHandle, Span};
+use crate::patch::indentation::{insertion_context, reindent_text};
+use crate::provider::{ProviderRegistry, StructureProvider};
+use crate::selector::Selector;
+use crate::transform::TransformInstruction;
+
+use super::edit_build::StdinEditOp;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct FanOutWire {
+    #[serde(deserialize_with = "crate::path_style::deserialize_paths")]
+    files: Vec<PathBuf>,
+    #[serde(default)]
+    selector: Option<Selector>,
+    /// A tree-sitter query; the nodes captured as `@target` are selected.
+    #[serde(default)]
+    query: Option<String>,
+    op: StdinEditOp,
+    #[serde(default)]
+    expected_target_count: Option<usize>,
+}
+
+/// The operations `fan_out` expands into, grouped by file in request order. Files without
+/// a matching node are listed with no operations.
+pub(super) fn expand_fan_out(
+    fan_out: FanOutWire,
+    registry: &ProviderRegistry,
+) -> Result<Vec<(PathBuf, Vec<TransformInstruction>)>, IdenteditError> {
+    if fan_out.files.is_empty() {
+        return Err(IdenteditError::InvalidRequest {
+            message: "fan_out.files must contain at least one file".to_string(),
+        });
+    }
+    if fan_out.selector.is_none() && fan_out.query.is_none() {
+        return Err(IdenteditError::InvalidRequest {
+            message: "fan_out needs a 'selector', a 'query', or both".to_string(),
+        });
+    }
+    if let Some(selector) = &fan_out.selector {
+        selector.validate()?;
+    }
+    let op = node_op(fan_out.op)?;
+
+    let mut expanded = Vec::with_capacity(fan_out.files.len());
+    let mut matched = 0;
+    for file in fan_out.files {
+        let source = std::fs::read(&file).map_err(|error| IdenteditError::io(&file, error))?;
+        let provider = registry.provider_for(&file)?;
+        let mut handles = provider.parse(&file, &source)?;
+        if let Some(selector) = &fan_out.selector {
+            handles = selector.filter(handles)?;
+        }
+        if let Some(query) = &fan_out.query {
+            let targets = query_targets(&file, &source, provider, query)?;
+            handles.retain(|handle| targets.contains(&(handle.kind.as_str(), handle.span)));
+        }
+        matched += handles.len();
+
+        let text = String::from_utf8_lossy(&source);
+        let instructions: Vec<_> = handles
+            .into_iter()
+            .map(|handle| fan_out_instruction(&text, handle, &op))
+            .collect();
+        expanded.push((file, instructions));
+    }
+
+    if let Some(expected) = fan_out.expected_target_count
+        && matched != expected
+    {
+        let counts = expanded
+            .iter()
+            .map(|(file, instructions)| format!("{}: {}", file.display(), instructions.len()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Err(IdenteditError::InvalidRequest {
+            message: format!(
+                "fan_out matched {matched} node(s), expected_target_count is {expected} ({counts})"
+            ),
+        });
+    }
+    Ok(expanded)
+}
+
+/// The operation to repeat; only operations on the selected node itself fan out.
+fn node_op(op: StdinEditOp) -> Result<OpKind, IdenteditError> {
+    match op {
+        StdinEditOp::Replace { new_text } => Ok(OpKind::Replace { new_text }),
+        StdinEditOp::Delete => Ok(OpKind::Delete),
+        StdinEditOp::InsertBefore { new_text } => Ok(OpKind::InsertBefore { new_text }),
+        StdinEditOp::InsertAfter { new_text } => Ok(OpKind::InsertAfter { new_text }),
+        _ => Err(IdenteditError::InvalidRequest {
+            message: "fan_out.op must be replace, delete, insert_before, or insert_after"
+                .to_string(),
+        }),
+    }
+}
+
+/// `op` on `handle`, with inserted text moved to the depth of the line the node starts on,
+/// so one snippet fits top-level functions and methods alike.
+fn fan_out_instruction(source: &str, handle: SelectionHandle, op: &OpKind) -> TransformInstruction {
+    let (indentation, _) = insertion_context(source, handle.span.start);
+    let reindented = |new_text: &str, offset: usize| {
+        let (_, at_line_start) = insertion_context(source, offset);
+        reindent_text(source, new_text, &indentation, at_line_start)
+    };
+    let op = match op {
+        OpKind::InsertBefore { new_text } => OpKind::InsertBefore {
+            new_text: reindented(new_text, handle.span.start),
+        },
+        OpKind::InsertAfter { new_text } => OpKind::InsertAfter {
+            new_text: reindented(new_text, handle.span.end),
+        },
+        other => other.clone(),
+    };
+    TransformInstruction {
+        target: TransformTarget::node(
+            handle.identity,
+            handle.kind,
+            Some(handle.span),
+            handle.expected_old_hash,
+        ),
+        op,
+    }
+}
+
+/// Kinds and spans of the nodes `query` captures as `@target` in `source`. The kind tells
+/// a node from a wrapper or child that covers the same bytes.
+fn query_targets(
+    file: &Path,
+    source: &[u8],
+    provider: &dyn StructureProvider,
+    query: &str,
+) -> Result<Vec<(&'static str, Span)>, IdenteditError> {
+    let tree = provider
+        .syntax_tree(source)
+        .ok_or_else(|| IdenteditError::InvalidRequest {
+            message: format!(
+                "fan_out.query needs a tree-sitter grammar; '{}' is handled by the {} provider",
+                file.display(),
+                provider.name()
+            ),
+        })??;
+    let query =
+        Query::new(&tree.language(), query).map_err(|error| IdenteditError::InvalidRequest {
+            message: format!("Invalid fan_out.query for {}: {error}", provider.name()),
+        })?;
+    let Some(target) = query.capture_index_for_name("target") else {
+        return Err(IdenteditError::InvalidRequest {
+            message: "fan_out.query must capture the nodes to edit as @target".to_string(),
+        });
+    };
+
+    let mut targets = Vec::new();
+    let mut cursor = QueryCursor::new();
+    let mut matches = cursor.matches(&query, tree.root_node(), source);
+    while let Some(found) = matches.next() {
+        targets.extend(
+            found
+                .captures
+                .iter()
+                .filter(|capture| capture.index == target)
+                .map(|capture| {
+                    let span = Span {
+                        start: capture.node.start_byte(),
+                        end: capture.node.end_byte(),
+                    };
+                    (capture.node.kind(), span)
+                }),
+        );
+    }
+    Ok(targets)
+}
@@ -0,0 +1,91 @@
+//! WASM codemods for `edit --codemod module.wasm` (`wasm` feature).
+//!
+//! A module exports `memory`, `alloc(len: i32) -> i32`, and
+//! `transform(ptr: i32, len: i32) -> i64`. The host allocates `len` bytes, writes the UTF-8
+//! JSON `{"handle": <read handle>, "args": [<--codemod-arg>...]}` there, and calls
+//! `transform`, which returns `(out_ptr << 32) | out_len` for the UTF-8 replacement text.
+//! A trap reports a failure.
+//!
+//! The module gets no imports, so it cannot reach files, the network, the clock, or the
+//! environment. Every node runs in a fresh instance with bounded fuel and memory, so one
+//! node cannot influence the next and a runaway loop fails instead of hanging.
+
+use std::path::Path;
+
+use serde_json::json;
+use wasmi::{Config, Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+use crate::error::IdenteditError;
+
+use super::read::ReadHandle;
+
+/// Roughly one unit per executed instruction.
+pub(super) const FUEL_PER_NODE: u64 = 1_000_000_000;
+pub(super) const MEMORY_LIMIT_BYTES: usize = 256 * 1024 * 1024;
+
+pub(super) struct WasmCodemod {
+    engine: Engine,
+    module: Module,
+}
+
+impl WasmCodemod {
+    pub(super) fn load(path: &Path) -> Result<Self, IdenteditError> {
+        let bytes = std::fs::read(path).map_err(|error| IdenteditError::io(path, error))?;
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module =
+            Module::new(&engine, &bytes).map_err(|error| IdenteditError::CodemodFailed {
+                program: path.display().to_string(),
+                message: format!("invalid WASM module: {error}"),
+            })?;
+        Ok(Self { engine, module })
+    }
+
+    pub(super) fn transform(&self, handle: &ReadHandle, args: &[String]) -> Result<String, String> {
+        let input = serde_json::to_vec(&json!({ "handle": handle, "args": args }))
+            .map_err(|error| error.to_string())?;
+        let input_len =
+            i32::try_from(input.len()).map_err(|_| "handle JSON is too large".to_string())?;
+
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(MEMORY_LIMIT_BYTES)
+            .instances(1)
+            .build();
+        let mut store = Store::new(&self.engine, limits);
+        store.limiter(|limits: &mut StoreLimits| limits);
+        store
+            .set_fuel(FUEL_PER_NODE)
+            .map_err(|error| error.to_string())?;
+        let instance = Linker::<StoreLimits>::new(&self.engine)
+            .instantiate(&mut store, &self.module)
+            .and_then(|instance| instance.start(&mut store))
+            .map_err(|error| format!("instantiation failed: {error}"))?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or_else(|| "module does not export `memory`".to_string())?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&store, "alloc")
+            .map_err(|error| format!("module export `alloc(i32) -> i32`: {error}"))?;
+        let transform = instance
+            .get_typed_func::<(i32, i32), i64>(&store, "transform")
+            .map_err(|error| format!("module export `transform(i32, i32) -> i64`: {error}"))?;
+
+        let input_ptr = alloc
+            .call(&mut store, input_len)
+            .map_err(|error| format!("alloc trapped: {error}"))?;
+        memory
+            .write(&mut store, input_ptr as u32 as usize, &input)
+            .map_err(|error| format!("alloc returned an unusable pointer: {error}"))?;
+        let packed = transform
+            .call(&mut store, (input_ptr, input_len))
+            .map_err(|error| format!("transform trapped: {error}"))? as u64;
+
+        let (output_ptr, output_len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+        let mut output = vec![0; output_len];
+        memory
+            .read(&store, output_ptr, &mut output)
+            .map_err(|error| format!("transform returned an out-of-bounds result: {error}"))?;
+        String::from_utf8(output).map_err(|_| "transform output is not valid UTF-8".to_string())
+    }
+}
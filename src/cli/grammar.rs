@@ -1,7 +1,12 @@
+use std::path::PathBuf;
+
 use clap::{Args, Subcommand};
 
 use crate::error::IdenteditError;
-use crate::grammar::{GrammarInstallResponse, InstallGrammarRequest, install_grammar};
+use crate::grammar::{
+    GrammarCleanupResponse, GrammarInstallResponse, InstallGrammarRequest, collect_grammar_garbage,
+    install_grammar, remove_grammar,
+};
 
 #[derive(Debug, Args)]
 pub struct GrammarArgs {
@@ -13,6 +18,12 @@ pub struct GrammarArgs {
 pub enum GrammarCommands {
     #[command(about = "Install a tree-sitter grammar and register it for runtime loading")]
     Install(GrammarInstallArgs),
+    #[command(about = "Remove an installed grammar's library and manifest entry")]
+    Remove(GrammarRemoveArgs),
+    #[command(
+        about = "Drop manifest entries without a library and delete files no entry refers to"
+    )]
+    Gc(GrammarGcArgs),
 }
 
 #[derive(Debug, Args)]
@@ -21,6 +32,13 @@ pub struct GrammarInstallArgs {
     pub language: String,
     #[arg(long, value_name = "URL", help = "Override grammar repository URL")]
     pub repo: Option<String>,
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with = "repo",
+        help = "Build from a local grammar checkout instead of cloning a repository"
+    )]
+    pub path: Option<PathBuf>,
     #[arg(long, value_name = "SYMBOL", help = "Override language symbol name")]
     pub symbol: Option<String>,
     #[arg(
@@ -29,18 +47,57 @@ pub struct GrammarInstallArgs {
         help = "Extension to route to this grammar (repeatable)"
     )]
     pub extensions: Vec<String>,
+    #[arg(
+        long = "kind-map",
+        value_name = "NODE=KIND",
+        help = "Report grammar node kind NODE as KIND (repeatable)"
+    )]
+    pub kind_map: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct GrammarRemoveArgs {
+    #[arg(
+        value_name = "LANG",
+        help = "Language name the grammar was installed as"
+    )]
+    pub language: String,
+    #[arg(long, help = "List what would be removed without changing anything")]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct GrammarGcArgs {
+    #[arg(long, help = "List what would be removed without changing anything")]
+    pub dry_run: bool,
+}
+
+pub enum GrammarCommandOutput {
+    Installed(GrammarInstallResponse),
+    Cleaned(GrammarCleanupResponse),
 }
 
-pub fn run_grammar(args: GrammarArgs) -> Result<GrammarInstallResponse, IdenteditError> {
+pub fn run_grammar(args: GrammarArgs) -> Result<GrammarCommandOutput, IdenteditError> {
     match args.command {
         GrammarCommands::Install(install_args) => {
             let installed = install_grammar(InstallGrammarRequest {
                 lang: install_args.language,
                 repo: install_args.repo,
+                path: install_args.path,
                 symbol: install_args.symbol,
                 extensions: install_args.extensions,
+                kind_map: install_args.kind_map,
             })?;
-            Ok(GrammarInstallResponse { installed })
+            Ok(GrammarCommandOutput::Installed(GrammarInstallResponse {
+                installed,
+            }))
         }
+        GrammarCommands::Remove(remove_args) => Ok(GrammarCommandOutput::Cleaned(remove_grammar(
+            &remove_args.language,
+            remove_args.dry_run,
+        )?)),
+        GrammarCommands::Gc(gc_args) => Ok(GrammarCommandOutput::Cleaned(collect_grammar_garbage(
+            gc_args.dry_run,
+        )?)),
     }
 }
@@ -0,0 +1,242 @@
+//! Git hook checks (`hook pre-commit`).
+//!
+//! Every file the index changes is parsed as staged with the provider lookup picks for it.
+//! A file fails when it has more syntax errors (tree-sitter ERROR and MISSING nodes, or a
+//! failed parse for other providers) than it had at `HEAD`, so a commit never adds broken
+//! syntax but does not have to fix what was already broken. With `--enforce-policy`, the
+//! `policy` of the project config is checked too.
+
+use std::path::{Path, PathBuf};
+
+use clap::{Args, Subcommand};
+use glob::{MatchOptions, Pattern};
+use serde::Serialize;
+
+use crate::error::IdenteditError;
+use crate::project_config::{PROJECT_CONFIG_FILE, Policy, load_project_config};
+use crate::provider::{ProviderRegistry, StructureProvider, syntax_error_count};
+
+use super::read_rev::git;
+
+#[derive(Debug, Args)]
+pub struct HookArgs {
+    #[command(subcommand)]
+    pub command: HookCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum HookCommands {
+    #[command(
+        about = "Check staged files for new syntax errors, and optionally the project policy; exits non-zero on failure"
+    )]
+    PreCommit(PreCommitArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct PreCommitArgs {
+    #[arg(
+        long = "enforce-policy",
+        help = "Also check the staged changes against the policy in .identedit/config.json"
+    )]
+    pub enforce_policy: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreCommitResponse {
+    pub passed: bool,
+    pub files: Vec<StagedFile>,
+    pub violations: Vec<HookViolation>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StagedFile {
+    /// Relative to the repository root.
+    pub file: String,
+    pub change: StagedChange,
+    /// Absent for deleted files and files no provider takes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub syntax_errors: Option<usize>,
+    /// Syntax errors of the file at `HEAD`; 0 for files `HEAD` does not have.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub head_syntax_errors: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StagedChange {
+    Added,
+    Modified,
+    Deleted,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HookViolation {
+    pub rule: HookRule,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookRule {
+    NewSyntaxErrors,
+    Protected,
+    MaxFiles,
+}
+
+pub fn run_hook(
+    args: HookArgs,
+    registry: &ProviderRegistry,
+) -> Result<PreCommitResponse, IdenteditError> {
+    match args.command {
+        HookCommands::PreCommit(pre_commit_args) => run_pre_commit(pre_commit_args, registry),
+    }
+}
+
+fn run_pre_commit(
+    args: PreCommitArgs,
+    registry: &ProviderRegistry,
+) -> Result<PreCommitResponse, IdenteditError> {
+    let policy = if args.enforce_policy {
+        Some(load_project_config()?.policy)
+    } else {
+        None
+    };
+    let root = repository_root()?;
+
+    let mut files = Vec::new();
+    let mut violations = Vec::new();
+    for (change, file) in staged_changes(&root)? {
+        let mut staged = StagedFile {
+            file,
+            change,
+            provider: None,
+            syntax_errors: None,
+            head_syntax_errors: None,
+        };
+        if change != StagedChange::Deleted
+            && let Ok(provider) = registry.provider_for(Path::new(&staged.file))
+        {
+            let source = blob(&root, &format!(":{}", staged.file))?.unwrap_or_default();
+            let errors = syntax_errors(provider, &staged.file, &source);
+            let head_errors = blob(&root, &format!("HEAD:{}", staged.file))?
+                .map_or(0, |head| syntax_errors(provider, &staged.file, &head));
+            if errors > head_errors {
+                violations.push(HookViolation {
+                    rule: HookRule::NewSyntaxErrors,
+                    file: Some(staged.file.clone()),
+                    message: format!(
+                        "{} parses with {errors} syntax error(s) as staged, {head_errors} at HEAD",
+                        staged.file
+                    ),
+                });
+            }
+            staged.provider = Some(provider.name());
+            staged.syntax_errors = Some(errors);
+            staged.head_syntax_errors = Some(head_errors);
+        }
+        files.push(staged);
+    }
+    if let Some(policy) = policy {
+        violations.extend(policy_violations(&policy, &files)?);
+    }
+
+    Ok(PreCommitResponse {
+        passed: violations.is_empty(),
+        files,
+        violations,
+    })
+}
+
+/// Syntax errors of `source`: ERROR and MISSING nodes for providers with a syntax tree, and
+/// one for a failed parse otherwise.
+fn syntax_errors(provider: &dyn StructureProvider, file: &str, source: &[u8]) -> usize {
+    match provider.syntax_tree(source) {
+        Some(Ok(tree)) => syntax_error_count(tree.root_node()),
+        _ => usize::from(provider.parse(Path::new(file), source).is_err()),
+    }
+}
+
+fn policy_violations(
+    policy: &Policy,
+    files: &[StagedFile],
+) -> Result<Vec<HookViolation>, IdenteditError> {
+    let mut violations = Vec::new();
+    if let Some(max_files) = policy.max_files
+        && files.len() > max_files
+    {
+        violations.push(HookViolation {
+            rule: HookRule::MaxFiles,
+            file: None,
+            message: format!(
+                "The commit touches {} files; the policy allows {max_files}",
+                files.len()
+            ),
+        });
+    }
+
+    let options = MatchOptions {
+        require_literal_separator: true,
+        ..MatchOptions::default()
+    };
+    for pattern in &policy.protected {
+        let compiled = Pattern::new(pattern).map_err(|error| IdenteditError::InvalidRequest {
+            message: format!(
+                "Invalid protected pattern '{pattern}' in '{PROJECT_CONFIG_FILE}': {error}"
+            ),
+        })?;
+        violations.extend(
+            files
+                .iter()
+                .filter(|staged| compiled.matches_with(&staged.file, options))
+                .map(|staged| HookViolation {
+                    rule: HookRule::Protected,
+                    file: Some(staged.file.clone()),
+                    message: format!("{} is protected by '{pattern}'", staged.file),
+                }),
+        );
+    }
+    Ok(violations)
+}
+
+fn repository_root() -> Result<PathBuf, IdenteditError> {
+    let output = git(Path::new("."), &["rev-parse", "--show-toplevel"])?.ok_or_else(|| {
+        IdenteditError::InvalidRequest {
+            message: "hook pre-commit must run inside a git repository".to_string(),
+        }
+    })?;
+    Ok(PathBuf::from(String::from_utf8_lossy(&output).trim_end()))
+}
+
+/// Files the index changes against `HEAD`, by repository-relative path. A rename is a
+/// deletion and an addition.
+fn staged_changes(root: &Path) -> Result<Vec<(StagedChange, String)>, IdenteditError> {
+    let output = git(
+        root,
+        &["diff", "--cached", "--name-status", "--no-renames", "-z"],
+    )?
+    .ok_or_else(|| IdenteditError::InvalidRequest {
+        message: "git diff --cached failed".to_string(),
+    })?;
+    let output = String::from_utf8_lossy(&output);
+    let mut fields = output.split('\0').filter(|field| !field.is_empty());
+    let mut changes = Vec::new();
+    while let (Some(status), Some(file)) = (fields.next(), fields.next()) {
+        let change = match status.as_bytes().first() {
+            Some(b'A') => StagedChange::Added,
+            Some(b'D') => StagedChange::Deleted,
+            _ => StagedChange::Modified,
+        };
+        changes.push((change, file.to_string()));
+    }
+    Ok(changes)
+}
+
+/// The blob `object` names, such as `:src/lib.rs` for the staged file; `None` when there is
+/// no such blob.
+fn blob(root: &Path, object: &str) -> Result<Option<Vec<u8>>, IdenteditError> {
+    git(root, &["cat-file", "blob", object])
+}
@@ -0,0 +1,131 @@
+//! Inverse plans: `apply --emit-inverse` and `--inverse-out` describe how to undo an apply,
+//! and `identedit revert` applies that description.
+//!
+//! Each rewritten file is line-diffed against its text before the apply, and every changed
+//! run of lines becomes a line-range `replace` anchored on the hashes of the new lines and
+//! carrying the original text. A revert therefore fails with the usual precondition errors
+//! when those lines changed again since, instead of overwriting the later edit the way a
+//! whole-file undo would.
+
+use std::fs;
+use std::path::PathBuf;
+
+use similar::{Algorithm, DiffTag, capture_diff_slices};
+
+use crate::changeset::{MultiFileChangeset, OpKind, TransformTarget, hash_text};
+use crate::error::IdenteditError;
+use crate::hashline::{format_line_ref, show_hashed_lines};
+use crate::provider::ProviderRegistry;
+use crate::transform::{TransformInstruction, build_changeset, line_spans};
+
+/// The files an apply is about to change, as they are before it.
+#[derive(Debug)]
+pub(super) struct InverseBaseline {
+    /// `None` for files that cannot be read as UTF-8; the apply cannot change them.
+    files: Vec<(PathBuf, Option<String>)>,
+}
+
+impl InverseBaseline {
+    /// Captures the files of `changeset`. Moves are refused: a line plan cannot rename a
+    /// file back.
+    pub(super) fn capture(changeset: &MultiFileChangeset) -> Result<Self, IdenteditError> {
+        let mut files = Vec::new();
+        for file_change in &changeset.files {
+            if file_change
+                .operations
+                .iter()
+                .any(|operation| matches!(operation.op, OpKind::Move { .. }))
+            {
+                return Err(IdenteditError::InvalidRequest {
+                    message: format!(
+                        "Cannot build an inverse plan for the move of '{}'; move the file back instead",
+                        file_change.file.display()
+                    ),
+                });
+            }
+            if files.iter().all(|(file, _)| *file != file_change.file) {
+                let source = fs::read_to_string(&file_change.file).ok();
+                files.push((file_change.file.clone(), source));
+            }
+        }
+        Ok(Self { files })
+    }
+
+    /// The plan that turns every file changed since the capture back into its captured text.
+    pub(super) fn inverse_plan(
+        self,
+        correlation_id: Option<String>,
+        registry: &ProviderRegistry,
+    ) -> Result<MultiFileChangeset, IdenteditError> {
+        let mut files = Vec::new();
+        for (file, original) in self.files {
+            let Some(original) = original else {
+                continue;
+            };
+            let current =
+                fs::read_to_string(&file).map_err(|error| IdenteditError::io(&file, error))?;
+            if current == original {
+                continue;
+            }
+            files.push(build_changeset(
+                &file,
+                inverse_instructions(&current, &original),
+                registry,
+            )?);
+        }
+        Ok(MultiFileChangeset {
+            files,
+            transaction: Default::default(),
+            correlation_id,
+        })
+    }
+}
+
+/// Line-range edits that turn `current` back into `original`, anchored on `current`.
+fn inverse_instructions(current: &str, original: &str) -> Vec<TransformInstruction> {
+    let line_texts = |text: &str| {
+        line_spans(text)
+            .into_iter()
+            .map(|span| text[span.start..span.end].to_string())
+            .collect::<Vec<_>>()
+    };
+    let current_lines = line_texts(current);
+    let original_lines = line_texts(original);
+    let anchors = show_hashed_lines(current);
+
+    capture_diff_slices(Algorithm::Myers, &current_lines, &original_lines)
+        .into_iter()
+        .filter(|change| change.tag() != DiffTag::Equal)
+        .map(|change| {
+            let removed = change.old_range();
+            let new_text = original_lines[change.new_range()].concat();
+            if !removed.is_empty() {
+                let first = &anchors[removed.start];
+                let last = &anchors[removed.end - 1];
+                return TransformInstruction {
+                    target: TransformTarget::Line {
+                        anchor: format_line_ref(first.line, &first.hash),
+                        end_anchor: (removed.len() > 1)
+                            .then(|| format_line_ref(last.line, &last.hash)),
+                    },
+                    op: OpKind::Replace { new_text },
+                };
+            }
+            match removed.start.checked_sub(1).map(|index| &anchors[index]) {
+                Some(previous) => TransformInstruction {
+                    target: TransformTarget::Line {
+                        anchor: format_line_ref(previous.line, &previous.hash),
+                        end_anchor: None,
+                    },
+                    op: OpKind::InsertAfter { new_text },
+                },
+                None => TransformInstruction {
+                    target: TransformTarget::FileStart {
+                        expected_file_hash: hash_text(current),
+                    },
+                    op: OpKind::Insert { new_text },
+                },
+            }
+        })
+        .collect()
+}
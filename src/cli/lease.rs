@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+
+use clap::{Args, Subcommand};
+use serde::Serialize;
+
+use crate::error::IdenteditError;
+use crate::lease::{DEFAULT_LEASE_SECONDS, Lease, acquire_leases, list_leases, release_leases};
+
+#[derive(Debug, Args)]
+pub struct LeaseArgs {
+    #[command(subcommand)]
+    pub command: LeaseCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum LeaseCommands {
+    #[command(about = "Lease files to the current session, renewing leases it already holds")]
+    Acquire(LeaseAcquireArgs),
+    #[command(about = "Release the current session's leases on files")]
+    Release(LeaseReleaseArgs),
+    #[command(about = "List live leases of every session")]
+    List,
+}
+
+#[derive(Debug, Args)]
+pub struct LeaseAcquireArgs {
+    #[arg(value_name = "FILE", required = true, help = "Files to lease")]
+    pub files: Vec<PathBuf>,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = DEFAULT_LEASE_SECONDS,
+        help = "Seconds until the leases expire unless renewed"
+    )]
+    pub ttl: u64,
+}
+
+#[derive(Debug, Args)]
+pub struct LeaseReleaseArgs {
+    #[arg(value_name = "FILE", required = true, help = "Files to release")]
+    pub files: Vec<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum LeaseResponse {
+    Acquired { acquired: Vec<Lease> },
+    Released { released: Vec<Lease> },
+    List { leases: Vec<Lease> },
+}
+
+pub fn run_lease(args: LeaseArgs, session: Option<&str>) -> Result<LeaseResponse, IdenteditError> {
+    match args.command {
+        LeaseCommands::Acquire(acquire_args) => Ok(LeaseResponse::Acquired {
+            acquired: acquire_leases(&acquire_args.files, acquire_args.ttl, session)?,
+        }),
+        LeaseCommands::Release(release_args) => Ok(LeaseResponse::Released {
+            released: release_leases(&release_args.files, session)?,
+        }),
+        LeaseCommands::List => Ok(LeaseResponse::List {
+            leases: list_leases()?,
+        }),
+    }
+}
@@ -1,13 +1,15 @@
 use std::fs;
 use std::path::PathBuf;
 
+use regex::Regex;
 use serde::Serialize;
 
+use crate::apply::{ApplySettings, ChangeStats};
 use crate::error::IdenteditError;
 use crate::hashline::{
-    HashlineApplyError, HashlineApplyMode, HashlineCheckError, HashlineCheckResult, HashlineCheckSummary,
-    HashlineEdit, HashlineMismatch, HashlineMismatchStatus, apply_hashline_edits_with_mode,
-    check_hashline_edits,
+    HashlineApplyError, HashlineApplyMode, HashlineCheckError, HashlineCheckResult,
+    HashlineCheckSummary, HashlineEdit, HashlineMismatch, HashlineMismatchStatus,
+    apply_hashline_edits_with_mode, check_hashline_edits, match_line_ref, offset_line_ref,
 };
 use crate::patch::engine::run_resolve_verify_apply;
 
@@ -28,6 +30,7 @@ pub enum HashlineModeResponse {
 
 #[derive(Debug, Serialize)]
 pub struct HashlinePatchResponse {
+    #[serde(serialize_with = "crate::path_style::serialize_path")]
     pub file: PathBuf,
     pub auto_repair: bool,
     pub strict_check: HashlineCheckPayload,
@@ -35,17 +38,47 @@ pub struct HashlinePatchResponse {
     pub changed: bool,
     pub operations_total: usize,
     pub operations_applied: usize,
+    pub stats: ChangeStats,
+}
+
+/// A line edit whose anchor may be a landmark: with a nonzero `anchor_offset`, the edit
+/// targets the line that many lines below (or above, when negative) the anchor.
+#[derive(Debug)]
+pub(crate) struct LineTargetEdit {
+    pub(crate) edit: HashlineEdit,
+    /// Finds the anchor line by pattern when the file is read; the edit's own start
+    /// anchor is then only a placeholder.
+    pub(crate) anchor_match: Option<LineMatch>,
+    pub(crate) anchor_offset: isize,
+}
+
+#[derive(Debug)]
+pub(crate) struct LineMatch {
+    pub(crate) pattern: Regex,
+    pub(crate) occurrence: Option<usize>,
+    pub(crate) expected_match_count: Option<usize>,
+}
+
+impl From<HashlineEdit> for LineTargetEdit {
+    fn from(edit: HashlineEdit) -> Self {
+        Self {
+            edit,
+            anchor_match: None,
+            anchor_offset: 0,
+        }
+    }
 }
 
 pub(crate) fn execute_hashline_patch(
     file: PathBuf,
-    edits: Vec<HashlineEdit>,
+    edits: Vec<LineTargetEdit>,
     auto_repair: bool,
+    settings: &ApplySettings,
 ) -> Result<HashlinePatchResponse, IdenteditError> {
     run_resolve_verify_apply(
         || resolve_hashline_patch_request(file, edits, auto_repair),
         verify_hashline_patch_request,
-        apply_hashline_patch_request,
+        |verified| apply_hashline_patch_request(verified, settings),
     )
 }
 
@@ -69,10 +102,14 @@ struct VerifiedHashlinePatch {
 
 fn resolve_hashline_patch_request(
     file: PathBuf,
-    edits: Vec<HashlineEdit>,
+    edits: Vec<LineTargetEdit>,
     auto_repair: bool,
 ) -> Result<ResolvedHashlinePatch, IdenteditError> {
     let source = fs::read_to_string(&file).map_err(|error| IdenteditError::io(&file, error))?;
+    let edits = edits
+        .into_iter()
+        .map(|edit| rebase_offset_anchor(&source, edit))
+        .collect::<Result<Vec<_>, _>>()?;
     Ok(ResolvedHashlinePatch {
         file,
         source,
@@ -81,6 +118,38 @@ fn resolve_hashline_patch_request(
     })
 }
 
+/// Swaps a pattern or landmark anchor for the exact anchor of the line it points at, so
+/// the strict check and overlap detection see the real target.
+fn rebase_offset_anchor(
+    source: &str,
+    edit: LineTargetEdit,
+) -> Result<HashlineEdit, IdenteditError> {
+    let LineTargetEdit {
+        mut edit,
+        anchor_match,
+        anchor_offset,
+    } = edit;
+    let anchor = match &mut edit {
+        HashlineEdit::SetLine { set_line } => &mut set_line.anchor,
+        HashlineEdit::ReplaceLines { replace_lines } => &mut replace_lines.start_anchor,
+        HashlineEdit::InsertAfter { insert_after } => &mut insert_after.anchor,
+    };
+    if let Some(line_match) = anchor_match {
+        *anchor = match_line_ref(
+            source,
+            &line_match.pattern,
+            line_match.occurrence,
+            line_match.expected_match_count,
+        )
+        .map_err(map_hashline_check_error)?;
+    }
+    if anchor_offset != 0 {
+        *anchor =
+            offset_line_ref(source, anchor, anchor_offset).map_err(map_hashline_apply_error)?;
+    }
+    Ok(edit)
+}
+
 fn verify_hashline_patch_request(
     resolved: ResolvedHashlinePatch,
 ) -> Result<VerifiedHashlinePatch, IdenteditError> {
@@ -107,6 +176,7 @@ fn verify_hashline_patch_request(
 
 fn apply_hashline_patch_request(
     verified: VerifiedHashlinePatch,
+    settings: &ApplySettings,
 ) -> Result<HashlinePatchResponse, IdenteditError> {
     let strict_check = build_hashline_check_payload(
         verified.strict_check_result.clone(),
@@ -116,6 +186,15 @@ fn apply_hashline_patch_request(
         apply_hashline_edits_with_mode(&verified.source, &verified.edits, verified.applied_mode)
             .map_err(map_hashline_apply_error)?;
     let changed = verified.source != applied.content;
+    let mut stats = ChangeStats::between(&verified.source, &applied.content);
+    if settings.invalidated_identities {
+        stats = stats.with_invalidations(
+            &verified.file,
+            &verified.source,
+            &applied.content,
+            &settings.provider_registry()?,
+        );
+    }
 
     if changed {
         fs::write(&verified.file, applied.content.as_bytes())
@@ -133,6 +212,7 @@ fn apply_hashline_patch_request(
         changed,
         operations_total: applied.operations_total,
         operations_applied: applied.operations_applied,
+        stats,
     })
 }
 
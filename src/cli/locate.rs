@@ -0,0 +1,270 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use clap::Args;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::error::{ErrorBody, IdenteditError};
+use crate::handle::LineIndex;
+use crate::hashline::{format_line_ref, show_hashed_lines};
+use crate::provider::ProviderRegistry;
+use crate::transform::parse_handles_for_source;
+
+use super::read::ReadHandle;
+use super::read_window::ReadWindow;
+
+#[derive(Debug, Args)]
+pub struct LocateArgs {
+    #[arg(
+        long = "diagnostic",
+        value_name = "TEXT",
+        help = "Compiler diagnostic, test failure, or stack trace text (repeatable; read from stdin when omitted)"
+    )]
+    pub diagnostics: Vec<String>,
+    #[arg(
+        long,
+        value_name = "KIND",
+        help = "Return the innermost enclosing node of this kind instead of the innermost node (repeatable)"
+    )]
+    pub kind: Vec<String>,
+    #[arg(long, help = "Include the full text of each enclosing node")]
+    pub verbose: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LocateResponse {
+    pub locations: Vec<LocatedDiagnostic>,
+    pub summary: LocateSummary,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LocateSummary {
+    pub locations: usize,
+    pub resolved: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LocatedDiagnostic {
+    #[serde(serialize_with = "crate::path_style::serialize_path")]
+    pub file: PathBuf,
+    pub line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    /// The diagnostic line the location was read from.
+    pub diagnostic: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_anchor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub handle: Option<ReadHandle>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorBody>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DiagnosticLocation {
+    file: PathBuf,
+    line: usize,
+    column: Option<usize>,
+    diagnostic: String,
+}
+
+pub fn run_locate(
+    args: LocateArgs,
+    registry: &ProviderRegistry,
+) -> Result<LocateResponse, IdenteditError> {
+    let text = if args.diagnostics.is_empty() {
+        let mut input = String::new();
+        std::io::stdin()
+            .read_to_string(&mut input)
+            .map_err(|error| IdenteditError::StdinRead { source: error })?;
+        input
+    } else {
+        args.diagnostics.join("\n")
+    };
+
+    let locations = parse_diagnostic_locations(&text);
+    if locations.is_empty() {
+        return Err(IdenteditError::InvalidRequest {
+            message: "No file:line locations found in diagnostic input; expected rustc, pytest, Python traceback, tsc, or go output".to_string(),
+        });
+    }
+
+    let located = locations
+        .into_iter()
+        .map(|location| locate(location, &args.kind, args.verbose, registry))
+        .collect::<Vec<_>>();
+    Ok(LocateResponse {
+        summary: LocateSummary {
+            locations: located.len(),
+            resolved: located
+                .iter()
+                .filter(|location| location.handle.is_some())
+                .count(),
+        },
+        locations: located,
+    })
+}
+
+/// Resolves one location to its line anchor and enclosing node. Failures stay attached to
+/// the location so one stale frame in a long trace does not hide the others.
+fn locate(
+    location: DiagnosticLocation,
+    kinds: &[String],
+    verbose: bool,
+    registry: &ProviderRegistry,
+) -> LocatedDiagnostic {
+    let DiagnosticLocation {
+        file,
+        line,
+        column,
+        diagnostic,
+    } = location;
+    let mut located = LocatedDiagnostic {
+        file,
+        line,
+        column,
+        diagnostic,
+        line_anchor: None,
+        handle: None,
+        error: None,
+    };
+
+    let source = match fs::read(&located.file) {
+        Ok(source) => source,
+        Err(error) => {
+            located.error = Some(error_body(IdenteditError::io(&located.file, error)));
+            return located;
+        }
+    };
+    let hashed_line = std::str::from_utf8(&source)
+        .ok()
+        .and_then(|text| show_hashed_lines(text).into_iter().nth(line - 1));
+    let Some(hashed_line) = hashed_line else {
+        located.error = Some(error_body(IdenteditError::InvalidRequest {
+            message: format!(
+                "Line {line} is past the end of '{}'",
+                located.file.display()
+            ),
+        }));
+        return located;
+    };
+    located.line_anchor = Some(format_line_ref(hashed_line.line, &hashed_line.hash));
+
+    match parse_handles_for_source(&located.file, &source, registry) {
+        Ok(handles) => {
+            let candidates = handles
+                .into_iter()
+                .filter(|handle| kinds.is_empty() || kinds.contains(&handle.kind))
+                .collect();
+            located.handle = ReadWindow::ContainingLine(line)
+                .filter_nodes(&source, candidates)
+                .into_iter()
+                .next()
+                .map(|handle| {
+                    ReadHandle::from_selection_handle(handle, &LineIndex::new(&source), verbose)
+                });
+        }
+        Err(error) => located.error = Some(error_body(error)),
+    }
+    located
+}
+
+fn error_body(error: IdenteditError) -> ErrorBody {
+    error.to_error_response().error
+}
+
+/// Extracts `file:line` locations in order of appearance, one per input line, skipping
+/// repeats of the same file and line.
+fn parse_diagnostic_locations(text: &str) -> Vec<DiagnosticLocation> {
+    let mut seen = HashSet::new();
+    let mut locations = Vec::new();
+    for raw_line in text.lines() {
+        let Some(captures) = location_patterns()
+            .iter()
+            .find_map(|pattern| pattern.captures(raw_line))
+        else {
+            continue;
+        };
+        let Some(line) = captures
+            .name("line")
+            .and_then(|line| line.as_str().parse::<usize>().ok())
+            .filter(|line| *line > 0)
+        else {
+            continue;
+        };
+        let file = PathBuf::from(&captures["file"]);
+        if !seen.insert((file.clone(), line)) {
+            continue;
+        }
+        locations.push(DiagnosticLocation {
+            file,
+            line,
+            column: captures
+                .name("column")
+                .and_then(|column| column.as_str().parse().ok()),
+            diagnostic: raw_line.trim().to_string(),
+        });
+    }
+    locations
+}
+
+/// Tried in order; the first match on a line wins.
+fn location_patterns() -> &'static [Regex] {
+    static PATTERNS: OnceLock<Vec<Regex>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            // Python traceback frame: `File "app/models.py", line 142, in save`
+            r#"File "(?P<file>[^"]+)", line (?P<line>\d+)"#,
+            // rustc: `  --> src/main.rs:12:5`
+            r"^\s*-->\s*(?P<file>.+?):(?P<line>\d+)(?::(?P<column>\d+))?\s*$",
+            // tsc: `src/app.ts(12,5): error TS2322: ...`
+            r"^(?P<file>[^\s(][^(]*?)\((?P<line>\d+),(?P<column>\d+)\)",
+            // pytest, go, rustc --message-format short, tsc --pretty:
+            // `tests/test_x.py:142: AssertionError`, `./main.go:12:5: undefined: x`
+            r"(?:^|[\s(\[])(?P<file>(?:[A-Za-z]:)?[\w.~/\\-]*\.[A-Za-z0-9]+):(?P<line>\d+)(?::(?P<column>\d+))?",
+        ]
+        .into_iter()
+        .map(|pattern| Regex::new(pattern).expect("diagnostic location pattern should compile"))
+        .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::parse_diagnostic_locations;
+
+    #[test]
+    fn common_diagnostic_formats_yield_file_and_line() {
+        let text = "\
+error[E0425]: cannot find value `x` in this scope
+  --> src/main.rs:4:13
+tests/test_models.py:142: AssertionError
+  File \"app/models.py\", line 57, in save
+src/app.ts(12,5): error TS2322: Type 'string' is not assignable to type 'number'.
+./main.go:8:2: undefined: fmt.Printn
+\t/home/dev/project/main.go:21 +0x1d
+  --> src/main.rs:4:13
+";
+        let locations = parse_diagnostic_locations(text)
+            .into_iter()
+            .map(|location| (location.file, location.line, location.column))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            locations,
+            vec![
+                (PathBuf::from("src/main.rs"), 4, Some(13)),
+                (PathBuf::from("tests/test_models.py"), 142, None),
+                (PathBuf::from("app/models.py"), 57, None),
+                (PathBuf::from("src/app.ts"), 12, Some(5)),
+                (PathBuf::from("./main.go"), 8, Some(2)),
+                (PathBuf::from("/home/dev/project/main.go"), 21, None),
+            ]
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use clap::{Args, Subcommand};
+use serde::{Deserialize, Serialize};
+
+use crate::error::IdenteditError;
+use crate::hash::{HASH_ALGORITHM, hash_reader};
+use crate::provider::ProviderRegistry;
+
+use super::scan::{ScanLimits, collect_files, scan_parallel};
+
+const MANIFEST_VERSION: u32 = 1;
+
+#[derive(Debug, Args)]
+pub struct ManifestArgs {
+    #[command(subcommand)]
+    pub command: ManifestCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ManifestCommands {
+    #[command(about = "Record the hash of every file under a directory")]
+    Create(ManifestCreateArgs),
+    #[command(
+        about = "Compare a directory against a manifest and list added, removed, and changed files"
+    )]
+    Verify(ManifestVerifyArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct ManifestCreateArgs {
+    #[arg(
+        value_name = "DIR",
+        default_value = ".",
+        help = "Directory hashed recursively; hidden entries and dependency/build directories are skipped"
+    )]
+    pub dir: PathBuf,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Write the manifest to FILE and print only its file count"
+    )]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct ManifestVerifyArgs {
+    #[arg(
+        value_name = "DIR",
+        default_value = ".",
+        help = "Directory compared recursively, walked as manifest create walks it"
+    )]
+    pub dir: PathBuf,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Manifest written by manifest create"
+    )]
+    pub manifest: PathBuf,
+}
+
+/// The hash of every file under a directory, keyed by its `/`-separated path relative to
+/// the directory, so a manifest verifies wherever the tree is checked out.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub version: u32,
+    pub hash_algorithm: String,
+    pub files: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ManifestWritten {
+    #[serde(serialize_with = "crate::path_style::serialize_path")]
+    pub output: PathBuf,
+    pub files: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ManifestVerifyResponse {
+    /// No file was added, removed, or changed since the manifest was created.
+    pub unchanged: bool,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<ChangedFile>,
+    pub summary: ManifestVerifySummary,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangedFile {
+    pub file: String,
+    pub expected_hash: String,
+    pub actual_hash: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ManifestVerifySummary {
+    pub files: usize,
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+}
+
+pub enum ManifestCommandOutput {
+    Manifest(Manifest),
+    Written(ManifestWritten),
+    Verified(ManifestVerifyResponse),
+}
+
+pub fn run_manifest(
+    args: ManifestArgs,
+    limits: ScanLimits,
+    registry: &ProviderRegistry,
+) -> Result<ManifestCommandOutput, IdenteditError> {
+    match args.command {
+        ManifestCommands::Create(create_args) => create_manifest(create_args, &limits, registry),
+        ManifestCommands::Verify(verify_args) => {
+            verify_manifest(verify_args, &limits, registry).map(ManifestCommandOutput::Verified)
+        }
+    }
+}
+
+fn create_manifest(
+    args: ManifestCreateArgs,
+    limits: &ScanLimits,
+    registry: &ProviderRegistry,
+) -> Result<ManifestCommandOutput, IdenteditError> {
+    let manifest = Manifest {
+        version: MANIFEST_VERSION,
+        hash_algorithm: HASH_ALGORITHM.to_string(),
+        files: hash_tree(&args.dir, args.output.as_deref(), limits, registry)?,
+    };
+    let Some(output) = args.output else {
+        return Ok(ManifestCommandOutput::Manifest(manifest));
+    };
+    let rendered = serde_json::to_string_pretty(&manifest)
+        .map_err(|source| IdenteditError::ResponseSerialization { source })?;
+    fs::write(&output, rendered).map_err(|error| IdenteditError::io(&output, error))?;
+    Ok(ManifestCommandOutput::Written(ManifestWritten {
+        output,
+        files: manifest.files.len(),
+    }))
+}
+
+fn verify_manifest(
+    args: ManifestVerifyArgs,
+    limits: &ScanLimits,
+    registry: &ProviderRegistry,
+) -> Result<ManifestVerifyResponse, IdenteditError> {
+    let text = fs::read_to_string(&args.manifest)
+        .map_err(|error| IdenteditError::io(&args.manifest, error))?;
+    let manifest: Manifest =
+        serde_json::from_str(&text).map_err(|error| IdenteditError::InvalidRequest {
+            message: format!(
+                "'{}' is not an identedit manifest: {error}",
+                args.manifest.display()
+            ),
+        })?;
+    if manifest.version != MANIFEST_VERSION || manifest.hash_algorithm != HASH_ALGORITHM {
+        return Err(IdenteditError::InvalidRequest {
+            message: format!(
+                "Manifest '{}' is version {} with {} hashes; this build reads version {MANIFEST_VERSION} with {HASH_ALGORITHM} hashes, so recreate it",
+                args.manifest.display(),
+                manifest.version,
+                manifest.hash_algorithm
+            ),
+        });
+    }
+
+    let mut current = hash_tree(&args.dir, Some(&args.manifest), limits, registry)?;
+    let mut response = ManifestVerifyResponse {
+        unchanged: true,
+        added: Vec::new(),
+        removed: Vec::new(),
+        changed: Vec::new(),
+        summary: ManifestVerifySummary {
+            files: current.len(),
+            ..ManifestVerifySummary::default()
+        },
+    };
+    for (file, expected_hash) in manifest.files {
+        match current.remove(&file) {
+            None => response.removed.push(file),
+            Some(actual_hash) if actual_hash != expected_hash => {
+                response.changed.push(ChangedFile {
+                    file,
+                    expected_hash,
+                    actual_hash,
+                });
+            }
+            Some(_) => {}
+        }
+    }
+    response.added = current.into_keys().collect();
+    response.summary.added = response.added.len();
+    response.summary.removed = response.removed.len();
+    response.summary.changed = response.changed.len();
+    response.unchanged =
+        response.added.is_empty() && response.removed.is_empty() && response.changed.is_empty();
+    Ok(response)
+}
+
+/// The hash of each file under `root` by relative path, leaving out `manifest` so a
+/// manifest kept inside the tree does not list itself.
+fn hash_tree(
+    root: &Path,
+    manifest: Option<&Path>,
+    limits: &ScanLimits,
+    registry: &ProviderRegistry,
+) -> Result<BTreeMap<String, String>, IdenteditError> {
+    let manifest = manifest.and_then(|manifest| fs::canonicalize(manifest).ok());
+    let include = |path: &Path| manifest.is_none() || fs::canonicalize(path).ok() != manifest;
+    let mut files = Vec::new();
+    collect_files(root, &include, limits, &mut files)?;
+
+    let hashes = scan_parallel(&files, limits, registry, |_, file| {
+        File::open(file)
+            .and_then(hash_reader)
+            .map_err(|error| IdenteditError::io(file, error))
+    })?;
+    files
+        .iter()
+        .zip(hashes)
+        .map(|(file, hash)| Ok((manifest_key(root, file), hash?)))
+        .collect()
+}
+
+fn manifest_key(root: &Path, file: &Path) -> String {
+    file.strip_prefix(root)
+        .unwrap_or(file)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
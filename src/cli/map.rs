@@ -0,0 +1,173 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use serde::Serialize;
+
+use crate::error::{ErrorBody, IdenteditError};
+use crate::handle::LineIndex;
+use crate::hash::{HASH_ALGORITHM, hash_bytes};
+use crate::provider::ProviderRegistry;
+
+use super::scan::{ScanLimits, collect_files, scan_parallel};
+
+#[derive(Debug, Args)]
+pub struct MapArgs {
+    #[arg(
+        value_name = "DIR",
+        default_value = ".",
+        help = "Directory mapped recursively; hidden entries and dependency/build directories are skipped"
+    )]
+    pub dir: PathBuf,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Write the map as compact JSON to FILE and print only its summary"
+    )]
+    pub output: Option<PathBuf>,
+}
+
+/// Every file under a directory with its size, hash, and named nodes, for an agent to take
+/// in before its first `read`.
+#[derive(Debug, Serialize)]
+pub struct RepositoryMap {
+    #[serde(serialize_with = "crate::path_style::serialize_path")]
+    pub root: PathBuf,
+    pub hash_algorithm: &'static str,
+    pub files: Vec<MapFile>,
+    pub summary: MapSummary,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MapFile {
+    /// Relative to the map root.
+    #[serde(serialize_with = "crate::path_style::serialize_path")]
+    pub file: PathBuf,
+    pub provider: &'static str,
+    pub bytes: usize,
+    pub lines: usize,
+    pub hash: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub outline: Vec<OutlineEntry>,
+    /// Why the file has no outline, when its provider could not parse it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorBody>,
+}
+
+/// A named node; `line` and `end_line` are 1-based and inclusive.
+#[derive(Debug, Serialize)]
+pub struct OutlineEntry {
+    pub kind: String,
+    pub name: String,
+    pub line: usize,
+    pub end_line: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct MapSummary {
+    pub files: usize,
+    pub bytes: usize,
+    pub outline_entries: usize,
+    pub errors: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MapWritten {
+    #[serde(serialize_with = "crate::path_style::serialize_path")]
+    pub output: PathBuf,
+    pub summary: MapSummary,
+}
+
+pub enum MapCommandOutput {
+    Map(RepositoryMap),
+    Written(MapWritten),
+}
+
+pub fn run_map(
+    args: MapArgs,
+    limits: ScanLimits,
+    registry: &ProviderRegistry,
+) -> Result<MapCommandOutput, IdenteditError> {
+    let mut files = Vec::new();
+    // A map written inside the mapped directory would otherwise list its previous version.
+    let output = args
+        .output
+        .as_deref()
+        .and_then(|output| fs::canonicalize(output).ok());
+    let include = |path: &Path| output.is_none() || fs::canonicalize(path).ok() != output;
+    collect_files(&args.dir, &include, &limits, &mut files)?;
+
+    let files = scan_parallel(&files, &limits, registry, |registry, file| {
+        map_file(&args.dir, file, registry)
+    })?
+    .into_iter()
+    .collect::<Result<Vec<_>, _>>()?;
+    let mut summary = MapSummary::default();
+    for file in &files {
+        summary.files += 1;
+        summary.bytes += file.bytes;
+        summary.outline_entries += file.outline.len();
+        summary.errors += usize::from(file.error.is_some());
+    }
+    let map = RepositoryMap {
+        root: args.dir,
+        hash_algorithm: HASH_ALGORITHM,
+        files,
+        summary,
+    };
+
+    let Some(output) = args.output else {
+        return Ok(MapCommandOutput::Map(map));
+    };
+    let rendered = serde_json::to_string(&map)
+        .map_err(|source| IdenteditError::ResponseSerialization { source })?;
+    fs::write(&output, rendered).map_err(|error| IdenteditError::io(&output, error))?;
+    Ok(MapCommandOutput::Written(MapWritten {
+        output,
+        summary: map.summary,
+    }))
+}
+
+fn map_file(
+    root: &Path,
+    file: &Path,
+    registry: &ProviderRegistry,
+) -> Result<MapFile, IdenteditError> {
+    let source = fs::read(file).map_err(|error| IdenteditError::io(file, error))?;
+    let provider = registry.provider_for(file)?;
+    let (outline, error) = match provider.parse(file, &source) {
+        Ok(handles) => {
+            let lines = LineIndex::new(&source);
+            let mut seen = HashSet::new();
+            let outline = handles
+                .into_iter()
+                .filter_map(|handle| {
+                    let name = handle.name?;
+                    let line = lines.position(handle.span.start).line;
+                    let end = handle.span.end.max(handle.span.start + 1) - 1;
+                    // Wrappers such as decorated definitions repeat the name they wrap.
+                    seen.insert((name.clone(), line)).then(|| OutlineEntry {
+                        kind: handle.kind,
+                        name,
+                        line,
+                        end_line: lines.position(end).line,
+                    })
+                })
+                .collect();
+            (outline, None)
+        }
+        Err(error) => (Vec::new(), Some(error.to_error_response().error)),
+    };
+    let lines = source.iter().filter(|byte| **byte == b'\n').count()
+        + usize::from(source.last().is_some_and(|byte| *byte != b'\n'));
+    Ok(MapFile {
+        file: file.strip_prefix(root).unwrap_or(file).to_path_buf(),
+        provider: provider.name(),
+        bytes: source.len(),
+        lines,
+        hash: hash_bytes(&source),
+        outline,
+        error,
+    })
+}
@@ -2,16 +2,14 @@ use std::collections::BTreeMap;
 use std::path::{Component, Path, PathBuf};
 
 use crate::changeset::{ChangeOp, FileChange, MultiFileChangeset, OpKind};
+use crate::compression::read_plan_file;
 use crate::error::IdenteditError;
 
-pub fn run_merge_inputs(
-    inputs: Vec<PathBuf>,
-) -> Result<MultiFileChangeset, IdenteditError> {
+pub fn run_merge_inputs(inputs: Vec<PathBuf>) -> Result<MultiFileChangeset, IdenteditError> {
     let mut merged_by_file = BTreeMap::<String, FileChange>::new();
 
     for input in &inputs {
-        let content =
-            std::fs::read_to_string(input).map_err(|error| IdenteditError::io(input, error))?;
+        let content = read_plan_file(input)?;
         let changeset: MultiFileChangeset = serde_json::from_str(&content)
             .map_err(|source| IdenteditError::InvalidJsonRequest { source })?;
 
@@ -48,6 +46,7 @@ pub fn run_merge_inputs(
     Ok(MultiFileChangeset {
         files,
         transaction: Default::default(),
+        correlation_id: None,
     })
 }
 
@@ -195,12 +194,17 @@ mod tests {
 
     fn op(kind: OpKind, span: Span) -> ChangeOp {
         ChangeOp {
+            id: None,
+            depends_on: Vec::new(),
+            correlation_id: None,
+            group: None,
             target: TransformTarget::node(
                 "id".to_string(),
                 "function_definition".to_string(),
                 Some(span),
                 "hash".to_string(),
             ),
+            optional: false,
             op: kind,
             preview: ChangePreview {
                 old_text: Some(String::new()),
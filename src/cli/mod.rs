@@ -1,15 +1,72 @@
-use clap::{Parser, Subcommand};
+use std::num::NonZeroUsize;
 
+use clap::{Args, Parser, Subcommand};
+
+use crate::apply::{ApplySettings, Durability};
+use crate::cli::scan::ScanLimits;
+use crate::compression::ContentEncoding;
+use crate::error::IdenteditError;
+use crate::path_style::PathStyle;
+use crate::project_config::{ChangeGuards, ProjectConfig, load_project_config};
+use crate::provider::{HeaderDialect, ProviderOverrides};
+
+pub mod analyze;
+pub mod anchors;
 pub mod apply;
+pub mod capabilities;
+pub mod completions;
+pub mod detect;
+pub mod diff_handle;
 mod merge_plan;
 mod line_patch;
+mod patch_retry;
 pub mod edit;
 pub mod grammar;
+pub mod hook;
+pub mod lease;
+pub mod locate;
+pub mod manifest;
+pub mod map;
 pub mod merge;
 pub mod patch;
+pub mod pin;
+pub mod plan;
 pub mod read;
+pub mod refactor;
+pub mod revert;
+pub mod scan;
+pub mod selftest;
+pub mod stats;
+pub mod toolspec;
+pub mod verify;
+mod analyze_imports;
+mod analyze_markers;
+mod analyze_sarif;
+mod analyze_unused;
+mod read_context;
+mod read_annotations;
+mod read_budget;
+mod read_metrics;
+mod read_page;
+mod read_redact;
+mod read_resolve;
+mod read_rev;
+mod refactor_inline;
 mod read_select;
+mod read_window;
 mod edit_build;
+mod edit_codemod;
+mod edit_fan_out;
+mod script;
+#[cfg(feature = "wasm")]
+mod edit_wasm;
+mod diff_plan;
+mod inverse;
+mod plan_export;
+mod plan_hash;
+mod plan_rebase;
+mod preflight_cmd;
+mod reread;
 
 #[derive(Debug, Parser)]
 #[command(name = "identedit")]
@@ -20,6 +77,171 @@ mod edit_build;
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+    #[command(flatten)]
+    pub global: GlobalArgs,
+}
+
+/// Flags every subcommand takes.
+#[derive(Debug, Args)]
+pub struct GlobalArgs {
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = PathStyle::Native,
+        help = "Separator style for file paths in handles, plans, and responses; posix lets Windows plans apply from WSL"
+    )]
+    pub path_style: PathStyle,
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = Durability::Dir,
+        help = "What rewrites fsync: none, the new file (file), or the file and its directory (dir)"
+    )]
+    pub durability: Durability,
+    #[arg(
+        long,
+        global = true,
+        value_name = "N",
+        help = "Cut read handle texts, never metadata, to keep the JSON response within N bytes; summary.truncation.next_offset continues"
+    )]
+    pub max_response_bytes: Option<usize>,
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_name = "ENCODING",
+        help = "Compress the response and print it as {\"encoding\", \"body\"} JSON with a base64 body; plan input is decompressed automatically"
+    )]
+    pub accept_encoding: Option<ContentEncoding>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "NAME",
+        help = "Session this process acts for: writes skip its own leases and fail on other sessions'; defaults to $IDENTEDIT_SESSION"
+    )]
+    pub session: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "MS",
+        help = "Abandon directory walks and scans (map, analyze, manifest, refactor) with a timeout error after MS milliseconds"
+    )]
+    pub timeout_ms: Option<u64>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "N",
+        help = "Parse or hash at most N files at once in directory scans (default: one per core)"
+    )]
+    pub max_concurrency: Option<NonZeroUsize>,
+    #[arg(
+        long,
+        global = true,
+        help = "Add this command's outcome to the counters shown by identedit stats; defaults to $IDENTEDIT_RECORD_STATS=1"
+    )]
+    pub record_stats: bool,
+    #[arg(
+        long,
+        global = true,
+        help = "Apply edits that the destructive-change guards would stop. Without a config, \
+                one apply may change at most 80% of a file of 20 or more lines"
+    )]
+    pub force: bool,
+    #[arg(
+        long,
+        global = true,
+        help = "Write new text that the secret_scan of .identedit/config.json flags as a credential"
+    )]
+    pub allow_secrets: bool,
+    #[arg(
+        long = "disable-provider",
+        global = true,
+        value_name = "PROVIDER",
+        help = "Leave a provider out of lookup, by provider or language name, e.g. tree-sitter-sql or sql (repeatable)"
+    )]
+    pub disabled_providers: Vec<String>,
+    #[arg(
+        long = "prefer",
+        global = true,
+        value_name = "EXT=PROVIDER",
+        value_parser = parse_preference,
+        help = "Send files with extension EXT to PROVIDER (provider or language name) ahead of lookup order, e.g. sql=fallback (repeatable)"
+    )]
+    pub preferred_providers: Vec<(String, String)>,
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_name = "LANG",
+        help = "Parse .h files as C or C++ instead of picking the grammar that parses without errors"
+    )]
+    pub header_lang: Option<HeaderDialect>,
+}
+
+impl GlobalArgs {
+    /// The settings writing and verifying commands put on their transactions, from these
+    /// flags and the project config.
+    pub fn apply_settings(&self) -> Result<ApplySettings, IdenteditError> {
+        let project_config = load_project_config()?;
+        let providers = self.provider_overrides(&project_config);
+        let guards = if self.force {
+            ChangeGuards {
+                max_deleted_lines_per_op: None,
+                max_file_fraction_changed: None,
+            }
+        } else {
+            project_config.guards
+        };
+        Ok(ApplySettings {
+            durability: self.durability,
+            guards,
+            secret_scan: project_config.secret_scan && !self.allow_secrets,
+            protected_regions: project_config.protected_regions,
+            project_root: project_config.root,
+            session: self.session(),
+            providers,
+            invalidated_identities: false,
+        })
+    }
+
+    /// The provider overrides from `--disable-provider`, `--prefer`, `--header-lang`, and the
+    /// `extensions` of `project_config`.
+    pub fn provider_overrides(&self, project_config: &ProjectConfig) -> ProviderOverrides {
+        ProviderOverrides {
+            disabled: self.disabled_providers.clone(),
+            preferred: self.preferred_providers.clone(),
+            header_dialect: self.header_lang,
+            mapped: project_config
+                .extensions
+                .iter()
+                .map(|(extension, provider)| (extension.clone(), provider.clone()))
+                .collect(),
+        }
+    }
+
+    /// The session this process acts for, from `--session` or `IDENTEDIT_SESSION`.
+    pub fn session(&self) -> Option<String> {
+        self.session
+            .clone()
+            .or_else(|| std::env::var("IDENTEDIT_SESSION").ok())
+            .filter(|session| !session.is_empty())
+    }
+
+    /// The limits of directory walks and scans, with the deadline counted from now.
+    pub fn scan_limits(&self) -> ScanLimits {
+        ScanLimits::new(self.timeout_ms, self.max_concurrency)
+    }
+}
+
+fn parse_preference(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((extension, provider)) if !provider.is_empty() => {
+            Ok((extension.to_string(), provider.to_string()))
+        }
+        _ => Err("expected EXT=PROVIDER, e.g. sql=fallback".to_string()),
+    }
 }
 
 #[derive(Debug, Subcommand)]
@@ -30,10 +252,67 @@ pub enum Commands {
     Edit(edit::EditArgs),
     #[command(about = "Commit a prepared edit plan to one or more files")]
     Apply(apply::ApplyArgs),
+    #[command(
+        about = "Check that an edit plan still applies, per operation, without writing files"
+    )]
+    Verify(verify::VerifyArgs),
+    #[command(
+        about = "Undo an apply with the inverse plan it wrote, if the lines it touched are unchanged"
+    )]
+    Revert(revert::RevertArgs),
     #[command(about = "Merge multiple edit plans with strict conflict checks")]
     Merge(merge::MergeArgs),
-    #[command(about = "Install dynamic tree-sitter grammars")]
+    #[command(about = "Install, remove, and clean up dynamic tree-sitter grammars")]
     Grammar(grammar::GrammarArgs),
     #[command(about = "One-shot single-target patch (build + apply)")]
     Patch(Box<patch::PatchArgs>),
+    #[command(about = "Bookmark node identities under aliases for later patches")]
+    Pin(pin::PinArgs),
+    #[command(about = "Build edit plans from other formats")]
+    Plan(plan::PlanArgs),
+    #[command(
+        about = "Emit verified line anchors for every line a git diff added to the working tree"
+    )]
+    Anchors(anchors::AnchorsArgs),
+    #[command(about = "Git hook checks, such as rejecting staged files with new syntax errors")]
+    Hook(hook::HookArgs),
+    #[command(about = "Run built-in edit property checks against sample files")]
+    Selftest(selftest::SelftestArgs),
+    #[command(about = "Resolve compiler diagnostics or stack traces to enclosing node handles")]
+    Locate(locate::LocateArgs),
+    #[command(
+        name = "diff-handle",
+        about = "Check a stored handle against the file and diff its node text if it changed"
+    )]
+    DiffHandle(diff_handle::DiffHandleArgs),
+    #[command(about = "Project-wide refactors that build one multi-file edit plan")]
+    Refactor(refactor::RefactorArgs),
+    #[command(
+        about = "Map every file under a directory with its size, hash, and outline of named nodes"
+    )]
+    Map(map::MapArgs),
+    #[command(about = "Static analyses across a directory, such as the import graph")]
+    Analyze(analyze::AnalyzeArgs),
+    #[command(
+        about = "Record file hashes under a directory and later detect added, removed, or changed files"
+    )]
+    Manifest(manifest::ManifestArgs),
+    #[command(
+        about = "Show counters recorded with --record-stats: requests, failures, durations, and error types per command"
+    )]
+    Stats(stats::StatsArgs),
+    #[command(about = "Lease files to a session so writes from other sessions fail until release")]
+    Lease(lease::LeaseArgs),
+    #[command(
+        about = "Report the provider each file goes to and why, with extension, shebang, modeline, and binary evidence"
+    )]
+    Detect(detect::DetectArgs),
+    #[command(
+        about = "Report supported commands, op and target types, providers, limits, and the stable API contract"
+    )]
+    Capabilities,
+    #[command(about = "Print a shell completion script")]
+    Completions(completions::CompletionsArgs),
+    #[command(about = "Emit agent tool definitions (openai or anthropic) for every command")]
+    Toolspec(toolspec::ToolspecArgs),
 }
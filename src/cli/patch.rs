@@ -1,27 +1,56 @@
 use std::io::Read;
 use std::path::{Path, PathBuf};
 
-use clap::Args;
+use clap::{Args, ValueEnum};
+use regex::Regex;
 use serde::Deserialize;
-use serde_json::Value;
+use serde_json::{Value, json};
 
-use crate::apply::apply_multi_file_changeset;
-use crate::changeset::{MultiFileChangeset, OpKind, TransformTarget, hash_text};
+use crate::apply::{
+    ApplyResponse, ApplySettings, WholeFilePrecondition, apply_multi_file_changeset,
+    write_byte_span, write_whole_file,
+};
+use crate::changeset::{MultiFileChangeset, OpKind, TransactionSpec, TransformTarget, hash_text};
 use crate::cli::apply::shape_apply_response;
 use crate::error::IdenteditError;
 use crate::handle::Span;
 use crate::hash::{HASH_HEX_LEN, hash_bytes};
-use crate::hashline::{HASHLINE_PUBLIC_HEX_LEN, parse_line_ref};
+use crate::hashline::{HASHLINE_LONG_HEX_LEN, HASHLINE_PUBLIC_HEX_LEN, parse_line_ref};
 use crate::hashline::{HashlineEdit, InsertAfterEdit, ReplaceLinesEdit, SetLineEdit};
-use crate::patch::config_path::{ConfigPathOperation, resolve_config_path_operation};
+use crate::patch::annotation::{
+    AnnotationMarker, AnnotationSpec, annotate_node_target, annotation_insert_text,
+};
+use crate::patch::config_path::{
+    ConfigPathOperation, ConfigValueType, DependencyOperation, MergeConflictPolicy, MergeObject,
+    check_config_value_hash, check_single_target_match_count, encode_config_value,
+    resolve_config_dependency_operation, resolve_config_merge_operation,
+    resolve_config_move_operation, resolve_config_path_pattern_operation,
+};
+use crate::patch::editorconfig::EditorconfigStyle;
 use crate::patch::engine::run_resolve_verify_apply;
-use crate::patch::scoped_regex::rewrite_node_target_with_scoped_regex;
+use crate::patch::file_edge::{FileEdge, file_edge_insert_text};
+use crate::patch::indentation::{Reindent, insertion_context, reindent_text};
+use crate::patch::marker_region::{MarkerRegionOp, marker_region_instruction, marker_region_text};
+use crate::patch::scoped_regex::{
+    ScopedRegexMatch, ScopedRegexOptions, compile_scoped_regex,
+    find_scoped_regex_matches_in_node_target, protected_ranges,
+    rewrite_node_target_with_scoped_regex, zero_matches_error,
+};
+use crate::patch::upsert::{UpsertAction, UpsertSpec, upsert_instruction};
+use crate::path_style::normalize_path;
+use crate::pin::{find_pin, refresh_pin};
+use crate::provider::ProviderRegistry;
+use crate::text_encoding::TextEncoding;
 use crate::transform::{
     TransformInstruction, build_changeset, build_delete_changeset, build_insert_after_changeset,
     build_insert_before_changeset, build_replace_changeset, parse_handles_for_file,
 };
 
-use super::line_patch::{HashlinePatchResponse, execute_hashline_patch};
+use super::line_patch::{HashlinePatchResponse, LineMatch, LineTargetEdit, execute_hashline_patch};
+use super::patch_retry::{StaleNode, run_with_stale_retries};
+use super::preflight_cmd::{PreflightCheck, run_preflight_cmds};
+use super::reread::{RereadBaseline, RereadScope};
+use super::script::ReplacementScript;
 
 #[derive(Debug, Args)]
 pub struct PatchArgs {
@@ -33,6 +62,12 @@ pub struct PatchArgs {
         help = "Unified target selector: node identity (hex16), line anchor (line:hex12), or file-start/file-end"
     )]
     pub at: Option<String>,
+    #[arg(
+        long,
+        value_name = "ALIAS",
+        help = "Target the node saved with `identedit pin add` (node flag mode; FILE optional)"
+    )]
+    pub pin: Option<String>,
     #[arg(
         long,
         value_name = "IDENTITY",
@@ -53,6 +88,13 @@ pub struct PatchArgs {
         help = "Optional end line anchor for --replace-range (line flag mode)"
     )]
     pub end_anchor: Option<String>,
+    #[arg(
+        long,
+        value_name = "N",
+        allow_hyphen_values = true,
+        help = "Target the line N lines below (or above, if negative) the verified line anchor (line flag mode)"
+    )]
+    pub offset: Option<isize>,
     #[arg(
         long = "config-path",
         value_name = "PATH",
@@ -65,18 +107,140 @@ pub struct PatchArgs {
         help = "Replace target node with text (node flag mode)"
     )]
     pub replace: Option<String>,
+    #[arg(
+        long = "replace-file",
+        value_name = "PATH",
+        conflicts_with_all = ["replace", "replace_stdin"],
+        help = "Like --replace, reading the text verbatim from a file (node flag mode)"
+    )]
+    pub replace_file: Option<PathBuf>,
+    #[arg(
+        long = "replace-stdin",
+        conflicts_with_all = ["replace", "json"],
+        help = "Like --replace, reading the text verbatim from stdin (node flag mode)"
+    )]
+    pub replace_stdin: bool,
     #[arg(
         long = "set-value",
         value_name = "TEXT",
         help = "Set config path value text (config path flag mode)"
     )]
     pub set_value: Option<String>,
+    #[arg(
+        long = "set-string",
+        value_name = "TEXT",
+        help = "Set config path to a string, quoted for the file's format (config path flag mode)"
+    )]
+    pub set_string: Option<String>,
+    #[arg(
+        long = "set-int",
+        value_name = "INT",
+        allow_hyphen_values = true,
+        help = "Set config path to an integer (config path flag mode)"
+    )]
+    pub set_int: Option<String>,
+    #[arg(
+        long = "set-bool",
+        value_name = "BOOL",
+        help = "Set config path to true or false (config path flag mode)"
+    )]
+    pub set_bool: Option<String>,
+    #[arg(
+        long = "set-raw",
+        value_name = "TEXT",
+        help = "Set config path to literal value text, same as --set-value (config path flag mode)"
+    )]
+    pub set_raw: Option<String>,
     #[arg(
         long = "append-value",
         value_name = "TEXT",
         help = "Append value text to target array at config path (config path flag mode)"
     )]
     pub append_value: Option<String>,
+    #[arg(
+        long = "insert-at",
+        value_name = "INDEX",
+        requires = "insert_value",
+        help = "Insert --insert-value before this index of the target array (config path flag mode)"
+    )]
+    pub insert_at: Option<usize>,
+    #[arg(
+        long = "insert-value",
+        value_name = "TEXT",
+        requires = "insert_at",
+        help = "Value text inserted by --insert-at (config path flag mode)"
+    )]
+    pub insert_value: Option<String>,
+    #[arg(
+        long = "remove-at",
+        value_name = "INDEX",
+        help = "Remove the element at this index of the target array (config path flag mode)"
+    )]
+    pub remove_at: Option<usize>,
+    #[arg(
+        long = "merge-object",
+        value_name = "JSON",
+        help = "Deep-merge a JSON object into the mapping at config path (config path flag mode)"
+    )]
+    pub merge_object: Option<String>,
+    #[arg(
+        long = "on-conflict",
+        value_enum,
+        value_name = "POLICY",
+        requires = "merge_object",
+        help = "How --merge-object treats existing values that differ [default: overwrite]"
+    )]
+    pub on_conflict: Option<MergeConflictArg>,
+    #[arg(
+        long = "rename-key",
+        value_name = "NEW_KEY",
+        help = "Rename the mapping key at config path, keeping its value (config path flag mode)"
+    )]
+    pub rename_key: Option<String>,
+    #[arg(
+        long = "move-to",
+        value_name = "PATH",
+        help = "Move the value at config path to another path in the same file (config path flag mode)"
+    )]
+    pub move_to: Option<String>,
+    #[arg(
+        long = "add-dependency",
+        value_name = "NAME",
+        requires = "dependency_version",
+        help = "Add a dependency to the Cargo.toml/package.json section at config path (config path flag mode)"
+    )]
+    pub add_dependency: Option<String>,
+    #[arg(
+        long = "remove-dependency",
+        value_name = "NAME",
+        help = "Remove a dependency from the Cargo.toml/package.json section at config path (config path flag mode)"
+    )]
+    pub remove_dependency: Option<String>,
+    #[arg(
+        long = "set-dependency-version",
+        value_name = "NAME",
+        requires = "dependency_version",
+        help = "Change the version of a dependency in the section at config path (config path flag mode)"
+    )]
+    pub set_dependency_version: Option<String>,
+    #[arg(
+        long = "dependency-version",
+        value_name = "VERSION",
+        help = "Version used by --add-dependency/--set-dependency-version (config path flag mode)"
+    )]
+    pub dependency_version: Option<String>,
+    #[arg(
+        long = "expected-match-count",
+        value_name = "N",
+        help = "Fail unless the config path (wildcards allowed: '*', '[*]') matches exactly N targets"
+    )]
+    pub expected_match_count: Option<usize>,
+    #[arg(
+        long = "doc",
+        value_name = "KIND/NAME",
+        help = "Select the YAML document with this kind and metadata.name (config path flag mode)"
+    )]
+    pub doc: Option<String>,
     #[arg(
         long = "create-missing",
         help = "Allow config path set to create missing map/table keys (not array indexes)"
@@ -88,6 +252,47 @@ pub struct PatchArgs {
         help = "Insert text for file-start/file-end targets"
     )]
     pub insert: Option<String>,
+    #[arg(
+        long = "write-file",
+        value_name = "TEXT",
+        help = "Replace the whole file with text, or create it; needs --expected-file-hash or --expect-absent"
+    )]
+    pub write_file: Option<String>,
+    #[arg(
+        long = "expected-file-hash",
+        value_name = "HASH",
+        requires = "write_file",
+        conflicts_with = "expect_absent",
+        help = "Hash the file must still have for --write-file to replace it (from read's file_preconditions)"
+    )]
+    pub expected_file_hash: Option<String>,
+    #[arg(
+        long = "expect-absent",
+        requires = "write_file",
+        help = "Let --write-file create the file, failing if it already exists"
+    )]
+    pub expect_absent: bool,
+    #[arg(
+        long = "byte-span",
+        value_name = "START:END",
+        conflicts_with = "json",
+        help = "Replace bytes START..END of FILE, which need not be UTF-8; needs --expected-bytes-hash and --replace-bytes-file"
+    )]
+    pub byte_span: Option<String>,
+    #[arg(
+        long = "expected-bytes-hash",
+        value_name = "HASH",
+        requires = "byte_span",
+        help = "Hash the bytes of --byte-span must still have"
+    )]
+    pub expected_bytes_hash: Option<String>,
+    #[arg(
+        long = "replace-bytes-file",
+        value_name = "PATH",
+        requires = "byte_span",
+        help = "File whose bytes replace --byte-span verbatim"
+    )]
+    pub replace_bytes_file: Option<PathBuf>,
     #[arg(
         long = "scoped-regex",
         value_name = "PATTERN",
@@ -100,6 +305,32 @@ pub struct PatchArgs {
         help = "Replacement text used with --scoped-regex (node flag mode)"
     )]
     pub scoped_replacement: Option<String>,
+    #[arg(
+        long,
+        requires = "scoped_regex",
+        help = "Leave --scoped-regex matches inside string literals alone; interpolations are still rewritten"
+    )]
+    pub skip_strings: bool,
+    #[arg(
+        long,
+        requires = "scoped_regex",
+        help = "Leave --scoped-regex matches inside comments alone"
+    )]
+    pub skip_comments: bool,
+    #[arg(
+        long,
+        requires = "scoped_regex",
+        conflicts_with_all = ["scoped_replacement", "script"],
+        help = "Report --scoped-regex match count and spans without modifying the file"
+    )]
+    pub count_only: bool,
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with = "scoped_replacement",
+        help = "Rhai script computing the node's replacement, or each --scoped-regex match's (node flag mode)"
+    )]
+    pub script: Option<PathBuf>,
     #[arg(long, help = "Delete target node (node flag mode)")]
     pub delete: bool,
     #[arg(
@@ -114,6 +345,33 @@ pub struct PatchArgs {
         help = "Insert text immediately after target node (node flag mode)"
     )]
     pub insert_after: Option<String>,
+    #[arg(
+        long,
+        value_name = "MESSAGE",
+        help = "Insert a marker comment above the target node (node flag mode)"
+    )]
+    pub annotate: Option<String>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = AnnotationMarker::Todo,
+        requires = "annotate",
+        help = "Marker used by --annotate (todo|fixme|hack|xxx)"
+    )]
+    pub marker: AnnotationMarker,
+    #[arg(
+        long,
+        value_name = "ID",
+        requires = "annotate",
+        help = "Ticket id written as MARKER(ID) by --annotate"
+    )]
+    pub ticket: Option<String>,
+    #[arg(
+        long,
+        requires = "annotate",
+        help = "Stamp the --annotate marker with today's UTC date"
+    )]
+    pub timestamp: bool,
     #[arg(
         long = "set-line",
         value_name = "TEXT",
@@ -137,21 +395,191 @@ pub struct PatchArgs {
         help = "If line-mode strict check fails with deterministic remap candidates, run one repair retry"
     )]
     pub auto_repair: bool,
+    #[arg(
+        long = "text-encoding",
+        value_enum,
+        default_value_t = TextEncoding::Utf8,
+        help = "Encoding of text flags and of op text fields in --json input (utf8|base64)"
+    )]
+    pub text_encoding: TextEncoding,
+    #[arg(
+        long = "respect-editorconfig",
+        help = "Restyle inserted and replacement text by the file's .editorconfig: indentation, line endings, final newline"
+    )]
+    pub respect_editorconfig: bool,
+    #[arg(
+        long,
+        value_enum,
+        value_name = "MODE",
+        help = "Reindent --insert-before/--insert-after/--insert-after-line text to the insertion point's depth (target)"
+    )]
+    pub reindent: Option<Reindent>,
     #[arg(long, help = "Include per-file apply results in output (flag mode)")]
     pub verbose: bool,
+    #[arg(
+        long = "reread-after",
+        help = "Include the handles the patch created in the response: new node handles, or fresh anchors around the changed lines for line targets"
+    )]
+    pub reread_after: bool,
+    #[arg(
+        long = "invalidated-identities",
+        help = "List in the stats the node identities the patch invalidated, parsing the file before and after (implied by --reread-after)"
+    )]
+    pub invalidated_identities: bool,
+    #[arg(
+        long = "preflight-cmd",
+        value_name = "PROGRAM",
+        help = "Run PROGRAM before writing, with the target file as JSON on stdin; a non-zero exit aborts the patch (repeatable)"
+    )]
+    pub preflight_cmds: Vec<PathBuf>,
+    #[arg(
+        long = "retry-on-stale",
+        value_name = "N",
+        default_value_t = 0,
+        help = "On precondition_failed, re-read the file, re-resolve the node target, and retry up to N times (node targets only)"
+    )]
+    pub retry_on_stale: usize,
     #[arg(value_name = "FILE", help = "Target file path in flag mode")]
     pub file: Option<PathBuf>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum MergeConflictArg {
+    Overwrite,
+    Keep,
+    Error,
+}
+
+impl From<MergeConflictArg> for MergeConflictPolicy {
+    fn from(value: MergeConflictArg) -> Self {
+        match value {
+            MergeConflictArg::Overwrite => MergeConflictPolicy::Overwrite,
+            MergeConflictArg::Keep => MergeConflictPolicy::Keep,
+            MergeConflictArg::Error => MergeConflictPolicy::Error,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(deny_unknown_fields)]
 struct StdinPatchRequest {
     command: String,
+    #[serde(deserialize_with = "crate::path_style::deserialize_path")]
     file: PathBuf,
     target: StdinPatchTarget,
     op: Value,
     #[serde(default)]
     options: StdinPatchOptions,
+    #[serde(default)]
+    correlation_id: Option<String>,
+}
+
+/// The `op.object` of a `merge_object` request, read straight from the body text.
+#[derive(Debug, Deserialize)]
+struct StdinMergeObjectRequest {
+    op: StdinMergeObjectOp,
+}
+
+#[derive(Debug, Deserialize)]
+struct StdinMergeObjectOp {
+    object: MergeObject,
+}
+
+/// Several operations on one file, resolved against the same source and applied in one
+/// bottom-up pass.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StdinMultiPatchRequest {
+    command: String,
+    #[serde(deserialize_with = "crate::path_style::deserialize_path")]
+    file: PathBuf,
+    operations: Vec<StdinPatchOperation>,
+    #[serde(default)]
+    options: StdinPatchOptions,
+    #[serde(default)]
+    correlation_id: Option<String>,
+}
+
+/// Several line edits on one file under a single `line` target, checked together and
+/// written once.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StdinLineBatchRequest {
+    command: String,
+    #[serde(deserialize_with = "crate::path_style::deserialize_path")]
+    file: PathBuf,
+    target: StdinLineBatchTarget,
+    #[serde(default)]
+    options: StdinPatchOptions,
+    #[serde(default)]
+    correlation_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum StdinLineBatchTarget {
+    Line { edits: Vec<StdinLineEdit> },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StdinLineEdit {
+    #[serde(default)]
+    anchor: Option<String>,
+    #[serde(default, rename = "match")]
+    pattern: Option<String>,
+    #[serde(default)]
+    occurrence: Option<usize>,
+    #[serde(default)]
+    expected_match_count: Option<usize>,
+    #[serde(default)]
+    offset: isize,
+    #[serde(default)]
+    end_anchor: Option<String>,
+    op: Value,
+}
+
+impl StdinLineEdit {
+    fn into_target_and_op(self) -> (StdinLineTarget, Value) {
+        let target = StdinLineTarget {
+            anchor: self.anchor,
+            pattern: self.pattern,
+            occurrence: self.occurrence,
+            expected_match_count: self.expected_match_count,
+            offset: self.offset,
+            end_anchor: self.end_anchor,
+        };
+        (target, self.op)
+    }
+}
+
+/// Where a line edit starts: an `anchor`, or a `match` regex resolved against the file as
+/// it is read, optionally shifted by `offset`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StdinLineTarget {
+    #[serde(default)]
+    anchor: Option<String>,
+    #[serde(default, rename = "match")]
+    pattern: Option<String>,
+    /// 1-based pick among the lines `match` finds; without it exactly one line must match.
+    #[serde(default)]
+    occurrence: Option<usize>,
+    #[serde(default)]
+    expected_match_count: Option<usize>,
+    /// Lines from the anchor to the edited line; only the anchor is verified.
+    #[serde(default)]
+    offset: isize,
+    #[serde(default)]
+    end_anchor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StdinPatchOperation {
+    target: StdinPatchTarget,
+    op: Value,
 }
 
 #[derive(Debug, Deserialize, Default)]
@@ -179,19 +607,31 @@ enum StdinPatchTarget {
     FileEnd {
         expected_file_hash: String,
     },
-    Line {
-        anchor: String,
+    /// The whole file, for `append_file`/`prepend_file`/`upsert`.
+    File {
+        expected_file_hash: String,
+    },
+    Line(StdinLineTarget),
+    /// The lines between `identedit:begin <name>` and `identedit:end <name>` comments.
+    MarkerRegion {
+        name: String,
         #[serde(default)]
-        end_anchor: Option<String>,
+        expected_region_hash: Option<String>,
     },
     ConfigPath {
         path: String,
         #[serde(default)]
+        document: Option<String>,
+        #[serde(default)]
         expected_file_hash: Option<String>,
+        #[serde(default)]
+        expected_match_count: Option<usize>,
+        #[serde(default)]
+        expected_value_hash: Option<String>,
     },
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
 enum NodePatchOp {
     Replace {
@@ -199,7 +639,16 @@ enum NodePatchOp {
     },
     ScopedRegex {
         pattern: String,
-        replacement: String,
+        /// Required unless `dry` is set.
+        #[serde(default)]
+        replacement: Option<String>,
+        #[serde(default)]
+        skip_strings: bool,
+        #[serde(default)]
+        skip_comments: bool,
+        /// Report the matches instead of rewriting them.
+        #[serde(default)]
+        dry: bool,
     },
     Delete,
     InsertBefore {
@@ -208,6 +657,8 @@ enum NodePatchOp {
     InsertAfter {
         new_text: String,
     },
+    /// A marker comment on its own line above the node.
+    Annotate(AnnotationSpec),
 }
 
 #[derive(Debug, Deserialize)]
@@ -231,23 +682,131 @@ enum FilePatchOp {
     Insert { new_text: String },
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum WholeFilePatchOp {
+    AppendFile {
+        new_text: String,
+    },
+    PrependFile {
+        new_text: String,
+    },
+    Upsert(UpsertSpec),
+    /// A new marker region holding `new_text`, appended to the file.
+    CreateMarkerRegion {
+        name: String,
+        new_text: String,
+    },
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
 enum ConfigPatchOp {
     Set {
         new_text: String,
         #[serde(default)]
+        value_type: ConfigValueType,
+        #[serde(default)]
         create_missing: bool,
     },
     Append {
         new_text: String,
+        #[serde(default)]
+        value_type: ConfigValueType,
+    },
+    InsertAt {
+        index: usize,
+        new_text: String,
+        #[serde(default)]
+        value_type: ConfigValueType,
+    },
+    RemoveAt {
+        index: usize,
+    },
+    MergeObject {
+        object: MergeObject,
+        #[serde(default)]
+        on_conflict: MergeConflictPolicy,
+    },
+    RenameKey {
+        new_key: String,
+    },
+    Move {
+        to: String,
+    },
+    AddDependency {
+        name: String,
+        version: String,
+    },
+    RemoveDependency {
+        name: String,
+    },
+    SetDependencyVersion {
+        name: String,
+        version: String,
     },
     Delete,
 }
 
-pub fn run_patch(args: PatchArgs) -> Result<Value, IdenteditError> {
+pub fn run_patch(args: PatchArgs, settings: ApplySettings) -> Result<Value, IdenteditError> {
+    let settings = ApplySettings {
+        invalidated_identities: args.invalidated_identities || args.reread_after,
+        ..settings
+    };
+    if args.reindent.is_some()
+        && (args.json
+            || (args.insert_before.is_none()
+                && args.insert_after.is_none()
+                && args.insert_after_line.is_none()))
+    {
+        return Err(IdenteditError::InvalidRequest {
+            message: "--reindent applies only to --insert-before, --insert-after and --insert-after-line in flag mode".to_string(),
+        });
+    }
     if args.json {
-        return run_patch_json_mode();
+        return run_patch_json_mode(
+            args.text_encoding,
+            args.retry_on_stale,
+            args.respect_editorconfig,
+            args.reread_after,
+            &args.preflight_cmds,
+            &settings,
+        );
+    }
+    let file = flag_patch_file(&args)?;
+    let preflight = run_preflight_cmds(&args.preflight_cmds, "patch", file.as_deref())?;
+    let baseline = match file {
+        Some(file) if args.reread_after => {
+            let lines = matches!(
+                resolve_patch_flag_target(&args),
+                Ok(PatchFlagTarget::LineAnchor(_))
+            );
+            Some(RereadBaseline::capture([(
+                file,
+                RereadScope {
+                    nodes: !lines,
+                    lines,
+                },
+            )]))
+        }
+        _ => None,
+    };
+    let mut response = run_patch_flag_mode(args, &settings)?;
+    attach_reread(&mut response, baseline, &settings.provider_registry()?)?;
+    attach_preflight(&mut response, preflight)?;
+    Ok(response)
+}
+
+fn run_patch_flag_mode(args: PatchArgs, settings: &ApplySettings) -> Result<Value, IdenteditError> {
+    if let Some(span) = args.byte_span.clone() {
+        return run_patch_flag_byte_span(&span, args, settings);
+    }
+    let args = restyle_text_flags(read_replace_input(decode_text_flags(args)?)?)?;
+    if let Some(contents) = args.write_file.clone() {
+        return run_patch_flag_write_file(contents, args, settings);
+    }
+    if let Some(alias) = args.pin.clone() {
+        return run_patch_flag_pin_mode(alias, args, settings);
     }
 
     let file = args
@@ -257,13 +816,209 @@ pub fn run_patch(args: PatchArgs) -> Result<Value, IdenteditError> {
             message: "FILE is required unless --json mode is enabled".to_string(),
         })?;
 
-    match resolve_patch_flag_target(&args)? {
-        PatchFlagTarget::NodeIdentity(identity) => run_patch_flag_node_mode(file, identity, args),
-        PatchFlagTarget::LineAnchor(anchor) => run_patch_flag_line_mode(file, anchor, args),
-        PatchFlagTarget::FileStart => run_patch_flag_file_mode(file, true, args),
-        PatchFlagTarget::FileEnd => run_patch_flag_file_mode(file, false, args),
-        PatchFlagTarget::ConfigPath(path) => run_patch_flag_config_mode(file, path, args),
+    let target = resolve_patch_flag_target(&args)?;
+    if !matches!(target, PatchFlagTarget::NodeIdentity(_)) {
+        ensure_no_retry_on_stale(args.retry_on_stale)?;
+    }
+    if args.offset.is_some() && !matches!(target, PatchFlagTarget::LineAnchor(_)) {
+        return Err(IdenteditError::InvalidRequest {
+            message: "--offset applies only to a line anchor target".to_string(),
+        });
+    }
+    match target {
+        PatchFlagTarget::NodeIdentity(identity) => {
+            run_patch_flag_node_mode(file, identity, args, settings)
+        }
+        PatchFlagTarget::LineAnchor(anchor) => {
+            run_patch_flag_line_mode(file, anchor, args, settings)
+        }
+        PatchFlagTarget::FileStart => run_patch_flag_file_mode(file, true, args, settings),
+        PatchFlagTarget::FileEnd => run_patch_flag_file_mode(file, false, args, settings),
+        PatchFlagTarget::ConfigPath(path) => run_patch_flag_config_mode(file, path, args, settings),
+    }
+}
+
+/// The file a flag-mode patch edits, before the edit; `None` when no file is named, which
+/// the patch itself rejects.
+fn flag_patch_file(args: &PatchArgs) -> Result<Option<PathBuf>, IdenteditError> {
+    Ok(match &args.pin {
+        Some(alias) => Some(find_pin(alias)?.file),
+        None => args.file.clone(),
+    })
+}
+
+/// The file a JSON patch request edits. Requests without a readable `file` are left for
+/// the request parser to reject.
+fn request_patch_file(body: Option<&Value>) -> Option<PathBuf> {
+    Some(normalize_path(Path::new(body?.get("file")?.as_str()?)))
+}
+
+/// The file a JSON patch request edits, before the edit.
+fn request_reread_baseline(body: Option<&Value>) -> Option<RereadBaseline> {
+    let file = request_patch_file(body)?;
+    let body = body?;
+    let targets = match body.get("operations").and_then(Value::as_array) {
+        Some(operations) => operations
+            .iter()
+            .filter_map(|operation| operation.get("target"))
+            .collect::<Vec<_>>(),
+        None => body.get("target").into_iter().collect(),
+    };
+    let mut scope = RereadScope::default();
+    for target in targets {
+        if target.get("type").and_then(Value::as_str) == Some("line") {
+            scope.lines = true;
+        } else {
+            scope.nodes = true;
+        }
+    }
+    Some(RereadBaseline::capture([(file, scope)]))
+}
+
+fn attach_preflight(
+    response: &mut Value,
+    preflight: Vec<PreflightCheck>,
+) -> Result<(), IdenteditError> {
+    if !preflight.is_empty()
+        && let Some(object) = response.as_object_mut()
+    {
+        let preflight = serde_json::to_value(preflight)
+            .map_err(|source| IdenteditError::ResponseSerialization { source })?;
+        object.insert("preflight".to_string(), preflight);
+    }
+    Ok(())
+}
+
+fn attach_reread(
+    response: &mut Value,
+    baseline: Option<RereadBaseline>,
+    registry: &ProviderRegistry,
+) -> Result<(), IdenteditError> {
+    if let Some(baseline) = baseline
+        && let Some(object) = response.as_object_mut()
+    {
+        let reread = serde_json::to_value(baseline.reread(registry))
+            .map_err(|source| IdenteditError::ResponseSerialization { source })?;
+        object.insert("reread".to_string(), reread);
+    }
+    Ok(())
+}
+
+/// Decodes the text-carrying flags when `--text-encoding base64` is set.
+fn decode_text_flags(mut args: PatchArgs) -> Result<PatchArgs, IdenteditError> {
+    let encoding = args.text_encoding;
+    args.replace = encoding.decode_option("--replace", args.replace)?;
+    args.insert = encoding.decode_option("--insert", args.insert)?;
+    args.write_file = encoding.decode_option("--write-file", args.write_file)?;
+    args.scoped_replacement =
+        encoding.decode_option("--scoped-replacement", args.scoped_replacement)?;
+    args.insert_before = encoding.decode_option("--insert-before", args.insert_before)?;
+    args.insert_after = encoding.decode_option("--insert-after", args.insert_after)?;
+    args.set_line = encoding.decode_option("--set-line", args.set_line)?;
+    args.replace_range = encoding.decode_option("--replace-range", args.replace_range)?;
+    args.insert_after_line =
+        encoding.decode_option("--insert-after-line", args.insert_after_line)?;
+    Ok(args)
+}
+
+/// Loads `--replace-file`/`--replace-stdin` into `--replace`. The text is taken as-is,
+/// trailing newline included, and is never base64-decoded.
+fn read_replace_input(mut args: PatchArgs) -> Result<PatchArgs, IdenteditError> {
+    if let Some(path) = args.replace_file.take() {
+        let text =
+            std::fs::read_to_string(&path).map_err(|error| IdenteditError::io(&path, error))?;
+        args.replace = Some(text);
+    } else if args.replace_stdin {
+        let mut text = String::new();
+        std::io::stdin()
+            .read_to_string(&mut text)
+            .map_err(|error| IdenteditError::StdinRead { source: error })?;
+        args.replace = Some(text);
+    }
+    Ok(args)
+}
+
+/// Restyles the text flags by the target file's `.editorconfig` under
+/// `--respect-editorconfig`. Only text that ends the file gets `insert_final_newline`.
+fn restyle_text_flags(mut args: PatchArgs) -> Result<PatchArgs, IdenteditError> {
+    if !args.respect_editorconfig {
+        return Ok(args);
     }
+    let file = match (&args.pin, &args.file) {
+        (Some(alias), _) => find_pin(alias)?.file,
+        (None, Some(file)) => file.clone(),
+        // The missing FILE is reported once the target is resolved.
+        (None, None) => return Ok(args),
+    };
+    let style = EditorconfigStyle::for_file(&file)?;
+    let restyle = |text: Option<String>| text.map(|text| style.apply(&text));
+    args.replace = restyle(args.replace);
+    args.insert_before = restyle(args.insert_before);
+    args.insert_after = restyle(args.insert_after);
+    args.set_line = restyle(args.set_line);
+    args.replace_range = restyle(args.replace_range);
+    args.insert_after_line = restyle(args.insert_after_line);
+    let at_file_end = args
+        .at
+        .as_deref()
+        .is_some_and(|at| at.trim().eq_ignore_ascii_case("file-end"));
+    args.insert = args.insert.map(|text| {
+        if at_file_end {
+            style.apply_at_file_end(&text)
+        } else {
+            style.apply(&text)
+        }
+    });
+    args.write_file = args.write_file.map(|text| style.apply_at_file_end(&text));
+    Ok(args)
+}
+
+/// Node flag mode against a pinned node. On success the pin follows the rewritten node
+/// (see [`refresh_pin`]) and the response reports its identity, or `null` if it is gone.
+fn run_patch_flag_pin_mode(
+    alias: String,
+    args: PatchArgs,
+    settings: &ApplySettings,
+) -> Result<Value, IdenteditError> {
+    if args.at.is_some()
+        || args.identity.is_some()
+        || args.anchor.is_some()
+        || args.offset.is_some()
+        || args.config_path.is_some()
+    {
+        return Err(IdenteditError::InvalidRequest {
+            message:
+                "--pin cannot be combined with --at/--identity/--anchor/--offset/--config-path"
+                    .to_string(),
+        });
+    }
+
+    let pin = find_pin(&alias)?;
+    if let Some(file) = args.file.as_deref() {
+        let file = std::path::absolute(file).map_err(|error| IdenteditError::io(file, error))?;
+        if file != pin.file {
+            return Err(IdenteditError::InvalidRequest {
+                message: format!(
+                    "Pin '{alias}' targets '{}', not '{}'",
+                    pin.file.display(),
+                    file.display()
+                ),
+            });
+        }
+    }
+
+    let registry = settings.provider_registry()?;
+    let handle = resolve_unique_identity_handle_for_patch(&pin.file, &pin.identity, &registry)?;
+    let mut response =
+        run_patch_flag_node_mode(pin.file.clone(), pin.identity.clone(), args, settings)?;
+    let identity = refresh_pin(&pin, handle.span.start, &registry)?;
+    if let Value::Object(fields) = &mut response {
+        fields.insert(
+            "pin".to_string(),
+            json!({ "alias": alias, "identity": identity }),
+        );
+    }
+    Ok(response)
 }
 
 enum PatchFlagTarget {
@@ -319,7 +1074,9 @@ fn parse_patch_at_target(raw: &str) -> Result<PatchFlagTarget, IdenteditError> {
         ));
     }
 
-    if is_line_anchor_with_hash_len(normalized, HASHLINE_PUBLIC_HEX_LEN) {
+    if is_line_anchor_with_hash_len(normalized, HASHLINE_PUBLIC_HEX_LEN)
+        || is_line_anchor_with_hash_len(normalized, HASHLINE_LONG_HEX_LEN)
+    {
         let parsed =
             parse_line_ref(normalized).map_err(|error| IdenteditError::InvalidRequest {
                 message: error.to_string(),
@@ -332,8 +1089,8 @@ fn parse_patch_at_target(raw: &str) -> Result<PatchFlagTarget, IdenteditError> {
 
     Err(IdenteditError::InvalidRequest {
         message: format!(
-            "Invalid --at target '{}': expected hex{} identity, <line>:<hex{}> anchor, file-start, or file-end",
-            raw, HASH_HEX_LEN, HASHLINE_PUBLIC_HEX_LEN
+            "Invalid --at target '{}': expected hex{} identity, <line>:<hex{}|hex{}> anchor, file-start, or file-end",
+            raw, HASH_HEX_LEN, HASHLINE_PUBLIC_HEX_LEN, HASHLINE_LONG_HEX_LEN
         ),
     })
 }
@@ -352,22 +1109,95 @@ fn is_line_anchor_with_hash_len(value: &str, hash_len: usize) -> bool {
         && hash.as_bytes().iter().all(u8::is_ascii_hexdigit)
 }
 
-fn run_patch_json_mode() -> Result<Value, IdenteditError> {
+fn run_patch_json_mode(
+    text_encoding: TextEncoding,
+    retry_on_stale: usize,
+    respect_editorconfig: bool,
+    reread_after: bool,
+    preflight_cmds: &[PathBuf],
+    settings: &ApplySettings,
+) -> Result<Value, IdenteditError> {
     let mut request_body = String::new();
     std::io::stdin()
         .read_to_string(&mut request_body)
         .map_err(|error| IdenteditError::StdinRead { source: error })?;
+    let (request_body, text_encoding) = if respect_editorconfig {
+        (
+            restyle_request_body(&request_body, text_encoding)?,
+            TextEncoding::Utf8,
+        )
+    } else {
+        (request_body, text_encoding)
+    };
 
-    let request: StdinPatchRequest = serde_json::from_str(&request_body)
-        .map_err(|source| IdenteditError::InvalidJsonRequest { source })?;
+    let body = serde_json::from_str::<Value>(&request_body).ok();
+    let preflight = run_preflight_cmds(
+        preflight_cmds,
+        "patch",
+        request_patch_file(body.as_ref()).as_deref(),
+    )?;
+    let baseline = if reread_after {
+        request_reread_baseline(body.as_ref())
+    } else {
+        None
+    };
+    let mut correlation_id = None;
+    let response = run_patch_json_request(
+        &request_body,
+        body,
+        text_encoding,
+        retry_on_stale,
+        settings,
+        &mut correlation_id,
+    );
+    let tag = |error: IdenteditError| error.with_correlation_id(correlation_id.as_deref());
+    let mut response = response.map_err(tag)?;
+    attach_reread(&mut response, baseline, &settings.provider_registry()?).map_err(tag)?;
+    attach_preflight(&mut response, preflight).map_err(tag)?;
+    if let (Some(correlation_id), Some(object)) = (&correlation_id, response.as_object_mut()) {
+        object.insert("correlation_id".to_string(), json!(correlation_id));
+    }
+    Ok(response)
+}
 
-    if request.command != "patch" {
-        return Err(IdenteditError::InvalidRequest {
-            message: format!(
-                "Unsupported command '{}' in patch JSON mode; expected 'patch'",
-                request.command
-            ),
-        });
+/// Runs the stdin request in `request_body`, setting `correlation_id` to the one it carries
+/// once it parses.
+fn run_patch_json_request(
+    request_body: &str,
+    body: Option<Value>,
+    text_encoding: TextEncoding,
+    retry_on_stale: usize,
+    settings: &ApplySettings,
+    correlation_id: &mut Option<String>,
+) -> Result<Value, IdenteditError> {
+    let is_multi_operation = body
+        .as_ref()
+        .is_some_and(|value| value.get("operations").is_some());
+    let is_line_batch = body
+        .as_ref()
+        .and_then(|value| value.get("target"))
+        .is_some_and(|target| target.get("edits").is_some());
+    let registry = settings.provider_registry()?;
+    if is_line_batch && !is_multi_operation {
+        let request: StdinLineBatchRequest = text_encoding.parse_request(request_body)?;
+        ensure_patch_command(&request.command)?;
+        correlation_id.clone_from(&request.correlation_id);
+        ensure_no_retry_on_stale(retry_on_stale)?;
+        return run_patch_json_line_batch(request, settings);
+    }
+    if is_multi_operation {
+        let request: StdinMultiPatchRequest = text_encoding.parse_request(request_body)?;
+        ensure_patch_command(&request.command)?;
+        correlation_id.clone_from(&request.correlation_id);
+        ensure_no_retry_on_stale(retry_on_stale)?;
+        return run_patch_json_operations(request, settings);
+    }
+
+    let request: StdinPatchRequest = text_encoding.parse_request(request_body)?;
+    ensure_patch_command(&request.command)?;
+    correlation_id.clone_from(&request.correlation_id);
+    if !matches!(request.target, StdinPatchTarget::Node { .. }) {
+        ensure_no_retry_on_stale(retry_on_stale)?;
     }
 
     match request.target {
@@ -378,85 +1208,480 @@ fn run_patch_json_mode() -> Result<Value, IdenteditError> {
             expected_old_hash,
         } => run_patch_json_node(
             request.file,
-            identity,
-            kind,
-            span_hint,
-            expected_old_hash,
+            StaleNode {
+                identity,
+                kind,
+                name: None,
+                span: span_hint,
+                expected_old_hash,
+            },
             request.op,
             request.options.verbose,
+            retry_on_stale,
+            settings,
         ),
         StdinPatchTarget::FileStart { expected_file_hash } => run_patch_json_file(
             request.file,
             TransformTarget::FileStart { expected_file_hash },
             request.op,
             request.options.verbose,
+            settings,
         ),
         StdinPatchTarget::FileEnd { expected_file_hash } => run_patch_json_file(
             request.file,
             TransformTarget::FileEnd { expected_file_hash },
             request.op,
             request.options.verbose,
+            settings,
         ),
-        StdinPatchTarget::Line { anchor, end_anchor } => run_patch_json_line(
+        StdinPatchTarget::File { expected_file_hash } => {
+            let (instruction, upsert_action) = parse_whole_file_patch_instruction(
+                &request.file,
+                expected_file_hash,
+                request.op,
+                &registry,
+            )?;
+            let mut response = run_patch_node_operation(
+                request.file,
+                instruction.target,
+                instruction.op,
+                request.options.verbose,
+                None,
+                settings,
+            )?;
+            if let Some(action) = upsert_action
+                && let Some(object) = response.as_object_mut()
+            {
+                object.insert("upsert".to_string(), json!(action));
+            }
+            Ok(response)
+        }
+        StdinPatchTarget::Line(target) => run_patch_json_line(
             request.file,
-            anchor,
-            end_anchor,
+            target,
             request.op,
             request.options.auto_repair,
+            settings,
         ),
+        StdinPatchTarget::MarkerRegion {
+            name,
+            expected_region_hash,
+        } => {
+            let instruction = parse_marker_region_instruction(
+                &request.file,
+                &name,
+                expected_region_hash,
+                request.op,
+            )?;
+            run_patch_node_operation(
+                request.file,
+                instruction.target,
+                instruction.op,
+                request.options.verbose,
+                None,
+                settings,
+            )
+        }
         StdinPatchTarget::ConfigPath {
             path,
+            document,
             expected_file_hash,
+            expected_match_count,
+            expected_value_hash,
         } => run_patch_json_config(
             request.file,
-            path,
-            expected_file_hash,
+            ConfigPathTarget {
+                path,
+                document,
+                expected_file_hash,
+                expected_match_count,
+                expected_value_hash,
+            },
             request.op,
+            request_body,
             request.options.verbose,
+            settings,
         ),
     }
 }
 
-fn run_patch_json_file(
-    file: PathBuf,
+/// Decodes the request's op text and restyles it by the `.editorconfig` of its `file`.
+fn restyle_request_body(
+    request_body: &str,
+    text_encoding: TextEncoding,
+) -> Result<String, IdenteditError> {
+    let mut request = serde_json::from_str::<Value>(request_body)
+        .map_err(|source| IdenteditError::InvalidJsonRequest { source })?;
+    text_encoding.decode_request_ops(&mut request)?;
+    let Some(file) = request.get("file").and_then(Value::as_str) else {
+        return Ok(request.to_string());
+    };
+    let style = EditorconfigStyle::for_file(&normalize_path(Path::new(file)))?;
+    restyle_request_ops(&mut request, &style);
+    Ok(request.to_string())
+}
+
+/// Restyles the text of every `op` in a JSON request, wherever it is nested. `config_path`
+/// values and regex replacements are left alone, and only text that ends the file
+/// (`file_end` inserts, `append_file`) gets `insert_final_newline`.
+fn restyle_request_ops(request: &mut Value, style: &EditorconfigStyle) {
+    match request {
+        Value::Object(fields) => {
+            let target_type = fields
+                .get("target")
+                .and_then(|target| target.get("type"))
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            if let Some(Value::Object(op)) = fields.get_mut("op")
+                && target_type.as_deref() != Some("config_path")
+            {
+                let at_file_end = target_type.as_deref() == Some("file_end")
+                    || op.get("type").and_then(Value::as_str) == Some("append_file");
+                for field in ["new_text", "text"] {
+                    if let Some(Value::String(text)) = op.get_mut(field) {
+                        *text = if at_file_end {
+                            style.apply_at_file_end(text)
+                        } else {
+                            style.apply(text)
+                        };
+                    }
+                }
+            }
+            for (key, value) in fields.iter_mut() {
+                if key != "op" {
+                    restyle_request_ops(value, style);
+                }
+            }
+        }
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| restyle_request_ops(item, style)),
+        _ => {}
+    }
+}
+
+/// `--retry-on-stale` re-resolves a single node target; other targets fail fast instead.
+fn ensure_no_retry_on_stale(retry_on_stale: usize) -> Result<(), IdenteditError> {
+    if retry_on_stale > 0 {
+        return Err(IdenteditError::InvalidRequest {
+            message: "--retry-on-stale applies only to a single node target".to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn ensure_patch_command(command: &str) -> Result<(), IdenteditError> {
+    if command != "patch" {
+        return Err(IdenteditError::InvalidRequest {
+            message: format!(
+                "Unsupported command '{command}' in patch JSON mode; expected 'patch'"
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Line operations go through one hashline patch so they keep single-request semantics,
+/// including `auto_repair`. Node and file-boundary operations become one changeset, which
+/// rejects overlapping spans and rewrites the file bottom-up. Config paths are structural
+/// rewrites of the whole document and stay one per request.
+fn run_patch_json_operations(
+    request: StdinMultiPatchRequest,
+    settings: &ApplySettings,
+) -> Result<Value, IdenteditError> {
+    if request.operations.is_empty() {
+        return Err(IdenteditError::InvalidRequest {
+            message: "operations must contain at least one operation".to_string(),
+        });
+    }
+    if request
+        .operations
+        .iter()
+        .any(|operation| matches!(operation.target, StdinPatchTarget::ConfigPath { .. }))
+    {
+        return Err(IdenteditError::InvalidRequest {
+            message: "config_path targets cannot be batched in operations; send one patch request per config path".to_string(),
+        });
+    }
+
+    let line_operations = request
+        .operations
+        .iter()
+        .filter(|operation| matches!(operation.target, StdinPatchTarget::Line { .. }))
+        .count();
+    let registry = settings.provider_registry()?;
+    if line_operations == request.operations.len() {
+        let mut edits = Vec::with_capacity(line_operations);
+        for operation in request.operations {
+            if let StdinPatchTarget::Line(target) = operation.target {
+                edits.push(parse_line_patch_edit(target, operation.op)?);
+            }
+        }
+        let patch_response =
+            execute_hashline_patch(request.file, edits, request.options.auto_repair, settings)?;
+        return serialize_line_patch_response(patch_response);
+    }
+    if line_operations > 0 {
+        return Err(IdenteditError::InvalidRequest {
+            message: "operations cannot mix line targets with node or file targets; use `edit` to build a mixed plan".to_string(),
+        });
+    }
+
+    let mut instructions = Vec::with_capacity(request.operations.len());
+    let mut regex_replacements = None;
+    for operation in request.operations {
+        let (instruction, replacements) =
+            parse_patch_instruction(&request.file, operation.target, operation.op, &registry)?;
+        if let Some(replacements) = replacements {
+            *regex_replacements.get_or_insert(0) += replacements;
+        }
+        instructions.push(instruction);
+    }
+    run_patch_node_operations(
+        request.file,
+        instructions,
+        request.options.verbose,
+        regex_replacements,
+        settings,
+    )
+}
+
+/// Converts one node or file-boundary operation of a batched request. Scoped regex
+/// operations are rewritten against the current node text and also return their match count.
+fn parse_patch_instruction(
+    file: &Path,
+    target: StdinPatchTarget,
+    op: Value,
+    registry: &ProviderRegistry,
+) -> Result<(TransformInstruction, Option<usize>), IdenteditError> {
+    match target {
+        StdinPatchTarget::Node {
+            identity,
+            kind,
+            span_hint,
+            expected_old_hash,
+        } => {
+            let node_op = serde_json::from_value::<NodePatchOp>(op).map_err(|error| {
+                IdenteditError::InvalidRequest {
+                    message: format!("Invalid node patch operation payload: {error}"),
+                }
+            })?;
+            let target = TransformTarget::node(identity, kind, span_hint, expected_old_hash);
+            let (op, replacements) = match node_op {
+                NodePatchOp::Replace { new_text } => (OpKind::Replace { new_text }, None),
+                NodePatchOp::Delete => (OpKind::Delete, None),
+                NodePatchOp::InsertBefore { new_text } => (OpKind::InsertBefore { new_text }, None),
+                NodePatchOp::InsertAfter { new_text } => (OpKind::InsertAfter { new_text }, None),
+                NodePatchOp::Annotate(spec) => (
+                    OpKind::InsertBefore {
+                        new_text: annotate_node_target(file, &target, &spec, registry)?,
+                    },
+                    None,
+                ),
+                NodePatchOp::ScopedRegex {
+                    pattern,
+                    replacement,
+                    skip_strings,
+                    skip_comments,
+                    dry,
+                } => {
+                    if dry {
+                        return Err(IdenteditError::InvalidRequest {
+                            message: "dry scoped_regex operations cannot be batched; send one patch request per dry run".to_string(),
+                        });
+                    }
+                    let rewritten = rewrite_node_target_with_scoped_regex(
+                        file,
+                        &target,
+                        &pattern,
+                        &required_scoped_replacement(replacement)?,
+                        ScopedRegexOptions {
+                            skip_strings,
+                            skip_comments,
+                        },
+                        registry,
+                    )?;
+                    (
+                        OpKind::Replace {
+                            new_text: rewritten.new_text,
+                        },
+                        Some(rewritten.replacements),
+                    )
+                }
+            };
+            Ok((TransformInstruction { target, op }, replacements))
+        }
+        StdinPatchTarget::FileStart { expected_file_hash } => Ok((
+            parse_file_patch_instruction(TransformTarget::FileStart { expected_file_hash }, op)?,
+            None,
+        )),
+        StdinPatchTarget::FileEnd { expected_file_hash } => Ok((
+            parse_file_patch_instruction(TransformTarget::FileEnd { expected_file_hash }, op)?,
+            None,
+        )),
+        StdinPatchTarget::File { expected_file_hash } => Ok((
+            parse_whole_file_patch_instruction(file, expected_file_hash, op, registry)?.0,
+            None,
+        )),
+        StdinPatchTarget::MarkerRegion {
+            name,
+            expected_region_hash,
+        } => Ok((
+            parse_marker_region_instruction(file, &name, expected_region_hash, op)?,
+            None,
+        )),
+        StdinPatchTarget::Line { .. } | StdinPatchTarget::ConfigPath { .. } => {
+            Err(IdenteditError::InvalidRequest {
+                message: "Only node and file targets can be combined into one changeset"
+                    .to_string(),
+            })
+        }
+    }
+}
+
+fn parse_file_patch_instruction(
     target: TransformTarget,
     op: Value,
-    verbose: bool,
-) -> Result<Value, IdenteditError> {
+) -> Result<TransformInstruction, IdenteditError> {
     let file_op = serde_json::from_value::<FilePatchOp>(op).map_err(|error| {
         IdenteditError::InvalidRequest {
             message: format!("Invalid file patch operation payload: {error}"),
         }
     })?;
-
     match file_op {
-        FilePatchOp::Insert { new_text } => {
-            run_patch_node_operation(file, target, OpKind::Insert { new_text }, verbose, None)
+        FilePatchOp::Insert { new_text } => Ok(TransformInstruction {
+            target,
+            op: OpKind::Insert { new_text },
+        }),
+    }
+}
+
+/// Turns `append_file`/`prepend_file` into an insert at that end of the file, and `upsert`
+/// into a node replacement or insert, checked against `expected_file_hash` before the
+/// file's content decides the edit. `upsert` also returns what it chose to do.
+fn parse_whole_file_patch_instruction(
+    file: &Path,
+    expected_file_hash: String,
+    op: Value,
+    registry: &ProviderRegistry,
+) -> Result<(TransformInstruction, Option<UpsertAction>), IdenteditError> {
+    let file_op = serde_json::from_value::<WholeFilePatchOp>(op).map_err(|error| {
+        IdenteditError::InvalidRequest {
+            message: format!("Invalid file patch operation payload: {error}"),
         }
+    })?;
+    let source = std::fs::read(file).map_err(|error| IdenteditError::io(file, error))?;
+    let actual_hash = hash_bytes(&source);
+    if actual_hash != expected_file_hash {
+        return Err(IdenteditError::PreconditionFailed {
+            expected_hash: expected_file_hash,
+            actual_hash,
+        });
     }
+    let (edge, new_text) = match file_op {
+        WholeFilePatchOp::AppendFile { new_text } => (FileEdge::End, new_text),
+        WholeFilePatchOp::PrependFile { new_text } => (FileEdge::Start, new_text),
+        WholeFilePatchOp::Upsert(spec) => {
+            let (instruction, action) =
+                upsert_instruction(file, &source, expected_file_hash, spec, registry)?;
+            return Ok((instruction, Some(action)));
+        }
+        WholeFilePatchOp::CreateMarkerRegion { name, new_text } => (
+            FileEdge::End,
+            marker_region_text(file, &String::from_utf8_lossy(&source), &name, &new_text)?,
+        ),
+    };
+    let new_text = file_edge_insert_text(&source, edge, &new_text)?;
+    let target = match edge {
+        FileEdge::Start => TransformTarget::FileStart { expected_file_hash },
+        FileEdge::End => TransformTarget::FileEnd { expected_file_hash },
+    };
+    Ok((
+        TransformInstruction {
+            target,
+            op: OpKind::Insert { new_text },
+        },
+        None,
+    ))
+}
+
+fn parse_marker_region_instruction(
+    file: &Path,
+    name: &str,
+    expected_region_hash: Option<String>,
+    op: Value,
+) -> Result<TransformInstruction, IdenteditError> {
+    let region_op = serde_json::from_value::<MarkerRegionOp>(op).map_err(|error| {
+        IdenteditError::InvalidRequest {
+            message: format!("Invalid marker_region patch operation payload: {error}"),
+        }
+    })?;
+    let source = std::fs::read_to_string(file).map_err(|error| IdenteditError::io(file, error))?;
+    marker_region_instruction(file, &source, name, expected_region_hash, region_op)
+}
+
+fn run_patch_json_file(
+    file: PathBuf,
+    target: TransformTarget,
+    op: Value,
+    verbose: bool,
+    settings: &ApplySettings,
+) -> Result<Value, IdenteditError> {
+    let instruction = parse_file_patch_instruction(target, op)?;
+    run_patch_node_operation(
+        file,
+        instruction.target,
+        instruction.op,
+        verbose,
+        None,
+        settings,
+    )
 }
 
 fn run_patch_json_node(
     file: PathBuf,
-    identity: String,
-    kind: String,
-    span_hint: Option<Span>,
-    expected_old_hash: String,
+    node: StaleNode,
     op: Value,
     verbose: bool,
+    retry_on_stale: usize,
+    settings: &ApplySettings,
 ) -> Result<Value, IdenteditError> {
     let node_op = serde_json::from_value::<NodePatchOp>(op).map_err(|error| {
         IdenteditError::InvalidRequest {
             message: format!("Invalid node patch operation payload: {error}"),
         }
     })?;
-    let target = TransformTarget::node(identity, kind, span_hint, expected_old_hash);
+    let registry = settings.provider_registry()?;
+    run_with_stale_retries(&file.clone(), node, retry_on_stale, &registry, |node| {
+        let target = TransformTarget::node(
+            node.identity.clone(),
+            node.kind.clone(),
+            node.span,
+            node.expected_old_hash.clone(),
+        );
+        run_patch_json_node_operation(file.clone(), target, node_op.clone(), verbose, settings)
+    })
+}
+
+fn run_patch_json_node_operation(
+    file: PathBuf,
+    target: TransformTarget,
+    node_op: NodePatchOp,
+    verbose: bool,
+    settings: &ApplySettings,
+) -> Result<Value, IdenteditError> {
+    let registry = settings.provider_registry()?;
     match node_op {
-        NodePatchOp::Replace { new_text } => {
-            run_patch_node_operation(file, target, OpKind::Replace { new_text }, verbose, None)
-        }
+        NodePatchOp::Replace { new_text } => run_patch_node_operation(
+            file,
+            target,
+            OpKind::Replace { new_text },
+            verbose,
+            None,
+            settings,
+        ),
         NodePatchOp::Delete => {
-            run_patch_node_operation(file, target, OpKind::Delete, verbose, None)
+            run_patch_node_operation(file, target, OpKind::Delete, verbose, None, settings)
         }
         NodePatchOp::InsertBefore { new_text } => run_patch_node_operation(
             file,
@@ -464,6 +1689,7 @@ fn run_patch_json_node(
             OpKind::InsertBefore { new_text },
             verbose,
             None,
+            settings,
         ),
         NodePatchOp::InsertAfter { new_text } => run_patch_node_operation(
             file,
@@ -471,25 +1697,77 @@ fn run_patch_json_node(
             OpKind::InsertAfter { new_text },
             verbose,
             None,
+            settings,
         ),
+        NodePatchOp::Annotate(spec) => {
+            let new_text = annotate_node_target(&file, &target, &spec, &registry)?;
+            run_patch_node_operation(
+                file,
+                target,
+                OpKind::InsertBefore { new_text },
+                verbose,
+                None,
+                settings,
+            )
+        }
         NodePatchOp::ScopedRegex {
             pattern,
             replacement,
-        } => run_patch_scoped_regex_node_operation(file, target, pattern, replacement, verbose),
+            skip_strings,
+            skip_comments,
+            dry,
+        } => {
+            let options = ScopedRegexOptions {
+                skip_strings,
+                skip_comments,
+            };
+            if dry {
+                let matches = find_scoped_regex_matches_in_node_target(
+                    &file, &target, &pattern, options, &registry,
+                )?;
+                return Ok(serialize_scoped_regex_matches(&matches));
+            }
+            run_patch_scoped_regex_node_operation(
+                file,
+                target,
+                pattern,
+                required_scoped_replacement(replacement)?,
+                options,
+                verbose,
+                settings,
+            )
+        }
     }
 }
 
-fn run_patch_node_operation(
+fn run_patch_node_operation(
+    file: PathBuf,
+    target: TransformTarget,
+    op: OpKind,
+    verbose: bool,
+    regex_replacements: Option<usize>,
+    settings: &ApplySettings,
+) -> Result<Value, IdenteditError> {
+    run_patch_node_operations(
+        file,
+        vec![TransformInstruction { target, op }],
+        verbose,
+        regex_replacements,
+        settings,
+    )
+}
+
+fn run_patch_node_operations(
     file: PathBuf,
-    target: TransformTarget,
-    op: OpKind,
+    instructions: Vec<TransformInstruction>,
     verbose: bool,
     regex_replacements: Option<usize>,
+    settings: &ApplySettings,
 ) -> Result<Value, IdenteditError> {
     let response = run_resolve_verify_apply(
         || {
-            let file_change = build_changeset(&file, vec![TransformInstruction { target, op }])?;
-            Ok(wrap_single_file(file_change))
+            let file_change = build_changeset(&file, instructions, &settings.provider_registry()?)?;
+            Ok(wrap_single_file(file_change, settings))
         },
         verify_prepared_changeset,
         |changeset| apply_multi_file_changeset(&changeset),
@@ -503,9 +1781,18 @@ fn run_patch_scoped_regex_node_operation(
     target: TransformTarget,
     pattern: String,
     replacement: String,
+    options: ScopedRegexOptions,
     verbose: bool,
+    settings: &ApplySettings,
 ) -> Result<Value, IdenteditError> {
-    let rewritten = rewrite_node_target_with_scoped_regex(&file, &target, &pattern, &replacement)?;
+    let rewritten = rewrite_node_target_with_scoped_regex(
+        &file,
+        &target,
+        &pattern,
+        &replacement,
+        options,
+        &settings.provider_registry()?,
+    )?;
     run_patch_node_operation(
         file,
         target,
@@ -514,9 +1801,24 @@ fn run_patch_scoped_regex_node_operation(
         },
         verbose,
         Some(rewritten.replacements),
+        settings,
     )
 }
 
+fn required_scoped_replacement(replacement: Option<String>) -> Result<String, IdenteditError> {
+    replacement.ok_or_else(|| IdenteditError::InvalidRequest {
+        message: "scoped_regex needs a replacement unless dry is true".to_string(),
+    })
+}
+
+/// Dry-run output: nothing is applied, so there is no summary or transaction.
+fn serialize_scoped_regex_matches(matches: &[ScopedRegexMatch]) -> Value {
+    json!({
+        "regex_matches": matches.len(),
+        "matches": matches,
+    })
+}
+
 fn serialize_node_patch_response(
     response: crate::apply::ApplyResponse,
     verbose: bool,
@@ -535,13 +1837,55 @@ fn serialize_node_patch_response(
     Ok(value)
 }
 
+/// All anchors are checked against one read of the file before anything is written; a
+/// stale anchor or two edits touching the same lines reject the whole batch.
+fn run_patch_json_line_batch(
+    request: StdinLineBatchRequest,
+    settings: &ApplySettings,
+) -> Result<Value, IdenteditError> {
+    let StdinLineBatchTarget::Line { edits } = request.target;
+    if edits.is_empty() {
+        return Err(IdenteditError::InvalidRequest {
+            message: "line target edits must contain at least one edit".to_string(),
+        });
+    }
+    let edits = edits
+        .into_iter()
+        .map(|edit| {
+            let (target, op) = edit.into_target_and_op();
+            parse_line_patch_edit(target, op)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let patch_response =
+        execute_hashline_patch(request.file, edits, request.options.auto_repair, settings)?;
+    serialize_line_patch_response(patch_response)
+}
+
 fn run_patch_json_line(
     file: PathBuf,
-    anchor: String,
-    end_anchor: Option<String>,
+    target: StdinLineTarget,
     op: Value,
     auto_repair: bool,
+    settings: &ApplySettings,
 ) -> Result<Value, IdenteditError> {
+    let edit = parse_line_patch_edit(target, op)?;
+    let patch_response = execute_hashline_patch(file, vec![edit], auto_repair, settings)?;
+    serialize_line_patch_response(patch_response)
+}
+
+fn parse_line_patch_edit(
+    target: StdinLineTarget,
+    op: Value,
+) -> Result<LineTargetEdit, IdenteditError> {
+    let anchor_match = parse_line_match(&target)?;
+    let StdinLineTarget {
+        anchor,
+        offset: anchor_offset,
+        end_anchor,
+        ..
+    } = target;
+    // A matched anchor is filled in once the file is read.
+    let anchor = anchor.unwrap_or_default();
     let line_op = serde_json::from_value::<LinePatchOp>(op).map_err(|error| {
         IdenteditError::InvalidRequest {
             message: format!("Invalid line patch operation payload: {error}"),
@@ -562,17 +1906,81 @@ fn run_patch_json_line(
             insert_after: InsertAfterEdit { anchor, text },
         },
     };
-    let patch_response = execute_hashline_patch(file, vec![edit], auto_repair)?;
-    serialize_line_patch_response(patch_response)
+    Ok(LineTargetEdit {
+        edit,
+        anchor_match,
+        anchor_offset,
+    })
 }
 
-fn run_patch_json_config(
-    file: PathBuf,
+fn parse_line_match(target: &StdinLineTarget) -> Result<Option<LineMatch>, IdenteditError> {
+    let Some(pattern) = &target.pattern else {
+        if target.anchor.is_none() {
+            return Err(IdenteditError::InvalidRequest {
+                message: "line target requires `anchor` or `match`".to_string(),
+            });
+        }
+        if target.occurrence.is_some() || target.expected_match_count.is_some() {
+            return Err(IdenteditError::InvalidRequest {
+                message: "occurrence and expected_match_count apply only to a `match` line target"
+                    .to_string(),
+            });
+        }
+        return Ok(None);
+    };
+    if target.anchor.is_some() {
+        return Err(IdenteditError::InvalidRequest {
+            message: "line target takes `anchor` or `match`, not both".to_string(),
+        });
+    }
+    let pattern = Regex::new(pattern).map_err(|error| IdenteditError::InvalidRequest {
+        message: format!("Invalid line match pattern: {error}"),
+    })?;
+    Ok(Some(LineMatch {
+        pattern,
+        occurrence: target.occurrence,
+        expected_match_count: target.expected_match_count,
+    }))
+}
+
+/// Dependency operations address one section of a Cargo.toml/package.json manifest, so
+/// document selection does not apply and wildcards are rejected like `merge_object`.
+fn check_dependency_target(
+    path: &str,
+    document: Option<&str>,
+    expected_match_count: Option<usize>,
+) -> Result<(), IdenteditError> {
+    if document.is_some() {
+        return Err(IdenteditError::InvalidRequest {
+            message: "Dependency operations do not support a document selector".to_string(),
+        });
+    }
+    check_single_target_match_count(path, expected_match_count)
+}
+
+struct ConfigPathTarget {
     path: String,
+    document: Option<String>,
     expected_file_hash: Option<String>,
+    expected_match_count: Option<usize>,
+    expected_value_hash: Option<String>,
+}
+
+fn run_patch_json_config(
+    file: PathBuf,
+    target: ConfigPathTarget,
     op: Value,
+    request_body: &str,
     verbose: bool,
+    settings: &ApplySettings,
 ) -> Result<Value, IdenteditError> {
+    let ConfigPathTarget {
+        path,
+        document,
+        expected_file_hash,
+        expected_match_count,
+        expected_value_hash,
+    } = target;
     if let Some(object) = op.as_object()
         && object
             .get("type")
@@ -585,40 +1993,167 @@ fn run_patch_json_config(
         });
     }
 
-    let config_op = serde_json::from_value::<ConfigPatchOp>(op).map_err(|error| {
+    let mut config_op = serde_json::from_value::<ConfigPatchOp>(op).map_err(|error| {
         IdenteditError::InvalidRequest {
             message: format!("Invalid config path operation payload: {error}"),
         }
     })?;
+    if let ConfigPatchOp::MergeObject { object, .. } = &mut config_op {
+        // `op` came through a `Value`, which sorts keys; take the object from the body text
+        // instead so its keys are written in request order.
+        let request = serde_json::from_str::<StdinMergeObjectRequest>(request_body)
+            .map_err(|source| IdenteditError::InvalidJsonRequest { source })?;
+        *object = request.op.object;
+    }
+    if let Some(expected_value_hash) = expected_value_hash.as_deref() {
+        check_config_value_hash(&file, &path, document.as_deref(), expected_value_hash)?;
+    }
 
+    let registry = settings.provider_registry()?;
     let canonical = match config_op {
         ConfigPatchOp::Set {
             new_text,
+            value_type,
             create_missing,
-        } => resolve_config_path_operation(
+        } => resolve_config_path_pattern_operation(
             file.as_path(),
             &path,
+            document.as_deref(),
             expected_file_hash.as_deref(),
+            expected_match_count,
             ConfigPathOperation::Set {
-                new_text,
+                new_text: encode_config_value(&file, value_type, new_text)?,
                 create_missing,
             },
+            &registry,
         )?,
-        ConfigPatchOp::Append { new_text } => resolve_config_path_operation(
+        ConfigPatchOp::Append {
+            new_text,
+            value_type,
+        } => resolve_config_path_pattern_operation(
             file.as_path(),
             &path,
+            document.as_deref(),
             expected_file_hash.as_deref(),
-            ConfigPathOperation::Append { new_text },
+            expected_match_count,
+            ConfigPathOperation::Append {
+                new_text: encode_config_value(&file, value_type, new_text)?,
+            },
+            &registry,
+        )?,
+        ConfigPatchOp::InsertAt {
+            index,
+            new_text,
+            value_type,
+        } => resolve_config_path_pattern_operation(
+            file.as_path(),
+            &path,
+            document.as_deref(),
+            expected_file_hash.as_deref(),
+            expected_match_count,
+            ConfigPathOperation::InsertAt {
+                index,
+                new_text: encode_config_value(&file, value_type, new_text)?,
+            },
+            &registry,
+        )?,
+        ConfigPatchOp::RemoveAt { index } => resolve_config_path_pattern_operation(
+            file.as_path(),
+            &path,
+            document.as_deref(),
+            expected_file_hash.as_deref(),
+            expected_match_count,
+            ConfigPathOperation::RemoveAt { index },
+            &registry,
+        )?,
+        ConfigPatchOp::MergeObject {
+            object,
+            on_conflict,
+        } => {
+            check_single_target_match_count(&path, expected_match_count)?;
+            let Some(patch) = resolve_config_merge_operation(
+                file.as_path(),
+                &path,
+                document.as_deref(),
+                expected_file_hash.as_deref(),
+                &object,
+                on_conflict,
+                &registry,
+            )?
+            else {
+                return serialize_node_patch_response(ApplyResponse::unchanged(), verbose, None);
+            };
+            patch
+        }
+        ConfigPatchOp::RenameKey { new_key } => resolve_config_path_pattern_operation(
+            file.as_path(),
+            &path,
+            document.as_deref(),
+            expected_file_hash.as_deref(),
+            expected_match_count,
+            ConfigPathOperation::RenameKey { new_key },
+            &registry,
         )?,
-        ConfigPatchOp::Delete => resolve_config_path_operation(
+        ConfigPatchOp::AddDependency { name, version } => {
+            check_dependency_target(&path, document.as_deref(), expected_match_count)?;
+            resolve_config_dependency_operation(
+                file.as_path(),
+                &path,
+                expected_file_hash.as_deref(),
+                DependencyOperation::Add { name, version },
+                &registry,
+            )?
+        }
+        ConfigPatchOp::RemoveDependency { name } => {
+            check_dependency_target(&path, document.as_deref(), expected_match_count)?;
+            resolve_config_dependency_operation(
+                file.as_path(),
+                &path,
+                expected_file_hash.as_deref(),
+                DependencyOperation::Remove { name },
+                &registry,
+            )?
+        }
+        ConfigPatchOp::SetDependencyVersion { name, version } => {
+            check_dependency_target(&path, document.as_deref(), expected_match_count)?;
+            resolve_config_dependency_operation(
+                file.as_path(),
+                &path,
+                expected_file_hash.as_deref(),
+                DependencyOperation::SetVersion { name, version },
+                &registry,
+            )?
+        }
+        ConfigPatchOp::Move { to } => {
+            check_single_target_match_count(&path, expected_match_count)?;
+            resolve_config_move_operation(
+                file.as_path(),
+                &path,
+                document.as_deref(),
+                expected_file_hash.as_deref(),
+                &to,
+                &registry,
+            )?
+        }
+        ConfigPatchOp::Delete => resolve_config_path_pattern_operation(
             file.as_path(),
             &path,
+            document.as_deref(),
             expected_file_hash.as_deref(),
+            expected_match_count,
             ConfigPathOperation::Delete,
+            &registry,
         )?,
     };
 
-    run_patch_node_operation(file, canonical.target, canonical.op, verbose, None)
+    run_patch_node_operation(
+        file,
+        canonical.target,
+        canonical.op,
+        verbose,
+        None,
+        settings,
+    )
 }
 
 fn serialize_line_patch_response(response: HashlinePatchResponse) -> Result<Value, IdenteditError> {
@@ -630,12 +2165,28 @@ fn run_patch_flag_node_mode(
     file: PathBuf,
     identity: String,
     args: PatchArgs,
+    settings: &ApplySettings,
 ) -> Result<Value, IdenteditError> {
     if args.anchor.is_some()
         || args.end_anchor.is_some()
         || args.insert.is_some()
         || args.set_value.is_some()
+        || args.set_string.is_some()
+        || args.set_int.is_some()
+        || args.set_bool.is_some()
+        || args.set_raw.is_some()
         || args.append_value.is_some()
+        || args.insert_at.is_some()
+        || args.remove_at.is_some()
+        || args.merge_object.is_some()
+        || args.rename_key.is_some()
+        || args.move_to.is_some()
+        || args.add_dependency.is_some()
+        || args.remove_dependency.is_some()
+        || args.set_dependency_version.is_some()
+        || args.dependency_version.is_some()
+        || args.expected_match_count.is_some()
+        || args.doc.is_some()
         || args.create_missing
         || args.set_line.is_some()
         || args.replace_range.is_some()
@@ -643,14 +2194,17 @@ fn run_patch_flag_node_mode(
         || args.auto_repair
     {
         return Err(IdenteditError::InvalidRequest {
-            message: "Node flag mode does not allow line/file/config options (--at line/file-start/file-end, --anchor/--end-anchor/--insert/--set-value/--append-value/--set-line/--replace-range/--insert-after-line/--auto-repair/--create-missing)".to_string(),
+            message: "Node flag mode does not allow line/file/config options (--at line/file-start/file-end, --anchor/--end-anchor/--insert/--set-value/--set-string/--set-int/--set-bool/--set-raw/--append-value/--insert-at/--remove-at/--merge-object/--rename-key/--move-to/--add-dependency/--remove-dependency/--set-dependency-version/--dependency-version/--expected-match-count/--doc/--set-line/--replace-range/--insert-after-line/--auto-repair/--create-missing)".to_string(),
         });
     }
 
     let scoped_regex_present = args.scoped_regex.is_some() || args.scoped_replacement.is_some();
-    if scoped_regex_present && (args.scoped_regex.is_none() || args.scoped_replacement.is_none()) {
+    if scoped_regex_present
+        && (args.scoped_regex.is_none()
+            || (args.scoped_replacement.is_none() && args.script.is_none() && !args.count_only))
+    {
         return Err(IdenteditError::InvalidRequest {
-            message: "--scoped-regex and --scoped-replacement must be provided together"
+            message: "--scoped-regex needs --scoped-replacement, --script, or --count-only"
                 .to_string(),
         });
     }
@@ -658,60 +2212,218 @@ fn run_patch_flag_node_mode(
         + usize::from(args.delete)
         + usize::from(args.insert_before.is_some())
         + usize::from(args.insert_after.is_some())
-        + usize::from(scoped_regex_present);
+        + usize::from(args.annotate.is_some())
+        + usize::from(scoped_regex_present || args.script.is_some());
 
     if operation_count != 1 {
         return Err(IdenteditError::InvalidRequest {
-            message: "Exactly one node operation is required: choose one of --replace, --delete, --insert-before, --insert-after, --scoped-regex+--scoped-replacement".to_string(),
+            message: "Exactly one node operation is required: choose one of --replace, --delete, --insert-before, --insert-after, --annotate, --script, --scoped-regex+--scoped-replacement/--script".to_string(),
         });
     }
-    if let Some(pattern) = args.scoped_regex {
+    if args.retry_on_stale == 0 {
+        return run_patch_flag_node_operation(file, &identity, &args, settings);
+    }
+    let registry = settings.provider_registry()?;
+    let handle = resolve_unique_identity_handle_for_patch(&file, &identity, &registry)?;
+    run_with_stale_retries(
+        &file.clone(),
+        StaleNode::from(&handle),
+        args.retry_on_stale,
+        &registry,
+        |node| run_patch_flag_node_operation(file.clone(), &node.identity, &args, settings),
+    )
+}
+
+fn run_patch_flag_node_operation(
+    file: PathBuf,
+    identity: &str,
+    args: &PatchArgs,
+    settings: &ApplySettings,
+) -> Result<Value, IdenteditError> {
+    let scoped_regex_options = ScopedRegexOptions {
+        skip_strings: args.skip_strings,
+        skip_comments: args.skip_comments,
+    };
+    if let Some(script) = &args.script {
+        return run_patch_flag_script(
+            file,
+            identity,
+            script,
+            args.scoped_regex.as_deref(),
+            scoped_regex_options,
+            args.verbose,
+            settings,
+        );
+    }
+    let registry = settings.provider_registry()?;
+    if let Some(pattern) = args.scoped_regex.as_deref()
+        && args.count_only
+    {
+        let handle = resolve_unique_identity_handle_for_patch(&file, identity, &registry)?;
+        let target = TransformTarget::node(
+            handle.identity,
+            handle.kind,
+            Some(handle.span),
+            hash_text(&handle.text),
+        );
+        let matches = find_scoped_regex_matches_in_node_target(
+            &file,
+            &target,
+            pattern,
+            scoped_regex_options,
+            &registry,
+        )?;
+        return Ok(serialize_scoped_regex_matches(&matches));
+    }
+    if let Some(pattern) = args.scoped_regex.clone() {
         let replacement =
             args.scoped_replacement
+                .clone()
                 .ok_or_else(|| IdenteditError::InvalidRequest {
                     message: "missing payload for --scoped-replacement".to_string(),
                 })?;
-        return run_patch_flag_scoped_regex(file, &identity, pattern, replacement, args.verbose);
+        return run_patch_flag_scoped_regex(
+            file,
+            identity,
+            pattern,
+            replacement,
+            scoped_regex_options,
+            args.verbose,
+            settings,
+        );
     }
 
-    let file_change = if let Some(new_text) = args.replace {
-        build_replace_changeset(&file, &identity, new_text)?
+    let file_change = if let Some(new_text) = args.replace.clone() {
+        build_replace_changeset(&file, identity, new_text, &registry)?
     } else if args.delete {
-        build_delete_changeset(&file, &identity)?
-    } else if let Some(new_text) = args.insert_before {
-        build_insert_before_changeset(&file, &identity, new_text)?
+        build_delete_changeset(&file, identity, &registry)?
+    } else if let Some(new_text) = args.insert_before.clone() {
+        let new_text =
+            reindent_node_insert(&file, identity, new_text, true, args.reindent, &registry)?;
+        build_insert_before_changeset(&file, identity, new_text, &registry)?
+    } else if let Some(message) = args.annotate.clone() {
+        let handle = resolve_unique_identity_handle_for_patch(&file, identity, &registry)?;
+        let source = std::fs::read(&file).map_err(|error| IdenteditError::io(&file, error))?;
+        let spec = AnnotationSpec {
+            marker: args.marker,
+            message,
+            ticket: args.ticket.clone(),
+            timestamp: args.timestamp,
+        };
+        let new_text = annotation_insert_text(&file, &source, handle.span.start, &spec)?;
+        build_insert_before_changeset(&file, identity, new_text, &registry)?
     } else {
         let new_text = args
             .insert_after
+            .clone()
             .ok_or_else(|| IdenteditError::InvalidRequest {
                 message: "missing operation payload for --insert-after".to_string(),
             })?;
-        build_insert_after_changeset(&file, &identity, new_text)?
+        let new_text =
+            reindent_node_insert(&file, identity, new_text, false, args.reindent, &registry)?;
+        build_insert_after_changeset(&file, identity, new_text, &registry)?
     };
 
     let response = run_resolve_verify_apply(
-        || Ok(wrap_single_file(file_change)),
+        || Ok(wrap_single_file(file_change, settings)),
         verify_prepared_changeset,
         |changeset| apply_multi_file_changeset(&changeset),
     )?;
     serialize_node_patch_response(response, args.verbose, None)
 }
 
+/// `--reindent target` for a node insert: the text goes to the depth of the line the
+/// node starts on, placed before the node's start or after its end.
+fn reindent_node_insert(
+    file: &Path,
+    identity: &str,
+    new_text: String,
+    before: bool,
+    reindent: Option<Reindent>,
+    registry: &ProviderRegistry,
+) -> Result<String, IdenteditError> {
+    let Some(Reindent::Target) = reindent else {
+        return Ok(new_text);
+    };
+    let handle = resolve_unique_identity_handle_for_patch(file, identity, registry)?;
+    let source = std::fs::read_to_string(file).map_err(|error| IdenteditError::io(file, error))?;
+    let (indentation, _) = insertion_context(&source, handle.span.start);
+    let offset = if before {
+        handle.span.start
+    } else {
+        handle.span.end
+    };
+    let (_, at_line_start) = insertion_context(&source, offset);
+    Ok(reindent_text(
+        &source,
+        &new_text,
+        &indentation,
+        at_line_start,
+    ))
+}
+
+/// `--reindent target` for `--insert-after-line`: the new lines take the deeper of the
+/// anchor line's indentation and the next non-blank line's, so text added after a line
+/// that opens a block lands inside it.
+fn reindent_line_insert(
+    file: &Path,
+    anchor: &str,
+    offset: isize,
+    text: String,
+) -> Result<String, IdenteditError> {
+    let source = std::fs::read_to_string(file).map_err(|error| IdenteditError::io(file, error))?;
+    let landmark = parse_line_ref(anchor).map_err(|error| IdenteditError::InvalidRequest {
+        message: error.to_string(),
+    })?;
+    // An offset outside the file is reported when the edit resolves its anchor.
+    let Some(line) = landmark
+        .line
+        .checked_add_signed(offset)
+        .filter(|line| *line > 0)
+    else {
+        return Ok(text);
+    };
+    let indentation_of =
+        |line: &str| line[..line.len() - line.trim_start_matches([' ', '\t']).len()].to_string();
+    let mut lines = source.lines().skip(line - 1);
+    let anchor_indentation = lines.next().map(indentation_of).unwrap_or_default();
+    let next_indentation = lines
+        .find(|line| !line.trim().is_empty())
+        .map(indentation_of)
+        .unwrap_or_default();
+    let indentation = if next_indentation.len() > anchor_indentation.len() {
+        next_indentation
+    } else {
+        anchor_indentation
+    };
+    Ok(reindent_text(&source, &text, &indentation, true))
+}
+
 fn run_patch_flag_scoped_regex(
     file: PathBuf,
     identity: &str,
     pattern: String,
     replacement: String,
+    options: ScopedRegexOptions,
     verbose: bool,
+    settings: &ApplySettings,
 ) -> Result<Value, IdenteditError> {
-    let handle = resolve_unique_identity_handle_for_patch(&file, identity)?;
+    let registry = settings.provider_registry()?;
+    let handle = resolve_unique_identity_handle_for_patch(&file, identity, &registry)?;
     let target = TransformTarget::node(
         handle.identity,
         handle.kind,
         Some(handle.span),
         hash_text(&handle.text),
     );
-    let rewritten = rewrite_node_target_with_scoped_regex(&file, &target, &pattern, &replacement)?;
+    let rewritten = rewrite_node_target_with_scoped_regex(
+        &file,
+        &target,
+        &pattern,
+        &replacement,
+        options,
+        &registry,
+    )?;
     run_patch_node_operation(
         file,
         target,
@@ -720,6 +2432,54 @@ fn run_patch_flag_scoped_regex(
         },
         verbose,
         Some(rewritten.replacements),
+        settings,
+    )
+}
+
+/// `--script`, alone or with `--scoped-regex`. A script that keeps the text everywhere
+/// still goes through apply as a no-op replace, like a literal `--replace` of the same text.
+fn run_patch_flag_script(
+    file: PathBuf,
+    identity: &str,
+    script: &Path,
+    pattern: Option<&str>,
+    options: ScopedRegexOptions,
+    verbose: bool,
+    settings: &ApplySettings,
+) -> Result<Value, IdenteditError> {
+    let script = ReplacementScript::load(script)?;
+    let registry = settings.provider_registry()?;
+    let handle = resolve_unique_identity_handle_for_patch(&file, identity, &registry)?;
+    let (new_text, replacements) = match pattern {
+        Some(pattern) => {
+            let regex = compile_scoped_regex(pattern)?;
+            let protected = protected_ranges(&file, handle.span, options, &registry)?;
+            let (new_text, matches) = script.rewrite_matches(&handle, &regex, &protected)?;
+            if matches == 0 {
+                return Err(zero_matches_error(pattern));
+            }
+            (new_text, Some(matches))
+        }
+        None => (
+            script
+                .replacement(&handle, None, None)?
+                .unwrap_or_else(|| handle.text.clone()),
+            None,
+        ),
+    };
+    let target = TransformTarget::node(
+        handle.identity,
+        handle.kind,
+        Some(handle.span),
+        hash_text(&handle.text),
+    );
+    run_patch_node_operation(
+        file,
+        target,
+        OpKind::Replace { new_text },
+        verbose,
+        replacements,
+        settings,
     )
 }
 
@@ -727,17 +2487,35 @@ fn run_patch_flag_file_mode(
     file: PathBuf,
     at_file_start: bool,
     args: PatchArgs,
+    settings: &ApplySettings,
 ) -> Result<Value, IdenteditError> {
     if args.identity.is_some()
         || args.anchor.is_some()
         || args.replace.is_some()
         || args.set_value.is_some()
+        || args.set_string.is_some()
+        || args.set_int.is_some()
+        || args.set_bool.is_some()
+        || args.set_raw.is_some()
         || args.append_value.is_some()
+        || args.insert_at.is_some()
+        || args.remove_at.is_some()
+        || args.merge_object.is_some()
+        || args.rename_key.is_some()
+        || args.move_to.is_some()
+        || args.add_dependency.is_some()
+        || args.remove_dependency.is_some()
+        || args.set_dependency_version.is_some()
+        || args.dependency_version.is_some()
+        || args.expected_match_count.is_some()
+        || args.doc.is_some()
         || args.scoped_regex.is_some()
         || args.scoped_replacement.is_some()
         || args.delete
         || args.insert_before.is_some()
         || args.insert_after.is_some()
+        || args.annotate.is_some()
+        || args.script.is_some()
         || args.create_missing
         || args.set_line.is_some()
         || args.replace_range.is_some()
@@ -768,14 +2546,157 @@ fn run_patch_flag_file_mode(
         },
         args.verbose,
         None,
+        settings,
     )
 }
 
+/// Whole-file mode: `--write-file` replaces FILE as it was hashed, or creates it, in one
+/// atomic write.
+fn run_patch_flag_write_file(
+    contents: String,
+    args: PatchArgs,
+    settings: &ApplySettings,
+) -> Result<Value, IdenteditError> {
+    if args.at.is_some()
+        || args.pin.is_some()
+        || args.identity.is_some()
+        || args.anchor.is_some()
+        || args.end_anchor.is_some()
+        || args.offset.is_some()
+        || args.config_path.is_some()
+        || args.replace.is_some()
+        || args.set_value.is_some()
+        || args.set_string.is_some()
+        || args.set_int.is_some()
+        || args.set_bool.is_some()
+        || args.set_raw.is_some()
+        || args.append_value.is_some()
+        || args.insert_at.is_some()
+        || args.remove_at.is_some()
+        || args.merge_object.is_some()
+        || args.rename_key.is_some()
+        || args.move_to.is_some()
+        || args.add_dependency.is_some()
+        || args.remove_dependency.is_some()
+        || args.set_dependency_version.is_some()
+        || args.expected_match_count.is_some()
+        || args.doc.is_some()
+        || args.create_missing
+        || args.insert.is_some()
+        || args.scoped_regex.is_some()
+        || args.script.is_some()
+        || args.delete
+        || args.insert_before.is_some()
+        || args.insert_after.is_some()
+        || args.annotate.is_some()
+        || args.set_line.is_some()
+        || args.replace_range.is_some()
+        || args.insert_after_line.is_some()
+        || args.auto_repair
+        || args.retry_on_stale > 0
+    {
+        return Err(IdenteditError::InvalidRequest {
+            message: "--write-file takes only FILE, --expected-file-hash or --expect-absent, and optional --verbose".to_string(),
+        });
+    }
+    let file = args.file.ok_or_else(|| IdenteditError::InvalidRequest {
+        message: "--write-file requires FILE".to_string(),
+    })?;
+    let precondition = match args.expected_file_hash {
+        Some(hash) => WholeFilePrecondition::FileHash(hash),
+        None if args.expect_absent => WholeFilePrecondition::Absent,
+        None => {
+            return Err(IdenteditError::InvalidRequest {
+                message: "--write-file needs --expected-file-hash to replace FILE or --expect-absent to create it".to_string(),
+            });
+        }
+    };
+
+    let response = write_whole_file(&file, &contents, &precondition, settings)?;
+    serialize_node_patch_response(response, args.verbose, None)
+}
+
+/// Byte-span mode: `--byte-span` replaces raw bytes of FILE with the bytes of
+/// `--replace-bytes-file`, for files the structural and line modes cannot handle.
+fn run_patch_flag_byte_span(
+    span: &str,
+    args: PatchArgs,
+    settings: &ApplySettings,
+) -> Result<Value, IdenteditError> {
+    if args.at.is_some()
+        || args.pin.is_some()
+        || args.identity.is_some()
+        || args.anchor.is_some()
+        || args.config_path.is_some()
+        || args.replace.is_some()
+        || args.replace_file.is_some()
+        || args.replace_stdin
+        || args.set_value.is_some()
+        || args.set_string.is_some()
+        || args.set_int.is_some()
+        || args.set_bool.is_some()
+        || args.set_raw.is_some()
+        || args.append_value.is_some()
+        || args.insert_at.is_some()
+        || args.remove_at.is_some()
+        || args.merge_object.is_some()
+        || args.rename_key.is_some()
+        || args.move_to.is_some()
+        || args.add_dependency.is_some()
+        || args.remove_dependency.is_some()
+        || args.set_dependency_version.is_some()
+        || args.insert.is_some()
+        || args.write_file.is_some()
+        || args.scoped_regex.is_some()
+        || args.script.is_some()
+        || args.delete
+        || args.insert_before.is_some()
+        || args.insert_after.is_some()
+        || args.annotate.is_some()
+        || args.set_line.is_some()
+        || args.replace_range.is_some()
+        || args.insert_after_line.is_some()
+        || args.auto_repair
+        || args.retry_on_stale > 0
+        || args.reread_after
+        || args.invalidated_identities
+    {
+        return Err(IdenteditError::InvalidRequest {
+            message: "--byte-span takes only FILE, --expected-bytes-hash, --replace-bytes-file, and optional --verbose".to_string(),
+        });
+    }
+    let file = args.file.ok_or_else(|| IdenteditError::InvalidRequest {
+        message: "--byte-span requires FILE".to_string(),
+    })?;
+    let (Some(expected_hash), Some(replacement_file)) =
+        (args.expected_bytes_hash, args.replace_bytes_file)
+    else {
+        return Err(IdenteditError::InvalidRequest {
+            message: "--byte-span needs --expected-bytes-hash and --replace-bytes-file".to_string(),
+        });
+    };
+    let span = parse_byte_span(span)?;
+    let replacement = std::fs::read(&replacement_file)
+        .map_err(|error| IdenteditError::io(&replacement_file, error))?;
+
+    let response = write_byte_span(&file, span, &expected_hash, &replacement, settings)?;
+    serialize_node_patch_response(response, args.verbose, None)
+}
+
+fn parse_byte_span(span: &str) -> Result<std::ops::Range<usize>, IdenteditError> {
+    span.split_once(':')
+        .and_then(|(start, end)| Some(start.parse().ok()?..end.parse().ok()?))
+        .ok_or_else(|| IdenteditError::InvalidRequest {
+            message: format!("--byte-span must be START:END byte offsets, got '{span}'"),
+        })
+}
+
 fn resolve_unique_identity_handle_for_patch(
     file: &Path,
     identity: &str,
+    registry: &ProviderRegistry,
 ) -> Result<crate::handle::SelectionHandle, IdenteditError> {
-    let handles = parse_handles_for_file(file)?;
+    let handles = parse_handles_for_file(file, registry)?;
     let matches = handles
         .into_iter()
         .filter(|handle| handle.identity == identity)
@@ -799,22 +2720,40 @@ fn run_patch_flag_line_mode(
     file: PathBuf,
     anchor: String,
     args: PatchArgs,
+    settings: &ApplySettings,
 ) -> Result<Value, IdenteditError> {
     if args.identity.is_some()
         || args.replace.is_some()
         || args.insert.is_some()
         || args.set_value.is_some()
+        || args.set_string.is_some()
+        || args.set_int.is_some()
+        || args.set_bool.is_some()
+        || args.set_raw.is_some()
         || args.append_value.is_some()
+        || args.insert_at.is_some()
+        || args.remove_at.is_some()
+        || args.merge_object.is_some()
+        || args.rename_key.is_some()
+        || args.move_to.is_some()
+        || args.add_dependency.is_some()
+        || args.remove_dependency.is_some()
+        || args.set_dependency_version.is_some()
+        || args.dependency_version.is_some()
+        || args.expected_match_count.is_some()
+        || args.doc.is_some()
         || args.scoped_regex.is_some()
         || args.scoped_replacement.is_some()
         || args.delete
         || args.insert_before.is_some()
         || args.insert_after.is_some()
+        || args.annotate.is_some()
+        || args.script.is_some()
         || args.create_missing
         || args.verbose
     {
         return Err(IdenteditError::InvalidRequest {
-            message: "Line flag mode does not allow node/file-target options (--identity/--replace/--insert/--scoped-regex/--scoped-replacement/--delete/--insert-before/--insert-after/--verbose/--create-missing)".to_string(),
+            message: "Line flag mode does not allow node/file-target options (--identity/--replace/--insert/--scoped-regex/--scoped-replacement/--delete/--insert-before/--insert-after/--annotate/--script/--verbose/--create-missing)".to_string(),
         });
     }
     let line_operation_count = usize::from(args.set_line.is_some())
@@ -856,12 +2795,23 @@ fn run_patch_flag_line_mode(
             .ok_or_else(|| IdenteditError::InvalidRequest {
                 message: "missing operation payload for --insert-after-line".to_string(),
             })?;
+        let text = match args.reindent {
+            Some(Reindent::Target) => {
+                reindent_line_insert(&file, &anchor, args.offset.unwrap_or_default(), text)?
+            }
+            None => text,
+        };
         HashlineEdit::InsertAfter {
             insert_after: InsertAfterEdit { anchor, text },
         }
     };
 
-    let patch_response = execute_hashline_patch(file, vec![edit], args.auto_repair)?;
+    let edit = LineTargetEdit {
+        edit,
+        anchor_match: None,
+        anchor_offset: args.offset.unwrap_or_default(),
+    };
+    let patch_response = execute_hashline_patch(file, vec![edit], args.auto_repair, settings)?;
     serialize_line_patch_response(patch_response)
 }
 
@@ -869,6 +2819,7 @@ fn run_patch_flag_config_mode(
     file: PathBuf,
     path: String,
     args: PatchArgs,
+    settings: &ApplySettings,
 ) -> Result<Value, IdenteditError> {
     if args.at.is_some()
         || args.identity.is_some()
@@ -880,62 +2831,212 @@ fn run_patch_flag_config_mode(
         || args.scoped_replacement.is_some()
         || args.insert_before.is_some()
         || args.insert_after.is_some()
+        || args.annotate.is_some()
+        || args.script.is_some()
         || args.set_line.is_some()
         || args.replace_range.is_some()
         || args.insert_after_line.is_some()
         || args.auto_repair
     {
         return Err(IdenteditError::InvalidRequest {
-            message: "Config path flag mode supports only --set-value, --append-value, or --delete (plus optional --create-missing/--verbose)".to_string(),
+            message: "Config path flag mode supports only --set-value (or --set-string/--set-int/--set-bool/--set-raw), --append-value, --insert-at/--insert-value, --remove-at, --merge-object, --rename-key, --move-to, --add-dependency/--remove-dependency/--set-dependency-version, or --delete (plus optional --dependency-version/ --doc/--create-missing/--on-conflict/--expected-match-count/--verbose)".to_string(),
         });
     }
 
-    if args.create_missing && (args.delete || args.append_value.is_some()) {
+    if args.create_missing
+        && (args.delete
+            || args.append_value.is_some()
+            || args.insert_at.is_some()
+            || args.remove_at.is_some()
+            || args.merge_object.is_some()
+            || args.rename_key.is_some()
+            || args.move_to.is_some()
+            || args.add_dependency.is_some()
+            || args.remove_dependency.is_some()
+            || args.set_dependency_version.is_some())
+    {
         return Err(IdenteditError::InvalidRequest {
-            message: "--create-missing is only valid with --set-value in config path mode"
-                .to_string(),
+            message:
+                "--create-missing is only valid with --set-value (or a typed --set-* flag) in config path mode"
+                    .to_string(),
         });
     }
 
-    let operation_count = usize::from(args.set_value.is_some())
+    let typed_sets = [
+        (args.set_value, ConfigValueType::Raw),
+        (args.set_raw, ConfigValueType::Raw),
+        (args.set_string, ConfigValueType::String),
+        (args.set_int, ConfigValueType::Int),
+        (args.set_bool, ConfigValueType::Bool),
+    ];
+    let set_count = typed_sets.iter().filter(|(text, _)| text.is_some()).count();
+    let set_value = typed_sets
+        .into_iter()
+        .find_map(|(text, value_type)| text.map(|text| (text, value_type)));
+
+    let operation_count = set_count
         + usize::from(args.append_value.is_some())
+        + usize::from(args.insert_at.is_some())
+        + usize::from(args.remove_at.is_some())
+        + usize::from(args.merge_object.is_some())
+        + usize::from(args.rename_key.is_some())
+        + usize::from(args.move_to.is_some())
+        + usize::from(args.add_dependency.is_some())
+        + usize::from(args.remove_dependency.is_some())
+        + usize::from(args.set_dependency_version.is_some())
         + usize::from(args.delete);
     if operation_count != 1 {
         return Err(IdenteditError::InvalidRequest {
             message:
-                "Exactly one config path operation is required: choose one of --set-value, --append-value, or --delete"
+                "Exactly one config path operation is required: choose one of --set-value/--set-string/--set-int/--set-bool/--set-raw, --append-value, --insert-at, --remove-at, --merge-object, --rename-key, --move-to, --add-dependency, --remove-dependency, --set-dependency-version, or --delete"
+                    .to_string(),
+        });
+    }
+
+    if args.dependency_version.is_some()
+        && args.add_dependency.is_none()
+        && args.set_dependency_version.is_none()
+    {
+        return Err(IdenteditError::InvalidRequest {
+            message:
+                "--dependency-version is only valid with --add-dependency or --set-dependency-version"
                     .to_string(),
         });
     }
+    let dependency_operation = match (
+        args.add_dependency,
+        args.remove_dependency,
+        args.set_dependency_version,
+        args.dependency_version,
+    ) {
+        (Some(name), _, _, Some(version)) => Some(DependencyOperation::Add { name, version }),
+        (_, Some(name), _, _) => Some(DependencyOperation::Remove { name }),
+        (_, _, Some(name), Some(version)) => {
+            Some(DependencyOperation::SetVersion { name, version })
+        }
+        _ => None,
+    };
 
-    let canonical = if let Some(new_text) = args.set_value {
-        resolve_config_path_operation(
+    let registry = settings.provider_registry()?;
+    let canonical = if let Some(operation) = dependency_operation {
+        check_dependency_target(&path, args.doc.as_deref(), args.expected_match_count)?;
+        resolve_config_dependency_operation(file.as_path(), &path, None, operation, &registry)?
+    } else if let Some((text, value_type)) = set_value {
+        resolve_config_path_pattern_operation(
             file.as_path(),
             &path,
+            args.doc.as_deref(),
             None,
+            args.expected_match_count,
             ConfigPathOperation::Set {
-                new_text,
+                new_text: encode_config_value(&file, value_type, text)?,
                 create_missing: args.create_missing,
             },
+            &registry,
         )?
     } else if let Some(new_text) = args.append_value {
-        resolve_config_path_operation(
+        resolve_config_path_pattern_operation(
             file.as_path(),
             &path,
+            args.doc.as_deref(),
             None,
+            args.expected_match_count,
             ConfigPathOperation::Append { new_text },
+            &registry,
+        )?
+    } else if let (Some(index), Some(new_text)) = (args.insert_at, args.insert_value) {
+        resolve_config_path_pattern_operation(
+            file.as_path(),
+            &path,
+            args.doc.as_deref(),
+            None,
+            args.expected_match_count,
+            ConfigPathOperation::InsertAt { index, new_text },
+            &registry,
+        )?
+    } else if let Some(index) = args.remove_at {
+        resolve_config_path_pattern_operation(
+            file.as_path(),
+            &path,
+            args.doc.as_deref(),
+            None,
+            args.expected_match_count,
+            ConfigPathOperation::RemoveAt { index },
+            &registry,
+        )?
+    } else if let Some(object_text) = args.merge_object {
+        let object = serde_json::from_str::<MergeObject>(&object_text).map_err(|error| {
+            IdenteditError::InvalidRequest {
+                message: format!("--merge-object must be a JSON object: {error}"),
+            }
+        })?;
+        check_single_target_match_count(&path, args.expected_match_count)?;
+        let Some(patch) = resolve_config_merge_operation(
+            file.as_path(),
+            &path,
+            args.doc.as_deref(),
+            None,
+            &object,
+            args.on_conflict.map(Into::into).unwrap_or_default(),
+            &registry,
+        )?
+        else {
+            return serialize_node_patch_response(ApplyResponse::unchanged(), args.verbose, None);
+        };
+        patch
+    } else if let Some(new_key) = args.rename_key {
+        resolve_config_path_pattern_operation(
+            file.as_path(),
+            &path,
+            args.doc.as_deref(),
+            None,
+            args.expected_match_count,
+            ConfigPathOperation::RenameKey { new_key },
+            &registry,
+        )?
+    } else if let Some(to) = args.move_to {
+        check_single_target_match_count(&path, args.expected_match_count)?;
+        resolve_config_move_operation(
+            file.as_path(),
+            &path,
+            args.doc.as_deref(),
+            None,
+            &to,
+            &registry,
         )?
     } else {
-        resolve_config_path_operation(file.as_path(), &path, None, ConfigPathOperation::Delete)?
+        resolve_config_path_pattern_operation(
+            file.as_path(),
+            &path,
+            args.doc.as_deref(),
+            None,
+            args.expected_match_count,
+            ConfigPathOperation::Delete,
+            &registry,
+        )?
     };
 
-    run_patch_node_operation(file, canonical.target, canonical.op, args.verbose, None)
+    run_patch_node_operation(
+        file,
+        canonical.target,
+        canonical.op,
+        args.verbose,
+        None,
+        settings,
+    )
 }
 
-fn wrap_single_file(file_change: crate::changeset::FileChange) -> MultiFileChangeset {
+fn wrap_single_file(
+    file_change: crate::changeset::FileChange,
+    settings: &ApplySettings,
+) -> MultiFileChangeset {
     MultiFileChangeset {
         files: vec![file_change],
-        transaction: Default::default(),
+        transaction: TransactionSpec {
+            settings: settings.clone(),
+            ..TransactionSpec::default()
+        },
+        correlation_id: None,
     }
 }
 
@@ -0,0 +1,178 @@
+use std::path::Path;
+
+use serde_json::{Value, json};
+
+use crate::error::IdenteditError;
+use crate::handle::{SelectionHandle, Span};
+use crate::provider::ProviderRegistry;
+use crate::transform::parse_handles_for_file;
+
+/// The node a `--retry-on-stale` patch last aimed at.
+#[derive(Debug, Clone)]
+pub(super) struct StaleNode {
+    pub(super) identity: String,
+    pub(super) kind: String,
+    /// Unknown until a resolution sees the node; JSON targets carry no name.
+    pub(super) name: Option<String>,
+    pub(super) span: Option<Span>,
+    pub(super) expected_old_hash: String,
+}
+
+impl From<&SelectionHandle> for StaleNode {
+    fn from(handle: &SelectionHandle) -> Self {
+        Self {
+            identity: handle.identity.clone(),
+            kind: handle.kind.clone(),
+            name: handle.name.clone(),
+            span: Some(handle.span),
+            expected_old_hash: handle.expected_old_hash.clone(),
+        }
+    }
+}
+
+/// Runs `attempt` against `node`, and on `precondition_failed` re-reads `file`, finds the
+/// node again, and retries with its current identity and hash, at most `retries` times.
+/// The node is found by identity, then by kind and name, then by kind at its old start;
+/// when none of these gives exactly one node the precondition failure stands.
+///
+/// With `retries > 0` the response gains `attempts`, one entry per try; so does the error
+/// once a retry has been made.
+pub(super) fn run_with_stale_retries(
+    file: &Path,
+    mut node: StaleNode,
+    retries: usize,
+    registry: &ProviderRegistry,
+    mut attempt: impl FnMut(&StaleNode) -> Result<Value, IdenteditError>,
+) -> Result<Value, IdenteditError> {
+    let mut attempts = Vec::new();
+    loop {
+        match attempt(&node) {
+            Ok(mut response) => {
+                if retries > 0 {
+                    attempts.push(json!({
+                        "attempt": attempts.len() + 1,
+                        "identity": node.identity,
+                        "expected_old_hash": node.expected_old_hash,
+                        "outcome": "applied",
+                    }));
+                    if let Value::Object(fields) = &mut response {
+                        fields.insert("attempts".to_string(), Value::Array(attempts));
+                    }
+                }
+                return Ok(response);
+            }
+            Err(IdenteditError::PreconditionFailed {
+                expected_hash,
+                actual_hash,
+            }) if retries > 0 => {
+                attempts.push(json!({
+                    "attempt": attempts.len() + 1,
+                    "identity": node.identity,
+                    "expected_old_hash": expected_hash,
+                    "actual_hash": actual_hash,
+                    "outcome": "precondition_failed",
+                }));
+                let stale = IdenteditError::PreconditionFailed {
+                    expected_hash,
+                    actual_hash,
+                };
+                if attempts.len() > retries {
+                    return Err(with_attempts(stale, attempts));
+                }
+                node = match re_resolve(file, &node, registry) {
+                    Ok(Some(current)) => current,
+                    Ok(None) => return Err(with_attempts(stale, attempts)),
+                    Err(error) => return Err(with_attempts(error, attempts)),
+                };
+            }
+            Err(error) => return Err(with_attempts(error, attempts)),
+        }
+    }
+}
+
+/// `error` with the tries made before it, when there were any.
+fn with_attempts(error: IdenteditError, attempts: Vec<Value>) -> IdenteditError {
+    if attempts.is_empty() {
+        return error;
+    }
+    IdenteditError::StaleRetries {
+        attempts,
+        error: Box::new(error),
+    }
+}
+
+fn re_resolve(
+    file: &Path,
+    node: &StaleNode,
+    registry: &ProviderRegistry,
+) -> Result<Option<StaleNode>, IdenteditError> {
+    let handles = parse_handles_for_file(file, registry)?;
+    let unique = |matches: &dyn Fn(&SelectionHandle) -> bool| {
+        let mut found = handles.iter().filter(|handle| matches(handle));
+        match (found.next(), found.next()) {
+            (Some(handle), None) => Some(StaleNode::from(handle)),
+            _ => None,
+        }
+    };
+
+    Ok(unique(&|handle| handle.identity == node.identity)
+        .or_else(|| {
+            node.name.as_ref().and_then(|name| {
+                unique(&|handle| handle.kind == node.kind && handle.name.as_ref() == Some(name))
+            })
+        })
+        .or_else(|| {
+            node.span.and_then(|span| {
+                unique(&|handle| handle.kind == node.kind && handle.span.start == span.start)
+            })
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{StaleNode, run_with_stale_retries};
+    use crate::error::IdenteditError;
+    use crate::provider::ProviderRegistry;
+    use crate::transform::parse_handles_for_file;
+
+    #[test]
+    fn exhausted_retries_report_every_attempt_in_the_error() {
+        let directory = tempfile::tempdir().expect("tempdir should be created");
+        let file = directory.path().join("example.py");
+        fs::write(&file, "def process(value):\n    return value + 1\n")
+            .expect("fixture should be written");
+        let registry = ProviderRegistry::default();
+        let handles = parse_handles_for_file(&file, &registry).expect("file should parse");
+        let handle = handles
+            .iter()
+            .find(|handle| handle.name.as_deref() == Some("process"))
+            .expect("function should be found");
+
+        let mut tries = 0;
+        let error = run_with_stale_retries(&file, StaleNode::from(handle), 2, &registry, |_| {
+            tries += 1;
+            Err(IdenteditError::PreconditionFailed {
+                expected_hash: "old".to_string(),
+                actual_hash: "new".to_string(),
+            })
+        })
+        .expect_err("a node that keeps changing should exhaust the retries");
+
+        assert_eq!(tries, 3);
+        let response = error.to_error_response();
+        assert_eq!(response.error.r#type, "precondition_failed");
+        let attempts = response
+            .error
+            .attempts
+            .expect("attempts should be reported");
+        assert_eq!(attempts.len(), 3);
+        assert!(
+            attempts
+                .iter()
+                .all(|attempt| attempt["outcome"] == "precondition_failed")
+        );
+        assert_eq!(attempts[2]["attempt"], 3);
+    }
+}
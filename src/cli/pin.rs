@@ -0,0 +1,111 @@
+use std::fs;
+use std::path::PathBuf;
+
+use clap::{Args, Subcommand};
+use serde::Serialize;
+
+use crate::cli::read::ReadHandle;
+use crate::error::IdenteditError;
+use crate::handle::LineIndex;
+use crate::pin::{Pin, PinStatus, add_pin, list_pins, remove_pin, resolve_pin};
+use crate::provider::ProviderRegistry;
+
+#[derive(Debug, Args)]
+pub struct PinArgs {
+    #[command(subcommand)]
+    pub command: PinCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PinCommands {
+    #[command(about = "Pin a node identity under an alias (replaces an existing pin)")]
+    Add(PinAddArgs),
+    #[command(about = "List pins with their current status")]
+    List,
+    #[command(about = "Resolve a pin to the current handle of its node")]
+    Resolve(PinResolveArgs),
+    #[command(about = "Remove a pin")]
+    Remove(PinRemoveArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct PinAddArgs {
+    #[arg(
+        value_name = "ALIAS",
+        help = "Pin name (letters, digits, '_', '-', '.')"
+    )]
+    pub alias: String,
+    #[arg(
+        long,
+        value_name = "IDENTITY",
+        help = "Node identity to pin (from read)"
+    )]
+    pub at: String,
+    #[arg(value_name = "FILE", help = "File containing the node")]
+    pub file: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct PinResolveArgs {
+    #[arg(value_name = "ALIAS")]
+    pub alias: String,
+    #[arg(long, help = "Include the node text in the resolved handle")]
+    pub verbose: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct PinRemoveArgs {
+    #[arg(value_name = "ALIAS")]
+    pub alias: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum PinResponse {
+    Pin { pin: Pin },
+    List { pins: Vec<PinListEntry> },
+    Resolved { pin: Pin, handle: Box<ReadHandle> },
+    Removed { removed: Pin },
+}
+
+#[derive(Debug, Serialize)]
+pub struct PinListEntry {
+    #[serde(flatten)]
+    pub pin: Pin,
+    pub status: PinStatus,
+}
+
+pub fn run_pin(args: PinArgs, registry: &ProviderRegistry) -> Result<PinResponse, IdenteditError> {
+    match args.command {
+        PinCommands::Add(add_args) => Ok(PinResponse::Pin {
+            pin: add_pin(
+                &add_args.alias,
+                &add_args.file,
+                &add_args.at.to_ascii_lowercase(),
+                registry,
+            )?,
+        }),
+        PinCommands::List => Ok(PinResponse::List {
+            pins: list_pins(registry)?
+                .into_iter()
+                .map(|(pin, status)| PinListEntry { pin, status })
+                .collect(),
+        }),
+        PinCommands::Resolve(resolve_args) => {
+            let (pin, handle) = resolve_pin(&resolve_args.alias, registry)?;
+            let source =
+                fs::read(&handle.file).map_err(|error| IdenteditError::io(&handle.file, error))?;
+            Ok(PinResponse::Resolved {
+                pin,
+                handle: Box::new(ReadHandle::from_selection_handle(
+                    handle,
+                    &LineIndex::new(&source),
+                    resolve_args.verbose,
+                )),
+            })
+        }
+        PinCommands::Remove(remove_args) => Ok(PinResponse::Removed {
+            removed: remove_pin(&remove_args.alias)?,
+        }),
+    }
+}
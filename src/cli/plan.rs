@@ -0,0 +1,155 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use clap::{Args, Subcommand, ValueEnum};
+
+use crate::changeset::MultiFileChangeset;
+use crate::compression::{read_plan_file, read_plan_stdin};
+use crate::error::IdenteditError;
+use crate::provider::ProviderRegistry;
+
+use super::plan_export::{ScriptFormat, export_plan};
+use super::plan_hash::{PlanHashResponse, hash_plan_response};
+use super::plan_rebase::{PlanRebaseResponse, rebase_plan};
+
+#[derive(Debug, Args)]
+pub struct PlanArgs {
+    #[command(subcommand)]
+    pub command: PlanCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PlanCommands {
+    #[command(
+        about = "Convert a unified diff into an edit plan with hashline anchors (pipe into apply)"
+    )]
+    FromDiff(PlanFromDiffArgs),
+    #[command(
+        about = "Render a plan as a script of standalone patch commands (shell) or JSON patch requests (python)"
+    )]
+    Export(PlanExportArgs),
+    #[command(
+        about = "Refresh a stale plan's span hints and hashes against the current files, dropping operations that no longer resolve"
+    )]
+    Rebase(PlanRebaseArgs),
+    #[command(
+        about = "Print a digest of the plan that ignores key order, whitespace, and correlation_id; apply reports it as plan_hash"
+    )]
+    Hash(PlanHashArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct PlanFromDiffArgs {
+    #[arg(
+        value_name = "DIFF",
+        help = "Unified diff file ('-' reads stdin); paths resolve from the current directory"
+    )]
+    pub diff: PathBuf,
+    #[arg(
+        long,
+        help = "Emit verbose preview fields (old_text) instead of compact fields"
+    )]
+    pub verbose: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct PlanExportArgs {
+    #[arg(long, value_enum, default_value_t = PlanExportFormat::Shell)]
+    pub format: PlanExportFormat,
+    #[arg(
+        value_name = "PLAN",
+        help = "Path to edit-plan JSON; if omitted, read raw plan JSON from stdin"
+    )]
+    pub input: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct PlanRebaseArgs {
+    #[arg(
+        value_name = "PLAN",
+        help = "Path to edit-plan JSON; if omitted, read raw plan JSON from stdin"
+    )]
+    pub input: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct PlanHashArgs {
+    #[arg(
+        value_name = "PLAN",
+        help = "Path to edit-plan JSON; if omitted, read raw plan JSON from stdin"
+    )]
+    pub input: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PlanExportFormat {
+    Shell,
+    Python,
+}
+
+impl From<PlanExportFormat> for ScriptFormat {
+    fn from(value: PlanExportFormat) -> Self {
+        match value {
+            PlanExportFormat::Shell => ScriptFormat::Shell,
+            PlanExportFormat::Python => ScriptFormat::Python,
+        }
+    }
+}
+
+pub enum PlanCommandOutput {
+    Plan(MultiFileChangeset),
+    Script(String),
+    Rebased(PlanRebaseResponse),
+    Hashed(PlanHashResponse),
+}
+
+pub fn run_plan(
+    args: PlanArgs,
+    registry: &ProviderRegistry,
+) -> Result<PlanCommandOutput, IdenteditError> {
+    match args.command {
+        PlanCommands::FromDiff(from_diff_args) => {
+            let diff_text = if from_diff_args.diff.as_os_str() == "-" {
+                let mut buffer = String::new();
+                std::io::stdin()
+                    .read_to_string(&mut buffer)
+                    .map_err(|source| IdenteditError::StdinRead { source })?;
+                buffer
+            } else {
+                std::fs::read_to_string(&from_diff_args.diff)
+                    .map_err(|error| IdenteditError::io(&from_diff_args.diff, error))?
+            };
+            let mut changeset = super::diff_plan::plan_from_diff(&diff_text, registry)?;
+            super::edit_build::apply_preview_mode(&mut changeset, from_diff_args.verbose);
+            Ok(PlanCommandOutput::Plan(changeset))
+        }
+        PlanCommands::Export(export_args) => {
+            let changeset = read_plan(export_args.input.as_deref())?;
+            let script = export_plan(&changeset, export_args.format.into())
+                .map_err(|error| error.with_correlation_id(changeset.correlation_id.as_deref()))?;
+            Ok(PlanCommandOutput::Script(
+                script.trim_end_matches('\n').to_string(),
+            ))
+        }
+        PlanCommands::Rebase(rebase_args) => Ok(PlanCommandOutput::Rebased(rebase_plan(
+            read_plan(rebase_args.input.as_deref())?,
+            registry,
+        ))),
+        PlanCommands::Hash(hash_args) => {
+            let changeset = read_plan(hash_args.input.as_deref())?;
+            let response = hash_plan_response(&changeset)
+                .map_err(|error| error.with_correlation_id(changeset.correlation_id.as_deref()))?;
+            Ok(PlanCommandOutput::Hashed(response))
+        }
+    }
+}
+
+fn read_plan(input: Option<&Path>) -> Result<MultiFileChangeset, IdenteditError> {
+    let plan_text = match input {
+        Some(path) => read_plan_file(path)?,
+        None => read_plan_stdin()?,
+    };
+    let changeset: MultiFileChangeset = serde_json::from_str(&plan_text)
+        .map_err(|source| IdenteditError::InvalidJsonRequest { source })?;
+    Ok(changeset)
+}
@@ -0,0 +1,464 @@
+//! Edit plan to script rendering (`plan export`).
+//!
+//! A plan is applied atomically against the files it was built from, while an exported
+//! script runs one `identedit patch` per operation. Operations are therefore emitted
+//! bottom-up within each file (by matched span) so that line anchors and node spans of
+//! the remaining steps still point at unchanged text. Each step keeps its own hash check,
+//! except file-start/file-end inserts: shell steps use patch flag mode, which hashes the
+//! file when the step runs, and Python steps do the same once an earlier step has
+//! changed the file.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use serde_json::json;
+
+use crate::changeset::{ChangeOp, MultiFileChangeset, OpKind, TransformTarget};
+use crate::error::IdenteditError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ScriptFormat {
+    Shell,
+    Python,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ExportStep {
+    file: String,
+    action: StepAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum StepAction {
+    Node {
+        target: TransformTarget,
+        identity: String,
+        op: NodeAction,
+    },
+    Line {
+        anchor: String,
+        end_anchor: Option<String>,
+        op: LineAction,
+    },
+    File {
+        at_start: bool,
+        expected_file_hash: String,
+        /// Set when an earlier step already changed the file, so the plan's hash is stale.
+        refresh_file_hash: bool,
+        new_text: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum NodeAction {
+    Replace(String),
+    Delete,
+    InsertBefore(String),
+    InsertAfter(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum LineAction {
+    ReplaceLines(String),
+    InsertAfter(String),
+}
+
+pub(super) fn export_plan(
+    changeset: &MultiFileChangeset,
+    format: ScriptFormat,
+) -> Result<String, IdenteditError> {
+    let steps = plan_steps(changeset)?;
+    match format {
+        ScriptFormat::Shell => Ok(render_shell(&steps)),
+        ScriptFormat::Python => render_python(&steps),
+    }
+}
+
+fn plan_steps(changeset: &MultiFileChangeset) -> Result<Vec<ExportStep>, IdenteditError> {
+    let mut steps = Vec::new();
+    for file_change in &changeset.files {
+        let file = display_path(&file_change.file);
+        let mut operations = file_change.operations.iter().collect::<Vec<_>>();
+        operations.sort_by_key(|operation| {
+            let span = &operation.preview.matched_span;
+            std::cmp::Reverse((span.start, span.end))
+        });
+        for (position, operation) in operations.into_iter().enumerate() {
+            steps.push(ExportStep {
+                file: file.clone(),
+                action: step_action(&file, operation, position > 0)?,
+            });
+        }
+    }
+    Ok(steps)
+}
+
+fn step_action(
+    file: &str,
+    operation: &ChangeOp,
+    file_already_changed: bool,
+) -> Result<StepAction, IdenteditError> {
+    let unsupported = || IdenteditError::InvalidRequest {
+        message: format!(
+            "plan export cannot express a '{}' operation on a '{}' target in {file}",
+            op_name(&operation.op),
+            target_name(&operation.target)
+        ),
+    };
+
+    match &operation.target {
+        TransformTarget::Node { identity, .. } => {
+            let op = match &operation.op {
+                OpKind::Replace { new_text } => NodeAction::Replace(new_text.clone()),
+                OpKind::Delete => NodeAction::Delete,
+                OpKind::InsertBefore { new_text } => NodeAction::InsertBefore(new_text.clone()),
+                OpKind::InsertAfter { new_text } => NodeAction::InsertAfter(new_text.clone()),
+                _ => return Err(unsupported()),
+            };
+            Ok(StepAction::Node {
+                target: operation.target.clone(),
+                identity: identity.clone(),
+                op,
+            })
+        }
+        TransformTarget::Line { anchor, end_anchor } => {
+            // Plan line targets cover whole lines including their line endings, while patch
+            // line operations take line contents and keep the file's line endings.
+            let op = match &operation.op {
+                OpKind::Replace { new_text } => {
+                    LineAction::ReplaceLines(strip_final_newline(new_text).to_string())
+                }
+                OpKind::Delete => LineAction::ReplaceLines(String::new()),
+                OpKind::InsertAfter { new_text } if end_anchor.is_none() => {
+                    let text = strip_final_newline(new_text);
+                    if text.is_empty() {
+                        return Err(IdenteditError::InvalidRequest {
+                            message: format!(
+                                "plan export cannot express an empty line insert after {anchor} in {file}"
+                            ),
+                        });
+                    }
+                    LineAction::InsertAfter(text.to_string())
+                }
+                _ => return Err(unsupported()),
+            };
+            Ok(StepAction::Line {
+                anchor: anchor.clone(),
+                end_anchor: end_anchor.clone(),
+                op,
+            })
+        }
+        TransformTarget::FileStart { expected_file_hash }
+        | TransformTarget::FileEnd { expected_file_hash } => {
+            let OpKind::Insert { new_text } = &operation.op else {
+                return Err(unsupported());
+            };
+            Ok(StepAction::File {
+                at_start: matches!(operation.target, TransformTarget::FileStart { .. }),
+                expected_file_hash: expected_file_hash.clone(),
+                refresh_file_hash: file_already_changed,
+                new_text: new_text.clone(),
+            })
+        }
+    }
+}
+
+fn strip_final_newline(text: &str) -> &str {
+    text.strip_suffix("\r\n")
+        .or_else(|| text.strip_suffix('\n'))
+        .unwrap_or(text)
+}
+
+fn display_path(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+fn op_name(op: &OpKind) -> &'static str {
+    match op {
+        OpKind::Replace { .. } => "replace",
+        OpKind::Delete => "delete",
+        OpKind::InsertBefore { .. } => "insert_before",
+        OpKind::InsertAfter { .. } => "insert_after",
+        OpKind::Insert { .. } => "insert",
+        OpKind::MoveBefore { .. } => "move_before",
+        OpKind::MoveAfter { .. } => "move_after",
+        OpKind::Move { .. } => "move",
+    }
+}
+
+fn target_name(target: &TransformTarget) -> &'static str {
+    match target {
+        TransformTarget::Node { .. } => "node",
+        TransformTarget::FileStart { .. } => "file_start",
+        TransformTarget::FileEnd { .. } => "file_end",
+        TransformTarget::Line { .. } => "line",
+    }
+}
+
+fn render_shell(steps: &[ExportStep]) -> String {
+    let mut script = String::from(
+        "#!/bin/sh\n\
+         # Generated by `identedit plan export --format shell`.\n\
+         # Each step re-checks its own target; the script stops at the first failure.\n\
+         set -e\n",
+    );
+    for step in steps {
+        let file = if step.file.starts_with('-') {
+            format!("./{}", step.file)
+        } else {
+            step.file.clone()
+        };
+        let mut words = vec![
+            "identedit".to_string(),
+            "patch".to_string(),
+            shell_quote(&file),
+        ];
+        match &step.action {
+            StepAction::Node { identity, op, .. } => {
+                push_flag(&mut words, "--at", identity);
+                match op {
+                    NodeAction::Replace(text) => push_flag(&mut words, "--replace", text),
+                    NodeAction::Delete => words.push("--delete".to_string()),
+                    NodeAction::InsertBefore(text) => {
+                        push_flag(&mut words, "--insert-before", text)
+                    }
+                    NodeAction::InsertAfter(text) => push_flag(&mut words, "--insert-after", text),
+                }
+            }
+            StepAction::Line {
+                anchor,
+                end_anchor,
+                op,
+            } => {
+                push_flag(&mut words, "--at", anchor);
+                match op {
+                    LineAction::ReplaceLines(text) => {
+                        push_flag(&mut words, "--replace-range", text);
+                        if let Some(end_anchor) = end_anchor {
+                            push_flag(&mut words, "--end-anchor", end_anchor);
+                        }
+                    }
+                    LineAction::InsertAfter(text) => {
+                        push_flag(&mut words, "--insert-after-line", text)
+                    }
+                }
+            }
+            StepAction::File {
+                at_start, new_text, ..
+            } => {
+                let at = if *at_start { "file-start" } else { "file-end" };
+                push_flag(&mut words, "--at", at);
+                push_flag(&mut words, "--insert", new_text);
+            }
+        }
+        script.push('\n');
+        script.push_str(&words.join(" "));
+        script.push('\n');
+    }
+    script
+}
+
+/// Values starting with `-` are attached with `=` so clap does not read them as flags.
+fn push_flag(words: &mut Vec<String>, flag: &str, value: &str) {
+    if value.starts_with('-') {
+        words.push(shell_quote(&format!("{flag}={value}")));
+    } else {
+        words.push(flag.to_string());
+        words.push(shell_quote(value));
+    }
+}
+
+fn shell_quote(value: &str) -> String {
+    let is_plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric() || "_-./:=,+@%".contains(character));
+    if is_plain {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+const PYTHON_PRELUDE: &str = r#"#!/usr/bin/env python3
+# Generated by `identedit plan export --format python`.
+# Each step re-checks its own target; the script stops at the first failure.
+import json
+import subprocess
+import sys
+
+IDENTEDIT = "identedit"
+
+
+def current_file_hash(path):
+    result = subprocess.run(
+        [IDENTEDIT, "read", "--mode", "line", "--json", path],
+        capture_output=True,
+        text=True,
+    )
+    if result.returncode != 0:
+        sys.stdout.write(result.stdout)
+        sys.exit(result.returncode)
+    return json.loads(result.stdout)["file_preconditions"][0]["expected_file_hash"]
+
+
+def patch(request, refresh_file_hash=False):
+    if refresh_file_hash:
+        request["target"]["expected_file_hash"] = current_file_hash(request["file"])
+    result = subprocess.run(
+        [IDENTEDIT, "patch", "--json"],
+        input=json.dumps(request),
+        capture_output=True,
+        text=True,
+    )
+    if result.returncode != 0:
+        sys.stdout.write(result.stdout)
+        sys.exit(result.returncode)
+"#;
+
+fn render_python(steps: &[ExportStep]) -> Result<String, IdenteditError> {
+    let mut script = format!("{PYTHON_PRELUDE}\n");
+    for step in steps {
+        let (target, op, refresh_file_hash) = match &step.action {
+            StepAction::Node { target, op, .. } => {
+                let op = match op {
+                    NodeAction::Replace(text) => json!({"type": "replace", "new_text": text}),
+                    NodeAction::Delete => json!({"type": "delete"}),
+                    NodeAction::InsertBefore(text) => {
+                        json!({"type": "insert_before", "new_text": text})
+                    }
+                    NodeAction::InsertAfter(text) => {
+                        json!({"type": "insert_after", "new_text": text})
+                    }
+                };
+                (
+                    serde_json::to_value(target)
+                        .map_err(|source| IdenteditError::ResponseSerialization { source })?,
+                    op,
+                    false,
+                )
+            }
+            StepAction::Line {
+                anchor,
+                end_anchor,
+                op,
+            } => {
+                let mut target = json!({"type": "line", "anchor": anchor});
+                if let Some(end_anchor) = end_anchor {
+                    target["end_anchor"] = json!(end_anchor);
+                }
+                let op = match op {
+                    LineAction::ReplaceLines(text) => {
+                        json!({"type": "replace_lines", "new_text": text})
+                    }
+                    LineAction::InsertAfter(text) => json!({"type": "insert_after", "text": text}),
+                };
+                (target, op, false)
+            }
+            StepAction::File {
+                at_start,
+                expected_file_hash,
+                refresh_file_hash,
+                new_text,
+            } => {
+                let target_type = if *at_start { "file_start" } else { "file_end" };
+                (
+                    json!({"type": target_type, "expected_file_hash": expected_file_hash}),
+                    json!({"type": "insert", "new_text": new_text}),
+                    *refresh_file_hash,
+                )
+            }
+        };
+        let request = json!({
+            "command": "patch",
+            "file": step.file,
+            "target": target,
+            "op": op,
+        });
+        // The request holds only objects, strings and integers, so its JSON text is also
+        // a valid Python literal.
+        let literal = serde_json::to_string_pretty(&request)
+            .map_err(|source| IdenteditError::ResponseSerialization { source })?;
+        if refresh_file_hash {
+            let _ = write!(script, "\npatch({literal}, refresh_file_hash=True)\n");
+        } else {
+            let _ = write!(script, "\npatch({literal})\n");
+        }
+    }
+    Ok(script)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{ScriptFormat, export_plan, shell_quote};
+    use crate::changeset::{
+        ChangeOp, ChangePreview, FileChange, MultiFileChangeset, OpKind, TransformTarget,
+    };
+    use crate::handle::Span;
+
+    fn line_op(anchor: &str, start: usize, new_text: &str) -> ChangeOp {
+        ChangeOp {
+            id: None,
+            depends_on: Vec::new(),
+            correlation_id: None,
+            group: None,
+            target: TransformTarget::Line {
+                anchor: anchor.to_string(),
+                end_anchor: None,
+            },
+            optional: false,
+            op: OpKind::Replace {
+                new_text: new_text.to_string(),
+            },
+            preview: ChangePreview {
+                old_text: None,
+                old_hash: None,
+                old_len: None,
+                new_text: new_text.to_string(),
+                matched_span: Span {
+                    start,
+                    end: start + 4,
+                },
+                move_preview: None,
+            },
+        }
+    }
+
+    #[test]
+    fn shell_quote_leaves_plain_words_bare() {
+        assert_eq!(shell_quote("src/lib.rs"), "src/lib.rs");
+        assert_eq!(shell_quote("3:a1b2c3d4e5f6"), "3:a1b2c3d4e5f6");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote(""), "''");
+    }
+
+    #[test]
+    fn shell_export_orders_steps_bottom_up_and_strips_line_endings() {
+        let changeset = MultiFileChangeset {
+            files: vec![FileChange {
+                file: PathBuf::from("notes.txt"),
+                operations: vec![
+                    line_op("1:aaaaaaaaaaaa", 0, "ALPHA\n"),
+                    line_op("3:bbbbbbbbbbbb", 11, "-gamma\n"),
+                ],
+            }],
+            transaction: Default::default(),
+            correlation_id: None,
+        };
+
+        let script = export_plan(&changeset, ScriptFormat::Shell).expect("export should succeed");
+        let commands = script
+            .lines()
+            .filter(|line| line.starts_with("identedit"))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            commands,
+            vec![
+                "identedit patch notes.txt --at 3:bbbbbbbbbbbb --replace-range=-gamma",
+                "identedit patch notes.txt --at 1:aaaaaaaaaaaa --replace-range ALPHA",
+            ]
+        );
+    }
+}
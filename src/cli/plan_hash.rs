@@ -0,0 +1,97 @@
+//! `plan hash`: a digest of what a plan does, independent of how its JSON is laid out.
+//!
+//! The plan is parsed first, so omitted defaults and path spellings do not matter, then
+//! written back with sorted keys and no whitespace. `correlation_id` is left out: it tags a
+//! request rather than describing an edit. `apply` reports the same digest as `plan_hash`.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::changeset::MultiFileChangeset;
+use crate::error::IdenteditError;
+use crate::hash::{HASH_ALGORITHM, hash_text};
+
+#[derive(Debug, Serialize)]
+pub struct PlanHashResponse {
+    pub plan_hash: String,
+    pub algorithm: &'static str,
+    pub files: usize,
+    pub operations: usize,
+}
+
+pub(super) fn hash_plan_response(
+    changeset: &MultiFileChangeset,
+) -> Result<PlanHashResponse, IdenteditError> {
+    Ok(PlanHashResponse {
+        plan_hash: plan_hash(changeset)?,
+        algorithm: HASH_ALGORITHM,
+        files: changeset.files.len(),
+        operations: changeset
+            .files
+            .iter()
+            .map(|file_change| file_change.operations.len())
+            .sum(),
+    })
+}
+
+/// The hash of the canonical JSON of `changeset`.
+pub(super) fn plan_hash(changeset: &MultiFileChangeset) -> Result<String, IdenteditError> {
+    let mut value = serde_json::to_value(changeset)
+        .map_err(|source| IdenteditError::ResponseSerialization { source })?;
+    if let Value::Object(fields) = &mut value {
+        fields.remove("correlation_id");
+    }
+    let canonical = serde_json::to_string(&sort_keys(value))
+        .map_err(|source| IdenteditError::ResponseSerialization { source })?;
+    Ok(hash_text(&canonical))
+}
+
+/// `value` with the keys of every object in sorted order, whatever map type serde_json
+/// was built with.
+fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Object(fields) => {
+            let mut entries = fields.into_iter().collect::<Vec<_>>();
+            entries.sort_by(|(left, _), (right, _)| left.cmp(right));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, sort_keys(value)))
+                    .collect::<Map<_, _>>(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(sort_keys).collect()),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::changeset::MultiFileChangeset;
+
+    use super::plan_hash;
+
+    #[test]
+    fn layout_defaults_and_correlation_ids_do_not_change_the_hash() {
+        let compact = r#"{"files":[{"file":"a.py","operations":[{"target":{"type":"file_end","expected_file_hash":"0123456789abcdef"},"op":{"type":"insert","new_text":"x\n"},"preview":{"old_text":"","new_text":"x\n","matched_span":{"start":0,"end":0}}}]}]}"#;
+        let spread = r#"{
+            "correlation_id": "run-7",
+            "transaction": {"mode": "all_or_nothing"},
+            "files": [{
+                "operations": [{
+                    "preview": {"matched_span": {"end": 0, "start": 0}, "new_text": "x\n", "old_text": ""},
+                    "op": {"new_text": "x\n", "type": "insert"},
+                    "target": {"expected_file_hash": "0123456789abcdef", "type": "file_end"}
+                }],
+                "file": "a.py"
+            }]
+        }"#;
+        let hash = |text: &str| {
+            let changeset: MultiFileChangeset =
+                serde_json::from_str(text).expect("plan should parse");
+            plan_hash(&changeset).expect("plan should hash")
+        };
+        assert_eq!(hash(compact), hash(spread));
+        assert_ne!(hash(compact), hash(&compact.replace("x\\n", "y\\n")));
+    }
+}
@@ -0,0 +1,316 @@
+//! `plan rebase`: moves a plan's preconditions onto the current files after unrelated
+//! edits, instead of regenerating the whole plan.
+//!
+//! A node target is rebased when its identity still names exactly one node of its kind:
+//! the identity covers the node's text, so only its position can have changed. A line
+//! anchor is rebased when its hash still matches exactly one line, and a file-start or
+//! file-end target takes the current file hash, since text inserted at either end does not
+//! depend on the rest of the file. Every other operation is dropped, together with the
+//! operations that list a dropped one in `depends_on`.
+
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+
+use crate::apply::{FailedOperation, OperationLabels, OperationRef};
+use crate::changeset::{ChangeOp, FileChange, MultiFileChangeset, OpKind, TransformTarget};
+use crate::error::{ErrorBody, IdenteditError};
+use crate::handle::SelectionHandle;
+use crate::hash::hash_text;
+use crate::hashline::{
+    HashlineAlgo, check_hashline_refs, format_line_ref, parse_line_ref, show_hashed_lines_with,
+};
+use crate::path_style::render_path;
+use crate::provider::ProviderRegistry;
+use crate::transform::{parse_handles_for_source, resolve_each_changeset_target_in_handles};
+
+#[derive(Debug, Serialize)]
+pub struct PlanRebaseResponse {
+    /// The plan with rebased operations in place and dropped ones removed.
+    pub changeset: MultiFileChangeset,
+    pub summary: PlanRebaseSummary,
+    /// Operations whose target or preview changed, indexed into the input plan.
+    pub rebased: Vec<OperationRef>,
+    /// Operations left out of `changeset`, indexed into the input plan.
+    pub dropped: Vec<FailedOperation>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct PlanRebaseSummary {
+    pub operations_total: usize,
+    pub unchanged: usize,
+    pub rebased: usize,
+    pub dropped: usize,
+}
+
+pub(super) fn rebase_plan(
+    changeset: MultiFileChangeset,
+    registry: &ProviderRegistry,
+) -> PlanRebaseResponse {
+    let mut outcomes = changeset
+        .files
+        .iter()
+        .map(|file_change| rebase_file(file_change, registry))
+        .collect::<Vec<_>>();
+
+    // Dependents of a dropped operation would fail `depends_on` validation, so they go too.
+    loop {
+        let dropped_ids = changeset
+            .files
+            .iter()
+            .zip(&outcomes)
+            .flat_map(|(file_change, outcomes)| file_change.operations.iter().zip(outcomes))
+            .filter(|(_, outcome)| outcome.is_err())
+            .filter_map(|(operation, _)| operation.id.clone())
+            .collect::<BTreeSet<_>>();
+        let mut changed = false;
+        for outcome in outcomes.iter_mut().flatten() {
+            let Ok(operation) = outcome else {
+                continue;
+            };
+            if let Some(prerequisite) = operation
+                .depends_on
+                .iter()
+                .find(|id| dropped_ids.contains(*id))
+            {
+                *outcome = Err(IdenteditError::InvalidRequest {
+                    message: format!("Depends on dropped operation '{prerequisite}'"),
+                }
+                .to_error_response()
+                .error);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let mut summary = PlanRebaseSummary::default();
+    let mut rebased = Vec::new();
+    let mut dropped = Vec::new();
+    let mut files = Vec::with_capacity(changeset.files.len());
+    for (file_change, outcomes) in changeset.files.iter().zip(outcomes) {
+        let file = render_path(&file_change.file);
+        let mut operations = Vec::with_capacity(outcomes.len());
+        for (index, (original, outcome)) in file_change.operations.iter().zip(outcomes).enumerate()
+        {
+            summary.operations_total += 1;
+            match outcome {
+                Ok(operation) => {
+                    if operation == *original {
+                        summary.unchanged += 1;
+                    } else {
+                        rebased.push(OperationRef {
+                            file: file.clone(),
+                            operation: index,
+                            labels: OperationLabels::of(original),
+                        });
+                    }
+                    operations.push(operation);
+                }
+                Err(error) => dropped.push(FailedOperation {
+                    file: file.clone(),
+                    operation: index,
+                    labels: OperationLabels::of(original),
+                    error,
+                }),
+            }
+        }
+        if !operations.is_empty() {
+            files.push(FileChange {
+                file: file_change.file.clone(),
+                operations,
+            });
+        }
+    }
+    summary.rebased = rebased.len();
+    summary.dropped = dropped.len();
+
+    PlanRebaseResponse {
+        changeset: MultiFileChangeset {
+            files,
+            transaction: changeset.transaction,
+            correlation_id: changeset.correlation_id,
+        },
+        summary,
+        rebased,
+        dropped,
+    }
+}
+
+/// The rebased form of each operation in `file_change`, or why it was dropped.
+fn rebase_file(
+    file_change: &FileChange,
+    registry: &ProviderRegistry,
+) -> Vec<Result<ChangeOp, ErrorBody>> {
+    let fail_all = |error: IdenteditError| {
+        let error = error.to_error_response().error;
+        file_change
+            .operations
+            .iter()
+            .map(|_| Err(error.clone()))
+            .collect()
+    };
+    let source = match std::fs::read_to_string(&file_change.file) {
+        Ok(source) => source,
+        Err(error) => return fail_all(IdenteditError::io(&file_change.file, error)),
+    };
+    let handles = if file_change
+        .operations
+        .iter()
+        .any(|operation| operation.target.requires_node_resolution())
+    {
+        match parse_handles_for_source(&file_change.file, source.as_bytes(), registry) {
+            Ok(handles) => handles,
+            Err(error) => return fail_all(error),
+        }
+    } else {
+        Vec::new()
+    };
+
+    file_change
+        .operations
+        .iter()
+        .map(|operation| {
+            rebase_operation(file_change, operation, &source, &handles)
+                .map_err(|error| error.to_error_response().error)
+        })
+        .collect()
+}
+
+fn rebase_operation(
+    file_change: &FileChange,
+    operation: &ChangeOp,
+    source: &str,
+    handles: &[SelectionHandle],
+) -> Result<ChangeOp, IdenteditError> {
+    if matches!(operation.op, OpKind::Move { .. }) {
+        return Ok(operation.clone());
+    }
+    let mut rebased = operation.clone();
+    rebased.target = rebase_target(file_change, &operation.target, source, handles)?;
+    if let OpKind::MoveBefore { destination } | OpKind::MoveAfter { destination } = &mut rebased.op
+    {
+        **destination = rebase_target(file_change, destination, source, handles)?;
+    }
+
+    let single = FileChange {
+        file: file_change.file.clone(),
+        operations: vec![rebased.clone()],
+    };
+    let matched = resolve_each_changeset_target_in_handles(&single, source, handles)
+        .into_iter()
+        .next()
+        .ok_or_else(|| IdenteditError::InvalidRequest {
+            message: "Internal rebase error: operation did not resolve".to_string(),
+        })??;
+
+    // The edit was planned against this text; a different text means a different edit.
+    let planned_hash = operation
+        .preview
+        .old_hash
+        .clone()
+        .or_else(|| operation.preview.old_text.as_deref().map(hash_text));
+    let actual_hash = hash_text(&matched.old_text);
+    if let Some(planned_hash) = planned_hash
+        && planned_hash != actual_hash
+    {
+        return Err(IdenteditError::PreconditionFailed {
+            expected_hash: planned_hash,
+            actual_hash,
+        });
+    }
+    rebased.preview.matched_span = matched.matched_span;
+    Ok(rebased)
+}
+
+fn rebase_target(
+    file_change: &FileChange,
+    target: &TransformTarget,
+    source: &str,
+    handles: &[SelectionHandle],
+) -> Result<TransformTarget, IdenteditError> {
+    let file = file_change.file.display().to_string();
+    match target {
+        TransformTarget::Node {
+            identity,
+            kind,
+            span_hint,
+            position_hint,
+            ..
+        } => {
+            let candidates = handles
+                .iter()
+                .filter(|handle| handle.identity == *identity && handle.kind == *kind)
+                .collect::<Vec<_>>();
+            let handle = match candidates.as_slice() {
+                [handle] => handle,
+                [] => {
+                    return Err(IdenteditError::TargetMissing {
+                        identity: identity.clone(),
+                        file,
+                    });
+                }
+                candidates => {
+                    return Err(IdenteditError::AmbiguousTarget {
+                        identity: identity.clone(),
+                        file,
+                        candidates: candidates.len(),
+                    });
+                }
+            };
+            Ok(TransformTarget::Node {
+                identity: identity.clone(),
+                kind: kind.clone(),
+                span_hint: (span_hint.is_some() || position_hint.is_some()).then_some(handle.span),
+                position_hint: None,
+                expected_old_hash: handle.expected_old_hash.clone(),
+            })
+        }
+        TransformTarget::FileStart { .. } => Ok(TransformTarget::FileStart {
+            expected_file_hash: hash_text(source),
+        }),
+        TransformTarget::FileEnd { .. } => Ok(TransformTarget::FileEnd {
+            expected_file_hash: hash_text(source),
+        }),
+        TransformTarget::Line { anchor, end_anchor } => Ok(TransformTarget::Line {
+            anchor: rebase_line_anchor(&file, anchor, source)?,
+            end_anchor: end_anchor
+                .as_deref()
+                .map(|end_anchor| rebase_line_anchor(&file, end_anchor, source))
+                .transpose()?,
+        }),
+    }
+}
+
+/// `anchor` if it still holds, else the anchor of the one line whose hash it carries.
+fn rebase_line_anchor(file: &str, anchor: &str, source: &str) -> Result<String, IdenteditError> {
+    let invalid = |error: crate::hashline::HashlineCheckError| IdenteditError::InvalidRequest {
+        message: error.to_string(),
+    };
+    if check_hashline_refs(source, &[anchor.to_string()])
+        .map_err(invalid)?
+        .ok
+    {
+        return Ok(anchor.to_string());
+    }
+    let parsed = parse_line_ref(anchor).map_err(invalid)?;
+    let algo = HashlineAlgo::from_hex_len(parsed.hash.len()).unwrap_or_default();
+    let candidates = show_hashed_lines_with(source, algo)
+        .into_iter()
+        .filter(|line| line.hash == parsed.hash)
+        .collect::<Vec<_>>();
+    match candidates.as_slice() {
+        [line] => Ok(format_line_ref(line.line, &line.hash)),
+        [] => Err(IdenteditError::TargetMissing {
+            identity: anchor.to_string(),
+            file: file.to_string(),
+        }),
+        candidates => Err(IdenteditError::AmbiguousTarget {
+            identity: anchor.to_string(),
+            file: file.to_string(),
+            candidates: candidates.len(),
+        }),
+    }
+}
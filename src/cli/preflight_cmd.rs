@@ -0,0 +1,99 @@
+//! `--preflight-cmd`: user checks that run before `apply` or `patch` writes, such as
+//! whether a formatter daemon is watching a target or a custom policy allows the edit.
+//!
+//! Each program gets `{"command": ..., "files": [...]}` on stdin. Exit status 0 lets the
+//! write go ahead; anything else aborts it before a file is touched. Whatever the program
+//! prints on stdout, parsed as JSON when it is JSON, is attached to the response.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::IdenteditError;
+use crate::path_style::render_path;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PreflightCheck {
+    pub program: String,
+    /// The program's stdout: JSON when it parses as JSON, otherwise the trimmed text; null
+    /// when it printed nothing.
+    pub output: Value,
+}
+
+#[derive(Debug, Serialize)]
+struct PreflightInput<'a> {
+    command: &'a str,
+    files: Vec<String>,
+}
+
+/// Runs every program in order against the files `command` is about to write, and stops
+/// at the first one that refuses.
+pub(super) fn run_preflight_cmds<'a>(
+    programs: &[PathBuf],
+    command: &str,
+    files: impl IntoIterator<Item = &'a Path>,
+) -> Result<Vec<PreflightCheck>, IdenteditError> {
+    if programs.is_empty() {
+        return Ok(Vec::new());
+    }
+    let input = PreflightInput {
+        command,
+        files: files.into_iter().map(render_path).collect(),
+    };
+    let input = serde_json::to_vec(&input)
+        .map_err(|source| IdenteditError::ResponseSerialization { source })?;
+    programs
+        .iter()
+        .map(|program| {
+            run_preflight_cmd(program, &input).map_err(|message| IdenteditError::PreflightFailed {
+                program: program.display().to_string(),
+                message,
+            })
+        })
+        .collect()
+}
+
+fn run_preflight_cmd(program: &Path, input: &[u8]) -> Result<PreflightCheck, String> {
+    let mut child = Command::new(program)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| format!("could not start: {error}"))?;
+    // As for codemods: written from a separate thread, and a program that ignores stdin
+    // is fine.
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = input.to_vec();
+    let writer = std::thread::spawn(move || {
+        let _ = stdin.write_all(&input);
+    });
+    let output = child
+        .wait_with_output()
+        .map_err(|error| format!("could not be waited on: {error}"))?;
+    let _ = writer.join();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let report = [stdout.trim(), stderr.trim()]
+            .into_iter()
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        return Err(format!("{}: {report}", output.status));
+    }
+    Ok(PreflightCheck {
+        program: program.display().to_string(),
+        output: parse_output(stdout.trim()),
+    })
+}
+
+fn parse_output(stdout: &str) -> Value {
+    if stdout.is_empty() {
+        return Value::Null;
+    }
+    serde_json::from_str(stdout).unwrap_or_else(|_| Value::String(stdout.to_string()))
+}
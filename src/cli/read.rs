@@ -2,17 +2,30 @@ use std::collections::{BTreeMap, HashSet};
 use std::fs;
 #[cfg(unix)]
 use std::os::unix::fs::MetadataExt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use clap::{Args, ValueEnum};
-use glob::Pattern;
 use serde::Serialize;
 
 use crate::error::IdenteditError;
-use crate::handle::SelectionHandle;
-use crate::hash::hash_bytes;
-use crate::hashline::{format_line_ref, show_hashed_lines};
-use crate::provider::ProviderRegistry;
+use crate::handle::{LineIndex, Position, SelectionHandle};
+use crate::hash::{hash_bytes, hash_text};
+use crate::hashline::{HashlineAlgo, format_line_ref, show_hashed_lines_with};
+use crate::patch::config_path::list_config_entries;
+use crate::patch::indentation::{Indentation, detect_indentation};
+use crate::provider::{HeaderDecision, ProviderRegistry, StructureProvider};
+use crate::selector::{HandleFilter, NameMode};
+use crate::text_encoding::TextEncoding;
+
+use super::read_annotations::collect_annotations;
+use super::read_budget::fit_response_bytes;
+use super::read_context::attach_context;
+use super::read_metrics::{HandleMetrics, attach_metrics};
+use super::read_page::sort_and_paginate;
+use super::read_redact::{compile_redactions, redact_response};
+use super::read_resolve::{collect_identities, unresolved_identities};
+use super::read_rev::read_at_revision;
+use super::read_window::{ReadWindow, parse_read_window};
 
 #[derive(Debug, Args)]
 pub struct ReadArgs {
@@ -20,27 +33,144 @@ pub struct ReadArgs {
         long,
         value_enum,
         default_value_t = ReadMode::Ast,
-        help = "Read mode (ast|line)"
+        help = "Read mode (ast|line|config)"
     )]
     pub mode: ReadMode,
     #[arg(
         long,
         value_name = "KIND",
-        help = "Optional node kind filter (ast mode only)"
+        help = "Node kind filter (repeatable; matches any, ast mode only)"
     )]
-    pub kind: Option<String>,
+    pub kind: Vec<String>,
     #[arg(
         long,
         value_name = "GLOB",
         help = "Optional glob pattern for symbol names (ast mode only)"
     )]
     pub name: Option<String>,
+    #[arg(
+        long = "name-regex",
+        value_name = "REGEX",
+        help = "Optional regular expression for symbol names, unanchored (ast mode only)"
+    )]
+    pub name_regex: Option<String>,
     #[arg(
         long = "exclude-kind",
+        visible_alias = "not-kind",
         value_name = "KIND",
         help = "Exclude a node kind (repeatable, ast mode only)"
     )]
     pub exclude_kinds: Vec<String>,
+    #[arg(
+        long = "not-name",
+        value_name = "GLOB",
+        help = "Exclude symbols whose name matches GLOB (repeatable, ast mode only)"
+    )]
+    pub not_names: Vec<String>,
+    #[arg(
+        long = "name-mode",
+        value_enum,
+        default_value_t = NameMode::Exact,
+        help = "How --name/--name-regex/--not-name compare names (exact|ci|nfc)"
+    )]
+    pub name_mode: NameMode,
+    #[arg(
+        long,
+        value_name = "START:END",
+        help = "Only handles intersecting lines START..=END (1-based)"
+    )]
+    pub lines: Option<String>,
+    #[arg(
+        long = "byte-range",
+        value_name = "START:END",
+        help = "Only handles intersecting bytes [START, END)"
+    )]
+    pub byte_range: Option<String>,
+    #[arg(
+        long = "containing-line",
+        value_name = "LINE",
+        help = "Only the innermost node covering LINE (ast mode only)"
+    )]
+    pub containing_line: Option<usize>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = ReadSort::Position,
+        help = "Handle order (position|name|size); size lists the largest first"
+    )]
+    pub sort: ReadSort,
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 0,
+        help = "Skip the first N handles after sorting"
+    )]
+    pub offset: usize,
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Return at most N handles; summary.page.next_offset points at the next page"
+    )]
+    pub limit: Option<usize>,
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Include N lines before and after each handle as context_before/context_after (ast and config modes)"
+    )]
+    pub context: Option<usize>,
+    #[arg(
+        long,
+        help = "List TODO/FIXME/HACK/XXX comment markers as annotation handles (ast mode only)"
+    )]
+    pub annotations: bool,
+    #[arg(
+        long = "with-metrics",
+        help = "Add line count, byte count, nesting depth, and a cyclomatic estimate to each handle (ast mode, --json only)"
+    )]
+    pub with_metrics: bool,
+    #[arg(
+        long,
+        value_name = "IDENTITY",
+        help = "Return the nodes with this identity, with current text, across all FILEs (repeatable, ast mode only)"
+    )]
+    pub resolve: Vec<String>,
+    #[arg(
+        long = "resolve-file",
+        value_name = "PATH",
+        help = "Also resolve the identities listed in PATH, one per line"
+    )]
+    pub resolve_file: Option<PathBuf>,
+    #[arg(
+        long = "hash-algo",
+        value_enum,
+        help = "Line-hash algorithm for anchors (line mode only): blake3-12 (default) or blake3-24, for very large files where short hashes collide"
+    )]
+    pub hash_algo: Option<HashlineAlgo>,
+    #[arg(
+        long = "text-encoding",
+        value_enum,
+        default_value_t = TextEncoding::Utf8,
+        help = "Encoding of handle text in JSON output (utf8|base64)"
+    )]
+    pub text_encoding: TextEncoding,
+    #[arg(
+        long,
+        value_name = "REGEX",
+        help = "Mask matches of REGEX in returned texts as [REDACTED]; spans and hashes still describe the file as it is (repeatable)"
+    )]
+    pub redact: Vec<String>,
+    #[arg(
+        long = "if-none-match",
+        value_name = "TOKEN",
+        help = "Return not_modified instead of handles when the files still match this state_token from an earlier --json read"
+    )]
+    pub if_none_match: Option<String>,
+    #[arg(
+        long,
+        value_name = "REV",
+        help = "Read each FILE as committed at this git revision instead of from the working tree"
+    )]
+    pub rev: Option<String>,
     #[arg(long, help = "Emit structured JSON output")]
     #[arg(action = clap::ArgAction::Count)]
     pub json: u8,
@@ -59,6 +189,15 @@ pub struct ReadArgs {
 pub enum ReadMode {
     Ast,
     Line,
+    Config,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum ReadSort {
+    Position,
+    Name,
+    Size,
 }
 
 #[derive(Debug, Serialize)]
@@ -66,14 +205,44 @@ pub struct ReadResponse {
     pub handles: Vec<ReadHandle>,
     pub summary: ReadSummary,
     pub file_preconditions: Vec<FilePrecondition>,
+    /// Set when `text` fields hold base64 of the UTF-8 source rather than the source itself.
+    #[serde(skip_serializing_if = "TextEncoding::is_utf8")]
+    pub text_encoding: TextEncoding,
+    /// Identities requested with `--resolve` that matched no node; absent without `--resolve`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unresolved: Option<Vec<String>>,
+    /// Changes whenever a file's content or the identedit build does; pass it back with
+    /// `--if-none-match` to skip an unchanged re-read.
+    pub state_token: String,
+    /// The `--json` stdin request's `correlation_id`, echoed back.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+}
+
+/// The `--if-none-match` response when no file changed since the token was issued.
+#[derive(Debug, Serialize)]
+pub struct ReadNotModified {
+    pub status: ReadStatus,
+    pub state_token: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadStatus {
+    NotModified,
 }
 
 #[derive(Debug, Serialize)]
 #[serde(tag = "target_type", rename_all = "snake_case")]
 pub enum ReadHandle {
     Node {
+        #[serde(serialize_with = "crate::path_style::serialize_path")]
         file: PathBuf,
         span: crate::handle::Span,
+        start_position: Position,
+        end_position: Position,
         kind: String,
         #[serde(skip_serializing_if = "Option::is_none")]
         name: Option<String>,
@@ -81,34 +250,123 @@ pub enum ReadHandle {
         expected_old_hash: String,
         #[serde(skip_serializing_if = "Option::is_none")]
         text: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        context_before: Option<Vec<String>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        context_after: Option<Vec<String>>,
+        /// `text` was cut short or left out, and context lines left out, to keep the
+        /// response within `--max-response-bytes`.
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        text_truncated: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        metrics: Option<HandleMetrics>,
     },
     Line {
+        #[serde(serialize_with = "crate::path_style::serialize_path")]
         file: PathBuf,
         line: usize,
         anchor: String,
         hash: String,
+        hash_algo: HashlineAlgo,
         text: String,
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        text_truncated: bool,
+    },
+    Config {
+        #[serde(serialize_with = "crate::path_style::serialize_path")]
+        file: PathBuf,
+        path: String,
+        value_type: String,
+        preview: String,
+        span: crate::handle::Span,
+        expected_value_hash: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        context_before: Option<Vec<String>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        context_after: Option<Vec<String>>,
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        text_truncated: bool,
+    },
+    /// A marker comment found by `--annotations`; `anchor` addresses its line.
+    Annotation {
+        #[serde(serialize_with = "crate::path_style::serialize_path")]
+        file: PathBuf,
+        line: usize,
+        anchor: String,
+        marker: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        ticket: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        date: Option<String>,
+        message: String,
     },
 }
 
 #[derive(Debug, Serialize)]
 pub struct ReadSummary {
     pub files_scanned: usize,
+    /// All matching handles, including those outside the returned page.
     pub matches: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<ReadPage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub truncation: Option<ReadTruncation>,
+    /// Matches masked by `--redact`; absent without it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redactions: Option<usize>,
+}
+
+/// Present when `--offset` or `--limit` selected a page of the sorted handles.
+#[derive(Debug, Serialize)]
+pub struct ReadPage {
+    pub offset: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    pub returned: usize,
+    /// Offset of the next page, or null on the last page.
+    pub next_offset: Option<usize>,
+}
+
+/// Present when handle texts were cut to keep the response within `--max-response-bytes`.
+/// Handle metadata is never cut.
+#[derive(Debug, Serialize)]
+pub struct ReadTruncation {
+    pub max_response_bytes: usize,
+    /// Handles marked `text_truncated`.
+    pub truncated_handles: usize,
+    /// Offset to read again from, with `--offset`, to get the texts left out; null when
+    /// none were. A handle whose text alone exceeds the limit is cut rather than left out,
+    /// so reading again always makes progress.
+    pub next_offset: Option<usize>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct FilePrecondition {
+    #[serde(serialize_with = "crate::path_style::serialize_path")]
     pub file: PathBuf,
     pub expected_file_hash: String,
+    /// Detected indentation; absent when no line is indented or the file is not UTF-8.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub indentation: Option<Indentation>,
+    /// Which grammar parsed a `.h` file and why; absent for other files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header_language: Option<HeaderDecision>,
+    /// Commit the file was read from with `--rev`; absent for working-tree reads.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revision: Option<String>,
 }
 
 pub enum ReadCommandOutput {
     Text(String),
     Json(ReadResponse),
+    NotModified(ReadNotModified),
 }
 
-pub fn run_read(args: ReadArgs) -> Result<ReadCommandOutput, IdenteditError> {
+pub fn run_read(
+    args: ReadArgs,
+    max_response_bytes: Option<usize>,
+    registry: &ProviderRegistry,
+) -> Result<ReadCommandOutput, IdenteditError> {
     if args.json > 1 && !args.files.is_empty() {
         return Err(IdenteditError::InvalidRequest {
             message:
@@ -117,7 +375,90 @@ pub fn run_read(args: ReadArgs) -> Result<ReadCommandOutput, IdenteditError> {
         });
     }
 
+    if args.json == 0 && !args.text_encoding.is_utf8() {
+        return Err(IdenteditError::InvalidRequest {
+            message: "--text-encoding applies only to --json output".to_string(),
+        });
+    }
+    if args.json == 0 && args.context.is_some() {
+        return Err(IdenteditError::InvalidRequest {
+            message: "--context applies only to --json output".to_string(),
+        });
+    }
+    if args.json == 0 && args.if_none_match.is_some() {
+        return Err(IdenteditError::InvalidRequest {
+            message: "--if-none-match applies only to --json output".to_string(),
+        });
+    }
+    if args.hash_algo.is_some() && args.mode != ReadMode::Line {
+        return Err(IdenteditError::InvalidRequest {
+            message: "--hash-algo applies only to --mode line".to_string(),
+        });
+    }
+    if args.context.is_some() && args.mode == ReadMode::Line {
+        return Err(IdenteditError::InvalidRequest {
+            message: "--context is not supported in --mode line; widen --lines instead".to_string(),
+        });
+    }
+    if args.annotations {
+        if args.mode != ReadMode::Ast || args.files.is_empty() {
+            return Err(IdenteditError::InvalidRequest {
+                message: "--annotations requires --mode ast and FILE arguments".to_string(),
+            });
+        }
+        if has_node_filters(&args) || args.context.is_some() {
+            return Err(IdenteditError::InvalidRequest {
+                message: "--annotations does not accept node filters or --context".to_string(),
+            });
+        }
+    }
+    if args.with_metrics
+        && (args.mode != ReadMode::Ast
+            || args.files.is_empty()
+            || args.json == 0
+            || args.annotations)
+    {
+        return Err(IdenteditError::InvalidRequest {
+            message: "--with-metrics requires --json, --mode ast, and FILE arguments, and does not combine with --annotations".to_string(),
+        });
+    }
+
+    let redactions = compile_redactions(&args.redact)?;
+
+    let resolve = if args.resolve.is_empty() && args.resolve_file.is_none() {
+        None
+    } else {
+        if args.mode != ReadMode::Ast || args.files.is_empty() {
+            return Err(IdenteditError::InvalidRequest {
+                message: "--resolve requires --mode ast and FILE arguments".to_string(),
+            });
+        }
+        if has_node_filters(&args)
+            || args.annotations
+            || args.lines.is_some()
+            || args.byte_range.is_some()
+            || args.containing_line.is_some()
+        {
+            return Err(IdenteditError::InvalidRequest {
+                message: "--resolve does not accept node filters, --lines/--byte-range/--containing-line, or --annotations"
+                    .to_string(),
+            });
+        }
+        Some(collect_identities(
+            &args.resolve,
+            args.resolve_file.as_deref(),
+        )?)
+    };
+    let resolve_set: Option<HashSet<&str>> = resolve
+        .as_ref()
+        .map(|identities| identities.iter().map(String::as_str).collect());
+
     if args.files.is_empty() {
+        if args.rev.is_some() {
+            return Err(IdenteditError::InvalidRequest {
+                message: "--rev requires FILE arguments".to_string(),
+            });
+        }
         if args.json == 0 {
             return Err(IdenteditError::InvalidRequest {
                 message: "At least one FILE is required".to_string(),
@@ -130,7 +471,7 @@ pub fn run_read(args: ReadArgs) -> Result<ReadCommandOutput, IdenteditError> {
         }
 
         if args.json > 1 {
-            if args.kind.is_some() {
+            if !args.kind.is_empty() {
                 return Err(IdenteditError::InvalidRequest {
                     message:
                         "--json stdin mode does not allow --kind; encode selector.kind in the JSON payload"
@@ -144,6 +485,13 @@ pub fn run_read(args: ReadArgs) -> Result<ReadCommandOutput, IdenteditError> {
                             .to_string(),
                 });
             }
+            if args.name_regex.is_some() {
+                return Err(IdenteditError::InvalidRequest {
+                    message:
+                        "--json stdin mode does not allow --name-regex; encode selector.name_regex in the JSON payload"
+                            .to_string(),
+                });
+            }
             if !args.exclude_kinds.is_empty() {
                 return Err(IdenteditError::InvalidRequest {
                     message:
@@ -151,33 +499,101 @@ pub fn run_read(args: ReadArgs) -> Result<ReadCommandOutput, IdenteditError> {
                             .to_string(),
                 });
             }
+            if !args.not_names.is_empty() {
+                return Err(IdenteditError::InvalidRequest {
+                    message:
+                        "--json stdin mode does not allow --not-name; encode selector.exclude_names in the JSON payload"
+                            .to_string(),
+                });
+            }
+            if args.name_mode != NameMode::Exact {
+                return Err(IdenteditError::InvalidRequest {
+                    message:
+                        "--json stdin mode does not allow --name-mode; encode selector.name_mode in the JSON payload"
+                            .to_string(),
+                });
+            }
+        }
+        if args.lines.is_some() || args.byte_range.is_some() || args.containing_line.is_some() {
+            return Err(IdenteditError::InvalidRequest {
+                message:
+                    "--json stdin mode does not allow --lines/--byte-range/--containing-line; pass FILE arguments instead"
+                        .to_string(),
+            });
+        }
+        if args.context.is_some() {
+            return Err(IdenteditError::InvalidRequest {
+                message: "--json stdin mode does not allow --context; pass FILE arguments instead"
+                    .to_string(),
+            });
+        }
+        let response = super::read_select::run_read_select_from_stdin(args.verbose, registry)?;
+        let mut response = ReadResponse::from_read_select_response(response);
+        if let Some(mut not_modified) =
+            not_modified(args.if_none_match.as_deref(), &response.state_token)
+        {
+            not_modified.correlation_id = response.correlation_id;
+            return Ok(ReadCommandOutput::NotModified(not_modified));
+        }
+        let correlation_id = response.correlation_id.clone();
+        let tag = |error: IdenteditError| error.with_correlation_id(correlation_id.as_deref());
+        sort_and_paginate(&mut response, args.sort, args.offset, args.limit).map_err(tag)?;
+        redact_response(&mut response, &redactions);
+        response.encode_text(args.text_encoding);
+        fit_response_bytes(&mut response, args.offset, max_response_bytes).map_err(tag)?;
+        return Ok(ReadCommandOutput::Json(response));
+    }
+
+    let window = parse_read_window(
+        args.lines.as_deref(),
+        args.byte_range.as_deref(),
+        args.containing_line,
+    )?;
+    if matches!(window, Some(ReadWindow::ContainingLine(_))) && args.mode != ReadMode::Ast {
+        return Err(IdenteditError::InvalidRequest {
+            message: "--containing-line is only supported in ast mode".to_string(),
+        });
+    }
+
+    // Hashing is much cheaper than parsing, so an unchanged poll stops here.
+    if let Some(token) = args.if_none_match.as_deref() {
+        let hashes = args
+            .files
+            .iter()
+            .map(|file| {
+                let (source, _) = load_source(file, args.rev.as_deref())?;
+                Ok((file.as_path(), hash_bytes(&source)))
+            })
+            .collect::<Result<Vec<_>, IdenteditError>>()?;
+        if let Some(not_modified) = not_modified(Some(token), &state_token(args.mode, hashes)) {
+            return Ok(ReadCommandOutput::NotModified(not_modified));
         }
-        let response = super::read_select::run_read_select_from_stdin(args.verbose)?;
-        return Ok(ReadCommandOutput::Json(ReadResponse::from_read_select_response(
-            response,
-        )));
     }
 
-    let provider_registry = ProviderRegistry::default();
     let mut handles = Vec::new();
     let mut file_preconditions = Vec::new();
     let mut seen_canonical_paths = HashSet::with_capacity(args.files.len());
     #[cfg(unix)]
     let mut seen_file_keys = HashSet::with_capacity(args.files.len());
 
-    let compiled_name_pattern =
-        args.name
+    let handle_filter = HandleFilter::new(
+        args.kind.clone(),
+        args.exclude_kinds.clone(),
+        args.name.as_deref(),
+        args.name_regex
             .as_deref()
-            .map(Pattern::new)
-            .transpose()
-            .map_err(|error| IdenteditError::InvalidNamePattern {
-                pattern: args.name.clone().unwrap_or_default(),
-                message: error.msg.to_string(),
-            })?;
+            .map(|regex| ("--name-regex", regex)),
+        &args.not_names,
+        args.name_mode,
+    )?;
 
     for file in &args.files {
-        let canonical_path =
-            fs::canonicalize(file).map_err(|error| IdenteditError::io(file, error))?;
+        let canonical_path = match fs::canonicalize(file) {
+            Ok(path) => path,
+            // A file deleted since the revision is still read from it.
+            Err(_) if args.rev.is_some() => file.clone(),
+            Err(error) => return Err(IdenteditError::io(file, error)),
+        };
         if !seen_canonical_paths.insert(canonical_path.clone()) {
             return Err(IdenteditError::InvalidRequest {
                 message: format!(
@@ -187,7 +603,7 @@ pub fn run_read(args: ReadArgs) -> Result<ReadCommandOutput, IdenteditError> {
             });
         }
         #[cfg(unix)]
-        {
+        if args.rev.is_none() || canonical_path.exists() {
             let metadata = fs::metadata(&canonical_path)
                 .map_err(|error| IdenteditError::io(&canonical_path, error))?;
             let file_key = (metadata.dev(), metadata.ino());
@@ -201,28 +617,85 @@ pub fn run_read(args: ReadArgs) -> Result<ReadCommandOutput, IdenteditError> {
             }
         }
 
-        let source = fs::read(file).map_err(|error| IdenteditError::io(file, error))?;
+        let (source, revision) = load_source(file, args.rev.as_deref())?;
+        let file_start = handles.len();
+        let mut header_language = None;
         match args.mode {
             ReadMode::Ast => {
-                let provider = provider_registry.provider_for(file)?;
+                let provider = registry.provider_for(file)?;
                 let parsed_handles = provider.parse(file, &source)?;
-                let filtered_handles = filter_ast_handles(
-                    parsed_handles,
-                    args.kind.as_deref(),
-                    compiled_name_pattern.as_ref(),
-                    &args.exclude_kinds,
-                );
+                header_language = header_decision(provider, &source)?;
+                if args.annotations {
+                    handles.extend(collect_annotations(file, &source, &parsed_handles, window)?);
+                    file_preconditions.push(FilePrecondition {
+                        file: file.clone(),
+                        expected_file_hash: hash_bytes(&source),
+                        indentation: detect_file_indentation(&source),
+                        header_language,
+                        revision,
+                    });
+                    continue;
+                }
+                let mut filtered_handles = parsed_handles
+                    .into_iter()
+                    .filter(|handle| match &resolve_set {
+                        Some(identities) => identities.contains(handle.identity.as_str()),
+                        None => handle_filter.matches(handle),
+                    })
+                    .collect();
+                if let Some(window) = window {
+                    filtered_handles = window.filter_nodes(&source, filtered_handles);
+                }
+                let lines = LineIndex::new(&source);
+                handles.extend(filtered_handles.into_iter().map(|handle| {
+                    ReadHandle::from_selection_handle(
+                        handle,
+                        &lines,
+                        args.verbose || resolve.is_some(),
+                    )
+                }));
+                if args.with_metrics {
+                    attach_metrics(&mut handles[file_start..], &source, provider);
+                }
+            }
+            ReadMode::Line => {
+                if has_node_filters(&args) {
+                    return Err(IdenteditError::InvalidRequest {
+                        message: "--mode line does not accept --kind/--name/--name-regex/--exclude-kind/--not-name filters"
+                            .to_string(),
+                    });
+                }
+                let source_text = String::from_utf8(source.clone()).map_err(|error| {
+                    IdenteditError::io(
+                        file,
+                        std::io::Error::new(std::io::ErrorKind::InvalidData, error),
+                    )
+                })?;
+                let hash_algo = args.hash_algo.unwrap_or_default();
+                let lines = show_hashed_lines_with(&source_text, hash_algo);
                 handles.extend(
-                    filtered_handles
+                    lines
                         .into_iter()
-                        .map(|handle| ReadHandle::from_selection_handle(handle, args.verbose)),
+                        .filter(|line| {
+                            window.is_none_or(|window| window.includes_line(&source, line.line))
+                        })
+                        .map(|line| ReadHandle::Line {
+                            file: file.clone(),
+                            line: line.line,
+                            anchor: format_line_ref(line.line, &line.hash),
+                            hash: line.hash,
+                            hash_algo,
+                            text: line.content,
+                            text_truncated: false,
+                        }),
                 );
             }
-            ReadMode::Line => {
-                if args.kind.is_some() || args.name.is_some() || !args.exclude_kinds.is_empty() {
+            ReadMode::Config => {
+                if has_node_filters(&args) {
                     return Err(IdenteditError::InvalidRequest {
-                        message: "--mode line does not accept --kind/--name/--exclude-kind filters"
-                            .to_string(),
+                        message:
+                            "--mode config does not accept --kind/--name/--name-regex/--exclude-kind/--not-name filters"
+                                .to_string(),
                     });
                 }
                 let source_text = String::from_utf8(source.clone()).map_err(|error| {
@@ -231,78 +704,162 @@ pub fn run_read(args: ReadArgs) -> Result<ReadCommandOutput, IdenteditError> {
                         std::io::Error::new(std::io::ErrorKind::InvalidData, error),
                     )
                 })?;
-                let lines = show_hashed_lines(&source_text);
-                handles.extend(lines.into_iter().map(|line| ReadHandle::Line {
-                    file: file.clone(),
-                    line: line.line,
-                    anchor: format_line_ref(line.line, &line.hash),
-                    hash: line.hash,
-                    text: line.content,
-                }));
+                let entries = list_config_entries(file, &source_text)?;
+                handles.extend(
+                    entries
+                        .into_iter()
+                        .filter(|entry| {
+                            window.is_none_or(|window| window.intersects(&source, entry.span))
+                        })
+                        .map(|entry| ReadHandle::Config {
+                            file: file.clone(),
+                            path: entry.path,
+                            value_type: entry.value_type.to_string(),
+                            preview: entry.preview,
+                            span: entry.span,
+                            expected_value_hash: entry.value_hash,
+                            context_before: None,
+                            context_after: None,
+                            text_truncated: false,
+                        }),
+                );
             }
         }
+        if let Some(lines) = args.context {
+            attach_context(&mut handles[file_start..], &source, lines);
+        }
         file_preconditions.push(FilePrecondition {
             file: file.clone(),
             expected_file_hash: hash_bytes(&source),
+            indentation: detect_file_indentation(&source),
+            header_language,
+            revision,
         });
     }
 
-    let response = ReadResponse {
+    let unresolved = resolve
+        .as_ref()
+        .map(|identities| unresolved_identities(identities, &handles));
+    let state_token = state_token(
+        args.mode,
+        file_preconditions.iter().map(|precondition| {
+            (
+                precondition.file.as_path(),
+                precondition.expected_file_hash.clone(),
+            )
+        }),
+    );
+    let mut response = ReadResponse {
         summary: ReadSummary {
             files_scanned: args.files.len(),
             matches: handles.len(),
+            page: None,
+            truncation: None,
+            redactions: None,
         },
         handles,
         file_preconditions,
+        text_encoding: TextEncoding::Utf8,
+        unresolved,
+        state_token,
+        correlation_id: None,
     };
+    sort_and_paginate(&mut response, args.sort, args.offset, args.limit)?;
+    redact_response(&mut response, &redactions);
 
     if args.json > 0 {
+        response.encode_text(args.text_encoding);
+        fit_response_bytes(&mut response, args.offset, max_response_bytes)?;
         return Ok(ReadCommandOutput::Json(response));
     }
 
+    if args.annotations {
+        return Ok(ReadCommandOutput::Text(render_annotation_text(
+            &response.handles,
+        )));
+    }
     Ok(ReadCommandOutput::Text(render_human_readable(
         &response, args.mode,
     )))
 }
 
-fn filter_ast_handles(
-    handles: Vec<SelectionHandle>,
-    kind_filter: Option<&str>,
-    name_pattern: Option<&Pattern>,
-    exclude_kinds: &[String],
-) -> Vec<SelectionHandle> {
-    handles
-        .into_iter()
-        .filter(|handle| {
-            if exclude_kinds
-                .iter()
-                .any(|excluded_kind| excluded_kind == &handle.kind)
-            {
-                return false;
-            }
+/// An opaque digest of the read mode, each file's path and content hash, and the identedit
+/// version, whose bundled grammars decide which handles a file yields.
+fn state_token<'a>(mode: ReadMode, files: impl IntoIterator<Item = (&'a Path, String)>) -> String {
+    let mut state = format!("identedit {}\n{mode:?}\n", crate::version());
+    for (file, hash) in files {
+        state.push_str(&format!("{}\t{hash}\n", file.display()));
+    }
+    hash_text(&state)
+}
 
-            if let Some(kind) = kind_filter
-                && handle.kind != kind
-            {
-                return false;
-            }
+/// The bytes of `file`, from the working tree or as committed at `rev`, with the commit id.
+fn load_source(
+    file: &Path,
+    rev: Option<&str>,
+) -> Result<(Vec<u8>, Option<String>), IdenteditError> {
+    match rev {
+        Some(rev) => read_at_revision(file, rev).map(|(source, commit)| (source, Some(commit))),
+        None => fs::read(file)
+            .map(|source| (source, None))
+            .map_err(|error| IdenteditError::io(file, error)),
+    }
+}
 
-            if let Some(pattern) = name_pattern {
-                return handle
-                    .name
-                    .as_deref()
-                    .is_some_and(|symbol_name| pattern.matches(symbol_name));
-            }
+/// The C/C++ decision behind a `.h` parse, which costs a second parse of the file.
+pub(super) fn header_decision(
+    provider: &dyn StructureProvider,
+    source: &[u8],
+) -> Result<Option<HeaderDecision>, IdenteditError> {
+    provider.header_decision(source).transpose()
+}
+
+fn not_modified(if_none_match: Option<&str>, state_token: &str) -> Option<ReadNotModified> {
+    (if_none_match == Some(state_token)).then(|| ReadNotModified {
+        status: ReadStatus::NotModified,
+        state_token: state_token.to_string(),
+        correlation_id: None,
+    })
+}
+
+pub(super) fn detect_file_indentation(source: &[u8]) -> Option<Indentation> {
+    std::str::from_utf8(source)
+        .ok()
+        .and_then(detect_indentation)
+}
 
-            true
-        })
-        .collect()
+fn has_node_filters(args: &ReadArgs) -> bool {
+    !args.kind.is_empty()
+        || args.name.is_some()
+        || args.name_regex.is_some()
+        || !args.exclude_kinds.is_empty()
+        || !args.not_names.is_empty()
 }
 
 fn render_human_readable(response: &ReadResponse, mode: ReadMode) -> String {
-    match mode {
+    let rendered = match mode {
         ReadMode::Ast => render_ast_text(&response.handles),
         ReadMode::Line => render_line_text(&response.handles),
+        ReadMode::Config => render_config_text(&response.handles),
+    };
+    let rendered = match response.unresolved.as_deref() {
+        Some(unresolved) if !unresolved.is_empty() => {
+            format!("{rendered}\n\nunresolved: {}", unresolved.join(" "))
+        }
+        _ => rendered,
+    };
+    match response
+        .summary
+        .page
+        .as_ref()
+        .and_then(|page| page.next_offset)
+    {
+        Some(next_offset) => format!(
+            "{rendered}\n\n({} more of {} handles; continue with --offset {next_offset})",
+            response.summary.matches - next_offset,
+            response.summary.matches
+        ),
+        None => rendered,
     }
 }
 
@@ -389,8 +946,90 @@ fn render_line_text(handles: &[ReadHandle]) -> String {
     sections.join("\n\n")
 }
 
+fn render_annotation_text(handles: &[ReadHandle]) -> String {
+    let mut grouped = BTreeMap::<String, Vec<String>>::new();
+    for handle in handles {
+        if let ReadHandle::Annotation {
+            file,
+            anchor,
+            marker,
+            ticket,
+            date,
+            message,
+            ..
+        } = handle
+        {
+            let ticket = ticket
+                .as_ref()
+                .map(|ticket| format!("({ticket})"))
+                .unwrap_or_default();
+            let date = date
+                .as_ref()
+                .map(|date| format!(" {date}"))
+                .unwrap_or_default();
+            grouped
+                .entry(file.display().to_string())
+                .or_default()
+                .push(format!("{anchor} {marker}{ticket}{date}: {message}"));
+        }
+    }
+
+    if grouped.is_empty() {
+        return "(no annotations)".to_string();
+    }
+
+    grouped
+        .into_iter()
+        .map(|(file, lines)| format!("## {file}\n{}", lines.join("\n")))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn render_config_text(handles: &[ReadHandle]) -> String {
+    let mut grouped = BTreeMap::<String, Vec<&ReadHandle>>::new();
+    for handle in handles {
+        if let ReadHandle::Config { file, .. } = handle {
+            grouped
+                .entry(file.display().to_string())
+                .or_default()
+                .push(handle);
+        }
+    }
+
+    if grouped.is_empty() {
+        return "(no matches)".to_string();
+    }
+
+    let mut sections = Vec::with_capacity(grouped.len());
+    for (file, file_handles) in grouped {
+        let mut section = Vec::with_capacity(file_handles.len() + 1);
+        section.push(format!("## {file}"));
+        for handle in file_handles {
+            if let ReadHandle::Config {
+                path,
+                value_type,
+                preview,
+                expected_value_hash,
+                ..
+            } = handle
+            {
+                section.push(format!(
+                    "{path} {value_type} {expected_value_hash} {preview}"
+                ));
+            }
+        }
+        sections.push(section.join("\n"));
+    }
+
+    sections.join("\n\n")
+}
+
 impl ReadHandle {
-    fn from_selection_handle(handle: SelectionHandle, verbose: bool) -> Self {
+    pub(crate) fn from_selection_handle(
+        handle: SelectionHandle,
+        lines: &LineIndex,
+        verbose: bool,
+    ) -> Self {
         let SelectionHandle {
             file,
             span,
@@ -400,14 +1039,21 @@ impl ReadHandle {
             expected_old_hash,
             text,
         } = handle;
+        let (start_position, end_position) = lines.span_positions(span);
         Self::Node {
             file,
             span,
+            start_position,
+            end_position,
             kind,
             name,
             identity,
             expected_old_hash,
             text: if verbose { Some(text) } else { None },
+            context_before: None,
+            context_after: None,
+            text_truncated: false,
+            metrics: None,
         }
     }
 }
@@ -422,6 +1068,9 @@ impl ReadResponse {
         let summary = ReadSummary {
             files_scanned: response.summary.files_scanned,
             matches: response.summary.matches,
+            page: None,
+            truncation: None,
+            redactions: None,
         };
         let file_preconditions = response
             .file_preconditions
@@ -429,13 +1078,67 @@ impl ReadResponse {
             .map(|item| FilePrecondition {
                 file: item.file,
                 expected_file_hash: item.expected_file_hash,
+                indentation: item.indentation,
+                header_language: item.header_language,
+                revision: None,
             })
-            .collect();
+            .collect::<Vec<_>>();
+        let state_token = state_token(
+            ReadMode::Ast,
+            file_preconditions.iter().map(|precondition| {
+                (
+                    precondition.file.as_path(),
+                    precondition.expected_file_hash.clone(),
+                )
+            }),
+        );
         Self {
             handles,
             summary,
             file_preconditions,
+            text_encoding: TextEncoding::Utf8,
+            unresolved: None,
+            state_token,
+            correlation_id: response.correlation_id,
+        }
+    }
+
+    /// Encodes node and line `text` fields and context lines; config previews are truncated
+    /// summaries and stay as they are.
+    fn encode_text(&mut self, encoding: TextEncoding) {
+        if encoding.is_utf8() {
+            return;
+        }
+        let encode = |text: &mut String| *text = encoding.encode(std::mem::take(text));
+        for handle in &mut self.handles {
+            match handle {
+                ReadHandle::Node {
+                    text,
+                    context_before,
+                    context_after,
+                    ..
+                } => {
+                    text.iter_mut()
+                        .chain(context_before.iter_mut().flatten())
+                        .chain(context_after.iter_mut().flatten())
+                        .for_each(encode);
+                }
+                ReadHandle::Config {
+                    context_before,
+                    context_after,
+                    ..
+                } => {
+                    context_before
+                        .iter_mut()
+                        .flatten()
+                        .chain(context_after.iter_mut().flatten())
+                        .for_each(encode);
+                }
+                ReadHandle::Line { text, .. } => encode(text),
+                ReadHandle::Annotation { .. } => {}
+            }
         }
+        self.text_encoding = encoding;
     }
 }
 
@@ -444,6 +1147,8 @@ impl ReadHandle {
         let super::read_select::ReadSelectHandle {
             file,
             span,
+            start_position,
+            end_position,
             kind,
             name,
             identity,
@@ -454,11 +1159,17 @@ impl ReadHandle {
         Self::Node {
             file,
             span,
+            start_position,
+            end_position,
             kind,
             name,
             identity,
             expected_old_hash,
             text,
+            context_before: None,
+            context_after: None,
+            text_truncated: false,
+            metrics: None,
         }
     }
 }
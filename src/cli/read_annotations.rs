@@ -0,0 +1,60 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::error::IdenteditError;
+use crate::handle::{LineIndex, SelectionHandle};
+use crate::hashline::{format_line_ref, show_hashed_lines};
+use crate::patch::annotation::find_annotation;
+
+use super::read::ReadHandle;
+use super::read_window::ReadWindow;
+
+/// One handle per comment line carrying a TODO/FIXME/HACK/XXX marker, in line order.
+/// Only comment nodes are scanned, so marker words inside strings are not reported.
+pub(super) fn collect_annotations(
+    file: &Path,
+    source: &[u8],
+    handles: &[SelectionHandle],
+    window: Option<ReadWindow>,
+) -> Result<Vec<ReadHandle>, IdenteditError> {
+    let index = LineIndex::new(source);
+    let mut found_by_line = BTreeMap::new();
+    for handle in handles
+        .iter()
+        .filter(|handle| handle.kind.contains("comment"))
+    {
+        let (start, _) = index.span_positions(handle.span);
+        for (offset, comment_line) in handle.text.lines().enumerate() {
+            if let Some(found) = find_annotation(comment_line) {
+                found_by_line.entry(start.line + offset).or_insert(found);
+            }
+        }
+    }
+    if found_by_line.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let source_text = std::str::from_utf8(source).map_err(|error| {
+        IdenteditError::io(
+            file,
+            std::io::Error::new(std::io::ErrorKind::InvalidData, error),
+        )
+    })?;
+    let hashed_lines = show_hashed_lines(source_text);
+    Ok(found_by_line
+        .into_iter()
+        .filter(|(line, _)| window.is_none_or(|window| window.includes_line(source, *line)))
+        .filter_map(|(line, found)| {
+            let hashed_line = hashed_lines.get(line - 1)?;
+            Some(ReadHandle::Annotation {
+                file: file.to_path_buf(),
+                line,
+                anchor: format_line_ref(line, &hashed_line.hash),
+                marker: found.marker,
+                ticket: found.ticket,
+                date: found.date,
+                message: found.message,
+            })
+        })
+        .collect())
+}
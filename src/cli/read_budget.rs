@@ -0,0 +1,260 @@
+use crate::error::IdenteditError;
+
+use super::read::{ReadHandle, ReadResponse, ReadTruncation};
+
+/// The texts of one handle: its `text` and its context lines.
+#[derive(Debug, Default)]
+struct HandleTexts {
+    text: Option<String>,
+    context_before: Option<Vec<String>>,
+    context_after: Option<Vec<String>>,
+}
+
+/// Cuts handle texts, in handle order, until the pretty-printed response fits within the
+/// `--max-response-bytes` limit, if there is one. `offset` is the position of the first returned handle among
+/// all matches. Handles keep every other field, so a response whose metadata alone exceeds
+/// the limit is returned over it.
+pub(super) fn fit_response_bytes(
+    response: &mut ReadResponse,
+    offset: usize,
+    max_response_bytes: Option<usize>,
+) -> Result<(), IdenteditError> {
+    let Some(max_bytes) = max_response_bytes else {
+        return Ok(());
+    };
+    if max_bytes == 0 {
+        return Err(IdenteditError::InvalidRequest {
+            message: "--max-response-bytes must be at least 1".to_string(),
+        });
+    }
+    if response_len(response)? <= max_bytes {
+        return Ok(());
+    }
+
+    let mut texts = response
+        .handles
+        .iter_mut()
+        .map(take_texts)
+        .collect::<Vec<_>>();
+    // The budget for texts is what the response takes without them, marked as truncated.
+    let truncated_handles = texts.iter().filter(|texts| !texts.is_empty()).count();
+    response.summary.truncation = Some(ReadTruncation {
+        max_response_bytes: max_bytes,
+        truncated_handles,
+        next_offset: None,
+    });
+    let mut remaining = max_bytes.saturating_sub(response_len(response)?);
+
+    let mut restored = 0;
+    for (handle, texts) in response.handles.iter_mut().zip(&mut texts) {
+        if texts.is_empty() {
+            restored += 1;
+            continue;
+        }
+        let cost = texts.cost();
+        if cost > remaining {
+            break;
+        }
+        remaining -= cost;
+        restore_texts(handle, std::mem::take(texts));
+        restored += 1;
+    }
+    // `cost` only estimates the indentation and separators the texts bring along.
+    while response_len(response)? > max_bytes && restored > 0 {
+        restored -= 1;
+        let handle = &mut response.handles[restored];
+        texts[restored] = take_texts(handle);
+    }
+
+    let truncated_handles = texts[restored..]
+        .iter()
+        .filter(|texts| !texts.is_empty())
+        .count();
+    let mut first_left_out = restored;
+    if restored == 0
+        && let Some(texts) = texts.first_mut()
+        && !texts.is_empty()
+    {
+        // Leaving out a text that never fits would hand back the same offset forever.
+        if let Some(text) = texts.text.take() {
+            let remaining = max_bytes.saturating_sub(response_len(response)?);
+            let cut = cut_text(text, remaining, response.text_encoding.is_utf8());
+            set_text(&mut response.handles[0], cut);
+        }
+        first_left_out = 1;
+    }
+    let next_offset = texts[first_left_out.min(texts.len())..]
+        .iter()
+        .position(|texts| !texts.is_empty())
+        .map(|index| offset + first_left_out + index);
+    response.summary.truncation = (truncated_handles > 0).then_some(ReadTruncation {
+        max_response_bytes: max_bytes,
+        truncated_handles,
+        next_offset,
+    });
+    Ok(())
+}
+
+fn response_len(response: &ReadResponse) -> Result<usize, IdenteditError> {
+    serde_json::to_string_pretty(response)
+        .map(|rendered| rendered.len())
+        .map_err(|source| IdenteditError::ResponseSerialization { source })
+}
+
+impl HandleTexts {
+    fn is_empty(&self) -> bool {
+        self.text.as_ref().is_none_or(String::is_empty)
+            && self.context_before.is_none()
+            && self.context_after.is_none()
+    }
+
+    /// Bytes the texts add to the pretty-printed response, including an estimate for each
+    /// field's key and indentation.
+    fn cost(&self) -> usize {
+        const FIELD: usize = 24;
+        const LINE: usize = 12;
+        let json_len = |text: &String| serde_json::to_string(text).map_or(0, |json| json.len());
+        let lines_len = |lines: &Vec<String>| {
+            FIELD
+                + lines
+                    .iter()
+                    .map(|line| json_len(line) + LINE)
+                    .sum::<usize>()
+        };
+        self.text.as_ref().map_or(0, |text| FIELD + json_len(text))
+            + self.context_before.as_ref().map_or(0, lines_len)
+            + self.context_after.as_ref().map_or(0, lines_len)
+    }
+}
+
+/// Removes the handle's texts and marks it truncated; line handles keep an empty `text`.
+fn take_texts(handle: &mut ReadHandle) -> HandleTexts {
+    match handle {
+        ReadHandle::Node {
+            text,
+            context_before,
+            context_after,
+            text_truncated,
+            ..
+        } => {
+            let texts = HandleTexts {
+                text: text.take(),
+                context_before: context_before.take(),
+                context_after: context_after.take(),
+            };
+            *text_truncated = !texts.is_empty();
+            texts
+        }
+        ReadHandle::Config {
+            context_before,
+            context_after,
+            text_truncated,
+            ..
+        } => {
+            let texts = HandleTexts {
+                text: None,
+                context_before: context_before.take(),
+                context_after: context_after.take(),
+            };
+            *text_truncated = !texts.is_empty();
+            texts
+        }
+        ReadHandle::Line {
+            text,
+            text_truncated,
+            ..
+        } => {
+            let texts = HandleTexts {
+                text: Some(std::mem::take(text)),
+                ..HandleTexts::default()
+            };
+            *text_truncated = !texts.is_empty();
+            texts
+        }
+        ReadHandle::Annotation { .. } => HandleTexts::default(),
+    }
+}
+
+fn restore_texts(handle: &mut ReadHandle, texts: HandleTexts) {
+    match handle {
+        ReadHandle::Node {
+            text,
+            context_before,
+            context_after,
+            text_truncated,
+            ..
+        } => {
+            *text = texts.text;
+            *context_before = texts.context_before;
+            *context_after = texts.context_after;
+            *text_truncated = false;
+        }
+        ReadHandle::Config {
+            context_before,
+            context_after,
+            text_truncated,
+            ..
+        } => {
+            *context_before = texts.context_before;
+            *context_after = texts.context_after;
+            *text_truncated = false;
+        }
+        ReadHandle::Line {
+            text,
+            text_truncated,
+            ..
+        } => {
+            *text = texts.text.unwrap_or_default();
+            *text_truncated = false;
+        }
+        ReadHandle::Annotation { .. } => {}
+    }
+}
+
+/// Sets the `text` of a handle that stays marked truncated.
+fn set_text(handle: &mut ReadHandle, cut: String) {
+    match handle {
+        ReadHandle::Node { text, .. } => *text = Some(cut),
+        ReadHandle::Line { text, .. } => *text = cut,
+        ReadHandle::Config { .. } | ReadHandle::Annotation { .. } => {}
+    }
+}
+
+/// The longest prefix of `text` whose JSON string form fits in `budget` bytes. Base64 text
+/// is cut at a 4-character boundary so the prefix still decodes.
+fn cut_text(text: String, budget: usize, utf8: bool) -> String {
+    // The quotes around the string, and the key and indentation before it.
+    let mut remaining = budget.saturating_sub(2 + 24);
+    let mut end = 0;
+    for (index, character) in text.char_indices() {
+        let escaped = match character {
+            '"' | '\\' | '\n' | '\r' | '\t' | '\u{8}' | '\u{c}' => 2,
+            character if (character as u32) < 0x20 => 6,
+            character => character.len_utf8(),
+        };
+        if escaped > remaining {
+            break;
+        }
+        remaining -= escaped;
+        end = index + character.len_utf8();
+    }
+    if !utf8 {
+        end -= end % 4;
+    }
+    let mut text = text;
+    text.truncate(end);
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::cut_text;
+
+    #[test]
+    fn cut_text_counts_escapes_and_keeps_base64_quads() {
+        assert_eq!(cut_text("abcdef".to_string(), 26 + 4, true), "abcd");
+        assert_eq!(cut_text("a\"bc".to_string(), 26 + 3, true), "a\"");
+        assert_eq!(cut_text("héllo".to_string(), 26 + 3, true), "hé");
+        assert_eq!(cut_text("QUJDREVG".to_string(), 26 + 7, false), "QUJD");
+    }
+}
@@ -0,0 +1,104 @@
+use crate::handle::{LineIndex, Span};
+
+use super::read::ReadHandle;
+
+/// Fills `context_before`/`context_after` with up to `lines` whole lines on each side of
+/// every handle's line range. All handles must come from `source`.
+pub(super) fn attach_context(handles: &mut [ReadHandle], source: &[u8], lines: usize) {
+    let index = LineIndex::new(source);
+    for handle in handles {
+        let (span, context_before, context_after) = match handle {
+            ReadHandle::Node {
+                span,
+                context_before,
+                context_after,
+                ..
+            }
+            | ReadHandle::Config {
+                span,
+                context_before,
+                context_after,
+                ..
+            } => (*span, context_before, context_after),
+            ReadHandle::Line { .. } | ReadHandle::Annotation { .. } => continue,
+        };
+        let (first, last) = line_range(&index, span);
+        *context_before = Some(
+            (first.saturating_sub(lines).max(1)..first)
+                .filter_map(|line| index.line_text(line))
+                .collect(),
+        );
+        *context_after = Some(
+            (last + 1..=last.saturating_add(lines))
+                .map_while(|line| index.line_text(line))
+                .collect(),
+        );
+    }
+}
+
+/// First and last line the span touches. A span that ends right after a line terminator
+/// does not touch the following line.
+pub(super) fn line_range(index: &LineIndex, span: Span) -> (usize, usize) {
+    let (start, end) = index.span_positions(span);
+    let last = if end.column == 1 && end.line > start.line {
+        end.line - 1
+    } else {
+        end.line
+    };
+    (start.line, last)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::attach_context;
+    use crate::cli::read::ReadHandle;
+    use crate::handle::Span;
+
+    fn config(start: usize, end: usize) -> ReadHandle {
+        ReadHandle::Config {
+            file: PathBuf::from("a.toml"),
+            path: "key".to_string(),
+            value_type: "string".to_string(),
+            preview: String::new(),
+            span: Span { start, end },
+            expected_value_hash: String::new(),
+            context_before: None,
+            context_after: None,
+            text_truncated: false,
+        }
+    }
+
+    fn context(handle: &ReadHandle) -> (Vec<String>, Vec<String>) {
+        let ReadHandle::Config {
+            context_before: Some(before),
+            context_after: Some(after),
+            ..
+        } = handle
+        else {
+            panic!("context should be attached");
+        };
+        (before.clone(), after.clone())
+    }
+
+    #[test]
+    fn context_takes_whole_lines_around_the_span_and_stops_at_file_edges() {
+        let source = b"one\r\ntwo\nthree\nfour\n";
+        let mut handles = [config(5, 14), config(0, 2), config(15, 19)];
+        attach_context(&mut handles, source, 2);
+
+        assert_eq!(
+            context(&handles[0]),
+            (vec!["one".to_string()], vec!["four".to_string()])
+        );
+        assert_eq!(
+            context(&handles[1]),
+            (Vec::new(), vec!["two".to_string(), "three".to_string()])
+        );
+        assert_eq!(
+            context(&handles[2]),
+            (vec!["two".to_string(), "three".to_string()], Vec::new())
+        );
+    }
+}
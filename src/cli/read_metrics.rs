@@ -0,0 +1,267 @@
+use serde::Serialize;
+use tree_sitter::{Node, Tree};
+
+use crate::handle::{LineIndex, Span};
+use crate::provider::StructureProvider;
+
+use super::read::ReadHandle;
+use super::read_context::line_range;
+
+/// Node kinds, across the bundled grammars, that add a decision point to a function.
+const DECISION_KINDS: &[&str] = &[
+    "if_statement",
+    "if_expression",
+    "if_let_expression",
+    "elif_clause",
+    "elsif",
+    "else_if_clause",
+    "unless",
+    "for_statement",
+    "for_in_statement",
+    "for_expression",
+    "enhanced_for_statement",
+    "foreach_statement",
+    "while_statement",
+    "while_expression",
+    "loop_expression",
+    "do_statement",
+    "repeat_statement",
+    "until",
+    "match_arm",
+    "case_clause",
+    "switch_case",
+    "switch_section",
+    "expression_case",
+    "type_case",
+    "communication_case",
+    "when_entry",
+    "when",
+    "catch_clause",
+    "except_clause",
+    "rescue",
+    "conditional_expression",
+    "ternary_expression",
+];
+
+/// Anonymous operator tokens that short-circuit, each a decision point of its own.
+const SHORT_CIRCUIT_TOKENS: &[&str] = &["&&", "||", "and", "or", "??"];
+
+/// Node kinds that nest the code inside them one level deeper.
+const NESTING_KINDS: &[&str] = &[
+    "if_statement",
+    "if_expression",
+    "if_let_expression",
+    "unless",
+    "for_statement",
+    "for_in_statement",
+    "for_expression",
+    "enhanced_for_statement",
+    "foreach_statement",
+    "while_statement",
+    "while_expression",
+    "loop_expression",
+    "do_statement",
+    "repeat_statement",
+    "until",
+    "match_expression",
+    "match_statement",
+    "switch_statement",
+    "switch_expression",
+    "expression_switch_statement",
+    "type_switch_statement",
+    "select_statement",
+    "case_statement",
+    "when_expression",
+    "try_statement",
+    "try_expression",
+    "begin",
+    "with_statement",
+];
+
+/// Size and shape of a node handle, for policies such as "split functions longer than N
+/// lines". `nesting_depth` and `cyclomatic` need a syntax tree and are left out for
+/// providers without one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HandleMetrics {
+    pub lines: usize,
+    pub bytes: usize,
+    /// Deepest nesting of conditionals, loops, switches, and try blocks inside the node;
+    /// an `else if` chain counts as one level.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nesting_depth: Option<usize>,
+    /// One plus the node's branches, loops, cases, catch clauses, and short-circuit
+    /// operators: a rough McCabe estimate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cyclomatic: Option<usize>,
+}
+
+/// Sets `metrics` on every node handle. All handles must come from `source`.
+pub(super) fn attach_metrics(
+    handles: &mut [ReadHandle],
+    source: &[u8],
+    provider: &dyn StructureProvider,
+) {
+    if !handles
+        .iter()
+        .any(|handle| matches!(handle, ReadHandle::Node { .. }))
+    {
+        return;
+    }
+    let index = LineIndex::new(source);
+    let tree = provider.syntax_tree(source).and_then(Result::ok);
+    for handle in handles {
+        let ReadHandle::Node {
+            span,
+            kind,
+            metrics,
+            ..
+        } = handle
+        else {
+            continue;
+        };
+        let (first, last) = line_range(&index, *span);
+        let node = tree.as_ref().and_then(|tree| find_node(tree, kind, *span));
+        *metrics = Some(HandleMetrics {
+            lines: last - first + 1,
+            bytes: span.end - span.start,
+            nesting_depth: node.map(|node| nesting_depth(node)),
+            cyclomatic: node.map(|node| 1 + decision_points(node)),
+        });
+    }
+}
+
+/// The node with exactly `span`, preferring one of `kind` when several share the span.
+fn find_node<'tree>(tree: &'tree Tree, kind: &str, span: Span) -> Option<Node<'tree>> {
+    let mut node = tree
+        .root_node()
+        .descendant_for_byte_range(span.start, span.end)?;
+    let mut same_span = None;
+    loop {
+        if node.start_byte() == span.start && node.end_byte() == span.end {
+            if node.kind() == kind {
+                return Some(node);
+            }
+            same_span.get_or_insert(node);
+        } else if node.start_byte() < span.start || node.end_byte() > span.end {
+            return same_span;
+        }
+        node = node.parent()?;
+    }
+}
+
+fn decision_points(node: Node<'_>) -> usize {
+    let mut count = 0;
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        let kind = child.kind();
+        if (child.is_named() && DECISION_KINDS.contains(&kind))
+            || (!child.is_named() && SHORT_CIRCUIT_TOKENS.contains(&kind))
+        {
+            count += 1;
+        }
+        count += decision_points(child);
+    }
+    count
+}
+
+fn nesting_depth(node: Node<'_>) -> usize {
+    let mut deepest = 0;
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        let nests = NESTING_KINDS.contains(&child.kind()) && !is_else_if(child);
+        deepest = deepest.max(nesting_depth(child) + usize::from(nests));
+    }
+    deepest
+}
+
+/// An `if` that is the `else` branch of another `if`, which grammars nest but which reads
+/// as the same level.
+fn is_else_if(node: Node<'_>) -> bool {
+    let Some(parent) = node.parent() else {
+        return false;
+    };
+    parent.kind() == "else_clause"
+        || (NESTING_KINDS.contains(&parent.kind())
+            && parent
+                .child_by_field_name("alternative")
+                .is_some_and(|alternative| alternative.id() == node.id()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{HandleMetrics, attach_metrics};
+    use crate::cli::read::ReadHandle;
+    use crate::handle::LineIndex;
+    use crate::provider::ProviderRegistry;
+
+    fn metrics(file: &str, source: &str, name: &str) -> HandleMetrics {
+        let registry = ProviderRegistry::default();
+        let path = Path::new(file);
+        let provider = registry.provider_for(path).expect("provider should exist");
+        let lines = LineIndex::new(source.as_bytes());
+        let mut handles = provider
+            .parse(path, source.as_bytes())
+            .expect("source should parse")
+            .into_iter()
+            .filter(|handle| handle.name.as_deref() == Some(name))
+            .take(1)
+            .map(|handle| ReadHandle::from_selection_handle(handle, &lines, false))
+            .collect::<Vec<_>>();
+        attach_metrics(&mut handles, source.as_bytes(), provider);
+        match handles.pop() {
+            Some(ReadHandle::Node {
+                metrics: Some(metrics),
+                ..
+            }) => metrics,
+            other => panic!("expected a node with metrics, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn python_branches_loops_and_boolean_operators_count() {
+        let source = "\
+def check(items):
+    for item in items:
+        if item and item.ok:
+            continue
+        elif item is None:
+            return 1
+    return 0
+";
+        assert_eq!(
+            metrics("a.py", source, "check"),
+            HandleMetrics {
+                lines: 7,
+                bytes: source.len() - 1,
+                nesting_depth: Some(2),
+                cyclomatic: Some(5),
+            }
+        );
+    }
+
+    #[test]
+    fn else_if_chains_stay_at_one_level() {
+        let source = "\
+function pick(a) {
+  if (a > 1) {
+    return 1;
+  } else if (a > 0) {
+    return a > 0.5 ? 2 : 3;
+  }
+  return 0;
+}
+";
+        let metrics = metrics("a.js", source, "pick");
+        assert_eq!(metrics.nesting_depth, Some(1));
+        assert_eq!(metrics.cyclomatic, Some(4));
+    }
+
+    #[test]
+    fn providers_without_a_syntax_tree_report_size_only() {
+        let metrics = metrics("a.json", "{\n  \"name\": {\n    \"a\": 1\n  }\n}\n", "name");
+        assert_eq!(metrics.nesting_depth, None);
+        assert_eq!(metrics.cyclomatic, None);
+    }
+}
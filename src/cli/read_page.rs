@@ -0,0 +1,227 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::error::IdenteditError;
+
+use super::read::{ReadHandle, ReadPage, ReadResponse, ReadSort};
+
+/// Sorts every handle in the response and keeps one page of them. `summary.matches` still
+/// counts all matches so callers know how many pages remain.
+pub(super) fn sort_and_paginate(
+    response: &mut ReadResponse,
+    sort: ReadSort,
+    offset: usize,
+    limit: Option<usize>,
+) -> Result<(), IdenteditError> {
+    if limit == Some(0) {
+        return Err(IdenteditError::InvalidRequest {
+            message: "--limit must be at least 1".to_string(),
+        });
+    }
+
+    let mut file_ranks = HashMap::<PathBuf, usize>::new();
+    for handle in &response.handles {
+        let next_rank = file_ranks.len();
+        file_ranks
+            .entry(handle_file(handle).clone())
+            .or_insert(next_rank);
+    }
+    let by_position = |left: &ReadHandle, right: &ReadHandle| {
+        let (left_start, left_end) = position(left);
+        let (right_start, right_end) = position(right);
+        file_ranks[handle_file(left)]
+            .cmp(&file_ranks[handle_file(right)])
+            .then(left_start.cmp(&right_start))
+            .then(Reverse(left_end).cmp(&Reverse(right_end)))
+    };
+    match sort {
+        ReadSort::Position => response.handles.sort_by(by_position),
+        ReadSort::Name => response.handles.sort_by(|left, right| {
+            match (sort_name(left), sort_name(right)) {
+                (Some(left_name), Some(right_name)) => left_name.cmp(right_name),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+            .then_with(|| by_position(left, right))
+        }),
+        ReadSort::Size => response.handles.sort_by(|left, right| {
+            size(right)
+                .cmp(&size(left))
+                .then_with(|| by_position(left, right))
+        }),
+    }
+
+    if offset == 0 && limit.is_none() {
+        return Ok(());
+    }
+    let total = response.handles.len();
+    let start = offset.min(total);
+    let end = limit.map_or(total, |limit| start.saturating_add(limit).min(total));
+    response.handles.truncate(end);
+    response.handles.drain(..start);
+    response.summary.page = Some(ReadPage {
+        offset,
+        limit,
+        returned: end - start,
+        next_offset: (end < total).then_some(end),
+    });
+    Ok(())
+}
+
+fn handle_file(handle: &ReadHandle) -> &PathBuf {
+    match handle {
+        ReadHandle::Node { file, .. }
+        | ReadHandle::Line { file, .. }
+        | ReadHandle::Config { file, .. }
+        | ReadHandle::Annotation { file, .. } => file,
+    }
+}
+
+/// Start and end within the file; line handles use their line number for both.
+fn position(handle: &ReadHandle) -> (usize, usize) {
+    match handle {
+        ReadHandle::Node { span, .. } | ReadHandle::Config { span, .. } => (span.start, span.end),
+        ReadHandle::Line { line, .. } | ReadHandle::Annotation { line, .. } => (*line, *line),
+    }
+}
+
+fn sort_name(handle: &ReadHandle) -> Option<&str> {
+    match handle {
+        ReadHandle::Node { name, .. } => name.as_deref(),
+        ReadHandle::Config { path, .. } => Some(path),
+        ReadHandle::Annotation { marker, .. } => Some(marker),
+        ReadHandle::Line { .. } => None,
+    }
+}
+
+fn size(handle: &ReadHandle) -> usize {
+    match handle {
+        ReadHandle::Node { span, .. } | ReadHandle::Config { span, .. } => span.end - span.start,
+        ReadHandle::Line { text, .. } => text.len(),
+        ReadHandle::Annotation { message, .. } => message.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::sort_and_paginate;
+    use crate::cli::read::{ReadHandle, ReadResponse, ReadSort, ReadSummary};
+    use crate::handle::{Position, Span};
+    use crate::text_encoding::TextEncoding;
+
+    fn position(offset: usize) -> Position {
+        Position {
+            line: 1,
+            column: offset + 1,
+            utf16_column: offset + 1,
+        }
+    }
+
+    fn node(file: &str, start: usize, end: usize, name: Option<&str>) -> ReadHandle {
+        ReadHandle::Node {
+            file: PathBuf::from(file),
+            span: Span { start, end },
+            start_position: position(start),
+            end_position: position(end),
+            kind: "function_definition".to_string(),
+            name: name.map(str::to_string),
+            identity: format!("{file}:{start}"),
+            expected_old_hash: String::new(),
+            text: None,
+            context_before: None,
+            context_after: None,
+            text_truncated: false,
+            metrics: None,
+        }
+    }
+
+    fn response(handles: Vec<ReadHandle>) -> ReadResponse {
+        ReadResponse {
+            summary: ReadSummary {
+                files_scanned: 2,
+                matches: handles.len(),
+                page: None,
+                truncation: None,
+                redactions: None,
+            },
+            handles,
+            file_preconditions: Vec::new(),
+            text_encoding: TextEncoding::Utf8,
+            unresolved: None,
+            state_token: String::new(),
+            correlation_id: None,
+        }
+    }
+
+    fn identities(response: &ReadResponse) -> Vec<&str> {
+        response
+            .handles
+            .iter()
+            .filter_map(|handle| match handle {
+                ReadHandle::Node { identity, .. } => Some(identity.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sort_orders_keep_file_order_and_break_ties_by_position() {
+        let handles = || {
+            vec![
+                node("b.py", 40, 50, Some("beta")),
+                node("b.py", 0, 30, None),
+                node("a.py", 10, 20, Some("alpha")),
+                node("a.py", 0, 20, Some("beta")),
+            ]
+        };
+
+        let mut by_position = response(handles());
+        sort_and_paginate(&mut by_position, ReadSort::Position, 0, None).expect("sort");
+        assert_eq!(
+            identities(&by_position),
+            ["b.py:0", "b.py:40", "a.py:0", "a.py:10"]
+        );
+        assert!(by_position.summary.page.is_none());
+
+        let mut by_name = response(handles());
+        sort_and_paginate(&mut by_name, ReadSort::Name, 0, None).expect("sort");
+        assert_eq!(
+            identities(&by_name),
+            ["a.py:10", "b.py:40", "a.py:0", "b.py:0"]
+        );
+
+        let mut by_size = response(handles());
+        sort_and_paginate(&mut by_size, ReadSort::Size, 0, None).expect("sort");
+        assert_eq!(
+            identities(&by_size),
+            ["b.py:0", "a.py:0", "b.py:40", "a.py:10"]
+        );
+    }
+
+    #[test]
+    fn pages_report_next_offset_until_exhausted() {
+        let handles = || (0..5).map(|index| node("a.py", index * 10, index * 10 + 5, None));
+
+        let mut first = response(handles().collect());
+        sort_and_paginate(&mut first, ReadSort::Position, 0, Some(2)).expect("page");
+        assert_eq!(identities(&first), ["a.py:0", "a.py:10"]);
+        let page = first.summary.page.expect("page info");
+        assert_eq!((page.returned, page.next_offset), (2, Some(2)));
+        assert_eq!(first.summary.matches, 5);
+
+        let mut last = response(handles().collect());
+        sort_and_paginate(&mut last, ReadSort::Position, 4, Some(2)).expect("page");
+        assert_eq!(identities(&last), ["a.py:40"]);
+        assert_eq!(last.summary.page.expect("page info").next_offset, None);
+
+        let mut past_end = response(handles().collect());
+        sort_and_paginate(&mut past_end, ReadSort::Position, 9, None).expect("page");
+        assert!(past_end.handles.is_empty());
+
+        assert!(sort_and_paginate(&mut response(Vec::new()), ReadSort::Name, 0, Some(0)).is_err());
+    }
+}
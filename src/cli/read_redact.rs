@@ -0,0 +1,91 @@
+//! `read --redact REGEX`: masks matches in the texts a read returns, so secrets in the
+//! files do not end up in a prompt.
+//!
+//! Only returned texts change. Spans, identities, and hashes were computed on the file as
+//! it is, so a plan built from a redacted read still checks against the real content.
+
+use regex::Regex;
+
+use crate::error::IdenteditError;
+
+use super::read::{ReadHandle, ReadResponse};
+
+const MASK: &str = "[REDACTED]";
+
+/// Compiles the `--redact` patterns, refusing ones that match the empty string, which would
+/// mask every position of every text.
+pub(super) fn compile_redactions(patterns: &[String]) -> Result<Vec<Regex>, IdenteditError> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            let regex = Regex::new(pattern).map_err(|error| IdenteditError::InvalidRequest {
+                message: format!("Invalid --redact '{pattern}': {error}"),
+            })?;
+            if regex.is_match("") {
+                return Err(IdenteditError::InvalidRequest {
+                    message: format!("--redact '{pattern}' matches the empty string"),
+                });
+            }
+            Ok(regex)
+        })
+        .collect()
+}
+
+/// Masks every match of `redactions` in handle texts, context lines, config previews, and
+/// annotation messages, and counts the masked matches in `summary.redactions`.
+pub(super) fn redact_response(response: &mut ReadResponse, redactions: &[Regex]) {
+    if redactions.is_empty() {
+        return;
+    }
+    let mut masked = 0;
+    let mut redact = |text: &mut String| {
+        for regex in redactions {
+            let matches = regex.find_iter(text).count();
+            if matches > 0 {
+                masked += matches;
+                *text = regex.replace_all(text, MASK).into_owned();
+            }
+        }
+    };
+    for handle in &mut response.handles {
+        match handle {
+            ReadHandle::Node {
+                text,
+                context_before,
+                context_after,
+                ..
+            } => text
+                .iter_mut()
+                .chain(context_before.iter_mut().flatten())
+                .chain(context_after.iter_mut().flatten())
+                .for_each(&mut redact),
+            ReadHandle::Config {
+                preview,
+                context_before,
+                context_after,
+                ..
+            } => std::iter::once(preview)
+                .chain(context_before.iter_mut().flatten())
+                .chain(context_after.iter_mut().flatten())
+                .for_each(&mut redact),
+            ReadHandle::Line { text, .. } => redact(text),
+            ReadHandle::Annotation { message, .. } => redact(message),
+        }
+    }
+    response.summary.redactions = Some(masked);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::compile_redactions;
+
+    #[test]
+    fn invalid_and_empty_matching_patterns_are_rejected() {
+        let error = compile_redactions(&["(unclosed".to_string()])
+            .expect_err("an unclosed group should not compile");
+        assert!(error.to_string().contains("Invalid --redact '(unclosed'"));
+        let error = compile_redactions(&["x*".to_string()])
+            .expect_err("a pattern matching nothing should be refused");
+        assert!(error.to_string().contains("matches the empty string"));
+    }
+}
@@ -0,0 +1,50 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::error::IdenteditError;
+
+use super::read::ReadHandle;
+
+/// Identities from `--resolve` followed by those in `--resolve-file`, one per line (blank
+/// lines and `#` comments skipped), in first-seen order without duplicates.
+pub(super) fn collect_identities(
+    identities: &[String],
+    list_file: Option<&Path>,
+) -> Result<Vec<String>, IdenteditError> {
+    let listed = match list_file {
+        Some(path) => fs::read_to_string(path).map_err(|error| IdenteditError::io(path, error))?,
+        None => String::new(),
+    };
+    let mut seen = HashSet::new();
+    let collected: Vec<String> = identities
+        .iter()
+        .map(|identity| identity.trim())
+        .chain(listed.lines().map(str::trim))
+        .filter(|identity| !identity.is_empty() && !identity.starts_with('#'))
+        .filter(|identity| seen.insert(*identity))
+        .map(str::to_string)
+        .collect();
+    if collected.is_empty() {
+        return Err(IdenteditError::InvalidRequest {
+            message: "--resolve/--resolve-file listed no identities".to_string(),
+        });
+    }
+    Ok(collected)
+}
+
+/// The requested identities that matched no node in any scanned file, in request order.
+pub(super) fn unresolved_identities(identities: &[String], handles: &[ReadHandle]) -> Vec<String> {
+    let found: HashSet<&str> = handles
+        .iter()
+        .filter_map(|handle| match handle {
+            ReadHandle::Node { identity, .. } => Some(identity.as_str()),
+            _ => None,
+        })
+        .collect();
+    identities
+        .iter()
+        .filter(|identity| !found.contains(identity.as_str()))
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,63 @@
+use std::path::Path;
+use std::process::Command;
+
+use crate::error::IdenteditError;
+
+/// `file` as committed at `rev`, with the commit id `rev` resolved to. git runs in the
+/// file's directory, so each file is read from the repository that contains it, and the
+/// file need not exist in the working tree.
+pub(super) fn read_at_revision(
+    file: &Path,
+    rev: &str,
+) -> Result<(Vec<u8>, String), IdenteditError> {
+    if rev.is_empty() || rev.starts_with('-') {
+        return Err(IdenteditError::InvalidRequest {
+            message: format!("--rev '{rev}' is not a git revision"),
+        });
+    }
+    let directory = file
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let Some(name) = file.file_name().and_then(|name| name.to_str()) else {
+        return Err(IdenteditError::InvalidRequest {
+            message: format!("--rev needs a UTF-8 file path, not '{}'", file.display()),
+        });
+    };
+
+    let commit = git(
+        directory,
+        &[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{rev}^{{commit}}"),
+        ],
+    )?
+    .ok_or_else(|| IdenteditError::InvalidRequest {
+        message: format!(
+            "Revision '{rev}' does not name a commit in the repository containing '{}'",
+            file.display()
+        ),
+    })?;
+    let commit = String::from_utf8_lossy(&commit).trim().to_string();
+    let source = git(
+        directory,
+        &["cat-file", "blob", &format!("{commit}:./{name}")],
+    )?
+    .ok_or_else(|| IdenteditError::InvalidRequest {
+        message: format!("'{}' does not exist at revision '{rev}'", file.display()),
+    })?;
+    Ok((source, commit))
+}
+
+/// Stdout of a git command that succeeded; `None` when git reported a failure.
+pub(super) fn git(directory: &Path, arguments: &[&str]) -> Result<Option<Vec<u8>>, IdenteditError> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .args(arguments)
+        .output()
+        .map_err(|error| IdenteditError::io(directory, error))?;
+    Ok(output.status.success().then_some(output.stdout))
+}
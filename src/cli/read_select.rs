@@ -8,20 +8,28 @@ use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
 
 use crate::error::IdenteditError;
-use crate::handle::SelectionHandle;
+use crate::handle::{LineIndex, Position, SelectionHandle};
 use crate::hash::hash_bytes;
-use crate::provider::ProviderRegistry;
+use crate::patch::indentation::Indentation;
+use crate::provider::{HeaderDecision, ProviderRegistry};
 use crate::selector::Selector;
 
+use super::read::{detect_file_indentation, header_decision};
+
 #[derive(Debug, Deserialize)]
 #[serde(deny_unknown_fields)]
 struct StdinReadRequest {
     command: String,
-    #[serde(default)]
+    #[serde(
+        default,
+        deserialize_with = "crate::path_style::deserialize_optional_path"
+    )]
     file: Option<PathBuf>,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "crate::path_style::deserialize_paths")]
     files: Vec<PathBuf>,
     selector: Selector,
+    #[serde(default)]
+    correlation_id: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -29,12 +37,18 @@ pub struct ReadSelectResponse {
     pub handles: Vec<ReadSelectHandle>,
     pub summary: ReadSelectSummary,
     pub file_preconditions: Vec<FilePrecondition>,
+    /// The request's `correlation_id`, echoed back.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct ReadSelectHandle {
+    #[serde(serialize_with = "crate::path_style::serialize_path")]
     pub file: PathBuf,
     pub span: crate::handle::Span,
+    pub start_position: Position,
+    pub end_position: Position,
     pub kind: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
@@ -52,11 +66,21 @@ pub struct ReadSelectSummary {
 
 #[derive(Debug, Serialize)]
 pub struct FilePrecondition {
+    #[serde(serialize_with = "crate::path_style::serialize_path")]
     pub file: PathBuf,
     pub expected_file_hash: String,
+    /// Detected indentation; absent when no line is indented or the file is not UTF-8.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub indentation: Option<Indentation>,
+    /// Which grammar parsed a `.h` file and why; absent for other files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header_language: Option<HeaderDecision>,
 }
 
-pub fn run_read_select_from_stdin(verbose: bool) -> Result<ReadSelectResponse, IdenteditError> {
+pub fn run_read_select_from_stdin(
+    verbose: bool,
+    registry: &ProviderRegistry,
+) -> Result<ReadSelectResponse, IdenteditError> {
     let mut request_body = String::new();
     std::io::stdin()
         .read_to_string(&mut request_body)
@@ -73,10 +97,20 @@ pub fn run_read_select_from_stdin(verbose: bool) -> Result<ReadSelectResponse, I
             ),
         });
     }
+    let correlation_id = request.correlation_id.clone();
+    select_handles(request, verbose, registry)
+        .map_err(|error| error.with_correlation_id(correlation_id.as_deref()))
+}
 
+/// The part of `run_read_select_from_stdin` after the request is parsed, whose errors echo
+/// its `correlation_id`.
+fn select_handles(
+    request: StdinReadRequest,
+    verbose: bool,
+    registry: &ProviderRegistry,
+) -> Result<ReadSelectResponse, IdenteditError> {
     let files = files_from_stdin_request(request.file, request.files)?;
 
-    let provider_registry = ProviderRegistry::default();
     let mut selected_handles = Vec::new();
     let mut file_preconditions = Vec::new();
     let mut seen_canonical_paths = HashSet::with_capacity(files.len());
@@ -110,18 +144,21 @@ pub fn run_read_select_from_stdin(verbose: bool) -> Result<ReadSelectResponse, I
         }
 
         let source = fs::read(file).map_err(|error| IdenteditError::io(file, error))?;
-        let provider = provider_registry.provider_for(file)?;
+        let provider = registry.provider_for(file)?;
         let parsed_handles = provider.parse(file, &source)?;
         let filtered_handles = request.selector.filter(parsed_handles)?;
+        let lines = LineIndex::new(&source);
         selected_handles.extend(
             filtered_handles
                 .into_iter()
-                .map(|handle| ReadSelectHandle::from_selection_handle(handle, verbose)),
+                .map(|handle| ReadSelectHandle::from_selection_handle(handle, &lines, verbose)),
         );
 
         file_preconditions.push(FilePrecondition {
             file: file.clone(),
             expected_file_hash: hash_bytes(&source),
+            indentation: detect_file_indentation(&source),
+            header_language: header_decision(provider, &source)?,
         });
     }
 
@@ -132,6 +169,7 @@ pub fn run_read_select_from_stdin(verbose: bool) -> Result<ReadSelectResponse, I
         },
         handles: selected_handles,
         file_preconditions,
+        correlation_id: request.correlation_id,
     })
 }
 
@@ -161,7 +199,7 @@ fn files_from_stdin_request(
 }
 
 impl ReadSelectHandle {
-    fn from_selection_handle(handle: SelectionHandle, verbose: bool) -> Self {
+    fn from_selection_handle(handle: SelectionHandle, lines: &LineIndex, verbose: bool) -> Self {
         let SelectionHandle {
             file,
             span,
@@ -171,10 +209,13 @@ impl ReadSelectHandle {
             expected_old_hash,
             text,
         } = handle;
+        let (start_position, end_position) = lines.span_positions(span);
 
         Self {
             file,
             span,
+            start_position,
+            end_position,
             kind,
             name,
             identity,
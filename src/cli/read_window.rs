@@ -0,0 +1,246 @@
+use std::ops::Range;
+
+use crate::error::IdenteditError;
+use crate::handle::{SelectionHandle, Span};
+
+/// Restricts `read` output to one region of each file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ReadWindow {
+    /// 1-based, inclusive line numbers, as in `--lines 100:250`.
+    Lines { start: usize, end: usize },
+    /// Byte offsets `[start, end)`, as in `--byte-range 0:512`.
+    Bytes { start: usize, end: usize },
+    /// The innermost node covering this 1-based line.
+    ContainingLine(usize),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LineBounds {
+    start: usize,
+    content_end: usize,
+    next_start: usize,
+}
+
+pub(super) fn parse_read_window(
+    lines: Option<&str>,
+    byte_range: Option<&str>,
+    containing_line: Option<usize>,
+) -> Result<Option<ReadWindow>, IdenteditError> {
+    let window = match (lines, byte_range, containing_line) {
+        (None, None, None) => return Ok(None),
+        (Some(raw), None, None) => {
+            let (start, end) = parse_bounds("--lines", raw)?;
+            if start == 0 || end < start {
+                return Err(invalid_window(format!(
+                    "--lines expects 1-based START:END with START <= END, got '{raw}'"
+                )));
+            }
+            ReadWindow::Lines { start, end }
+        }
+        (None, Some(raw), None) => {
+            let (start, end) = parse_bounds("--byte-range", raw)?;
+            if end < start {
+                return Err(invalid_window(format!(
+                    "--byte-range expects START:END with START <= END, got '{raw}'"
+                )));
+            }
+            ReadWindow::Bytes { start, end }
+        }
+        (None, None, Some(line)) => {
+            if line == 0 {
+                return Err(invalid_window(
+                    "--containing-line expects a 1-based line number".to_string(),
+                ));
+            }
+            ReadWindow::ContainingLine(line)
+        }
+        _ => {
+            return Err(invalid_window(
+                "--lines, --byte-range, and --containing-line cannot be combined".to_string(),
+            ));
+        }
+    };
+    Ok(Some(window))
+}
+
+/// Accepts `START:END`, or a single number meaning `N:N`.
+fn parse_bounds(flag: &str, raw: &str) -> Result<(usize, usize), IdenteditError> {
+    let parse = |value: &str| {
+        value.trim().parse::<usize>().map_err(|_| {
+            invalid_window(format!(
+                "{flag} expects START:END with non-negative integers, got '{raw}'"
+            ))
+        })
+    };
+    match raw.split_once(':') {
+        Some((start, end)) => Ok((parse(start)?, parse(end)?)),
+        None => {
+            let value = parse(raw)?;
+            Ok((value, value))
+        }
+    }
+}
+
+impl ReadWindow {
+    pub(super) fn filter_nodes(
+        self,
+        source: &[u8],
+        handles: Vec<SelectionHandle>,
+    ) -> Vec<SelectionHandle> {
+        let ReadWindow::ContainingLine(line) = self else {
+            return handles
+                .into_iter()
+                .filter(|handle| self.intersects(source, handle.span))
+                .collect();
+        };
+
+        let Some(bounds) = line_bounds(source).get(line - 1).copied() else {
+            return Vec::new();
+        };
+        let (content_start, content_end) = trimmed_content(source, bounds);
+        let mut innermost: Option<SelectionHandle> = None;
+        for handle in handles {
+            let covers = handle.span.start < handle.span.end
+                && handle.span.start <= content_start
+                && handle.span.end >= content_end;
+            let narrower = innermost.as_ref().is_none_or(|current| {
+                handle.span.end - handle.span.start <= current.span.end - current.span.start
+            });
+            if covers && narrower {
+                innermost = Some(handle);
+            }
+        }
+        innermost.into_iter().collect()
+    }
+
+    /// Line mode keeps its own numbering: `--lines` selects by line number, `--byte-range`
+    /// keeps every line that overlaps the window.
+    pub(super) fn includes_line(self, source: &[u8], line: usize) -> bool {
+        match self {
+            ReadWindow::Lines { start, end } => (start..=end).contains(&line),
+            ReadWindow::Bytes { .. } => line_bounds(source).get(line - 1).is_some_and(|bounds| {
+                self.intersects(
+                    source,
+                    Span {
+                        start: bounds.start,
+                        end: bounds.next_start,
+                    },
+                )
+            }),
+            ReadWindow::ContainingLine(_) => true,
+        }
+    }
+
+    pub(super) fn intersects(self, source: &[u8], span: Span) -> bool {
+        let window = self.byte_window(source);
+        if span.start == span.end {
+            window.start <= span.start && span.start < window.end
+        } else {
+            span.start < window.end && window.start < span.end
+        }
+    }
+
+    fn byte_window(self, source: &[u8]) -> Range<usize> {
+        match self {
+            ReadWindow::Lines { start, end } => {
+                let lines = line_bounds(source);
+                let Some(first) = lines.get(start - 1) else {
+                    return source.len()..source.len();
+                };
+                let last_end = lines
+                    .get(end - 1)
+                    .or(lines.last())
+                    .map_or(source.len(), |bounds| bounds.next_start);
+                first.start..last_end
+            }
+            ReadWindow::Bytes { start, end } => start..end,
+            ReadWindow::ContainingLine(line) => ReadWindow::Lines {
+                start: line,
+                end: line,
+            }
+            .byte_window(source),
+        }
+    }
+}
+
+/// Splits lines the way line mode numbers them: `\n`, `\r\n`, and a lone `\r` all end a line.
+fn line_bounds(source: &[u8]) -> Vec<LineBounds> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    let mut index = 0;
+    while index < source.len() {
+        let terminator_len = match source[index] {
+            b'\n' => 1,
+            b'\r' if source.get(index + 1) == Some(&b'\n') => 2,
+            b'\r' => 1,
+            _ => {
+                index += 1;
+                continue;
+            }
+        };
+        lines.push(LineBounds {
+            start,
+            content_end: index,
+            next_start: index + terminator_len,
+        });
+        index += terminator_len;
+        start = index;
+    }
+    if start < source.len() {
+        lines.push(LineBounds {
+            start,
+            content_end: source.len(),
+            next_start: source.len(),
+        });
+    }
+    lines
+}
+
+/// The line without indentation or trailing whitespace, so a statement that starts after
+/// the indent still counts as covering its line. Blank lines collapse to their start.
+fn trimmed_content(source: &[u8], bounds: LineBounds) -> (usize, usize) {
+    let content = &source[bounds.start..bounds.content_end];
+    let Some(first) = content.iter().position(|byte| !byte.is_ascii_whitespace()) else {
+        return (bounds.start, bounds.start);
+    };
+    let last = content
+        .iter()
+        .rposition(|byte| !byte.is_ascii_whitespace())
+        .unwrap_or(first);
+    (bounds.start + first, bounds.start + last + 1)
+}
+
+fn invalid_window(message: String) -> IdenteditError {
+    IdenteditError::InvalidRequest { message }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ReadWindow, line_bounds, parse_read_window};
+    use crate::handle::Span;
+
+    #[test]
+    fn line_windows_cover_whole_lines_across_line_endings() {
+        let source = b"one\r\ntwo\rthree\nfour";
+        assert_eq!(line_bounds(source).len(), 4);
+        let window = ReadWindow::Lines { start: 2, end: 3 };
+        assert_eq!(window.byte_window(source), 5..15);
+        assert!(window.intersects(source, Span { start: 14, end: 19 }));
+        assert!(!window.intersects(source, Span { start: 15, end: 19 }));
+        assert!(!window.intersects(source, Span { start: 0, end: 5 }));
+        let past_end = ReadWindow::Lines { start: 9, end: 12 };
+        assert!(!past_end.intersects(source, Span { start: 0, end: 19 }));
+    }
+
+    #[test]
+    fn window_flags_parse_and_reject_bad_input() {
+        assert_eq!(
+            parse_read_window(Some("3"), None, None).expect("single line should parse"),
+            Some(ReadWindow::Lines { start: 3, end: 3 })
+        );
+        assert!(parse_read_window(Some("0:2"), None, None).is_err());
+        assert!(parse_read_window(Some("5:2"), None, None).is_err());
+        assert!(parse_read_window(None, Some("a:b"), None).is_err());
+        assert!(parse_read_window(Some("1:2"), None, Some(1)).is_err());
+    }
+}
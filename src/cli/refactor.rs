@@ -0,0 +1,232 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::{Args, Subcommand};
+
+use crate::apply::{ApplySettings, apply_multi_file_changeset};
+use crate::changeset::{MultiFileChangeset, OpKind, TransformTarget};
+use crate::error::IdenteditError;
+use crate::handle::{SelectionHandle, Span};
+use crate::transform::{TransformInstruction, build_changeset};
+
+use super::apply::{ApplyCliResponse, shape_apply_response};
+use super::edit_build::apply_preview_mode;
+use super::refactor_inline::inline_variable;
+use super::scan::{ScanLimits, collect_files};
+
+#[derive(Debug, Args)]
+pub struct RefactorArgs {
+    #[command(subcommand)]
+    pub command: RefactorCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RefactorCommands {
+    #[command(
+        about = "Rename an identifier across every source file under a directory (identifier nodes only, never strings or comments)"
+    )]
+    Rename(RefactorRenameArgs),
+    #[command(
+        about = "Replace the uses of a single-assignment local variable with its initializer and remove the declaration"
+    )]
+    InlineVariable(RefactorInlineVariableArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct RefactorRenameArgs {
+    #[arg(long, value_name = "NAME", help = "Identifier to rename")]
+    pub symbol: String,
+    #[arg(long, value_name = "NAME", help = "New identifier text")]
+    pub to: String,
+    #[arg(
+        long,
+        value_name = "DIR",
+        default_value = ".",
+        help = "Directory searched recursively; hidden entries and dependency/build directories are skipped"
+    )]
+    pub dir: PathBuf,
+    #[arg(
+        long,
+        help = "Apply the plan as one transaction instead of printing it"
+    )]
+    pub apply: bool,
+    #[arg(
+        long,
+        help = "Emit verbose preview fields (old_text) or per-file apply results"
+    )]
+    pub verbose: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct RefactorInlineVariableArgs {
+    #[arg(
+        long,
+        value_name = "IDENTITY",
+        help = "Identity of the function containing the variable, from read output"
+    )]
+    pub identity: String,
+    #[arg(long, value_name = "NAME", help = "Local variable to inline")]
+    pub variable: String,
+    #[arg(long, help = "Apply the plan instead of printing it")]
+    pub apply: bool,
+    #[arg(
+        long,
+        help = "Emit verbose preview fields (old_text) or per-file apply results"
+    )]
+    pub verbose: bool,
+    #[arg(value_name = "FILE", help = "File containing the function")]
+    pub file: PathBuf,
+}
+
+pub enum RefactorCommandOutput {
+    Plan(Box<MultiFileChangeset>),
+    Applied(Box<ApplyCliResponse>),
+}
+
+pub fn run_refactor(
+    args: RefactorArgs,
+    settings: ApplySettings,
+    limits: ScanLimits,
+) -> Result<RefactorCommandOutput, IdenteditError> {
+    match args.command {
+        RefactorCommands::Rename(rename_args) => run_rename(rename_args, settings, &limits),
+        RefactorCommands::InlineVariable(inline_args) => {
+            let changeset = inline_variable(
+                &inline_args.file,
+                &inline_args.identity,
+                &inline_args.variable,
+                &settings.provider_registry()?,
+            )?;
+            finish(changeset, inline_args.apply, inline_args.verbose, settings)
+        }
+    }
+}
+
+fn run_rename(
+    args: RefactorRenameArgs,
+    settings: ApplySettings,
+    limits: &ScanLimits,
+) -> Result<RefactorCommandOutput, IdenteditError> {
+    for (flag, value) in [("--symbol", &args.symbol), ("--to", &args.to)] {
+        if value.is_empty() || value.chars().any(char::is_whitespace) {
+            return Err(IdenteditError::InvalidRequest {
+                message: format!("{flag} must be a non-empty identifier without whitespace"),
+            });
+        }
+    }
+    if args.symbol == args.to {
+        return Err(IdenteditError::InvalidRequest {
+            message: "--symbol and --to are the same; nothing to rename".to_string(),
+        });
+    }
+
+    let registry = settings.provider_registry()?;
+    let mut files = Vec::new();
+    let is_source_file = |path: &Path| {
+        registry
+            .provider_for(path)
+            .is_ok_and(|provider| provider.name().starts_with("tree-sitter"))
+    };
+    collect_files(&args.dir, &is_source_file, limits, &mut files)?;
+
+    let mut changes = Vec::new();
+    for file in files {
+        limits.check_deadline()?;
+        let source = fs::read(&file).map_err(|error| IdenteditError::io(&file, error))?;
+        let handles = registry.provider_for(&file)?.parse(&file, &source)?;
+        let instructions = identifier_occurrences(handles, &args.symbol)
+            .into_iter()
+            .map(|handle| TransformInstruction {
+                target: TransformTarget::node(
+                    handle.identity,
+                    handle.kind,
+                    Some(handle.span),
+                    handle.expected_old_hash,
+                ),
+                op: OpKind::Replace {
+                    new_text: args.to.clone(),
+                },
+            })
+            .collect::<Vec<_>>();
+        if !instructions.is_empty() {
+            changes.push(build_changeset(&file, instructions, &registry)?);
+        }
+    }
+    if changes.is_empty() {
+        return Err(IdenteditError::InvalidRequest {
+            message: format!(
+                "No identifier named '{}' found under '{}'",
+                args.symbol,
+                args.dir.display()
+            ),
+        });
+    }
+
+    let changeset = MultiFileChangeset {
+        files: changes,
+        transaction: Default::default(),
+        correlation_id: None,
+    };
+    finish(changeset, args.apply, args.verbose, settings)
+}
+
+fn finish(
+    mut changeset: MultiFileChangeset,
+    apply: bool,
+    verbose: bool,
+    settings: ApplySettings,
+) -> Result<RefactorCommandOutput, IdenteditError> {
+    if apply {
+        changeset.transaction.settings = settings;
+        let response = apply_multi_file_changeset(&changeset)?;
+        return Ok(RefactorCommandOutput::Applied(Box::new(
+            shape_apply_response(response, verbose),
+        )));
+    }
+    apply_preview_mode(&mut changeset, verbose);
+    Ok(RefactorCommandOutput::Plan(Box::new(changeset)))
+}
+
+/// Identifier leaves whose text is exactly `symbol`. Grammars name these `identifier`,
+/// `*_identifier`, `constant` (Ruby), or `name` (PHP); strings and comments never produce
+/// them. When two kinds share a span, the first one wins.
+fn identifier_occurrences(handles: Vec<SelectionHandle>, symbol: &str) -> Vec<SelectionHandle> {
+    let mut seen_spans: Vec<Span> = Vec::new();
+    handles
+        .into_iter()
+        .filter(|handle| is_identifier_kind(&handle.kind) && handle.text == symbol)
+        .filter(|handle| {
+            if seen_spans.contains(&handle.span) {
+                return false;
+            }
+            seen_spans.push(handle.span);
+            true
+        })
+        .collect()
+}
+
+fn is_identifier_kind(kind: &str) -> bool {
+    kind == "identifier" || kind.ends_with("_identifier") || kind == "constant" || kind == "name"
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::identifier_occurrences;
+    use crate::provider::{StructureProvider, TreeSitterProvider};
+
+    #[test]
+    fn only_identifier_nodes_with_the_exact_name_are_renamed() {
+        let source =
+            b"def total(x):\n    # total of x\n    subtotal = x\n    return \"total\" + total(x)\n";
+        let handles = TreeSitterProvider::python()
+            .parse(Path::new("a.py"), source)
+            .expect("python should parse");
+        let spans = identifier_occurrences(handles, "total")
+            .into_iter()
+            .map(|handle| (handle.span.start, handle.span.end))
+            .collect::<Vec<_>>();
+        assert_eq!(spans, vec![(4, 9), (69, 74)]);
+    }
+}
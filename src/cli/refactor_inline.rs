@@ -0,0 +1,399 @@
+//! `refactor inline-variable`: replaces every use of a single-assignment local with its
+//! initializer and removes the declaration.
+//!
+//! The analysis works on the tree-sitter syntax tree of the enclosing function. Every
+//! identifier named like the variable is either a write (the declaration, a later
+//! assignment, an update, a loop target, a parameter) or a use. Inlining is only safe when
+//! the declaration is the one write, so any other write fails the precondition.
+
+use std::fs;
+use std::path::Path;
+
+use tree_sitter::Node;
+
+use crate::changeset::{MultiFileChangeset, OpKind, TransformTarget};
+use crate::error::IdenteditError;
+use crate::handle::{LineIndex, SelectionHandle, Span};
+use crate::hashline::{format_line_ref, show_hashed_lines};
+use crate::provider::ProviderRegistry;
+use crate::transform::{TransformInstruction, build_changeset};
+
+/// A declaration that binds one name to an initializer: node kind, the fields holding the
+/// name and the value, and the statement kinds that may wrap it.
+struct DeclarationShape {
+    kind: &'static str,
+    name_field: &'static str,
+    value_field: &'static str,
+    statements: &'static [&'static str],
+}
+
+const DECLARATION_SHAPES: &[DeclarationShape] = &[
+    // Python: `total = price * count`
+    DeclarationShape {
+        kind: "assignment",
+        name_field: "left",
+        value_field: "right",
+        statements: &["expression_statement"],
+    },
+    // JavaScript/TypeScript `const total = ...;`, Java `int total = ...;`
+    DeclarationShape {
+        kind: "variable_declarator",
+        name_field: "name",
+        value_field: "value",
+        statements: &[
+            "lexical_declaration",
+            "variable_declaration",
+            "local_variable_declaration",
+        ],
+    },
+    // C/C++: `int total = ...;`
+    DeclarationShape {
+        kind: "init_declarator",
+        name_field: "declarator",
+        value_field: "value",
+        statements: &["declaration"],
+    },
+    // Rust: `let total = ...;`
+    DeclarationShape {
+        kind: "let_declaration",
+        name_field: "pattern",
+        value_field: "value",
+        statements: &[],
+    },
+    // Go: `total := ...`
+    DeclarationShape {
+        kind: "short_var_declaration",
+        name_field: "left",
+        value_field: "right",
+        statements: &[],
+    },
+];
+
+/// Initializer kinds that bind tighter than any operator around a use, so they are
+/// inlined without parentheses.
+const ATOMIC_KINDS: &[&str] = &[
+    "identifier",
+    "integer",
+    "float",
+    "number",
+    "string",
+    "true",
+    "false",
+    "none",
+    "null",
+    "call",
+    "call_expression",
+    "attribute",
+    "member_expression",
+    "field_expression",
+    "selector_expression",
+    "subscript",
+    "subscript_expression",
+    "index_expression",
+    "parenthesized_expression",
+    "list",
+    "dictionary",
+    "array",
+    "object",
+    "string_literal",
+    "integer_literal",
+    "float_literal",
+    "boolean_literal",
+    "int_literal",
+    "interpreted_string_literal",
+    "raw_string_literal",
+    "template_string",
+];
+
+#[derive(Debug)]
+struct Occurrences<'tree> {
+    writes: Vec<Node<'tree>>,
+    uses: Vec<Node<'tree>>,
+}
+
+pub(super) fn inline_variable(
+    file: &Path,
+    function_identity: &str,
+    variable: &str,
+    registry: &ProviderRegistry,
+) -> Result<MultiFileChangeset, IdenteditError> {
+    let source = fs::read(file).map_err(|error| IdenteditError::io(file, error))?;
+    let provider = registry.provider_for(file)?;
+    let handles = provider.parse(file, &source)?;
+    let tree = provider
+        .syntax_tree(&source)
+        .ok_or_else(|| IdenteditError::InvalidRequest {
+            message: format!(
+                "inline_variable needs a tree-sitter grammar; '{}' is handled by the {} provider",
+                file.display(),
+                provider.name()
+            ),
+        })??;
+
+    let functions = handles
+        .iter()
+        .filter(|handle| handle.identity == function_identity)
+        .collect::<Vec<_>>();
+    let function = match functions.as_slice() {
+        [function] => *function,
+        [] => {
+            return Err(IdenteditError::TargetMissing {
+                identity: function_identity.to_string(),
+                file: file.display().to_string(),
+            });
+        }
+        _ => {
+            return Err(IdenteditError::AmbiguousTarget {
+                identity: function_identity.to_string(),
+                file: file.display().to_string(),
+                candidates: functions.len(),
+            });
+        }
+    };
+    let function_node = find_node(tree.root_node(), &function.kind, function.span)
+        .expect("provider handles come from the same syntax tree");
+
+    let mut occurrences = Occurrences {
+        writes: Vec::new(),
+        uses: Vec::new(),
+    };
+    collect_occurrences(function_node, &source, variable, &mut occurrences);
+
+    let declaration = match occurrences.writes.as_slice() {
+        [write] => *write,
+        [] => {
+            return Err(invalid(format!(
+                "No assignment to '{variable}' found in the selected {}",
+                function.kind
+            )));
+        }
+        writes => {
+            return Err(invalid(format!(
+                "inline_variable requires exactly one assignment to '{variable}'; found {}",
+                writes.len()
+            )));
+        }
+    };
+    let (statement, value) = declaration_statement(declaration).ok_or_else(|| {
+        invalid(format!(
+            "The assignment to '{variable}' is not a single-variable declaration with an initializer"
+        ))
+    })?;
+    for use_node in &occurrences.uses {
+        if use_node.start_byte() < statement.end_byte() {
+            return Err(invalid(format!(
+                "'{variable}' is used before or inside its own declaration at byte {}",
+                use_node.start_byte()
+            )));
+        }
+    }
+
+    let value_text = node_text(value, &source);
+    let replacement = if ATOMIC_KINDS.contains(&value.kind()) {
+        value_text.to_string()
+    } else {
+        format!("({value_text})")
+    };
+
+    let mut instructions = Vec::with_capacity(occurrences.uses.len() + 1);
+    instructions.push(remove_statement(&handles, &source, statement)?);
+    for use_node in &occurrences.uses {
+        let new_text = if use_node.kind() == "shorthand_property_identifier" {
+            format!("{variable}: {value_text}")
+        } else {
+            replacement.clone()
+        };
+        instructions.push(TransformInstruction {
+            target: node_target(&handles, *use_node),
+            op: OpKind::Replace { new_text },
+        });
+    }
+
+    Ok(MultiFileChangeset {
+        files: vec![build_changeset(file, instructions, registry)?],
+        transaction: Default::default(),
+        correlation_id: None,
+    })
+}
+
+fn find_node<'tree>(root: Node<'tree>, kind: &str, span: Span) -> Option<Node<'tree>> {
+    let mut node = root.descendant_for_byte_range(span.start, span.end)?;
+    loop {
+        if node.kind() == kind && node.start_byte() == span.start && node.end_byte() == span.end {
+            return Some(node);
+        }
+        node = node.parent()?;
+    }
+}
+
+fn collect_occurrences<'tree>(
+    node: Node<'tree>,
+    source: &[u8],
+    variable: &str,
+    occurrences: &mut Occurrences<'tree>,
+) {
+    if matches!(node.kind(), "identifier" | "shorthand_property_identifier")
+        && node_text(node, source) == variable
+        && !is_member_name(node)
+    {
+        if is_write(node) {
+            occurrences.writes.push(node);
+        } else {
+            occurrences.uses.push(node);
+        }
+    }
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect_occurrences(child, source, variable, occurrences);
+    }
+}
+
+/// Python spells attribute names and keyword arguments with plain identifiers.
+fn is_member_name(node: Node<'_>) -> bool {
+    node.parent().is_some_and(|parent| match parent.kind() {
+        "attribute" => is_field(parent, "attribute", node),
+        "keyword_argument" => is_field(parent, "name", node),
+        _ => false,
+    })
+}
+
+fn is_write(node: Node<'_>) -> bool {
+    let Some(mut parent) = node.parent() else {
+        return false;
+    };
+    let mut target = node;
+    if matches!(
+        parent.kind(),
+        "expression_list" | "pattern_list" | "tuple_pattern"
+    ) {
+        let Some(grandparent) = parent.parent() else {
+            return false;
+        };
+        target = parent;
+        parent = grandparent;
+    }
+    let kind = parent.kind();
+    if kind.contains("parameter")
+        || matches!(
+            kind,
+            "update_expression" | "inc_statement" | "dec_statement"
+        )
+    {
+        return true;
+    }
+    let binds = kind.contains("assignment") || kind.contains("declarat") || kind.starts_with("for");
+    binds
+        && ["left", "pattern", "name", "declarator"]
+            .into_iter()
+            .any(|field| is_field(parent, field, target))
+}
+
+/// The statement to remove and the initializer, when `name` is the whole binding of a
+/// supported declaration shape.
+fn declaration_statement(name: Node<'_>) -> Option<(Node<'_>, Node<'_>)> {
+    let mut declaration = name.parent()?;
+    let mut binding = name;
+    if declaration.kind() == "expression_list" {
+        binding = declaration;
+        declaration = declaration.parent()?;
+    }
+    let shape = DECLARATION_SHAPES
+        .iter()
+        .find(|shape| shape.kind == declaration.kind())?;
+    if !is_field(declaration, shape.name_field, binding)
+        || (binding.id() != name.id() && binding.named_child_count() != 1)
+    {
+        return None;
+    }
+    let mut value = declaration.child_by_field_name(shape.value_field)?;
+    if value.kind() == "expression_list" {
+        if value.named_child_count() != 1 {
+            return None;
+        }
+        value = value.named_child(0)?;
+    }
+    if value.kind() == declaration.kind() {
+        // Chained assignment: `a = b = 1`.
+        return None;
+    }
+    if shape.statements.is_empty() {
+        return Some((declaration, value));
+    }
+    let statement = declaration.parent()?;
+    (shape.statements.contains(&statement.kind()) && statement.named_child_count() == 1)
+        .then_some((statement, value))
+}
+
+/// Removes whole lines when the statement is alone on them, otherwise just its text.
+fn remove_statement(
+    handles: &[SelectionHandle],
+    source: &[u8],
+    statement: Node<'_>,
+) -> Result<TransformInstruction, IdenteditError> {
+    let index = LineIndex::new(source);
+    let (start, end) = index.span_positions(node_span(statement));
+    let first_line = index.line_text(start.line).unwrap_or_default();
+    let last_line = index.line_text(end.line).unwrap_or_default();
+    let alone = first_line.as_bytes()[..start.column - 1]
+        .iter()
+        .all(u8::is_ascii_whitespace)
+        && last_line.as_bytes()[(end.column - 1).min(last_line.len())..]
+            .iter()
+            .all(u8::is_ascii_whitespace);
+    if !alone {
+        return Ok(TransformInstruction {
+            target: node_target(handles, statement),
+            op: OpKind::Delete,
+        });
+    }
+
+    let source_text = std::str::from_utf8(source).map_err(|_| {
+        invalid("inline_variable requires UTF-8 source to remove whole lines".to_string())
+    })?;
+    let lines = show_hashed_lines(source_text);
+    let anchor = |line: usize| format_line_ref(line, &lines[line - 1].hash);
+    Ok(TransformInstruction {
+        target: TransformTarget::Line {
+            anchor: anchor(start.line),
+            end_anchor: (end.line > start.line).then(|| anchor(end.line)),
+        },
+        op: OpKind::Replace {
+            new_text: String::new(),
+        },
+    })
+}
+
+fn node_target(handles: &[SelectionHandle], node: Node<'_>) -> TransformTarget {
+    let span = node_span(node);
+    let handle = handles
+        .iter()
+        .find(|handle| handle.kind == node.kind() && handle.span == span)
+        .expect("provider handles cover every named node");
+    TransformTarget::node(
+        handle.identity.clone(),
+        handle.kind.clone(),
+        Some(span),
+        handle.expected_old_hash.clone(),
+    )
+}
+
+fn is_field(parent: Node<'_>, field: &str, child: Node<'_>) -> bool {
+    parent
+        .child_by_field_name(field)
+        .is_some_and(|candidate| candidate.id() == child.id())
+}
+
+fn node_span(node: Node<'_>) -> Span {
+    Span {
+        start: node.start_byte(),
+        end: node.end_byte(),
+    }
+}
+
+fn node_text<'source>(node: Node<'_>, source: &'source [u8]) -> &'source str {
+    std::str::from_utf8(&source[node.start_byte()..node.end_byte()]).unwrap_or_default()
+}
+
+fn invalid(message: String) -> IdenteditError {
+    IdenteditError::InvalidRequest { message }
+}
@@ -0,0 +1,175 @@
+//! `--reread-after` for `apply` and `patch`: the handles an edit created, returned with the
+//! edit so no follow-up `read` is needed.
+//!
+//! Node handles are the ones that did not exist at the same span before the edit, which
+//! together with `invalidated_identities` is everything a handle cache must change. Line
+//! anchors carry their line number, so for line targets the changed lines are returned with
+//! one line of context on each side.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+
+use similar::{DiffTag, TextDiff};
+
+use crate::changeset::{FileChange, OpKind, TransformTarget};
+use crate::handle::LineIndex;
+use crate::hashline::{HashlineAlgo, format_line_ref, show_hashed_lines_with};
+use crate::provider::ProviderRegistry;
+use crate::transform::parse_handles_for_source;
+
+use super::read::ReadHandle;
+
+/// Which kinds of handle to return for a file.
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct RereadScope {
+    pub(super) nodes: bool,
+    pub(super) lines: bool,
+}
+
+impl RereadScope {
+    pub(super) fn for_targets<'a>(targets: impl IntoIterator<Item = &'a TransformTarget>) -> Self {
+        let mut scope = Self::default();
+        for target in targets {
+            match target {
+                TransformTarget::Line { .. } => scope.lines = true,
+                _ => scope.nodes = true,
+            }
+        }
+        scope
+    }
+}
+
+/// The files an edit is about to change, as they are before it.
+#[derive(Debug)]
+pub(super) struct RereadBaseline {
+    files: Vec<BaselineFile>,
+}
+
+#[derive(Debug)]
+struct BaselineFile {
+    file: PathBuf,
+    /// `None` when the file does not exist yet or is not UTF-8.
+    source: Option<String>,
+    scope: RereadScope,
+}
+
+impl RereadBaseline {
+    pub(super) fn capture(files: impl IntoIterator<Item = (PathBuf, RereadScope)>) -> Self {
+        Self {
+            files: files
+                .into_iter()
+                .map(|(file, scope)| BaselineFile {
+                    source: fs::read_to_string(&file).ok(),
+                    file,
+                    scope,
+                })
+                .collect(),
+        }
+    }
+
+    /// The files of a plan; moved files are left out, since their content does not change.
+    pub(super) fn for_changesets(changesets: &[FileChange]) -> Self {
+        Self::capture(
+            changesets
+                .iter()
+                .filter(|changeset| {
+                    !changeset
+                        .operations
+                        .iter()
+                        .any(|operation| matches!(operation.op, OpKind::Move { .. }))
+                })
+                .map(|changeset| {
+                    (
+                        changeset.file.clone(),
+                        RereadScope::for_targets(
+                            changeset
+                                .operations
+                                .iter()
+                                .map(|operation| &operation.target),
+                        ),
+                    )
+                }),
+        )
+    }
+
+    /// Reads every file again and returns the handles that are new since the capture.
+    pub(super) fn reread(self, registry: &ProviderRegistry) -> Vec<ReadHandle> {
+        let mut handles = Vec::new();
+        for baseline in self.files {
+            let Ok(source) = fs::read_to_string(&baseline.file) else {
+                continue;
+            };
+            if baseline.source.as_deref() == Some(source.as_str()) {
+                continue;
+            }
+            if baseline.scope.nodes {
+                handles.extend(fresh_nodes(&baseline, &source, registry));
+            }
+            if baseline.scope.lines {
+                handles.extend(touched_lines(&baseline, &source));
+            }
+        }
+        handles
+    }
+}
+
+fn fresh_nodes(
+    baseline: &BaselineFile,
+    source: &str,
+    registry: &ProviderRegistry,
+) -> Vec<ReadHandle> {
+    let parse =
+        |text: &str| parse_handles_for_source(&baseline.file, text.as_bytes(), registry).ok();
+    let Some(after) = parse(source) else {
+        return Vec::new();
+    };
+    let before = baseline
+        .source
+        .as_deref()
+        .and_then(parse)
+        .unwrap_or_default();
+    let existing = before
+        .iter()
+        .map(|handle| (handle.identity.as_str(), handle.span.start, handle.span.end))
+        .collect::<HashSet<_>>();
+    let lines = LineIndex::new(source.as_bytes());
+    after
+        .into_iter()
+        .filter(|handle| {
+            !existing.contains(&(handle.identity.as_str(), handle.span.start, handle.span.end))
+        })
+        .map(|handle| ReadHandle::from_selection_handle(handle, &lines, false))
+        .collect()
+}
+
+fn touched_lines(baseline: &BaselineFile, source: &str) -> Vec<ReadHandle> {
+    let hashed = show_hashed_lines_with(source, HashlineAlgo::default());
+    let previous = baseline.source.as_deref().unwrap_or_default();
+    let mut touched = vec![false; hashed.len()];
+    for operation in TextDiff::from_lines(previous, source).ops() {
+        let (tag, _, new_range) = operation.as_tag_tuple();
+        if tag == DiffTag::Equal {
+            continue;
+        }
+        let start = new_range.start.saturating_sub(1);
+        let end = (new_range.end + 1).min(touched.len());
+        for flag in touched.iter_mut().take(end).skip(start) {
+            *flag = true;
+        }
+    }
+    hashed
+        .into_iter()
+        .zip(touched)
+        .filter(|(_, touched)| *touched)
+        .map(|(line, _)| ReadHandle::Line {
+            file: baseline.file.clone(),
+            line: line.line,
+            anchor: format_line_ref(line.line, &line.hash),
+            hash: line.hash,
+            hash_algo: HashlineAlgo::default(),
+            text: line.content,
+            text_truncated: false,
+        })
+        .collect()
+}
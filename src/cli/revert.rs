@@ -0,0 +1,84 @@
+//! `identedit revert`: applies an inverse plan written by `apply --emit-inverse` or
+//! `--inverse-out`, with the same hash preconditions as any other plan.
+
+use std::path::PathBuf;
+
+use clap::Args;
+
+use crate::apply::ApplySettings;
+use crate::changeset::{MultiFileChangeset, OpKind, TransformTarget};
+use crate::error::IdenteditError;
+
+use super::apply::{
+    ApplyCliResponse, apply_changeset_with_optional_injection, read_changeset_from_file,
+    read_changeset_from_stdin, shape_apply_response,
+};
+use super::plan_hash::plan_hash;
+
+#[derive(Debug, Args)]
+pub struct RevertArgs {
+    #[arg(long, help = "Validate and preview without writing files")]
+    pub dry_run: bool,
+    #[arg(long, help = "Include per-file apply results in output")]
+    pub verbose: bool,
+    #[arg(
+        value_name = "INVERSE_PLAN",
+        help = "Path to the inverse plan from apply --inverse-out; if omitted, read it from stdin"
+    )]
+    pub input: Option<PathBuf>,
+}
+
+pub fn run_revert(
+    args: RevertArgs,
+    settings: ApplySettings,
+) -> Result<ApplyCliResponse, IdenteditError> {
+    let mut changeset = match args.input {
+        Some(input_path) => read_changeset_from_file(&input_path)?,
+        None => read_changeset_from_stdin()?,
+    };
+    changeset.transaction.settings = settings;
+    let correlation_id = changeset.correlation_id.clone();
+    revert_plan(changeset, args.dry_run, args.verbose)
+        .map_err(|error| error.with_correlation_id(correlation_id.as_deref()))
+}
+
+/// The part of `run_revert` after the plan is read, whose errors echo its `correlation_id`.
+fn revert_plan(
+    changeset: MultiFileChangeset,
+    dry_run: bool,
+    verbose: bool,
+) -> Result<ApplyCliResponse, IdenteditError> {
+    reject_non_inverse_plan(&changeset)?;
+    let plan_hash = plan_hash(&changeset)?;
+    let response = apply_changeset_with_optional_injection(&changeset, None, dry_run)?;
+
+    let mut response = shape_apply_response(response, verbose);
+    response.correlation_id = changeset.correlation_id;
+    response.plan_hash = Some(plan_hash);
+    Ok(response)
+}
+
+/// Inverse plans only hold line-range replacements and insertions, so anything else is a
+/// forward plan passed to `revert` by mistake.
+fn reject_non_inverse_plan(changeset: &MultiFileChangeset) -> Result<(), IdenteditError> {
+    for file_change in &changeset.files {
+        for (index, operation) in file_change.operations.iter().enumerate() {
+            let inverse_shaped = matches!(
+                (&operation.target, &operation.op),
+                (
+                    TransformTarget::Line { .. },
+                    OpKind::Replace { .. } | OpKind::InsertAfter { .. }
+                ) | (TransformTarget::FileStart { .. }, OpKind::Insert { .. })
+            );
+            if !inverse_shaped {
+                return Err(IdenteditError::InvalidRequest {
+                    message: format!(
+                        "Operation {index} for '{}' is not part of an inverse plan; revert takes plans from apply --emit-inverse or --inverse-out, use apply for other plans",
+                        file_change.file.display()
+                    ),
+                });
+            }
+        }
+    }
+    Ok(())
+}
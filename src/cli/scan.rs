@@ -0,0 +1,146 @@
+//! Directory walks for the commands that work on a whole tree (`refactor rename`, `map`).
+//!
+//! Walks and scans stop with a `timeout` error once the deadline of their [`ScanLimits`] passes. The
+//! deadline is checked per directory and per file, so one slow parse still runs to its end.
+
+use std::fs;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::error::IdenteditError;
+use crate::path_style::{path_style, with_path_style};
+use crate::provider::ProviderRegistry;
+
+/// Directories that hold dependencies or build output rather than project sources.
+const SKIPPED_DIRECTORIES: &[&str] = &["node_modules", "target", "vendor", "__pycache__"];
+
+/// When walks and scans give up and how many threads they use, from `--timeout-ms` and
+/// `--max-concurrency`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScanLimits {
+    deadline: Option<(Instant, u64)>,
+    /// One thread per available core when unset.
+    max_concurrency: Option<NonZeroUsize>,
+}
+
+impl ScanLimits {
+    /// Limits with a deadline `timeout_ms` from now, or none, and at most
+    /// `max_concurrency` scan threads.
+    pub fn new(timeout_ms: Option<u64>, max_concurrency: Option<NonZeroUsize>) -> Self {
+        Self {
+            deadline: timeout_ms.map(|timeout_ms| {
+                (
+                    Instant::now() + Duration::from_millis(timeout_ms),
+                    timeout_ms,
+                )
+            }),
+            max_concurrency,
+        }
+    }
+
+    /// Fails with `timeout` once the deadline has passed.
+    pub(super) fn check_deadline(&self) -> Result<(), IdenteditError> {
+        match self.deadline {
+            Some((deadline, timeout_ms)) if Instant::now() >= deadline => {
+                Err(IdenteditError::TimedOut { timeout_ms })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Files under `dir` that `include` accepts, in sorted path order so output is stable.
+/// Hidden entries, symlinks, and dependency/build directories are skipped.
+pub(super) fn collect_files(
+    dir: &Path,
+    include: &dyn Fn(&Path) -> bool,
+    limits: &ScanLimits,
+    files: &mut Vec<PathBuf>,
+) -> Result<(), IdenteditError> {
+    limits.check_deadline()?;
+    let mut entries = fs::read_dir(dir)
+        .map_err(|error| IdenteditError::io(dir, error))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|error| IdenteditError::io(dir, error))?;
+    entries.sort();
+
+    for path in entries {
+        let file_name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("");
+        if file_name.starts_with('.') {
+            continue;
+        }
+        let file_type = fs::symlink_metadata(&path)
+            .map_err(|error| IdenteditError::io(&path, error))?
+            .file_type();
+        if file_type.is_dir() {
+            if !SKIPPED_DIRECTORIES.contains(&file_name) {
+                collect_files(&path, include, limits, files)?;
+            }
+        } else if file_type.is_file() && include(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Runs `scan` over `files` on up to `--max-concurrency` threads of `limits` and returns
+/// the results in `files` order, or a `timeout` error as soon as a thread finds the
+/// deadline passed.
+/// Threads take the next file as they finish one, so a slow file holds up only its own
+/// thread. Providers are not thread-safe, so each thread builds its own registry with the
+/// overrides of `registry` and renders paths in the calling thread's style.
+pub(super) fn scan_parallel<T: Send>(
+    files: &[PathBuf],
+    limits: &ScanLimits,
+    registry: &ProviderRegistry,
+    scan: impl Fn(&ProviderRegistry, &Path) -> T + Sync,
+) -> Result<Vec<T>, IdenteditError> {
+    if files.is_empty() {
+        return Ok(Vec::new());
+    }
+    let threads = limits
+        .max_concurrency
+        .or_else(|| std::thread::available_parallelism().ok())
+        .map_or(1, NonZeroUsize::get)
+        .min(files.len());
+    let overrides = registry.overrides();
+    let style = path_style();
+    let next = AtomicUsize::new(0);
+    std::thread::scope(|scope| {
+        let workers = (0..threads)
+            .map(|_| {
+                let (scan, next) = (&scan, &next);
+                scope.spawn(move || {
+                    with_path_style(style, || {
+                        let registry = ProviderRegistry::resolved(overrides.clone());
+                        let mut scanned = Vec::new();
+                        loop {
+                            let index = next.fetch_add(1, Ordering::Relaxed);
+                            let Some(file) = files.get(index) else {
+                                return Ok(scanned);
+                            };
+                            if let Err(error) = limits.check_deadline() {
+                                // Stops the other threads at their next file.
+                                next.store(files.len(), Ordering::Relaxed);
+                                return Err(error);
+                            }
+                            scanned.push((index, scan(&registry, file)));
+                        }
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut results = Vec::with_capacity(files.len());
+        for worker in workers {
+            results.extend(worker.join().expect("scan thread should not panic")?);
+        }
+        results.sort_unstable_by_key(|(index, _)| *index);
+        Ok(results.into_iter().map(|(_, result)| result).collect())
+    })
+}
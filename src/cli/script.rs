@@ -0,0 +1,251 @@
+//! `--script FILE` for `edit` and `patch`: Rhai scripts that compute replacement text.
+//!
+//! The script runs once per node (or once per regex match with `patch --scoped-regex`)
+//! with two variables in scope:
+//! - `handle`: `#{file, kind, name, identity, text, span: #{start, end}}`, `name` being `()`
+//!   for anonymous nodes;
+//! - `captures`: the regex match groups by index (`captures["0"]` is the whole match) and
+//!   by name, with `()` for groups that did not participate; empty outside regex mode.
+//!
+//! Both are fresh copies per run. The script's value is the replacement. `()` keeps the current text, so a script can
+//! rewrite conditionally. `print`/`debug` go to stderr so they never corrupt JSON output.
+
+use std::ops::Range;
+use std::path::Path;
+#[cfg(feature = "script")]
+use std::path::PathBuf;
+
+use regex::{Captures, Regex};
+
+use crate::error::IdenteditError;
+use crate::handle::SelectionHandle;
+use crate::patch::scoped_regex::is_protected;
+
+/// Bounds a runaway script instead of letting it hang the command.
+#[cfg(feature = "script")]
+pub(super) const MAX_OPERATIONS: u64 = 50_000_000;
+
+pub(super) struct ReplacementScript {
+    #[cfg(feature = "script")]
+    path: PathBuf,
+    #[cfg(feature = "script")]
+    engine: rhai::Engine,
+    #[cfg(feature = "script")]
+    ast: rhai::AST,
+    #[cfg(not(feature = "script"))]
+    never: std::convert::Infallible,
+}
+
+impl ReplacementScript {
+    #[cfg(feature = "script")]
+    pub(super) fn load(path: &Path) -> Result<Self, IdenteditError> {
+        let source =
+            std::fs::read_to_string(path).map_err(|error| IdenteditError::io(path, error))?;
+        let mut engine = rhai::Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.on_print(|text| eprintln!("{text}"));
+        engine.on_debug(|text, _, position| eprintln!("{position:?}: {text}"));
+        let ast = engine
+            .compile(source)
+            .map_err(|error| IdenteditError::ScriptFailed {
+                script: path.display().to_string(),
+                message: error.to_string(),
+            })?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            engine,
+            ast,
+        })
+    }
+
+    #[cfg(not(feature = "script"))]
+    pub(super) fn load(path: &Path) -> Result<Self, IdenteditError> {
+        Err(IdenteditError::InvalidRequest {
+            message: format!(
+                "--script '{}' needs identedit built with the `script` feature",
+                path.display()
+            ),
+        })
+    }
+
+    /// The replacement for `handle`, or `None` when the script keeps the current text.
+    #[cfg(feature = "script")]
+    pub(super) fn replacement(
+        &self,
+        handle: &SelectionHandle,
+        captures: Option<&Captures<'_>>,
+        regex: Option<&Regex>,
+    ) -> Result<Option<String>, IdenteditError> {
+        let mut scope = rhai::Scope::new();
+        scope.push("handle", handle_map(handle));
+        scope.push("captures", captures_map(captures, regex));
+        let value = self
+            .engine
+            .eval_ast_with_scope::<rhai::Dynamic>(&mut scope, &self.ast)
+            .map_err(|error| self.failed(handle, error.to_string()))?;
+        if value.is_unit() {
+            return Ok(None);
+        }
+        let type_name = value.type_name();
+        value.into_string().map(Some).map_err(|_| {
+            self.failed(
+                handle,
+                format!("the script must evaluate to a string or (), not {type_name}"),
+            )
+        })
+    }
+
+    #[cfg(not(feature = "script"))]
+    pub(super) fn replacement(
+        &self,
+        _handle: &SelectionHandle,
+        _captures: Option<&Captures<'_>>,
+        _regex: Option<&Regex>,
+    ) -> Result<Option<String>, IdenteditError> {
+        match self.never {}
+    }
+
+    /// Replaces every match of `regex` inside the node with the script's value for that
+    /// match; matches the script leaves alone keep their text. Matches overlapping a
+    /// `protected` range (relative to the node) are not offered to the script. Returns the
+    /// new node text and the number of matches offered.
+    pub(super) fn rewrite_matches(
+        &self,
+        handle: &SelectionHandle,
+        regex: &Regex,
+        protected: &[Range<usize>],
+    ) -> Result<(String, usize), IdenteditError> {
+        let mut new_text = String::with_capacity(handle.text.len());
+        let mut last_end = 0;
+        let mut matches = 0;
+        for captures in regex.captures_iter(&handle.text) {
+            let whole = captures.get(0).expect("group 0 always participates");
+            if is_protected(protected, whole.range()) {
+                continue;
+            }
+            new_text.push_str(&handle.text[last_end..whole.start()]);
+            match self.replacement(handle, Some(&captures), Some(regex))? {
+                Some(replacement) => new_text.push_str(&replacement),
+                None => new_text.push_str(whole.as_str()),
+            }
+            last_end = whole.end();
+            matches += 1;
+        }
+        new_text.push_str(&handle.text[last_end..]);
+        Ok((new_text, matches))
+    }
+
+    #[cfg(feature = "script")]
+    fn failed(&self, handle: &SelectionHandle, message: String) -> IdenteditError {
+        IdenteditError::ScriptFailed {
+            script: self.path.display().to_string(),
+            message: format!("on node '{}': {message}", handle.identity),
+        }
+    }
+}
+
+#[cfg(feature = "script")]
+fn handle_map(handle: &SelectionHandle) -> rhai::Map {
+    let mut span = rhai::Map::new();
+    span.insert("start".into(), (handle.span.start as rhai::INT).into());
+    span.insert("end".into(), (handle.span.end as rhai::INT).into());
+
+    let mut map = rhai::Map::new();
+    map.insert("file".into(), handle.file.display().to_string().into());
+    map.insert("kind".into(), handle.kind.clone().into());
+    map.insert(
+        "name".into(),
+        handle
+            .name
+            .clone()
+            .map_or(rhai::Dynamic::UNIT, rhai::Dynamic::from),
+    );
+    map.insert("identity".into(), handle.identity.clone().into());
+    map.insert("text".into(), handle.text.clone().into());
+    map.insert("span".into(), span.into());
+    map
+}
+
+#[cfg(feature = "script")]
+fn captures_map(captures: Option<&Captures<'_>>, regex: Option<&Regex>) -> rhai::Map {
+    let mut map = rhai::Map::new();
+    let (Some(captures), Some(regex)) = (captures, regex) else {
+        return map;
+    };
+    let group = |index: usize| {
+        captures.get(index).map_or(rhai::Dynamic::UNIT, |group| {
+            group.as_str().to_string().into()
+        })
+    };
+    for (index, name) in regex.capture_names().enumerate() {
+        map.insert(index.to_string().into(), group(index));
+        if let Some(name) = name {
+            map.insert(name.into(), group(index));
+        }
+    }
+    map
+}
+
+#[cfg(all(test, feature = "script"))]
+mod tests {
+    use std::io::Write;
+
+    use regex::Regex;
+    use tempfile::NamedTempFile;
+
+    use super::ReplacementScript;
+    use crate::handle::{SelectionHandle, Span};
+
+    fn load(source: &str) -> (NamedTempFile, ReplacementScript) {
+        let mut file = NamedTempFile::new().expect("temp script");
+        file.write_all(source.as_bytes()).expect("script written");
+        let script = ReplacementScript::load(file.path()).expect("script compiles");
+        (file, script)
+    }
+
+    fn handle(text: &str) -> SelectionHandle {
+        SelectionHandle {
+            file: "app.py".into(),
+            span: Span {
+                start: 0,
+                end: text.len(),
+            },
+            kind: "call".to_string(),
+            name: None,
+            identity: "call-1".to_string(),
+            expected_old_hash: String::new(),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn match_rewrites_see_captures_and_unit_keeps_the_match() {
+        let (_file, script) =
+            load(r#"let n = parse_int(captures.value); if n > 10 { `${captures["1"]}=${n * 2}` }"#);
+        let regex = Regex::new(r"(\w+)=(?<value>\d+)").expect("regex");
+        let (text, matches) = script
+            .rewrite_matches(&handle("f(a=5, b=20)"), &regex, &[])
+            .expect("rewrite");
+        assert_eq!(text, "f(a=5, b=40)");
+        assert_eq!(matches, 2);
+    }
+
+    #[test]
+    fn non_string_results_and_runtime_errors_name_the_node() {
+        let (_file, script) = load("42");
+        let error = script
+            .replacement(&handle("x"), None, None)
+            .expect_err("integer result should be rejected");
+        assert!(error.to_string().contains("call-1"));
+
+        let (_file, script) = load("handle.kind + handle.missing.len()");
+        assert!(script.replacement(&handle("x"), None, None).is_err());
+        let (_file, script) = load("handle.text.to_upper()");
+        assert_eq!(
+            script
+                .replacement(&handle("abc"), None, None)
+                .expect("upper"),
+            Some("ABC".to_string())
+        );
+    }
+}
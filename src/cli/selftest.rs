@@ -0,0 +1,434 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use serde::Serialize;
+
+use crate::apply::{ApplySettings, apply_multi_file_changeset};
+use crate::changeset::{MultiFileChangeset, OpKind, TransformTarget};
+use crate::error::IdenteditError;
+use crate::handle::SelectionHandle;
+use crate::hashline::{HashlineEdit, SetLineEdit, format_line_ref, show_hashed_lines};
+use crate::provider::ProviderRegistry;
+use crate::transform::{TransformInstruction, build_changeset, parse_handles_for_file};
+
+use super::line_patch::execute_hashline_patch;
+
+const UTF8_BOM: &str = "\u{feff}";
+const PLAN_APPLY_ROUNDTRIP: &str = "plan_apply_roundtrip";
+const IDEMPOTENT_REAPPLY: &str = "idempotent_reapply";
+const LINE_ENDING_ROUNDTRIP: &str = "line_ending_roundtrip";
+
+#[derive(Debug, Args)]
+pub struct SelftestArgs {
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 16,
+        help = "Maximum number of nodes to edit per file"
+    )]
+    pub max_nodes: usize,
+    #[arg(
+        value_name = "FILE",
+        required = true,
+        help = "Sample files to check (never modified; checks run on scratch copies)"
+    )]
+    pub files: Vec<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SelftestResponse {
+    pub passed: bool,
+    pub summary: SelftestSummary,
+    pub files: Vec<SelftestFileReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SelftestSummary {
+    pub files: usize,
+    pub checks_passed: usize,
+    pub checks_failed: usize,
+    pub checks_skipped: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SelftestFileReport {
+    pub file: String,
+    pub checks: Vec<SelftestCheck>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SelftestCheck {
+    pub name: &'static str,
+    pub status: SelftestStatus,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelftestStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+/// Removes the scratch directory when the run ends, including on early returns.
+struct ScratchDir(PathBuf);
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Runs the built-in property checks against copies of the given files:
+///
+/// - `plan_apply_roundtrip`: for a sample of nodes from `read`, applying a replace plan
+///   yields exactly the source with that span replaced.
+/// - `idempotent_reapply`: applying the same plan a second time is rejected as stale and
+///   leaves the first result untouched.
+/// - `line_ending_roundtrip`: on a CRLF copy with a UTF-8 BOM, node and line edits keep
+///   both, and no-op edits leave the bytes unchanged.
+pub fn run_selftest(
+    args: SelftestArgs,
+    registry: &ProviderRegistry,
+) -> Result<SelftestResponse, IdenteditError> {
+    let scratch =
+        ScratchDir(std::env::temp_dir().join(format!("identedit-selftest-{}", std::process::id())));
+    let mut files = Vec::with_capacity(args.files.len());
+    for (index, file) in args.files.iter().enumerate() {
+        let source = fs::read_to_string(file).map_err(|error| IdenteditError::io(file, error))?;
+        let file_name = file
+            .file_name()
+            .ok_or_else(|| IdenteditError::InvalidRequest {
+                message: format!("'{}' is not a file path", file.display()),
+            })?;
+        let directory = scratch.0.join(index.to_string());
+        fs::create_dir_all(&directory).map_err(|error| IdenteditError::io(&directory, error))?;
+        let copy = directory.join(file_name);
+
+        let mut checks = check_plan_apply(&copy, &source, args.max_nodes, registry)?;
+        checks.push(check_line_endings(&copy, &source, registry)?);
+        files.push(SelftestFileReport {
+            file: file.display().to_string(),
+            checks,
+        });
+    }
+
+    let count = |status| {
+        files
+            .iter()
+            .flat_map(|report| &report.checks)
+            .filter(|check| check.status == status)
+            .count()
+    };
+    let summary = SelftestSummary {
+        files: files.len(),
+        checks_passed: count(SelftestStatus::Passed),
+        checks_failed: count(SelftestStatus::Failed),
+        checks_skipped: count(SelftestStatus::Skipped),
+    };
+    Ok(SelftestResponse {
+        passed: summary.checks_failed == 0,
+        summary,
+        files,
+    })
+}
+
+fn check_plan_apply(
+    copy: &Path,
+    source: &str,
+    max_nodes: usize,
+    registry: &ProviderRegistry,
+) -> Result<Vec<SelftestCheck>, IdenteditError> {
+    write_scratch(copy, source)?;
+    let handles = match parse_handles_for_file(copy, registry) {
+        Ok(handles) => handles,
+        Err(error) => {
+            let detail = format!("file could not be read structurally: {error}");
+            return Ok(vec![
+                skipped(PLAN_APPLY_ROUNDTRIP, detail.clone()),
+                skipped(IDEMPOTENT_REAPPLY, detail),
+            ]);
+        }
+    };
+    let sample = sample_handles(&handles, max_nodes);
+    if sample.is_empty() {
+        let detail = "no structural nodes to edit".to_string();
+        return Ok(vec![
+            skipped(PLAN_APPLY_ROUNDTRIP, detail.clone()),
+            skipped(IDEMPOTENT_REAPPLY, detail),
+        ]);
+    }
+
+    let mut roundtrip_failure = None;
+    let mut reapply = None;
+    for handle in &sample {
+        write_scratch(copy, source)?;
+        let new_text = altered_text(&handle.text);
+        let expected = splice(source, handle, &new_text);
+        let outcome = build_node_plan(copy, handle, new_text, registry).and_then(|plan| {
+            apply_multi_file_changeset(&plan)?;
+            Ok(plan)
+        });
+        let plan = match outcome {
+            Ok(plan) => plan,
+            Err(error) => {
+                roundtrip_failure = Some(format!("{}: {error}", describe_handle(handle)));
+                break;
+            }
+        };
+        let actual = read_scratch(copy)?;
+        if actual != expected {
+            roundtrip_failure = Some(format!(
+                "{}: applied text differs from the expected splice",
+                describe_handle(handle)
+            ));
+            break;
+        }
+        if reapply.is_none() {
+            reapply = Some(check_reapply(copy, &plan, &expected)?);
+        }
+    }
+
+    let roundtrip = match roundtrip_failure {
+        Some(detail) => failed(PLAN_APPLY_ROUNDTRIP, detail),
+        None => passed(
+            PLAN_APPLY_ROUNDTRIP,
+            format!("{} nodes edited", sample.len()),
+        ),
+    };
+    let reapply =
+        reapply.unwrap_or_else(|| skipped(IDEMPOTENT_REAPPLY, "no plan was applied".to_string()));
+    Ok(vec![roundtrip, reapply])
+}
+
+fn check_reapply(
+    copy: &Path,
+    plan: &MultiFileChangeset,
+    expected: &str,
+) -> Result<SelftestCheck, IdenteditError> {
+    let second = apply_multi_file_changeset(plan);
+    let actual = read_scratch(copy)?;
+    Ok(match second {
+        Ok(_) => failed(
+            IDEMPOTENT_REAPPLY,
+            "stale plan applied a second time".to_string(),
+        ),
+        Err(_) if actual != expected => failed(
+            IDEMPOTENT_REAPPLY,
+            "rejected re-apply still changed the file".to_string(),
+        ),
+        Err(IdenteditError::PreconditionFailed { .. }) => passed(
+            IDEMPOTENT_REAPPLY,
+            "second apply rejected as stale".to_string(),
+        ),
+        Err(error) => passed(
+            IDEMPOTENT_REAPPLY,
+            format!("second apply rejected: {error}"),
+        ),
+    })
+}
+
+fn check_line_endings(
+    copy: &Path,
+    source: &str,
+    registry: &ProviderRegistry,
+) -> Result<SelftestCheck, IdenteditError> {
+    let lf_source = source
+        .strip_prefix(UTF8_BOM)
+        .unwrap_or(source)
+        .replace("\r\n", "\n");
+    let variant = format!("{UTF8_BOM}{}", lf_source.replace('\n', "\r\n"));
+    let lines = show_hashed_lines(&variant);
+    // Line 1 carries the BOM, so edit a later line when there is one.
+    let Some(line) = lines.get(lines.len() / 2).filter(|_| lines.len() > 1) else {
+        return Ok(skipped(
+            LINE_ENDING_ROUNDTRIP,
+            "needs at least two lines".to_string(),
+        ));
+    };
+    let anchor = format_line_ref(line.line, &line.hash);
+
+    write_scratch(copy, &variant)?;
+    let set_line = |new_text: String| HashlineEdit::SetLine {
+        set_line: SetLineEdit {
+            anchor: anchor.clone(),
+            new_text,
+        },
+    };
+    if let Err(error) = execute_hashline_patch(
+        copy.to_path_buf(),
+        vec![set_line(line.content.clone()).into()],
+        false,
+        &ApplySettings::default(),
+    ) {
+        return Ok(failed(
+            LINE_ENDING_ROUNDTRIP,
+            format!("no-op line edit failed: {error}"),
+        ));
+    }
+    if read_scratch(copy)? != variant {
+        return Ok(failed(
+            LINE_ENDING_ROUNDTRIP,
+            "no-op line edit changed the bytes".to_string(),
+        ));
+    }
+
+    let new_line = altered_text(&line.content);
+    if let Err(error) = execute_hashline_patch(
+        copy.to_path_buf(),
+        vec![set_line(new_line.clone()).into()],
+        false,
+        &ApplySettings::default(),
+    ) {
+        return Ok(failed(
+            LINE_ENDING_ROUNDTRIP,
+            format!("line edit failed: {error}"),
+        ));
+    }
+    let mut expected_lines = lf_source.split('\n').collect::<Vec<_>>();
+    expected_lines[line.line - 1] = &new_line;
+    let expected = format!("{UTF8_BOM}{}", expected_lines.join("\r\n"));
+    if read_scratch(copy)? != expected {
+        return Ok(failed(
+            LINE_ENDING_ROUNDTRIP,
+            format!("line {} edit did not keep CRLF and BOM", line.line),
+        ));
+    }
+
+    write_scratch(copy, &variant)?;
+    let handle = match parse_handles_for_file(copy, registry) {
+        Ok(handles) => handles
+            .into_iter()
+            .find(|handle| handle.span.start < handle.span.end),
+        Err(_) => None,
+    };
+    let Some(handle) = handle else {
+        return Ok(passed(
+            LINE_ENDING_ROUNDTRIP,
+            "line edits only; no structural nodes".to_string(),
+        ));
+    };
+    let new_text = altered_text(&handle.text);
+    let expected = splice(&variant, &handle, &new_text);
+    if let Err(error) = build_node_plan(copy, &handle, new_text, registry)
+        .and_then(|plan| apply_multi_file_changeset(&plan))
+    {
+        return Ok(failed(
+            LINE_ENDING_ROUNDTRIP,
+            format!("{}: {error}", describe_handle(&handle)),
+        ));
+    }
+    if read_scratch(copy)? != expected {
+        return Ok(failed(
+            LINE_ENDING_ROUNDTRIP,
+            format!(
+                "{} edit did not keep CRLF and BOM",
+                describe_handle(&handle)
+            ),
+        ));
+    }
+    Ok(passed(
+        LINE_ENDING_ROUNDTRIP,
+        "CRLF and BOM preserved".to_string(),
+    ))
+}
+
+fn build_node_plan(
+    copy: &Path,
+    handle: &SelectionHandle,
+    new_text: String,
+    registry: &ProviderRegistry,
+) -> Result<MultiFileChangeset, IdenteditError> {
+    let file_change = build_changeset(
+        copy,
+        vec![TransformInstruction {
+            target: TransformTarget::node(
+                handle.identity.clone(),
+                handle.kind.clone(),
+                Some(handle.span),
+                handle.expected_old_hash.clone(),
+            ),
+            op: OpKind::Replace { new_text },
+        }],
+        registry,
+    )?;
+    Ok(MultiFileChangeset {
+        files: vec![file_change],
+        transaction: Default::default(),
+        correlation_id: None,
+    })
+}
+
+/// Picks up to `max_nodes` handles spread evenly over the file. Zero-length handles mark
+/// positions rather than text and cannot be edit targets, so they are left out.
+fn sample_handles(handles: &[SelectionHandle], max_nodes: usize) -> Vec<&SelectionHandle> {
+    let targetable = handles
+        .iter()
+        .filter(|handle| handle.span.start < handle.span.end)
+        .collect::<Vec<_>>();
+    if targetable.len() <= max_nodes {
+        return targetable;
+    }
+    (0..max_nodes)
+        .map(|step| targetable[step * targetable.len() / max_nodes])
+        .collect()
+}
+
+/// A different text of the same shape, so spans and line endings stay comparable.
+fn altered_text(text: &str) -> String {
+    let upper = text.to_ascii_uppercase();
+    if upper == text {
+        format!("{text}_")
+    } else {
+        upper
+    }
+}
+
+fn splice(source: &str, handle: &SelectionHandle, new_text: &str) -> String {
+    format!(
+        "{}{new_text}{}",
+        &source[..handle.span.start],
+        &source[handle.span.end..]
+    )
+}
+
+fn describe_handle(handle: &SelectionHandle) -> String {
+    format!(
+        "{} at [{}, {})",
+        handle.kind, handle.span.start, handle.span.end
+    )
+}
+
+fn write_scratch(copy: &Path, text: &str) -> Result<(), IdenteditError> {
+    fs::write(copy, text).map_err(|error| IdenteditError::io(copy, error))
+}
+
+fn read_scratch(copy: &Path) -> Result<String, IdenteditError> {
+    fs::read_to_string(copy).map_err(|error| IdenteditError::io(copy, error))
+}
+
+fn passed(name: &'static str, detail: String) -> SelftestCheck {
+    SelftestCheck {
+        name,
+        status: SelftestStatus::Passed,
+        detail,
+    }
+}
+
+fn failed(name: &'static str, detail: String) -> SelftestCheck {
+    SelftestCheck {
+        name,
+        status: SelftestStatus::Failed,
+        detail,
+    }
+}
+
+fn skipped(name: &'static str, detail: String) -> SelftestCheck {
+    SelftestCheck {
+        name,
+        status: SelftestStatus::Skipped,
+        detail,
+    }
+}
@@ -0,0 +1,40 @@
+use clap::{Args, ValueEnum};
+
+use crate::error::IdenteditError;
+use crate::stats::{Stats, load_stats, render_prometheus, reset_stats};
+
+#[derive(Debug, Args)]
+pub struct StatsArgs {
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = StatsFormat::Json,
+        help = "Output format: json, or prometheus for the Prometheus text format"
+    )]
+    pub format: StatsFormat,
+    #[arg(long, help = "Clear the counters after printing them")]
+    pub reset: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StatsFormat {
+    Json,
+    Prometheus,
+}
+
+pub enum StatsCommandOutput {
+    Json(Stats),
+    Prometheus(String),
+}
+
+pub fn run_stats(args: StatsArgs) -> Result<StatsCommandOutput, IdenteditError> {
+    let stats = if args.reset {
+        reset_stats()?
+    } else {
+        load_stats()?
+    };
+    Ok(match args.format {
+        StatsFormat::Json => StatsCommandOutput::Json(stats),
+        StatsFormat::Prometheus => StatsCommandOutput::Prometheus(render_prometheus(&stats)),
+    })
+}
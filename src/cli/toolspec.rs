@@ -0,0 +1,199 @@
+//! `identedit toolspec`: agent tool definitions generated from the clap command tree, so
+//! harnesses never hand-maintain argument lists that drift from the binary.
+//!
+//! Each leaf command becomes one tool named `identedit_<command>[_<subcommand>]`. A flag
+//! property is the long flag with `_` for `-` (`dry_run` is passed as `--dry-run`),
+//! positional arguments (`input`, `files`, ...) follow in declaration order, and `stdin`,
+//! offered by commands with a `--json` mode, is piped to standard input.
+
+use clap::builder::ValueParser;
+use clap::{Arg, ArgAction, Args, Command, CommandFactory, ValueEnum, value_parser};
+use serde_json::{Map, Value, json};
+
+use super::Cli;
+
+/// Commands that only matter to a human at a shell, not to an agent.
+const EXCLUDED_COMMANDS: &[&str] = &["completions", "toolspec", "help"];
+
+#[derive(Debug, Args)]
+pub struct ToolspecArgs {
+    #[arg(
+        long,
+        value_enum,
+        help = "Tool definition dialect: openai (function tools) or anthropic (input_schema)"
+    )]
+    pub format: ToolspecFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum ToolspecFormat {
+    Openai,
+    Anthropic,
+}
+
+pub fn run_toolspec(args: ToolspecArgs) -> Value {
+    let mut tools = Vec::new();
+    collect_tools(&Cli::command(), "identedit", &mut tools);
+    Value::Array(
+        tools
+            .into_iter()
+            .map(|tool| render_tool(tool, args.format))
+            .collect(),
+    )
+}
+
+struct Tool {
+    name: String,
+    description: String,
+    parameters: Value,
+}
+
+fn collect_tools(command: &Command, prefix: &str, tools: &mut Vec<Tool>) {
+    for subcommand in command.get_subcommands() {
+        if subcommand.is_hide_set() || EXCLUDED_COMMANDS.contains(&subcommand.get_name()) {
+            continue;
+        }
+        let name = format!("{prefix}_{}", subcommand.get_name().replace('-', "_"));
+        if subcommand.has_subcommands() {
+            collect_tools(subcommand, &name, tools);
+            continue;
+        }
+        tools.push(Tool {
+            name,
+            description: subcommand
+                .get_about()
+                .map(ToString::to_string)
+                .unwrap_or_default(),
+            parameters: parameters_schema(subcommand),
+        });
+    }
+}
+
+fn render_tool(tool: Tool, format: ToolspecFormat) -> Value {
+    match format {
+        ToolspecFormat::Openai => json!({
+            "type": "function",
+            "function": {
+                "name": tool.name,
+                "description": tool.description,
+                "parameters": tool.parameters,
+            }
+        }),
+        ToolspecFormat::Anthropic => json!({
+            "name": tool.name,
+            "description": tool.description,
+            "input_schema": tool.parameters,
+        }),
+    }
+}
+
+fn parameters_schema(command: &Command) -> Value {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for arg in command.get_arguments() {
+        let Some(schema) = argument_schema(arg) else {
+            continue;
+        };
+        let id = match arg.get_long() {
+            Some(long) => long.replace('-', "_"),
+            None => arg.get_id().as_str().to_string(),
+        };
+        if arg.is_required_set() {
+            required.push(Value::String(id.clone()));
+        }
+        properties.insert(id, schema);
+    }
+    if properties.contains_key("json") {
+        properties.insert(
+            "stdin".to_string(),
+            json!({
+                "type": "string",
+                "description": "Text piped to standard input: the JSON request when `json` is set",
+            }),
+        );
+    }
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+fn argument_schema(arg: &Arg) -> Option<Value> {
+    if arg.is_hide_set() {
+        return None;
+    }
+    let mut schema = match arg.get_action() {
+        ArgAction::Help | ArgAction::HelpShort | ArgAction::HelpLong | ArgAction::Version => {
+            return None;
+        }
+        ArgAction::SetTrue | ArgAction::SetFalse => object(json!({"type": "boolean"})),
+        ArgAction::Count => object(json!({"type": "integer", "minimum": 0})),
+        _ => value_schema(arg),
+    };
+    let takes_many = matches!(arg.get_action(), ArgAction::Append)
+        || arg
+            .get_num_args()
+            .is_some_and(|range| range.max_values() > 1);
+    if takes_many {
+        schema = object(json!({"type": "array", "items": schema}));
+    }
+
+    let mut description = arg.get_help().map(ToString::to_string).unwrap_or_default();
+    if arg.is_positional() {
+        description = format!("{description} (positional)")
+            .trim_start()
+            .to_string();
+    }
+    schema.insert("description".to_string(), Value::String(description));
+    if let [default] = arg.get_default_values()
+        && !matches!(arg.get_action(), ArgAction::SetTrue | ArgAction::SetFalse)
+    {
+        let default = default.to_string_lossy();
+        let default = match default.parse::<u64>() {
+            Ok(number) if schema["type"] == "integer" => Value::from(number),
+            _ => Value::String(default.into_owned()),
+        };
+        schema.insert("default".to_string(), default);
+    }
+    Some(Value::Object(schema))
+}
+
+fn value_schema(arg: &Arg) -> Map<String, Value> {
+    let choices: Vec<Value> = arg
+        .get_possible_values()
+        .into_iter()
+        .filter(|value| !value.is_hide_set())
+        .map(|value| Value::String(value.get_name().to_string()))
+        .collect();
+    if !choices.is_empty() {
+        return object(json!({"type": "string", "enum": choices}));
+    }
+    if is_integer_parser(arg.get_value_parser()) {
+        return object(json!({"type": "integer", "minimum": 0}));
+    }
+    if arg.get_value_parser().type_id() == ValueParser::from(value_parser!(isize)).type_id() {
+        return object(json!({"type": "integer"}));
+    }
+    object(json!({"type": "string"}))
+}
+
+fn object(value: Value) -> Map<String, Value> {
+    match value {
+        Value::Object(map) => map,
+        _ => Map::new(),
+    }
+}
+
+fn is_integer_parser(parser: &ValueParser) -> bool {
+    let integer_parsers: [ValueParser; 3] = [
+        value_parser!(usize).into(),
+        value_parser!(u64).into(),
+        value_parser!(u32).into(),
+    ];
+    integer_parsers
+        .iter()
+        .any(|integer| integer.type_id() == parser.type_id())
+}
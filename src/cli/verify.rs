@@ -0,0 +1,33 @@
+//! `identedit verify`: checks that a plan still applies, operation by operation, so an
+//! orchestrator can regenerate it before the apply window instead of failing inside it.
+
+use std::path::PathBuf;
+
+use clap::Args;
+
+use crate::apply::{ApplySettings, VerifyResponse, verify_multi_file_changeset};
+use crate::error::IdenteditError;
+
+use super::apply::{read_changeset_from_file, read_changeset_from_stdin};
+
+#[derive(Debug, Args)]
+pub struct VerifyArgs {
+    #[arg(
+        value_name = "PLAN",
+        help = "Path to edit-plan JSON, optionally gzip or zstd compressed; if omitted, read raw plan JSON from stdin"
+    )]
+    pub input: Option<PathBuf>,
+}
+
+pub fn run_verify(
+    args: VerifyArgs,
+    settings: ApplySettings,
+) -> Result<VerifyResponse, IdenteditError> {
+    let mut changeset = match args.input {
+        Some(input_path) => read_changeset_from_file(&input_path)?,
+        None => read_changeset_from_stdin()?,
+    };
+    changeset.transaction.settings = settings;
+    verify_multi_file_changeset(&changeset)
+        .map_err(|error| error.with_correlation_id(changeset.correlation_id.as_deref()))
+}
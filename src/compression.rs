@@ -0,0 +1,138 @@
+//! Compressed plans and responses, for payloads too large to pass around as plain JSON.
+//!
+//! Plan input, from a file or stdin, may be gzip or zstd compressed; the format is told
+//! apart by its magic bytes, so no flag is needed. `--accept-encoding` compresses the
+//! response instead and wraps it as `{"encoding": "gzip", "body": "<base64>"}`, keeping
+//! stdout a single JSON document.
+
+use std::io::{Read, Write};
+use std::path::Path;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use clap::ValueEnum;
+use flate2::Compression;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use ruzstd::decoding::StreamingDecoder;
+use ruzstd::encoding::{CompressionLevel, compress_to_vec};
+use serde::Serialize;
+
+use crate::error::IdenteditError;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum ContentEncoding {
+    Gzip,
+    Zstd,
+}
+
+#[derive(Debug, Serialize)]
+struct EncodedResponse {
+    encoding: ContentEncoding,
+    body: String,
+}
+
+/// Reads the plan at `path`, decompressing it when it is gzip or zstd compressed.
+pub fn read_plan_file(path: &Path) -> Result<String, IdenteditError> {
+    let bytes = std::fs::read(path).map_err(|error| IdenteditError::io(path, error))?;
+    String::from_utf8(decompress(bytes)?).map_err(|_| IdenteditError::io(path, not_utf8()))
+}
+
+/// Reads a plan from stdin, decompressing it when it is gzip or zstd compressed.
+pub fn read_plan_stdin() -> Result<String, IdenteditError> {
+    let mut bytes = Vec::new();
+    std::io::stdin()
+        .read_to_end(&mut bytes)
+        .map_err(|source| IdenteditError::StdinRead { source })?;
+    String::from_utf8(decompress(bytes)?)
+        .map_err(|_| IdenteditError::StdinRead { source: not_utf8() })
+}
+
+/// `bytes` decompressed when they start with a gzip or zstd magic, and as-is otherwise.
+pub fn decompress(bytes: Vec<u8>) -> Result<Vec<u8>, IdenteditError> {
+    if bytes.starts_with(&GZIP_MAGIC) {
+        let mut decoded = Vec::new();
+        MultiGzDecoder::new(bytes.as_slice())
+            .read_to_end(&mut decoded)
+            .map_err(|error| IdenteditError::InvalidRequest {
+                message: format!("Plan input is not valid gzip: {error}"),
+            })?;
+        Ok(decoded)
+    } else if bytes.starts_with(&ZSTD_MAGIC) {
+        let mut decoded = Vec::new();
+        StreamingDecoder::new(bytes.as_slice())
+            .map_err(|error| error.to_string())
+            .and_then(|mut decoder| {
+                decoder
+                    .read_to_end(&mut decoded)
+                    .map_err(|error| error.to_string())
+            })
+            .map_err(|error| IdenteditError::InvalidRequest {
+                message: format!("Plan input is not valid zstd: {error}"),
+            })?;
+        Ok(decoded)
+    } else {
+        Ok(bytes)
+    }
+}
+
+/// The error `read_to_string` reports for non-UTF-8 input.
+fn not_utf8() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        "stream did not contain valid UTF-8",
+    )
+}
+
+/// `output` compressed with `encoding` and wrapped in its JSON envelope.
+pub fn encode_response(output: &str, encoding: ContentEncoding) -> String {
+    let compressed = match encoding {
+        ContentEncoding::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder
+                .write_all(output.as_bytes())
+                .and_then(|()| encoder.finish())
+                .expect("compressing into memory should not fail")
+        }
+        ContentEncoding::Zstd => compress_to_vec(output.as_bytes(), CompressionLevel::Fastest),
+    };
+    let response = EncodedResponse {
+        encoding,
+        body: STANDARD.encode(compressed),
+    };
+    serde_json::to_string_pretty(&response).expect("encoded response should serialize")
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+    use serde_json::Value;
+
+    use super::{ContentEncoding, decompress, encode_response};
+
+    #[test]
+    fn encoded_responses_decode_back_to_the_output() {
+        let output = "{\"files\": []}";
+        for encoding in [ContentEncoding::Gzip, ContentEncoding::Zstd] {
+            let envelope: Value = serde_json::from_str(&encode_response(output, encoding))
+                .expect("envelope should be JSON");
+            let body = STANDARD
+                .decode(envelope["body"].as_str().expect("body should be a string"))
+                .expect("body should be base64");
+            assert_eq!(
+                decompress(body).expect("body should decompress"),
+                output.as_bytes()
+            );
+        }
+        assert_eq!(
+            decompress(output.as_bytes().to_vec()).expect("plain input passes through"),
+            output.as_bytes()
+        );
+    }
+}
@@ -4,6 +4,8 @@ use miette::Diagnostic;
 use serde::Serialize;
 use thiserror::Error;
 
+use crate::lease::Lease;
+
 #[derive(Debug, Error, Diagnostic)]
 pub enum IdenteditError {
     #[error("Failed to read file '{path}': {source}")]
@@ -37,9 +39,27 @@ pub enum IdenteditError {
     #[error("File '{path}' is busy: another apply operation is in progress")]
     ResourceBusy { path: String },
 
+    #[error(
+        "File '{path}' is leased by session '{}' until {} (Unix seconds)",
+        .lease.session,
+        .lease.expires_at
+    )]
+    LockHeldByOtherSession { path: String, lease: Lease },
+
+    #[error("Directory scan did not finish within --timeout-ms {timeout_ms}")]
+    TimedOut { timeout_ms: u64 },
+
     #[error("File '{path}' changed during apply; retry with a fresh selection")]
     PathChanged { path: String },
 
+    #[error("File '{path}' is not writable: {reason}")]
+    ReadOnlyTarget { path: String, reason: String },
+
+    #[error(
+        "File '{path}' has {links} hard links; rewriting it would detach this path from the others"
+    )]
+    HardLinkedTarget { path: String, links: u64 },
+
     #[error("No structure provider available for extension '{extension}'")]
     NoProvider {
         extension: String,
@@ -52,6 +72,15 @@ pub enum IdenteditError {
     #[error("Grammar install failed: {message}")]
     GrammarInstall { message: String },
 
+    #[error("Codemod '{program}' failed: {message}")]
+    CodemodFailed { program: String, message: String },
+
+    #[error("Script '{script}' failed: {message}")]
+    ScriptFailed { script: String, message: String },
+
+    #[error("Preflight command '{program}' refused the write: {message}")]
+    PreflightFailed { program: String, message: String },
+
     #[error("Provider '{provider}' failed to parse input: {message}")]
     ParseFailure {
         provider: &'static str,
@@ -81,8 +110,38 @@ pub enum IdenteditError {
         actual_hash: String,
     },
 
+    #[error(
+        "The edit would modify protected lines {start_line}-{end_line} of '{file}', protected by {origin}"
+    )]
+    ProtectedRegion {
+        file: String,
+        start_line: usize,
+        end_line: usize,
+        origin: String,
+    },
+
+    #[error("Refusing a destructive change to '{file}': {message}")]
+    DestructiveChange { file: String, message: String },
+
+    #[error("The text written to '{file}' contains what looks like {kind}")]
+    PotentialSecret { file: String, kind: &'static str },
+
     #[error("Commit failed and rollback did not fully succeed: {message}")]
     RollbackFailed { message: String },
+
+    /// `error` of a request that carried `correlation_id`, which its response echoes.
+    #[error("{error}")]
+    Correlated {
+        correlation_id: String,
+        error: Box<IdenteditError>,
+    },
+
+    /// `error` of a `--retry-on-stale` patch that gave up, after the tries in `attempts`.
+    #[error("{error}")]
+    StaleRetries {
+        attempts: Vec<serde_json::Value>,
+        error: Box<IdenteditError>,
+    },
 }
 
 impl IdenteditError {
@@ -93,8 +152,43 @@ impl IdenteditError {
         }
     }
 
+    /// Tags the error with the `correlation_id` of the request it failed, when there was one.
+    pub fn with_correlation_id(self, correlation_id: Option<&str>) -> Self {
+        match correlation_id {
+            Some(correlation_id) if !matches!(self, Self::Correlated { .. }) => Self::Correlated {
+                correlation_id: correlation_id.to_string(),
+                error: Box::new(self),
+            },
+            _ => self,
+        }
+    }
+
+    /// The error response as printed for a failed command: tagged with the `correlation_id`
+    /// of the request being run, when it had one.
+    pub fn to_correlated_error_response(&self) -> CorrelatedErrorResponse {
+        match self {
+            Self::Correlated {
+                correlation_id,
+                error,
+            } => CorrelatedErrorResponse {
+                response: error.to_error_response(),
+                correlation_id: Some(correlation_id.clone()),
+            },
+            _ => CorrelatedErrorResponse {
+                response: self.to_error_response(),
+                correlation_id: None,
+            },
+        }
+    }
+
     pub fn to_error_response(&self) -> ErrorResponse {
         match self {
+            Self::Correlated { error, .. } => error.to_error_response(),
+            Self::StaleRetries { attempts, error } => {
+                let mut response = error.to_error_response();
+                response.error.attempts = Some(attempts.clone());
+                response
+            }
             Self::NoProvider {
                 extension: _,
                 supported_extensions,
@@ -110,6 +204,8 @@ impl IdenteditError {
                             .collect::<Vec<_>>()
                             .join(", ")
                     )),
+                    lease: None,
+                    attempts: None,
                 },
             },
             Self::InvalidRequest { .. } | Self::InvalidJsonRequest { .. } => ErrorResponse {
@@ -117,6 +213,8 @@ impl IdenteditError {
                     r#type: "invalid_request".to_string(),
                     message: self.to_string(),
                     suggestion: None,
+                    lease: None,
+                    attempts: None,
                 },
             },
             Self::ResourceBusy { .. } => ErrorResponse {
@@ -126,6 +224,31 @@ impl IdenteditError {
                     suggestion: Some(
                         "Retry after the current apply operation completes".to_string(),
                     ),
+                    lease: None,
+                    attempts: None,
+                },
+            },
+            Self::LockHeldByOtherSession { lease, .. } => ErrorResponse {
+                error: ErrorBody {
+                    r#type: "lock_held_by_other_session".to_string(),
+                    message: self.to_string(),
+                    suggestion: Some(format!(
+                        "Wait for the lease to expire, or have session '{}' run 'identedit lease release'",
+                        lease.session
+                    )),
+                    lease: Some(Box::new(lease.clone())),
+                    attempts: None,
+                },
+            },
+            Self::TimedOut { .. } => ErrorResponse {
+                error: ErrorBody {
+                    r#type: "timeout".to_string(),
+                    message: self.to_string(),
+                    suggestion: Some(
+                        "Scan a narrower directory or raise --timeout-ms".to_string(),
+                    ),
+                    lease: None,
+                    attempts: None,
                 },
             },
             Self::PathChanged { .. } => ErrorResponse {
@@ -135,6 +258,30 @@ impl IdenteditError {
                     suggestion: Some(
                         "Re-run 'identedit select' and 'identedit transform', then retry apply".to_string(),
                     ),
+                    lease: None,
+                    attempts: None,
+                },
+            },
+            Self::ReadOnlyTarget { .. } => ErrorResponse {
+                error: ErrorBody {
+                    r#type: "read_only_target".to_string(),
+                    message: self.to_string(),
+                    suggestion: Some(
+                        "Pass --chmod-writable to write read-only files anyway; immutable files need 'chattr -i' first".to_string(),
+                    ),
+                    lease: None,
+                    attempts: None,
+                },
+            },
+            Self::HardLinkedTarget { .. } => ErrorResponse {
+                error: ErrorBody {
+                    r#type: "hard_linked_target".to_string(),
+                    message: self.to_string(),
+                    suggestion: Some(
+                        "Pass --force-unlink to rewrite only this path; the other links keep the old contents".to_string(),
+                    ),
+                    lease: None,
+                    attempts: None,
                 },
             },
             Self::InvalidNamePattern { .. } => ErrorResponse {
@@ -142,6 +289,8 @@ impl IdenteditError {
                     r#type: "invalid_selector".to_string(),
                     message: self.to_string(),
                     suggestion: Some("Use a valid glob pattern such as 'process_*'".to_string()),
+                    lease: None,
+                    attempts: None,
                 },
             },
             Self::ParseFailure { .. } | Self::LanguageSetup { .. } => ErrorResponse {
@@ -149,6 +298,8 @@ impl IdenteditError {
                     r#type: "parse_failure".to_string(),
                     message: self.to_string(),
                     suggestion: None,
+                    lease: None,
+                    attempts: None,
                 },
             },
             Self::GrammarInstall { .. } => ErrorResponse {
@@ -156,6 +307,41 @@ impl IdenteditError {
                     r#type: "grammar_install_failed".to_string(),
                     message: self.to_string(),
                     suggestion: None,
+                    lease: None,
+                    attempts: None,
+                },
+            },
+            Self::CodemodFailed { .. } => ErrorResponse {
+                error: ErrorBody {
+                    r#type: "codemod_failed".to_string(),
+                    message: self.to_string(),
+                    suggestion: Some(
+                        "The codemod reads one JSON handle on stdin and must exit 0 with the replacement text on stdout".to_string(),
+                    ),
+                    lease: None,
+                    attempts: None,
+                },
+            },
+            Self::PreflightFailed { .. } => ErrorResponse {
+                error: ErrorBody {
+                    r#type: "preflight_failed".to_string(),
+                    message: self.to_string(),
+                    suggestion: Some(
+                        "Nothing was written; resolve what the preflight command reported, then retry".to_string(),
+                    ),
+                    lease: None,
+                    attempts: None,
+                },
+            },
+            Self::ScriptFailed { .. } => ErrorResponse {
+                error: ErrorBody {
+                    r#type: "script_failed".to_string(),
+                    message: self.to_string(),
+                    suggestion: Some(
+                        "The script must evaluate to the replacement string, or () to keep the text".to_string(),
+                    ),
+                    lease: None,
+                    attempts: None,
                 },
             },
             Self::Io { .. } | Self::StdinRead { .. } => ErrorResponse {
@@ -163,6 +349,8 @@ impl IdenteditError {
                     r#type: "io_error".to_string(),
                     message: self.to_string(),
                     suggestion: None,
+                    lease: None,
+                    attempts: None,
                 },
             },
             Self::ResponseSerialization { .. } => ErrorResponse {
@@ -170,6 +358,8 @@ impl IdenteditError {
                     r#type: "serialization_error".to_string(),
                     message: self.to_string(),
                     suggestion: None,
+                    lease: None,
+                    attempts: None,
                 },
             },
             Self::TargetMissing { .. } => ErrorResponse {
@@ -177,6 +367,8 @@ impl IdenteditError {
                     r#type: "target_missing".to_string(),
                     message: self.to_string(),
                     suggestion: Some("Re-run 'identedit select' to get updated handles".to_string()),
+                    lease: None,
+                    attempts: None,
                 },
             },
             Self::AmbiguousTarget { .. } => ErrorResponse {
@@ -186,6 +378,8 @@ impl IdenteditError {
                     suggestion: Some(
                         "Provide span_hint or refresh handles from 'identedit select'".to_string(),
                     ),
+                    lease: None,
+                    attempts: None,
                 },
             },
             Self::PreconditionFailed { .. } => ErrorResponse {
@@ -193,6 +387,41 @@ impl IdenteditError {
                     r#type: "precondition_failed".to_string(),
                     message: self.to_string(),
                     suggestion: Some("Re-run 'identedit select' to get updated handles".to_string()),
+                    lease: None,
+                    attempts: None,
+                },
+            },
+            Self::ProtectedRegion { .. } => ErrorResponse {
+                error: ErrorBody {
+                    r#type: "protected_region".to_string(),
+                    message: self.to_string(),
+                    suggestion: Some(
+                        "Edit outside the protected lines; inserting right before or after them is allowed".to_string(),
+                    ),
+                    lease: None,
+                    attempts: None,
+                },
+            },
+            Self::DestructiveChange { .. } => ErrorResponse {
+                error: ErrorBody {
+                    r#type: "destructive_change".to_string(),
+                    message: self.to_string(),
+                    suggestion: Some(
+                        "Check the new text for truncation; pass --force if the deletion is intended".to_string(),
+                    ),
+                    lease: None,
+                    attempts: None,
+                },
+            },
+            Self::PotentialSecret { .. } => ErrorResponse {
+                error: ErrorBody {
+                    r#type: "potential_secret".to_string(),
+                    message: self.to_string(),
+                    suggestion: Some(
+                        "Remove the credential from the new text; pass --allow-secrets if it is a known test value".to_string(),
+                    ),
+                    lease: None,
+                    attempts: None,
                 },
             },
             Self::RollbackFailed { .. } => ErrorResponse {
@@ -202,6 +431,8 @@ impl IdenteditError {
                     suggestion: Some(
                         "Inspect affected files, manually reconcile rollback failures, then re-run identedit select/transform/apply".to_string(),
                     ),
+                    lease: None,
+                    attempts: None,
                 },
             },
         }
@@ -214,11 +445,25 @@ pub struct ErrorResponse {
 }
 
 #[derive(Debug, Serialize)]
+pub struct CorrelatedErrorResponse {
+    #[serde(flatten)]
+    pub response: ErrorResponse,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct ErrorBody {
     pub r#type: String,
     pub message: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub suggestion: Option<String>,
+    /// The other session's lease, for `lock_held_by_other_session`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lease: Option<Box<Lease>>,
+    /// The tries of a `--retry-on-stale` patch that gave up.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attempts: Option<Vec<serde_json::Value>>,
 }
 
 #[cfg(test)]
@@ -350,6 +595,11 @@ mod tests {
             "resource_busy",
             Some("Retry after"),
         );
+        assert_error_type(
+            IdenteditError::TimedOut { timeout_ms: 50 },
+            "timeout",
+            Some("--timeout-ms"),
+        );
         assert_error_type(
             IdenteditError::PathChanged {
                 path: "fixture.py".to_string(),
@@ -359,6 +609,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn unwritable_targets_map_to_specific_types_with_opt_in_suggestions() {
+        assert_error_type(
+            IdenteditError::ReadOnlyTarget {
+                path: "fixture.py".to_string(),
+                reason: "read-only permissions".to_string(),
+            },
+            "read_only_target",
+            Some("--chmod-writable"),
+        );
+        assert_error_type(
+            IdenteditError::HardLinkedTarget {
+                path: "fixture.py".to_string(),
+                links: 2,
+            },
+            "hard_linked_target",
+            Some("--force-unlink"),
+        );
+    }
+
     #[test]
     fn rollback_failed_maps_to_dedicated_error_type_with_recovery_suggestion() {
         assert_error_type(
@@ -369,4 +639,17 @@ mod tests {
             Some("manually reconcile rollback failures"),
         );
     }
+
+    #[test]
+    fn correlated_errors_keep_their_type_and_echo_the_first_id() {
+        let error = IdenteditError::TimedOut { timeout_ms: 5 }
+            .with_correlation_id(Some("first"))
+            .with_correlation_id(Some("second"));
+        let response = error.to_correlated_error_response();
+        assert_eq!(response.response.error.r#type, "timeout");
+        assert_eq!(response.correlation_id.as_deref(), Some("first"));
+
+        let untagged = IdenteditError::TimedOut { timeout_ms: 5 }.with_correlation_id(None);
+        assert_eq!(untagged.to_correlated_error_response().correlation_id, None);
+    }
 }
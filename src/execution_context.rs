@@ -5,19 +5,17 @@ use crate::handle::SelectionHandle;
 use crate::provider::ProviderRegistry;
 
 /// Request-scoped execution context that owns shared runtime dependencies.
-pub(crate) struct ExecutionContext {
-    registry: ProviderRegistry,
+pub(crate) struct ExecutionContext<'a> {
+    registry: &'a ProviderRegistry,
 }
 
-impl ExecutionContext {
-    pub(crate) fn new() -> Self {
-        Self {
-            registry: ProviderRegistry::default(),
-        }
+impl<'a> ExecutionContext<'a> {
+    pub(crate) fn new(registry: &'a ProviderRegistry) -> Self {
+        Self { registry }
     }
 
-    pub(crate) fn registry(&self) -> &ProviderRegistry {
-        &self.registry
+    pub(crate) fn registry(&self) -> &'a ProviderRegistry {
+        self.registry
     }
 
     pub(crate) fn parse_handles_for_file(
@@ -51,9 +49,3 @@ impl ExecutionContext {
         })
     }
 }
-
-impl Default for ExecutionContext {
-    fn default() -> Self {
-        Self::new()
-    }
-}
@@ -1,11 +1,13 @@
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use libloading::Library;
 use serde::{Deserialize, Serialize};
+use tree_sitter_language::LanguageFn;
 
 use crate::error::IdenteditError;
 
@@ -17,6 +19,12 @@ pub struct InstalledGrammar {
     pub symbol: String,
     pub extensions: Vec<String>,
     pub library_path: PathBuf,
+    /// Grammar node kinds reported under another kind name, keyed by the node kind.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub kind_map: BTreeMap<String, String>,
+    /// The tree-sitter ABI the parser was generated for; absent for older manifest entries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub abi_version: Option<usize>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -24,18 +32,33 @@ pub struct GrammarInstallResponse {
     pub installed: InstalledGrammar,
 }
 
+/// What `grammar remove` and `grammar gc` removed, or would remove under `--dry-run`.
+#[derive(Debug, Clone, Serialize)]
+pub struct GrammarCleanupResponse {
+    pub dry_run: bool,
+    /// Languages whose manifest entries were dropped.
+    pub removed: Vec<String>,
+    /// Libraries, staged files, and install workspaces deleted.
+    pub deleted_paths: Vec<PathBuf>,
+}
+
 #[derive(Debug, Clone)]
 pub struct InstallGrammarRequest {
     pub lang: String,
     pub repo: Option<String>,
+    /// A local grammar checkout to build in place of cloning a repository.
+    pub path: Option<PathBuf>,
     pub symbol: Option<String>,
     pub extensions: Vec<String>,
+    /// `NODE=KIND` pairs, as passed to `--kind-map`.
+    pub kind_map: Vec<String>,
 }
 
 #[derive(Debug, Clone, Copy)]
 enum ResolutionSource {
     Builtin,
     Convention,
+    LocalPath,
 }
 
 #[derive(Debug, Clone)]
@@ -45,6 +68,13 @@ struct InstallResolution {
     repo_candidates: Vec<String>,
     symbol_candidates: Vec<String>,
     extensions: Vec<String>,
+    kind_map: BTreeMap<String, String>,
+}
+
+/// A compiled grammar whose symbol and ABI were checked, ready to be registered.
+struct BuiltGrammar {
+    symbol: String,
+    abi_version: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -59,6 +89,11 @@ struct BuiltinGrammar {
     extensions: &'static [&'static str],
 }
 
+const INSTALL_WORKSPACE_PREFIX: &str = "identedit-grammar-install-";
+
+/// Install workspaces older than this are left over from an install that was killed.
+const STALE_INSTALL_WORKSPACE_AGE: Duration = Duration::from_secs(60 * 60);
+
 const BUILTIN_GRAMMARS: &[BuiltinGrammar] = &[
     BuiltinGrammar {
         lang: "toml",
@@ -358,47 +393,38 @@ pub fn install_grammar(request: InstallGrammarRequest) -> Result<InstalledGramma
 
     for repo in &resolution.repo_candidates {
         let workspace = InstallWorkspace::new(&resolution.lang)?;
-        let source_dir = workspace.path().join("source");
         let build_output = workspace
             .path()
             .join(shared_library_filename(&resolution.lang));
-
-        if let Err(error) = clone_repo(repo, &source_dir) {
-            failures.push(format!("{repo}: {error}"));
-            continue;
-        }
-
-        if let Err(error) = compile_grammar_repository(&source_dir, &build_output) {
-            failures.push(format!("{repo}: {error}"));
-            continue;
-        }
-
-        let resolved_symbol = match resolve_symbol(&build_output, &resolution.symbol_candidates) {
-            Ok(symbol) => symbol,
-            Err(error) => {
+        let source_dir = if matches!(resolution.source, ResolutionSource::LocalPath) {
+            PathBuf::from(repo)
+        } else {
+            let source_dir = workspace.path().join("source");
+            if let Err(error) = clone_repo(repo, &source_dir) {
                 failures.push(format!("{repo}: {error}"));
                 continue;
             }
+            source_dir
         };
 
-        let installed_path = grammars_dir.join(shared_library_filename(&resolution.lang));
-        fs::copy(&build_output, &installed_path).map_err(|error| {
-            IdenteditError::GrammarInstall {
-                message: format!(
-                    "failed to copy compiled grammar to '{}': {error}",
-                    installed_path.display()
-                ),
+        let built = match build_grammar(&source_dir, &build_output, &resolution) {
+            Ok(built) => built,
+            Err(error) => {
+                failures.push(format!("{repo}: {error}"));
+                continue;
             }
-        })?;
+        };
 
         let installed = InstalledGrammar {
             lang: resolution.lang.clone(),
             repo: repo.clone(),
-            symbol: resolved_symbol,
+            symbol: built.symbol,
             extensions: resolution.extensions.clone(),
-            library_path: installed_path,
+            library_path: grammars_dir.join(shared_library_filename(&resolution.lang)),
+            kind_map: resolution.kind_map.clone(),
+            abi_version: Some(built.abi_version),
         };
-        upsert_manifest_entry(&installed)?;
+        register_grammar(&installed, &build_output)?;
         return Ok(installed);
     }
 
@@ -410,6 +436,9 @@ pub fn install_grammar(request: InstallGrammarRequest) -> Result<InstalledGramma
             "Convention fallback failed. Retry with --repo and --symbol for explicit source details."
                 .to_string()
         }
+        ResolutionSource::LocalPath => {
+            "Check that the directory holds a generated src/parser.c, or pass --symbol.".to_string()
+        }
     };
 
     Err(IdenteditError::GrammarInstall {
@@ -440,13 +469,193 @@ pub fn installed_grammars_for_runtime() -> Vec<InstalledGrammar> {
         .collect()
 }
 
+/// Drops the manifest entry for `lang` and deletes its library, along with staged files and
+/// install workspaces an interrupted install of it left behind.
+pub fn remove_grammar(lang: &str, dry_run: bool) -> Result<GrammarCleanupResponse, IdenteditError> {
+    let lang = normalize_language_name(lang)?;
+    let mut manifest = load_manifest()?;
+    let Some(position) = manifest
+        .grammars
+        .iter()
+        .position(|entry| entry.lang == lang)
+    else {
+        return Err(IdenteditError::InvalidRequest {
+            message: format!("no installed grammar named '{lang}'"),
+        });
+    };
+    let removed = manifest.grammars.remove(position);
+
+    let grammars_dir = grammars_dir()?;
+    let mut deleted_paths = Vec::new();
+    let still_referenced = manifest
+        .grammars
+        .iter()
+        .any(|entry| same_file(&entry.library_path, &removed.library_path));
+    if removed.library_path.is_file()
+        && !still_referenced
+        && is_inside(&removed.library_path, &grammars_dir)
+    {
+        deleted_paths.push(removed.library_path.clone());
+    }
+    deleted_paths.extend(staged_leftovers(&grammars_dir, |name| {
+        removed
+            .library_path
+            .file_name()
+            .is_some_and(|library| name.starts_with(&format!(".{}.", library.to_string_lossy())))
+    }));
+    deleted_paths.extend(stale_install_workspaces(Some(&lang)));
+
+    clean_up(&manifest, vec![removed.lang], deleted_paths, dry_run)
+}
+
+/// Drops manifest entries whose library is gone, and deletes libraries no entry refers to,
+/// staged files left by interrupted installs, and stale install workspaces.
+pub fn collect_grammar_garbage(dry_run: bool) -> Result<GrammarCleanupResponse, IdenteditError> {
+    let mut manifest = load_manifest()?;
+    let (kept, stale): (Vec<_>, Vec<_>) = manifest
+        .grammars
+        .into_iter()
+        .partition(|entry| entry.library_path.is_file());
+    manifest.grammars = kept;
+    let removed = stale.into_iter().map(|entry| entry.lang).collect();
+
+    let grammars_dir = grammars_dir()?;
+    let library_suffix = format!(".{}", shared_library_extension());
+    let mut deleted_paths = staged_leftovers(&grammars_dir, |_| true);
+    for path in directory_files(&grammars_dir) {
+        let is_library = path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().ends_with(&library_suffix));
+        if is_library
+            && !deleted_paths.contains(&path)
+            && !manifest
+                .grammars
+                .iter()
+                .any(|entry| same_file(&entry.library_path, &path))
+        {
+            deleted_paths.push(path);
+        }
+    }
+    deleted_paths.extend(stale_install_workspaces(None));
+
+    clean_up(&manifest, removed, deleted_paths, dry_run)
+}
+
+/// Writes `manifest` unless nothing changed, then deletes `deleted_paths`. The manifest
+/// goes first, so a library is never deleted while an entry still points at it.
+fn clean_up(
+    manifest: &GrammarManifest,
+    removed: Vec<String>,
+    mut deleted_paths: Vec<PathBuf>,
+    dry_run: bool,
+) -> Result<GrammarCleanupResponse, IdenteditError> {
+    deleted_paths.sort();
+    deleted_paths.dedup();
+    if !dry_run {
+        if !removed.is_empty() {
+            let staged_manifest = StagedFile::new(&manifest_path()?)?;
+            fs::write(staged_manifest.path(), serialize_manifest(manifest)?)
+                .map_err(|error| IdenteditError::io(staged_manifest.path(), error))?;
+            staged_manifest.commit()?;
+        }
+        for path in &deleted_paths {
+            let result = if path.is_dir() {
+                fs::remove_dir_all(path)
+            } else {
+                fs::remove_file(path)
+            };
+            result.map_err(|error| IdenteditError::io(path, error))?;
+        }
+    }
+
+    Ok(GrammarCleanupResponse {
+        dry_run,
+        removed,
+        deleted_paths,
+    })
+}
+
+fn directory_files(directory: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Files `StagedFile` left in `grammars_dir` whose name passes `matches`.
+fn staged_leftovers(grammars_dir: &Path, matches: impl Fn(&str) -> bool) -> Vec<PathBuf> {
+    directory_files(grammars_dir)
+        .into_iter()
+        .filter(|path| {
+            path.file_name().is_some_and(|name| {
+                let name = name.to_string_lossy();
+                name.starts_with('.') && name.ends_with(".tmp") && matches(&name)
+            })
+        })
+        .collect()
+}
+
+/// Install workspaces in the temp directory, for `lang` or for any language, that were last
+/// touched long enough ago that no install can still be using them.
+fn stale_install_workspaces(lang: Option<&str>) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(env::temp_dir()) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let Some(rest) = name.strip_prefix(INSTALL_WORKSPACE_PREFIX) else {
+                return false;
+            };
+            let Some((workspace_lang, nonce)) = rest.rsplit_once('-') else {
+                return false;
+            };
+            lang.is_none_or(|lang| workspace_lang == lang)
+                && !nonce.is_empty()
+                && nonce.bytes().all(|byte| byte.is_ascii_digit())
+        })
+        .filter(|entry| {
+            entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|age| age >= STALE_INSTALL_WORKSPACE_AGE)
+        })
+        .map(|entry| entry.path())
+        .collect()
+}
+
+fn same_file(left: &Path, right: &Path) -> bool {
+    match (fs::canonicalize(left), fs::canonicalize(right)) {
+        (Ok(left), Ok(right)) => left == right,
+        _ => left == right,
+    }
+}
+
+/// Whether `path` lies in `directory`; libraries a manifest entry points at elsewhere are
+/// not identedit's to delete.
+fn is_inside(path: &Path, directory: &Path) -> bool {
+    match (fs::canonicalize(path), fs::canonicalize(directory)) {
+        (Ok(path), Ok(directory)) => path.starts_with(directory),
+        _ => false,
+    }
+}
+
 fn resolve_install_request(
     request: &InstallGrammarRequest,
 ) -> Result<InstallResolution, IdenteditError> {
     let lang = normalize_language_name(&request.lang)?;
     let maybe_builtin = BUILTIN_GRAMMARS.iter().find(|entry| entry.lang == lang);
 
-    let source = if maybe_builtin.is_some() {
+    let source = if request.path.is_some() {
+        ResolutionSource::LocalPath
+    } else if maybe_builtin.is_some() {
         ResolutionSource::Builtin
     } else {
         ResolutionSource::Convention
@@ -472,7 +681,9 @@ fn resolve_install_request(
         normalize_extensions(&request.extensions)?
     };
 
-    let repo_candidates = if let Some(repo) = &request.repo {
+    let repo_candidates = if let Some(path) = &request.path {
+        vec![local_grammar_path(path)?]
+    } else if let Some(repo) = &request.repo {
         vec![repo.clone()]
     } else {
         default_repository_candidates(&lang)
@@ -490,9 +701,51 @@ fn resolve_install_request(
         repo_candidates,
         symbol_candidates,
         extensions,
+        kind_map: parse_kind_map(&request.kind_map)?,
     })
 }
 
+/// `path` made absolute, so the manifest records where the grammar was built from.
+fn local_grammar_path(path: &Path) -> Result<String, IdenteditError> {
+    if !path.is_dir() {
+        return Err(IdenteditError::InvalidRequest {
+            message: format!(
+                "--path '{}' is not a directory holding a grammar checkout",
+                path.display()
+            ),
+        });
+    }
+    let canonical = fs::canonicalize(path).map_err(|error| IdenteditError::io(path, error))?;
+    Ok(canonical.display().to_string())
+}
+
+fn parse_kind_map(values: &[String]) -> Result<BTreeMap<String, String>, IdenteditError> {
+    let mut kind_map = BTreeMap::new();
+
+    for value in values {
+        let Some((node_kind, kind)) = value
+            .split_once('=')
+            .map(|(node_kind, kind)| (node_kind.trim(), kind.trim()))
+            .filter(|(node_kind, kind)| !node_kind.is_empty() && !kind.is_empty())
+        else {
+            return Err(IdenteditError::InvalidRequest {
+                message: format!("--kind-map '{value}' must have the form NODE=KIND"),
+            });
+        };
+        if let Some(previous) = kind_map.insert(node_kind.to_string(), kind.to_string())
+            && previous != kind
+        {
+            return Err(IdenteditError::InvalidRequest {
+                message: format!(
+                    "--kind-map maps node kind '{node_kind}' to both '{previous}' and '{kind}'"
+                ),
+            });
+        }
+    }
+
+    Ok(kind_map)
+}
+
 fn normalize_language_name(value: &str) -> Result<String, IdenteditError> {
     let trimmed = value.trim();
     if trimmed.is_empty() {
@@ -545,7 +798,63 @@ fn default_symbol_candidates(lang: &str) -> Vec<String> {
     }
 }
 
-fn resolve_symbol(library_path: &Path, candidates: &[String]) -> Result<String, IdenteditError> {
+/// Compiles the grammar in `source_dir` to `output_path`, then checks that the library
+/// exports a language symbol whose ABI the bundled runtime can load and whose node kinds
+/// cover `resolution.kind_map`.
+fn build_grammar(
+    source_dir: &Path,
+    output_path: &Path,
+    resolution: &InstallResolution,
+) -> Result<BuiltGrammar, IdenteditError> {
+    compile_grammar_repository(source_dir, output_path)?;
+    let (library, symbol) = resolve_symbol(output_path, &resolution.symbol_candidates)?;
+    let language_fn = unsafe {
+        library
+            .get::<unsafe extern "C" fn() -> *const ()>(symbol.as_bytes())
+            .map(|symbol| *symbol)
+    }
+    .map_err(|error| IdenteditError::GrammarInstall {
+        message: format!("failed to load symbol '{symbol}': {error}"),
+    })?;
+    let language = tree_sitter::Language::new(unsafe { LanguageFn::from_raw(language_fn) });
+
+    let abi_version = language.abi_version();
+    check_abi_version(abi_version)?;
+    for node_kind in resolution.kind_map.keys() {
+        if language.id_for_node_kind(node_kind, true) == 0 {
+            return Err(IdenteditError::GrammarInstall {
+                message: format!(
+                    "--kind-map names node kind '{node_kind}', which the grammar does not define"
+                ),
+            });
+        }
+    }
+
+    Ok(BuiltGrammar {
+        symbol,
+        abi_version,
+    })
+}
+
+fn check_abi_version(abi_version: usize) -> Result<(), IdenteditError> {
+    let supported = tree_sitter::MIN_COMPATIBLE_LANGUAGE_VERSION..=tree_sitter::LANGUAGE_VERSION;
+    if supported.contains(&abi_version) {
+        return Ok(());
+    }
+
+    Err(IdenteditError::GrammarInstall {
+        message: format!(
+            "the parser was generated for tree-sitter ABI {abi_version}, but the bundled runtime loads ABI {} through {}; regenerate src/parser.c with a matching tree-sitter CLI",
+            supported.start(),
+            supported.end()
+        ),
+    })
+}
+
+fn resolve_symbol(
+    library_path: &Path,
+    candidates: &[String],
+) -> Result<(Library, String), IdenteditError> {
     let library =
         unsafe { Library::new(library_path) }.map_err(|error| IdenteditError::GrammarInstall {
             message: format!(
@@ -558,7 +867,7 @@ fn resolve_symbol(library_path: &Path, candidates: &[String]) -> Result<String,
         let symbol =
             unsafe { library.get::<unsafe extern "C" fn() -> *const ()>(candidate.as_bytes()) };
         if symbol.is_ok() {
-            return Ok(candidate.clone());
+            return Ok((library, candidate.clone()));
         }
     }
 
@@ -600,9 +909,14 @@ fn compile_grammar_repository(source_dir: &Path, output_path: &Path) -> Result<(
     let src_dir = source_dir.join("src");
     let parser_path = src_dir.join("parser.c");
     if !parser_path.is_file() {
+        let hint = if source_dir.join("grammar.js").is_file() {
+            "; run `tree-sitter generate` in it first"
+        } else {
+            ""
+        };
         return Err(IdenteditError::GrammarInstall {
             message: format!(
-                "grammar source '{}' does not contain src/parser.c",
+                "grammar source '{}' does not contain src/parser.c{hint}",
                 source_dir.display()
             ),
         });
@@ -706,27 +1020,39 @@ fn ensure_grammars_dir() -> Result<PathBuf, IdenteditError> {
     Ok(path)
 }
 
-fn upsert_manifest_entry(entry: &InstalledGrammar) -> Result<(), IdenteditError> {
-    let path = manifest_path()?;
-    let mut manifest = if path.is_file() {
-        let content =
-            fs::read_to_string(&path).map_err(|error| IdenteditError::GrammarInstall {
-                message: format!(
-                    "failed to read grammar manifest '{}': {error}",
-                    path.display()
-                ),
-            })?;
-        serde_json::from_str::<GrammarManifest>(&content).map_err(|error| {
-            IdenteditError::GrammarInstall {
-                message: format!(
-                    "failed to parse grammar manifest '{}': {error}",
-                    path.display()
-                ),
-            }
-        })?
-    } else {
-        GrammarManifest::default()
-    };
+/// Installs the library built at `build_output` and its manifest entry together. Both are
+/// staged next to their targets first, so a failure before the renames leaves the previous
+/// library and manifest in place, and a process still holding the old library open keeps a
+/// valid file.
+fn register_grammar(entry: &InstalledGrammar, build_output: &Path) -> Result<(), IdenteditError> {
+    let manifest = updated_manifest(entry)?;
+    let manifest_path = manifest_path()?;
+    let staged_library = StagedFile::new(&entry.library_path)?;
+    fs::copy(build_output, staged_library.path()).map_err(|error| {
+        IdenteditError::GrammarInstall {
+            message: format!(
+                "failed to copy compiled grammar to '{}': {error}",
+                staged_library.path().display()
+            ),
+        }
+    })?;
+    let staged_manifest = StagedFile::new(&manifest_path)?;
+    fs::write(staged_manifest.path(), manifest).map_err(|error| {
+        IdenteditError::GrammarInstall {
+            message: format!(
+                "failed to write grammar manifest '{}': {error}",
+                staged_manifest.path().display()
+            ),
+        }
+    })?;
+
+    staged_library.commit()?;
+    staged_manifest.commit()
+}
+
+/// The serialized manifest with `entry` added or replacing the entry for its language.
+fn updated_manifest(entry: &InstalledGrammar) -> Result<String, IdenteditError> {
+    let mut manifest = load_manifest()?;
 
     if let Some(position) = manifest
         .grammars
@@ -742,19 +1068,36 @@ fn upsert_manifest_entry(entry: &InstalledGrammar) -> Result<(), IdenteditError>
         .grammars
         .sort_by(|left, right| left.lang.cmp(&right.lang));
 
-    let serialized = serde_json::to_string_pretty(&manifest).map_err(|error| {
-        IdenteditError::GrammarInstall {
-            message: format!("failed to serialize grammar manifest: {error}"),
-        }
-    })?;
-    fs::write(&path, serialized).map_err(|error| IdenteditError::GrammarInstall {
+    serialize_manifest(&manifest)
+}
+
+/// The manifest on disk, or an empty one when none was written yet.
+fn load_manifest() -> Result<GrammarManifest, IdenteditError> {
+    let path = manifest_path()?;
+    if !path.is_file() {
+        return Ok(GrammarManifest::default());
+    }
+
+    let content = fs::read_to_string(&path).map_err(|error| IdenteditError::GrammarInstall {
         message: format!(
-            "failed to write grammar manifest '{}': {error}",
+            "failed to read grammar manifest '{}': {error}",
             path.display()
         ),
     })?;
+    serde_json::from_str::<GrammarManifest>(&content).map_err(|error| {
+        IdenteditError::GrammarInstall {
+            message: format!(
+                "failed to parse grammar manifest '{}': {error}",
+                path.display()
+            ),
+        }
+    })
+}
 
-    Ok(())
+fn serialize_manifest(manifest: &GrammarManifest) -> Result<String, IdenteditError> {
+    serde_json::to_string_pretty(manifest).map_err(|error| IdenteditError::GrammarInstall {
+        message: format!("failed to serialize grammar manifest: {error}"),
+    })
 }
 
 fn shared_library_filename(lang: &str) -> String {
@@ -844,7 +1187,7 @@ impl InstallWorkspace {
                 message: format!("system clock error while preparing install workspace: {error}"),
             })?
             .as_nanos();
-        let path = env::temp_dir().join(format!("identedit-grammar-install-{lang}-{nonce}"));
+        let path = env::temp_dir().join(format!("{INSTALL_WORKSPACE_PREFIX}{lang}-{nonce}"));
         fs::create_dir_all(&path).map_err(|error| IdenteditError::GrammarInstall {
             message: format!(
                 "failed to create install workspace '{}': {error}",
@@ -865,11 +1208,56 @@ impl Drop for InstallWorkspace {
     }
 }
 
+/// A temporary file next to `target` that replaces it on `commit` and is removed otherwise.
+struct StagedFile {
+    path: PathBuf,
+    target: PathBuf,
+}
+
+impl StagedFile {
+    fn new(target: &Path) -> Result<Self, IdenteditError> {
+        let nonce = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|error| IdenteditError::GrammarInstall {
+                message: format!(
+                    "system clock error while staging '{}': {error}",
+                    target.display()
+                ),
+            })?
+            .as_nanos();
+        let file_name = target
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(Self {
+            path: target.with_file_name(format!(".{file_name}.{nonce}.tmp")),
+            target: target.to_path_buf(),
+        })
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn commit(self) -> Result<(), IdenteditError> {
+        fs::rename(&self.path, &self.target).map_err(|error| IdenteditError::GrammarInstall {
+            message: format!("failed to install '{}': {error}", self.target.display()),
+        })
+    }
+}
+
+impl Drop for StagedFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{
-        InstallGrammarRequest, ResolutionSource, ensure_grammar_install_supported,
-        resolve_install_request, shared_library_extension,
+        InstallGrammarRequest, ResolutionSource, check_abi_version,
+        ensure_grammar_install_supported, parse_kind_map, resolve_install_request,
+        shared_library_extension,
     };
 
     #[test]
@@ -877,8 +1265,10 @@ mod tests {
         let request = InstallGrammarRequest {
             lang: "toml".to_string(),
             repo: None,
+            path: None,
             symbol: None,
             extensions: Vec::new(),
+            kind_map: Vec::new(),
         };
         let resolved =
             resolve_install_request(&request).expect("builtin resolution should succeed");
@@ -895,8 +1285,10 @@ mod tests {
         let request = InstallGrammarRequest {
             lang: "unknownlang".to_string(),
             repo: None,
+            path: None,
             symbol: None,
             extensions: Vec::new(),
+            kind_map: Vec::new(),
         };
         let error = resolve_install_request(&request)
             .expect_err("convention fallback without --ext should fail");
@@ -914,8 +1306,10 @@ mod tests {
         let request = InstallGrammarRequest {
             lang: "foo-bar".to_string(),
             repo: None,
+            path: None,
             symbol: None,
             extensions: vec!["foo".to_string()],
+            kind_map: Vec::new(),
         };
         let resolved = resolve_install_request(&request).expect("resolution should succeed");
 
@@ -936,6 +1330,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn kind_map_entries_must_be_pairs_without_conflicts() {
+        let parsed = parse_kind_map(&[
+            "function_declaration = function_definition".to_string(),
+            "function_declaration=function_definition".to_string(),
+        ])
+        .expect("repeated identical mappings should be accepted");
+        assert_eq!(
+            parsed.get("function_declaration").map(String::as_str),
+            Some("function_definition")
+        );
+
+        for invalid in [vec!["object"], vec!["=x"], vec!["a=b", "a=c"]] {
+            let values = invalid
+                .iter()
+                .map(|value| value.to_string())
+                .collect::<Vec<_>>();
+            assert!(
+                parse_kind_map(&values).is_err(),
+                "{invalid:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn abi_check_accepts_only_the_runtime_range() {
+        check_abi_version(tree_sitter::LANGUAGE_VERSION).expect("current ABI should load");
+        check_abi_version(tree_sitter::MIN_COMPATIBLE_LANGUAGE_VERSION)
+            .expect("oldest compatible ABI should load");
+        let error = check_abi_version(tree_sitter::LANGUAGE_VERSION + 1)
+            .expect_err("a newer ABI should be rejected");
+        assert!(error.to_string().contains("regenerate"), "{error}");
+        assert!(check_abi_version(tree_sitter::MIN_COMPATIBLE_LANGUAGE_VERSION - 1).is_err());
+    }
+
     #[cfg(any(target_os = "macos", target_os = "linux"))]
     #[test]
     fn grammar_install_support_check_allows_supported_hosts() {
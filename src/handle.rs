@@ -14,6 +14,10 @@ pub struct Span {
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SelectionHandle {
+    #[serde(
+        serialize_with = "crate::path_style::serialize_path",
+        deserialize_with = "crate::path_style::deserialize_path"
+    )]
     pub file: PathBuf,
     pub span: Span,
     pub kind: String,
@@ -46,6 +50,148 @@ impl SelectionHandle {
     }
 }
 
+/// A point in a file as editors and language servers report it: a 1-based line plus a
+/// 1-based column counted both in UTF-8 bytes and in UTF-16 code units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+    pub utf16_column: usize,
+}
+
+/// A `line`/`column` coordinate accepted in place of a byte offset. Give `column` (UTF-8
+/// bytes) or `utf16_column` (as language servers count), both 1-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PositionInput {
+    pub line: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub utf16_column: Option<usize>,
+}
+
+/// A `[start, end)` range in line/column coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PositionRange {
+    pub start: PositionInput,
+    pub end: PositionInput,
+}
+
+/// Line starts of one source, for converting between byte offsets and positions.
+///
+/// Lines end at `\n`, `\r\n`, or a lone `\r`, matching line-anchor numbering.
+#[derive(Debug, Clone)]
+pub struct LineIndex<'a> {
+    source: &'a [u8],
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(source: &'a [u8]) -> Self {
+        let mut line_starts = vec![0];
+        let mut index = 0;
+        while index < source.len() {
+            match source[index] {
+                b'\r' if source.get(index + 1) == Some(&b'\n') => index += 2,
+                b'\n' | b'\r' => index += 1,
+                _ => {
+                    index += 1;
+                    continue;
+                }
+            }
+            line_starts.push(index);
+        }
+        Self {
+            source,
+            line_starts,
+        }
+    }
+
+    pub fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.source.len());
+        let line_index = self.line_starts.partition_point(|start| *start <= offset) - 1;
+        let prefix = &self.source[self.line_starts[line_index]..offset];
+        Position {
+            line: line_index + 1,
+            column: prefix.len() + 1,
+            utf16_column: String::from_utf8_lossy(prefix).encode_utf16().count() + 1,
+        }
+    }
+
+    pub fn span_positions(&self, span: Span) -> (Position, Position) {
+        (self.position(span.start), self.position(span.end))
+    }
+
+    /// Byte offset of a position, or `None` when the line does not exist, the column runs
+    /// past the end of the line, or a UTF-8 column splits a character.
+    pub fn offset(&self, position: PositionInput) -> Option<usize> {
+        let line_start = *self.line_starts.get(position.line.checked_sub(1)?)?;
+        let content = &self.source[line_start..self.content_end(position.line - 1)];
+        let text = std::str::from_utf8(content).ok()?;
+        match (position.column, position.utf16_column) {
+            (Some(column), None) => {
+                let byte = column.checked_sub(1)?;
+                text.is_char_boundary(byte).then_some(line_start + byte)
+            }
+            (None, Some(utf16_column)) => {
+                let target = utf16_column.checked_sub(1)?;
+                let mut units = 0;
+                for (byte, character) in text.char_indices() {
+                    if units == target {
+                        return Some(line_start + byte);
+                    }
+                    units += character.len_utf16();
+                }
+                (units == target).then_some(line_start + content.len())
+            }
+            _ => None,
+        }
+    }
+
+    pub fn span(&self, range: PositionRange) -> Option<Span> {
+        Some(Span {
+            start: self.offset(range.start)?,
+            end: self.offset(range.end)?,
+        })
+    }
+
+    /// Number of lines; a trailing line terminator does not start another line.
+    pub fn line_count(&self) -> usize {
+        match self.line_starts.last() {
+            Some(&start) if start == self.source.len() && self.line_starts.len() > 1 => {
+                self.line_starts.len() - 1
+            }
+            _ => self.line_starts.len(),
+        }
+    }
+
+    /// The 1-based line without its terminator, lossily decoded.
+    pub fn line_text(&self, line: usize) -> Option<String> {
+        if line == 0 || line > self.line_count() {
+            return None;
+        }
+        let content = &self.source[self.line_starts[line - 1]..self.content_end(line - 1)];
+        Some(String::from_utf8_lossy(content).into_owned())
+    }
+
+    fn content_end(&self, line_index: usize) -> usize {
+        let next_start = self
+            .line_starts
+            .get(line_index + 1)
+            .copied()
+            .unwrap_or(self.source.len());
+        let line = &self.source[self.line_starts[line_index]..next_start];
+        let terminator = if line.ends_with(b"\r\n") {
+            2
+        } else {
+            usize::from(line.ends_with(b"\n") || line.ends_with(b"\r"))
+        };
+        next_start - terminator
+    }
+}
+
 pub fn compute_identity(kind: &str, name: Option<&str>, text: &str) -> String {
     let mut hasher = Hasher::new();
     hasher.update(kind.as_bytes());
@@ -65,7 +211,7 @@ pub fn compute_identity(kind: &str, name: Option<&str>, text: &str) -> String {
 mod tests {
     use std::path::PathBuf;
 
-    use super::{SelectionHandle, Span, compute_identity};
+    use super::{LineIndex, PositionInput, SelectionHandle, Span, compute_identity};
 
     #[test]
     fn compute_identity_is_deterministic() {
@@ -143,4 +289,47 @@ mod tests {
                 .all(|character| character.is_ascii_hexdigit())
         );
     }
+
+    #[test]
+    fn line_index_converts_offsets_and_positions_in_both_column_units() {
+        let source = "ab\r\n\u{e9}\u{1f600}x\rlast".as_bytes();
+        let index = LineIndex::new(source);
+
+        let emoji_end = index.position(10);
+        assert_eq!(
+            (emoji_end.line, emoji_end.column, emoji_end.utf16_column),
+            (2, 7, 4)
+        );
+        let last = index.position(source.len());
+        assert_eq!((last.line, last.column, last.utf16_column), (3, 5, 5));
+
+        let at = |line, column, utf16_column| PositionInput {
+            line,
+            column,
+            utf16_column,
+        };
+        assert_eq!(index.offset(at(2, None, Some(4))), Some(10));
+        assert_eq!(index.offset(at(2, Some(7), None)), Some(10));
+        assert_eq!(index.offset(at(1, Some(3), None)), Some(2));
+        assert_eq!(
+            index.offset(at(2, Some(2), None)),
+            None,
+            "splits a character"
+        );
+        assert_eq!(
+            index.offset(at(1, Some(4), None)),
+            None,
+            "past the line end"
+        );
+        assert_eq!(
+            index.offset(at(4, Some(1), None)),
+            None,
+            "past the last line"
+        );
+        assert_eq!(
+            index.offset(at(1, Some(1), Some(1))),
+            None,
+            "ambiguous units"
+        );
+    }
 }
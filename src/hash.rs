@@ -1,3 +1,4 @@
+pub const HASH_ALGORITHM: &str = "blake3";
 pub const HASH_HEX_LEN: usize = 16;
 
 pub fn hash_bytes(bytes: &[u8]) -> String {
@@ -9,6 +10,13 @@ pub fn hash_text(text: &str) -> String {
     hash_bytes(text.as_bytes())
 }
 
+/// Same digest as [`hash_bytes`] over everything `reader` yields, without buffering it all.
+pub fn hash_reader(reader: impl std::io::Read) -> std::io::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(reader)?;
+    Ok(shorten_hex(&hasher.finalize().to_hex()))
+}
+
 pub fn shorten_hex(full_hex: &str) -> String {
     let prefix_len = HASH_HEX_LEN.min(full_hex.len());
     full_hex[..prefix_len].to_string()
@@ -1,3 +1,5 @@
+use clap::ValueEnum;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -7,12 +9,45 @@ mod repair;
 mod show;
 
 pub const HASHLINE_PUBLIC_HEX_LEN: usize = 12;
+/// Hash length of [`HashlineAlgo::Blake3_24`], for files large enough that 12 hex chars
+/// start to collide.
+pub const HASHLINE_LONG_HEX_LEN: usize = 24;
 pub const HASHLINE_MIN_HEX_LEN: usize = HASHLINE_PUBLIC_HEX_LEN;
-pub const HASHLINE_MAX_HEX_LEN: usize = HASHLINE_PUBLIC_HEX_LEN;
+pub const HASHLINE_MAX_HEX_LEN: usize = HASHLINE_LONG_HEX_LEN;
 pub const HASHLINE_DEFAULT_HEX_LEN: usize = HASHLINE_PUBLIC_HEX_LEN;
 const HASHLINE_DISPLAY_MIN_HEX_LEN: usize = 8;
 const HASHLINE_DISPLAY_MAX_HEX_LEN: usize = 64;
 
+/// Line-hash algorithm: blake3 truncated to a fixed number of hex chars. An anchor's hash
+/// length says which algorithm made it, so anchors of both kinds can be checked together.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, ValueEnum,
+)]
+pub enum HashlineAlgo {
+    #[default]
+    #[serde(rename = "blake3-12")]
+    #[value(name = "blake3-12")]
+    Blake3_12,
+    #[serde(rename = "blake3-24")]
+    #[value(name = "blake3-24")]
+    Blake3_24,
+}
+
+impl HashlineAlgo {
+    pub fn hex_len(self) -> usize {
+        match self {
+            HashlineAlgo::Blake3_12 => HASHLINE_PUBLIC_HEX_LEN,
+            HashlineAlgo::Blake3_24 => HASHLINE_LONG_HEX_LEN,
+        }
+    }
+
+    pub fn from_hex_len(hex_len: usize) -> Option<Self> {
+        [HashlineAlgo::Blake3_12, HashlineAlgo::Blake3_24]
+            .into_iter()
+            .find(|algo| algo.hex_len() == hex_len)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct HashedLine {
     pub line: usize,
@@ -216,8 +251,12 @@ enum ResolvedOperation {
 }
 
 pub fn compute_line_hash(line: &str) -> String {
+    compute_line_hash_with(line, HashlineAlgo::default())
+}
+
+pub fn compute_line_hash_with(line: &str, algo: HashlineAlgo) -> String {
     let full_hex = compute_line_hash_full(line);
-    full_hex[..HASHLINE_DEFAULT_HEX_LEN].to_string()
+    full_hex[..algo.hex_len()].to_string()
 }
 
 pub fn format_line_ref(line: usize, hash: &str) -> String {
@@ -266,7 +305,11 @@ pub fn parse_line_ref(value: &str) -> Result<LineRef, HashlineCheckError> {
 }
 
 pub fn show_hashed_lines(source: &str) -> Vec<HashedLine> {
-    show::show_hashed_lines(source)
+    show::show_hashed_lines(source, HashlineAlgo::default())
+}
+
+pub fn show_hashed_lines_with(source: &str, algo: HashlineAlgo) -> Vec<HashedLine> {
+    show::show_hashed_lines(source, algo)
 }
 
 pub fn format_hashed_lines(source: &str) -> String {
@@ -300,6 +343,93 @@ pub fn check_hashline_refs(
     check::check_hashline_anchors(source, &anchors)
 }
 
+/// Resolves a landmark `anchor` plus a line `offset` to an exact anchor for the line that
+/// sits `offset` lines away. Only the landmark is verified: a stale landmark fails the
+/// precondition (repair never moves it), and the target line may hold anything.
+pub fn offset_line_ref(
+    source: &str,
+    anchor: &str,
+    offset: isize,
+) -> Result<String, HashlineApplyError> {
+    let check = check_hashline_refs(source, &[anchor.to_string()])?;
+    if !check.ok {
+        return Err(HashlineApplyError::PreconditionFailed { check });
+    }
+
+    let landmark = parse_line_ref(anchor)?;
+    let algo = HashlineAlgo::from_hex_len(landmark.hash.len()).unwrap_or_default();
+    let lines = show::show_hashed_lines(source, algo);
+    landmark
+        .line
+        .checked_add_signed(offset)
+        .and_then(|line| line.checked_sub(1))
+        .and_then(|index| lines.get(index))
+        .map(|line| format_line_ref(line.line, &line.hash))
+        .ok_or_else(|| {
+            HashlineCheckError::InvalidRequest {
+                message: format!(
+                    "Offset {offset:+} from anchor '{anchor}' falls outside lines 1..={}",
+                    lines.len()
+                ),
+            }
+            .into()
+        })
+}
+
+/// Finds the line matching `pattern` and returns its exact anchor. `occurrence` (1-based)
+/// picks among several matches; without it the pattern must match exactly one line. A set
+/// `expected_match_count` must equal the number of matching lines.
+pub fn match_line_ref(
+    source: &str,
+    pattern: &Regex,
+    occurrence: Option<usize>,
+    expected_match_count: Option<usize>,
+) -> Result<String, HashlineCheckError> {
+    let matches = show::show_hashed_lines(source, HashlineAlgo::default())
+        .into_iter()
+        .filter(|line| pattern.is_match(&line.content))
+        .collect::<Vec<_>>();
+    let matched_lines = || {
+        matches
+            .iter()
+            .map(|line| line.line.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let invalid = |message: String| HashlineCheckError::InvalidRequest { message };
+
+    if let Some(expected) = expected_match_count
+        && matches.len() != expected
+    {
+        return Err(invalid(format!(
+            "Line pattern /{pattern}/ matched {} line(s) but expected_match_count is {expected} (lines: {})",
+            matches.len(),
+            matched_lines()
+        )));
+    }
+    let line = match (occurrence, matches.as_slice()) {
+        (Some(0), _) => return Err(invalid("occurrence must be >= 1".to_string())),
+        (Some(occurrence), _) => matches.get(occurrence - 1).ok_or_else(|| {
+            invalid(format!(
+                "Line pattern /{pattern}/ matched {} line(s); occurrence {occurrence} does not exist",
+                matches.len()
+            ))
+        })?,
+        (None, [line]) => line,
+        (None, []) => {
+            return Err(invalid(format!("Line pattern /{pattern}/ matched no lines")));
+        }
+        (None, _) => {
+            return Err(invalid(format!(
+                "Line pattern /{pattern}/ matched {} lines ({}); set occurrence to pick one",
+                matches.len(),
+                matched_lines()
+            )));
+        }
+    };
+    Ok(format_line_ref(line.line, &line.hash))
+}
+
 pub fn apply_hashline_edits(
     source: &str,
     edits: &[HashlineEdit],
@@ -370,33 +500,13 @@ fn compute_line_hash_full(line: &str) -> String {
 }
 
 fn validate_hash_segment(anchor: &str, hash: &str) -> Result<(), HashlineCheckError> {
-    if HASHLINE_MIN_HEX_LEN == HASHLINE_MAX_HEX_LEN {
-        if hash.len() != HASHLINE_MIN_HEX_LEN {
-            return Err(HashlineCheckError::InvalidRequest {
-                message: format!(
-                    "Invalid hashline anchor '{}': hash must be exactly {} hex chars",
-                    anchor, HASHLINE_MIN_HEX_LEN
-                ),
-            });
-        }
-    } else {
-        if hash.len() < HASHLINE_MIN_HEX_LEN {
-            return Err(HashlineCheckError::InvalidRequest {
-                message: format!(
-                    "Invalid hashline anchor '{}': hash must be at least {} hex chars",
-                    anchor, HASHLINE_MIN_HEX_LEN
-                ),
-            });
-        }
-
-        if hash.len() > HASHLINE_MAX_HEX_LEN {
-            return Err(HashlineCheckError::InvalidRequest {
-                message: format!(
-                    "Invalid hashline anchor '{}': hash must be at most {} hex chars",
-                    anchor, HASHLINE_MAX_HEX_LEN
-                ),
-            });
-        }
+    if HashlineAlgo::from_hex_len(hash.len()).is_none() {
+        return Err(HashlineCheckError::InvalidRequest {
+            message: format!(
+                "Invalid hashline anchor '{}': hash must be exactly {} (blake3-12) or {} (blake3-24) hex chars",
+                anchor, HASHLINE_PUBLIC_HEX_LEN, HASHLINE_LONG_HEX_LEN
+            ),
+        });
     }
 
     if !hash.chars().all(|ch| ch.is_ascii_hexdigit()) {
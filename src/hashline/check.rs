@@ -1,26 +1,43 @@
 use std::collections::BTreeMap;
 
 use super::{
-    AnchorCheckRequest, HashlineCheckError, HashlineCheckResult, HashlineCheckSummary,
-    HashlineMismatch, HashlineMismatchStatus, HashlineRemapTarget, parse_line_ref,
+    AnchorCheckRequest, HashlineAlgo, HashlineCheckError, HashlineCheckResult,
+    HashlineCheckSummary, HashlineMismatch, HashlineMismatchStatus, HashlineRemapTarget,
+    parse_line_ref,
 };
 
+struct HashTables {
+    line_to_hash: BTreeMap<usize, String>,
+    hash_to_lines: BTreeMap<String, Vec<usize>>,
+}
+
+impl HashTables {
+    fn new(source: &str, algo: HashlineAlgo) -> Self {
+        let hashed_lines = super::show::show_hashed_lines(source, algo);
+        let line_to_hash = hashed_lines
+            .iter()
+            .map(|line| (line.line, line.hash.clone()))
+            .collect::<BTreeMap<_, _>>();
+        let hash_to_lines =
+            hashed_lines
+                .iter()
+                .fold(BTreeMap::<String, Vec<usize>>::new(), |mut acc, line| {
+                    acc.entry(line.hash.clone()).or_default().push(line.line);
+                    acc
+                });
+        Self {
+            line_to_hash,
+            hash_to_lines,
+        }
+    }
+}
+
 pub(super) fn check_hashline_anchors(
     source: &str,
     anchors: &[AnchorCheckRequest],
 ) -> Result<HashlineCheckResult, HashlineCheckError> {
-    let hashed_lines = super::show::show_hashed_lines(source);
-    let line_to_hash = hashed_lines
-        .iter()
-        .map(|line| (line.line, line.hash.clone()))
-        .collect::<BTreeMap<_, _>>();
-    let hash_to_lines =
-        hashed_lines
-            .iter()
-            .fold(BTreeMap::<String, Vec<usize>>::new(), |mut acc, line| {
-                acc.entry(line.hash.clone()).or_default().push(line.line);
-                acc
-            });
+    // Anchors of either algorithm can be mixed, so keep one table per hash length in use.
+    let mut tables = BTreeMap::<HashlineAlgo, HashTables>::new();
 
     let mut summary = HashlineCheckSummary::default();
     let mut mismatches = Vec::new();
@@ -29,6 +46,13 @@ pub(super) fn check_hashline_anchors(
         summary.total += 1;
 
         let parsed = parse_line_ref(&anchor_request.anchor)?;
+        let algo = HashlineAlgo::from_hex_len(parsed.hash.len()).unwrap_or_default();
+        let HashTables {
+            line_to_hash,
+            hash_to_lines,
+        } = tables
+            .entry(algo)
+            .or_insert_with(|| HashTables::new(source, algo));
         let actual_hash = line_to_hash.get(&parsed.line);
         if actual_hash.is_some_and(|actual| actual == &parsed.hash) {
             summary.matched += 1;
@@ -1,20 +1,20 @@
-use super::{HashedLine, compute_line_hash};
+use super::{HashedLine, HashlineAlgo, compute_line_hash_with};
 
-pub(super) fn show_hashed_lines(source: &str) -> Vec<HashedLine> {
+pub(super) fn show_hashed_lines(source: &str, algo: HashlineAlgo) -> Vec<HashedLine> {
     split_source_lines(source)
         .lines
         .into_iter()
         .enumerate()
         .map(|(index, content)| HashedLine {
             line: index + 1,
-            hash: compute_line_hash(&content),
+            hash: compute_line_hash_with(&content, algo),
             content,
         })
         .collect()
 }
 
 pub(super) fn format_hashed_lines(source: &str) -> String {
-    show_hashed_lines(source)
+    show_hashed_lines(source, HashlineAlgo::default())
         .into_iter()
         .map(|line| format!("{}:{}|{}", line.line, line.hash, line.content))
         .collect::<Vec<_>>()
@@ -1,8 +1,9 @@
 use super::{
-    HASHLINE_DEFAULT_HEX_LEN, HASHLINE_MIN_HEX_LEN, HashlineApplyError, HashlineApplyMode,
-    HashlineEdit, HashlineMismatchStatus, apply_hashline_edits, apply_hashline_edits_with_mode,
-    check_hashline_edits, check_hashline_refs, compute_line_hash, format_hashed_lines,
-    format_line_ref, parse_line_ref,
+    HASHLINE_DEFAULT_HEX_LEN, HASHLINE_MIN_HEX_LEN, HashlineAlgo, HashlineApplyError,
+    HashlineApplyMode, HashlineEdit, HashlineMismatchStatus, apply_hashline_edits,
+    apply_hashline_edits_with_mode, check_hashline_edits, check_hashline_refs, compute_line_hash,
+    compute_line_hash_with, format_hashed_lines, format_line_ref, match_line_ref, offset_line_ref,
+    parse_line_ref,
 };
 
 fn line_ref(source: &str, line: usize) -> String {
@@ -1664,3 +1665,87 @@ fn apply_mixed_newline_source_normalizes_to_lf() {
     let applied = apply_hashline_edits(source, &edits).expect("apply should succeed");
     assert_eq!(applied.content, "a\nB\nc\n");
 }
+
+#[test]
+fn offset_line_ref_points_at_lines_around_a_verified_landmark() {
+    let source = "header\ngenerated 1\nlandmark\ngenerated 2\n";
+    let landmark = line_ref(source, 3);
+
+    assert_eq!(
+        offset_line_ref(source, &landmark, -2).expect("offset should resolve"),
+        line_ref(source, 1)
+    );
+    assert_eq!(
+        offset_line_ref(source, &landmark, 1).expect("offset should resolve"),
+        line_ref(source, 4)
+    );
+
+    let error = offset_line_ref(source, &landmark, 2).expect_err("line 5 does not exist");
+    assert!(matches!(error, HashlineApplyError::Check(_)));
+    let error = offset_line_ref(source, &landmark, -3).expect_err("line 0 does not exist");
+    assert!(matches!(error, HashlineApplyError::Check(_)));
+
+    let moved = "inserted\nheader\ngenerated 1\nlandmark\ngenerated 2\n";
+    let error = offset_line_ref(moved, &landmark, -2).expect_err("stale landmark should fail");
+    let HashlineApplyError::PreconditionFailed { check } = error else {
+        panic!("expected precondition failure, got {error:?}");
+    };
+    assert_eq!(
+        check.mismatches[0].status,
+        HashlineMismatchStatus::Remappable
+    );
+}
+
+#[test]
+fn match_line_ref_resolves_occurrences_and_guards_the_match_count() {
+    let source = "version = 1\nname = \"a\"\nversion = 2\n";
+    let version = regex::Regex::new("^version").expect("pattern should compile");
+
+    assert_eq!(
+        match_line_ref(source, &version, Some(2), Some(2)).expect("second match should resolve"),
+        line_ref(source, 3)
+    );
+    let name = regex::Regex::new("^name").expect("pattern should compile");
+    assert_eq!(
+        match_line_ref(source, &name, None, None).expect("unique match should resolve"),
+        line_ref(source, 2)
+    );
+
+    for (occurrence, expected_match_count) in [(None, None), (Some(3), None), (Some(1), Some(3))] {
+        assert!(
+            match_line_ref(source, &version, occurrence, expected_match_count).is_err(),
+            "occurrence {occurrence:?} with count {expected_match_count:?} should be rejected"
+        );
+    }
+    let missing = regex::Regex::new("^license").expect("pattern should compile");
+    assert!(match_line_ref(source, &missing, None, None).is_err());
+}
+
+#[test]
+fn check_accepts_both_hash_lengths_and_remaps_long_anchors() {
+    let source = "alpha\nbeta\n";
+    let long = |line: usize, text: &str| {
+        format_line_ref(line, &compute_line_hash_with(text, HashlineAlgo::Blake3_24))
+    };
+    let check = check_hashline_refs(source, &[line_ref(source, 1), long(2, "beta")])
+        .expect("mixed-length anchors should parse");
+    assert!(check.ok);
+
+    let shifted = "header\nalpha\nbeta\n";
+    let check = check_hashline_refs(shifted, &[long(2, "beta")]).expect("anchor should parse");
+    assert_eq!(
+        check.mismatches[0].status,
+        HashlineMismatchStatus::Remappable
+    );
+    assert_eq!(check.mismatches[0].remaps[0].line, 3);
+    assert_eq!(
+        check.mismatches[0].actual_hash.as_deref().map(str::len),
+        Some(HashlineAlgo::Blake3_24.hex_len())
+    );
+
+    let error = parse_line_ref(&format!("1:{}", "a".repeat(16))).expect_err("16 hex is no algo");
+    assert!(
+        error.to_string().contains("blake3-24"),
+        "unexpected error: {error}"
+    );
+}
@@ -0,0 +1,224 @@
+//! Session leases on files (`identedit lease`).
+//!
+//! The apply lock only covers one write, so two agents can still interleave their
+//! read-edit-apply loops on the same file. A lease claims a file for a named session
+//! (`--session` or `IDENTEDIT_SESSION`) until it expires or is released. Leases live in
+//! `.identedit/leases.json` under the current directory, shared by every identedit process
+//! started there, and every write checks them: a file leased by another live session fails
+//! with `lock_held_by_other_session`, whose error carries the lease.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+
+use crate::error::IdenteditError;
+
+/// Lease store location, relative to the current directory.
+pub const LEASE_FILE: &str = ".identedit/leases.json";
+
+/// Lease length when `lease acquire` is not given `--ttl`.
+pub const DEFAULT_LEASE_SECONDS: u64 = 300;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Lease {
+    /// Canonical path of the leased file.
+    pub file: PathBuf,
+    pub session: String,
+    /// Seconds since the Unix epoch.
+    pub acquired_at: u64,
+    /// Seconds since the Unix epoch; the lease is ignored from then on.
+    pub expires_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct LeaseStore {
+    leases: Vec<Lease>,
+}
+
+/// Leases every file in `files` to `session` for `seconds`, renewing leases it already
+/// holds. Nothing is leased if another session holds any of them.
+pub fn acquire_leases(
+    files: &[PathBuf],
+    seconds: u64,
+    session: Option<&str>,
+) -> Result<Vec<Lease>, IdenteditError> {
+    let session = require_session("lease acquire", session)?;
+    let files = canonical_files(files)?;
+    update_store(|store, now| {
+        for file in &files {
+            if let Some(lease) = held_by_other(store, file, Some(session), now) {
+                return Err(lock_held(file, lease));
+            }
+        }
+        let mut acquired = Vec::new();
+        for file in files {
+            store.leases.retain(|lease| lease.file != file);
+            let lease = Lease {
+                file,
+                session: session.to_string(),
+                acquired_at: now,
+                expires_at: now.saturating_add(seconds),
+            };
+            store.leases.push(lease.clone());
+            acquired.push(lease);
+        }
+        Ok(acquired)
+    })
+}
+
+/// Ends the leases of `session` on `files`; files it does not lease are skipped, and a
+/// file leased by another session fails.
+pub fn release_leases(
+    files: &[PathBuf],
+    session: Option<&str>,
+) -> Result<Vec<Lease>, IdenteditError> {
+    let session = require_session("lease release", session)?;
+    let files = canonical_files(files)?;
+    update_store(|store, now| {
+        for file in &files {
+            if let Some(lease) = held_by_other(store, file, Some(session), now) {
+                return Err(lock_held(file, lease));
+            }
+        }
+        let (released, kept) = store
+            .leases
+            .drain(..)
+            .partition(|lease| lease.session == session && files.contains(&lease.file));
+        store.leases = kept;
+        Ok(released)
+    })
+}
+
+/// Live leases of every session.
+pub fn list_leases() -> Result<Vec<Lease>, IdenteditError> {
+    let now = now();
+    Ok(load_store()?
+        .leases
+        .into_iter()
+        .filter(|lease| lease.expires_at > now)
+        .collect())
+}
+
+/// Fails with `lock_held_by_other_session` when a session other than `session` holds a live
+/// lease on `file`. Writes call this for every file they touch.
+pub fn check_lease(file: &Path, session: Option<&str>) -> Result<(), IdenteditError> {
+    if !Path::new(LEASE_FILE).is_file() {
+        return Ok(());
+    }
+    let Ok(canonical) = fs::canonicalize(file) else {
+        return Ok(());
+    };
+    match held_by_other(&load_store()?, &canonical, session, now()) {
+        Some(lease) => Err(lock_held(file, lease)),
+        None => Ok(()),
+    }
+}
+
+fn require_session<'session>(
+    command: &str,
+    session: Option<&'session str>,
+) -> Result<&'session str, IdenteditError> {
+    session.ok_or_else(|| IdenteditError::InvalidRequest {
+        message: format!(
+            "{command} needs a session name: pass --session NAME or set IDENTEDIT_SESSION"
+        ),
+    })
+}
+
+fn canonical_files(files: &[PathBuf]) -> Result<Vec<PathBuf>, IdenteditError> {
+    files
+        .iter()
+        .map(|file| fs::canonicalize(file).map_err(|error| IdenteditError::io(file, error)))
+        .collect()
+}
+
+fn held_by_other<'store>(
+    store: &'store LeaseStore,
+    file: &Path,
+    session: Option<&str>,
+    now: u64,
+) -> Option<&'store Lease> {
+    store.leases.iter().find(|lease| {
+        lease.file == file && lease.expires_at > now && Some(lease.session.as_str()) != session
+    })
+}
+
+fn lock_held(file: &Path, lease: &Lease) -> IdenteditError {
+    IdenteditError::LockHeldByOtherSession {
+        path: file.display().to_string(),
+        lease: lease.clone(),
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// Runs `update` on the store under an exclusive lock, dropping expired leases first, and
+/// writes the store back.
+fn update_store<T>(
+    update: impl FnOnce(&mut LeaseStore, u64) -> Result<T, IdenteditError>,
+) -> Result<T, IdenteditError> {
+    let path = Path::new(LEASE_FILE);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|error| IdenteditError::io(parent, error))?;
+    }
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .map_err(|error| IdenteditError::io(path, error))?;
+    file.lock_exclusive()
+        .map_err(|error| IdenteditError::io(path, error))?;
+    let mut store = read_store(&mut file)?;
+    let now = now();
+    store.leases.retain(|lease| lease.expires_at > now);
+    let result = update(&mut store, now)?;
+
+    store
+        .leases
+        .sort_by(|left, right| left.file.cmp(&right.file));
+    let mut serialized = serde_json::to_string_pretty(&store)
+        .map_err(|source| IdenteditError::ResponseSerialization { source })?;
+    serialized.push('\n');
+    file.set_len(0)
+        .and_then(|()| file.rewind())
+        .and_then(|()| file.write_all(serialized.as_bytes()))
+        .map_err(|error| IdenteditError::io(path, error))?;
+    Ok(result)
+}
+
+/// The store as last written, read under a shared lock; empty when there is none.
+fn load_store() -> Result<LeaseStore, IdenteditError> {
+    let path = Path::new(LEASE_FILE);
+    if !path.is_file() {
+        return Ok(LeaseStore::default());
+    }
+    let mut file = File::open(path).map_err(|error| IdenteditError::io(path, error))?;
+    file.lock_shared()
+        .map_err(|error| IdenteditError::io(path, error))?;
+    read_store(&mut file)
+}
+
+fn read_store(file: &mut File) -> Result<LeaseStore, IdenteditError> {
+    let mut content = String::new();
+    file.read_to_string(&mut content)
+        .map_err(|error| IdenteditError::io(Path::new(LEASE_FILE), error))?;
+    if content.trim().is_empty() {
+        return Ok(LeaseStore::default());
+    }
+    serde_json::from_str(&content).map_err(|error| IdenteditError::InvalidRequest {
+        message: format!("Failed to parse lease file '{LEASE_FILE}': {error}"),
+    })
+}
@@ -3,14 +3,21 @@ mod execution_context;
 pub mod apply;
 pub mod changeset;
 pub mod cli;
+pub mod compression;
 pub mod error;
 pub mod grammar;
 pub mod handle;
 pub mod hash;
 pub mod hashline;
+pub mod lease;
 mod patch;
+pub mod path_style;
+pub mod pin;
+pub mod project_config;
 pub mod provider;
 pub mod selector;
+pub mod stats;
+pub mod text_encoding;
 pub mod transform;
 
 pub fn version() -> &'static str {
@@ -1,18 +1,32 @@
 use std::process::ExitCode;
+use std::time::Instant;
 
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches};
+use identedit::cli::analyze::AnalyzeCommandOutput;
+use identedit::cli::grammar::GrammarCommandOutput;
+use identedit::cli::manifest::ManifestCommandOutput;
+use identedit::cli::map::MapCommandOutput;
+use identedit::cli::plan::PlanCommandOutput;
 use identedit::cli::read::ReadCommandOutput;
-use identedit::cli::{Cli, Commands};
+use identedit::cli::refactor::RefactorCommandOutput;
+use identedit::cli::stats::StatsCommandOutput;
+use identedit::cli::{Cli, Commands, GlobalArgs};
 use identedit::error::IdenteditError;
+use identedit::project_config::{ProjectConfig, load_project_config};
+use identedit::provider::ProviderRegistry;
 
 fn main() -> ExitCode {
     match run() {
-        Ok(output) => {
+        Ok((output, succeeded)) => {
             println!("{output}");
-            ExitCode::SUCCESS
+            if succeeded {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
         }
         Err(error) => {
-            let serialized = serde_json::to_string_pretty(&error.to_error_response()).unwrap_or_else(
+            let serialized = serde_json::to_string_pretty(&error.to_correlated_error_response()).unwrap_or_else(
                 |_| {
                     "{\"error\":{\"type\":\"serialization_error\",\"message\":\"Failed to serialize error response\"}}"
                         .to_string()
@@ -24,22 +38,107 @@ fn main() -> ExitCode {
     }
 }
 
-fn run() -> Result<String, IdenteditError> {
-    let cli = Cli::parse();
+/// Applies the global flags, runs the command, and records it for `identedit stats`.
+fn run() -> Result<(String, bool), IdenteditError> {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+    let command = matches.subcommand_name().unwrap_or_default().to_string();
+    let project_config = project_config(&cli.command)?;
+    let registry =
+        ProviderRegistry::with_overrides(cli.global.provider_overrides(&project_config))?;
+    let recording = cli.global.record_stats
+        || std::env::var("IDENTEDIT_RECORD_STATS").is_ok_and(|value| value == "1");
 
-    match cli.command {
-        Commands::Read(args) => match identedit::cli::read::run_read(args)? {
-            ReadCommandOutput::Text(output) => Ok(output),
-            ReadCommandOutput::Json(response) => serde_json::to_string_pretty(&response)
-                .map_err(|source| IdenteditError::ResponseSerialization { source }),
-        },
+    let started = Instant::now();
+    let accept_encoding = cli.global.accept_encoding;
+    let mut read_cache_hit = None;
+    let result = identedit::path_style::with_path_style(cli.global.path_style, || {
+        run_command(cli.command, &cli.global, &registry, &mut read_cache_hit)
+    });
+    if recording {
+        let error_type = result
+            .as_ref()
+            .err()
+            .map(|error| error.to_error_response().error.r#type);
+        identedit::stats::record(
+            &command,
+            started.elapsed(),
+            matches!(result, Ok((_, true))),
+            error_type.as_deref(),
+            read_cache_hit,
+        );
+    }
+    result.map(|(output, succeeded)| match accept_encoding {
+        Some(encoding) => (
+            identedit::compression::encode_response(&output, encoding),
+            succeeded,
+        ),
+        None => (output, succeeded),
+    })
+}
+
+/// The project config. A malformed one fails only the commands whose writes it governs;
+/// the rest warn on stderr and run with the defaults.
+fn project_config(command: &Commands) -> Result<ProjectConfig, IdenteditError> {
+    let error = match load_project_config() {
+        Ok(config) => return Ok(config),
+        Err(error) => error,
+    };
+    match command {
+        Commands::Apply(_)
+        | Commands::Patch(_)
+        | Commands::Revert(_)
+        | Commands::Refactor(_)
+        | Commands::Verify(_) => Err(error),
+        _ => {
+            eprintln!("identedit: ignoring the project config: {error}");
+            Ok(ProjectConfig::default())
+        }
+    }
+}
+
+/// Returns the rendered output and whether the command succeeded. `selftest`, `verify`, and
+/// `hook pre-commit` report failure through a regular response, so operators keep the
+/// per-check details. A `read --if-none-match` sets `read_cache_hit`.
+fn run_command(
+    command: Commands,
+    global: &GlobalArgs,
+    registry: &ProviderRegistry,
+    read_cache_hit: &mut Option<bool>,
+) -> Result<(String, bool), IdenteditError> {
+    let mut succeeded = true;
+    let output = match command {
+        Commands::Read(args) => {
+            let cache_queried = args.if_none_match.is_some();
+            let output = identedit::cli::read::run_read(args, global.max_response_bytes, registry)?;
+            *read_cache_hit =
+                cache_queried.then_some(matches!(output, ReadCommandOutput::NotModified(_)));
+            match output {
+                ReadCommandOutput::Text(output) => Ok(output),
+                ReadCommandOutput::Json(response) => serde_json::to_string_pretty(&response)
+                    .map_err(|source| IdenteditError::ResponseSerialization { source }),
+                ReadCommandOutput::NotModified(response) => serde_json::to_string_pretty(&response)
+                    .map_err(|source| IdenteditError::ResponseSerialization { source }),
+            }
+        }
         Commands::Edit(args) => {
-            let response = identedit::cli::edit::run_edit(args)?;
+            let response = identedit::cli::edit::run_edit(args, registry)?;
             serde_json::to_string_pretty(&response)
                 .map_err(|source| IdenteditError::ResponseSerialization { source })
         }
         Commands::Apply(args) => {
-            let response = identedit::cli::apply::run_apply(args)?;
+            let response = identedit::cli::apply::run_apply(args, global.apply_settings()?)?;
+            serde_json::to_string_pretty(&response)
+                .map_err(|source| IdenteditError::ResponseSerialization { source })
+        }
+        Commands::Revert(args) => {
+            let response = identedit::cli::revert::run_revert(args, global.apply_settings()?)?;
+            serde_json::to_string_pretty(&response)
+                .map_err(|source| IdenteditError::ResponseSerialization { source })
+        }
+        Commands::Verify(args) => {
+            let response = identedit::cli::verify::run_verify(args, global.apply_settings()?)?;
+            succeeded = response.applicable;
             serde_json::to_string_pretty(&response)
                 .map_err(|source| IdenteditError::ResponseSerialization { source })
         }
@@ -48,15 +147,133 @@ fn run() -> Result<String, IdenteditError> {
             serde_json::to_string_pretty(&response)
                 .map_err(|source| IdenteditError::ResponseSerialization { source })
         }
-        Commands::Grammar(args) => {
-            let response = identedit::cli::grammar::run_grammar(args)?;
+        Commands::Grammar(args) => match identedit::cli::grammar::run_grammar(args)? {
+            GrammarCommandOutput::Installed(response) => serde_json::to_string_pretty(&response)
+                .map_err(|source| IdenteditError::ResponseSerialization { source }),
+            GrammarCommandOutput::Cleaned(response) => serde_json::to_string_pretty(&response)
+                .map_err(|source| IdenteditError::ResponseSerialization { source }),
+        },
+        Commands::Patch(args) => {
+            let response = identedit::cli::patch::run_patch(*args, global.apply_settings()?)?;
             serde_json::to_string_pretty(&response)
                 .map_err(|source| IdenteditError::ResponseSerialization { source })
         }
-        Commands::Patch(args) => {
-            let response = identedit::cli::patch::run_patch(*args)?;
+        Commands::Pin(args) => {
+            let response = identedit::cli::pin::run_pin(args, registry)?;
             serde_json::to_string_pretty(&response)
                 .map_err(|source| IdenteditError::ResponseSerialization { source })
         }
-    }
+        Commands::Lease(args) => {
+            let response = identedit::cli::lease::run_lease(args, global.session().as_deref())?;
+            serde_json::to_string_pretty(&response)
+                .map_err(|source| IdenteditError::ResponseSerialization { source })
+        }
+        Commands::Detect(args) => {
+            let response = identedit::cli::detect::run_detect(args, registry)?;
+            serde_json::to_string_pretty(&response)
+                .map_err(|source| IdenteditError::ResponseSerialization { source })
+        }
+        Commands::Anchors(args) => {
+            let response = identedit::cli::anchors::run_anchors(args)?;
+            serde_json::to_string_pretty(&response)
+                .map_err(|source| IdenteditError::ResponseSerialization { source })
+        }
+        Commands::Hook(args) => {
+            let response = identedit::cli::hook::run_hook(args, registry)?;
+            succeeded = response.passed;
+            serde_json::to_string_pretty(&response)
+                .map_err(|source| IdenteditError::ResponseSerialization { source })
+        }
+        Commands::Plan(args) => match identedit::cli::plan::run_plan(args, registry)? {
+            PlanCommandOutput::Script(script) => Ok(script),
+            PlanCommandOutput::Plan(changeset) => serde_json::to_string_pretty(&changeset)
+                .map_err(|source| IdenteditError::ResponseSerialization { source }),
+            PlanCommandOutput::Rebased(response) => serde_json::to_string_pretty(&response)
+                .map_err(|source| IdenteditError::ResponseSerialization { source }),
+            PlanCommandOutput::Hashed(response) => serde_json::to_string_pretty(&response)
+                .map_err(|source| IdenteditError::ResponseSerialization { source }),
+        },
+        Commands::Selftest(args) => {
+            let response = identedit::cli::selftest::run_selftest(args, registry)?;
+            succeeded = response.passed;
+            serde_json::to_string_pretty(&response)
+                .map_err(|source| IdenteditError::ResponseSerialization { source })
+        }
+        Commands::Locate(args) => {
+            let response = identedit::cli::locate::run_locate(args, registry)?;
+            serde_json::to_string_pretty(&response)
+                .map_err(|source| IdenteditError::ResponseSerialization { source })
+        }
+        Commands::DiffHandle(args) => {
+            let response = identedit::cli::diff_handle::run_diff_handle(args, registry)?;
+            serde_json::to_string_pretty(&response)
+                .map_err(|source| IdenteditError::ResponseSerialization { source })
+        }
+        Commands::Refactor(args) => {
+            match identedit::cli::refactor::run_refactor(
+                args,
+                global.apply_settings()?,
+                global.scan_limits(),
+            )? {
+                RefactorCommandOutput::Plan(changeset) => serde_json::to_string_pretty(&changeset)
+                    .map_err(|source| IdenteditError::ResponseSerialization { source }),
+                RefactorCommandOutput::Applied(response) => serde_json::to_string_pretty(&response)
+                    .map_err(|source| IdenteditError::ResponseSerialization { source }),
+            }
+        }
+        Commands::Map(args) => {
+            match identedit::cli::map::run_map(args, global.scan_limits(), registry)? {
+                MapCommandOutput::Map(map) => serde_json::to_string_pretty(&map)
+                    .map_err(|source| IdenteditError::ResponseSerialization { source }),
+                MapCommandOutput::Written(written) => serde_json::to_string_pretty(&written)
+                    .map_err(|source| IdenteditError::ResponseSerialization { source }),
+            }
+        }
+        Commands::Analyze(args) => {
+            match identedit::cli::analyze::run_analyze(args, global.scan_limits(), registry)? {
+                AnalyzeCommandOutput::Imports(graph) => serde_json::to_string_pretty(&graph)
+                    .map_err(|source| IdenteditError::ResponseSerialization { source }),
+                AnalyzeCommandOutput::Markers(inventory) => {
+                    serde_json::to_string_pretty(&inventory)
+                        .map_err(|source| IdenteditError::ResponseSerialization { source })
+                }
+                AnalyzeCommandOutput::Unused(report) => serde_json::to_string_pretty(&report)
+                    .map_err(|source| IdenteditError::ResponseSerialization { source }),
+                AnalyzeCommandOutput::Sarif(log) => serde_json::to_string_pretty(&log)
+                    .map_err(|source| IdenteditError::ResponseSerialization { source }),
+            }
+        }
+        Commands::Manifest(args) => {
+            match identedit::cli::manifest::run_manifest(args, global.scan_limits(), registry)? {
+                ManifestCommandOutput::Manifest(manifest) => {
+                    serde_json::to_string_pretty(&manifest)
+                        .map_err(|source| IdenteditError::ResponseSerialization { source })
+                }
+                ManifestCommandOutput::Written(written) => serde_json::to_string_pretty(&written)
+                    .map_err(|source| IdenteditError::ResponseSerialization { source }),
+                ManifestCommandOutput::Verified(response) => {
+                    succeeded = response.unchanged;
+                    serde_json::to_string_pretty(&response)
+                        .map_err(|source| IdenteditError::ResponseSerialization { source })
+                }
+            }
+        }
+        Commands::Stats(args) => match identedit::cli::stats::run_stats(args)? {
+            StatsCommandOutput::Json(stats) => serde_json::to_string_pretty(&stats)
+                .map_err(|source| IdenteditError::ResponseSerialization { source }),
+            StatsCommandOutput::Prometheus(text) => Ok(text.trim_end().to_string()),
+        },
+        Commands::Capabilities => {
+            let response = identedit::cli::capabilities::run_capabilities(registry);
+            serde_json::to_string_pretty(&response)
+                .map_err(|source| IdenteditError::ResponseSerialization { source })
+        }
+        Commands::Completions(args) => Ok(identedit::cli::completions::run_completions(args)),
+        Commands::Toolspec(args) => {
+            let response = identedit::cli::toolspec::run_toolspec(args);
+            serde_json::to_string_pretty(&response)
+                .map_err(|source| IdenteditError::ResponseSerialization { source })
+        }
+    };
+    output.map(|output| (output, succeeded))
 }
@@ -0,0 +1,277 @@
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::ValueEnum;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::changeset::TransformTarget;
+use crate::error::IdenteditError;
+use crate::provider::ProviderRegistry;
+use crate::transform::{parse_handles_for_file, resolve_target_in_handles};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum AnnotationMarker {
+    #[default]
+    Todo,
+    Fixme,
+    Hack,
+    Xxx,
+}
+
+impl AnnotationMarker {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AnnotationMarker::Todo => "TODO",
+            AnnotationMarker::Fixme => "FIXME",
+            AnnotationMarker::Hack => "HACK",
+            AnnotationMarker::Xxx => "XXX",
+        }
+    }
+}
+
+/// What to write in an `annotate` marker.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AnnotationSpec {
+    #[serde(default)]
+    pub marker: AnnotationMarker,
+    pub message: String,
+    #[serde(default)]
+    pub ticket: Option<String>,
+    /// Stamp the marker with today's UTC date.
+    #[serde(default)]
+    pub timestamp: bool,
+}
+
+/// A marker found in an existing comment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundAnnotation {
+    pub marker: String,
+    pub ticket: Option<String>,
+    pub date: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CommentSyntax {
+    Line(&'static str),
+    Block(&'static str, &'static str),
+}
+
+/// Resolves `target` in `file` and returns the `insert_before` text for its marker.
+pub(crate) fn annotate_node_target(
+    file: &Path,
+    target: &TransformTarget,
+    spec: &AnnotationSpec,
+    registry: &ProviderRegistry,
+) -> Result<String, IdenteditError> {
+    let handles = parse_handles_for_file(file, registry)?;
+    let resolved = resolve_target_in_handles(file, &handles, target)?;
+    let source = std::fs::read(file).map_err(|error| IdenteditError::io(file, error))?;
+    annotation_insert_text(file, &source, resolved.span.start, spec)
+}
+
+/// Text to insert before the node starting at `node_start`: the marker comment on its
+/// own line, followed by the node's indentation so the node keeps its column.
+pub(crate) fn annotation_insert_text(
+    file: &Path,
+    source: &[u8],
+    node_start: usize,
+    spec: &AnnotationSpec,
+) -> Result<String, IdenteditError> {
+    let syntax = comment_syntax(file).ok_or_else(|| IdenteditError::InvalidRequest {
+        message: format!(
+            "annotate does not know the comment syntax for '{}'",
+            file.display()
+        ),
+    })?;
+    if spec.message.trim().is_empty() || spec.message.contains(['\n', '\r']) {
+        return Err(IdenteditError::InvalidRequest {
+            message: "annotate message must be a non-empty single line".to_string(),
+        });
+    }
+    if let Some(ticket) = &spec.ticket
+        && (ticket.trim().is_empty() || ticket.contains(['(', ')', '\n', '\r']))
+    {
+        return Err(IdenteditError::InvalidRequest {
+            message: format!(
+                "annotate ticket '{ticket}' must be non-empty and free of parentheses and newlines"
+            ),
+        });
+    }
+
+    let line_start = source[..node_start]
+        .iter()
+        .rposition(|byte| matches!(byte, b'\n' | b'\r'))
+        .map_or(0, |index| index + 1);
+    let indentation = &source[line_start..node_start];
+    if !indentation.iter().all(|byte| matches!(byte, b' ' | b'\t')) {
+        return Err(IdenteditError::InvalidRequest {
+            message: "annotate needs a target that starts its own line".to_string(),
+        });
+    }
+    let newline = if source.windows(2).any(|pair| pair == b"\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+
+    let date = spec.timestamp.then(today_utc);
+    let body = format_marker(spec, date.as_deref());
+    let comment = match syntax {
+        CommentSyntax::Line(prefix) => format!("{prefix} {body}"),
+        CommentSyntax::Block(open, close) => format!("{open} {body} {close}"),
+    };
+    Ok(format!(
+        "{comment}{newline}{}",
+        String::from_utf8_lossy(indentation)
+    ))
+}
+
+/// `TODO(ABC-123) 2026-01-31: message`, with the ticket and date optional.
+fn format_marker(spec: &AnnotationSpec, date: Option<&str>) -> String {
+    let mut marker = spec.marker.as_str().to_string();
+    if let Some(ticket) = &spec.ticket {
+        marker.push_str(&format!("({})", ticket.trim()));
+    }
+    if let Some(date) = date {
+        marker.push(' ');
+        marker.push_str(date);
+    }
+    format!("{marker}: {}", spec.message.trim())
+}
+
+/// The marker in one line of comment text, if any.
+pub fn find_annotation(comment_line: &str) -> Option<FoundAnnotation> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| {
+        Regex::new(
+            r"\b(?P<marker>TODO|FIXME|HACK|XXX)\b(?:\((?P<ticket>[^()\n]*)\))?(?:\s+(?P<date>\d{4}-\d{2}-\d{2}))?\s*:?\s*(?P<message>.*)",
+        )
+        .expect("annotation pattern should compile")
+    });
+    let captures = pattern.captures(comment_line)?;
+    let message = captures["message"].trim_end();
+    let message = message
+        .strip_suffix("*/")
+        .or_else(|| message.strip_suffix("-->"))
+        .unwrap_or(message)
+        .trim();
+    Some(FoundAnnotation {
+        marker: captures["marker"].to_string(),
+        ticket: captures
+            .name("ticket")
+            .map(|ticket| ticket.as_str().to_string()),
+        date: captures.name("date").map(|date| date.as_str().to_string()),
+        message: message.to_string(),
+    })
+}
+
+pub(crate) fn comment_syntax(file: &Path) -> Option<CommentSyntax> {
+    let file_name = file.file_name()?.to_str()?.to_ascii_lowercase();
+    if file_name == "dockerfile" || file_name.starts_with("dockerfile.") {
+        return Some(CommentSyntax::Line("#"));
+    }
+    let extension = file.extension()?.to_str()?.to_ascii_lowercase();
+    let syntax = match extension.as_str() {
+        "py" | "pyi" | "pyw" | "rb" | "sh" | "bash" | "zsh" | "fish" | "yaml" | "yml" | "toml"
+        | "pl" | "pm" | "r" | "tf" | "hcl" | "dockerfile" | "ex" | "exs" | "nix" => {
+            CommentSyntax::Line("#")
+        }
+        "rs" | "js" | "mjs" | "cjs" | "jsx" | "ts" | "mts" | "cts" | "tsx" | "go" | "java"
+        | "kt" | "kts" | "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "hh" | "hxx" | "cs"
+        | "swift" | "php" | "scss" | "proto" | "dart" | "scala" | "zig" => {
+            CommentSyntax::Line("//")
+        }
+        "sql" | "lua" | "hs" => CommentSyntax::Line("--"),
+        "css" => CommentSyntax::Block("/*", "*/"),
+        "html" | "htm" | "xml" | "svg" | "md" | "markdown" => CommentSyntax::Block("<!--", "-->"),
+        _ => return None,
+    };
+    Some(syntax)
+}
+
+/// Today's date in UTC as `YYYY-MM-DD`.
+fn today_utc() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() / 86_400);
+    let (year, month, day) = civil_from_days(days as i64);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Proleptic Gregorian date of a day count since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{
+        AnnotationMarker, AnnotationSpec, annotation_insert_text, civil_from_days, find_annotation,
+    };
+
+    fn spec(ticket: Option<&str>) -> AnnotationSpec {
+        AnnotationSpec {
+            marker: AnnotationMarker::Fixme,
+            message: "handle empty carts".to_string(),
+            ticket: ticket.map(str::to_string),
+            timestamp: false,
+        }
+    }
+
+    #[test]
+    fn markers_use_the_language_comment_syntax_and_keep_indentation() {
+        let source = b"class Cart:\n    def total(self):\n        pass\n";
+        let start = 16;
+        assert_eq!(
+            annotation_insert_text(Path::new("cart.py"), source, start, &spec(Some("SHOP-12")))
+                .expect("python marker"),
+            "# FIXME(SHOP-12): handle empty carts\n    "
+        );
+        assert_eq!(
+            annotation_insert_text(Path::new("cart.css"), b"a {}\r\n", 0, &spec(None))
+                .expect("css marker"),
+            "/* FIXME: handle empty carts */\r\n"
+        );
+        assert!(annotation_insert_text(Path::new("cart.py"), source, 20, &spec(None)).is_err());
+        assert!(annotation_insert_text(Path::new("cart.bin"), source, 0, &spec(None)).is_err());
+    }
+
+    #[test]
+    fn found_markers_split_ticket_date_and_message() {
+        let found = find_annotation("// TODO(ABC-1) 2026-01-31: drop legacy path */")
+            .expect("marker should be found");
+        assert_eq!(found.marker, "TODO");
+        assert_eq!(found.ticket.as_deref(), Some("ABC-1"));
+        assert_eq!(found.date.as_deref(), Some("2026-01-31"));
+        assert_eq!(found.message, "drop legacy path");
+        assert_eq!(
+            find_annotation("# XXX racy").map(|found| found.message),
+            Some("racy".to_string())
+        );
+        assert!(find_annotation("# TODOS are tracked elsewhere").is_none());
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(20_484), (2026, 1, 31));
+    }
+}
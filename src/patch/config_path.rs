@@ -1,15 +1,34 @@
 use std::collections::HashMap;
 use std::path::Path;
 
+use serde::Deserialize;
 use tree_sitter::{Node, Parser, Tree};
 
 use crate::changeset::{OpKind, TransformTarget};
 use crate::error::IdenteditError;
 use crate::handle::{SelectionHandle, Span};
 use crate::hash::hash_bytes;
-use crate::provider::node_text;
+use crate::provider::{ProviderRegistry, node_text};
 use crate::transform::parse_handles_for_source;
 
+mod array_edit;
+mod dependency;
+mod document;
+mod listing;
+mod merge;
+mod relocate;
+mod toml_insert;
+mod typed_value;
+mod wildcard;
+mod yaml_insert;
+
+pub use dependency::{DependencyOperation, resolve_config_dependency_operation};
+pub use listing::{check_config_value_hash, list_config_entries};
+pub use merge::{MergeConflictPolicy, MergeObject, resolve_config_merge_operation};
+pub use relocate::resolve_config_move_operation;
+pub use typed_value::{ConfigValueType, encode_config_value};
+pub use wildcard::{check_single_target_match_count, resolve_config_path_pattern_operation};
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ConfigPathOperation {
     Set {
@@ -19,21 +38,52 @@ pub enum ConfigPathOperation {
     Append {
         new_text: String,
     },
+    InsertAt {
+        index: usize,
+        new_text: String,
+    },
+    RemoveAt {
+        index: usize,
+    },
+    RenameKey {
+        new_key: String,
+    },
     Delete,
 }
 
+impl ConfigPathOperation {
+    fn name(&self) -> &'static str {
+        match self {
+            ConfigPathOperation::Set { .. } => "set",
+            ConfigPathOperation::Append { .. } => "append",
+            ConfigPathOperation::InsertAt { .. } => "insert_at",
+            ConfigPathOperation::RemoveAt { .. } => "remove_at",
+            ConfigPathOperation::RenameKey { .. } => "rename_key",
+            ConfigPathOperation::Delete => "delete",
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ResolvedConfigPatch {
     pub target: TransformTarget,
     pub op: OpKind,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 enum PathToken {
     Key(String),
     Index(usize),
 }
 
+/// A parsed path segment that may be a wildcard (`*` for any key, `[*]` for any index).
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PathSegment {
+    Token(PathToken),
+    AnyKey,
+    AnyIndex,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct ResolvedContainerEdit {
     container_span: Span,
@@ -56,6 +106,7 @@ struct TomlCandidate {
     set_span: Span,
     set_kind: String,
     delete_entry_span: Span,
+    key_span: Option<Span>,
 }
 
 struct CreateMissingSetRequest<'a> {
@@ -68,14 +119,12 @@ struct CreateMissingSetRequest<'a> {
     new_text: &'a str,
 }
 
-pub fn resolve_config_path_operation(
+fn read_config_source(
     file: &Path,
-    raw_path: &str,
     expected_file_hash: Option<&str>,
-    operation: ConfigPathOperation,
-) -> Result<ResolvedConfigPatch, IdenteditError> {
+) -> Result<String, IdenteditError> {
     let source = std::fs::read(file).map_err(|error| IdenteditError::io(file, error))?;
-    let source_text = std::str::from_utf8(&source).map_err(|_| IdenteditError::InvalidRequest {
+    let source_text = String::from_utf8(source).map_err(|_| IdenteditError::InvalidRequest {
         message: format!(
             "Config path operations require UTF-8 source; file '{}' is not UTF-8",
             file.display()
@@ -83,7 +132,7 @@ pub fn resolve_config_path_operation(
     })?;
 
     if let Some(expected_hash) = expected_file_hash {
-        let actual_hash = hash_bytes(&source);
+        let actual_hash = hash_bytes(source_text.as_bytes());
         if actual_hash != expected_hash {
             return Err(IdenteditError::PreconditionFailed {
                 expected_hash: expected_hash.to_string(),
@@ -92,8 +141,19 @@ pub fn resolve_config_path_operation(
         }
     }
 
+    Ok(source_text)
+}
+
+fn resolve_config_path_operation_for_source(
+    file: &Path,
+    source: &[u8],
+    source_text: &str,
+    path_tokens: &[PathToken],
+    raw_path: &str,
+    operation: ConfigPathOperation,
+    registry: &ProviderRegistry,
+) -> Result<ResolvedConfigPatch, IdenteditError> {
     let format = detect_config_format(file)?;
-    let path_tokens = parse_config_path(raw_path)?;
 
     if let ConfigPathOperation::Set {
         new_text,
@@ -102,16 +162,16 @@ pub fn resolve_config_path_operation(
         && matches!(format, ConfigFormat::Json)
         && source.is_empty()
     {
-        let updated = render_json_with_create_missing("", &path_tokens, raw_path, new_text)?;
+        let updated = render_json_with_create_missing("", path_tokens, raw_path, new_text)?;
         return Ok(ResolvedConfigPatch {
             target: TransformTarget::FileStart {
-                expected_file_hash: hash_bytes(&source),
+                expected_file_hash: hash_bytes(source),
             },
             op: OpKind::Insert { new_text: updated },
         });
     }
 
-    let tree = parse_tree_for_format(&format, &source)?;
+    let tree = parse_tree_for_format(&format, source)?;
     if let ConfigPathOperation::Set {
         new_text,
         create_missing: true,
@@ -121,27 +181,20 @@ pub fn resolve_config_path_operation(
             new_text: String::new(),
             create_missing: false,
         };
-        let strict_resolved = match format {
-            ConfigFormat::Json => {
-                resolve_json_path(&tree, &source, &path_tokens, &strict_probe, raw_path)
-            }
-            ConfigFormat::Yaml => {
-                resolve_yaml_path(&tree, &source, &path_tokens, &strict_probe, raw_path)
-            }
-            ConfigFormat::Toml => {
-                resolve_toml_path(&tree, &source, &path_tokens, &strict_probe, raw_path)
-            }
-        };
-        match strict_resolved {
+        match resolve_path_in_tree(&format, &tree, source, path_tokens, &strict_probe, raw_path) {
             Ok(resolved) => {
                 return build_resolved_patch_from_container_edit(
                     file,
-                    &source,
+                    source,
                     source_text,
                     resolved,
                     new_text,
+                    registry,
                 );
             }
+            Err(_)
+                if matches!(format, ConfigFormat::Yaml)
+                    && yaml_root_value(tree.root_node()).is_none() => {}
             Err(error) if !is_missing_config_path_error(&error) => return Err(error),
             Err(_) => {}
         }
@@ -151,26 +204,17 @@ pub fn resolve_config_path_operation(
             CreateMissingSetRequest {
                 format: &format,
                 tree: &tree,
-                source: &source,
+                source,
                 source_text,
-                path_tokens: &path_tokens,
+                path_tokens,
                 raw_path,
                 new_text,
             },
+            registry,
         );
     }
 
-    let resolved = match format {
-        ConfigFormat::Json => {
-            resolve_json_path(&tree, &source, &path_tokens, &operation, raw_path)?
-        }
-        ConfigFormat::Yaml => {
-            resolve_yaml_path(&tree, &source, &path_tokens, &operation, raw_path)?
-        }
-        ConfigFormat::Toml => {
-            resolve_toml_path(&tree, &source, &path_tokens, &operation, raw_path)?
-        }
-    };
+    let resolved = resolve_path_in_tree(&format, &tree, source, path_tokens, &operation, raw_path)?;
 
     let replacement = match &operation {
         ConfigPathOperation::Set { new_text, .. } => new_text.clone(),
@@ -181,9 +225,51 @@ pub fn resolve_config_path_operation(
             new_text,
             raw_path,
         )?,
+        ConfigPathOperation::InsertAt { .. } | ConfigPathOperation::RemoveAt { .. } => {
+            let (edit, replacement) = array_edit::resolve_array_index_edit(
+                &tree,
+                source_text,
+                resolved,
+                &operation,
+                raw_path,
+            )?;
+            return build_resolved_patch_from_container_edit(
+                file,
+                source,
+                source_text,
+                edit,
+                &replacement,
+                registry,
+            );
+        }
+        ConfigPathOperation::RenameKey { new_key } => {
+            relocate::render_renamed_key(&format, &tree, source, path_tokens, raw_path, new_key)?
+        }
         ConfigPathOperation::Delete => String::new(),
     };
-    build_resolved_patch_from_container_edit(file, &source, source_text, resolved, &replacement)
+    build_resolved_patch_from_container_edit(
+        file,
+        source,
+        source_text,
+        resolved,
+        &replacement,
+        registry,
+    )
+}
+
+fn resolve_path_in_tree(
+    format: &ConfigFormat,
+    tree: &Tree,
+    source: &[u8],
+    path_tokens: &[PathToken],
+    operation: &ConfigPathOperation,
+    raw_path: &str,
+) -> Result<ResolvedContainerEdit, IdenteditError> {
+    match format {
+        ConfigFormat::Json => resolve_json_path(tree, source, path_tokens, operation, raw_path),
+        ConfigFormat::Yaml => resolve_yaml_path(tree, source, path_tokens, operation, raw_path),
+        ConfigFormat::Toml => resolve_toml_path(tree, source, path_tokens, operation, raw_path),
+    }
 }
 
 fn build_resolved_patch_from_container_edit(
@@ -192,8 +278,9 @@ fn build_resolved_patch_from_container_edit(
     source_text: &str,
     resolved: ResolvedContainerEdit,
     replacement: &str,
+    registry: &ProviderRegistry,
 ) -> Result<ResolvedConfigPatch, IdenteditError> {
-    let handles = parse_handles_for_source(file, source)?;
+    let handles = parse_handles_for_source(file, source, registry)?;
     let container_handle = find_handle_for_span(
         file,
         &handles,
@@ -222,19 +309,139 @@ fn build_resolved_patch_from_container_edit(
     })
 }
 
-fn resolve_config_path_set_with_create_missing(
+/// Parsed view of the document (first document for YAML) used to plan multi-target edits.
+fn load_document_value(
+    format: &ConfigFormat,
+    source_text: &str,
+) -> Result<serde_json::Value, IdenteditError> {
+    let parse_error = |error: String| IdenteditError::InvalidRequest {
+        message: format!("Config path could not read document: {error}"),
+    };
+    if source_text.trim().is_empty() {
+        return Ok(serde_json::Value::Null);
+    }
+
+    match format {
+        ConfigFormat::Json => {
+            serde_json::from_str(source_text).map_err(|error| parse_error(error.to_string()))
+        }
+        ConfigFormat::Yaml => {
+            let Some(document) = serde_yaml::Deserializer::from_str(source_text).next() else {
+                return Ok(serde_json::Value::Null);
+            };
+            serde_json::Value::deserialize(document).map_err(|error| parse_error(error.to_string()))
+        }
+        ConfigFormat::Toml => {
+            let table = toml::from_str::<toml::Table>(source_text)
+                .map_err(|error| parse_error(error.to_string()))?;
+            serde_json::to_value(table).map_err(|error| parse_error(error.to_string()))
+        }
+    }
+}
+
+fn apply_patch_to_text(text: &str, patch: ResolvedConfigPatch) -> Result<String, IdenteditError> {
+    match (patch.target, patch.op) {
+        (
+            TransformTarget::Node {
+                span_hint: Some(span),
+                ..
+            },
+            OpKind::Replace { new_text },
+        ) => {
+            let mut updated = text.to_string();
+            updated.replace_range(span.start..span.end, &new_text);
+            Ok(updated)
+        }
+        (TransformTarget::FileStart { .. }, OpKind::Insert { new_text }) => {
+            Ok(format!("{new_text}{text}"))
+        }
+        (TransformTarget::FileEnd { .. }, OpKind::Insert { new_text }) => {
+            Ok(format!("{text}{new_text}"))
+        }
+        _ => Err(IdenteditError::InvalidRequest {
+            message: "Config path operation produced an unsupported intermediate edit".to_string(),
+        }),
+    }
+}
+
+/// Collapses the before/after texts into one edit: the smallest structural node that
+/// covers every changed byte is replaced with its merged text.
+fn build_patch_from_text_change(
     file: &Path,
-    request: CreateMissingSetRequest<'_>,
+    source_text: &str,
+    merged_text: &str,
+    registry: &ProviderRegistry,
 ) -> Result<ResolvedConfigPatch, IdenteditError> {
-    if matches!(request.format, ConfigFormat::Yaml) {
-        validate_yaml_create_missing_safety(request.tree, request.source_text)?;
-    }
-    if matches!(request.format, ConfigFormat::Toml) && has_toml_comments(request.tree.root_node()) {
-        return Err(IdenteditError::InvalidRequest {
-            message: "Config path create-missing does not support TOML comments yet".to_string(),
+    if source_text.is_empty() {
+        if merged_text.is_empty() {
+            return Err(IdenteditError::InvalidRequest {
+                message: "Config path operation has nothing to write into an empty document"
+                    .to_string(),
+            });
+        }
+        return Ok(ResolvedConfigPatch {
+            target: TransformTarget::FileStart {
+                expected_file_hash: hash_bytes(source_text.as_bytes()),
+            },
+            op: OpKind::Insert {
+                new_text: merged_text.to_string(),
+            },
         });
     }
 
+    let old = source_text.as_bytes();
+    let new = merged_text.as_bytes();
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(left, right)| left == right)
+        .count();
+    let max_suffix = old.len().min(new.len()) - prefix;
+    let suffix = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take(max_suffix)
+        .take_while(|(left, right)| left == right)
+        .count();
+    let changed_end = old.len() - suffix;
+
+    let handles = parse_handles_for_source(file, old, registry)?;
+    let container = handles
+        .iter()
+        .filter(|handle| handle.span.start <= prefix && handle.span.end >= changed_end)
+        // Some handles (e.g. JSON keys) hash a normalized form rather than their raw span;
+        // only nodes whose span text matches their precondition hash can be replaced.
+        .filter(|handle| {
+            hash_bytes(&old[handle.span.start..handle.span.end]) == handle.expected_old_hash
+        })
+        .min_by_key(|handle| handle.span.end - handle.span.start)
+        .ok_or_else(|| IdenteditError::InvalidRequest {
+            message: format!(
+                "Config path operation changed bytes [{prefix}, {changed_end}) outside any structural node in '{}'",
+                file.display()
+            ),
+        })?;
+
+    let new_end = container.span.end + new.len() - old.len();
+    Ok(ResolvedConfigPatch {
+        target: TransformTarget::node(
+            container.identity.clone(),
+            container.kind.clone(),
+            Some(container.span),
+            container.expected_old_hash.clone(),
+        ),
+        op: OpKind::Replace {
+            new_text: merged_text[container.span.start..new_end].to_string(),
+        },
+    })
+}
+
+fn resolve_config_path_set_with_create_missing(
+    file: &Path,
+    request: CreateMissingSetRequest<'_>,
+    registry: &ProviderRegistry,
+) -> Result<ResolvedConfigPatch, IdenteditError> {
     let updated_root_text = match request.format {
         ConfigFormat::Json => render_json_with_create_missing(
             request.source_text,
@@ -242,13 +449,10 @@ fn resolve_config_path_set_with_create_missing(
             request.raw_path,
             request.new_text,
         )?,
-        ConfigFormat::Yaml => render_yaml_with_create_missing(
-            request.source_text,
-            request.path_tokens,
-            request.raw_path,
-            request.new_text,
-        )?,
-        ConfigFormat::Toml => render_toml_with_create_missing(
+        ConfigFormat::Yaml => {
+            return yaml_insert::resolve_yaml_set_with_create_missing(file, &request, registry);
+        }
+        ConfigFormat::Toml => toml_insert::render_toml_with_create_missing(
             request.source_text,
             request.path_tokens,
             request.raw_path,
@@ -256,7 +460,7 @@ fn resolve_config_path_set_with_create_missing(
         )?,
     };
 
-    if matches!(request.format, ConfigFormat::Json) && request.source.is_empty() {
+    if request.source.is_empty() {
         return Ok(ResolvedConfigPatch {
             target: TransformTarget::FileStart {
                 expected_file_hash: hash_bytes(request.source),
@@ -267,23 +471,17 @@ fn resolve_config_path_set_with_create_missing(
         });
     }
 
-    let root_node = match request.format {
-        ConfigFormat::Json => json_root_value(request.tree.root_node()).ok_or_else(|| {
-            IdenteditError::InvalidRequest {
-                message: "JSON document has no root value".to_string(),
-            }
-        })?,
-        ConfigFormat::Yaml => yaml_root_value(request.tree.root_node()).ok_or_else(|| {
-            IdenteditError::InvalidRequest {
-                message: "YAML document has no root value".to_string(),
-            }
-        })?,
-        ConfigFormat::Toml => request.tree.root_node(),
+    let root_node = if matches!(request.format, ConfigFormat::Json) {
+        json_root_value(request.tree.root_node()).ok_or_else(|| IdenteditError::InvalidRequest {
+            message: "JSON document has no root value".to_string(),
+        })?
+    } else {
+        request.tree.root_node()
     };
 
     let root_span = span_from_node(root_node);
     let root_kind = root_node.kind().to_string();
-    let handles = parse_handles_for_source(file, request.source)?;
+    let handles = parse_handles_for_source(file, request.source, registry)?;
     let container_handle = find_handle_for_span(file, &handles, root_span, &root_kind)?;
     let target = TransformTarget::node(
         container_handle.identity,
@@ -328,75 +526,6 @@ fn render_json_with_create_missing(
     Ok(apply_source_line_ending_style(&rendered, source_text))
 }
 
-fn render_yaml_with_create_missing(
-    source_text: &str,
-    path_tokens: &[PathToken],
-    raw_path: &str,
-    new_text: &str,
-) -> Result<String, IdenteditError> {
-    let mut root: serde_yaml::Value =
-        serde_yaml::from_str(source_text).map_err(|error| IdenteditError::InvalidRequest {
-            message: format!("Config path create-missing could not parse YAML document: {error}"),
-        })?;
-    let parsed_new_value: serde_yaml::Value =
-        serde_yaml::from_str(new_text).map_err(|error| IdenteditError::InvalidRequest {
-            message: format!("Config path set value is not valid YAML: {error}"),
-        })?;
-    apply_yaml_set_create_missing(&mut root, path_tokens, raw_path, &parsed_new_value)?;
-
-    let rendered =
-        serde_yaml::to_string(&root).map_err(|error| IdenteditError::InvalidRequest {
-            message: format!(
-                "Config path create-missing could not serialize YAML document: {error}"
-            ),
-        })?;
-    let normalized = rendered
-        .strip_prefix("---\n")
-        .unwrap_or(&rendered)
-        .to_string();
-    Ok(apply_source_line_ending_style(&normalized, source_text))
-}
-
-fn render_toml_with_create_missing(
-    source_text: &str,
-    path_tokens: &[PathToken],
-    raw_path: &str,
-    new_text: &str,
-) -> Result<String, IdenteditError> {
-    let parse_input = if source_text.contains('\r') && !source_text.contains('\n') {
-        source_text.replace('\r', "\n")
-    } else {
-        source_text.to_string()
-    };
-    let mut root: toml::Value =
-        toml::from_str(&parse_input).map_err(|error| IdenteditError::InvalidRequest {
-            message: format!("Config path create-missing could not parse TOML document: {error}"),
-        })?;
-    let parsed_new_value = parse_toml_value_fragment(new_text)?;
-    apply_toml_set_create_missing(&mut root, path_tokens, raw_path, &parsed_new_value)?;
-
-    let rendered =
-        toml::to_string_pretty(&root).map_err(|error| IdenteditError::InvalidRequest {
-            message: format!(
-                "Config path create-missing could not serialize TOML document: {error}"
-            ),
-        })?;
-    Ok(apply_source_line_ending_style(&rendered, source_text))
-}
-
-fn parse_toml_value_fragment(fragment: &str) -> Result<toml::Value, IdenteditError> {
-    let wrapped = format!("__identedit_tmp__ = {fragment}");
-    let mut table: toml::Table =
-        toml::from_str(&wrapped).map_err(|error| IdenteditError::InvalidRequest {
-            message: format!("Config path set value is not valid TOML value text: {error}"),
-        })?;
-    table
-        .remove("__identedit_tmp__")
-        .ok_or_else(|| IdenteditError::InvalidRequest {
-            message: "Config path set value parsing produced no value".to_string(),
-        })
-}
-
 fn apply_json_set_create_missing(
     current: &mut serde_json::Value,
     path_tokens: &[PathToken],
@@ -461,136 +590,6 @@ fn apply_json_set_create_missing(
     }
 }
 
-fn apply_yaml_set_create_missing(
-    current: &mut serde_yaml::Value,
-    path_tokens: &[PathToken],
-    raw_path: &str,
-    new_value: &serde_yaml::Value,
-) -> Result<(), IdenteditError> {
-    let Some((head, tail)) = path_tokens.split_first() else {
-        *current = new_value.clone();
-        return Ok(());
-    };
-
-    match head {
-        PathToken::Key(key) => {
-            let mapping = match current {
-                serde_yaml::Value::Mapping(mapping) => mapping,
-                _ => {
-                    return Err(expected_path_container_error(
-                        raw_path,
-                        head,
-                        yaml_value_kind_name(current),
-                    ));
-                }
-            };
-            let key_value = serde_yaml::Value::String(key.clone());
-            if tail.is_empty() {
-                mapping.insert(key_value, new_value.clone());
-                return Ok(());
-            }
-            if !mapping.contains_key(&key_value) {
-                mapping.insert(key_value.clone(), empty_yaml_container_for_token(&tail[0]));
-            }
-            let child =
-                mapping
-                    .get_mut(&key_value)
-                    .ok_or_else(|| IdenteditError::InvalidRequest {
-                        message: format!("Config path '{raw_path}' segment '{key}' was not found"),
-                    })?;
-            apply_yaml_set_create_missing(child, tail, raw_path, new_value)
-        }
-        PathToken::Index(index) => {
-            let sequence = match current {
-                serde_yaml::Value::Sequence(sequence) => sequence,
-                _ => {
-                    return Err(expected_path_container_error(
-                        raw_path,
-                        head,
-                        yaml_value_kind_name(current),
-                    ));
-                }
-            };
-            if *index >= sequence.len() {
-                return Err(array_index_out_of_bounds_error(
-                    raw_path,
-                    *index,
-                    sequence.len(),
-                ));
-            }
-            if tail.is_empty() {
-                sequence[*index] = new_value.clone();
-                return Ok(());
-            }
-            apply_yaml_set_create_missing(&mut sequence[*index], tail, raw_path, new_value)
-        }
-    }
-}
-
-fn apply_toml_set_create_missing(
-    current: &mut toml::Value,
-    path_tokens: &[PathToken],
-    raw_path: &str,
-    new_value: &toml::Value,
-) -> Result<(), IdenteditError> {
-    let Some((head, tail)) = path_tokens.split_first() else {
-        *current = new_value.clone();
-        return Ok(());
-    };
-
-    match head {
-        PathToken::Key(key) => {
-            let table = match current {
-                toml::Value::Table(table) => table,
-                _ => {
-                    return Err(expected_path_container_error(
-                        raw_path,
-                        head,
-                        toml_value_kind_name(current),
-                    ));
-                }
-            };
-            if tail.is_empty() {
-                table.insert(key.clone(), new_value.clone());
-                return Ok(());
-            }
-            if !table.contains_key(key) {
-                table.insert(key.clone(), empty_toml_container_for_token(&tail[0]));
-            }
-            let child = table
-                .get_mut(key)
-                .ok_or_else(|| IdenteditError::InvalidRequest {
-                    message: format!("Config path '{raw_path}' segment '{key}' was not found"),
-                })?;
-            apply_toml_set_create_missing(child, tail, raw_path, new_value)
-        }
-        PathToken::Index(index) => {
-            let array = match current {
-                toml::Value::Array(array) => array,
-                _ => {
-                    return Err(expected_path_container_error(
-                        raw_path,
-                        head,
-                        toml_value_kind_name(current),
-                    ));
-                }
-            };
-            if *index >= array.len() {
-                return Err(array_index_out_of_bounds_error(
-                    raw_path,
-                    *index,
-                    array.len(),
-                ));
-            }
-            if tail.is_empty() {
-                array[*index] = new_value.clone();
-                return Ok(());
-            }
-            apply_toml_set_create_missing(&mut array[*index], tail, raw_path, new_value)
-        }
-    }
-}
-
 fn empty_json_container_for_token(next: &PathToken) -> serde_json::Value {
     match next {
         PathToken::Key(_) => serde_json::Value::Object(serde_json::Map::new()),
@@ -598,20 +597,6 @@ fn empty_json_container_for_token(next: &PathToken) -> serde_json::Value {
     }
 }
 
-fn empty_yaml_container_for_token(next: &PathToken) -> serde_yaml::Value {
-    match next {
-        PathToken::Key(_) => serde_yaml::Value::Mapping(serde_yaml::Mapping::new()),
-        PathToken::Index(_) => serde_yaml::Value::Sequence(Vec::new()),
-    }
-}
-
-fn empty_toml_container_for_token(next: &PathToken) -> toml::Value {
-    match next {
-        PathToken::Key(_) => toml::Value::Table(toml::Table::new()),
-        PathToken::Index(_) => toml::Value::Array(Vec::new()),
-    }
-}
-
 fn json_value_kind_name(value: &serde_json::Value) -> &'static str {
     match value {
         serde_json::Value::Null => "null",
@@ -623,30 +608,6 @@ fn json_value_kind_name(value: &serde_json::Value) -> &'static str {
     }
 }
 
-fn yaml_value_kind_name(value: &serde_yaml::Value) -> &'static str {
-    match value {
-        serde_yaml::Value::Null => "null",
-        serde_yaml::Value::Bool(_) => "boolean",
-        serde_yaml::Value::Number(_) => "number",
-        serde_yaml::Value::String(_) => "string",
-        serde_yaml::Value::Sequence(_) => "sequence",
-        serde_yaml::Value::Mapping(_) => "mapping",
-        serde_yaml::Value::Tagged(_) => "tagged",
-    }
-}
-
-fn toml_value_kind_name(value: &toml::Value) -> &'static str {
-    match value {
-        toml::Value::String(_) => "string",
-        toml::Value::Integer(_) => "integer",
-        toml::Value::Float(_) => "float",
-        toml::Value::Boolean(_) => "boolean",
-        toml::Value::Datetime(_) => "datetime",
-        toml::Value::Array(_) => "array",
-        toml::Value::Table(_) => "table",
-    }
-}
-
 fn array_index_out_of_bounds_error(
     raw_path: &str,
     expected_index: usize,
@@ -685,7 +646,11 @@ fn render_append_array_replacement(
             raw_path,
             &indentation_before_offset(source_text, container_span.start),
         ),
-        _ => Err(append_requires_array_error(raw_path, container_kind)),
+        _ => Err(array_target_required_error(
+            raw_path,
+            "append",
+            container_kind,
+        )),
     }
 }
 
@@ -696,12 +661,12 @@ fn append_to_comma_delimited_array_text(
 ) -> Result<String, IdenteditError> {
     let open = array_text
         .find('[')
-        .ok_or_else(|| append_requires_array_error(raw_path, "unknown"))?;
+        .ok_or_else(|| array_target_required_error(raw_path, "append", "unknown"))?;
     let close = array_text
         .rfind(']')
-        .ok_or_else(|| append_requires_array_error(raw_path, "unknown"))?;
+        .ok_or_else(|| array_target_required_error(raw_path, "append", "unknown"))?;
     if open >= close {
-        return Err(append_requires_array_error(raw_path, "unknown"));
+        return Err(array_target_required_error(raw_path, "append", "unknown"));
     }
 
     let inner = &array_text[open + 1..close];
@@ -743,7 +708,11 @@ fn append_to_block_sequence_text(
         .filter(|value| !value.is_empty())
         .unwrap_or_else(|| base_indent.to_string());
     if indent.is_empty() {
-        return Err(append_requires_array_error(raw_path, "block_sequence"));
+        return Err(array_target_required_error(
+            raw_path,
+            "append",
+            "block_sequence",
+        ));
     }
     let separator = if sequence_text.ends_with('\n') || sequence_text.ends_with('\r') {
         ""
@@ -816,10 +785,22 @@ fn line_ending_literal(source_text: &str) -> &'static str {
     }
 }
 
-fn append_requires_array_error(raw_path: &str, actual_kind: &str) -> IdenteditError {
+fn array_target_required_error(
+    raw_path: &str,
+    operation_name: &str,
+    actual_kind: &str,
+) -> IdenteditError {
     IdenteditError::InvalidRequest {
         message: format!(
-            "Config path '{raw_path}' append requires an array/sequence target, found node kind '{actual_kind}'"
+            "Config path '{raw_path}' {operation_name} requires an array/sequence target, found node kind '{actual_kind}'"
+        ),
+    }
+}
+
+fn key_target_required_error(raw_path: &str, operation_name: &str) -> IdenteditError {
+    IdenteditError::InvalidRequest {
+        message: format!(
+            "Config path '{raw_path}' {operation_name} requires a path that ends in a mapping key"
         ),
     }
 }
@@ -831,101 +812,6 @@ fn is_missing_config_path_error(error: &IdenteditError) -> bool {
     )
 }
 
-fn validate_yaml_create_missing_safety(
-    tree: &Tree,
-    source_text: &str,
-) -> Result<(), IdenteditError> {
-    let document_count = count_nodes_by_kind(tree.root_node(), "document");
-    if document_count > 1 {
-        return Err(IdenteditError::InvalidRequest {
-            message:
-                "Config path create-missing does not support multiple YAML documents in one file"
-                    .to_string(),
-        });
-    }
-
-    if has_yaml_anchor_or_alias(tree.root_node(), source_text) {
-        return Err(IdenteditError::InvalidRequest {
-            message: "Config path create-missing does not support YAML anchor/alias documents"
-                .to_string(),
-        });
-    }
-
-    if has_yaml_comments(tree.root_node()) {
-        return Err(IdenteditError::InvalidRequest {
-            message: "Config path create-missing does not support YAML comments yet".to_string(),
-        });
-    }
-
-    Ok(())
-}
-
-fn has_yaml_anchor_or_alias(root: Node<'_>, source_text: &str) -> bool {
-    if source_text.contains("<<: *") {
-        return true;
-    }
-
-    let mut stack = vec![root];
-    while let Some(node) = stack.pop() {
-        let kind = node.kind();
-        if kind.contains("anchor") || kind.contains("alias") {
-            return true;
-        }
-        for index in 0..node.child_count() {
-            if let Some(child) = node.child(index as u32) {
-                stack.push(child);
-            }
-        }
-    }
-    false
-}
-
-fn count_nodes_by_kind(root: Node<'_>, expected_kind: &str) -> usize {
-    let mut count = 0usize;
-    let mut stack = vec![root];
-    while let Some(node) = stack.pop() {
-        if node.kind() == expected_kind {
-            count += 1;
-        }
-        for index in 0..node.child_count() {
-            if let Some(child) = node.child(index as u32) {
-                stack.push(child);
-            }
-        }
-    }
-    count
-}
-
-fn has_yaml_comments(root: Node<'_>) -> bool {
-    let mut stack = vec![root];
-    while let Some(node) = stack.pop() {
-        if node.kind().contains("comment") {
-            return true;
-        }
-        for index in 0..node.child_count() {
-            if let Some(child) = node.child(index as u32) {
-                stack.push(child);
-            }
-        }
-    }
-    false
-}
-
-fn has_toml_comments(root: Node<'_>) -> bool {
-    let mut stack = vec![root];
-    while let Some(node) = stack.pop() {
-        if node.kind().contains("comment") {
-            return true;
-        }
-        for index in 0..node.child_count() {
-            if let Some(child) = node.child(index as u32) {
-                stack.push(child);
-            }
-        }
-    }
-    false
-}
-
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum LineEndingStyle {
     Lf,
@@ -1088,6 +974,21 @@ fn provider_name(format: &ConfigFormat) -> &'static str {
 }
 
 fn parse_config_path(raw_path: &str) -> Result<Vec<PathToken>, IdenteditError> {
+    parse_config_path_pattern(raw_path)?
+        .into_iter()
+        .map(|segment| match segment {
+            PathSegment::Token(token) => Ok(token),
+            PathSegment::AnyKey | PathSegment::AnyIndex => Err(IdenteditError::InvalidRequest {
+                message: format!(
+                    "Config path '{}' uses wildcard segments ('*', '[*]'), which this operation does not support",
+                    raw_path.trim()
+                ),
+            }),
+        })
+        .collect()
+}
+
+fn parse_config_path_pattern(raw_path: &str) -> Result<Vec<PathSegment>, IdenteditError> {
     let path = raw_path.trim();
     if path.is_empty() {
         return Err(IdenteditError::InvalidRequest {
@@ -1102,8 +1003,8 @@ fn parse_config_path(raw_path: &str) -> Result<Vec<PathToken>, IdenteditError> {
     while index < bytes.len() {
         match bytes[index] {
             b'[' => {
-                let (value, consumed) = parse_index_segment(path, index)?;
-                tokens.push(PathToken::Index(value));
+                let (segment, consumed) = parse_index_segment(path, index)?;
+                tokens.push(segment);
                 index = consumed;
             }
             b'.' => {
@@ -1113,6 +1014,10 @@ fn parse_config_path(raw_path: &str) -> Result<Vec<PathToken>, IdenteditError> {
                     ),
                 });
             }
+            b'*' => {
+                tokens.push(PathSegment::AnyKey);
+                index += 1;
+            }
             _ => {
                 let start = index;
                 while index < bytes.len() && is_key_char(bytes[index]) {
@@ -1126,13 +1031,15 @@ fn parse_config_path(raw_path: &str) -> Result<Vec<PathToken>, IdenteditError> {
                         ),
                     });
                 }
-                tokens.push(PathToken::Key(path[start..index].to_string()));
+                tokens.push(PathSegment::Token(PathToken::Key(
+                    path[start..index].to_string(),
+                )));
             }
         }
 
         while index < bytes.len() && bytes[index] == b'[' {
-            let (value, consumed) = parse_index_segment(path, index)?;
-            tokens.push(PathToken::Index(value));
+            let (segment, consumed) = parse_index_segment(path, index)?;
+            tokens.push(segment);
             index = consumed;
         }
 
@@ -1162,8 +1069,11 @@ fn parse_config_path(raw_path: &str) -> Result<Vec<PathToken>, IdenteditError> {
     Ok(tokens)
 }
 
-fn parse_index_segment(path: &str, start: usize) -> Result<(usize, usize), IdenteditError> {
+fn parse_index_segment(path: &str, start: usize) -> Result<(PathSegment, usize), IdenteditError> {
     let bytes = path.as_bytes();
+    if bytes.get(start + 1) == Some(&b'*') && bytes.get(start + 2) == Some(&b']') {
+        return Ok((PathSegment::AnyIndex, start + 3));
+    }
     let mut cursor = start + 1;
     let digit_start = cursor;
     while cursor < bytes.len() && bytes[cursor].is_ascii_digit() {
@@ -1196,7 +1106,7 @@ fn parse_index_segment(path: &str, start: usize) -> Result<(usize, usize), Ident
                 ),
             })?;
 
-    Ok((value, cursor + 1))
+    Ok((PathSegment::Token(PathToken::Index(value)), cursor + 1))
 }
 
 fn is_key_char(byte: u8) -> bool {
@@ -1246,6 +1156,13 @@ fn resolve_json_path(
                 }
 
                 let matched_pair = unique_match(raw_path, token, matches)?;
+                let key_node = matched_pair.child_by_field_name("key").ok_or_else(|| {
+                    IdenteditError::InvalidRequest {
+                        message: format!(
+                            "Config path '{raw_path}' matched key '{expected_key}' without a key node"
+                        ),
+                    }
+                })?;
                 let value_node = matched_pair
                     .child_by_field_name("value")
                     .ok_or_else(|| IdenteditError::InvalidRequest {
@@ -1261,10 +1178,13 @@ fn resolve_json_path(
                             container_kind: current.kind().to_string(),
                             replace_span: span_from_node(value_node),
                         },
-                        ConfigPathOperation::Append { .. } => {
+                        ConfigPathOperation::Append { .. }
+                        | ConfigPathOperation::InsertAt { .. }
+                        | ConfigPathOperation::RemoveAt { .. } => {
                             if value_node.kind() != "array" {
-                                return Err(append_requires_array_error(
+                                return Err(array_target_required_error(
                                     raw_path,
+                                    operation.name(),
                                     value_node.kind(),
                                 ));
                             }
@@ -1274,6 +1194,11 @@ fn resolve_json_path(
                                 replace_span: span_from_node(value_node),
                             }
                         }
+                        ConfigPathOperation::RenameKey { .. } => ResolvedContainerEdit {
+                            container_span: span_from_node(current),
+                            container_kind: current.kind().to_string(),
+                            replace_span: span_from_node(key_node),
+                        },
                         ConfigPathOperation::Delete => ResolvedContainerEdit {
                             container_span: span_from_node(current),
                             container_kind: current.kind().to_string(),
@@ -1310,9 +1235,15 @@ fn resolve_json_path(
                             container_kind: current.kind().to_string(),
                             replace_span: span_from_node(*entry),
                         },
-                        ConfigPathOperation::Append { .. } => {
+                        ConfigPathOperation::Append { .. }
+                        | ConfigPathOperation::InsertAt { .. }
+                        | ConfigPathOperation::RemoveAt { .. } => {
                             if entry.kind() != "array" {
-                                return Err(append_requires_array_error(raw_path, entry.kind()));
+                                return Err(array_target_required_error(
+                                    raw_path,
+                                    operation.name(),
+                                    entry.kind(),
+                                ));
                             }
                             ResolvedContainerEdit {
                                 container_span: span_from_node(*entry),
@@ -1320,6 +1251,9 @@ fn resolve_json_path(
                                 replace_span: span_from_node(*entry),
                             }
                         }
+                        ConfigPathOperation::RenameKey { .. } => {
+                            return Err(key_target_required_error(raw_path, operation.name()));
+                        }
                         ConfigPathOperation::Delete => ResolvedContainerEdit {
                             container_span: span_from_node(current),
                             container_kind: current.kind().to_string(),
@@ -1388,6 +1322,13 @@ fn resolve_yaml_path(
                 }
 
                 let matched_pair = unique_match(raw_path, token, matches)?;
+                let key_node = matched_pair.child_by_field_name("key").ok_or_else(|| {
+                    IdenteditError::InvalidRequest {
+                        message: format!(
+                            "Config path '{raw_path}' matched key '{expected_key}' without a key node"
+                        ),
+                    }
+                })?;
                 let value_node = matched_pair
                     .child_by_field_name("value")
                     .and_then(yaml_unwrap_node)
@@ -1404,12 +1345,15 @@ fn resolve_yaml_path(
                             container_kind: current.kind().to_string(),
                             replace_span: span_from_node(value_node),
                         },
-                        ConfigPathOperation::Append { .. } => {
+                        ConfigPathOperation::Append { .. }
+                        | ConfigPathOperation::InsertAt { .. }
+                        | ConfigPathOperation::RemoveAt { .. } => {
                             if value_node.kind() != "block_sequence"
                                 && value_node.kind() != "flow_sequence"
                             {
-                                return Err(append_requires_array_error(
+                                return Err(array_target_required_error(
                                     raw_path,
+                                    operation.name(),
                                     value_node.kind(),
                                 ));
                             }
@@ -1419,6 +1363,11 @@ fn resolve_yaml_path(
                                 replace_span: span_from_node(value_node),
                             }
                         }
+                        ConfigPathOperation::RenameKey { .. } => ResolvedContainerEdit {
+                            container_span: span_from_node(current),
+                            container_kind: current.kind().to_string(),
+                            replace_span: span_from_node(key_node),
+                        },
                         ConfigPathOperation::Delete => ResolvedContainerEdit {
                             container_span: span_from_node(current),
                             container_kind: current.kind().to_string(),
@@ -1443,13 +1392,12 @@ fn resolve_yaml_path(
                     let item = items.get(*expected_index).ok_or_else(|| {
                         array_index_out_of_bounds_error(raw_path, *expected_index, items.len())
                     })?;
-                    let value_node = item.child(0).and_then(yaml_unwrap_node).ok_or_else(|| {
-                        IdenteditError::InvalidRequest {
+                    let value_node =
+                        yaml_unwrap_node(*item).ok_or_else(|| IdenteditError::InvalidRequest {
                             message: format!(
                                 "Config path '{raw_path}' points at empty YAML sequence item"
                             ),
-                        }
-                    })?;
+                        })?;
                     if last {
                         return Ok(match operation {
                             ConfigPathOperation::Set { .. } => ResolvedContainerEdit {
@@ -1457,12 +1405,15 @@ fn resolve_yaml_path(
                                 container_kind: current.kind().to_string(),
                                 replace_span: span_from_node(value_node),
                             },
-                            ConfigPathOperation::Append { .. } => {
+                            ConfigPathOperation::Append { .. }
+                            | ConfigPathOperation::InsertAt { .. }
+                            | ConfigPathOperation::RemoveAt { .. } => {
                                 if value_node.kind() != "block_sequence"
                                     && value_node.kind() != "flow_sequence"
                                 {
-                                    return Err(append_requires_array_error(
+                                    return Err(array_target_required_error(
                                         raw_path,
+                                        operation.name(),
                                         value_node.kind(),
                                     ));
                                 }
@@ -1472,6 +1423,9 @@ fn resolve_yaml_path(
                                     replace_span: span_from_node(value_node),
                                 }
                             }
+                            ConfigPathOperation::RenameKey { .. } => {
+                                return Err(key_target_required_error(raw_path, operation.name()));
+                            }
                             ConfigPathOperation::Delete => ResolvedContainerEdit {
                                 container_span: span_from_node(current),
                                 container_kind: current.kind().to_string(),
@@ -1499,10 +1453,16 @@ fn resolve_yaml_path(
                                 container_kind: current.kind().to_string(),
                                 replace_span: span_from_node(next),
                             },
-                            ConfigPathOperation::Append { .. } => {
+                            ConfigPathOperation::Append { .. }
+                            | ConfigPathOperation::InsertAt { .. }
+                            | ConfigPathOperation::RemoveAt { .. } => {
                                 if next.kind() != "block_sequence" && next.kind() != "flow_sequence"
                                 {
-                                    return Err(append_requires_array_error(raw_path, next.kind()));
+                                    return Err(array_target_required_error(
+                                        raw_path,
+                                        operation.name(),
+                                        next.kind(),
+                                    ));
                                 }
                                 ResolvedContainerEdit {
                                     container_span: span_from_node(next),
@@ -1510,6 +1470,9 @@ fn resolve_yaml_path(
                                     replace_span: span_from_node(next),
                                 }
                             }
+                            ConfigPathOperation::RenameKey { .. } => {
+                                return Err(key_target_required_error(raw_path, operation.name()));
+                            }
                             ConfigPathOperation::Delete => ResolvedContainerEdit {
                                 container_span: span_from_node(current),
                                 container_kind: current.kind().to_string(),
@@ -1579,9 +1542,15 @@ fn resolve_toml_path(
             selected.container_kind.clone(),
             selected.set_span,
         ),
-        ConfigPathOperation::Append { .. } => {
+        ConfigPathOperation::Append { .. }
+        | ConfigPathOperation::InsertAt { .. }
+        | ConfigPathOperation::RemoveAt { .. } => {
             if selected.set_kind != "array" {
-                return Err(append_requires_array_error(raw_path, &selected.set_kind));
+                return Err(array_target_required_error(
+                    raw_path,
+                    operation.name(),
+                    &selected.set_kind,
+                ));
             }
             (
                 selected.set_span,
@@ -1589,6 +1558,16 @@ fn resolve_toml_path(
                 selected.set_span,
             )
         }
+        ConfigPathOperation::RenameKey { .. } => {
+            let key_span = selected
+                .key_span
+                .ok_or_else(|| key_target_required_error(raw_path, operation.name()))?;
+            (
+                selected.container_span,
+                selected.container_kind.clone(),
+                key_span,
+            )
+        }
         ConfigPathOperation::Delete => (
             selected.container_span,
             selected.container_kind.clone(),
@@ -1654,7 +1633,7 @@ fn collect_toml_pair_candidates(
     container: Node<'_>,
     out: &mut Vec<TomlCandidate>,
 ) {
-    let Some((key_segments, value_node)) = toml_pair_key_and_value(pair, source) else {
+    let Some((key_node, key_segments, value_node)) = toml_pair_key_and_value(pair, source) else {
         return;
     };
 
@@ -1668,6 +1647,7 @@ fn collect_toml_pair_candidates(
         set_span: span_from_node(value_node),
         set_kind: value_node.kind().to_string(),
         delete_entry_span: span_from_node(pair),
+        key_span: Some(span_from_node(toml_last_key_segment(key_node))),
     });
 
     collect_toml_nested_value_candidates(value_node, source, full_path, out);
@@ -1700,6 +1680,7 @@ fn collect_toml_nested_value_candidates(
                     set_span: span_from_node(element),
                     set_kind: element.kind().to_string(),
                     delete_entry_span: span_from_node(element),
+                    key_span: None,
                 });
 
                 collect_toml_nested_value_candidates(element, source, indexed_path, out);
@@ -1722,7 +1703,10 @@ fn toml_table_prefix(table: Node<'_>, source: &[u8]) -> Vec<PathToken> {
     prefix
 }
 
-fn toml_pair_key_and_value<'a>(pair: Node<'a>, source: &[u8]) -> Option<(Vec<String>, Node<'a>)> {
+fn toml_pair_key_and_value<'a>(
+    pair: Node<'a>,
+    source: &[u8],
+) -> Option<(Node<'a>, Vec<String>, Node<'a>)> {
     let children = named_children(pair);
     if children.len() < 2 {
         return None;
@@ -1743,7 +1727,17 @@ fn toml_pair_key_and_value<'a>(pair: Node<'a>, source: &[u8]) -> Option<(Vec<Str
         return None;
     }
 
-    Some((key_segments, value_node))
+    Some((key_node, key_segments, value_node))
+}
+
+/// The key node naming the entry itself: `c` in `a.b.c = 1`.
+fn toml_last_key_segment(key_node: Node<'_>) -> Node<'_> {
+    if key_node.kind() == "dotted_key"
+        && let Some(last) = named_children(key_node).last()
+    {
+        return toml_last_key_segment(*last);
+    }
+    key_node
 }
 
 fn toml_key_segments(key_node: Node<'_>, source: &[u8]) -> Vec<String> {
@@ -1782,10 +1776,10 @@ fn json_root_value(root: Node<'_>) -> Option<Node<'_>> {
 fn yaml_root_value(root: Node<'_>) -> Option<Node<'_>> {
     let mut node = root;
     if node.kind() == "stream" {
-        node = first_named_child(node)?;
+        node = first_yaml_content_child(node)?;
     }
     if node.kind() == "document" {
-        node = first_named_child(node)?;
+        node = first_yaml_content_child(node)?;
     }
     yaml_unwrap_node(node)
 }
@@ -1794,13 +1788,21 @@ fn yaml_unwrap_node(mut node: Node<'_>) -> Option<Node<'_>> {
     loop {
         match node.kind() {
             "block_node" | "flow_node" | "block_sequence_item" => {
-                node = first_named_child(node)?;
+                node = first_yaml_content_child(node)?;
             }
             _ => return Some(node),
         }
     }
 }
 
+/// First named child that carries content, skipping comments and node properties
+/// (`&anchor`, `!tag`) that precede the value.
+fn first_yaml_content_child(node: Node<'_>) -> Option<Node<'_>> {
+    named_children(node)
+        .into_iter()
+        .find(|child| !matches!(child.kind(), "comment" | "anchor" | "tag"))
+}
+
 fn yaml_key_text(key_node: Node<'_>, source: &[u8]) -> Option<String> {
     let node = yaml_unwrap_node(key_node)?;
     let raw = node_text(node, source)?;
@@ -1984,6 +1986,52 @@ fn adjusted_delete_span_for_container(
     adjusted_line_delimited_delete_span(source, container_span, entry_span)
 }
 
+/// `span`, from [`adjusted_delete_span_for_container`], widened so that an entry on a line
+/// of its own takes the whole line instead of leaving blank indentation behind. `remove_at`,
+/// `move`, and `remove_dependency` delete with it; `delete` keeps the narrower span.
+fn whole_line_delete_span(
+    source: &[u8],
+    container_span: Span,
+    container_kind: &str,
+    span: Span,
+) -> Span {
+    let is_space = |byte: u8| byte == b' ' || byte == b'\t';
+    let mut widened = span;
+    if is_comma_delimited_container(container_kind) {
+        // Only a span that took the entry's trailing comma can end at a line break.
+        let took_trailing_comma = source[span.start..span.end]
+            .iter()
+            .rev()
+            .find(|byte| !is_space(**byte))
+            == Some(&b',');
+        if !took_trailing_comma || span.end >= container_span.end {
+            return span;
+        }
+        let mut line_start = span.start;
+        while line_start > container_span.start && is_space(source[line_start - 1]) {
+            line_start -= 1;
+        }
+        let owns_line =
+            line_start > container_span.start && matches!(source[line_start - 1], b'\n' | b'\r');
+        let newline_len = match source[span.end] {
+            b'\r' if source.get(span.end + 1) == Some(&b'\n') => 2,
+            b'\r' | b'\n' => 1,
+            _ => 0,
+        };
+        if owns_line && newline_len > 0 {
+            widened.start = line_start;
+            widened.end += newline_len;
+        }
+    } else if span.start == container_span.start {
+        // The container span begins at its first entry, so that entry's indentation lies
+        // outside the span; take the next entry's indentation instead to keep columns aligned.
+        while widened.end < container_span.end && is_space(source[widened.end]) {
+            widened.end += 1;
+        }
+    }
+    widened
+}
+
 fn is_comma_delimited_container(kind: &str) -> bool {
     matches!(
         kind,
@@ -2066,6 +2114,18 @@ fn adjusted_line_delimited_delete_span(
     Span { start, end }
 }
 
+fn find_node_for_span<'tree>(tree: &'tree Tree, span: Span, kind: &str) -> Option<Node<'tree>> {
+    let mut node = tree
+        .root_node()
+        .descendant_for_byte_range(span.start, span.end)?;
+    loop {
+        if node.kind() == kind && node.start_byte() == span.start && node.end_byte() == span.end {
+            return Some(node);
+        }
+        node = node.parent()?;
+    }
+}
+
 fn named_children(node: Node<'_>) -> Vec<Node<'_>> {
     let mut cursor = node.walk();
     node.named_children(&mut cursor).collect()
@@ -2086,8 +2146,56 @@ fn span_from_node(node: Node<'_>) -> Span {
 mod tests {
     use std::path::Path;
 
-    use super::{ConfigPathOperation, detect_config_format, parse_config_path};
-    use crate::patch::config_path::{PathToken, path_tokens_display};
+    use super::{
+        ConfigPathOperation, adjusted_delete_span_for_container, detect_config_format,
+        parse_config_path, whole_line_delete_span,
+    };
+    use crate::handle::Span;
+    use crate::patch::config_path::{
+        PathSegment, PathToken, parse_config_path_pattern, path_tokens_display,
+    };
+
+    /// `source` without the entry at `entry` of the container at `container`, deleted with
+    /// the `delete` span and then with the whole-line span.
+    fn delete_and_remove(
+        source: &str,
+        container: Span,
+        kind: &str,
+        entry: Span,
+    ) -> (String, String) {
+        let without = |span: Span| format!("{}{}", &source[..span.start], &source[span.end..]);
+        let deleted = adjusted_delete_span_for_container(source.as_bytes(), container, kind, entry);
+        let removed = whole_line_delete_span(source.as_bytes(), container, kind, deleted);
+        (without(deleted), without(removed))
+    }
+
+    #[test]
+    fn delete_keeps_its_span_where_whole_line_removal_takes_the_line() {
+        let source = "[\n  1,\n  2\n]";
+        let container = Span {
+            start: 0,
+            end: source.len(),
+        };
+        let (deleted, removed) =
+            delete_and_remove(source, container, "array", Span { start: 4, end: 5 });
+        assert_eq!(deleted, "[\n  \n  2\n]");
+        assert_eq!(removed, "[\n  2\n]");
+
+        let (deleted, removed) =
+            delete_and_remove("[1, 2]", container, "array", Span { start: 1, end: 2 });
+        assert_eq!(deleted, "[2]");
+        assert_eq!(removed, "[2]");
+
+        let source = "items:\n  - a\n  - b\n";
+        let (deleted, removed) = delete_and_remove(
+            source,
+            Span { start: 9, end: 18 },
+            "block_sequence",
+            Span { start: 9, end: 12 },
+        );
+        assert_eq!(deleted, "items:\n    - b\n");
+        assert_eq!(removed, "items:\n  - b\n");
+    }
 
     #[test]
     fn parse_config_path_supports_dot_and_index_tokens() {
@@ -2118,6 +2226,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_config_path_pattern_accepts_key_and_index_wildcards() {
+        let parsed =
+            parse_config_path_pattern("services.*.ports[*]").expect("pattern should parse");
+        assert_eq!(
+            parsed,
+            vec![
+                PathSegment::Token(PathToken::Key("services".to_string())),
+                PathSegment::AnyKey,
+                PathSegment::Token(PathToken::Key("ports".to_string())),
+                PathSegment::AnyIndex,
+            ]
+        );
+
+        parse_config_path_pattern("services.a*").expect_err("partial key wildcard must fail");
+        parse_config_path("services.*").expect_err("plain paths reject wildcards");
+    }
+
     #[test]
     fn detect_config_format_accepts_supported_extensions() {
         assert_eq!(
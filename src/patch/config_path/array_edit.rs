@@ -0,0 +1,153 @@
+//! Index-addressed array edits (`insert_at` / `remove_at`).
+//!
+//! The config path resolves to the array itself; the index picks the element to
+//! insert before or remove, so neighbouring elements keep their formatting.
+
+use tree_sitter::{Node, Tree};
+
+use super::{
+    ConfigPathOperation, ResolvedContainerEdit, adjusted_delete_span_for_container,
+    find_node_for_span, indentation_before_offset, is_comma_delimited_container,
+    line_ending_literal, named_children, render_append_array_replacement, span_from_node,
+    whole_line_delete_span,
+};
+use crate::error::IdenteditError;
+use crate::handle::Span;
+
+pub(super) fn resolve_array_index_edit(
+    tree: &Tree,
+    source_text: &str,
+    array: ResolvedContainerEdit,
+    operation: &ConfigPathOperation,
+    raw_path: &str,
+) -> Result<(ResolvedContainerEdit, String), IdenteditError> {
+    let array_node = find_node_for_span(tree, array.container_span, &array.container_kind)
+        .ok_or_else(|| IdenteditError::InvalidRequest {
+            message: format!(
+                "Config path '{raw_path}' resolved to an array span that is no longer present in the syntax tree"
+            ),
+        })?;
+    let items = array_items(array_node);
+
+    match operation {
+        ConfigPathOperation::InsertAt { index, new_text } => {
+            if *index > items.len() {
+                return Err(index_out_of_range_error(
+                    raw_path,
+                    operation.name(),
+                    *index,
+                    items.len(),
+                    format!("valid positions are 0..={}", items.len()),
+                ));
+            }
+            if *index == items.len() {
+                let replacement = render_append_array_replacement(
+                    source_text,
+                    array.container_span,
+                    &array.container_kind,
+                    new_text,
+                    raw_path,
+                )?;
+                return Ok((
+                    ResolvedContainerEdit {
+                        replace_span: array.container_span,
+                        ..array
+                    },
+                    replacement,
+                ));
+            }
+
+            let insert_at = items[*index].start_byte();
+            let line_ending = line_ending_literal(source_text);
+            let indent = indentation_before_offset(source_text, insert_at);
+            let replacement = if array.container_kind == "block_sequence" {
+                format!("- {new_text}{line_ending}{indent}")
+            } else if spans_multiple_lines(source_text, array.container_span) {
+                format!("{new_text},{line_ending}{indent}")
+            } else {
+                format!("{new_text}, ")
+            };
+            Ok((
+                ResolvedContainerEdit {
+                    replace_span: Span {
+                        start: insert_at,
+                        end: insert_at,
+                    },
+                    ..array
+                },
+                replacement,
+            ))
+        }
+        ConfigPathOperation::RemoveAt { index } => {
+            let item = items.get(*index).ok_or_else(|| {
+                index_out_of_range_error(
+                    raw_path,
+                    operation.name(),
+                    *index,
+                    items.len(),
+                    if items.is_empty() {
+                        "the array is empty".to_string()
+                    } else {
+                        format!("valid positions are 0..={}", items.len() - 1)
+                    },
+                )
+            })?;
+            let source = source_text.as_bytes();
+            let replace_span = whole_line_delete_span(
+                source,
+                array.container_span,
+                &array.container_kind,
+                adjusted_delete_span_for_container(
+                    source,
+                    array.container_span,
+                    &array.container_kind,
+                    span_from_node(*item),
+                ),
+            );
+            Ok((
+                ResolvedContainerEdit {
+                    replace_span,
+                    ..array
+                },
+                String::new(),
+            ))
+        }
+        other => Err(IdenteditError::InvalidRequest {
+            message: format!(
+                "Config path '{raw_path}' {} is not an index-addressed array operation",
+                other.name()
+            ),
+        }),
+    }
+}
+
+fn array_items(array: Node<'_>) -> Vec<Node<'_>> {
+    named_children(array)
+        .into_iter()
+        .filter(|child| {
+            if is_comma_delimited_container(array.kind()) {
+                child.kind() != "comment"
+            } else {
+                child.kind() == "block_sequence_item"
+            }
+        })
+        .collect()
+}
+
+fn spans_multiple_lines(source_text: &str, span: Span) -> bool {
+    source_text[span.start..span.end].contains(['\n', '\r'])
+}
+
+fn index_out_of_range_error(
+    raw_path: &str,
+    operation_name: &str,
+    index: usize,
+    len: usize,
+    valid_range: String,
+) -> IdenteditError {
+    IdenteditError::InvalidRequest {
+        message: format!(
+            "Config path '{raw_path}' {operation_name} index {index} is out of range (len={len}); {valid_range}"
+        ),
+    }
+}
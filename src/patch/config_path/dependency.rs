@@ -0,0 +1,507 @@
+//! Dependency edits for `Cargo.toml` and `package.json` (`add_dependency`,
+//! `remove_dependency`, `set_dependency_version`).
+//!
+//! The config path names the dependency section (`dev-dependencies`,
+//! `devDependencies`, ...). Cargo manifests are edited through `toml_edit` so comments
+//! and table layout survive; `package.json` is edited as text so key order and
+//! indentation stay as written. A new entry keeps an alphabetically sorted section
+//! sorted and is appended to an unsorted one.
+
+use std::path::Path;
+use std::str::FromStr;
+
+use toml_edit::{DocumentMut, InlineTable, Item, TableLike, Value};
+
+use super::{
+    ConfigFormat, ConfigPathOperation, PathToken, ResolvedConfigPatch, apply_patch_to_text,
+    apply_source_line_ending_style, build_patch_from_text_change, find_node_for_span,
+    indentation_before_offset, json_root_value, line_ending_literal, parse_config_path,
+    parse_tree_for_format, path_tokens_display, read_config_source,
+    relocate::{insert_json_entry, json_object_pairs, json_pair_key},
+    resolve_config_path_operation_for_source, resolve_path_in_tree,
+    toml_insert::render_toml_with_create_missing,
+    whole_line_delete_span,
+};
+use crate::error::IdenteditError;
+use crate::provider::ProviderRegistry;
+
+const CARGO_SECTIONS: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+const NPM_SECTIONS: [&str; 4] = [
+    "dependencies",
+    "devDependencies",
+    "peerDependencies",
+    "optionalDependencies",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DependencyOperation {
+    Add { name: String, version: String },
+    Remove { name: String },
+    SetVersion { name: String, version: String },
+}
+
+impl DependencyOperation {
+    fn name(&self) -> &str {
+        match self {
+            DependencyOperation::Add { name, .. }
+            | DependencyOperation::Remove { name }
+            | DependencyOperation::SetVersion { name, .. } => name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Manifest {
+    Cargo,
+    Npm,
+}
+
+pub fn resolve_config_dependency_operation(
+    file: &Path,
+    raw_path: &str,
+    expected_file_hash: Option<&str>,
+    operation: DependencyOperation,
+    registry: &ProviderRegistry,
+) -> Result<ResolvedConfigPatch, IdenteditError> {
+    let manifest = detect_manifest(file)?;
+    let source_text = read_config_source(file, expected_file_hash)?;
+    let section = parse_config_path(raw_path)?;
+    check_dependency_section(manifest, &section, raw_path)?;
+    if operation.name().is_empty() {
+        return Err(IdenteditError::InvalidRequest {
+            message: format!(
+                "Config path '{raw_path}' dependency operations require a non-empty name"
+            ),
+        });
+    }
+    if let DependencyOperation::Add { version, .. }
+    | DependencyOperation::SetVersion { version, .. } = &operation
+        && version.trim().is_empty()
+    {
+        return Err(IdenteditError::InvalidRequest {
+            message: format!(
+                "Config path '{raw_path}' dependency '{}' requires a non-empty version",
+                operation.name()
+            ),
+        });
+    }
+
+    let updated = match manifest {
+        Manifest::Cargo => edit_cargo_manifest(&source_text, &section, raw_path, &operation)?,
+        Manifest::Npm => {
+            edit_package_json(file, &source_text, &section, raw_path, &operation, registry)?
+        }
+    };
+    build_patch_from_text_change(file, &source_text, &updated, registry)
+}
+
+fn detect_manifest(file: &Path) -> Result<Manifest, IdenteditError> {
+    match file.file_name().and_then(|name| name.to_str()) {
+        Some("Cargo.toml") => Ok(Manifest::Cargo),
+        Some("package.json") => Ok(Manifest::Npm),
+        _ => Err(IdenteditError::InvalidRequest {
+            message: format!(
+                "Dependency operations support only Cargo.toml and package.json; '{}' is neither",
+                file.display()
+            ),
+        }),
+    }
+}
+
+fn check_dependency_section(
+    manifest: Manifest,
+    section: &[PathToken],
+    raw_path: &str,
+) -> Result<(), IdenteditError> {
+    let keys = section
+        .iter()
+        .map(|token| match token {
+            PathToken::Key(key) => Some(key.as_str()),
+            PathToken::Index(_) => None,
+        })
+        .collect::<Option<Vec<_>>>()
+        .unwrap_or_default();
+    let valid = match (manifest, keys.as_slice()) {
+        (Manifest::Cargo, [name]) | (Manifest::Cargo, ["target", _, name]) => {
+            CARGO_SECTIONS.contains(name)
+        }
+        (Manifest::Cargo, ["workspace", "dependencies"]) => true,
+        (Manifest::Npm, [name]) => NPM_SECTIONS.contains(name),
+        _ => false,
+    };
+    if valid {
+        return Ok(());
+    }
+
+    let expected = match manifest {
+        Manifest::Cargo => {
+            "dependencies, dev-dependencies, build-dependencies, workspace.dependencies, or target.<triple>.<section>"
+                .to_string()
+        }
+        Manifest::Npm => NPM_SECTIONS.join(", "),
+    };
+    Err(IdenteditError::InvalidRequest {
+        message: format!(
+            "Config path '{raw_path}' is not a dependency section (expected {expected})"
+        ),
+    })
+}
+
+fn edit_cargo_manifest(
+    source_text: &str,
+    section: &[PathToken],
+    raw_path: &str,
+    operation: &DependencyOperation,
+) -> Result<String, IdenteditError> {
+    let mut document =
+        DocumentMut::from_str(source_text).map_err(|error| IdenteditError::InvalidRequest {
+            message: format!("Dependency operation could not parse Cargo.toml: {error}"),
+        })?;
+
+    let Some(table) = cargo_section_mut(&mut document, section) else {
+        return match operation {
+            DependencyOperation::Add { name, version } => {
+                let mut entry_path = section.to_vec();
+                entry_path.push(PathToken::Key(name.clone()));
+                render_toml_with_create_missing(
+                    source_text,
+                    &entry_path,
+                    raw_path,
+                    &Value::from(version.as_str()).to_string(),
+                )
+            }
+            DependencyOperation::Remove { .. } | DependencyOperation::SetVersion { .. } => {
+                Err(missing_dependency_error(raw_path, operation.name()))
+            }
+        };
+    };
+
+    match operation {
+        DependencyOperation::Add { name, version } => {
+            if table.contains_key(name) {
+                return Err(existing_dependency_error(raw_path, name));
+            }
+            let was_sorted = is_sorted(table.iter().map(|(key, _)| key));
+            table.insert(name, toml_edit::value(version.as_str()));
+            if was_sorted {
+                table.sort_values();
+            }
+        }
+        DependencyOperation::Remove { name } => {
+            table
+                .remove(name)
+                .ok_or_else(|| missing_dependency_error(raw_path, name))?;
+        }
+        DependencyOperation::SetVersion { name, version } => {
+            let entry = table
+                .get_mut(name)
+                .ok_or_else(|| missing_dependency_error(raw_path, name))?;
+            if let Some(Value::String(_)) = entry.as_value() {
+                replace_string_item(entry, version);
+            } else {
+                let detail = entry
+                    .as_table_like_mut()
+                    .ok_or_else(|| IdenteditError::InvalidRequest {
+                        message: format!(
+                            "Config path '{raw_path}' dependency '{name}' is neither a version string nor a table"
+                        ),
+                    })?;
+                if detail.contains_key("workspace") {
+                    return Err(IdenteditError::InvalidRequest {
+                        message: format!(
+                            "Config path '{raw_path}' dependency '{name}' inherits its version from the workspace; set it in workspace.dependencies instead"
+                        ),
+                    });
+                }
+                if let Some(existing) = detail.get_mut("version") {
+                    replace_string_item(existing, version);
+                } else if let Some(inline) = entry.as_inline_table_mut() {
+                    append_inline_entry(inline, "version", version);
+                } else if let Some(detail) = entry.as_table_like_mut() {
+                    detail.insert("version", toml_edit::value(version.as_str()));
+                }
+            }
+        }
+    }
+
+    Ok(apply_source_line_ending_style(
+        &document.to_string(),
+        source_text,
+    ))
+}
+
+fn cargo_section_mut<'a>(
+    document: &'a mut DocumentMut,
+    section: &[PathToken],
+) -> Option<&'a mut dyn TableLike> {
+    let mut current: &mut dyn TableLike = document.as_table_mut();
+    for token in section {
+        let PathToken::Key(key) = token else {
+            return None;
+        };
+        current = current.get_mut(key)?.as_table_like_mut()?;
+    }
+    Some(current)
+}
+
+/// Replaces a string value while keeping the whitespace and comments around it.
+/// Appends `key = "text"` to an inline table. The last value's trailing space moves to
+/// the new entry so the closing brace keeps its spacing (`{ a = 1, key = "text" }`).
+fn append_inline_entry(table: &mut InlineTable, key: &str, text: &str) {
+    let trailing = table.iter_mut().last().map(|(_, value)| {
+        let suffix = value
+            .decor()
+            .suffix()
+            .and_then(|suffix| suffix.as_str())
+            .unwrap_or_default()
+            .to_string();
+        value.decor_mut().set_suffix("");
+        suffix
+    });
+    let mut value = Value::from(text);
+    value.decor_mut().set_prefix(" ");
+    value
+        .decor_mut()
+        .set_suffix(trailing.unwrap_or_else(|| " ".to_string()));
+    table.insert(key, value);
+}
+
+fn replace_string_item(item: &mut Item, text: &str) {
+    match item.as_value_mut() {
+        Some(value) => {
+            let decor = value.decor().clone();
+            *value = Value::from(text);
+            *value.decor_mut() = decor;
+        }
+        None => *item = toml_edit::value(text),
+    }
+}
+
+fn edit_package_json(
+    file: &Path,
+    source_text: &str,
+    section: &[PathToken],
+    raw_path: &str,
+    operation: &DependencyOperation,
+    registry: &ProviderRegistry,
+) -> Result<String, IdenteditError> {
+    let format = ConfigFormat::Json;
+    let source = source_text.as_bytes();
+    let tree = parse_tree_for_format(&format, source)?;
+    let probe = ConfigPathOperation::Set {
+        new_text: String::new(),
+        create_missing: false,
+    };
+    let section_keys = resolve_path_in_tree(&format, &tree, source, section, &probe, raw_path)
+        .ok()
+        .and_then(|resolved| find_node_for_span(&tree, resolved.replace_span, "object"))
+        .map(|object| {
+            json_object_pairs(object)
+                .into_iter()
+                .filter_map(|pair| json_pair_key(pair, source))
+                .collect::<Vec<_>>()
+        });
+
+    let mut entry_path = section.to_vec();
+    entry_path.push(PathToken::Key(operation.name().to_string()));
+    let entry_display = path_tokens_display(&entry_path);
+
+    match operation {
+        DependencyOperation::Add { name, version } => {
+            let version_text = json_string_literal(version);
+            let Some(keys) = section_keys else {
+                return insert_missing_npm_section(
+                    source_text,
+                    &tree,
+                    section,
+                    raw_path,
+                    name,
+                    &version_text,
+                );
+            };
+            if keys.iter().any(|key| key == name) {
+                return Err(existing_dependency_error(raw_path, name));
+            }
+            let before_key = if is_sorted(keys.iter().map(String::as_str)) {
+                keys.iter().find(|key| key.as_str() > name.as_str())
+            } else {
+                None
+            };
+            insert_json_entry(
+                &format,
+                source_text,
+                section,
+                name,
+                &version_text,
+                before_key.map(String::as_str),
+            )
+            .ok_or_else(|| section_not_object_error(raw_path))
+        }
+        DependencyOperation::Remove { name } => {
+            if !section_keys.is_some_and(|keys| keys.contains(name)) {
+                return Err(missing_dependency_error(raw_path, name));
+            }
+            let delete = resolve_path_in_tree(
+                &format,
+                &tree,
+                source,
+                &entry_path,
+                &ConfigPathOperation::Delete,
+                &entry_display,
+            )?;
+            let delete_span = whole_line_delete_span(
+                source,
+                delete.container_span,
+                &delete.container_kind,
+                delete.replace_span,
+            );
+            Ok(format!(
+                "{}{}",
+                &source_text[..delete_span.start],
+                &source_text[delete_span.end..]
+            ))
+        }
+        DependencyOperation::SetVersion { name, version } => {
+            if !section_keys.is_some_and(|keys| keys.contains(name)) {
+                return Err(missing_dependency_error(raw_path, name));
+            }
+            let patch = resolve_config_path_operation_for_source(
+                file,
+                source,
+                source_text,
+                &entry_path,
+                &entry_display,
+                ConfigPathOperation::Set {
+                    new_text: json_string_literal(version),
+                    create_missing: false,
+                },
+                registry,
+            )?;
+            apply_patch_to_text(source_text, patch)
+        }
+    }
+}
+
+/// Adds `"section": {"name": "version"}` to the root object, laid out like its siblings.
+fn insert_missing_npm_section(
+    source_text: &str,
+    tree: &tree_sitter::Tree,
+    section: &[PathToken],
+    raw_path: &str,
+    name: &str,
+    version_text: &str,
+) -> Result<String, IdenteditError> {
+    let Some(PathToken::Key(section_key)) = section.first() else {
+        return Err(section_not_object_error(raw_path));
+    };
+    let root = json_root_value(tree.root_node())
+        .filter(|root| root.kind() == "object")
+        .ok_or_else(|| section_not_object_error(raw_path))?;
+    let multiline = source_text[root.start_byte()..root.end_byte()].contains('\n');
+    let entry = format!("{}: {version_text}", json_string_literal(name));
+    let value_text = match json_object_pairs(root).first() {
+        Some(first_pair) if multiline => {
+            let unit = indentation_before_offset(source_text, first_pair.start_byte());
+            let line_ending = line_ending_literal(source_text);
+            format!("{{{line_ending}{unit}{entry}{line_ending}}}")
+        }
+        _ => format!("{{{entry}}}"),
+    };
+
+    insert_json_entry(
+        &ConfigFormat::Json,
+        source_text,
+        &[],
+        section_key,
+        &value_text,
+        None,
+    )
+    .ok_or_else(|| section_not_object_error(raw_path))
+}
+
+fn is_sorted<'a>(keys: impl Iterator<Item = &'a str>) -> bool {
+    let keys = keys.collect::<Vec<_>>();
+    keys.windows(2).all(|pair| pair[0] <= pair[1])
+}
+
+fn json_string_literal(text: &str) -> String {
+    serde_json::to_string(text).unwrap_or_else(|_| format!("\"{text}\""))
+}
+
+fn existing_dependency_error(raw_path: &str, name: &str) -> IdenteditError {
+    IdenteditError::InvalidRequest {
+        message: format!(
+            "Config path '{raw_path}' already has dependency '{name}'; use set_dependency_version to change it"
+        ),
+    }
+}
+
+fn missing_dependency_error(raw_path: &str, name: &str) -> IdenteditError {
+    IdenteditError::InvalidRequest {
+        message: format!("Config path '{raw_path}' dependency '{name}' was not found"),
+    }
+}
+
+fn section_not_object_error(raw_path: &str) -> IdenteditError {
+    IdenteditError::InvalidRequest {
+        message: format!("Config path '{raw_path}' dependency section must be a JSON object"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DependencyOperation, edit_cargo_manifest, is_sorted};
+    use crate::patch::config_path::parse_config_path;
+
+    fn edit(source: &str, section: &str, operation: DependencyOperation) -> String {
+        let tokens = parse_config_path(section).expect("section should parse");
+        edit_cargo_manifest(source, &tokens, section, &operation).expect("edit should succeed")
+    }
+
+    #[test]
+    fn cargo_add_keeps_sorted_sections_sorted_and_appends_to_unsorted_ones() {
+        let add = || DependencyOperation::Add {
+            name: "bytes".to_string(),
+            version: "1".to_string(),
+        };
+        assert_eq!(
+            edit(
+                "[dependencies]\nanyhow = \"1\"\nserde = \"1\"\n",
+                "dependencies",
+                add()
+            ),
+            "[dependencies]\nanyhow = \"1\"\nbytes = \"1\"\nserde = \"1\"\n"
+        );
+        assert_eq!(
+            edit(
+                "[dependencies]\nserde = \"1\"\nanyhow = \"1\"\n",
+                "dependencies",
+                add()
+            ),
+            "[dependencies]\nserde = \"1\"\nanyhow = \"1\"\nbytes = \"1\"\n"
+        );
+    }
+
+    #[test]
+    fn cargo_set_version_updates_tables_and_keeps_comments() {
+        let source = "[dependencies]\nserde = { version = \"1.0\", features = [\"derive\"] } # core\n\n[dependencies.tokio]\nversion = \"1\"\n";
+        let set = |name: &str| DependencyOperation::SetVersion {
+            name: name.to_string(),
+            version: "2".to_string(),
+        };
+        let updated = edit(source, "dependencies", set("serde"));
+        assert_eq!(
+            updated,
+            "[dependencies]\nserde = { version = \"2\", features = [\"derive\"] } # core\n\n[dependencies.tokio]\nversion = \"1\"\n"
+        );
+        let updated = edit(&updated, "dependencies", set("tokio"));
+        assert!(updated.ends_with("[dependencies.tokio]\nversion = \"2\"\n"));
+    }
+
+    #[test]
+    fn is_sorted_accepts_empty_and_ordered_keys() {
+        assert!(is_sorted([].into_iter()));
+        assert!(is_sorted(["a", "b", "b"].into_iter()));
+        assert!(!is_sorted(["b", "a"].into_iter()));
+    }
+}
@@ -0,0 +1,147 @@
+//! Document selection in multi-document YAML (`--doc Kind/name`).
+//!
+//! Kubernetes manifests keep many resources in one stream and their order is not
+//! meaningful, so a document is picked by its `kind` and `metadata.name` rather than by
+//! position. The selected document is edited on its own by the regular operations and
+//! spliced back, which leaves every other document byte-for-byte unchanged.
+
+use std::ops::Range;
+use std::path::Path;
+
+use serde_json::Value;
+
+use super::{
+    ConfigFormat, ResolvedConfigPatch, apply_patch_to_text, build_patch_from_text_change,
+    detect_config_format, load_document_value, named_children, parse_tree_for_format,
+};
+use crate::error::IdenteditError;
+use crate::provider::ProviderRegistry;
+
+/// Runs `resolve` against the selected document (or the whole source when no selector
+/// is given) and returns a patch against the whole file.
+pub(super) fn resolve_in_document(
+    file: &Path,
+    source_text: &str,
+    document: Option<&str>,
+    resolve: impl FnOnce(&str) -> Result<ResolvedConfigPatch, IdenteditError>,
+    registry: &ProviderRegistry,
+) -> Result<ResolvedConfigPatch, IdenteditError> {
+    let Some(selector) = document else {
+        return resolve(source_text);
+    };
+
+    let range = select_document_range(file, source_text, selector)?;
+    let document_text = &source_text[range.clone()];
+    let patch = resolve(document_text)?;
+    let updated_document = apply_patch_to_text(document_text, patch)?;
+
+    let mut updated = String::with_capacity(source_text.len() + updated_document.len());
+    updated.push_str(&source_text[..range.start]);
+    updated.push_str(&updated_document);
+    updated.push_str(&source_text[range.end..]);
+    build_patch_from_text_change(file, source_text, &updated, registry)
+}
+
+/// Text of the selected document, or the whole source when no selector is given.
+pub(super) fn document_source<'a>(
+    file: &Path,
+    source_text: &'a str,
+    document: Option<&str>,
+) -> Result<&'a str, IdenteditError> {
+    match document {
+        Some(selector) => Ok(&source_text[select_document_range(file, source_text, selector)?]),
+        None => Ok(source_text),
+    }
+}
+
+fn select_document_range(
+    file: &Path,
+    source_text: &str,
+    selector: &str,
+) -> Result<Range<usize>, IdenteditError> {
+    if detect_config_format(file)? != ConfigFormat::Yaml {
+        return Err(IdenteditError::InvalidRequest {
+            message: format!(
+                "Config document selector '{selector}' only applies to YAML files; '{}' is not YAML",
+                file.display()
+            ),
+        });
+    }
+    let (kind, name) = selector
+        .split_once('/')
+        .filter(|(kind, name)| !kind.is_empty() && !name.is_empty() && !name.contains('/'))
+        .ok_or_else(|| IdenteditError::InvalidRequest {
+            message: format!(
+                "Config document selector '{selector}' must have the form 'Kind/name' (for example 'Deployment/web')"
+            ),
+        })?;
+
+    let tree = parse_tree_for_format(&ConfigFormat::Yaml, source_text.as_bytes())?;
+    let mut labels = Vec::new();
+    let mut matches = Vec::new();
+    for node in named_children(tree.root_node()) {
+        if node.kind() != "document" {
+            continue;
+        }
+        let range = document_range_with_line_ending(source_text, node.byte_range());
+        let value = load_document_value(&ConfigFormat::Yaml, &source_text[range.clone()])?;
+        let label = document_label(&value);
+        if label
+            .as_ref()
+            .is_some_and(|(doc_kind, doc_name)| *doc_kind == kind && *doc_name == name)
+        {
+            matches.push(range);
+        }
+        labels.push(match label {
+            Some((doc_kind, doc_name)) => format!("{doc_kind}/{doc_name}"),
+            None => "-".to_string(),
+        });
+    }
+
+    match matches.len() {
+        1 => Ok(matches.remove(0)),
+        0 => Err(IdenteditError::InvalidRequest {
+            message: format!(
+                "No YAML document in '{}' matches '{selector}' (documents: {})",
+                file.display(),
+                labels.join(", ")
+            ),
+        }),
+        count => Err(IdenteditError::InvalidRequest {
+            message: format!(
+                "Config document selector '{selector}' is ambiguous: {count} documents in '{}' match",
+                file.display()
+            ),
+        }),
+    }
+}
+
+/// Document nodes end before their line break; keeping it makes the document text end
+/// like a file does, so appended entries land on their own line.
+fn document_range_with_line_ending(source_text: &str, range: Range<usize>) -> Range<usize> {
+    let rest = &source_text[range.end..];
+    let line_ending = if rest.starts_with("\r\n") {
+        2
+    } else {
+        usize::from(rest.starts_with('\n'))
+    };
+    range.start..range.end + line_ending
+}
+
+fn document_label(value: &Value) -> Option<(&str, &str)> {
+    let kind = value.get("kind")?.as_str()?;
+    let name = value.get("metadata")?.get("name")?.as_str()?;
+    Some((kind, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::document_range_with_line_ending;
+
+    #[test]
+    fn document_range_takes_the_following_line_break() {
+        assert_eq!(document_range_with_line_ending("a: 1\n---\n", 0..4), 0..5);
+        assert_eq!(document_range_with_line_ending("a: 1\r\n", 0..4), 0..6);
+        assert_eq!(document_range_with_line_ending("a: 1", 0..4), 0..4);
+    }
+}
@@ -0,0 +1,375 @@
+//! Flattened listing of config documents (`read --mode config`).
+//!
+//! Every path the patch syntax can address is listed with its value type, a one-line
+//! preview, and a hash of the value's source text. The same hash is accepted back as
+//! `expected_value_hash` on a `config_path` target, so a patch fails if the value
+//! changed since it was read.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde_json::Value;
+use tree_sitter::{Node, Tree};
+
+use super::{
+    ConfigFormat, ConfigPathOperation, PathToken, collect_toml_candidates, decode_json_string,
+    detect_config_format, document::document_source, is_key_char, json_root_value,
+    json_value_kind_name, load_document_value, named_children, parse_config_path,
+    parse_tree_for_format, path_tokens_display, read_config_source, resolve_path_in_tree,
+    span_from_node, yaml_key_text, yaml_root_value, yaml_unwrap_node,
+};
+use crate::error::IdenteditError;
+use crate::handle::Span;
+use crate::hash::hash_bytes;
+use crate::provider::node_text;
+
+/// Spans of each path's value; more than one means the path has duplicate keys.
+type ValueSpans = HashMap<Vec<PathToken>, Vec<Span>>;
+
+/// Longest scalar preview, in characters, before it is cut off with `...`.
+const PREVIEW_MAX_CHARS: usize = 80;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigEntry {
+    pub path: String,
+    pub value_type: &'static str,
+    pub preview: String,
+    pub span: Span,
+    pub value_hash: String,
+}
+
+/// Lists addressable paths in document order. Paths that have no value node of their
+/// own (TOML `[table]` headers) and keys the path syntax cannot spell are skipped, but
+/// entries beneath a table are still listed.
+pub fn list_config_entries(
+    file: &Path,
+    source_text: &str,
+) -> Result<Vec<ConfigEntry>, IdenteditError> {
+    let format = detect_config_format(file)?;
+    let document = load_document_value(&format, source_text)?;
+    let source = source_text.as_bytes();
+    let tree = parse_tree_for_format(&format, source)?;
+
+    let mut paths = Vec::new();
+    collect_paths(&document, &mut Vec::new(), &mut paths);
+    let value_spans = collect_value_spans(&format, &tree, source);
+
+    let mut entries = Vec::with_capacity(paths.len());
+    for (path_tokens, value) in paths {
+        let raw_path = path_tokens_display(&path_tokens);
+        let span = match value_spans.get(&path_tokens).map(Vec::as_slice) {
+            None | Some([]) => continue,
+            Some([span]) => *span,
+            Some(many) => {
+                return Err(IdenteditError::InvalidRequest {
+                    message: format!("Config path '{raw_path}' is ambiguous ({})", many.len()),
+                });
+            }
+        };
+        let value_text = &source_text[span.start..span.end];
+        entries.push(ConfigEntry {
+            preview: value_preview(value, value_text),
+            value_type: json_value_kind_name(value),
+            value_hash: hash_bytes(value_text.as_bytes()),
+            span,
+            path: raw_path,
+        });
+    }
+    // Parsed maps are key-sorted; spans restore document order (parents sort first).
+    entries.sort_by_key(|entry| entry.span.start);
+    Ok(entries)
+}
+
+/// Fails with `precondition_failed` unless the value at `raw_path` still hashes to
+/// `expected_value_hash`.
+pub fn check_config_value_hash(
+    file: &Path,
+    raw_path: &str,
+    document: Option<&str>,
+    expected_value_hash: &str,
+) -> Result<(), IdenteditError> {
+    let source_text = read_config_source(file, None)?;
+    let source = document_source(file, &source_text, document)?.as_bytes();
+    let format = detect_config_format(file)?;
+    let path_tokens = parse_config_path(raw_path)?;
+    let tree = parse_tree_for_format(&format, source)?;
+    let probe = ConfigPathOperation::Set {
+        new_text: String::new(),
+        create_missing: false,
+    };
+    let resolved = resolve_path_in_tree(&format, &tree, source, &path_tokens, &probe, raw_path)?;
+
+    let actual_hash = hash_bytes(&source[resolved.replace_span.start..resolved.replace_span.end]);
+    if actual_hash != expected_value_hash {
+        return Err(IdenteditError::PreconditionFailed {
+            expected_hash: expected_value_hash.to_string(),
+            actual_hash,
+        });
+    }
+    Ok(())
+}
+
+fn collect_paths<'a>(
+    value: &'a Value,
+    prefix: &mut Vec<PathToken>,
+    out: &mut Vec<(Vec<PathToken>, &'a Value)>,
+) {
+    if !prefix.is_empty() {
+        out.push((prefix.clone(), value));
+    }
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                if key.is_empty() || !key.bytes().all(is_key_char) {
+                    continue;
+                }
+                prefix.push(PathToken::Key(key.clone()));
+                collect_paths(child, prefix, out);
+                prefix.pop();
+            }
+        }
+        Value::Array(items) => {
+            for (index, child) in items.iter().enumerate() {
+                prefix.push(PathToken::Index(index));
+                collect_paths(child, prefix, out);
+                prefix.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Value spans by path, gathered in one walk of the syntax tree. Each span is the
+/// `replace_span` a `set` on that path resolves to, so listed hashes match
+/// [`check_config_value_hash`].
+fn collect_value_spans(format: &ConfigFormat, tree: &Tree, source: &[u8]) -> ValueSpans {
+    let mut out = HashMap::new();
+    match format {
+        ConfigFormat::Json => {
+            if let Some(root) = json_root_value(tree.root_node()) {
+                collect_json_spans(root, source, &mut Vec::new(), &mut out);
+            }
+        }
+        ConfigFormat::Yaml => {
+            if let Some(root) = yaml_root_value(tree.root_node()) {
+                collect_yaml_spans(root, source, &mut Vec::new(), &mut out);
+            }
+        }
+        ConfigFormat::Toml => {
+            let mut candidates = Vec::new();
+            collect_toml_candidates(tree.root_node(), source, &mut candidates);
+            for candidate in candidates {
+                out.entry(candidate.path)
+                    .or_default()
+                    .push(candidate.set_span);
+            }
+        }
+    }
+    out
+}
+
+fn collect_json_spans(
+    node: Node<'_>,
+    source: &[u8],
+    prefix: &mut Vec<PathToken>,
+    out: &mut ValueSpans,
+) {
+    match node.kind() {
+        "object" => {
+            for pair in named_children(node) {
+                if pair.kind() != "pair" {
+                    continue;
+                }
+                let (Some(key_node), Some(value_node)) = (
+                    pair.child_by_field_name("key"),
+                    pair.child_by_field_name("value"),
+                ) else {
+                    continue;
+                };
+                let Some(raw_key) = node_text(key_node, source) else {
+                    continue;
+                };
+                let key = decode_json_string(&raw_key)
+                    .unwrap_or_else(|| raw_key.trim_matches('"').to_string());
+                prefix.push(PathToken::Key(key));
+                record_span(value_node, source, prefix, out, collect_json_spans);
+                prefix.pop();
+            }
+        }
+        "array" => {
+            for (index, element) in named_children(node).into_iter().enumerate() {
+                prefix.push(PathToken::Index(index));
+                record_span(element, source, prefix, out, collect_json_spans);
+                prefix.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+fn collect_yaml_spans(
+    node: Node<'_>,
+    source: &[u8],
+    prefix: &mut Vec<PathToken>,
+    out: &mut ValueSpans,
+) {
+    match node.kind() {
+        "block_mapping" | "flow_mapping" => {
+            let pair_kind = if node.kind() == "block_mapping" {
+                "block_mapping_pair"
+            } else {
+                "flow_pair"
+            };
+            for pair in named_children(node) {
+                if pair.kind() != pair_kind {
+                    continue;
+                }
+                let Some(key) = pair
+                    .child_by_field_name("key")
+                    .and_then(|key_node| yaml_key_text(key_node, source))
+                else {
+                    continue;
+                };
+                let Some(value_node) = pair.child_by_field_name("value").and_then(yaml_unwrap_node)
+                else {
+                    continue;
+                };
+                prefix.push(PathToken::Key(key));
+                record_span(value_node, source, prefix, out, collect_yaml_spans);
+                prefix.pop();
+            }
+        }
+        "block_sequence" => {
+            let items = named_children(node)
+                .into_iter()
+                .filter(|child| child.kind() == "block_sequence_item");
+            for (index, item) in items.enumerate() {
+                let Some(value_node) = yaml_unwrap_node(item) else {
+                    continue;
+                };
+                prefix.push(PathToken::Index(index));
+                record_span(value_node, source, prefix, out, collect_yaml_spans);
+                prefix.pop();
+            }
+        }
+        "flow_sequence" => {
+            for (index, item) in named_children(node).into_iter().enumerate() {
+                let value_node = yaml_unwrap_node(item).unwrap_or(item);
+                prefix.push(PathToken::Index(index));
+                record_span(value_node, source, prefix, out, collect_yaml_spans);
+                prefix.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Records `value_node` under `prefix`, then descends into it with `walk`.
+fn record_span<'tree>(
+    value_node: Node<'tree>,
+    source: &[u8],
+    prefix: &mut Vec<PathToken>,
+    out: &mut ValueSpans,
+    walk: fn(Node<'tree>, &[u8], &mut Vec<PathToken>, &mut ValueSpans),
+) {
+    out.entry(prefix.clone())
+        .or_default()
+        .push(span_from_node(value_node));
+    walk(value_node, source, prefix, out);
+}
+
+fn value_preview(value: &Value, value_text: &str) -> String {
+    match value {
+        Value::Object(map) => format!("{{{} key(s)}}", map.len()),
+        Value::Array(items) => format!("[{} item(s)]", items.len()),
+        _ => {
+            let first_line = value_text.lines().next().unwrap_or_default().trim_end();
+            let mut preview = first_line
+                .chars()
+                .take(PREVIEW_MAX_CHARS)
+                .collect::<String>();
+            if preview.len() < value_text.trim_end().len() {
+                preview.push_str("...");
+            }
+            preview
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{collect_paths, collect_value_spans, value_preview};
+    use crate::patch::config_path::{
+        ConfigFormat, ConfigPathOperation, load_document_value, parse_tree_for_format,
+        path_tokens_display, resolve_path_in_tree,
+    };
+
+    #[test]
+    fn collect_paths_skips_keys_the_path_syntax_cannot_spell() {
+        let document = json!({"a": {"b.c": 1, "d": [true]}});
+        let mut out = Vec::new();
+        collect_paths(&document, &mut Vec::new(), &mut out);
+        let paths = out
+            .iter()
+            .map(|(path, _)| path_tokens_display(path))
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec!["a", "a.d", "a.d[0]"]);
+    }
+
+    #[test]
+    fn value_preview_summarizes_containers_and_truncates_scalars() {
+        assert_eq!(value_preview(&json!({"a": 1}), "{\"a\": 1}"), "{1 key(s)}");
+        assert_eq!(value_preview(&json!([1, 2]), "[1, 2]"), "[2 item(s)]");
+        assert_eq!(value_preview(&json!("x"), "\"x\""), "\"x\"");
+        assert_eq!(value_preview(&json!("ab"), "|\n  a\n  b"), "|...");
+        let long = "x".repeat(100);
+        assert_eq!(
+            value_preview(&json!(long), &long),
+            format!("{}...", "x".repeat(80))
+        );
+    }
+
+    #[test]
+    fn collect_value_spans_matches_per_path_resolution() {
+        let fixtures = [
+            (
+                ConfigFormat::Json,
+                r#"{"a": {"b": [1, {"c": "x"}], "d": null}, "e": true}"#,
+            ),
+            (
+                ConfigFormat::Yaml,
+                "a:\n  b:\n    - 1\n    - c: x\n  d: [2, {e: y}]\nf: &anchor z\n",
+            ),
+            (
+                ConfigFormat::Toml,
+                "a = [1, { c = \"x\" }]\n[t]\nd.e = 2\n[[arr]]\nf = 3\n[[arr]]\nf = 4\n",
+            ),
+        ];
+        let probe = ConfigPathOperation::Set {
+            new_text: String::new(),
+            create_missing: false,
+        };
+        for (format, source_text) in fixtures {
+            let source = source_text.as_bytes();
+            let document = load_document_value(&format, source_text).expect("fixture should load");
+            let tree = parse_tree_for_format(&format, source).expect("fixture should parse");
+            let spans = collect_value_spans(&format, &tree, source);
+            let mut paths = Vec::new();
+            collect_paths(&document, &mut Vec::new(), &mut paths);
+            for (path_tokens, _) in paths {
+                let raw_path = path_tokens_display(&path_tokens);
+                let resolved =
+                    resolve_path_in_tree(&format, &tree, source, &path_tokens, &probe, &raw_path)
+                        .ok()
+                        .map(|resolved| vec![resolved.replace_span]);
+                assert_eq!(
+                    spans.get(&path_tokens).cloned(),
+                    resolved,
+                    "{format:?} path {raw_path}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,439 @@
+//! Deep-merge of a JSON object into a config mapping (`merge_object`).
+//!
+//! The merge is planned against a parsed view of the document and then executed as a
+//! series of create-missing `set` operations on an in-memory copy, so every leaf goes
+//! through the same format-preserving writers as a single `set`. The combined result
+//! is emitted as one replacement of the smallest node covering all changed bytes.
+//! Incoming keys are written in the order the request lists them.
+
+use std::fmt;
+use std::path::Path;
+
+use serde::de::{Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{SerializeMap, Serializer};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::{
+    ConfigFormat, ConfigPathOperation, PathToken, ResolvedConfigPatch, apply_patch_to_text,
+    build_patch_from_text_change, detect_config_format,
+    document::{document_source, resolve_in_document},
+    load_document_value, parse_config_path, path_tokens_display, read_config_source,
+    relocate::insert_json_entry,
+    resolve_config_path_operation_for_source,
+};
+use crate::error::IdenteditError;
+use crate::provider::ProviderRegistry;
+
+/// What to do when an incoming leaf would replace an existing, different value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeConflictPolicy {
+    /// Replace the existing value with the incoming one.
+    #[default]
+    Overwrite,
+    /// Leave the existing value in place and skip the incoming one.
+    Keep,
+    /// Fail the whole merge without modifying the file.
+    Error,
+}
+
+/// A JSON value whose objects keep their keys in the order they were written.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeValue {
+    Object(MergeObject),
+    Array(Vec<MergeValue>),
+    Scalar(Value),
+}
+
+/// The object of a `merge_object` operation, keys in source order. A repeated key keeps
+/// its first position and its last value, as `serde_json` does.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MergeObject(Vec<(String, MergeValue)>);
+
+impl MergeObject {
+    fn insert(&mut self, key: String, value: MergeValue) {
+        match self.0.iter_mut().find(|(existing, _)| *existing == key) {
+            Some((_, slot)) => *slot = value,
+            None => self.0.push((key, value)),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Object(
+            self.0
+                .iter()
+                .map(|(key, value)| (key.clone(), value.to_value()))
+                .collect::<Map<_, _>>(),
+        )
+    }
+}
+
+impl MergeValue {
+    fn to_value(&self) -> Value {
+        match self {
+            MergeValue::Object(object) => object.to_value(),
+            MergeValue::Array(items) => Value::Array(items.iter().map(Self::to_value).collect()),
+            MergeValue::Scalar(value) => value.clone(),
+        }
+    }
+}
+
+impl Serialize for MergeValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            MergeValue::Object(object) => object.serialize(serializer),
+            MergeValue::Array(items) => items.serialize(serializer),
+            MergeValue::Scalar(value) => value.serialize(serializer),
+        }
+    }
+}
+
+impl Serialize for MergeObject {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (key, value) in &self.0 {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+struct MergeValueVisitor;
+
+impl<'de> Visitor<'de> for MergeValueVisitor {
+    type Value = MergeValue;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any JSON value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<MergeValue, E> {
+        Ok(MergeValue::Scalar(value.into()))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<MergeValue, E> {
+        Ok(MergeValue::Scalar(value.into()))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<MergeValue, E> {
+        Ok(MergeValue::Scalar(value.into()))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<MergeValue, E> {
+        Ok(MergeValue::Scalar(value.into()))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<MergeValue, E> {
+        Ok(MergeValue::Scalar(value.into()))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<MergeValue, E> {
+        Ok(MergeValue::Scalar(value.into()))
+    }
+
+    fn visit_unit<E>(self) -> Result<MergeValue, E> {
+        Ok(MergeValue::Scalar(Value::Null))
+    }
+
+    fn visit_none<E>(self) -> Result<MergeValue, E> {
+        Ok(MergeValue::Scalar(Value::Null))
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<MergeValue, D::Error> {
+        MergeValue::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<MergeValue, A::Error> {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(MergeValue::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<MergeValue, A::Error> {
+        MergeObjectVisitor.visit_map(map).map(MergeValue::Object)
+    }
+}
+
+struct MergeObjectVisitor;
+
+impl<'de> Visitor<'de> for MergeObjectVisitor {
+    type Value = MergeObject;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a JSON object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<MergeObject, A::Error> {
+        let mut object = MergeObject::default();
+        while let Some((key, value)) = map.next_entry()? {
+            object.insert(key, value);
+        }
+        Ok(object)
+    }
+}
+
+impl<'de> Deserialize<'de> for MergeValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(MergeValueVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for MergeObject {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(MergeObjectVisitor)
+    }
+}
+
+/// Resolves the merge to one replacement, or `None` when every incoming value is already
+/// in place and the file should be left untouched.
+pub fn resolve_config_merge_operation(
+    file: &Path,
+    raw_path: &str,
+    document: Option<&str>,
+    expected_file_hash: Option<&str>,
+    object: &MergeObject,
+    on_conflict: MergeConflictPolicy,
+    registry: &ProviderRegistry,
+) -> Result<Option<ResolvedConfigPatch>, IdenteditError> {
+    let source_text = read_config_source(file, expected_file_hash)?;
+    let document_text = document_source(file, &source_text, document)?;
+    let merged_text =
+        merge_for_source(file, document_text, raw_path, object, on_conflict, registry)?;
+    if merged_text == document_text {
+        return Ok(None);
+    }
+    resolve_in_document(
+        file,
+        &source_text,
+        document,
+        |document_text| build_patch_from_text_change(file, document_text, &merged_text, registry),
+        registry,
+    )
+    .map(Some)
+}
+
+fn merge_for_source(
+    file: &Path,
+    source_text: &str,
+    raw_path: &str,
+    object: &MergeObject,
+    on_conflict: MergeConflictPolicy,
+    registry: &ProviderRegistry,
+) -> Result<String, IdenteditError> {
+    let format = detect_config_format(file)?;
+    let path_tokens = parse_config_path(raw_path)?;
+    let document = load_document_value(&format, source_text)?;
+
+    // A missing JSON object is written whole, so it can go in as text beside its siblings
+    // instead of through create-missing, which re-serializes the document. YAML and TOML
+    // build it key by key to get block mappings and tables.
+    let nest_missing = !matches!(format, ConfigFormat::Json);
+    let mut assignments = Vec::new();
+    match navigate(&document, &path_tokens) {
+        Some(existing) if !existing.is_object() => {
+            resolve_conflict(
+                on_conflict,
+                &path_tokens,
+                existing,
+                &MergeValue::Object(object.clone()),
+                &mut assignments,
+            )?;
+        }
+        None if !nest_missing => {
+            assignments.push((path_tokens.clone(), MergeValue::Object(object.clone())));
+        }
+        existing => plan_merge(
+            existing,
+            &path_tokens,
+            object,
+            on_conflict,
+            nest_missing,
+            &mut assignments,
+        )?,
+    }
+
+    let mut merged_text = source_text.to_string();
+    for (tokens, value) in assignments {
+        if navigate(&document, &tokens).is_none()
+            && let Some((PathToken::Key(key), parent)) = tokens.split_last()
+            && let Some(inserted) = insert_json_entry(
+                &format,
+                &merged_text,
+                parent,
+                key,
+                &render_json_entry_text(&value),
+                None,
+            )
+        {
+            merged_text = inserted;
+            continue;
+        }
+        let display_path = path_tokens_display(&tokens);
+        let new_text = render_value_text(&format, &value, &display_path)?;
+        let patch = resolve_config_path_operation_for_source(
+            file,
+            merged_text.as_bytes(),
+            &merged_text,
+            &tokens,
+            &display_path,
+            ConfigPathOperation::Set {
+                new_text,
+                create_missing: true,
+            },
+            registry,
+        )?;
+        merged_text = apply_patch_to_text(&merged_text, patch)?;
+    }
+    Ok(merged_text)
+}
+
+fn navigate<'a>(document: &'a Value, path_tokens: &[PathToken]) -> Option<&'a Value> {
+    path_tokens
+        .iter()
+        .try_fold(document, |current, token| match token {
+            PathToken::Key(key) => current.as_object()?.get(key),
+            PathToken::Index(index) => current.as_array()?.get(*index),
+        })
+}
+
+fn plan_merge(
+    existing: Option<&Value>,
+    path: &[PathToken],
+    incoming: &MergeObject,
+    on_conflict: MergeConflictPolicy,
+    nest_missing: bool,
+    assignments: &mut Vec<(Vec<PathToken>, MergeValue)>,
+) -> Result<(), IdenteditError> {
+    for (key, value) in &incoming.0 {
+        let mut child_path = path.to_vec();
+        child_path.push(PathToken::Key(key.clone()));
+        let current = existing
+            .and_then(Value::as_object)
+            .and_then(|map| map.get(key));
+
+        match (current, value) {
+            (None, MergeValue::Object(nested)) if nest_missing && !nested.is_empty() => {
+                plan_merge(
+                    None,
+                    &child_path,
+                    nested,
+                    on_conflict,
+                    nest_missing,
+                    assignments,
+                )?;
+            }
+            (Some(Value::Object(_)), MergeValue::Object(nested)) if !nested.is_empty() => {
+                plan_merge(
+                    current,
+                    &child_path,
+                    nested,
+                    on_conflict,
+                    nest_missing,
+                    assignments,
+                )?;
+            }
+            (None, _) => assignments.push((child_path, value.clone())),
+            (Some(current), _) => {
+                resolve_conflict(on_conflict, &child_path, current, value, assignments)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn resolve_conflict(
+    on_conflict: MergeConflictPolicy,
+    path: &[PathToken],
+    current: &Value,
+    incoming: &MergeValue,
+    assignments: &mut Vec<(Vec<PathToken>, MergeValue)>,
+) -> Result<(), IdenteditError> {
+    let incoming_value = incoming.to_value();
+    if *current == incoming_value {
+        return Ok(());
+    }
+    match on_conflict {
+        MergeConflictPolicy::Overwrite => assignments.push((path.to_vec(), incoming.clone())),
+        MergeConflictPolicy::Keep => {}
+        MergeConflictPolicy::Error => {
+            return Err(IdenteditError::InvalidRequest {
+                message: format!(
+                    "Config path merge_object conflict at '{}': existing value {current} differs from incoming value {incoming_value} (on_conflict=error)",
+                    path_tokens_display(path)
+                ),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn render_value_text(
+    format: &ConfigFormat,
+    value: &MergeValue,
+    display_path: &str,
+) -> Result<String, IdenteditError> {
+    match format {
+        // JSON text is also valid YAML flow syntax.
+        ConfigFormat::Json | ConfigFormat::Yaml => {
+            Ok(serde_json::to_string(value).expect("merge values should serialize"))
+        }
+        ConfigFormat::Toml => Ok(json_to_toml_value(value, display_path)?.to_string()),
+    }
+}
+
+/// Pretty JSON for a new entry, indented relative to the object it joins.
+fn render_json_entry_text(value: &MergeValue) -> String {
+    serde_json::to_string_pretty(value).expect("merge values should serialize")
+}
+
+fn json_to_toml_value(
+    value: &MergeValue,
+    display_path: &str,
+) -> Result<toml_edit::Value, IdenteditError> {
+    Ok(match value {
+        MergeValue::Scalar(Value::Null) => {
+            return Err(IdenteditError::InvalidRequest {
+                message: format!(
+                    "Config path merge_object value at '{display_path}' is null, which TOML cannot represent"
+                ),
+            });
+        }
+        MergeValue::Scalar(Value::Bool(flag)) => (*flag).into(),
+        MergeValue::Scalar(Value::Number(number)) => match number.as_i64() {
+            Some(integer) => integer.into(),
+            None if number.is_u64() => {
+                return Err(IdenteditError::InvalidRequest {
+                    message: format!(
+                        "Config path merge_object value {number} at '{display_path}' exceeds the TOML integer range"
+                    ),
+                });
+            }
+            None => number.as_f64().unwrap_or_default().into(),
+        },
+        MergeValue::Scalar(Value::String(text)) => text.as_str().into(),
+        MergeValue::Scalar(Value::Array(_) | Value::Object(_)) => {
+            unreachable!("merge values keep arrays and objects structured")
+        }
+        MergeValue::Array(items) => {
+            let mut array = toml_edit::Array::new();
+            for item in items {
+                array.push(json_to_toml_value(item, display_path)?);
+            }
+            toml_edit::Value::Array(array)
+        }
+        MergeValue::Object(object) => {
+            let mut table = toml_edit::InlineTable::new();
+            for (key, item) in &object.0 {
+                table.insert(key, json_to_toml_value(item, display_path)?);
+            }
+            toml_edit::Value::InlineTable(table)
+        }
+    })
+}
@@ -0,0 +1,380 @@
+//! Key renames and value moves (`rename_key` / `move`).
+//!
+//! A rename rewrites only the key token, so the entry keeps its position, value, and
+//! trailing comments. A move within one mapping is a rename; any other move deletes
+//! the source entry and re-inserts the original value text (re-indented for its new
+//! depth) at the destination, so the value's own formatting survives the trip.
+
+use std::path::Path;
+
+use tree_sitter::{Node, Tree};
+
+use super::{
+    ConfigFormat, ConfigPathOperation, PathToken, ResolvedConfigPatch, apply_patch_to_text,
+    build_patch_from_text_change, decode_json_string, detect_config_format,
+    document::resolve_in_document, find_node_for_span, indentation_before_offset,
+    is_missing_config_path_error, json_root_value, line_ending_literal, named_children,
+    parse_config_path, parse_tree_for_format, path_tokens_display, read_config_source,
+    resolve_config_path_operation_for_source, resolve_path_in_tree, whole_line_delete_span,
+    yaml_insert::yaml_string_literal,
+};
+use crate::error::IdenteditError;
+use crate::provider::{ProviderRegistry, node_text};
+
+pub(super) fn render_renamed_key(
+    format: &ConfigFormat,
+    tree: &Tree,
+    source: &[u8],
+    path_tokens: &[PathToken],
+    raw_path: &str,
+    new_key: &str,
+) -> Result<String, IdenteditError> {
+    if new_key.is_empty() {
+        return Err(IdenteditError::InvalidRequest {
+            message: format!("Config path '{raw_path}' rename_key requires a non-empty new key"),
+        });
+    }
+
+    let Some((PathToken::Key(old_key), parent)) = path_tokens.split_last() else {
+        return Err(IdenteditError::InvalidRequest {
+            message: format!(
+                "Config path '{raw_path}' rename_key requires a path that ends in a mapping key"
+            ),
+        });
+    };
+    if old_key != new_key {
+        let mut renamed_path = parent.to_vec();
+        renamed_path.push(PathToken::Key(new_key.to_string()));
+        ensure_path_is_free(format, tree, source, &renamed_path, raw_path, "rename_key")?;
+    }
+
+    Ok(match format {
+        ConfigFormat::Json => json_string_literal(new_key),
+        ConfigFormat::Yaml => yaml_string_literal(new_key),
+        ConfigFormat::Toml => toml_key_literal(new_key),
+    })
+}
+
+pub fn resolve_config_move_operation(
+    file: &Path,
+    raw_path: &str,
+    document: Option<&str>,
+    expected_file_hash: Option<&str>,
+    to: &str,
+    registry: &ProviderRegistry,
+) -> Result<ResolvedConfigPatch, IdenteditError> {
+    let source_text = read_config_source(file, expected_file_hash)?;
+    resolve_in_document(
+        file,
+        &source_text,
+        document,
+        |source_text| move_for_source(file, source_text, raw_path, to, registry),
+        registry,
+    )
+}
+
+fn move_for_source(
+    file: &Path,
+    source_text: &str,
+    raw_path: &str,
+    to: &str,
+    registry: &ProviderRegistry,
+) -> Result<ResolvedConfigPatch, IdenteditError> {
+    let format = detect_config_format(file)?;
+    let from_tokens = parse_config_path(raw_path)?;
+    let to_tokens = parse_config_path(to)?;
+
+    let Some((PathToken::Key(to_key), to_parent)) = to_tokens.split_last() else {
+        return Err(IdenteditError::InvalidRequest {
+            message: format!("Config path move destination '{to}' must end in a mapping key"),
+        });
+    };
+    if to_tokens.starts_with(&from_tokens) {
+        return Err(IdenteditError::InvalidRequest {
+            message: format!("Config path '{raw_path}' cannot be moved into itself ('{to}')"),
+        });
+    }
+
+    let source = source_text.as_bytes();
+    if let Some((PathToken::Key(_), from_parent)) = from_tokens.split_last()
+        && from_parent == to_parent
+    {
+        return resolve_config_path_operation_for_source(
+            file,
+            source,
+            source_text,
+            &from_tokens,
+            raw_path,
+            ConfigPathOperation::RenameKey {
+                new_key: to_key.clone(),
+            },
+            registry,
+        );
+    }
+
+    let tree = parse_tree_for_format(&format, source)?;
+    let value_probe = ConfigPathOperation::Set {
+        new_text: String::new(),
+        create_missing: false,
+    };
+    let value_edit =
+        resolve_path_in_tree(&format, &tree, source, &from_tokens, &value_probe, raw_path)?;
+    ensure_path_is_free(&format, &tree, source, &to_tokens, raw_path, "move")?;
+    let value_text = relocatable_value_text(
+        source_text,
+        value_edit.replace_span.start,
+        value_edit.replace_span.end,
+    );
+
+    let delete = resolve_path_in_tree(
+        &format,
+        &tree,
+        source,
+        &from_tokens,
+        &ConfigPathOperation::Delete,
+        raw_path,
+    )?;
+    let delete_span = whole_line_delete_span(
+        source,
+        delete.container_span,
+        &delete.container_kind,
+        delete.replace_span,
+    );
+    let without_source = format!(
+        "{}{}",
+        &source_text[..delete_span.start],
+        &source_text[delete_span.end..]
+    );
+
+    let moved_text = match insert_json_entry(
+        &format,
+        &without_source,
+        to_parent,
+        to_key,
+        &value_text,
+        None,
+    ) {
+        Some(inserted) => inserted,
+        None => {
+            let insert = resolve_config_path_operation_for_source(
+                file,
+                without_source.as_bytes(),
+                &without_source,
+                &to_tokens,
+                &path_tokens_display(&to_tokens),
+                ConfigPathOperation::Set {
+                    new_text: value_text,
+                    create_missing: true,
+                },
+                registry,
+            )?;
+            apply_patch_to_text(&without_source, insert)?
+        }
+    };
+
+    build_patch_from_text_change(file, source_text, &moved_text, registry)
+}
+
+fn ensure_path_is_free(
+    format: &ConfigFormat,
+    tree: &Tree,
+    source: &[u8],
+    path_tokens: &[PathToken],
+    raw_path: &str,
+    operation_name: &str,
+) -> Result<(), IdenteditError> {
+    let probe = ConfigPathOperation::Set {
+        new_text: String::new(),
+        create_missing: false,
+    };
+    match resolve_path_in_tree(format, tree, source, path_tokens, &probe, raw_path) {
+        Ok(_) => Err(IdenteditError::InvalidRequest {
+            message: format!(
+                "Config path '{raw_path}' {operation_name} target '{}' already exists",
+                path_tokens_display(path_tokens)
+            ),
+        }),
+        Err(error) if is_missing_config_path_error(&error) => Ok(()),
+        Err(error) => Err(error),
+    }
+}
+
+/// Value text with continuation lines made relative to the line the value starts on,
+/// ready to be re-indented at the destination.
+fn relocatable_value_text(source_text: &str, start: usize, end: usize) -> String {
+    let base_indent = indentation_before_offset(source_text, start);
+    let mut lines = source_text[start..end].split('\n');
+    let mut relocated = lines.next().unwrap_or_default().to_string();
+    for line in lines {
+        relocated.push('\n');
+        let strip = line
+            .bytes()
+            .take(base_indent.len())
+            .take_while(|byte| *byte == b' ' || *byte == b'\t')
+            .count();
+        relocated.push_str(&line[strip..]);
+    }
+    relocated
+}
+
+/// Inserts `"key": value` into an existing JSON object as text, keeping the rest of the
+/// document as-is. The entry goes right before the `before_key` pair when that exists,
+/// and after the last pair otherwise. Returns `None` when the parent object does not
+/// exist yet, in which case the regular create-missing path builds it.
+pub(super) fn insert_json_entry(
+    format: &ConfigFormat,
+    source_text: &str,
+    parent_tokens: &[PathToken],
+    key: &str,
+    value_text: &str,
+    before_key: Option<&str>,
+) -> Option<String> {
+    if !matches!(format, ConfigFormat::Json) {
+        return None;
+    }
+    let source = source_text.as_bytes();
+    let tree = parse_tree_for_format(format, source).ok()?;
+    let object = if parent_tokens.is_empty() {
+        json_root_value(tree.root_node())?
+    } else {
+        let probe = ConfigPathOperation::Set {
+            new_text: String::new(),
+            create_missing: false,
+        };
+        let parent = resolve_path_in_tree(format, &tree, source, parent_tokens, &probe, "").ok()?;
+        find_node_for_span(&tree, parent.replace_span, "object")?
+    };
+    if object.kind() != "object" {
+        return None;
+    }
+
+    let line_ending = line_ending_literal(source_text);
+    let entry_key = json_string_literal(key);
+    let pairs = json_object_pairs(object);
+    let multiline = source_text[object.start_byte()..object.end_byte()].contains('\n');
+
+    if let Some(next_pair) = before_key.and_then(|before_key| {
+        pairs
+            .iter()
+            .find(|pair| json_pair_key(**pair, source).as_deref() == Some(before_key))
+    }) {
+        let indent = indentation_before_offset(source_text, next_pair.start_byte());
+        let separator = if multiline {
+            format!(",{line_ending}{indent}")
+        } else {
+            ", ".to_string()
+        };
+        let mut updated = source_text[..next_pair.start_byte()].to_string();
+        updated.push_str(&format!(
+            "{entry_key}: {}{separator}",
+            indent_continuation_lines(value_text, &indent, line_ending)
+        ));
+        updated.push_str(&source_text[next_pair.start_byte()..]);
+        return Some(updated);
+    }
+
+    let last_pair = pairs.last().copied();
+
+    let (insert_at, indent, separator) = match last_pair {
+        Some(pair) => {
+            let indent = indentation_before_offset(source_text, pair.start_byte());
+            let separator = if multiline {
+                format!(",{line_ending}{indent}")
+            } else {
+                ", ".to_string()
+            };
+            (pair.end_byte(), indent, separator)
+        }
+        None => {
+            let indent = indentation_before_offset(source_text, object.start_byte());
+            // Replace the whole (possibly whitespace-only) body of an empty object.
+            let mut updated = source_text[..object.start_byte()].to_string();
+            updated.push_str(&format!(
+                "{{{entry_key}: {}}}",
+                indent_continuation_lines(value_text, &indent, line_ending)
+            ));
+            updated.push_str(&source_text[object.end_byte()..]);
+            return Some(updated);
+        }
+    };
+
+    let mut updated = source_text[..insert_at].to_string();
+    updated.push_str(&format!(
+        "{separator}{entry_key}: {}",
+        indent_continuation_lines(value_text, &indent, line_ending)
+    ));
+    updated.push_str(&source_text[insert_at..]);
+    Some(updated)
+}
+
+pub(super) fn json_object_pairs(object: Node<'_>) -> Vec<Node<'_>> {
+    named_children(object)
+        .into_iter()
+        .filter(|child| child.kind() == "pair")
+        .collect()
+}
+
+pub(super) fn json_pair_key(pair: Node<'_>, source: &[u8]) -> Option<String> {
+    let raw_key = node_text(pair.child_by_field_name("key")?, source)?;
+    Some(decode_json_string(&raw_key).unwrap_or_else(|| raw_key.trim_matches('"').to_string()))
+}
+
+fn indent_continuation_lines(value_text: &str, indent: &str, line_ending: &str) -> String {
+    value_text
+        .lines()
+        .enumerate()
+        .map(|(index, line)| {
+            if index == 0 || line.trim().is_empty() {
+                line.to_string()
+            } else {
+                format!("{indent}{line}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(line_ending)
+}
+
+fn json_string_literal(text: &str) -> String {
+    serde_json::to_string(text).unwrap_or_else(|_| format!("\"{text}\""))
+}
+
+/// Bare keys stay bare; anything else becomes a basic string, whose escapes are a
+/// superset of the ones `serde_json` emits.
+fn toml_key_literal(key: &str) -> String {
+    if key
+        .bytes()
+        .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_')
+    {
+        key.to_string()
+    } else {
+        json_string_literal(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{indent_continuation_lines, relocatable_value_text, toml_key_literal};
+
+    #[test]
+    fn relocatable_value_text_strips_the_source_line_indentation() {
+        let source = "a:\n  b:\n    c: 1\n    d: 2\n";
+        let start = source.find("c:").expect("value start");
+        let end = source.len() - 1;
+        assert_eq!(relocatable_value_text(source, start, end), "c: 1\nd: 2");
+    }
+
+    #[test]
+    fn indent_continuation_lines_leaves_the_first_line_in_place() {
+        assert_eq!(
+            indent_continuation_lines("[\n  1\n]", "    ", "\r\n"),
+            "[\r\n      1\r\n    ]"
+        );
+    }
+
+    #[test]
+    fn toml_key_literal_quotes_keys_outside_the_bare_key_charset() {
+        assert_eq!(toml_key_literal("listen_port"), "listen_port");
+        assert_eq!(toml_key_literal("listen.port"), "\"listen.port\"");
+        assert_eq!(toml_key_literal("ünïcode"), "\"ünïcode\"");
+    }
+}
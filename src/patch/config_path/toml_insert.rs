@@ -0,0 +1,201 @@
+//! Format-preserving create-missing for TOML documents.
+//!
+//! The document is loaded with `toml_edit`, which keeps comments, whitespace, and
+//! table ordering, so only the created keys/tables show up as new text. Missing
+//! intermediate tables under a standard table become implicit (no header of their
+//! own); missing keys inside inline tables stay inline.
+
+use std::str::FromStr;
+
+use toml_edit::{Array, DocumentMut, InlineTable, Item, Table, TableLike, Value};
+
+use super::{
+    PathToken, apply_source_line_ending_style, array_index_out_of_bounds_error,
+    expected_path_container_error,
+};
+use crate::error::IdenteditError;
+
+pub(super) fn render_toml_with_create_missing(
+    source_text: &str,
+    path_tokens: &[PathToken],
+    raw_path: &str,
+    new_text: &str,
+) -> Result<String, IdenteditError> {
+    let parse_input = if source_text.contains('\r') && !source_text.contains('\n') {
+        source_text.replace('\r', "\n")
+    } else {
+        source_text.to_string()
+    };
+    let mut document =
+        DocumentMut::from_str(&parse_input).map_err(|error| IdenteditError::InvalidRequest {
+            message: format!("Config path create-missing could not parse TOML document: {error}"),
+        })?;
+    let new_value = parse_toml_value_fragment(new_text)?;
+
+    // A comment-only document keeps its comments as trailing decor; lift them above
+    // the inserted tables so they stay at the top of the file.
+    let mut leading = String::new();
+    if document.as_table().is_empty() {
+        leading = document.trailing().as_str().unwrap_or_default().to_string();
+        if !leading.is_empty() && !leading.ends_with('\n') {
+            leading.push('\n');
+        }
+        document.set_trailing("");
+    }
+
+    let insert = TomlInsert {
+        raw_path,
+        new_value: &new_value,
+    };
+    insert.set_in_item(document.as_item_mut(), path_tokens)?;
+
+    Ok(apply_source_line_ending_style(
+        &format!("{leading}{document}"),
+        source_text,
+    ))
+}
+
+fn parse_toml_value_fragment(fragment: &str) -> Result<Value, IdenteditError> {
+    let mut value =
+        Value::from_str(fragment.trim()).map_err(|error| IdenteditError::InvalidRequest {
+            message: format!("Config path set value is not valid TOML value text: {error}"),
+        })?;
+    value.decor_mut().clear();
+    Ok(value)
+}
+
+struct TomlInsert<'a> {
+    raw_path: &'a str,
+    new_value: &'a Value,
+}
+
+impl TomlInsert<'_> {
+    fn set_in_item(
+        &self,
+        item: &mut Item,
+        path_tokens: &[PathToken],
+    ) -> Result<(), IdenteditError> {
+        let Some((head, tail)) = path_tokens.split_first() else {
+            *item = Item::Value(self.new_value.clone());
+            return Ok(());
+        };
+
+        match item {
+            Item::Table(table) => self.set_in_table(table, false, head, tail),
+            Item::Value(value) => self.set_in_value(value, path_tokens),
+            Item::ArrayOfTables(tables) => {
+                let PathToken::Index(index) = head else {
+                    return Err(self.container_error(head, "array of tables"));
+                };
+                let len = tables.len();
+                let table = tables
+                    .get_mut(*index)
+                    .ok_or_else(|| array_index_out_of_bounds_error(self.raw_path, *index, len))?;
+                let Some((next, rest)) = tail.split_first() else {
+                    return Err(IdenteditError::InvalidRequest {
+                        message: format!(
+                            "Config path '{}' cannot replace an array-of-tables entry with a value",
+                            self.raw_path
+                        ),
+                    });
+                };
+                self.set_in_table(table, false, next, rest)
+            }
+            Item::None => Err(self.container_error(head, item.type_name())),
+        }
+    }
+
+    fn set_in_value(
+        &self,
+        value: &mut Value,
+        path_tokens: &[PathToken],
+    ) -> Result<(), IdenteditError> {
+        let Some((head, tail)) = path_tokens.split_first() else {
+            let decor = value.decor().clone();
+            *value = self.new_value.clone();
+            *value.decor_mut() = decor;
+            return Ok(());
+        };
+
+        match value {
+            Value::InlineTable(table) => {
+                let PathToken::Key(key) = head else {
+                    return Err(self.container_error(head, "inline table"));
+                };
+                if table.contains_key(key) {
+                    return self.set_in_table(table, true, head, tail);
+                }
+
+                // Move the space before `}` from the old last entry to the new one so the
+                // table reads `{ a = 1, b = 2 }` rather than `{ a = 1 , b = 2}`.
+                let closing_space = table.iter_mut().last().and_then(|(_, last)| {
+                    let suffix = last.decor().suffix().cloned();
+                    last.decor_mut().set_suffix("");
+                    suffix
+                });
+                self.set_in_table(table, true, head, tail)?;
+                if let (Some(space), Some(inserted)) = (closing_space, table.get_mut(key)) {
+                    inserted.decor_mut().set_suffix(space);
+                }
+                Ok(())
+            }
+            Value::Array(array) => {
+                let PathToken::Index(index) = head else {
+                    return Err(self.container_error(head, "array"));
+                };
+                let len = array.len();
+                let element = array
+                    .get_mut(*index)
+                    .ok_or_else(|| array_index_out_of_bounds_error(self.raw_path, *index, len))?;
+                self.set_in_value(element, tail)
+            }
+            other => Err(self.container_error(head, other.type_name())),
+        }
+    }
+
+    fn set_in_table(
+        &self,
+        table: &mut dyn TableLike,
+        inline: bool,
+        head: &PathToken,
+        tail: &[PathToken],
+    ) -> Result<(), IdenteditError> {
+        let PathToken::Key(key) = head else {
+            let kind = if inline { "inline table" } else { "table" };
+            return Err(self.container_error(head, kind));
+        };
+
+        if tail.is_empty() {
+            table.insert(key, Item::Value(self.new_value.clone()));
+            return Ok(());
+        }
+        if !table.contains_key(key) {
+            table.insert(key, empty_container_for_token(&tail[0], inline));
+        }
+        let child = table
+            .get_mut(key)
+            .ok_or_else(|| IdenteditError::InvalidRequest {
+                message: format!(
+                    "Config path '{}' segment '{key}' was not found",
+                    self.raw_path
+                ),
+            })?;
+        self.set_in_item(child, tail)
+    }
+
+    fn container_error(&self, token: &PathToken, actual_kind: &str) -> IdenteditError {
+        expected_path_container_error(self.raw_path, token, actual_kind)
+    }
+}
+
+fn empty_container_for_token(next: &PathToken, inline: bool) -> Item {
+    match next {
+        PathToken::Key(_) if inline => Item::Value(Value::InlineTable(InlineTable::new())),
+        PathToken::Key(_) => {
+            let mut table = Table::new();
+            table.set_implicit(true);
+            Item::Table(table)
+        }
+        PathToken::Index(_) => Item::Value(Value::Array(Array::new())),
+    }
+}
@@ -0,0 +1,125 @@
+//! Typed value text (`value_type`) for config writes.
+//!
+//! Callers pass a plain value plus its type and get the literal spelled the way the
+//! target file's format expects, instead of hand-quoting for JSON, YAML, or TOML.
+
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::{ConfigFormat, detect_config_format, yaml_insert::yaml_string_literal};
+use crate::error::IdenteditError;
+
+/// How the value text of a config write is interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigValueType {
+    /// Literal syntax of the target format, inserted verbatim.
+    #[default]
+    Raw,
+    /// Plain text, quoted and escaped for the target format.
+    String,
+    /// A base-10 signed 64-bit integer.
+    Int,
+    /// `true` or `false`.
+    Bool,
+}
+
+impl ConfigValueType {
+    fn name(self) -> &'static str {
+        match self {
+            ConfigValueType::Raw => "raw",
+            ConfigValueType::String => "string",
+            ConfigValueType::Int => "int",
+            ConfigValueType::Bool => "bool",
+        }
+    }
+}
+
+pub fn encode_config_value(
+    file: &Path,
+    value_type: ConfigValueType,
+    text: String,
+) -> Result<String, IdenteditError> {
+    match value_type {
+        ConfigValueType::Raw => Ok(text),
+        ConfigValueType::String => Ok(match detect_config_format(file)? {
+            ConfigFormat::Json => serde_json::to_string(&text)
+                .map_err(|error| invalid_value_error(value_type, &text, &error.to_string()))?,
+            ConfigFormat::Yaml => yaml_string_literal(&text),
+            ConfigFormat::Toml => toml_edit::Value::from(text.as_str()).to_string(),
+        }),
+        ConfigValueType::Int => text
+            .trim()
+            .parse::<i64>()
+            .map(|value| value.to_string())
+            .map_err(|error| invalid_value_error(value_type, &text, &error.to_string())),
+        ConfigValueType::Bool => match text.trim() {
+            "true" | "false" => Ok(text.trim().to_string()),
+            _ => Err(invalid_value_error(
+                value_type,
+                &text,
+                "expected 'true' or 'false'",
+            )),
+        },
+    }
+}
+
+fn invalid_value_error(value_type: ConfigValueType, text: &str, detail: &str) -> IdenteditError {
+    IdenteditError::InvalidRequest {
+        message: format!(
+            "Config value '{text}' is not a valid {} value: {detail}",
+            value_type.name()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{ConfigValueType, encode_config_value};
+
+    fn encode(file: &str, value_type: ConfigValueType, text: &str) -> String {
+        encode_config_value(Path::new(file), value_type, text.to_string())
+            .expect("value should encode")
+    }
+
+    #[test]
+    fn string_values_are_quoted_for_each_format() {
+        assert_eq!(
+            encode("a.json", ConfigValueType::String, "say \"hi\""),
+            r#""say \"hi\"""#
+        );
+        assert_eq!(
+            encode("a.yaml", ConfigValueType::String, "8080"),
+            r#""8080""#
+        );
+        assert_eq!(encode("a.yaml", ConfigValueType::String, "plain"), "plain");
+        for text in ["yes", "off", "~", "0755", "1:30"] {
+            assert_eq!(
+                encode("a.yaml", ConfigValueType::String, text),
+                format!("\"{text}\""),
+                "{text}"
+            );
+        }
+        assert_eq!(
+            encode("a.toml", ConfigValueType::String, "C:\\dir"),
+            r"'C:\dir'"
+        );
+    }
+
+    #[test]
+    fn int_and_bool_values_are_validated() {
+        assert_eq!(encode("a.toml", ConfigValueType::Int, " +42 "), "42");
+        assert_eq!(encode("a.yaml", ConfigValueType::Bool, "false"), "false");
+        encode_config_value(Path::new("a.json"), ConfigValueType::Int, "4.2".to_string())
+            .expect_err("non-integer must fail");
+        encode_config_value(
+            Path::new("a.json"),
+            ConfigValueType::Bool,
+            "yes".to_string(),
+        )
+        .expect_err("non-boolean must fail");
+    }
+}
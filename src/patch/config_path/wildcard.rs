@@ -0,0 +1,286 @@
+//! Wildcard config paths (`services.*.image`, `items[*].name`).
+//!
+//! A pattern is expanded against a parsed view of the document into concrete paths,
+//! checked against the caller's `expected_match_count`, and then applied one path at a
+//! time to an in-memory copy. Paths are applied in descending order so removing an
+//! array element never shifts the index of a path that is still pending. The combined
+//! result is emitted as a single replacement, like `merge_object`.
+
+use std::path::Path;
+
+use serde_json::Value;
+
+use super::{
+    ConfigPathOperation, PathSegment, PathToken, ResolvedConfigPatch, apply_patch_to_text,
+    build_patch_from_text_change, detect_config_format, document::resolve_in_document,
+    load_document_value, parse_config_path, parse_config_path_pattern, path_tokens_display,
+    read_config_source, resolve_config_path_operation_for_source,
+};
+use crate::error::IdenteditError;
+use crate::provider::ProviderRegistry;
+
+/// How many matched paths are listed in a count-mismatch error.
+const MATCH_PREVIEW_LIMIT: usize = 10;
+
+pub fn resolve_config_path_pattern_operation(
+    file: &Path,
+    raw_path: &str,
+    document: Option<&str>,
+    expected_file_hash: Option<&str>,
+    expected_match_count: Option<usize>,
+    operation: ConfigPathOperation,
+    registry: &ProviderRegistry,
+) -> Result<ResolvedConfigPatch, IdenteditError> {
+    let source_text = read_config_source(file, expected_file_hash)?;
+    resolve_in_document(
+        file,
+        &source_text,
+        document,
+        |source_text| {
+            resolve_pattern_for_source(
+                file,
+                source_text,
+                raw_path,
+                expected_match_count,
+                operation,
+                registry,
+            )
+        },
+        registry,
+    )
+}
+
+fn resolve_pattern_for_source(
+    file: &Path,
+    source_text: &str,
+    raw_path: &str,
+    expected_match_count: Option<usize>,
+    operation: ConfigPathOperation,
+    registry: &ProviderRegistry,
+) -> Result<ResolvedConfigPatch, IdenteditError> {
+    let segments = parse_config_path_pattern(raw_path)?;
+
+    let concrete = segments
+        .iter()
+        .map(|segment| match segment {
+            PathSegment::Token(token) => Some(token.clone()),
+            PathSegment::AnyKey | PathSegment::AnyIndex => None,
+        })
+        .collect::<Option<Vec<_>>>();
+    if let Some(path_tokens) = concrete {
+        check_expected_match_count(
+            raw_path,
+            expected_match_count,
+            std::slice::from_ref(&path_tokens),
+        )?;
+        return resolve_config_path_operation_for_source(
+            file,
+            source_text.as_bytes(),
+            source_text,
+            &path_tokens,
+            raw_path,
+            operation,
+            registry,
+        );
+    }
+
+    let format = detect_config_format(file)?;
+    let document = load_document_value(&format, source_text)?;
+    let allow_missing_tail = matches!(
+        operation,
+        ConfigPathOperation::Set {
+            create_missing: true,
+            ..
+        }
+    );
+    let mut matches = Vec::new();
+    expand_pattern(
+        &document,
+        &segments,
+        allow_missing_tail,
+        &mut Vec::new(),
+        &mut matches,
+    );
+    if matches.is_empty() {
+        return Err(IdenteditError::InvalidRequest {
+            message: format!("Config path pattern '{raw_path}' matched no targets"),
+        });
+    }
+    check_expected_match_count(raw_path, expected_match_count, &matches)?;
+
+    matches.sort();
+    let mut updated_text = source_text.to_string();
+    for path_tokens in matches.iter().rev() {
+        let patch = resolve_config_path_operation_for_source(
+            file,
+            updated_text.as_bytes(),
+            &updated_text,
+            path_tokens,
+            &path_tokens_display(path_tokens),
+            operation.clone(),
+            registry,
+        )?;
+        updated_text = apply_patch_to_text(&updated_text, patch)?;
+    }
+
+    build_patch_from_text_change(file, source_text, &updated_text, registry)
+}
+
+/// Operations that always address exactly one path (`merge_object`, `move`) still honour
+/// an explicit `expected_match_count`, which can then only be 1.
+pub fn check_single_target_match_count(
+    raw_path: &str,
+    expected_match_count: Option<usize>,
+) -> Result<(), IdenteditError> {
+    let path_tokens = parse_config_path(raw_path)?;
+    check_expected_match_count(raw_path, expected_match_count, &[path_tokens])
+}
+
+fn check_expected_match_count(
+    raw_path: &str,
+    expected_match_count: Option<usize>,
+    matches: &[Vec<PathToken>],
+) -> Result<(), IdenteditError> {
+    let Some(expected) = expected_match_count else {
+        return Ok(());
+    };
+    if matches.len() == expected {
+        return Ok(());
+    }
+
+    let mut preview = matches
+        .iter()
+        .take(MATCH_PREVIEW_LIMIT)
+        .map(|path| path_tokens_display(path))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if matches.len() > MATCH_PREVIEW_LIMIT {
+        preview.push_str(", ...");
+    }
+    Err(IdenteditError::InvalidRequest {
+        message: format!(
+            "Config path '{raw_path}' matched {} target(s) but expected_match_count is {expected} (matched: {preview})",
+            matches.len()
+        ),
+    })
+}
+
+/// Collects every concrete path matching `segments`. Wildcards only match entries that
+/// exist; a literal segment must exist too, unless `allow_missing_tail` is set and no
+/// wildcard follows it (create-missing `set` then builds the rest).
+fn expand_pattern(
+    current: &Value,
+    segments: &[PathSegment],
+    allow_missing_tail: bool,
+    prefix: &mut Vec<PathToken>,
+    out: &mut Vec<Vec<PathToken>>,
+) {
+    let Some((head, rest)) = segments.split_first() else {
+        out.push(prefix.clone());
+        return;
+    };
+
+    let children: Vec<(PathToken, &Value)> = match (head, current) {
+        (PathSegment::AnyKey, Value::Object(map)) => map
+            .iter()
+            .map(|(key, value)| (PathToken::Key(key.clone()), value))
+            .collect(),
+        (PathSegment::AnyIndex, Value::Array(items)) => items
+            .iter()
+            .enumerate()
+            .map(|(index, value)| (PathToken::Index(index), value))
+            .collect(),
+        (PathSegment::Token(token), _) => {
+            let child = match (token, current) {
+                (PathToken::Key(key), Value::Object(map)) => map.get(key),
+                (PathToken::Index(index), Value::Array(items)) => items.get(*index),
+                _ => None,
+            };
+            match child {
+                Some(child) => vec![(token.clone(), child)],
+                None if allow_missing_tail && is_literal_tail(rest) => {
+                    let mut path = prefix.clone();
+                    path.push(token.clone());
+                    path.extend(rest.iter().filter_map(|segment| match segment {
+                        PathSegment::Token(token) => Some(token.clone()),
+                        PathSegment::AnyKey | PathSegment::AnyIndex => None,
+                    }));
+                    out.push(path);
+                    return;
+                }
+                None => return,
+            }
+        }
+        _ => return,
+    };
+
+    for (token, child) in children {
+        prefix.push(token);
+        expand_pattern(child, rest, allow_missing_tail, prefix, out);
+        prefix.pop();
+    }
+}
+
+fn is_literal_tail(segments: &[PathSegment]) -> bool {
+    segments
+        .iter()
+        .all(|segment| matches!(segment, PathSegment::Token(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{PathToken, expand_pattern};
+    use crate::patch::config_path::parse_config_path_pattern;
+
+    fn expand(document: serde_json::Value, pattern: &str, allow_missing_tail: bool) -> Vec<String> {
+        let segments = parse_config_path_pattern(pattern).expect("pattern should parse");
+        let mut out = Vec::new();
+        expand_pattern(
+            &document,
+            &segments,
+            allow_missing_tail,
+            &mut Vec::new(),
+            &mut out,
+        );
+        out.iter()
+            .map(|path| super::path_tokens_display(path))
+            .collect()
+    }
+
+    #[test]
+    fn expand_pattern_skips_entries_without_the_literal_tail() {
+        let document = json!({"services": {"api": {"image": "a"}, "db": {"port": 1}}});
+        assert_eq!(
+            expand(document.clone(), "services.*.image", false),
+            vec!["services.api.image"]
+        );
+        assert_eq!(
+            expand(document, "services.*.image", true),
+            vec!["services.api.image", "services.db.image"]
+        );
+    }
+
+    #[test]
+    fn expand_pattern_walks_every_array_index() {
+        let document = json!({"items": [{"name": "a"}, {"name": "b"}, 3]});
+        assert_eq!(
+            expand(document, "items[*].name", false),
+            vec!["items[0].name", "items[1].name"]
+        );
+    }
+
+    #[test]
+    fn descending_path_order_puts_higher_indices_first() {
+        let mut paths = [
+            vec![PathToken::Key("items".to_string()), PathToken::Index(2)],
+            vec![PathToken::Key("items".to_string()), PathToken::Index(10)],
+        ];
+        paths.sort();
+        assert_eq!(
+            paths.last().map(|path| path[1].clone()),
+            Some(PathToken::Index(10))
+        );
+    }
+}
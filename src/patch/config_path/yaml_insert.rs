@@ -0,0 +1,428 @@
+//! Format-preserving create-missing for YAML documents.
+//!
+//! Missing keys are spliced in as new text next to the deepest mapping that already
+//! exists on the path, so comments, anchors, key order, and sibling documents stay
+//! byte-for-byte intact. Only the first document of a multi-document stream is
+//! addressed, matching regular config path resolution.
+//!
+//! Still unsupported: creating keys beneath an alias (`*name`), creating sequence
+//! items by index, and multi-line values inside flow mappings (`{...}`).
+
+use std::path::Path;
+use std::sync::OnceLock;
+
+use regex::Regex;
+use tree_sitter::Node;
+
+use super::{
+    ConfigFormat, ConfigPathOperation, CreateMissingSetRequest, PathToken, ResolvedConfigPatch,
+    ResolvedContainerEdit, array_index_out_of_bounds_error,
+    build_resolved_patch_from_container_edit, expected_path_container_error, line_ending_literal,
+    named_children, parse_tree_for_format, resolve_yaml_path, span_from_node, unique_match,
+    yaml_key_text, yaml_root_value, yaml_unwrap_node,
+};
+use crate::changeset::{OpKind, TransformTarget};
+use crate::error::IdenteditError;
+use crate::handle::Span;
+use crate::hash::hash_bytes;
+use crate::provider::ProviderRegistry;
+
+struct InsertionPoint<'tree> {
+    mapping: Node<'tree>,
+    depth: usize,
+}
+
+pub(super) fn resolve_yaml_set_with_create_missing(
+    file: &Path,
+    request: &CreateMissingSetRequest<'_>,
+    registry: &ProviderRegistry,
+) -> Result<ResolvedConfigPatch, IdenteditError> {
+    serde_yaml::from_str::<serde_yaml::Value>(request.new_text).map_err(|error| {
+        IdenteditError::InvalidRequest {
+            message: format!("Config path set value is not valid YAML: {error}"),
+        }
+    })?;
+    let value_text = request.new_text.trim_end_matches(['\n', '\r']);
+    let line_ending = line_ending_literal(request.source_text);
+
+    let Some(root) = yaml_root_value(request.tree.root_node()) else {
+        let keys = missing_key_chain(request.path_tokens, request.raw_path)?;
+        let block = render_block_entries(&keys, "", value_text, line_ending);
+        let separator =
+            if request.source_text.is_empty() || request.source_text.ends_with(['\n', '\r']) {
+                ""
+            } else {
+                line_ending
+            };
+        return Ok(ResolvedConfigPatch {
+            target: TransformTarget::FileEnd {
+                expected_file_hash: hash_bytes(request.source),
+            },
+            op: OpKind::Insert {
+                new_text: format!("{separator}{block}{line_ending}"),
+            },
+        });
+    };
+
+    let insertion =
+        locate_insertion_point(root, request.source, request.path_tokens, request.raw_path)?;
+    let keys = missing_key_chain(&request.path_tokens[insertion.depth..], request.raw_path)?;
+    let (replace_span, replacement) = match insertion.mapping.kind() {
+        "block_mapping" => block_mapping_insertion(
+            insertion.mapping,
+            request.source_text,
+            &keys,
+            value_text,
+            line_ending,
+        ),
+        "flow_mapping" => {
+            flow_mapping_insertion(insertion.mapping, &keys, value_text, request.raw_path)?
+        }
+        other => {
+            return Err(expected_path_container_error(
+                request.raw_path,
+                &request.path_tokens[insertion.depth],
+                other,
+            ));
+        }
+    };
+
+    verify_inserted_path_resolves(request, replace_span, &replacement)?;
+    build_resolved_patch_from_container_edit(
+        file,
+        request.source,
+        request.source_text,
+        ResolvedContainerEdit {
+            container_span: span_from_node(insertion.mapping),
+            container_kind: insertion.mapping.kind().to_string(),
+            replace_span,
+        },
+        &replacement,
+        registry,
+    )
+}
+
+fn locate_insertion_point<'tree>(
+    root: Node<'tree>,
+    source: &[u8],
+    path_tokens: &[PathToken],
+    raw_path: &str,
+) -> Result<InsertionPoint<'tree>, IdenteditError> {
+    let mut current = root;
+
+    for (depth, token) in path_tokens.iter().enumerate() {
+        match token {
+            PathToken::Key(expected_key) => {
+                let pair_kind = match current.kind() {
+                    "block_mapping" => "block_mapping_pair",
+                    "flow_mapping" => "flow_pair",
+                    other => return Err(expected_path_container_error(raw_path, token, other)),
+                };
+                let matches = named_children(current)
+                    .into_iter()
+                    .filter(|pair| {
+                        pair.kind() == pair_kind
+                            && pair
+                                .child_by_field_name("key")
+                                .and_then(|key_node| yaml_key_text(key_node, source))
+                                .is_some_and(|key_text| key_text == *expected_key)
+                    })
+                    .collect::<Vec<_>>();
+                if matches.is_empty() {
+                    return Ok(InsertionPoint {
+                        mapping: current,
+                        depth,
+                    });
+                }
+
+                let matched_pair = unique_match(raw_path, token, matches)?;
+                current = matched_pair
+                    .child_by_field_name("value")
+                    .and_then(yaml_unwrap_node)
+                    .ok_or_else(|| IdenteditError::InvalidRequest {
+                        message: format!(
+                            "Config path '{raw_path}' matched key '{expected_key}' without a value node"
+                        ),
+                    })?;
+            }
+            PathToken::Index(expected_index) => {
+                let items = match current.kind() {
+                    "block_sequence" => named_children(current)
+                        .into_iter()
+                        .filter(|child| child.kind() == "block_sequence_item")
+                        .collect::<Vec<_>>(),
+                    "flow_sequence" => named_children(current)
+                        .into_iter()
+                        .filter(|child| child.kind() != "comment")
+                        .collect::<Vec<_>>(),
+                    other => return Err(expected_path_container_error(raw_path, token, other)),
+                };
+                let item = items.get(*expected_index).ok_or_else(|| {
+                    array_index_out_of_bounds_error(raw_path, *expected_index, items.len())
+                })?;
+                current =
+                    yaml_unwrap_node(*item).ok_or_else(|| IdenteditError::InvalidRequest {
+                        message: format!(
+                            "Config path '{raw_path}' points at empty YAML sequence item"
+                        ),
+                    })?;
+            }
+        }
+    }
+
+    Err(IdenteditError::InvalidRequest {
+        message: format!(
+            "Config path '{raw_path}' already exists; create-missing found no key to insert"
+        ),
+    })
+}
+
+/// Missing path suffix as plain keys. Sequence slots are never created, so an index
+/// below a missing key is out of range of the (empty) sequence that would exist there.
+fn missing_key_chain<'a>(
+    path_tokens: &'a [PathToken],
+    raw_path: &str,
+) -> Result<Vec<&'a str>, IdenteditError> {
+    path_tokens
+        .iter()
+        .map(|token| match token {
+            PathToken::Key(key) => Ok(key.as_str()),
+            PathToken::Index(index) => Err(array_index_out_of_bounds_error(raw_path, *index, 0)),
+        })
+        .collect()
+}
+
+fn block_mapping_insertion(
+    mapping: Node<'_>,
+    source_text: &str,
+    keys: &[&str],
+    value_text: &str,
+    line_ending: &str,
+) -> (Span, String) {
+    let pairs = named_children(mapping)
+        .into_iter()
+        .filter(|child| child.kind() == "block_mapping_pair")
+        .collect::<Vec<_>>();
+    let first = pairs.first().copied().unwrap_or(mapping);
+    let last = pairs.last().copied().unwrap_or(mapping);
+
+    let line_start = source_text[..first.start_byte()]
+        .rfind(['\n', '\r'])
+        .map(|index| index + 1)
+        .unwrap_or(0);
+    let column = source_text[line_start..first.start_byte()].chars().count();
+    let block = render_block_entries(keys, &" ".repeat(column), value_text, line_ending);
+
+    // Insert after the rest of the last pair's line so trailing comments stay attached.
+    let insert_at = source_text[last.end_byte()..]
+        .find(['\n', '\r'])
+        .map(|offset| last.end_byte() + offset)
+        .unwrap_or(source_text.len());
+    let replacement = format!("{line_ending}{block}");
+    (
+        Span {
+            start: insert_at,
+            end: insert_at,
+        },
+        replacement,
+    )
+}
+
+fn flow_mapping_insertion(
+    mapping: Node<'_>,
+    keys: &[&str],
+    value_text: &str,
+    raw_path: &str,
+) -> Result<(Span, String), IdenteditError> {
+    if value_text.contains(['\n', '\r']) {
+        return Err(IdenteditError::InvalidRequest {
+            message: format!(
+                "Config path create-missing cannot insert a multi-line value into a YAML flow mapping at '{raw_path}'"
+            ),
+        });
+    }
+
+    let entry = render_flow_entry(keys, value_text);
+    let last_entry = named_children(mapping)
+        .into_iter()
+        .rev()
+        .find(|child| child.kind() != "comment");
+    Ok(match last_entry {
+        Some(last) => (
+            Span {
+                start: last.end_byte(),
+                end: last.end_byte(),
+            },
+            format!(", {entry}"),
+        ),
+        None => {
+            let after_brace = mapping.start_byte() + 1;
+            (
+                Span {
+                    start: after_brace,
+                    end: after_brace,
+                },
+                entry,
+            )
+        }
+    })
+}
+
+fn render_block_entries(
+    keys: &[&str],
+    indent: &str,
+    value_text: &str,
+    line_ending: &str,
+) -> String {
+    let mut lines = Vec::new();
+    for (level, key) in keys.iter().enumerate() {
+        let pad = format!("{indent}{}", "  ".repeat(level));
+        let key = yaml_string_literal(key);
+        if level + 1 < keys.len() {
+            lines.push(format!("{pad}{key}:"));
+        } else if !value_text.contains(['\n', '\r']) {
+            lines.push(format!("{pad}{key}: {value_text}"));
+        } else {
+            lines.push(format!("{pad}{key}:"));
+            for value_line in value_text.lines() {
+                if value_line.trim().is_empty() {
+                    lines.push(String::new());
+                } else {
+                    lines.push(format!("{pad}  {value_line}"));
+                }
+            }
+        }
+    }
+    lines.join(line_ending)
+}
+
+fn render_flow_entry(keys: &[&str], value_text: &str) -> String {
+    let mut rendered = value_text.to_string();
+    for (level, key) in keys.iter().enumerate().rev() {
+        let key = yaml_string_literal(key);
+        rendered = if level == 0 {
+            format!("{key}: {rendered}")
+        } else {
+            format!("{{{key}: {rendered}}}")
+        };
+    }
+    rendered
+}
+
+/// Plain strings stay plain; text that YAML would read as a non-string (`true`, `123`,
+/// `null`, ...) or differently (comments, folding) is double-quoted so it reads back
+/// verbatim. That includes what only YAML 1.1 readers such as PyYAML resolve to other types
+/// (`yes`, `off`, `~`, `0755`, `1:30`, dates). Flow indicators are always quoted so the text
+/// is also safe inside `{...}`.
+pub(super) fn yaml_string_literal(text: &str) -> String {
+    let plain_is_exact = !text.contains([',', '[', ']', '{', '}'])
+        && !is_yaml_1_1_non_string(text)
+        && matches!(
+            serde_yaml::from_str::<serde_yaml::Value>(text),
+            Ok(serde_yaml::Value::String(parsed)) if parsed == text
+        );
+    if plain_is_exact {
+        text.to_string()
+    } else {
+        serde_json::to_string(text).unwrap_or_else(|_| format!("\"{text}\""))
+    }
+}
+
+/// Whether a YAML 1.1 reader resolves the plain scalar `text` to a bool, null, int, float,
+/// or timestamp, following the 1.1 type repository's regular expressions.
+fn is_yaml_1_1_non_string(text: &str) -> bool {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| {
+        Regex::new(concat!(
+            r"^(?:",
+            r"y|Y|yes|Yes|YES|n|N|no|No|NO|true|True|TRUE|false|False|FALSE|on|On|ON|off|Off|OFF",
+            r"|~|null|Null|NULL",
+            r"|[-+]?0b[01_]+|[-+]?0[0-7_]+|[-+]?(?:0|[1-9][0-9_]*)|[-+]?0x[0-9a-fA-F_]+",
+            r"|[-+]?[1-9][0-9_]*(?::[0-5]?[0-9])+",
+            r"|[-+]?(?:[0-9][0-9_]*)?\.[0-9.]*(?:[eE][-+][0-9]+)?",
+            r"|[-+]?[0-9][0-9_]*(?::[0-5]?[0-9])+\.[0-9_]*",
+            r"|[-+]?\.(?:inf|Inf|INF)|\.(?:nan|NaN|NAN)",
+            r"|[0-9]{4}-[0-9]{1,2}-[0-9]{1,2}(?:(?:[Tt]|[ \t]+)[0-9]{1,2}:[0-9]{2}:[0-9]{2}.*)?",
+            r")$",
+        ))
+        .expect("YAML 1.1 scalar pattern should compile")
+    });
+    pattern.is_match(text)
+}
+
+fn verify_inserted_path_resolves(
+    request: &CreateMissingSetRequest<'_>,
+    replace_span: Span,
+    replacement: &str,
+) -> Result<(), IdenteditError> {
+    let mut updated = request.source_text.to_string();
+    updated.replace_range(replace_span.start..replace_span.end, replacement);
+
+    let invalid_insertion = |detail: String| IdenteditError::InvalidRequest {
+        message: format!(
+            "Config path create-missing could not insert '{}' into YAML document without breaking its structure: {detail}",
+            request.raw_path
+        ),
+    };
+    let tree = parse_tree_for_format(&ConfigFormat::Yaml, updated.as_bytes())
+        .map_err(|error| invalid_insertion(error.to_string()))?;
+    let probe = ConfigPathOperation::Set {
+        new_text: String::new(),
+        create_missing: false,
+    };
+    resolve_yaml_path(
+        &tree,
+        updated.as_bytes(),
+        request.path_tokens,
+        &probe,
+        request.raw_path,
+    )
+    .map(|_| ())
+    .map_err(|error| invalid_insertion(error.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_block_entries, render_flow_entry, yaml_string_literal};
+
+    #[test]
+    fn render_block_entries_nests_keys_and_indents_multiline_values() {
+        let rendered = render_block_entries(&["sidecar", "env"], "  ", "A: 1\nB: 2", "\n");
+        assert_eq!(rendered, "  sidecar:\n    env:\n      A: 1\n      B: 2");
+    }
+
+    #[test]
+    fn render_flow_entry_wraps_nested_keys_in_flow_mappings() {
+        assert_eq!(render_flow_entry(&["a", "b", "c"], "1"), "a: {b: {c: 1}}");
+    }
+
+    #[test]
+    fn yaml_string_literal_quotes_text_that_would_not_read_back_as_strings() {
+        assert_eq!(yaml_string_literal("port"), "port");
+        assert_eq!(yaml_string_literal("true"), "\"true\"");
+        assert_eq!(yaml_string_literal("8080"), "\"8080\"");
+        assert_eq!(yaml_string_literal("a, b"), "\"a, b\"");
+    }
+
+    #[test]
+    fn yaml_string_literal_quotes_yaml_1_1_scalars() {
+        for text in [
+            "yes",
+            "no",
+            "on",
+            "off",
+            "y",
+            "N",
+            "~",
+            "0755",
+            "1:30",
+            "0x1F",
+            "1_000",
+            "2001-12-14",
+        ] {
+            assert_eq!(yaml_string_literal(text), format!("\"{text}\""), "{text}");
+        }
+        assert_eq!(yaml_string_literal("yesterday"), "yesterday");
+        assert_eq!(yaml_string_literal("v1:30"), "v1:30");
+    }
+}
@@ -0,0 +1,148 @@
+//! `--respect-editorconfig`: inserted and replacement text follows the indentation and line
+//! endings that `.editorconfig` sets for the target file.
+
+use std::path::Path;
+
+use ec4rs::property::{EndOfLine, FinalNewline, IndentSize, IndentStyle, TabWidth};
+
+use crate::error::IdenteditError;
+
+/// The `.editorconfig` properties that shape new text; unset ones leave it alone.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct EditorconfigStyle {
+    indent_style: Option<IndentStyle>,
+    indent_size: Option<usize>,
+    tab_width: Option<usize>,
+    newline: Option<&'static str>,
+    /// `insert_final_newline`: text that ends the file gets a line break.
+    pub(crate) insert_final_newline: bool,
+}
+
+impl EditorconfigStyle {
+    /// Resolves every `.editorconfig` from the file's directory up to the first with
+    /// `root = true`.
+    pub(crate) fn for_file(file: &Path) -> Result<Self, IdenteditError> {
+        let absolute =
+            std::path::absolute(file).map_err(|error| IdenteditError::io(file, error))?;
+        let mut properties =
+            ec4rs::properties_of(&absolute).map_err(|error| IdenteditError::InvalidRequest {
+                message: format!(
+                    "Failed to read .editorconfig for '{}': {error}",
+                    file.display()
+                ),
+            })?;
+        properties.use_fallbacks();
+
+        let tab_width = match properties.get::<TabWidth>() {
+            Ok(TabWidth::Value(width)) if width > 0 => Some(width),
+            _ => None,
+        };
+        Ok(Self {
+            indent_style: properties.get::<IndentStyle>().ok(),
+            indent_size: match properties.get::<IndentSize>() {
+                Ok(IndentSize::Value(size)) if size > 0 => Some(size),
+                Ok(IndentSize::UseTabWidth) => tab_width,
+                _ => None,
+            },
+            tab_width,
+            newline: match properties.get::<EndOfLine>() {
+                Ok(EndOfLine::Lf) => Some("\n"),
+                Ok(EndOfLine::CrLf) => Some("\r\n"),
+                Ok(EndOfLine::Cr) => Some("\r"),
+                Err(_) => None,
+            },
+            insert_final_newline: matches!(
+                properties.get::<FinalNewline>(),
+                Ok(FinalNewline::Value(true))
+            ),
+        })
+    }
+
+    /// `text` with each line's leading indentation redone in the configured style and
+    /// its line breaks in the configured ending.
+    pub(crate) fn apply(&self, text: &str) -> String {
+        let newline = self.newline;
+        let mut styled = String::with_capacity(text.len());
+        for line in text.split_inclusive('\n') {
+            let (content, ending) = match line.strip_suffix("\r\n") {
+                Some(content) => (content, "\r\n"),
+                None => match line.strip_suffix('\n') {
+                    Some(content) => (content, "\n"),
+                    None => (line, ""),
+                },
+            };
+            let body = content.trim_start_matches([' ', '\t']);
+            let indentation = &content[..content.len() - body.len()];
+            styled.push_str(&self.restyle_indentation(indentation));
+            styled.push_str(body);
+            if !ending.is_empty() {
+                styled.push_str(newline.unwrap_or(ending));
+            }
+        }
+        styled
+    }
+
+    /// Text that will end the file: styled, plus a line break when `insert_final_newline`
+    /// asks for one.
+    pub(crate) fn apply_at_file_end(&self, text: &str) -> String {
+        let mut styled = self.apply(text);
+        if self.insert_final_newline && !styled.is_empty() && !styled.ends_with(['\n', '\r']) {
+            styled.push_str(self.newline.unwrap_or("\n"));
+        }
+        styled
+    }
+
+    fn restyle_indentation(&self, indentation: &str) -> String {
+        match self.indent_style {
+            Some(IndentStyle::Spaces) => match self.tab_width.or(self.indent_size) {
+                Some(tab_width) => indentation.replace('\t', &" ".repeat(tab_width)),
+                None => indentation.to_string(),
+            },
+            Some(IndentStyle::Tabs) => {
+                let Some(tab_width) = self.tab_width.or(self.indent_size) else {
+                    return indentation.to_string();
+                };
+                let width = indentation
+                    .chars()
+                    .map(|character| if character == '\t' { tab_width } else { 1 })
+                    .sum::<usize>();
+                format!(
+                    "{}{}",
+                    "\t".repeat(width / tab_width),
+                    " ".repeat(width % tab_width)
+                )
+            }
+            None => indentation.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn style_follows_the_nearest_matching_section() {
+        let directory = tempfile::tempdir().expect("temp directory should be created");
+        fs::write(
+            directory.path().join(".editorconfig"),
+            "root = true\n\n[*]\nindent_style = space\nindent_size = 2\n\n[*.go]\nindent_style = tab\ntab_width = 4\nindent_size = 4\nend_of_line = crlf\ninsert_final_newline = true\n",
+        )
+        .expect("editorconfig should be written");
+
+        let python = EditorconfigStyle::for_file(&directory.path().join("app.py"))
+            .expect("style should resolve");
+        assert_eq!(python.apply("if x:\n\treturn 1\n"), "if x:\n  return 1\n");
+        assert!(!python.insert_final_newline);
+
+        let go = EditorconfigStyle::for_file(&directory.path().join("main.go"))
+            .expect("style should resolve");
+        assert_eq!(
+            go.apply("func f() {\n        return\n  }"),
+            "func f() {\r\n\t\treturn\r\n  }"
+        );
+        assert_eq!(go.apply_at_file_end("}"), "}\r\n");
+    }
+}
@@ -0,0 +1,78 @@
+//! `append_file` and `prepend_file`: text added at either end of a file, separated from the
+//! existing content by the file's own line ending, without line anchors.
+
+use crate::error::IdenteditError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FileEdge {
+    /// After the byte-order mark, before the first line.
+    Start,
+    /// After the last line.
+    End,
+}
+
+/// The `insert` text that puts `new_text` at `edge` of `source` on lines of its own.
+///
+/// Added line breaks use the file's ending (`\r\n` if it has any, else `\n`). Appending
+/// to a file whose last line is unterminated first ends that line; appending to a file
+/// that ends with a newline, or prepending to a non-empty file, terminates `new_text` if it
+/// is not already. An empty file takes `new_text` as given.
+pub(crate) fn file_edge_insert_text(
+    source: &[u8],
+    edge: FileEdge,
+    new_text: &str,
+) -> Result<String, IdenteditError> {
+    if new_text.is_empty() {
+        return Err(IdenteditError::InvalidRequest {
+            message: "append_file/prepend_file need non-empty new_text".to_string(),
+        });
+    }
+    let content = source.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(source);
+    if content.is_empty() {
+        return Ok(new_text.to_string());
+    }
+
+    let newline = if content.windows(2).any(|pair| pair == b"\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let terminated = |text: &str| {
+        if text.ends_with('\n') {
+            text.to_string()
+        } else {
+            format!("{text}{newline}")
+        }
+    };
+    Ok(match edge {
+        FileEdge::Start => terminated(new_text),
+        FileEdge::End if content.ends_with(b"\n") => terminated(new_text),
+        FileEdge::End => format!("{newline}{new_text}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edge_text_follows_the_file_line_endings() {
+        let append = |source: &str, text| {
+            file_edge_insert_text(source.as_bytes(), FileEdge::End, text).expect("append text")
+        };
+        let prepend = |source: &str, text| {
+            file_edge_insert_text(source.as_bytes(), FileEdge::Start, text).expect("prepend text")
+        };
+
+        assert_eq!(append("a\nb\n", "c"), "c\n");
+        assert_eq!(append("a\nb", "c"), "\nc");
+        assert_eq!(append("a\r\nb\r\n", "c"), "c\r\n");
+        assert_eq!(append("a\r\nb", "c\r\n"), "\r\nc\r\n");
+        assert_eq!(append("", "c"), "c");
+        assert_eq!(append("\u{feff}", "c"), "c");
+        assert_eq!(prepend("a\n", "header"), "header\n");
+        assert_eq!(prepend("\u{feff}a\r\n", "header"), "header\r\n");
+        assert_eq!(prepend("a", "header\n"), "header\n");
+        assert!(file_edge_insert_text(b"a\n", FileEdge::End, "").is_err());
+    }
+}
@@ -0,0 +1,240 @@
+//! Indentation detection for `read` output, and `--reindent target`: inserted text moved to
+//! the depth of the code around the insertion point.
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndentStyle {
+    Tabs,
+    Spaces,
+}
+
+/// How a file indents one level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Indentation {
+    pub style: IndentStyle,
+    /// Characters per level: 1 for tabs, the usual step for spaces.
+    pub width: usize,
+}
+
+impl Indentation {
+    fn unit(self) -> String {
+        match self.style {
+            IndentStyle::Tabs => "\t".repeat(self.width),
+            IndentStyle::Spaces => " ".repeat(self.width),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum Reindent {
+    /// Match the indentation of the line at the insertion point.
+    Target,
+}
+
+/// Detects `source`'s indentation from its indented lines: the style most lines start
+/// with, and the most common step between a line and a deeper line after it. Returns `None`
+/// when no line is indented.
+pub fn detect_indentation(source: &str) -> Option<Indentation> {
+    let mut tab_lines = 0usize;
+    let mut space_lines = 0usize;
+    let mut tab_steps = Vec::new();
+    let mut space_steps = Vec::new();
+    let mut previous = (0usize, 0usize);
+    for line in source.lines() {
+        let body = line.trim_start_matches([' ', '\t']);
+        if body.is_empty() {
+            continue;
+        }
+        let indentation = &line[..line.len() - body.len()];
+        let depth = if indentation.bytes().all(|byte| byte == b'\t') {
+            (indentation.len(), 0)
+        } else if indentation.bytes().all(|byte| byte == b' ') {
+            (0, indentation.len())
+        } else {
+            previous = (usize::MAX, usize::MAX);
+            continue;
+        };
+        match depth {
+            (0, 0) => {}
+            (0, _) => space_lines += 1,
+            _ => tab_lines += 1,
+        }
+        if previous.0 != usize::MAX {
+            if depth.0 > previous.0 && previous.1 == 0 && depth.1 == 0 {
+                tab_steps.push(depth.0 - previous.0);
+            }
+            if depth.1 > previous.1 && previous.0 == 0 && depth.0 == 0 {
+                space_steps.push(depth.1 - previous.1);
+            }
+        }
+        previous = depth;
+    }
+
+    if tab_lines == 0 && space_lines == 0 {
+        return None;
+    }
+    let (style, mut steps) = if tab_lines > space_lines {
+        (IndentStyle::Tabs, tab_steps)
+    } else {
+        (IndentStyle::Spaces, space_steps)
+    };
+    // One-space steps are mostly continuation lines, such as ` * ` in block comments.
+    if style == IndentStyle::Spaces && steps.iter().any(|step| *step > 1) {
+        steps.retain(|step| *step > 1);
+    }
+    Some(Indentation {
+        style,
+        width: most_common(&steps).unwrap_or(1),
+    })
+}
+
+/// The most frequent value, preferring the smaller on ties.
+fn most_common(values: &[usize]) -> Option<usize> {
+    let mut counts = std::collections::BTreeMap::new();
+    for value in values {
+        *counts.entry(*value).or_insert(0usize) += 1;
+    }
+    counts
+        .into_iter()
+        .max_by(|(left_value, left), (right_value, right)| {
+            left.cmp(right).then(right_value.cmp(left_value))
+        })
+        .map(|(value, _)| value)
+}
+
+/// The insertion point's surroundings in `source`: the leading indentation of the line it
+/// sits on, and whether it is at the start of that line.
+pub(crate) fn insertion_context(source: &str, offset: usize) -> (String, bool) {
+    let line_start = source[..offset].rfind('\n').map_or(0, |index| index + 1);
+    let line = &source[line_start..];
+    let body = line.trim_start_matches([' ', '\t']);
+    (
+        line[..line.len() - body.len()].to_string(),
+        offset == line_start,
+    )
+}
+
+/// Reindents `text` for insertion into `source` at the depth `target_indentation`.
+///
+/// The text's common leading indentation is replaced by `target_indentation`, and deeper
+/// lines keep their extra levels, rewritten in `source`'s detected style. When the text
+/// starts mid-line (`at_line_start` is false), its first line continues that line unchanged,
+/// and a trailing line break is followed by `target_indentation` so whatever comes after
+/// the insertion point keeps its column. Blank lines stay empty.
+pub(crate) fn reindent_text(
+    source: &str,
+    text: &str,
+    target_indentation: &str,
+    at_line_start: bool,
+) -> String {
+    let text_unit = detect_indentation(text);
+    let file_unit = detect_indentation(source).or(text_unit);
+    let depth_of = |indentation: &str| -> (usize, usize) {
+        let tabs = indentation
+            .bytes()
+            .take_while(|byte| *byte == b'\t')
+            .count();
+        let spaces = indentation.len() - tabs;
+        match text_unit {
+            Some(Indentation {
+                style: IndentStyle::Spaces,
+                width,
+            }) if tabs == 0 && width > 1 => (spaces / width, spaces % width),
+            _ => (tabs, spaces),
+        }
+    };
+
+    let lines = text.split_inclusive('\n').collect::<Vec<_>>();
+    let indented = |index: usize| at_line_start || index > 0;
+    let base = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let body = line.trim_start_matches([' ', '\t']);
+            depth_of(&line[..line.len() - body.len()])
+        })
+        .min()
+        .unwrap_or((0, 0));
+
+    let unit = file_unit.map(Indentation::unit).unwrap_or_default();
+    let mut reindented = String::with_capacity(text.len());
+    for (index, line) in lines.iter().enumerate() {
+        let body = line.trim_start_matches([' ', '\t']);
+        if !indented(index) {
+            reindented.push_str(line);
+            continue;
+        }
+        if body.trim().is_empty() {
+            reindented.push_str(body);
+            continue;
+        }
+        let (levels, spaces) = depth_of(&line[..line.len() - body.len()]);
+        let (levels, spaces) = if levels == base.0 {
+            (0, spaces.saturating_sub(base.1))
+        } else {
+            (levels - base.0, spaces)
+        };
+        reindented.push_str(target_indentation);
+        reindented.push_str(&unit.repeat(levels));
+        reindented.push_str(&" ".repeat(spaces));
+        reindented.push_str(body);
+    }
+    if !at_line_start && text.ends_with('\n') {
+        reindented.push_str(target_indentation);
+    }
+    reindented
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detection_reports_style_and_step() {
+        assert_eq!(
+            detect_indentation("fn f() {\n    if x {\n        y();\n    }\n}\n"),
+            Some(Indentation {
+                style: IndentStyle::Spaces,
+                width: 4
+            })
+        );
+        assert_eq!(
+            detect_indentation("/**\n * doc\n */\ndef f():\n  return 1\n"),
+            Some(Indentation {
+                style: IndentStyle::Spaces,
+                width: 2
+            })
+        );
+        assert_eq!(
+            detect_indentation("func f() {\n\tif x {\n\t\ty()\n\t}\n}\n"),
+            Some(Indentation {
+                style: IndentStyle::Tabs,
+                width: 1
+            })
+        );
+        assert_eq!(detect_indentation("a\nb\n"), None);
+    }
+
+    #[test]
+    fn reindent_moves_text_to_the_target_depth_in_the_file_style() {
+        let source = "def f():\n    if x:\n        pass\n";
+        assert_eq!(
+            reindent_text(source, "  if y:\n    return 1\n", "        ", true),
+            "        if y:\n            return 1\n"
+        );
+
+        let tabbed = "func f() {\n\tif x {\n\t\ty()\n\t}\n}\n";
+        assert_eq!(
+            reindent_text(tabbed, "if z {\n  w()\n}\n\n", "\t", false),
+            "if z {\n\t\tw()\n\t}\n\n\t"
+        );
+        assert_eq!(
+            reindent_text(tabbed, "\nfunc g() {\n    h()\n}", "", false),
+            "\nfunc g() {\n\th()\n}"
+        );
+    }
+}
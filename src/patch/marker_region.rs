@@ -0,0 +1,228 @@
+//! Marker regions: the lines between an `identedit:begin <name>` comment and the matching
+//! `identedit:end <name>` comment, for generated sections in otherwise hand-edited files.
+//!
+//! A region resolves to hashline targets on the lines it holds, so the edit touches only
+//! those lines and the marker comments stay as they are.
+
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::changeset::{OpKind, TransformTarget, hash_text};
+use crate::error::IdenteditError;
+use crate::hashline::{HashlineAlgo, compute_line_hash_with, format_line_ref};
+use crate::patch::annotation::{CommentSyntax, comment_syntax};
+use crate::transform::TransformInstruction;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum MarkerRegionOp {
+    /// The region's lines become `new_text`.
+    Replace { new_text: String },
+    /// `new_text` is added as the region's last lines.
+    Append { new_text: String },
+}
+
+/// One line of the source: its text without the line break, and its byte range with it.
+struct SourceLine<'a> {
+    text: &'a str,
+    start: usize,
+    end: usize,
+}
+
+/// Resolves the region `name` in `source` and returns the line operation that carries out
+/// `op` on it. `expected_region_hash`, when given, must be the hash of the region's text.
+pub(crate) fn marker_region_instruction(
+    file: &Path,
+    source: &str,
+    name: &str,
+    expected_region_hash: Option<String>,
+    op: MarkerRegionOp,
+) -> Result<TransformInstruction, IdenteditError> {
+    let lines = source_lines(source);
+    let (begin, end) = find_region(file, &lines, name)?;
+    let inner = &lines[begin + 1..end];
+    if let Some(expected_hash) = expected_region_hash {
+        let actual_hash = hash_text(&region_text(source, inner));
+        if actual_hash != expected_hash {
+            return Err(IdenteditError::PreconditionFailed {
+                expected_hash,
+                actual_hash,
+            });
+        }
+    }
+
+    let newline = if source.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let terminated = |text: String| {
+        if text.is_empty() || text.ends_with('\n') {
+            text
+        } else {
+            format!("{text}{newline}")
+        }
+    };
+    // Line indexes are 0-based here and 1-based in anchors.
+    let anchor = |index: usize| {
+        format_line_ref(
+            index + 1,
+            &compute_line_hash_with(lines[index].text, HashlineAlgo::Blake3_24),
+        )
+    };
+    Ok(match op {
+        MarkerRegionOp::Replace { new_text } if !inner.is_empty() => TransformInstruction {
+            target: TransformTarget::Line {
+                anchor: anchor(begin + 1),
+                end_anchor: Some(anchor(end - 1)),
+            },
+            op: OpKind::Replace {
+                new_text: terminated(new_text),
+            },
+        },
+        MarkerRegionOp::Replace { new_text } | MarkerRegionOp::Append { new_text } => {
+            TransformInstruction {
+                target: TransformTarget::Line {
+                    anchor: anchor(end - 1),
+                    end_anchor: None,
+                },
+                op: OpKind::InsertAfter {
+                    new_text: terminated(new_text),
+                },
+            }
+        }
+    })
+}
+
+/// The marker region `name` holding `new_text`, to append to `file`. Fails when the file
+/// already has a region of that name.
+pub(crate) fn marker_region_text(
+    file: &Path,
+    source: &str,
+    name: &str,
+    new_text: &str,
+) -> Result<String, IdenteditError> {
+    validate_name(name)?;
+    let lines = source_lines(source);
+    if lines
+        .iter()
+        .any(|line| marker_name(line.text, "begin") == Some(name))
+    {
+        return Err(IdenteditError::InvalidRequest {
+            message: format!(
+                "'{}' already has a marker region named '{name}'",
+                file.display()
+            ),
+        });
+    }
+    let newline = if source.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut region = marker_comment(file, "begin", name)?;
+    region.push_str(newline);
+    if !new_text.is_empty() {
+        region.push_str(new_text);
+        if !new_text.ends_with('\n') {
+            region.push_str(newline);
+        }
+    }
+    region.push_str(&marker_comment(file, "end", name)?);
+    region.push_str(newline);
+    Ok(region)
+}
+
+fn marker_comment(file: &Path, edge: &str, name: &str) -> Result<String, IdenteditError> {
+    let syntax = comment_syntax(file).ok_or_else(|| IdenteditError::InvalidRequest {
+        message: format!(
+            "marker regions need a known comment syntax; '{}' has none",
+            file.display()
+        ),
+    })?;
+    Ok(match syntax {
+        CommentSyntax::Line(prefix) => format!("{prefix} identedit:{edge} {name}"),
+        CommentSyntax::Block(open, close) => format!("{open} identedit:{edge} {name} {close}"),
+    })
+}
+
+/// The 0-based lines of the begin and end markers of region `name`.
+fn find_region(
+    file: &Path,
+    lines: &[SourceLine<'_>],
+    name: &str,
+) -> Result<(usize, usize), IdenteditError> {
+    validate_name(name)?;
+    let marked = |edge: &str| {
+        lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| marker_name(line.text, edge) == Some(name))
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>()
+    };
+    let (begins, ends) = (marked("begin"), marked("end"));
+    match (begins.as_slice(), ends.as_slice()) {
+        ([begin], [end]) if begin < end => Ok((*begin, *end)),
+        ([], _) => Err(IdenteditError::TargetMissing {
+            identity: format!("identedit:begin {name}"),
+            file: file.display().to_string(),
+        }),
+        (_, []) => Err(IdenteditError::TargetMissing {
+            identity: format!("identedit:end {name}"),
+            file: file.display().to_string(),
+        }),
+        ([_], [_]) => Err(IdenteditError::InvalidRequest {
+            message: format!(
+                "The end marker of region '{name}' in '{}' comes before its begin marker",
+                file.display()
+            ),
+        }),
+        _ => Err(IdenteditError::AmbiguousTarget {
+            identity: format!("identedit:begin {name}"),
+            file: file.display().to_string(),
+            candidates: begins.len().max(ends.len()),
+        }),
+    }
+}
+
+/// The region name of an `identedit:begin` or `identedit:end` marker line.
+fn marker_name<'a>(line: &'a str, edge: &str) -> Option<&'a str> {
+    let (_, rest) = line.split_once(&format!("identedit:{edge} "))?;
+    rest.split_whitespace().next()
+}
+
+fn validate_name(name: &str) -> Result<(), IdenteditError> {
+    if name.is_empty() || name.chars().any(char::is_whitespace) {
+        return Err(IdenteditError::InvalidRequest {
+            message: format!("Marker region name '{name}' must be non-empty and without spaces"),
+        });
+    }
+    Ok(())
+}
+
+fn source_lines(source: &str) -> Vec<SourceLine<'_>> {
+    let mut start = 0;
+    source
+        .split_inclusive('\n')
+        .map(|line| {
+            let text = line.strip_suffix('\n').unwrap_or(line);
+            let source_line = SourceLine {
+                text: text.strip_suffix('\r').unwrap_or(text),
+                start,
+                end: start + line.len(),
+            };
+            start = source_line.end;
+            source_line
+        })
+        .collect()
+}
+
+/// The region's lines as they are in `source`, line breaks included.
+fn region_text(source: &str, inner: &[SourceLine<'_>]) -> String {
+    match (inner.first(), inner.last()) {
+        (Some(first), Some(last)) => source[first.start..last.end].to_string(),
+        _ => String::new(),
+    }
+}
@@ -1,3 +1,9 @@
+pub mod annotation;
 pub mod config_path;
+pub mod editorconfig;
 pub mod engine;
+pub mod file_edge;
+pub mod indentation;
+pub mod marker_region;
 pub mod scoped_regex;
+pub mod upsert;
@@ -1,9 +1,14 @@
+use std::ops::Range;
 use std::path::Path;
 
 use regex::Regex;
+use serde::Serialize;
+use tree_sitter::Node;
 
 use crate::changeset::TransformTarget;
 use crate::error::IdenteditError;
+use crate::handle::{LineIndex, Position, Span};
+use crate::provider::ProviderRegistry;
 use crate::transform::{parse_handles_for_file, resolve_target_in_handles};
 
 #[derive(Debug, Clone)]
@@ -12,30 +17,237 @@ pub(crate) struct ScopedRegexRewrite {
     pub(crate) replacements: usize,
 }
 
+/// One match a scoped regex would rewrite, located in the whole file.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ScopedRegexMatch {
+    pub(crate) span: Span,
+    pub(crate) start_position: Position,
+    pub(crate) text: String,
+}
+
+/// Regions of the target the pattern must leave alone. Interpolated code inside
+/// f-strings and template literals (`{x}`, `${x}`) is not string text and stays in scope.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ScopedRegexOptions {
+    pub(crate) skip_strings: bool,
+    pub(crate) skip_comments: bool,
+}
+
+impl ScopedRegexOptions {
+    fn skips_anything(self) -> bool {
+        self.skip_strings || self.skip_comments
+    }
+}
+
 pub(crate) fn rewrite_node_target_with_scoped_regex(
     file: &Path,
     target: &TransformTarget,
     pattern: &str,
     replacement: &str,
+    options: ScopedRegexOptions,
+    registry: &ProviderRegistry,
 ) -> Result<ScopedRegexRewrite, IdenteditError> {
-    let regex = Regex::new(pattern).map_err(|error| IdenteditError::InvalidRequest {
-        message: format!("Invalid scoped regex pattern: {error}"),
-    })?;
-    let handles = parse_handles_for_file(file)?;
+    let regex = compile_scoped_regex(pattern)?;
+    let handles = parse_handles_for_file(file, registry)?;
     let resolved = resolve_target_in_handles(file, &handles, target)?;
+    let protected = protected_ranges(file, resolved.span, options, registry)?;
 
-    let replacements = regex.find_iter(&resolved.text).count();
+    let mut new_text = String::with_capacity(resolved.text.len());
+    let mut last_end = 0;
+    let mut replacements = 0;
+    for captures in regex.captures_iter(&resolved.text) {
+        let whole = captures.get(0).expect("group 0 always participates");
+        if is_protected(&protected, whole.range()) {
+            continue;
+        }
+        new_text.push_str(&resolved.text[last_end..whole.start()]);
+        captures.expand(replacement, &mut new_text);
+        last_end = whole.end();
+        replacements += 1;
+    }
     if replacements == 0 {
-        return Err(IdenteditError::InvalidRequest {
-            message: format!(
-                "Scoped regex matched 0 occurrences inside the resolved target span: /{pattern}/"
-            ),
-        });
+        return Err(zero_matches_error(pattern));
     }
+    new_text.push_str(&resolved.text[last_end..]);
 
-    let new_text = regex.replace_all(&resolved.text, replacement).into_owned();
     Ok(ScopedRegexRewrite {
         new_text,
         replacements,
     })
 }
+
+/// The matches `rewrite_node_target_with_scoped_regex` would replace, without rewriting
+/// anything. Zero matches is a result here, not an error.
+pub(crate) fn find_scoped_regex_matches_in_node_target(
+    file: &Path,
+    target: &TransformTarget,
+    pattern: &str,
+    options: ScopedRegexOptions,
+    registry: &ProviderRegistry,
+) -> Result<Vec<ScopedRegexMatch>, IdenteditError> {
+    let regex = compile_scoped_regex(pattern)?;
+    let handles = parse_handles_for_file(file, registry)?;
+    let resolved = resolve_target_in_handles(file, &handles, target)?;
+    let protected = protected_ranges(file, resolved.span, options, registry)?;
+    let source = std::fs::read(file).map_err(|error| IdenteditError::io(file, error))?;
+    let lines = LineIndex::new(&source);
+
+    Ok(regex
+        .find_iter(&resolved.text)
+        .filter(|found| !is_protected(&protected, found.range()))
+        .map(|found| {
+            let start = resolved.span.start + found.start();
+            ScopedRegexMatch {
+                span: Span {
+                    start,
+                    end: resolved.span.start + found.end(),
+                },
+                start_position: lines.position(start),
+                text: found.as_str().to_string(),
+            }
+        })
+        .collect())
+}
+
+pub(crate) fn compile_scoped_regex(pattern: &str) -> Result<Regex, IdenteditError> {
+    Regex::new(pattern).map_err(|error| IdenteditError::InvalidRequest {
+        message: format!("Invalid scoped regex pattern: {error}"),
+    })
+}
+
+pub(crate) fn zero_matches_error(pattern: &str) -> IdenteditError {
+    IdenteditError::InvalidRequest {
+        message: format!(
+            "Scoped regex matched 0 occurrences inside the resolved target span: /{pattern}/"
+        ),
+    }
+}
+
+/// Byte ranges, relative to `span.start`, of the string literals and/or comments inside
+/// `span`. Empty when `options` skips nothing.
+pub(crate) fn protected_ranges(
+    file: &Path,
+    span: Span,
+    options: ScopedRegexOptions,
+    registry: &ProviderRegistry,
+) -> Result<Vec<Range<usize>>, IdenteditError> {
+    if !options.skips_anything() {
+        return Ok(Vec::new());
+    }
+    let source = std::fs::read(file).map_err(|error| IdenteditError::io(file, error))?;
+    let provider = registry.provider_for(file)?;
+    let tree = provider
+        .syntax_tree(&source)
+        .ok_or_else(|| IdenteditError::InvalidRequest {
+            message: format!(
+                "skip_strings/skip_comments need a tree-sitter grammar; '{}' is handled by the {} provider",
+                file.display(),
+                provider.name()
+            ),
+        })??;
+
+    let mut ranges = Vec::new();
+    collect_protected(tree.root_node(), span, options, &mut ranges);
+    Ok(ranges
+        .into_iter()
+        .filter_map(|range| {
+            let start = range.start.max(span.start);
+            let end = range.end.min(span.end);
+            (start < end).then(|| start - span.start..end - span.start)
+        })
+        .collect())
+}
+
+pub(crate) fn is_protected(protected: &[Range<usize>], matched: Range<usize>) -> bool {
+    protected
+        .iter()
+        .any(|range| range.start < matched.end.max(matched.start + 1) && matched.start < range.end)
+}
+
+fn collect_protected(
+    node: Node<'_>,
+    span: Span,
+    options: ScopedRegexOptions,
+    ranges: &mut Vec<Range<usize>>,
+) {
+    if node.end_byte() <= span.start || node.start_byte() >= span.end {
+        return;
+    }
+    if is_comment_kind(node.kind()) {
+        if options.skip_comments {
+            ranges.push(node.byte_range());
+        }
+        return;
+    }
+    if is_string_kind(node.kind()) {
+        if options.skip_strings {
+            let mut start = node.start_byte();
+            collect_string_text(node, span, options, &mut start, ranges);
+            ranges.push(start..node.end_byte());
+        } else {
+            // Comments can still sit inside interpolations.
+            for_each_child(node, |child| {
+                collect_protected(child, span, options, ranges)
+            });
+        }
+        return;
+    }
+    for_each_child(node, |child| {
+        collect_protected(child, span, options, ranges)
+    });
+}
+
+/// Protects a string's text up to each interpolation, whose code is scanned like any
+/// other code; `start` is where the current stretch of string text began.
+fn collect_string_text(
+    node: Node<'_>,
+    span: Span,
+    options: ScopedRegexOptions,
+    start: &mut usize,
+    ranges: &mut Vec<Range<usize>>,
+) {
+    for_each_child(node, |child| {
+        if is_interpolation_kind(child.kind()) {
+            ranges.push(*start..child.start_byte());
+            for_each_child(child, |code| collect_protected(code, span, options, ranges));
+            *start = child.end_byte();
+        } else {
+            collect_string_text(child, span, options, start, ranges);
+        }
+    });
+}
+
+fn for_each_child<'tree>(node: Node<'tree>, mut visit: impl FnMut(Node<'tree>)) {
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        visit(child);
+    }
+}
+
+fn is_comment_kind(kind: &str) -> bool {
+    kind.contains("comment")
+}
+
+fn is_string_kind(kind: &str) -> bool {
+    kind.contains("string") || kind.contains("heredoc") || kind == "char_literal"
+}
+
+fn is_interpolation_kind(kind: &str) -> bool {
+    kind.contains("interpolation") || kind.contains("substitution") || kind.contains("expansion")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_protected;
+
+    #[test]
+    fn matches_touching_a_protected_range_are_skipped() {
+        let protected = vec![4..10, 20..30];
+        assert!(is_protected(&protected, 2..5));
+        assert!(is_protected(&protected, 9..12));
+        assert!(is_protected(&protected, 6..6));
+        assert!(!is_protected(&protected, 0..4));
+        assert!(!is_protected(&protected, 10..11));
+        assert!(!is_protected(&protected, 10..10));
+    }
+}
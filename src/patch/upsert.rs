@@ -0,0 +1,249 @@
+//! `upsert`: a named definition replaced where it already is, or inserted where the request
+//! places it when it is missing, so sending the same request twice leaves one copy.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::changeset::{OpKind, TransformTarget};
+use crate::error::IdenteditError;
+use crate::handle::{SelectionHandle, Span};
+use crate::patch::file_edge::{FileEdge, file_edge_insert_text};
+use crate::patch::indentation::{insertion_context, reindent_text};
+use crate::provider::ProviderRegistry;
+use crate::transform::{TransformInstruction, parse_handles_for_source};
+
+/// A node by kind and name, such as `{"kind": "class_definition", "name": "Parser"}`, or
+/// by identity for nodes without a name, such as a Rust `impl` block.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum NodeRef {
+    Named { kind: String, name: String },
+    Identity { identity: String },
+}
+
+impl NodeRef {
+    fn matches(&self, handle: &SelectionHandle) -> bool {
+        match self {
+            NodeRef::Named { kind, name } => {
+                handle.kind == *kind && handle.name.as_deref() == Some(name.as_str())
+            }
+            NodeRef::Identity { identity } => handle.identity == *identity,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            NodeRef::Named { kind, name } => format!("{kind} named '{name}'"),
+            NodeRef::Identity { identity } => format!("node with identity '{identity}'"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UpsertSpec {
+    pub kind: String,
+    pub name: String,
+    pub new_text: String,
+    /// Only nodes inside this container are matched, and a missing node is added to it.
+    #[serde(default)]
+    pub within: Option<NodeRef>,
+    /// Where a missing node goes: after this sibling.
+    #[serde(default)]
+    pub after: Option<NodeRef>,
+    /// Where a missing node goes: before this sibling.
+    #[serde(default)]
+    pub before: Option<NodeRef>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpsertAction {
+    /// The node existed with different text.
+    Replaced,
+    /// The node existed with exactly `new_text`; the file is rewritten unchanged.
+    Unchanged,
+    Inserted,
+}
+
+/// Resolves `spec` against `source` (already checked against the request's file hash) and
+/// returns the node replacement or the insert that carries it out.
+///
+/// A missing node goes after or before the named sibling, else after the last node of the
+/// same kind in `within`, else at the end of the file. Inserted text is reindented to its
+/// neighbour's depth and set off by a blank line.
+pub(crate) fn upsert_instruction(
+    file: &Path,
+    source: &[u8],
+    expected_file_hash: String,
+    spec: UpsertSpec,
+    registry: &ProviderRegistry,
+) -> Result<(TransformInstruction, UpsertAction), IdenteditError> {
+    if spec.after.is_some() && spec.before.is_some() {
+        return Err(IdenteditError::InvalidRequest {
+            message: "upsert accepts 'after' or 'before', not both".to_string(),
+        });
+    }
+    let new_text = spec.new_text.trim_end_matches(['\r', '\n']);
+    if new_text.trim().is_empty() {
+        return Err(IdenteditError::InvalidRequest {
+            message: "upsert needs non-empty new_text".to_string(),
+        });
+    }
+    let text = std::str::from_utf8(source).map_err(|error| {
+        IdenteditError::io(
+            file,
+            std::io::Error::new(std::io::ErrorKind::InvalidData, error),
+        )
+    })?;
+    let handles = parse_handles_for_source(file, source, registry)?;
+    let scope = match &spec.within {
+        Some(container) => Some(unique_node(file, &handles, container, None)?.span),
+        None => None,
+    };
+    let wanted = NodeRef::Named {
+        kind: spec.kind.clone(),
+        name: spec.name.clone(),
+    };
+
+    let existing = find_nodes(&handles, &wanted, scope);
+    if existing.len() > 1 {
+        return Err(ambiguous(file, &wanted));
+    }
+    if let Some(existing) = existing.first() {
+        let (indentation, _) = insertion_context(text, existing.span.start);
+        let replacement = reindented(text, new_text, &indentation);
+        let action = if replacement == existing.text {
+            UpsertAction::Unchanged
+        } else {
+            UpsertAction::Replaced
+        };
+        return Ok((
+            TransformInstruction {
+                target: TransformTarget::node(
+                    existing.identity.clone(),
+                    existing.kind.clone(),
+                    Some(existing.span),
+                    existing.expected_old_hash.clone(),
+                ),
+                op: OpKind::Replace {
+                    new_text: replacement,
+                },
+            },
+            action,
+        ));
+    }
+
+    let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+    let (anchor, before) = match (&spec.after, &spec.before) {
+        (Some(after), _) => (Some(unique_node(file, &handles, after, scope)?), false),
+        (_, Some(before)) => (Some(unique_node(file, &handles, before, scope)?), true),
+        (None, None) if scope.is_some() => {
+            let last = handles
+                .iter()
+                .filter(|handle| handle.kind == spec.kind && contains(scope, handle.span))
+                .max_by_key(|handle| handle.span.end)
+                .ok_or_else(|| IdenteditError::InvalidRequest {
+                    message: format!(
+                        "The {} has no {} to insert '{}' after; give 'after' or 'before'",
+                        spec.within
+                            .as_ref()
+                            .map_or(String::new(), NodeRef::describe),
+                        spec.kind,
+                        spec.name
+                    ),
+                })?;
+            (Some(last), false)
+        }
+        (None, None) => (None, false),
+    };
+    let instruction = match anchor {
+        Some(anchor) => {
+            let (indentation, _) = insertion_context(text, anchor.span.start);
+            let inserted = reindented(text, new_text, &indentation);
+            let target = TransformTarget::node(
+                anchor.identity.clone(),
+                anchor.kind.clone(),
+                Some(anchor.span),
+                anchor.expected_old_hash.clone(),
+            );
+            let op = if before {
+                OpKind::InsertBefore {
+                    new_text: format!("{inserted}{newline}{newline}{indentation}"),
+                }
+            } else {
+                OpKind::InsertAfter {
+                    new_text: format!("{newline}{newline}{indentation}{inserted}"),
+                }
+            };
+            TransformInstruction { target, op }
+        }
+        None => {
+            let content = text.trim_start_matches('\u{feff}');
+            let separated =
+                if content.is_empty() || content.ends_with(&format!("{newline}{newline}")) {
+                    new_text.to_string()
+                } else {
+                    format!("{newline}{new_text}")
+                };
+            TransformInstruction {
+                target: TransformTarget::FileEnd { expected_file_hash },
+                op: OpKind::Insert {
+                    new_text: file_edge_insert_text(source, FileEdge::End, &separated)?,
+                },
+            }
+        }
+    };
+    Ok((instruction, UpsertAction::Inserted))
+}
+
+/// `new_text` at depth `indentation`, with its first line left for the caller to place.
+fn reindented(source: &str, new_text: &str, indentation: &str) -> String {
+    let reindented = reindent_text(source, new_text, indentation, true);
+    match reindented.strip_prefix(indentation) {
+        Some(rest) => rest.to_string(),
+        None => reindented,
+    }
+}
+
+fn find_nodes<'a>(
+    handles: &'a [SelectionHandle],
+    wanted: &NodeRef,
+    scope: Option<Span>,
+) -> Vec<&'a SelectionHandle> {
+    handles
+        .iter()
+        .filter(|handle| wanted.matches(handle) && contains(scope, handle.span))
+        .collect()
+}
+
+fn unique_node<'a>(
+    file: &Path,
+    handles: &'a [SelectionHandle],
+    wanted: &NodeRef,
+    scope: Option<Span>,
+) -> Result<&'a SelectionHandle, IdenteditError> {
+    match find_nodes(handles, wanted, scope).as_slice() {
+        [single] => Ok(single),
+        [] => Err(IdenteditError::InvalidRequest {
+            message: format!("No {} in '{}'", wanted.describe(), file.display()),
+        }),
+        _ => Err(ambiguous(file, wanted)),
+    }
+}
+
+/// Whether `span` lies strictly inside `scope`; without a scope, everything does.
+fn contains(scope: Option<Span>, span: Span) -> bool {
+    scope.is_none_or(|scope| scope.start <= span.start && span.end <= scope.end && scope != span)
+}
+
+fn ambiguous(file: &Path, wanted: &NodeRef) -> IdenteditError {
+    IdenteditError::InvalidRequest {
+        message: format!(
+            "More than one {} in '{}'; narrow it with 'within'",
+            wanted.describe(),
+            file.display()
+        ),
+    }
+}
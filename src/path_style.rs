@@ -0,0 +1,220 @@
+//! How file paths cross machine boundaries.
+//!
+//! Paths read from plans and JSON requests are normalized for the running platform:
+//! Windows verbatim prefixes (`\\?\C:\`, `\\?\UNC\server\share`) are dropped, drive letters
+//! are upper-cased, and a drive path maps to its WSL mount (`C:\repo` <-> `/mnt/c/repo`).
+//! Relative paths only have their separators rewritten on Windows, where `/` is a separator
+//! too; elsewhere a `\` may be part of a file name.
+//!
+//! Paths written to handles, plans, and responses are normalized the same way and then
+//! rendered in the [`PathStyle`] of the enclosing [`with_path_style`]: `native`, or `posix`
+//! with `/` separators, so a plan made on Windows with `--path-style posix` applies from WSL
+//! and vice versa. Outside one, paths render `native`.
+
+use std::cell::Cell;
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Deserializer, Serializer};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum PathStyle {
+    #[default]
+    Native,
+    Posix,
+}
+
+thread_local! {
+    static PATH_STYLE: Cell<PathStyle> = const { Cell::new(PathStyle::Native) };
+}
+
+/// Runs `f` with the paths it renders on this thread in `style`. The serde helpers below
+/// cannot take parameters, so the style is scoped to the call instead.
+pub fn with_path_style<T>(style: PathStyle, f: impl FnOnce() -> T) -> T {
+    struct Restore(PathStyle);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            PATH_STYLE.with(|current| current.set(self.0));
+        }
+    }
+    let _restore = Restore(PATH_STYLE.with(|current| current.replace(style)));
+    f()
+}
+
+pub fn path_style() -> PathStyle {
+    PATH_STYLE.with(Cell::get)
+}
+
+/// `path` as the running platform should open it.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    PathBuf::from(normalize(&path.to_string_lossy(), cfg!(windows)))
+}
+
+/// `path` as handles, plans, and responses report it.
+pub fn render_path(path: &Path) -> String {
+    render(&path.to_string_lossy(), path_style(), cfg!(windows))
+}
+
+pub fn serialize_path<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&render_path(path))
+}
+
+pub fn serialize_optional_path<S: Serializer>(
+    path: &Option<PathBuf>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match path {
+        Some(path) => serialize_path(path, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+pub fn deserialize_path<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+    PathBuf::deserialize(deserializer).map(|path| normalize_path(&path))
+}
+
+pub fn deserialize_optional_path<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<PathBuf>, D::Error> {
+    Option::<PathBuf>::deserialize(deserializer).map(|path| path.map(|path| normalize_path(&path)))
+}
+
+pub fn deserialize_paths<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<PathBuf>, D::Error> {
+    Vec::<PathBuf>::deserialize(deserializer)
+        .map(|paths| paths.iter().map(|path| normalize_path(path)).collect())
+}
+
+fn render(path: &str, style: PathStyle, windows: bool) -> String {
+    let normalized = normalize(path, windows);
+    match style {
+        PathStyle::Native => normalized,
+        PathStyle::Posix if windows => normalized.replace('\\', "/"),
+        PathStyle::Posix => normalized,
+    }
+}
+
+fn normalize(path: &str, windows: bool) -> String {
+    let path = strip_verbatim_prefix(path);
+    if windows {
+        let path = match wsl_mount(&path) {
+            Some((drive, rest)) => format!("{}:{rest}", drive.to_ascii_uppercase()),
+            None => upper_case_drive(&path),
+        };
+        return path.replace('/', "\\");
+    }
+    if let Some((drive, rest)) = drive_prefix(&path) {
+        return format!(
+            "/mnt/{}{}",
+            drive.to_ascii_lowercase(),
+            rest.replace('\\', "/")
+        );
+    }
+    if path.starts_with("\\\\") {
+        return path.replace('\\', "/");
+    }
+    path
+}
+
+/// `\\?\C:\x` -> `C:\x` and `\\?\UNC\server\share` -> `\\server\share`.
+fn strip_verbatim_prefix(path: &str) -> String {
+    for prefix in ["\\\\?\\", "//?/"] {
+        if let Some(rest) = path.strip_prefix(prefix) {
+            return match rest
+                .strip_prefix("UNC\\")
+                .or_else(|| rest.strip_prefix("UNC/"))
+            {
+                Some(share) => format!("\\\\{share}"),
+                None => rest.to_string(),
+            };
+        }
+    }
+    path.to_string()
+}
+
+/// `C:\x` -> `('C', "\x")`. A bare `C:` counts, but `a:b.py` is a file name.
+fn drive_prefix(path: &str) -> Option<(char, &str)> {
+    let mut chars = path.chars();
+    let drive = chars.next().filter(char::is_ascii_alphabetic)?;
+    if chars.next() != Some(':') {
+        return None;
+    }
+    let rest = &path[2..];
+    (rest.is_empty() || rest.starts_with(['\\', '/'])).then_some((drive, rest))
+}
+
+fn upper_case_drive(path: &str) -> String {
+    match drive_prefix(path) {
+        Some((drive, rest)) => format!("{}:{rest}", drive.to_ascii_uppercase()),
+        None => path.to_string(),
+    }
+}
+
+/// `/mnt/c/repo` -> `('c', "/repo")`.
+fn wsl_mount(path: &str) -> Option<(char, &str)> {
+    let rest = path.strip_prefix("/mnt/")?;
+    let mut chars = rest.chars();
+    let drive = chars.next().filter(char::is_ascii_alphabetic)?;
+    let after = &rest[1..];
+    (after.is_empty() || after.starts_with('/')).then_some((drive, after))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{PathStyle, normalize, render, render_path, with_path_style};
+
+    #[test]
+    fn path_styles_are_scoped_to_their_call() {
+        let path = Path::new("src/a.py");
+        assert_eq!(
+            with_path_style(PathStyle::Posix, || render_path(path)),
+            "src/a.py"
+        );
+        let nested = with_path_style(PathStyle::Posix, || {
+            with_path_style(PathStyle::Native, super::path_style)
+        });
+        assert_eq!(nested, PathStyle::Native);
+        assert_eq!(super::path_style(), PathStyle::Native);
+    }
+
+    #[test]
+    fn windows_paths_drop_verbatim_prefixes_and_map_wsl_mounts() {
+        assert_eq!(
+            normalize(r"\\?\c:\repo\src\a.py", true),
+            r"C:\repo\src\a.py"
+        );
+        assert_eq!(
+            normalize(r"\\?\UNC\server\share\a.py", true),
+            r"\\server\share\a.py"
+        );
+        assert_eq!(normalize("/mnt/d/repo/a.py", true), r"D:\repo\a.py");
+        assert_eq!(normalize("src/a.py", true), r"src\a.py");
+        assert_eq!(
+            render(r"\\?\c:\repo\a.py", PathStyle::Posix, true),
+            "C:/repo/a.py"
+        );
+        assert_eq!(render(r"src\a.py", PathStyle::Native, true), r"src\a.py");
+    }
+
+    #[test]
+    fn unix_paths_map_drives_to_wsl_mounts_and_keep_backslash_names() {
+        assert_eq!(
+            normalize(r"C:\repo\src\a.py", false),
+            "/mnt/c/repo/src/a.py"
+        );
+        assert_eq!(normalize("C:/repo/a.py", false), "/mnt/c/repo/a.py");
+        assert_eq!(normalize(r"\\?\C:\repo", false), "/mnt/c/repo");
+        assert_eq!(
+            normalize(r"\\server\share\a.py", false),
+            "//server/share/a.py"
+        );
+        assert_eq!(normalize("/mnt/c/repo/a.py", false), "/mnt/c/repo/a.py");
+        assert_eq!(normalize(r"odd\name.py", false), r"odd\name.py");
+        assert_eq!(normalize("a:b.py", false), "a:b.py");
+        assert_eq!(render("src/a.py", PathStyle::Posix, false), "src/a.py");
+    }
+}
@@ -0,0 +1,267 @@
+//! Named bookmarks for node identities (`identedit pin`).
+//!
+//! A pin maps an alias to a file, node kind, name, and identity. Pins live in
+//! `.identedit/pins.json` under the current directory, so a later session started in the
+//! same workspace can patch the node by alias. Identities still follow the node's text:
+//! a pin whose identity is gone reports `stale` instead of guessing, and `patch --pin`
+//! moves the pin to the rewritten node when it can find it again.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::IdenteditError;
+use crate::handle::SelectionHandle;
+use crate::provider::ProviderRegistry;
+use crate::transform::parse_handles_for_file;
+
+/// Pin store location, relative to the current directory.
+pub const PIN_FILE: &str = ".identedit/pins.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Pin {
+    pub alias: String,
+    pub file: PathBuf,
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub identity: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PinStatus {
+    /// The identity matches exactly one node.
+    Current,
+    /// No node has the identity any more (its text, kind, or name changed).
+    Stale,
+    /// Several nodes share the identity.
+    Ambiguous,
+    MissingFile,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct PinStore {
+    pins: Vec<Pin>,
+}
+
+/// Pins the node with `identity` in `file` under `alias`, replacing an existing pin of
+/// the same name.
+pub fn add_pin(
+    alias: &str,
+    file: &Path,
+    identity: &str,
+    registry: &ProviderRegistry,
+) -> Result<Pin, IdenteditError> {
+    validate_alias(alias)?;
+    let handle = resolve_unique_handle(file, identity, registry)?;
+    let file = std::path::absolute(file).map_err(|error| IdenteditError::io(file, error))?;
+    let pin = Pin {
+        alias: alias.to_string(),
+        file,
+        kind: handle.kind,
+        name: handle.name,
+        identity: handle.identity,
+    };
+
+    let mut store = load_store()?;
+    store.pins.retain(|existing| existing.alias != alias);
+    store.pins.push(pin.clone());
+    store
+        .pins
+        .sort_by(|left, right| left.alias.cmp(&right.alias));
+    save_store(&store)?;
+    Ok(pin)
+}
+
+pub fn list_pins(registry: &ProviderRegistry) -> Result<Vec<(Pin, PinStatus)>, IdenteditError> {
+    load_store()?
+        .pins
+        .into_iter()
+        .map(|pin| {
+            let status = pin_status(&pin, registry)?;
+            Ok((pin, status))
+        })
+        .collect()
+}
+
+pub fn find_pin(alias: &str) -> Result<Pin, IdenteditError> {
+    let store = load_store()?;
+    store
+        .pins
+        .iter()
+        .find(|pin| pin.alias == alias)
+        .cloned()
+        .ok_or_else(|| unknown_pin_error(alias, &store))
+}
+
+/// Current handle of the pinned node; fails with `target_missing` when the pin is stale.
+pub fn resolve_pin(
+    alias: &str,
+    registry: &ProviderRegistry,
+) -> Result<(Pin, SelectionHandle), IdenteditError> {
+    let pin = find_pin(alias)?;
+    let handle = resolve_unique_handle(&pin.file, &pin.identity, registry)?;
+    Ok((pin, handle))
+}
+
+pub fn remove_pin(alias: &str) -> Result<Pin, IdenteditError> {
+    let mut store = load_store()?;
+    let position = store
+        .pins
+        .iter()
+        .position(|pin| pin.alias == alias)
+        .ok_or_else(|| unknown_pin_error(alias, &store))?;
+    let pin = store.pins.remove(position);
+    save_store(&store)?;
+    Ok(pin)
+}
+
+/// Re-points `pin` after a patch of its node, which started at `start`. A node whose
+/// identity survived (for example after an insert beside it) keeps the pin; otherwise
+/// the node is found again by kind and name at its old start. Returns the identity the
+/// pin now holds, or `None` (leaving the pin untouched) when no single node qualifies,
+/// for example after a delete.
+pub fn refresh_pin(
+    pin: &Pin,
+    start: usize,
+    registry: &ProviderRegistry,
+) -> Result<Option<String>, IdenteditError> {
+    let handles = parse_handles_for_file(&pin.file, registry)?;
+    if handles
+        .iter()
+        .filter(|handle| handle.identity == pin.identity)
+        .count()
+        == 1
+    {
+        return Ok(Some(pin.identity.clone()));
+    }
+    let candidates = handles
+        .iter()
+        .filter(|handle| {
+            handle.span.start == start && handle.kind == pin.kind && handle.name == pin.name
+        })
+        .collect::<Vec<_>>();
+    let [handle] = candidates.as_slice() else {
+        return Ok(None);
+    };
+
+    let mut store = load_store()?;
+    if let Some(stored) = store
+        .pins
+        .iter_mut()
+        .find(|stored| stored.alias == pin.alias)
+    {
+        stored.identity = handle.identity.clone();
+        save_store(&store)?;
+    }
+    Ok(Some(handle.identity.clone()))
+}
+
+pub(crate) fn resolve_unique_handle(
+    file: &Path,
+    identity: &str,
+    registry: &ProviderRegistry,
+) -> Result<SelectionHandle, IdenteditError> {
+    let matches = parse_handles_for_file(file, registry)?
+        .into_iter()
+        .filter(|handle| handle.identity == identity)
+        .collect::<Vec<_>>();
+
+    match matches.as_slice() {
+        [] => Err(IdenteditError::TargetMissing {
+            identity: identity.to_string(),
+            file: file.display().to_string(),
+        }),
+        [single] => Ok(single.clone()),
+        candidates => Err(IdenteditError::AmbiguousTarget {
+            identity: identity.to_string(),
+            file: file.display().to_string(),
+            candidates: candidates.len(),
+        }),
+    }
+}
+
+fn pin_status(pin: &Pin, registry: &ProviderRegistry) -> Result<PinStatus, IdenteditError> {
+    if !pin.file.is_file() {
+        return Ok(PinStatus::MissingFile);
+    }
+    let count = parse_handles_for_file(&pin.file, registry)?
+        .iter()
+        .filter(|handle| handle.identity == pin.identity)
+        .count();
+    Ok(match count {
+        0 => PinStatus::Stale,
+        1 => PinStatus::Current,
+        _ => PinStatus::Ambiguous,
+    })
+}
+
+fn validate_alias(alias: &str) -> Result<(), IdenteditError> {
+    let valid = !alias.is_empty()
+        && alias
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'-' | b'.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(IdenteditError::InvalidRequest {
+            message: format!(
+                "Pin alias '{alias}' must be non-empty and use only ASCII letters, digits, '_', '-', or '.'"
+            ),
+        })
+    }
+}
+
+fn unknown_pin_error(alias: &str, store: &PinStore) -> IdenteditError {
+    let known = store
+        .pins
+        .iter()
+        .map(|pin| pin.alias.as_str())
+        .collect::<Vec<_>>();
+    IdenteditError::InvalidRequest {
+        message: if known.is_empty() {
+            format!("Unknown pin '{alias}' (no pins in '{PIN_FILE}')")
+        } else {
+            format!("Unknown pin '{alias}' (pins: {})", known.join(", "))
+        },
+    }
+}
+
+fn load_store() -> Result<PinStore, IdenteditError> {
+    let path = Path::new(PIN_FILE);
+    if !path.is_file() {
+        return Ok(PinStore::default());
+    }
+    let content = fs::read_to_string(path).map_err(|error| IdenteditError::io(path, error))?;
+    serde_json::from_str(&content).map_err(|error| IdenteditError::InvalidRequest {
+        message: format!("Failed to parse pin file '{PIN_FILE}': {error}"),
+    })
+}
+
+fn save_store(store: &PinStore) -> Result<(), IdenteditError> {
+    let path = Path::new(PIN_FILE);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|error| IdenteditError::io(parent, error))?;
+    }
+    let mut serialized = serde_json::to_string_pretty(store)
+        .map_err(|source| IdenteditError::ResponseSerialization { source })?;
+    serialized.push('\n');
+    fs::write(path, serialized).map_err(|error| IdenteditError::io(path, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::validate_alias;
+
+    #[test]
+    fn validate_alias_accepts_simple_names_only() {
+        validate_alias("auth.handler-v2_1").expect("simple alias should be accepted");
+        validate_alias("").expect_err("empty alias must fail");
+        validate_alias("a b").expect_err("whitespace must fail");
+        validate_alias("a/b").expect_err("path separators must fail");
+    }
+}
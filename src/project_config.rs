@@ -0,0 +1,103 @@
+//! Project settings in `.identedit/config.json` under the current directory.
+//!
+//! The file is optional and read once per command. It holds extension mappings for files
+//! no bundled or installed provider claims, the line ranges `apply` refuses to modify, the
+//! limits on how much one apply may delete, whether writes are scanned for credentials, and
+//! the policy `hook pre-commit --enforce-policy` checks commits against:
+//!
+//! ```json
+//! {
+//!   "extensions": { "pyi": "python", "mjs": "javascript", "gyp": "python" },
+//!   "protected_regions": [{ "path": "src/**/*.rs", "lines": [1, 3] }],
+//!   "guards": { "max_deleted_lines_per_op": 200, "max_file_fraction_changed": 0.5 },
+//!   "secret_scan": true,
+//!   "policy": { "protected": ["Cargo.lock", "migrations/**"], "max_files": 20 }
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::error::IdenteditError;
+
+/// Project config location, relative to the current directory.
+pub const PROJECT_CONFIG_FILE: &str = ".identedit/config.json";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// Extension to the language or provider name that parses it, consulted before a file
+    /// falls through to the fallback provider.
+    #[serde(default)]
+    pub extensions: BTreeMap<String, String>,
+    #[serde(default)]
+    pub protected_regions: Vec<ProtectedRegionRule>,
+    #[serde(default)]
+    pub guards: ChangeGuards,
+    /// Refuse writes whose new text holds something that looks like a credential.
+    #[serde(default)]
+    pub secret_scan: bool,
+    #[serde(default)]
+    pub policy: Policy,
+    /// Absolute directory holding `.identedit`, which `protected_regions` paths are
+    /// relative to; empty when there is no config.
+    #[serde(skip)]
+    pub root: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChangeGuards {
+    /// Most lines one operation may delete, net of the lines it writes.
+    pub max_deleted_lines_per_op: Option<usize>,
+    /// Largest share, from 0 to 1, of a file's lines one apply may remove or change.
+    pub max_file_fraction_changed: Option<f64>,
+}
+
+impl Default for ChangeGuards {
+    fn default() -> Self {
+        Self {
+            max_deleted_lines_per_op: None,
+            max_file_fraction_changed: Some(0.8),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProtectedRegionRule {
+    /// Glob pattern, relative to the directory holding `.identedit`, of the files the rule
+    /// covers.
+    pub path: String,
+    /// First and last 1-based line of the region.
+    pub lines: [usize; 2],
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    /// Glob patterns, relative to the repository root, of files a commit may not touch.
+    #[serde(default)]
+    pub protected: Vec<String>,
+    /// Most files one commit may touch.
+    pub max_files: Option<usize>,
+}
+
+/// The project config, or the defaults when there is none.
+pub fn load_project_config() -> Result<ProjectConfig, IdenteditError> {
+    let path = Path::new(PROJECT_CONFIG_FILE);
+    if !path.is_file() {
+        return Ok(ProjectConfig::default());
+    }
+    let content = fs::read_to_string(path).map_err(|error| IdenteditError::io(path, error))?;
+    let mut config: ProjectConfig =
+        serde_json::from_str(&content).map_err(|error| IdenteditError::InvalidRequest {
+            message: format!("Failed to parse project config '{PROJECT_CONFIG_FILE}': {error}"),
+        })?;
+    config.root =
+        std::env::current_dir().map_err(|error| IdenteditError::io(Path::new("."), error))?;
+    Ok(config)
+}
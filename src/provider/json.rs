@@ -53,21 +53,25 @@ impl StructureProvider for JsonProvider {
     }
 }
 
+/// Values and keys are named by their config path (`config.retries`, `items[0]`), the
+/// same spelling `--config-path` accepts, so nested objects and arrays read as a
+/// hierarchy. The root value has no name.
 fn collect_json_nodes(
     node: Node<'_>,
     path: &Path,
     source: &[u8],
-    inherited_name: Option<String>,
+    value_path: Option<String>,
     handles: &mut Vec<SelectionHandle>,
 ) {
-    if node.kind() == "pair" {
-        collect_pair(node, path, source, handles);
+    let Some(kind) = normalized_kind(node.kind()) else {
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            collect_json_nodes(child, path, source, value_path.clone(), handles);
+        }
         return;
-    }
+    };
 
-    if let Some(kind) = normalized_kind(node.kind())
-        && let Some(text) = node_text(node, source)
-    {
+    if let Some(text) = node_text(node, source) {
         let span = Span {
             start: node.start_byte(),
             end: node.end_byte(),
@@ -78,18 +82,34 @@ fn collect_json_nodes(
             path.to_path_buf(),
             span,
             kind.to_string(),
-            inherited_name.clone(),
+            value_path.clone(),
             normalized_text,
         ));
     }
 
     let mut cursor = node.walk();
+    let mut index = 0;
     for child in node.named_children(&mut cursor) {
-        collect_json_nodes(child, path, source, inherited_name.clone(), handles);
+        match child.kind() {
+            "pair" => collect_pair(child, path, source, value_path.as_deref(), handles),
+            _ if kind == "array" && normalized_kind(child.kind()).is_some() => {
+                let prefix = value_path.as_deref().unwrap_or_default();
+                let element_path = format!("{prefix}[{index}]");
+                collect_json_nodes(child, path, source, Some(element_path), handles);
+                index += 1;
+            }
+            _ => {}
+        }
     }
 }
 
-fn collect_pair(node: Node<'_>, path: &Path, source: &[u8], handles: &mut Vec<SelectionHandle>) {
+fn collect_pair(
+    node: Node<'_>,
+    path: &Path,
+    source: &[u8],
+    parent_path: Option<&str>,
+    handles: &mut Vec<SelectionHandle>,
+) {
     let key_node = match node.child_by_field_name("key") {
         Some(node) => node,
         None => return,
@@ -110,16 +130,20 @@ fn collect_pair(node: Node<'_>, path: &Path, source: &[u8], handles: &mut Vec<Se
         start: key_node.start_byte(),
         end: key_node.end_byte(),
     };
+    let key_path = match parent_path {
+        Some(parent) => format!("{parent}.{key_name}"),
+        None => key_name.clone(),
+    };
 
     handles.push(SelectionHandle::from_parts(
         path.to_path_buf(),
         key_span,
         "key".to_string(),
-        Some(key_name.clone()),
-        key_name.clone(),
+        Some(key_path.clone()),
+        key_name,
     ));
 
-    collect_json_nodes(value_node, path, source, Some(key_name), handles);
+    collect_json_nodes(value_node, path, source, Some(key_path), handles);
 }
 
 fn normalized_kind(node_kind: &str) -> Option<&'static str> {
@@ -235,7 +259,7 @@ mod tests {
     }
 
     #[test]
-    fn parse_propagates_immediate_pair_key_as_value_name() {
+    fn parse_names_values_and_keys_by_config_path() {
         let provider = JsonProvider;
         let source = br#"{"outer":{"inner":1}}"#;
 
@@ -247,17 +271,53 @@ mod tests {
             .iter()
             .find(|handle| handle.kind == "number" && handle.text == "1")
             .expect("nested number handle should exist");
-        assert_eq!(nested_number.name.as_deref(), Some("inner"));
+        assert_eq!(nested_number.name.as_deref(), Some("outer.inner"));
+
+        let inner_key = handles
+            .iter()
+            .find(|handle| handle.kind == "key" && handle.text == "inner")
+            .expect("inner key handle should exist");
+        assert_eq!(inner_key.name.as_deref(), Some("outer.inner"));
 
         let inner_object = handles
             .iter()
-            .find(|handle| {
-                handle.kind == "object"
-                    && handle.name.as_deref() == Some("outer")
-                    && handle.text.contains("\"inner\":1")
-            })
-            .expect("inner object should inherit outer key name");
+            .find(|handle| handle.kind == "object" && handle.span.start > 0)
+            .expect("inner object handle should exist");
         assert_eq!(inner_object.name.as_deref(), Some("outer"));
+
+        let root = handles
+            .iter()
+            .find(|handle| handle.kind == "object" && handle.span.start == 0)
+            .expect("root object handle should exist");
+        assert_eq!(root.name, None);
+    }
+
+    #[test]
+    fn parse_names_array_elements_by_index() {
+        let provider = JsonProvider;
+        let source = br#"{"items":[{"id":1},[true]],"empty":[]}"#;
+
+        let handles = provider
+            .parse(Path::new("fixture.json"), source)
+            .expect("json parse should succeed");
+        let names = handles
+            .iter()
+            .filter(|handle| handle.kind != "key")
+            .map(|handle| handle.name.as_deref().unwrap_or("<root>"))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            names,
+            vec![
+                "<root>",
+                "items",
+                "items[0]",
+                "items[0].id",
+                "items[1]",
+                "items[1][0]",
+                "empty"
+            ]
+        );
     }
 
     #[test]
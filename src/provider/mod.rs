@@ -1,6 +1,8 @@
 use std::collections::BTreeSet;
 use std::path::Path;
 
+use serde::Serialize;
+
 use crate::error::IdenteditError;
 use crate::handle::SelectionHandle;
 
@@ -11,8 +13,11 @@ mod util;
 
 pub use fallback::FallbackProvider;
 pub use json::JsonProvider;
-pub use tree_sitter::{HeaderTreeSitterProvider, TreeSitterProvider};
-pub(crate) use util::{node_text, normalize_bare_cr_for_parser};
+pub use tree_sitter::{
+    HeaderDecision, HeaderDialect, HeaderReason, HeaderTreeSitterProvider, TreeSitterProvider,
+    detect_header_language,
+};
+pub(crate) use util::{node_text, normalize_bare_cr_for_parser, syntax_error_count};
 
 pub(crate) fn normalize_extension(value: &str) -> Option<String> {
     let trimmed = value.trim();
@@ -29,19 +34,150 @@ pub trait StructureProvider {
     fn can_handle(&self, path: &Path) -> bool;
     fn name(&self) -> &'static str;
     fn supported_extensions(&self) -> &'static [&'static str];
+
+    /// The full syntax tree, for providers backed by tree-sitter.
+    fn syntax_tree(&self, _source: &[u8]) -> Option<Result<::tree_sitter::Tree, IdenteditError>> {
+        None
+    }
+
+    /// Which grammar a `.h` file parses under, for the C/C++ header provider.
+    fn header_decision(&self, _source: &[u8]) -> Option<Result<HeaderDecision, IdenteditError>> {
+        None
+    }
+}
+
+/// Changes to the built-in providers, from `--disable-provider`, `--prefer`, `--header-lang`,
+/// and the `extensions` of the project config. Names are provider names as `capabilities`
+/// lists them, or language names such as `python` for `tree-sitter-python`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProviderOverrides {
+    /// Providers left out of every registry.
+    pub disabled: Vec<String>,
+    /// Extension and provider pairs; a file with the extension goes to that provider ahead
+    /// of lookup order, whether or not the provider claims the extension.
+    pub preferred: Vec<(String, String)>,
+    /// Extension and provider pairs for files that would otherwise reach the fallback
+    /// provider, such as `pyi` and `python`.
+    pub mapped: Vec<(String, String)>,
+    /// Dialect `.h` files parse as instead of trying both C and C++.
+    pub header_dialect: Option<HeaderDialect>,
+}
+
+impl ProviderOverrides {
+    /// These overrides with every name resolved to a known provider and every extension
+    /// normalized.
+    fn resolve(self) -> Result<Self, IdenteditError> {
+        let known = ProviderRegistry::unfiltered(None)
+            .providers
+            .iter()
+            .map(|provider| provider.name())
+            .collect::<Vec<_>>();
+        let disabled = self
+            .disabled
+            .iter()
+            .map(|name| resolve_provider_name(&known, name))
+            .collect::<Result<Vec<_>, _>>()?;
+        let routes = |pairs: Vec<(String, String)>| {
+            pairs
+                .into_iter()
+                .map(|(extension, name)| {
+                    let name = resolve_provider_name(&known, &name)?;
+                    if disabled.contains(&name) {
+                        return Err(IdenteditError::InvalidRequest {
+                            message: format!("Provider '{name}' is both disabled and routed to"),
+                        });
+                    }
+                    let extension = normalize_extension(&extension).ok_or_else(|| {
+                        IdenteditError::InvalidRequest {
+                            message: format!("Extension routed to '{name}' is empty"),
+                        }
+                    })?;
+                    Ok((extension, name))
+                })
+                .collect::<Result<Vec<_>, IdenteditError>>()
+        };
+        let preferred = routes(self.preferred)?;
+        let mapped = routes(self.mapped)?;
+        Ok(Self {
+            disabled,
+            preferred,
+            mapped,
+            header_dialect: self.header_dialect,
+        })
+    }
+}
+
+fn resolve_provider_name(known: &[&str], name: &str) -> Result<String, IdenteditError> {
+    let language = format!("tree-sitter-{}", name.to_lowercase());
+    [name, language.as_str()]
+        .into_iter()
+        .find(|candidate| known.contains(candidate))
+        .map(str::to_string)
+        .ok_or_else(|| IdenteditError::InvalidRequest {
+            message: format!(
+                "Unknown provider '{name}'; known providers: {}",
+                known.join(", ")
+            ),
+        })
+}
+
+/// Why a file went to its provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderRoute {
+    /// A `--prefer` route for the file's extension.
+    Preferred,
+    /// The first provider in lookup order that claims the file.
+    Extension,
+    /// An `extensions` mapping in the project config.
+    ProjectConfig,
+    /// No provider claims the file, so the fallback provider takes it.
+    Fallback,
 }
 
 pub struct ProviderRegistry {
     providers: Vec<Box<dyn StructureProvider>>,
+    overrides: ProviderOverrides,
 }
 
 impl Default for ProviderRegistry {
     fn default() -> Self {
+        Self::resolved(ProviderOverrides::default())
+    }
+}
+
+impl ProviderRegistry {
+    /// The built-in providers with `overrides` applied, once every name in them resolves to
+    /// a known provider.
+    pub fn with_overrides(overrides: ProviderOverrides) -> Result<Self, IdenteditError> {
+        Ok(Self::resolved(overrides.resolve()?))
+    }
+
+    /// A registry applying `overrides`, already resolved as [`Self::overrides`] returns them;
+    /// scan threads, which cannot share a registry, each build one this way.
+    pub(crate) fn resolved(overrides: ProviderOverrides) -> Self {
+        let mut registry = Self::unfiltered(overrides.header_dialect);
+        registry.providers.retain(|provider| {
+            !overrides
+                .disabled
+                .iter()
+                .any(|name| name == provider.name())
+        });
+        registry.overrides = overrides;
+        registry
+    }
+
+    /// The overrides this registry applies, with names resolved.
+    pub fn overrides(&self) -> &ProviderOverrides {
+        &self.overrides
+    }
+
+    fn unfiltered(header_dialect: Option<HeaderDialect>) -> Self {
         let mut providers: Vec<Box<dyn StructureProvider>> = TreeSitterProvider::bundled()
             .into_iter()
             .map(|provider| Box::new(provider) as Box<dyn StructureProvider>)
             .collect();
-        providers.push(Box::new(HeaderTreeSitterProvider::new()));
+        providers.push(Box::new(HeaderTreeSitterProvider::new(header_dialect)));
         providers.extend(
             TreeSitterProvider::dynamic_from_manifest()
                 .into_iter()
@@ -50,15 +186,33 @@ impl Default for ProviderRegistry {
         providers.push(Box::new(JsonProvider));
         providers.push(Box::new(FallbackProvider));
 
-        Self { providers }
+        Self {
+            providers,
+            overrides: ProviderOverrides::default(),
+        }
     }
-}
 
-impl ProviderRegistry {
     pub fn provider_for(&self, path: &Path) -> Result<&dyn StructureProvider, IdenteditError> {
+        self.provider_route(path).map(|(provider, _)| provider)
+    }
+
+    /// The provider `provider_for` picks, with the rule that picked it.
+    pub fn provider_route(
+        &self,
+        path: &Path,
+    ) -> Result<(&dyn StructureProvider, ProviderRoute), IdenteditError> {
+        if let Some(provider) = self.routed_provider(path, &self.overrides.preferred) {
+            return Ok((provider, ProviderRoute::Preferred));
+        }
         for provider in &self.providers {
             if provider.can_handle(path) {
-                return Ok(provider.as_ref());
+                if provider.name() != FallbackProvider.name() {
+                    return Ok((provider.as_ref(), ProviderRoute::Extension));
+                }
+                if let Some(mapped) = self.routed_provider(path, &self.overrides.mapped) {
+                    return Ok((mapped, ProviderRoute::ProjectConfig));
+                }
+                return Ok((provider.as_ref(), ProviderRoute::Fallback));
             }
         }
 
@@ -74,6 +228,26 @@ impl ProviderRegistry {
         })
     }
 
+    fn routed_provider(
+        &self,
+        path: &Path,
+        routes: &[(String, String)],
+    ) -> Option<&dyn StructureProvider> {
+        let extension = path
+            .extension()
+            .and_then(|value| value.to_str())
+            .and_then(normalize_extension)?;
+        let (_, name) = routes.iter().find(|(routed, _)| *routed == extension)?;
+        self.providers()
+            .find(|provider| provider.name() == name.as_str())
+    }
+
+    /// Every provider in lookup order; after `--prefer` routes, the first whose `can_handle`
+    /// accepts a path wins, with project extension mappings ahead of the fallback provider.
+    pub fn providers(&self) -> impl Iterator<Item = &dyn StructureProvider> {
+        self.providers.iter().map(|provider| provider.as_ref())
+    }
+
     fn supported_extensions(&self) -> Vec<String> {
         let mut unique = BTreeSet::new();
 
@@ -98,7 +272,7 @@ mod tests {
     use std::path::Path;
     use std::path::PathBuf;
 
-    use super::{ProviderRegistry, StructureProvider};
+    use super::{ProviderOverrides, ProviderRegistry, ProviderRoute, StructureProvider};
     use crate::error::IdenteditError;
     use crate::handle::SelectionHandle;
 
@@ -497,6 +671,52 @@ mod tests {
         assert_eq!(provider.name(), "tree-sitter-c-cpp-header");
     }
 
+    #[test]
+    fn registries_apply_only_their_own_overrides() {
+        let preferring = ProviderRegistry::with_overrides(ProviderOverrides {
+            preferred: vec![("py".to_string(), "fallback".to_string())],
+            mapped: vec![("pyi".to_string(), "python".to_string())],
+            ..ProviderOverrides::default()
+        })
+        .expect("overrides should resolve");
+        let plain = ProviderRegistry::default();
+
+        let (provider, route) = preferring
+            .provider_route(Path::new("example.py"))
+            .expect("preferred provider");
+        assert_eq!(
+            (provider.name(), route),
+            ("fallback", ProviderRoute::Preferred)
+        );
+        let (provider, route) = preferring
+            .provider_route(Path::new("example.pyi"))
+            .expect("mapped provider");
+        assert_eq!(
+            (provider.name(), route),
+            ("tree-sitter-python", ProviderRoute::ProjectConfig)
+        );
+        let (provider, route) = plain
+            .provider_route(Path::new("example.py"))
+            .expect("python provider");
+        assert_eq!(
+            (provider.name(), route),
+            ("tree-sitter-python", ProviderRoute::Extension)
+        );
+    }
+
+    #[test]
+    fn with_overrides_rejects_unknown_provider_names() {
+        let error = match ProviderRegistry::with_overrides(ProviderOverrides {
+            disabled: vec!["cobol".to_string()],
+            ..ProviderOverrides::default()
+        }) {
+            Ok(_) => panic!("unknown provider should fail"),
+            Err(error) => error,
+        };
+
+        assert!(error.to_string().contains("Unknown provider 'cobol'"));
+    }
+
     #[test]
     fn provider_for_extensionless_path_routes_to_fallback() {
         let registry = ProviderRegistry::default();
@@ -563,6 +783,7 @@ mod tests {
                     extensions: &["dup"],
                 }),
             ],
+            overrides: ProviderOverrides::default(),
         };
 
         for _ in 0..8 {
@@ -590,6 +811,7 @@ mod tests {
                     extensions: &["json", "go"],
                 }),
             ],
+            overrides: ProviderOverrides::default(),
         };
 
         let error = match registry.provider_for(Path::new("fixture.unknown")) {
@@ -628,6 +850,7 @@ mod tests {
                     extensions: &["TS", ".ts", "json"],
                 }),
             ],
+            overrides: ProviderOverrides::default(),
         };
 
         let error = match registry.provider_for(Path::new("fixture.unknown")) {
@@ -661,6 +884,7 @@ mod tests {
                     extensions: &["go"],
                 }),
             ],
+            overrides: ProviderOverrides::default(),
         };
 
         for path in [Path::new("README"), Path::new("fixture."), Path::new(".go")] {
@@ -703,6 +927,7 @@ mod tests {
     fn empty_registry_returns_no_provider_with_empty_supported_extensions() {
         let registry = ProviderRegistry {
             providers: Vec::new(),
+            overrides: ProviderOverrides::default(),
         };
 
         let error = match registry.provider_for(Path::new("fixture.py")) {
@@ -728,6 +953,7 @@ mod tests {
     fn no_provider_error_extension_uses_normalized_trailing_whitespace_token() {
         let registry = ProviderRegistry {
             providers: Vec::new(),
+            overrides: ProviderOverrides::default(),
         };
 
         let error = match registry.provider_for(Path::new("fixture.ToMl ")) {
@@ -755,6 +981,7 @@ mod tests {
                     extensions: &["äxt", ".ßeta", "Λ"],
                 }),
             ],
+            overrides: ProviderOverrides::default(),
         };
 
         let error = match registry.provider_for(Path::new("fixture.none")) {
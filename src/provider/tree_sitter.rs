@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::path::Path;
 use std::sync::Arc;
 
@@ -21,17 +22,22 @@ use catalog::{
     LoadedGrammar, basename_aliases_for_provider, bundled_language_specs, leak_extensions,
     leak_string, python_language_spec,
 };
-#[cfg(test)]
-use header::HeaderDialect;
-use header::parse_c_cpp_header_with_dialect;
-use parser::parse_with_spec;
+pub use header::{HeaderDecision, HeaderDialect, HeaderReason, detect_header_language};
+use header::{parse_c_cpp_header_tree, parse_c_cpp_header_with_dialect};
+use parser::{parse_syntax_tree, parse_with_spec};
 
 pub struct TreeSitterProvider {
     spec: &'static LanguageSpec,
     basename_aliases: &'static [&'static str],
+    /// Node kinds reported under another name, from `grammar install --kind-map`.
+    kind_map: BTreeMap<String, String>,
 }
 
-pub struct HeaderTreeSitterProvider;
+#[derive(Default)]
+pub struct HeaderTreeSitterProvider {
+    /// Dialect every `.h` file parses as, from `--header-lang`; both are tried when unset.
+    forced: Option<HeaderDialect>,
+}
 
 impl TreeSitterProvider {
     pub fn bundled() -> Vec<Self> {
@@ -71,26 +77,24 @@ impl TreeSitterProvider {
             normalize_bare_cr: true,
         }));
 
-        Ok(Self::from_spec(spec))
+        Ok(Self {
+            kind_map: grammar.kind_map.clone(),
+            ..Self::from_spec(spec)
+        })
     }
 
     fn from_spec(spec: &'static LanguageSpec) -> Self {
         Self {
             spec,
             basename_aliases: basename_aliases_for_provider(spec.name),
+            kind_map: BTreeMap::new(),
         }
     }
 }
 
 impl HeaderTreeSitterProvider {
-    pub fn new() -> Self {
-        Self
-    }
-}
-
-impl Default for HeaderTreeSitterProvider {
-    fn default() -> Self {
-        Self::new()
+    pub fn new(forced: Option<HeaderDialect>) -> Self {
+        Self { forced }
     }
 }
 
@@ -105,7 +109,7 @@ fn normalize_basename_alias(value: &str) -> Option<String> {
 
 impl StructureProvider for HeaderTreeSitterProvider {
     fn parse(&self, path: &Path, source: &[u8]) -> Result<Vec<SelectionHandle>, IdenteditError> {
-        let (handles, _) = parse_c_cpp_header_with_dialect(path, source)?;
+        let (handles, _) = parse_c_cpp_header_with_dialect(path, source, self.forced)?;
         Ok(handles)
     }
 
@@ -132,11 +136,36 @@ impl StructureProvider for HeaderTreeSitterProvider {
     fn supported_extensions(&self) -> &'static [&'static str] {
         C_CPP_HEADER_EXTENSIONS
     }
+
+    fn syntax_tree(&self, source: &[u8]) -> Option<Result<tree_sitter::Tree, IdenteditError>> {
+        Some(parse_c_cpp_header_tree(source, self.forced).map(|(tree, _)| tree))
+    }
+
+    fn header_decision(&self, source: &[u8]) -> Option<Result<HeaderDecision, IdenteditError>> {
+        Some(detect_header_language(source, self.forced))
+    }
 }
 
 impl StructureProvider for TreeSitterProvider {
     fn parse(&self, path: &Path, source: &[u8]) -> Result<Vec<SelectionHandle>, IdenteditError> {
-        parse_with_spec(self.spec, path, source)
+        let handles = parse_with_spec(self.spec, path, source)?;
+        if self.kind_map.is_empty() {
+            return Ok(handles);
+        }
+
+        Ok(handles
+            .into_iter()
+            .map(|handle| match self.kind_map.get(&handle.kind) {
+                Some(kind) => SelectionHandle::from_parts(
+                    handle.file,
+                    handle.span,
+                    kind.clone(),
+                    handle.name,
+                    handle.text,
+                ),
+                None => handle,
+            })
+            .collect())
     }
 
     fn can_handle(&self, path: &Path) -> bool {
@@ -178,6 +207,10 @@ impl StructureProvider for TreeSitterProvider {
     fn supported_extensions(&self) -> &'static [&'static str] {
         self.spec.extensions
     }
+
+    fn syntax_tree(&self, source: &[u8]) -> Option<Result<tree_sitter::Tree, IdenteditError>> {
+        Some(parse_syntax_tree(self.spec, source))
+    }
 }
 
 #[cfg(test)]
@@ -186,8 +219,8 @@ mod tests {
     use std::path::Path;
 
     use super::{
-        HeaderDialect, HeaderTreeSitterProvider, LanguageSource, LanguageSpec, TreeSitterProvider,
-        load_python_language, parse_c_cpp_header_with_dialect,
+        HeaderDialect, HeaderReason, HeaderTreeSitterProvider, LanguageSource, LanguageSpec,
+        TreeSitterProvider, load_python_language, parse_c_cpp_header_with_dialect,
     };
     use crate::error::IdenteditError;
     use crate::provider::StructureProvider;
@@ -814,7 +847,7 @@ mod tests {
 
     #[test]
     fn header_provider_handles_h_extension_case_insensitively() {
-        let provider = HeaderTreeSitterProvider::new();
+        let provider = HeaderTreeSitterProvider::new(None);
         assert!(provider.can_handle(Path::new("fixture.h")));
         assert!(provider.can_handle(Path::new("fixture.H")));
         assert!(!provider.can_handle(Path::new("fixture.hpp")));
@@ -824,23 +857,52 @@ mod tests {
     #[test]
     fn parse_c_cpp_header_prefers_cpp_when_both_parsers_succeed() {
         let source = b"int configure(int value);\n";
-        let (_handles, dialect) = parse_c_cpp_header_with_dialect(Path::new("fixture.h"), source)
-            .expect("dual parser should succeed when both grammars accept source");
-        assert_eq!(dialect, HeaderDialect::Cpp);
+        let (_handles, decision) =
+            parse_c_cpp_header_with_dialect(Path::new("fixture.h"), source, None)
+                .expect("dual parser should succeed when both grammars accept source");
+        assert_eq!(decision.language, HeaderDialect::Cpp);
+        assert_eq!(decision.reason, HeaderReason::BothParse);
     }
 
     #[test]
     fn parse_c_cpp_header_falls_back_to_c_when_cpp_has_errors() {
         let source = b"int configure(value)\nint value;\n{\n    return value;\n}\n";
-        let (_handles, dialect) = parse_c_cpp_header_with_dialect(Path::new("fixture.h"), source)
-            .expect("dual parser should fall back to C for C-only header syntax");
-        assert_eq!(dialect, HeaderDialect::C);
+        let (_handles, decision) =
+            parse_c_cpp_header_with_dialect(Path::new("fixture.h"), source, None)
+                .expect("dual parser should fall back to C for C-only header syntax");
+        assert_eq!(decision.language, HeaderDialect::C);
+        assert_eq!(decision.reason, HeaderReason::OnlyCParses);
+        assert!(decision.cpp_errors.is_some_and(|errors| errors > 0));
+        assert_eq!(decision.c_errors, Some(0));
+    }
+
+    #[test]
+    fn header_providers_keep_their_own_forced_dialect() {
+        let source = b"int configure(int value);\n";
+        let forced_c = HeaderTreeSitterProvider::new(Some(HeaderDialect::C))
+            .header_decision(source)
+            .expect("header provider decides")
+            .expect("source parses as C");
+        let detected = HeaderTreeSitterProvider::new(None)
+            .header_decision(source)
+            .expect("header provider decides")
+            .expect("source parses under both grammars");
+
+        assert_eq!(
+            (forced_c.language, forced_c.reason),
+            (HeaderDialect::C, HeaderReason::Forced)
+        );
+        assert_eq!(forced_c.cpp_errors, None);
+        assert_eq!(
+            (detected.language, detected.reason),
+            (HeaderDialect::Cpp, HeaderReason::BothParse)
+        );
     }
 
     #[test]
     fn parse_c_cpp_header_returns_parse_failure_when_both_grammars_fail() {
         let source = b"int broken( {\n";
-        let error = parse_c_cpp_header_with_dialect(Path::new("fixture.h"), source)
+        let error = parse_c_cpp_header_with_dialect(Path::new("fixture.h"), source, None)
             .expect_err("invalid header source should fail under both grammars");
 
         match error {
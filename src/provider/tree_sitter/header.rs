@@ -1,8 +1,11 @@
 use std::path::Path;
 
+use clap::ValueEnum;
+use serde::Serialize;
+
 use crate::error::IdenteditError;
 use crate::handle::SelectionHandle;
-use crate::provider::normalize_bare_cr_for_parser;
+use crate::provider::{normalize_bare_cr_for_parser, syntax_error_count};
 
 use super::catalog::{
     C_CPP_HEADER_PROVIDER_NAME, C_CPP_HEADER_SYNTAX_ERROR_MESSAGE, c_language_spec,
@@ -10,44 +13,135 @@ use super::catalog::{
 };
 use super::parser::{collect_nodes, parse_tree_from_source};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub(super) enum HeaderDialect {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum HeaderDialect {
     C,
     Cpp,
 }
 
+impl HeaderDialect {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::C => "C",
+            Self::Cpp => "C++",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeaderReason {
+    /// Set with `--header-lang`; the other grammar was not tried.
+    Forced,
+    OnlyCppParses,
+    OnlyCParses,
+    /// Both grammars parse without errors, and C++ is the default.
+    BothParse,
+}
+
+/// Which grammar parsed a `.h` file and why. Templated or macro-heavy headers can parse
+/// under both grammars with different trees, so a wrong guess changes spans.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct HeaderDecision {
+    pub language: HeaderDialect,
+    pub reason: HeaderReason,
+    /// Error and missing nodes in the C++ parse; absent when `--header-lang c` skipped it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpp_errors: Option<usize>,
+    /// Error and missing nodes in the C parse; absent when `--header-lang cpp` skipped it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub c_errors: Option<usize>,
+}
+
+/// The grammar a `.h` file with `source` parses under, and the evidence for it; `forced`
+/// is the dialect `--header-lang` asks for, if any.
+pub fn detect_header_language(
+    source: &[u8],
+    forced: Option<HeaderDialect>,
+) -> Result<HeaderDecision, IdenteditError> {
+    parse_c_cpp_header_tree(source, forced).map(|(_, decision)| decision)
+}
+
 pub(super) fn parse_c_cpp_header_with_dialect(
     path: &Path,
     source: &[u8],
-) -> Result<(Vec<SelectionHandle>, HeaderDialect), IdenteditError> {
+    forced: Option<HeaderDialect>,
+) -> Result<(Vec<SelectionHandle>, HeaderDecision), IdenteditError> {
+    let (tree, decision) = parse_c_cpp_header_tree(source, forced)?;
+    let mut handles = Vec::new();
+    collect_nodes(tree.root_node(), path, source, &mut handles);
+    Ok((handles, decision))
+}
+
+/// Parses a header as both C and C++ and keeps the dialect that parses without errors,
+/// or only as `forced` when `--header-lang` sets it.
+pub(super) fn parse_c_cpp_header_tree(
+    source: &[u8],
+    forced: Option<HeaderDialect>,
+) -> Result<(tree_sitter::Tree, HeaderDecision), IdenteditError> {
     let parse_source = normalize_bare_cr_for_parser(source);
     debug_assert_eq!(parse_source.len(), source.len());
+    let parse = |dialect: HeaderDialect| {
+        let spec = match dialect {
+            HeaderDialect::C => c_language_spec(),
+            HeaderDialect::Cpp => cpp_language_spec(),
+        };
+        parse_tree_from_source(parse_source.as_ref(), &spec.source, spec.name)
+    };
 
-    let cpp_spec = cpp_language_spec();
-    let c_spec = c_language_spec();
-
-    let cpp_tree = parse_tree_from_source(parse_source.as_ref(), &cpp_spec.source, cpp_spec.name)?;
-    let c_tree = parse_tree_from_source(parse_source.as_ref(), &c_spec.source, c_spec.name)?;
-
-    let cpp_has_error = cpp_tree.root_node().has_error();
-    let c_has_error = c_tree.root_node().has_error();
-
-    let (selected_tree, dialect) = match (cpp_has_error, c_has_error) {
-        (false, true) => (&cpp_tree, HeaderDialect::Cpp),
-        (true, false) => (&c_tree, HeaderDialect::C),
-        (false, false) => {
-            // TODO: Replace this with content-based heuristics for ambiguous headers.
-            (&cpp_tree, HeaderDialect::Cpp)
-        }
-        (true, true) => {
+    if let Some(dialect) = forced {
+        let tree = parse(dialect)?;
+        let errors = syntax_error_count(tree.root_node());
+        if errors > 0 {
             return Err(IdenteditError::ParseFailure {
                 provider: C_CPP_HEADER_PROVIDER_NAME,
-                message: C_CPP_HEADER_SYNTAX_ERROR_MESSAGE.to_string(),
+                message: format!(
+                    "Syntax errors detected in header parsed as {} (forced by --header-lang)",
+                    dialect.as_str()
+                ),
             });
         }
+        let decision = HeaderDecision {
+            language: dialect,
+            reason: HeaderReason::Forced,
+            cpp_errors: (dialect == HeaderDialect::Cpp).then_some(errors),
+            c_errors: (dialect == HeaderDialect::C).then_some(errors),
+        };
+        return Ok((tree, decision));
+    }
+
+    let cpp_tree = parse(HeaderDialect::Cpp)?;
+    let c_tree = parse(HeaderDialect::C)?;
+    let cpp_errors = syntax_error_count(cpp_tree.root_node());
+    let c_errors = syntax_error_count(c_tree.root_node());
+    let decision = |language, reason| HeaderDecision {
+        language,
+        reason,
+        cpp_errors: Some(cpp_errors),
+        c_errors: Some(c_errors),
     };
 
-    let mut handles = Vec::new();
-    collect_nodes(selected_tree.root_node(), path, source, &mut handles);
-    Ok((handles, dialect))
+    match (cpp_errors > 0, c_errors > 0) {
+        (false, true) => Ok((
+            cpp_tree,
+            decision(HeaderDialect::Cpp, HeaderReason::OnlyCppParses),
+        )),
+        (true, false) => Ok((
+            c_tree,
+            decision(HeaderDialect::C, HeaderReason::OnlyCParses),
+        )),
+        (false, false) => {
+            // TODO: Replace this with content-based heuristics for ambiguous headers.
+            Ok((
+                cpp_tree,
+                decision(HeaderDialect::Cpp, HeaderReason::BothParse),
+            ))
+        }
+        (true, true) => Err(IdenteditError::ParseFailure {
+            provider: C_CPP_HEADER_PROVIDER_NAME,
+            message: C_CPP_HEADER_SYNTAX_ERROR_MESSAGE.to_string(),
+        }),
+    }
 }
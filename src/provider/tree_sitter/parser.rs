@@ -14,13 +14,7 @@ pub(super) fn parse_with_spec(
     path: &Path,
     source: &[u8],
 ) -> Result<Vec<SelectionHandle>, IdenteditError> {
-    let parse_source = if spec.normalize_bare_cr {
-        normalize_bare_cr_for_parser(source)
-    } else {
-        Cow::Borrowed(source)
-    };
-    debug_assert_eq!(parse_source.len(), source.len());
-    let tree = parse_tree_from_source(parse_source.as_ref(), &spec.source, spec.name)?;
+    let tree = parse_syntax_tree(spec, source)?;
 
     if tree.root_node().has_error() {
         return Err(IdenteditError::ParseFailure {
@@ -34,6 +28,19 @@ pub(super) fn parse_with_spec(
     Ok(handles)
 }
 
+pub(super) fn parse_syntax_tree(
+    spec: &'static LanguageSpec,
+    source: &[u8],
+) -> Result<tree_sitter::Tree, IdenteditError> {
+    let parse_source = if spec.normalize_bare_cr {
+        normalize_bare_cr_for_parser(source)
+    } else {
+        Cow::Borrowed(source)
+    };
+    debug_assert_eq!(parse_source.len(), source.len());
+    parse_tree_from_source(parse_source.as_ref(), &spec.source, spec.name)
+}
+
 pub(super) fn parse_tree_from_source(
     source: &[u8],
     language_source: &LanguageSource,
@@ -13,6 +13,19 @@ pub(crate) fn node_text(node: Node<'_>, source: &[u8]) -> Option<String> {
     Some(String::from_utf8_lossy(&source[start..end]).to_string())
 }
 
+/// ERROR and MISSING nodes under `node`, itself included.
+pub(crate) fn syntax_error_count(node: Node<'_>) -> usize {
+    if !node.has_error() && !node.is_missing() {
+        return 0;
+    }
+    let own = usize::from(node.is_error() || node.is_missing());
+    let mut cursor = node.walk();
+    own + node
+        .children(&mut cursor)
+        .map(syntax_error_count)
+        .sum::<usize>()
+}
+
 pub(crate) fn normalize_bare_cr_for_parser(source: &[u8]) -> Cow<'_, [u8]> {
     let mut contains_standalone_cr = false;
 
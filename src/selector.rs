@@ -1,35 +1,122 @@
+use std::borrow::Cow;
+
+use clap::ValueEnum;
 use glob::Pattern;
+use regex::{Regex, RegexBuilder};
 use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
 
 use crate::error::IdenteditError;
-use crate::handle::SelectionHandle;
+use crate::handle::{SelectionHandle, Span};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Kind and name filters, optionally composed with other selectors. A handle is selected
+/// when it passes the filters and every composed condition.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Selector {
+    #[serde(default)]
     pub kind: String,
+    /// Additional kinds; a handle matching `kind` or any of these is selected.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub kinds: Vec<String>,
     #[serde(default)]
     pub name_pattern: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_regex: Option<String>,
     #[serde(default)]
     pub exclude_kinds: Vec<String>,
+    /// Glob patterns; handles whose name matches any of them are dropped.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_names: Vec<String>,
+    #[serde(default, skip_serializing_if = "NameMode::is_exact")]
+    pub name_mode: NameMode,
+    /// Selectors the handle must also match, every one of them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub and: Vec<Selector>,
+    /// Selectors of which the handle must match at least one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub or: Vec<Selector>,
+    /// A selector the handle must not match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not: Option<Box<Selector>>,
+    /// The handle must lie inside a node this selector matches.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub within: Option<Box<Selector>>,
+}
+
+/// Which of the nodes a selector matches a positional target picks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Position {
+    /// The one that starts first.
+    First,
+    /// The one that ends last.
+    Last,
+}
+
+/// How name filters compare symbol names with the patterns they were given.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum NameMode {
+    /// Byte-for-byte comparison.
+    #[default]
+    Exact,
+    /// Ignore case (Unicode lowercase folding).
+    Ci,
+    /// Compare after NFC normalization, so composed and decomposed accents match.
+    Nfc,
+}
+
+impl NameMode {
+    fn is_exact(&self) -> bool {
+        *self == NameMode::Exact
+    }
+
+    fn normalize<'a>(self, text: &'a str) -> Cow<'a, str> {
+        match self {
+            NameMode::Exact => Cow::Borrowed(text),
+            NameMode::Ci => Cow::Owned(text.to_lowercase()),
+            NameMode::Nfc => Cow::Owned(text.nfc().collect()),
+        }
+    }
 }
 
 impl Selector {
     pub fn validate(&self) -> Result<(), IdenteditError> {
-        if self.kind.trim().is_empty() {
+        if self.kind.trim().is_empty() && self.kinds.is_empty() && !self.is_composed() {
             return Err(IdenteditError::InvalidRequest {
                 message: "selector.kind must not be empty".to_string(),
             });
         }
+        self.validate_parts()
+    }
 
-        if let Some(name_pattern) = self.name_pattern.as_deref() {
-            Pattern::new(name_pattern).map_err(|error| IdenteditError::InvalidNamePattern {
-                pattern: name_pattern.to_string(),
-                message: error.msg.to_string(),
-            })?;
+    /// Which of a file's `handles` match a selector built from `edit` flags. There a name
+    /// filter alone selects, so unlike [`Selector::filter`] the kind may be left out.
+    pub(crate) fn matches_among(
+        &self,
+        handles: &[SelectionHandle],
+    ) -> Result<Vec<bool>, IdenteditError> {
+        self.validate_parts()?;
+        self.select(handles)
+    }
+
+    /// Checks the filters and every composed selector, but not that a kind is given.
+    fn validate_parts(&self) -> Result<(), IdenteditError> {
+        if self.kinds.iter().any(|kind| kind.trim().is_empty()) {
+            return Err(IdenteditError::InvalidRequest {
+                message: "selector.kinds must not contain empty kinds".to_string(),
+            });
         }
 
-        Ok(())
+        self.compile()?;
+        self.and
+            .iter()
+            .chain(&self.or)
+            .chain(self.not.as_deref())
+            .chain(self.within.as_deref())
+            .try_for_each(Selector::validate)
     }
 
     pub fn filter(
@@ -38,25 +125,154 @@ impl Selector {
     ) -> Result<Vec<SelectionHandle>, IdenteditError> {
         self.validate()?;
 
-        let compiled_pattern = self
-            .name_pattern
-            .as_deref()
-            .map(Pattern::new)
-            .transpose()
-            .map_err(|error| IdenteditError::InvalidNamePattern {
-                pattern: self.name_pattern.as_deref().unwrap_or_default().to_string(),
-                message: error.msg.to_string(),
-            })?;
-
+        let selected = self.select(&handles)?;
         let filtered = handles
             .into_iter()
-            .filter(|handle| self.matches(handle, compiled_pattern.as_ref()))
+            .zip(selected)
+            .filter_map(|(handle, selected)| selected.then_some(handle))
             .collect();
 
         Ok(filtered)
     }
 
-    fn matches(&self, handle: &SelectionHandle, name_pattern: Option<&Pattern>) -> bool {
+    /// The first or last of the handles this selector matches, leaving out matches nested
+    /// in another match, so "the last function" is never a closure inside it.
+    pub fn pick(
+        &self,
+        handles: Vec<SelectionHandle>,
+        position: Position,
+    ) -> Result<Option<SelectionHandle>, IdenteditError> {
+        let matched = self.filter(handles)?;
+        let outermost = matched
+            .iter()
+            .filter(|handle| !matched.iter().any(|outer| inside(outer.span, handle.span)))
+            .cloned();
+        Ok(match position {
+            Position::First => outermost.min_by_key(|handle| handle.span.start),
+            Position::Last => outermost.max_by_key(|handle| handle.span.end),
+        })
+    }
+
+    /// Whether the kind may be left out: a composed selector can take its kinds from the
+    /// selectors it is built from.
+    fn is_composed(&self) -> bool {
+        !self.and.is_empty() || !self.or.is_empty() || self.within.is_some()
+    }
+
+    /// Which of `handles` this selector matches. `within` looks for containers among the
+    /// same handles, so they must be all the handles of one file.
+    fn select(&self, handles: &[SelectionHandle]) -> Result<Vec<bool>, IdenteditError> {
+        let filter = self.compile()?;
+        let mut selected = handles
+            .iter()
+            .map(|handle| filter.matches(handle))
+            .collect::<Vec<_>>();
+        for and in &self.and {
+            keep_where(&mut selected, and.select(handles)?);
+        }
+        if !self.or.is_empty() {
+            let mut any = vec![false; handles.len()];
+            for or in &self.or {
+                for (any, matched) in any.iter_mut().zip(or.select(handles)?) {
+                    *any |= matched;
+                }
+            }
+            keep_where(&mut selected, any);
+        }
+        if let Some(not) = &self.not {
+            let matched = not.select(handles)?;
+            keep_where(&mut selected, matched.into_iter().map(|matched| !matched));
+        }
+        if let Some(within) = &self.within {
+            let containers = handles
+                .iter()
+                .zip(within.select(handles)?)
+                .filter_map(|(handle, matched)| matched.then_some(handle.span))
+                .collect::<Vec<_>>();
+            let inside = handles.iter().map(|handle| {
+                containers
+                    .iter()
+                    .any(|&container| inside(container, handle.span))
+            });
+            keep_where(&mut selected, inside);
+        }
+        Ok(selected)
+    }
+
+    fn compile(&self) -> Result<HandleFilter, IdenteditError> {
+        let mut kinds = self.kinds.clone();
+        if !self.kind.trim().is_empty() {
+            kinds.push(self.kind.clone());
+        }
+        HandleFilter::new(
+            kinds,
+            self.exclude_kinds.clone(),
+            self.name_pattern.as_deref(),
+            self.name_regex
+                .as_deref()
+                .map(|regex| ("selector.name_regex", regex)),
+            &self.exclude_names,
+            self.name_mode,
+        )
+    }
+}
+
+/// Compiled kind and name filters, shared by `read` flags and JSON selectors so both
+/// accept the same rules.
+///
+/// Exclusions win over inclusions. Name filters only pass handles that carry a name;
+/// name exclusions never drop unnamed handles.
+#[derive(Debug, Default)]
+pub(crate) struct HandleFilter {
+    kinds: Vec<String>,
+    exclude_kinds: Vec<String>,
+    name_pattern: Option<Pattern>,
+    name_regex: Option<Regex>,
+    exclude_names: Vec<Pattern>,
+    name_mode: NameMode,
+}
+
+impl HandleFilter {
+    /// `name_regex` carries the flag or field name used in error messages.
+    pub(crate) fn new(
+        kinds: Vec<String>,
+        exclude_kinds: Vec<String>,
+        name_pattern: Option<&str>,
+        name_regex: Option<(&str, &str)>,
+        exclude_names: &[String],
+        name_mode: NameMode,
+    ) -> Result<Self, IdenteditError> {
+        // Regex patterns are not lowercased (that would change `\W` and friends);
+        // case-insensitive matching is left to the regex engine instead.
+        let name_regex = name_regex
+            .map(|(label, regex)| {
+                let source = match name_mode {
+                    NameMode::Nfc => name_mode.normalize(regex),
+                    NameMode::Exact | NameMode::Ci => Cow::Borrowed(regex),
+                };
+                RegexBuilder::new(&source)
+                    .case_insensitive(name_mode == NameMode::Ci)
+                    .build()
+                    .map_err(|error| IdenteditError::InvalidRequest {
+                        message: format!("Invalid {label} '{regex}': {error}"),
+                    })
+            })
+            .transpose()?;
+        let compile_glob = |pattern: &str| compile_glob(pattern, name_mode);
+        Ok(Self {
+            kinds,
+            exclude_kinds,
+            name_pattern: name_pattern.map(compile_glob).transpose()?,
+            name_regex,
+            exclude_names: exclude_names
+                .iter()
+                .map(|pattern| compile_glob(pattern))
+                .collect::<Result<_, _>>()?,
+            name_mode,
+        })
+    }
+
+    pub(crate) fn matches(&self, handle: &SelectionHandle) -> bool {
         if self
             .exclude_kinds
             .iter()
@@ -65,28 +281,64 @@ impl Selector {
             return false;
         }
 
-        if self.kind != handle.kind {
+        if !self.kinds.is_empty() && !self.kinds.contains(&handle.kind) {
             return false;
         }
 
-        if let Some(pattern) = name_pattern {
-            return handle
-                .name
-                .as_deref()
-                .is_some_and(|symbol_name| pattern.matches(symbol_name));
+        let name = handle.name.as_deref();
+        let glob_name = name.map(|symbol_name| self.name_mode.normalize(symbol_name));
+        let glob_name = glob_name.as_deref();
+        if let Some(pattern) = &self.name_pattern
+            && !glob_name.is_some_and(|symbol_name| pattern.matches(symbol_name))
+        {
+            return false;
         }
+        if let Some(regex) = &self.name_regex {
+            let regex_name = match self.name_mode {
+                NameMode::Nfc => glob_name,
+                NameMode::Exact | NameMode::Ci => name,
+            };
+            if !regex_name.is_some_and(|symbol_name| regex.is_match(symbol_name)) {
+                return false;
+            }
+        }
+
+        !glob_name.is_some_and(|symbol_name| {
+            self.exclude_names
+                .iter()
+                .any(|pattern| pattern.matches(symbol_name))
+        })
+    }
+}
 
-        true
+fn keep_where(selected: &mut [bool], matched: impl IntoIterator<Item = bool>) {
+    for (selected, matched) in selected.iter_mut().zip(matched) {
+        *selected &= matched;
     }
 }
 
+/// Whether `span` lies inside `container` without being the container itself.
+fn inside(container: Span, span: Span) -> bool {
+    container.start <= span.start && span.end <= container.end && container != span
+}
+
+/// Globs are normalized like the names they are matched against.
+fn compile_glob(pattern: &str, name_mode: NameMode) -> Result<Pattern, IdenteditError> {
+    Pattern::new(&name_mode.normalize(pattern)).map_err(|error| {
+        IdenteditError::InvalidNamePattern {
+            pattern: pattern.to_string(),
+            message: error.msg.to_string(),
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
 
     use crate::handle::{SelectionHandle, Span};
 
-    use super::Selector;
+    use super::{NameMode, Selector};
 
     fn handle(kind: &str, name: Option<&str>, text: &str) -> SelectionHandle {
         SelectionHandle::from_parts(
@@ -105,8 +357,13 @@ mod tests {
     fn validate_rejects_empty_kind() {
         let selector = Selector {
             kind: "   ".to_string(),
+            kinds: vec![],
             name_pattern: None,
+            name_regex: None,
             exclude_kinds: vec![],
+            exclude_names: vec![],
+            name_mode: NameMode::Exact,
+            ..Selector::default()
         };
 
         let error = selector.validate().expect_err("empty kind should fail");
@@ -121,8 +378,13 @@ mod tests {
     fn validate_rejects_invalid_glob_pattern() {
         let selector = Selector {
             kind: "function_definition".to_string(),
+            kinds: vec![],
             name_pattern: Some("[".to_string()),
+            name_regex: None,
             exclude_kinds: vec![],
+            exclude_names: vec![],
+            name_mode: NameMode::Exact,
+            ..Selector::default()
         };
 
         let error = selector.validate().expect_err("invalid glob should fail");
@@ -133,8 +395,13 @@ mod tests {
     fn filter_applies_kind_name_and_exclude_rules() {
         let selector = Selector {
             kind: "function_definition".to_string(),
+            kinds: vec![],
             name_pattern: Some("process_*".to_string()),
+            name_regex: None,
             exclude_kinds: vec!["comment".to_string()],
+            exclude_names: vec![],
+            name_mode: NameMode::Exact,
+            ..Selector::default()
         };
 
         let handles = vec![
@@ -162,8 +429,13 @@ mod tests {
     fn filter_supports_literal_wildcard_matching() {
         let selector = Selector {
             kind: "function_definition".to_string(),
+            kinds: vec![],
             name_pattern: Some("process[*]".to_string()),
+            name_regex: None,
             exclude_kinds: vec![],
+            exclude_names: vec![],
+            name_mode: NameMode::Exact,
+            ..Selector::default()
         };
 
         let handles = vec![
@@ -188,8 +460,13 @@ mod tests {
     fn filter_with_name_pattern_excludes_handles_without_symbol_name() {
         let selector = Selector {
             kind: "function_definition".to_string(),
+            kinds: vec![],
             name_pattern: Some("process_*".to_string()),
+            name_regex: None,
             exclude_kinds: vec![],
+            exclude_names: vec![],
+            name_mode: NameMode::Exact,
+            ..Selector::default()
         };
 
         let handles = vec![
@@ -208,8 +485,13 @@ mod tests {
     fn filter_exclude_kind_overrides_primary_kind_match() {
         let selector = Selector {
             kind: "function_definition".to_string(),
+            kinds: vec![],
             name_pattern: None,
+            name_regex: None,
             exclude_kinds: vec!["function_definition".to_string()],
+            exclude_names: vec![],
+            name_mode: NameMode::Exact,
+            ..Selector::default()
         };
 
         let handles = vec![handle(
@@ -224,4 +506,172 @@ mod tests {
             "exclude_kinds should take precedence over kind inclusion"
         );
     }
+
+    #[test]
+    fn filter_accepts_multiple_kinds_name_regex_and_name_exclusions() {
+        let selector = Selector {
+            kind: String::new(),
+            kinds: vec![
+                "function_definition".to_string(),
+                "class_definition".to_string(),
+            ],
+            name_pattern: None,
+            name_regex: Some("^(process|Process)".to_string()),
+            exclude_kinds: vec![],
+            exclude_names: vec!["*_legacy".to_string()],
+            name_mode: NameMode::Exact,
+            ..Selector::default()
+        };
+
+        let handles = vec![
+            handle(
+                "function_definition",
+                Some("process_data"),
+                "def process_data(): pass",
+            ),
+            handle(
+                "function_definition",
+                Some("process_legacy"),
+                "def process_legacy(): pass",
+            ),
+            handle(
+                "class_definition",
+                Some("Processor"),
+                "class Processor: pass",
+            ),
+            handle("function_definition", Some("helper"), "def helper(): pass"),
+            handle("decorated_definition", Some("process_cached"), "@cache"),
+        ];
+
+        let filtered = selector.filter(handles).expect("filter should succeed");
+        let names = filtered
+            .iter()
+            .filter_map(|handle| handle.name.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["process_data", "Processor"]);
+
+        let invalid = Selector {
+            name_regex: Some("(".to_string()),
+            ..selector
+        };
+        let error = invalid.validate().expect_err("invalid regex should fail");
+        assert!(error.to_string().contains("Invalid selector.name_regex"));
+    }
+
+    #[test]
+    fn composed_selectors_intersect_unite_and_exclude() {
+        let at = |kind: &str, name: &str, start: usize, end: usize| {
+            SelectionHandle::from_parts(
+                PathBuf::from("fixture.py"),
+                Span { start, end },
+                kind.to_string(),
+                Some(name.to_string()),
+                "x".repeat(end - start),
+            )
+        };
+        let handles = vec![
+            at("class_definition", "LegacyRouter", 0, 100),
+            at("function_definition", "handle_get", 20, 50),
+            at("class_definition", "Router", 100, 200),
+            at("function_definition", "handle_get", 120, 150),
+            at("function_definition", "route", 150, 190),
+            at("function_definition", "handle_post", 200, 230),
+        ];
+        let kind = |kind: &str, name_pattern: &str| Selector {
+            kind: kind.to_string(),
+            name_pattern: Some(name_pattern.to_string()),
+            ..Selector::default()
+        };
+        let spans = |selector: Selector| {
+            selector
+                .filter(handles.clone())
+                .expect("filter should succeed")
+                .iter()
+                .map(|handle| handle.span.start)
+                .collect::<Vec<_>>()
+        };
+
+        let outside_legacy = Selector {
+            not: Some(Box::new(Selector {
+                within: Some(Box::new(kind("class_definition", "LegacyRouter"))),
+                ..Selector::default()
+            })),
+            ..kind("function_definition", "handle_*")
+        };
+        assert_eq!(spans(outside_legacy), [120, 200]);
+
+        let in_any_class = Selector {
+            within: Some(Box::new(Selector {
+                or: vec![
+                    kind("class_definition", "Legacy*"),
+                    kind("class_definition", "Router"),
+                ],
+                ..Selector::default()
+            })),
+            ..kind("function_definition", "*")
+        };
+        assert_eq!(spans(in_any_class), [20, 120, 150]);
+
+        let handlers_in_router = Selector {
+            and: vec![Selector {
+                within: Some(Box::new(kind("class_definition", "Router"))),
+                ..Selector::default()
+            }],
+            ..kind("function_definition", "handle_*")
+        };
+        assert_eq!(spans(handlers_in_router), [120]);
+
+        let error = Selector {
+            not: Some(Box::new(Selector::default())),
+            ..kind("function_definition", "*")
+        }
+        .validate()
+        .expect_err("an empty nested selector should fail");
+        assert!(
+            error
+                .to_string()
+                .contains("selector.kind must not be empty")
+        );
+    }
+
+    #[test]
+    fn name_modes_fold_case_or_normalize_before_matching() {
+        let handles = || {
+            vec![
+                handle(
+                    "function_definition",
+                    Some("cafe\u{301}_total"),
+                    "def cafe\u{301}_total(): pass",
+                ),
+                handle(
+                    "function_definition",
+                    Some("LoadUser"),
+                    "def LoadUser(): pass",
+                ),
+            ]
+        };
+        let selector = |name_pattern: &str, name_mode| Selector {
+            kind: "function_definition".to_string(),
+            kinds: vec![],
+            name_pattern: Some(name_pattern.to_string()),
+            name_regex: None,
+            exclude_kinds: vec![],
+            exclude_names: vec![],
+            name_mode,
+            ..Selector::default()
+        };
+        let count = |selector: Selector| selector.filter(handles()).expect("filter").len();
+
+        assert_eq!(count(selector("caf\u{e9}_*", NameMode::Exact)), 0);
+        assert_eq!(count(selector("caf\u{e9}_*", NameMode::Nfc)), 1);
+        assert_eq!(count(selector("load*", NameMode::Exact)), 0);
+        assert_eq!(count(selector("load*", NameMode::Ci)), 1);
+
+        let regex = Selector {
+            name_pattern: None,
+            name_regex: Some("^loaduser$".to_string()),
+            ..selector("*", NameMode::Ci)
+        };
+        assert_eq!(count(regex), 1);
+    }
 }
@@ -0,0 +1,277 @@
+//! Cumulative usage counters (`identedit stats`).
+//!
+//! With `--record-stats` or `IDENTEDIT_RECORD_STATS=1`, every command adds its outcome to
+//! `.identedit/stats.json` under the current directory: requests, failures, and durations per
+//! command, error types per command, and `read --if-none-match` cache hits. Agents that share
+//! a workspace share the counters, so an operator can watch them as one service. Recording is
+//! best effort and never fails the command it records.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+
+use crate::error::IdenteditError;
+
+/// Stats store location, relative to the current directory.
+pub const STATS_FILE: &str = ".identedit/stats.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Stats {
+    /// Seconds since the Unix epoch of the first recorded command since the last reset.
+    pub since: u64,
+    pub commands: BTreeMap<String, CommandStats>,
+    pub read_cache: CacheStats,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct CommandStats {
+    pub requests: u64,
+    /// Requests that returned an error or a failing report, such as `verify` or `selftest`.
+    pub failures: u64,
+    pub total_ms: u64,
+    pub max_ms: u64,
+    /// Error responses by `error.type`.
+    pub errors: BTreeMap<String, u64>,
+}
+
+/// `read --if-none-match` outcomes: `not_modified` responses are hits.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// Adds one run of `command` to the store. `read_cache_hit` is whether a `read
+/// --if-none-match` was answered `not_modified`, and `None` for other runs. `stats` itself
+/// is not counted, so reading the counters does not change them.
+pub fn record(
+    command: &str,
+    elapsed: Duration,
+    succeeded: bool,
+    error_type: Option<&str>,
+    read_cache_hit: Option<bool>,
+) {
+    if command == "stats" {
+        return;
+    }
+    let elapsed_ms = u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX);
+    let _ = update_store(|stats| {
+        let entry = stats.commands.entry(command.to_string()).or_default();
+        entry.requests += 1;
+        entry.failures += u64::from(!succeeded || error_type.is_some());
+        entry.total_ms = entry.total_ms.saturating_add(elapsed_ms);
+        entry.max_ms = entry.max_ms.max(elapsed_ms);
+        if let Some(error_type) = error_type {
+            *entry.errors.entry(error_type.to_string()).or_default() += 1;
+        }
+        match read_cache_hit {
+            Some(true) => stats.read_cache.hits += 1,
+            Some(false) => stats.read_cache.misses += 1,
+            None => {}
+        }
+        Ok(())
+    });
+}
+
+/// The counters as last written; empty when nothing was recorded.
+pub fn load_stats() -> Result<Stats, IdenteditError> {
+    let path = Path::new(STATS_FILE);
+    if !path.is_file() {
+        return Ok(Stats::default());
+    }
+    let mut file = File::open(path).map_err(|error| IdenteditError::io(path, error))?;
+    file.lock_shared()
+        .map_err(|error| IdenteditError::io(path, error))?;
+    read_store(&mut file)
+}
+
+/// Clears the counters and returns them as they were.
+pub fn reset_stats() -> Result<Stats, IdenteditError> {
+    update_store(|stats| Ok(std::mem::take(stats)))
+}
+
+/// Prometheus text exposition of `stats`, with metric names prefixed `identedit_`.
+pub fn render_prometheus(stats: &Stats) -> String {
+    let mut output = String::new();
+    let mut family = |name: &str, kind: &str, help: &str, samples: Vec<(String, u64)>| {
+        let _ = writeln!(output, "# HELP identedit_{name} {help}");
+        let _ = writeln!(output, "# TYPE identedit_{name} {kind}");
+        for (labels, value) in samples {
+            let _ = writeln!(output, "identedit_{name}{labels} {value}");
+        }
+    };
+    let per_command = |value: fn(&CommandStats) -> u64| {
+        stats
+            .commands
+            .iter()
+            .map(|(command, counters)| {
+                (
+                    format!("{{command=\"{}\"}}", escape_label(command)),
+                    value(counters),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+
+    family(
+        "requests_total",
+        "counter",
+        "Commands run, by command.",
+        per_command(|counters| counters.requests),
+    );
+    family(
+        "failures_total",
+        "counter",
+        "Commands that returned an error or a failing report, by command.",
+        per_command(|counters| counters.failures),
+    );
+    family(
+        "errors_total",
+        "counter",
+        "Error responses, by command and error type.",
+        stats
+            .commands
+            .iter()
+            .flat_map(|(command, counters)| {
+                counters.errors.iter().map(move |(error_type, count)| {
+                    (
+                        format!(
+                            "{{command=\"{}\",type=\"{}\"}}",
+                            escape_label(command),
+                            escape_label(error_type)
+                        ),
+                        *count,
+                    )
+                })
+            })
+            .collect(),
+    );
+    family(
+        "duration_milliseconds_total",
+        "counter",
+        "Wall-clock time spent in commands, by command.",
+        per_command(|counters| counters.total_ms),
+    );
+    family(
+        "duration_milliseconds_max",
+        "gauge",
+        "Slowest run of each command.",
+        per_command(|counters| counters.max_ms),
+    );
+    family(
+        "read_cache_hits_total",
+        "counter",
+        "read --if-none-match requests answered not_modified.",
+        vec![(String::new(), stats.read_cache.hits)],
+    );
+    family(
+        "read_cache_misses_total",
+        "counter",
+        "read --if-none-match requests that returned handles.",
+        vec![(String::new(), stats.read_cache.misses)],
+    );
+    family(
+        "stats_since_seconds",
+        "gauge",
+        "Unix time of the first command counted since the last reset.",
+        vec![(String::new(), stats.since)],
+    );
+    output
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Runs `update` on the store under an exclusive lock and writes the store back.
+fn update_store<T>(
+    update: impl FnOnce(&mut Stats) -> Result<T, IdenteditError>,
+) -> Result<T, IdenteditError> {
+    let path = Path::new(STATS_FILE);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|error| IdenteditError::io(parent, error))?;
+    }
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .map_err(|error| IdenteditError::io(path, error))?;
+    file.lock_exclusive()
+        .map_err(|error| IdenteditError::io(path, error))?;
+    let mut stats = read_store(&mut file)?;
+    let result = update(&mut stats)?;
+
+    if stats.since == 0 && !stats.commands.is_empty() {
+        stats.since = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+    }
+    let mut serialized = serde_json::to_string_pretty(&stats)
+        .map_err(|source| IdenteditError::ResponseSerialization { source })?;
+    serialized.push('\n');
+    file.set_len(0)
+        .and_then(|()| file.rewind())
+        .and_then(|()| file.write_all(serialized.as_bytes()))
+        .map_err(|error| IdenteditError::io(path, error))?;
+    Ok(result)
+}
+
+fn read_store(file: &mut File) -> Result<Stats, IdenteditError> {
+    let mut content = String::new();
+    file.read_to_string(&mut content)
+        .map_err(|error| IdenteditError::io(Path::new(STATS_FILE), error))?;
+    if content.trim().is_empty() {
+        return Ok(Stats::default());
+    }
+    serde_json::from_str(&content).map_err(|error| IdenteditError::InvalidRequest {
+        message: format!("Failed to parse stats file '{STATS_FILE}': {error}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{CacheStats, CommandStats, Stats, render_prometheus};
+
+    #[test]
+    fn prometheus_output_labels_commands_and_error_types() {
+        let stats = Stats {
+            since: 1_700_000_000,
+            commands: BTreeMap::from([(
+                "apply".to_string(),
+                CommandStats {
+                    requests: 3,
+                    failures: 1,
+                    total_ms: 42,
+                    max_ms: 30,
+                    errors: BTreeMap::from([("precondition_failed".to_string(), 1)]),
+                },
+            )]),
+            read_cache: CacheStats { hits: 2, misses: 5 },
+        };
+        let output = render_prometheus(&stats);
+        assert!(output.contains("# TYPE identedit_requests_total counter\n"));
+        assert!(output.contains("identedit_requests_total{command=\"apply\"} 3\n"));
+        assert!(output.contains(
+            "identedit_errors_total{command=\"apply\",type=\"precondition_failed\"} 1\n"
+        ));
+        assert!(output.contains("identedit_duration_milliseconds_max{command=\"apply\"} 30\n"));
+        assert!(output.contains("identedit_read_cache_hits_total 2\n"));
+    }
+}
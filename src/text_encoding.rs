@@ -0,0 +1,154 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use clap::ValueEnum;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::error::IdenteditError;
+
+/// Op fields that carry replacement text.
+const OP_TEXT_FIELDS: [&str; 3] = ["new_text", "text", "replacement"];
+
+/// How text payloads travel through JSON: as-is, or base64 of their UTF-8 bytes for
+/// consumers that mishandle control characters or other exotic content.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum TextEncoding {
+    #[default]
+    Utf8,
+    Base64,
+}
+
+impl TextEncoding {
+    pub fn is_utf8(&self) -> bool {
+        *self == TextEncoding::Utf8
+    }
+
+    pub fn encode(self, text: String) -> String {
+        match self {
+            TextEncoding::Utf8 => text,
+            TextEncoding::Base64 => STANDARD.encode(text),
+        }
+    }
+
+    /// `field` names the input in error messages, for example `--replace`.
+    pub fn decode(self, field: &str, text: String) -> Result<String, IdenteditError> {
+        match self {
+            TextEncoding::Utf8 => Ok(text),
+            TextEncoding::Base64 => {
+                let bytes = STANDARD.decode(text.trim()).map_err(|error| {
+                    IdenteditError::InvalidRequest {
+                        message: format!("{field} is not valid base64: {error}"),
+                    }
+                })?;
+                String::from_utf8(bytes).map_err(|_| IdenteditError::InvalidRequest {
+                    message: format!("{field} does not decode to UTF-8 text"),
+                })
+            }
+        }
+    }
+
+    pub fn decode_option(
+        self,
+        field: &str,
+        text: Option<String>,
+    ) -> Result<Option<String>, IdenteditError> {
+        text.map(|text| self.decode(field, text)).transpose()
+    }
+
+    /// Parses a stdin JSON request, decoding op text fields first.
+    pub fn parse_request<T: DeserializeOwned>(self, body: &str) -> Result<T, IdenteditError> {
+        let parse_error = |source| IdenteditError::InvalidJsonRequest { source };
+        if self.is_utf8() {
+            return serde_json::from_str(body).map_err(parse_error);
+        }
+        let mut request = serde_json::from_str::<Value>(body).map_err(parse_error)?;
+        self.decode_request_ops(&mut request)?;
+        serde_json::from_value(request).map_err(parse_error)
+    }
+
+    /// Decodes the text fields of every `op` object in a JSON request, wherever it is
+    /// nested (`op`, `operations[].op`, `files[].operations[].op`).
+    pub fn decode_request_ops(self, request: &mut Value) -> Result<(), IdenteditError> {
+        if self.is_utf8() {
+            return Ok(());
+        }
+        match request {
+            Value::Object(fields) => {
+                for (key, value) in fields.iter_mut() {
+                    if key == "op"
+                        && let Value::Object(op) = value
+                    {
+                        for field in OP_TEXT_FIELDS {
+                            if let Some(Value::String(text)) = op.get_mut(field) {
+                                *text =
+                                    self.decode(&format!("op.{field}"), std::mem::take(text))?;
+                            }
+                        }
+                    } else {
+                        self.decode_request_ops(value)?;
+                    }
+                }
+                Ok(())
+            }
+            Value::Array(items) => items
+                .iter_mut()
+                .try_for_each(|item| self.decode_request_ops(item)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::TextEncoding;
+
+    #[test]
+    fn base64_round_trips_control_characters_and_decodes_nested_ops() {
+        let text = "tab\there\u{0}\u{1b}[0m\r\n".to_string();
+        let encoded = TextEncoding::Base64.encode(text.clone());
+        assert!(
+            encoded
+                .chars()
+                .all(|character| character.is_ascii_graphic())
+        );
+        assert_eq!(
+            TextEncoding::Base64
+                .decode("new_text", encoded.clone())
+                .expect("decode"),
+            text
+        );
+        assert!(
+            TextEncoding::Base64
+                .decode("new_text", "not base64!".to_string())
+                .is_err()
+        );
+        assert!(
+            TextEncoding::Base64
+                .decode("new_text", "//8=".to_string())
+                .is_err(),
+            "decoded bytes must be UTF-8"
+        );
+
+        let mut request = json!({
+            "files": [{ "operations": [{ "target": { "anchor": "1:abc" }, "op": {
+                "type": "replace", "new_text": encoded
+            }}]}]
+        });
+        TextEncoding::Base64
+            .decode_request_ops(&mut request)
+            .expect("decode request");
+        assert_eq!(
+            request["files"][0]["operations"][0]["op"]["new_text"],
+            text.as_str()
+        );
+        assert_eq!(
+            request["files"][0]["operations"][0]["target"]["anchor"],
+            "1:abc"
+        );
+    }
+}
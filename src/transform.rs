@@ -33,41 +33,56 @@ pub fn build_replace_changeset(
     file: &Path,
     identity: &str,
     replacement: String,
+    registry: &ProviderRegistry,
 ) -> Result<FileChange, IdenteditError> {
-    build::build_replace_changeset(file, identity, replacement)
+    build::build_replace_changeset(file, identity, replacement, registry)
 }
 
-pub fn build_delete_changeset(file: &Path, identity: &str) -> Result<FileChange, IdenteditError> {
-    build::build_delete_changeset(file, identity)
+pub fn build_delete_changeset(
+    file: &Path,
+    identity: &str,
+    registry: &ProviderRegistry,
+) -> Result<FileChange, IdenteditError> {
+    build::build_delete_changeset(file, identity, registry)
 }
 
 pub fn build_insert_before_changeset(
     file: &Path,
     identity: &str,
     new_text: String,
+    registry: &ProviderRegistry,
 ) -> Result<FileChange, IdenteditError> {
-    build::build_insert_before_changeset(file, identity, new_text)
+    build::build_insert_before_changeset(file, identity, new_text, registry)
 }
 
 pub fn build_insert_after_changeset(
     file: &Path,
     identity: &str,
     new_text: String,
+    registry: &ProviderRegistry,
 ) -> Result<FileChange, IdenteditError> {
-    build::build_insert_after_changeset(file, identity, new_text)
+    build::build_insert_after_changeset(file, identity, new_text, registry)
 }
 
 pub fn build_changeset(
     file: &Path,
     instructions: Vec<TransformInstruction>,
+    registry: &ProviderRegistry,
 ) -> Result<FileChange, IdenteditError> {
-    build::build_changeset(file, instructions)
+    build::build_changeset(file, instructions, registry)
 }
 
 pub fn resolve_changeset_targets(
     changeset: &FileChange,
+    registry: &ProviderRegistry,
 ) -> Result<Vec<MatchedChange>, IdenteditError> {
-    build::resolve_changeset_targets(changeset)
+    build::resolve_changeset_targets(changeset, registry)
+}
+
+/// Byte span of each line, terminator included, as line targets see them: line N is at
+/// index N - 1.
+pub fn line_spans(source_text: &str) -> Vec<Span> {
+    resolve::line_spans(source_text)
 }
 
 pub fn resolve_changeset_targets_in_handles(
@@ -78,23 +93,27 @@ pub fn resolve_changeset_targets_in_handles(
     build::resolve_changeset_targets_in_handles(changeset, source_text, handles)
 }
 
-pub fn parse_handles_for_file(file: &Path) -> Result<Vec<SelectionHandle>, IdenteditError> {
-    parse::parse_handles_for_file(file)
+pub fn resolve_each_changeset_target_in_handles(
+    changeset: &FileChange,
+    source_text: &str,
+    handles: &[SelectionHandle],
+) -> Vec<Result<MatchedChange, IdenteditError>> {
+    build::resolve_each_changeset_target_in_handles(changeset, source_text, handles)
 }
 
-pub fn parse_handles_for_source(
+pub fn parse_handles_for_file(
     file: &Path,
-    source: &[u8],
+    registry: &ProviderRegistry,
 ) -> Result<Vec<SelectionHandle>, IdenteditError> {
-    parse::parse_handles_for_source(file, source)
+    parse::parse_handles_for_file(file, registry)
 }
 
-pub fn parse_handles_for_source_with_registry(
+pub fn parse_handles_for_source(
     file: &Path,
     source: &[u8],
     registry: &ProviderRegistry,
 ) -> Result<Vec<SelectionHandle>, IdenteditError> {
-    parse::parse_handles_for_source_with_registry(file, source, registry)
+    parse::parse_handles_for_source(file, source, registry)
 }
 
 #[cfg(test)]
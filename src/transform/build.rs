@@ -4,9 +4,9 @@ use crate::changeset::{ChangeOp, ChangePreview, FileChange, OpKind, TransformTar
 use crate::error::IdenteditError;
 use crate::execution_context::ExecutionContext;
 use crate::handle::SelectionHandle;
+use crate::provider::ProviderRegistry;
 
 use super::conflict::{reject_move_operation, validate_change_conflicts};
-use super::parse::{parse_handles_for_file_with_context, parse_handles_for_source_with_context};
 use super::resolve::{HandleIndex, ResolvedOperationView, resolve_operation_view};
 use super::{MatchedChange, TransformInstruction};
 
@@ -14,6 +14,7 @@ pub(super) fn build_replace_changeset(
     file: &Path,
     identity: &str,
     replacement: String,
+    registry: &ProviderRegistry,
 ) -> Result<FileChange, IdenteditError> {
     build_single_identity_changeset(
         file,
@@ -21,39 +22,44 @@ pub(super) fn build_replace_changeset(
         OpKind::Replace {
             new_text: replacement,
         },
+        registry,
     )
 }
 
 pub(super) fn build_delete_changeset(
     file: &Path,
     identity: &str,
+    registry: &ProviderRegistry,
 ) -> Result<FileChange, IdenteditError> {
-    build_single_identity_changeset(file, identity, OpKind::Delete)
+    build_single_identity_changeset(file, identity, OpKind::Delete, registry)
 }
 
 pub(super) fn build_insert_before_changeset(
     file: &Path,
     identity: &str,
     new_text: String,
+    registry: &ProviderRegistry,
 ) -> Result<FileChange, IdenteditError> {
-    build_single_identity_changeset(file, identity, OpKind::InsertBefore { new_text })
+    build_single_identity_changeset(file, identity, OpKind::InsertBefore { new_text }, registry)
 }
 
 pub(super) fn build_insert_after_changeset(
     file: &Path,
     identity: &str,
     new_text: String,
+    registry: &ProviderRegistry,
 ) -> Result<FileChange, IdenteditError> {
-    build_single_identity_changeset(file, identity, OpKind::InsertAfter { new_text })
+    build_single_identity_changeset(file, identity, OpKind::InsertAfter { new_text }, registry)
 }
 
 fn build_single_identity_changeset(
     file: &Path,
     identity: &str,
     op: OpKind,
+    registry: &ProviderRegistry,
 ) -> Result<FileChange, IdenteditError> {
-    let context = ExecutionContext::new();
-    let handles = parse_handles_for_file_with_context(file, &context)?;
+    let context = ExecutionContext::new(registry);
+    let handles = context.parse_handles_for_file(file)?;
     let matched_handle = resolve_unique_identity_handle(file, &handles, identity)?;
 
     let target = TransformTarget::node(
@@ -99,14 +105,15 @@ fn resolve_unique_identity_handle<'a>(
 pub(super) fn build_changeset(
     file: &Path,
     instructions: Vec<TransformInstruction>,
+    registry: &ProviderRegistry,
 ) -> Result<FileChange, IdenteditError> {
-    let context = ExecutionContext::new();
+    let context = ExecutionContext::new(registry);
     let source_text = context.read_file_utf8(file)?;
     let requires_structure_parse = instructions
         .iter()
         .any(|instruction| instruction.target.requires_node_resolution());
     let handles = if requires_structure_parse {
-        parse_handles_for_source_with_context(file, source_text.as_bytes(), &context)?
+        context.parse_handles_for_source(file, source_text.as_bytes())?
     } else {
         Vec::new()
     };
@@ -149,7 +156,12 @@ fn build_changeset_with_handles(
         });
 
         operations.push(ChangeOp {
+            id: None,
+            depends_on: Vec::new(),
+            correlation_id: None,
+            group: None,
             target: canonical_target,
+            optional: false,
             op: instruction.op,
             preview: ChangePreview {
                 old_text: Some(resolved.old_text),
@@ -199,15 +211,16 @@ fn canonicalize_operation_target(
 
 pub(super) fn resolve_changeset_targets(
     changeset: &FileChange,
+    registry: &ProviderRegistry,
 ) -> Result<Vec<MatchedChange>, IdenteditError> {
-    let context = ExecutionContext::new();
+    let context = ExecutionContext::new(registry);
     let source_text = context.read_file_utf8(&changeset.file)?;
     let handles = if changeset
         .operations
         .iter()
         .any(|operation| operation.target.requires_node_resolution())
     {
-        parse_handles_for_source_with_context(&changeset.file, source_text.as_bytes(), &context)?
+        context.parse_handles_for_source(&changeset.file, source_text.as_bytes())?
     } else {
         Vec::new()
     };
@@ -222,33 +235,59 @@ pub(super) fn resolve_changeset_targets_in_handles(
     let handle_index = HandleIndex::new(handles);
     let mut matched = Vec::new();
 
-    for (index, operation) in changeset.operations.iter().enumerate() {
-        reject_move_operation(&operation.op, index)?;
-        let resolved = resolve_operation_view(
-            &changeset.file,
+    for index in 0..changeset.operations.len() {
+        matched.push(resolve_changeset_operation(
+            changeset,
             source_text,
             &handle_index,
-            &operation.target,
-            &operation.op,
             index,
-        )?;
-
-        matched.push(MatchedChange {
-            index,
-            target: operation.target.clone(),
-            op: operation.op.clone(),
-            expected_hash: resolved.expected_hash,
-            old_text: resolved.old_text,
-            matched_span: resolved.matched_span,
-            move_insert_at: resolved.move_insert_at,
-            anchor_kind: resolved.anchor_kind,
-            anchor_span: resolved.anchor_span,
-        });
+        )?);
     }
 
     Ok(matched)
 }
 
+pub(super) fn resolve_each_changeset_target_in_handles(
+    changeset: &FileChange,
+    source_text: &str,
+    handles: &[SelectionHandle],
+) -> Vec<Result<MatchedChange, IdenteditError>> {
+    let handle_index = HandleIndex::new(handles);
+    (0..changeset.operations.len())
+        .map(|index| resolve_changeset_operation(changeset, source_text, &handle_index, index))
+        .collect()
+}
+
+fn resolve_changeset_operation(
+    changeset: &FileChange,
+    source_text: &str,
+    handle_index: &HandleIndex,
+    index: usize,
+) -> Result<MatchedChange, IdenteditError> {
+    let operation = &changeset.operations[index];
+    reject_move_operation(&operation.op, index)?;
+    let resolved = resolve_operation_view(
+        &changeset.file,
+        source_text,
+        handle_index,
+        &operation.target,
+        &operation.op,
+        index,
+    )?;
+
+    Ok(MatchedChange {
+        index,
+        target: operation.target.clone(),
+        op: operation.op.clone(),
+        expected_hash: resolved.expected_hash,
+        old_text: resolved.old_text,
+        matched_span: resolved.matched_span,
+        move_insert_at: resolved.move_insert_at,
+        anchor_kind: resolved.anchor_kind,
+        anchor_span: resolved.anchor_span,
+    })
+}
+
 fn op_new_text(op: &OpKind) -> &str {
     match op {
         OpKind::Replace { new_text } => new_text,
@@ -1,10 +1,12 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fs;
 use std::path::Path;
 
 use crate::changeset::{OpKind, TransformTarget, hash_text};
 use crate::error::IdenteditError;
-use crate::handle::{SelectionHandle, Span};
-use crate::hashline::{compute_line_hash, parse_line_ref};
+use crate::handle::{LineIndex, SelectionHandle, Span};
+use crate::hashline::{HashlineAlgo, compute_line_hash_with, parse_line_ref};
 
 pub(super) struct ResolvedOperationView {
     pub(super) expected_hash: String,
@@ -102,6 +104,7 @@ pub(super) fn resolve_operation_view(
     index: usize,
 ) -> Result<ResolvedOperationView, IdenteditError> {
     validate_target_op_compatibility(target, op, index)?;
+    let target = &*resolve_position_hint(source_text.as_bytes(), target)?;
     match target {
         TransformTarget::Node { .. } => {
             if let OpKind::MoveBefore { destination } | OpKind::MoveAfter { destination } = op {
@@ -426,7 +429,61 @@ pub(super) fn resolve_target_in_handles(
     target: &TransformTarget,
 ) -> Result<SelectionHandle, IdenteditError> {
     let handle_index = HandleIndex::new(handles);
-    resolve_target_in_handles_with_index(file, &handle_index, target)
+    let target = if matches!(
+        target,
+        TransformTarget::Node {
+            position_hint: Some(_),
+            ..
+        }
+    ) {
+        let source = fs::read(file).map_err(|error| IdenteditError::io(file, error))?;
+        resolve_position_hint(&source, target)?
+    } else {
+        Cow::Borrowed(target)
+    };
+    resolve_target_in_handles_with_index(file, &handle_index, &target)
+}
+
+/// Replaces a node target's `position_hint` with the byte `span_hint` it names in `source`.
+pub(super) fn resolve_position_hint<'a>(
+    source: &[u8],
+    target: &'a TransformTarget,
+) -> Result<Cow<'a, TransformTarget>, IdenteditError> {
+    let TransformTarget::Node {
+        identity,
+        kind,
+        span_hint,
+        position_hint: Some(position_hint),
+        expected_old_hash,
+    } = target
+    else {
+        return Ok(Cow::Borrowed(target));
+    };
+
+    let lines = LineIndex::new(source);
+    let span = lines
+        .span(*position_hint)
+        .ok_or_else(|| IdenteditError::InvalidRequest {
+            message: format!(
+                "position_hint for target '{identity}' does not name a valid position in the file; give 1-based line and column (or utf16_column) inside an existing line"
+            ),
+        })?;
+    if let Some(span_hint) = span_hint
+        && *span_hint != span
+    {
+        return Err(IdenteditError::InvalidRequest {
+            message: format!(
+                "position_hint for target '{identity}' resolves to [{}, {}) but span_hint is [{}, {})",
+                span.start, span.end, span_hint.start, span_hint.end
+            ),
+        });
+    }
+    Ok(Cow::Owned(TransformTarget::node(
+        identity.clone(),
+        kind.clone(),
+        Some(span),
+        expected_old_hash.clone(),
+    )))
 }
 
 pub(super) fn resolve_target_in_handles_with_index(
@@ -440,6 +497,7 @@ pub(super) fn resolve_target_in_handles_with_index(
             kind,
             span_hint,
             expected_old_hash,
+            ..
         } => (identity, kind, *span_hint, expected_old_hash),
         TransformTarget::FileStart { .. } | TransformTarget::FileEnd { .. } => {
             return Err(IdenteditError::InvalidRequest {
@@ -692,7 +750,7 @@ fn compute_line_ranges(source_text: &str) -> Vec<LineRange> {
                     line,
                     full_start: start,
                     full_end: index + 1,
-                    expected_hash: compute_line_hash(content),
+                    expected_hash: compute_line_hash_with(content, HashlineAlgo::Blake3_24),
                 });
                 index += 1;
                 start = index;
@@ -709,7 +767,7 @@ fn compute_line_ranges(source_text: &str) -> Vec<LineRange> {
                     line,
                     full_start: start,
                     full_end: index + delimiter_len,
-                    expected_hash: compute_line_hash(content),
+                    expected_hash: compute_line_hash_with(content, HashlineAlgo::Blake3_24),
                 });
                 index += delimiter_len;
                 start = index;
@@ -727,13 +785,23 @@ fn compute_line_ranges(source_text: &str) -> Vec<LineRange> {
             line,
             full_start: start,
             full_end: source_text.len(),
-            expected_hash: compute_line_hash(content),
+            expected_hash: compute_line_hash_with(content, HashlineAlgo::Blake3_24),
         });
     }
 
     ranges
 }
 
+pub(super) fn line_spans(source_text: &str) -> Vec<Span> {
+    compute_line_ranges(source_text)
+        .into_iter()
+        .map(|range| Span {
+            start: range.full_start,
+            end: range.full_end,
+        })
+        .collect()
+}
+
 fn resolve_line_anchor(anchor: &str, ranges: &[LineRange]) -> Result<LineRange, IdenteditError> {
     let parsed = parse_line_ref(anchor).map_err(|error| IdenteditError::InvalidRequest {
         message: error.to_string(),
@@ -749,7 +817,9 @@ fn resolve_line_anchor(anchor: &str, ranges: &[LineRange]) -> Result<LineRange,
         });
     }
 
-    let range = ranges[parsed.line - 1].clone();
+    // Ranges carry the longest line hash; a shorter anchor hash is a prefix of it.
+    let mut range = ranges[parsed.line - 1].clone();
+    range.expected_hash.truncate(parsed.hash.len());
     if range.expected_hash != parsed.hash {
         return Err(IdenteditError::PreconditionFailed {
             expected_hash: parsed.hash,
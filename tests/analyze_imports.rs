@@ -0,0 +1,86 @@
+use std::fs;
+
+use serde_json::{Value, json};
+
+mod common;
+
+#[test]
+fn analyze_imports_builds_the_file_graph_and_its_reverse() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let root = workspace.path();
+    fs::create_dir_all(root.join("app")).expect("app should be created");
+    fs::create_dir_all(root.join("web")).expect("web should be created");
+    fs::write(
+        root.join("app/main.py"),
+        "import os\nfrom app import models\nfrom .service import run\n",
+    )
+    .expect("fixture should be written");
+    fs::write(
+        root.join("app/service.py"),
+        "from . import models\nimport app.models\n",
+    )
+    .expect("fixture should be written");
+    fs::write(root.join("app/models.py"), "class User:\n    pass\n")
+        .expect("fixture should be written");
+    fs::write(
+        root.join("web/index.js"),
+        "import { render } from './view';\nconst lodash = require('lodash');\n",
+    )
+    .expect("fixture should be written");
+    fs::write(root.join("web/view.js"), "export function render() {}\n")
+        .expect("fixture should be written");
+    fs::write(root.join("notes.txt"), "import nothing\n").expect("fixture should be written");
+
+    let output = common::run_identedit(&[
+        "analyze",
+        "imports",
+        root.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "analyze imports should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let graph: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+
+    let files = graph["files"].as_array().expect("files should be an array");
+    let paths = files
+        .iter()
+        .map(|file| file["file"].as_str().expect("file should be a string"))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        vec![
+            "app/main.py",
+            "app/models.py",
+            "app/service.py",
+            "web/index.js",
+            "web/view.js"
+        ]
+    );
+    assert_eq!(
+        files[0]["imports"],
+        json!([
+            {"module": "os", "line": 1},
+            {"module": "app", "line": 2, "resolved": "app/models.py"},
+            {"module": ".service", "line": 3, "resolved": "app/service.py"}
+        ])
+    );
+    assert_eq!(
+        graph["adjacency"],
+        json!({
+            "app/main.py": ["app/models.py", "app/service.py"],
+            "app/service.py": ["app/models.py"],
+            "web/index.js": ["web/view.js"]
+        })
+    );
+    assert_eq!(
+        graph["imported_by"],
+        json!({
+            "app/models.py": ["app/main.py", "app/service.py"],
+            "app/service.py": ["app/main.py"],
+            "web/view.js": ["web/index.js"]
+        })
+    );
+    assert_eq!(graph["summary"]["edges"], 4);
+}
@@ -0,0 +1,113 @@
+use std::fs;
+use std::process::Command;
+
+mod common;
+
+const SOURCE: &str = "\
+def load(path):
+    # TODO: cache parsed files
+    return open(path).read()
+
+
+def todo_list():
+    return \"TODO in a string is not a marker\"
+
+# FIXME(ada) top-level marker
+";
+
+#[test]
+fn markers_come_with_line_anchors_and_enclosing_handles() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("loader.py");
+    fs::write(&file, SOURCE).expect("fixture should be written");
+    let file_arg = file.to_str().expect("path should be utf-8");
+
+    let inventory = common::run_json(&[
+        "analyze",
+        "markers",
+        workspace.path().to_str().expect("path should be utf-8"),
+    ]);
+    let markers = inventory["markers"]
+        .as_array()
+        .expect("markers should be an array");
+    assert_eq!(markers.len(), 2);
+    assert_eq!(markers[0]["file"], file_arg);
+    assert_eq!(markers[0]["line"], 2);
+    assert_eq!(markers[0]["marker"], "TODO");
+    assert_eq!(markers[0]["target_type"], "annotation");
+    assert_eq!(markers[0]["message"], "cache parsed files");
+    assert_eq!(markers[0]["handle"]["name"], "load");
+    assert_eq!(markers[1]["marker"], "FIXME");
+    assert_eq!(markers[1]["ticket"], "ada");
+    assert_eq!(markers[1]["message"], "top-level marker");
+    assert!(markers[1].get("handle").is_none());
+    assert_eq!(inventory["summary"]["by_marker"]["TODO"], 1);
+
+    // The anchor and handle are ready-to-use patch targets.
+    let anchor = markers[0]["anchor"]
+        .as_str()
+        .expect("anchor should be a string");
+    common::run_json(&[
+        "patch",
+        file_arg,
+        "--at",
+        anchor,
+        "--set-line",
+        "    # cached by the caller",
+    ]);
+    let identity = markers[0]["handle"]["identity"]
+        .as_str()
+        .expect("identity should be a string");
+    assert!(
+        fs::read_to_string(&file)
+            .expect("file should be readable")
+            .contains("# cached by the caller")
+    );
+    let reread = common::run_json(&["read", "--json", "--kind", "function_definition", file_arg]);
+    assert_ne!(reread["handles"][0]["identity"], identity);
+}
+
+#[test]
+fn marker_words_can_be_replaced_and_blamed() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let root = workspace.path();
+    fs::write(root.join("loader.py"), SOURCE).expect("fixture should be written");
+    let git = |args: &[&str]| {
+        let status = Command::new("git")
+            .args(args)
+            .current_dir(root)
+            .env("GIT_AUTHOR_NAME", "Ada")
+            .env("GIT_AUTHOR_EMAIL", "ada@example.com")
+            .env("GIT_COMMITTER_NAME", "Ada")
+            .env("GIT_COMMITTER_EMAIL", "ada@example.com")
+            .status()
+            .expect("git should run");
+        assert!(status.success(), "git {args:?} should succeed");
+    };
+    git(&["init", "--quiet"]);
+    git(&["add", "loader.py"]);
+    git(&["commit", "--quiet", "-m", "add loader"]);
+
+    let inventory = common::run_json(&[
+        "analyze",
+        "markers",
+        "--marker",
+        "fixme",
+        "--blame",
+        root.to_str().expect("path should be utf-8"),
+    ]);
+    let markers = inventory["markers"]
+        .as_array()
+        .expect("markers should be an array");
+    assert_eq!(markers.len(), 1);
+    assert_eq!(markers[0]["marker"], "FIXME");
+    assert_eq!(markers[0]["blame"]["author"], "Ada");
+    assert_eq!(markers[0]["blame"]["author_mail"], "ada@example.com");
+    assert_eq!(
+        markers[0]["blame"]["commit"]
+            .as_str()
+            .expect("commit should be a string")
+            .len(),
+        40
+    );
+}
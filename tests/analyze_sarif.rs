@@ -0,0 +1,79 @@
+use std::fs;
+
+mod common;
+
+#[test]
+fn unused_findings_export_as_sarif_with_handle_regions() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("jobs.py");
+    fs::write(
+        &file,
+        "def run():\n    return 1\n\n\ndef _stale():\n    return 2\n",
+    )
+    .expect("fixture should be written");
+
+    let json = common::run_json(&[
+        "analyze",
+        "unused",
+        file.to_str().expect("path should be utf-8"),
+    ]);
+    let sarif = common::run_json(&[
+        "analyze",
+        "unused",
+        "--output",
+        "sarif",
+        file.to_str().expect("path should be utf-8"),
+    ]);
+    assert_eq!(sarif["version"], "2.1.0");
+    let run = &sarif["runs"][0];
+    assert_eq!(run["tool"]["driver"]["name"], "identedit");
+    assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "unused/function");
+    let result = &run["results"][0];
+    assert_eq!(result["ruleId"], "unused/function");
+    assert_eq!(result["ruleIndex"], 0);
+    assert_eq!(result["level"], "warning");
+    assert_eq!(result["message"]["text"], "Unreferenced function '_stale'");
+    assert_eq!(
+        result["partialFingerprints"]["identeditIdentity/v1"],
+        json["symbols"][0]["handle"]["identity"]
+    );
+    let location = &result["locations"][0];
+    let uri = location["physicalLocation"]["artifactLocation"]["uri"]
+        .as_str()
+        .expect("uri should be a string");
+    assert!(uri.starts_with("file://") && uri.ends_with("/jobs.py"));
+    let region = &location["physicalLocation"]["region"];
+    assert_eq!(region["startLine"], 5);
+    assert_eq!(region["startColumn"], 1);
+    assert_eq!(region["endLine"], 6);
+    assert_eq!(region["byteOffset"], 26);
+    assert_eq!(location["logicalLocations"][0]["name"], "_stale");
+}
+
+#[test]
+fn marker_findings_export_as_sarif_and_imports_refuse() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    fs::write(
+        workspace.path().join("a.py"),
+        "def load():\n    # FIXME: retry on timeout\n    return 1\n",
+    )
+    .expect("fixture should be written");
+    let dir = workspace.path().to_str().expect("path should be utf-8");
+
+    let sarif = common::run_json(&["analyze", "--output", "sarif", "markers", dir]);
+    let result = &sarif["runs"][0]["results"][0];
+    assert_eq!(result["ruleId"], "markers/fixme");
+    assert_eq!(result["level"], "note");
+    assert_eq!(result["message"]["text"], "FIXME: retry on timeout");
+    assert_eq!(
+        result["locations"][0]["physicalLocation"]["region"]["startLine"],
+        2
+    );
+    assert_eq!(
+        result["locations"][0]["logicalLocations"][0]["name"],
+        "load"
+    );
+
+    let output = common::run_identedit(&["analyze", "imports", "--output", "sarif", dir]);
+    assert!(!output.status.success(), "imports has no SARIF form");
+}
@@ -0,0 +1,83 @@
+use std::fs;
+
+mod common;
+
+#[test]
+fn unused_symbols_come_with_handles_ready_for_deletion() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("service.py");
+    fs::write(
+        &file,
+        "\
+def run(job):
+    started = job.start()
+    return _finish(job)
+
+
+def _finish(job):
+    return job.result
+
+
+def _retry(job, attempts):
+    return job.start()
+",
+    )
+    .expect("fixture should be written");
+    fs::write(workspace.path().join("notes.txt"), "def _ignored():\n")
+        .expect("fixture should be written");
+    let file_arg = file.to_str().expect("path should be utf-8");
+
+    let report = common::run_json(&[
+        "analyze",
+        "unused",
+        workspace.path().to_str().expect("path should be utf-8"),
+    ]);
+    let symbols = report["symbols"]
+        .as_array()
+        .expect("symbols should be an array");
+    let found = symbols
+        .iter()
+        .map(|symbol| {
+            (
+                symbol["name"].as_str().expect("name should be a string"),
+                symbol["category"]
+                    .as_str()
+                    .expect("category should be a string"),
+                symbol["line"].as_u64().expect("line should be a number"),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        found,
+        vec![
+            ("started", "variable", 2),
+            ("_retry", "function", 10),
+            ("attempts", "parameter", 10),
+        ]
+    );
+    assert_eq!(symbols[0]["handle"]["kind"], "expression_statement");
+    assert_eq!(symbols[1]["handle"]["file"], file_arg);
+    assert_eq!(report["summary"]["files_scanned"], 1);
+    assert_eq!(report["summary"]["by_category"]["function"], 1);
+
+    let identity = symbols[1]["handle"]["identity"]
+        .as_str()
+        .expect("identity should be a string");
+    common::run_json(&["patch", "--identity", identity, "--delete", file_arg]);
+    assert!(
+        !fs::read_to_string(&file)
+            .expect("file should be readable")
+            .contains("_retry")
+    );
+
+    let output = common::run_identedit(&[
+        "analyze",
+        "unused",
+        workspace
+            .path()
+            .join("notes.txt")
+            .to_str()
+            .expect("path should be utf-8"),
+    ]);
+    assert!(!output.status.success(), "unsupported files should fail");
+}
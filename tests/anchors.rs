@@ -0,0 +1,87 @@
+use std::fs;
+
+use serde_json::Value;
+
+mod common;
+
+const COMMITTED: &[(&str, &str)] = &[
+    ("app.py", "a = 1\nb = 2\nc = 3\nd = 4\n"),
+    ("gone.py", "x = 1\n"),
+];
+
+#[test]
+fn anchors_cover_added_lines_of_changed_and_new_files() {
+    let workspace = common::committed_repo(COMMITTED);
+    let root = workspace.path();
+    fs::write(
+        root.join("app.py"),
+        "a = 1\nB = 2\nc = 3\nd = 4\ne = 5\nf = 6\n",
+    )
+    .expect("edit");
+    fs::remove_file(root.join("gone.py")).expect("delete");
+    fs::write(root.join("new.py"), "n = 1\n").expect("create");
+    common::git(root, &["add", "--intent-to-add", "new.py"]);
+    let diff = common::git(root, &["diff"]);
+
+    let output = common::run_in_with_stdin(root, &["anchors", "--from-git-diff"], &diff);
+    assert!(
+        output.status.success(),
+        "anchors failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["summary"]["files"], 2);
+    assert_eq!(response["summary"]["ranges"], 3);
+    assert_eq!(response["summary"]["lines"], 4);
+
+    let app = &response["files"][0];
+    assert_eq!(app["file"], "app.py");
+    let ranges = app["ranges"].as_array().expect("ranges should be an array");
+    assert_eq!(ranges[0]["lines"][0]["line"], 2);
+    assert_eq!(ranges[0]["lines"][0]["text"], "B = 2");
+    assert!(ranges[0].get("end_anchor").is_none());
+    assert_eq!(ranges[1]["lines"][0]["line"], 5);
+    assert_eq!(ranges[1]["lines"][1]["line"], 6);
+    assert_eq!(ranges[1]["end_anchor"], ranges[1]["lines"][1]["anchor"]);
+    assert_eq!(response["files"][1]["file"], "new.py");
+
+    let start = ranges[1]["start_anchor"].as_str().expect("anchor");
+    let end = ranges[1]["end_anchor"].as_str().expect("anchor");
+    let patched = common::run_in_with_stdin(
+        root,
+        &[
+            "patch",
+            "--anchor",
+            start,
+            "--end-anchor",
+            end,
+            "--replace-range",
+            "e = 50",
+            "app.py",
+        ],
+        "",
+    );
+    assert!(
+        patched.status.success(),
+        "patch with diff anchors failed: {}",
+        String::from_utf8_lossy(&patched.stderr)
+    );
+    assert_eq!(
+        fs::read_to_string(root.join("app.py")).expect("app.py should be readable"),
+        "a = 1\nB = 2\nc = 3\nd = 4\ne = 50\n"
+    );
+}
+
+#[test]
+fn anchors_reject_a_diff_the_working_tree_no_longer_matches() {
+    let workspace = common::committed_repo(COMMITTED);
+    let root = workspace.path();
+    fs::write(root.join("app.py"), "a = 1\nB = 2\nc = 3\nd = 4\n").expect("edit");
+    let diff = common::git(root, &["diff"]);
+    fs::write(root.join("app.py"), "a = 1\nb = 22\nc = 3\nd = 4\n").expect("edit again");
+
+    let output = common::run_in_with_stdin(root, &["anchors", "--from-git-diff", "-"], &diff);
+    assert!(!output.status.success());
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+}
@@ -266,3 +266,17 @@ mod scenario_04_payload_validation;
 mod scenario_05_move_and_transactions;
 #[path = "apply_integration/scenario_06_permissions_and_fs_edges.rs"]
 mod scenario_06_permissions_and_fs_edges;
+#[path = "apply_integration/scenario_07_continue_on_error.rs"]
+mod scenario_07_continue_on_error;
+#[path = "apply_integration/scenario_08_optional_targets.rs"]
+mod scenario_08_optional_targets;
+#[path = "apply_integration/scenario_09_protected_regions.rs"]
+mod scenario_09_protected_regions;
+#[path = "apply_integration/scenario_10_change_guards.rs"]
+mod scenario_10_change_guards;
+#[path = "apply_integration/scenario_11_secret_scan.rs"]
+mod scenario_11_secret_scan;
+#[path = "apply_integration/scenario_12_compressed_plans.rs"]
+mod scenario_12_compressed_plans;
+#[path = "apply_integration/scenario_13_operation_groups.rs"]
+mod scenario_13_operation_groups;
@@ -28,6 +28,7 @@ fn apply_changeset_file_modifies_target_file() {
     );
     let apply_output = run_identedit(&[
         "apply",
+        "--invalidated-identities",
         changeset_file
             .path()
             .to_str()
@@ -44,6 +45,22 @@ fn apply_changeset_file_modifies_target_file() {
     assert_eq!(response["summary"]["files_modified"], 1);
     assert_eq!(response["summary"]["operations_applied"], 1);
     assert_eq!(response["summary"]["operations_failed"], 0);
+    assert_eq!(response["summary"]["lines_added"], 1);
+    assert_eq!(response["summary"]["lines_removed"], 2);
+    assert_eq!(response["summary"]["bytes_delta"], -20);
+    let file_stats = &response["summary"]["files"][0];
+    assert_eq!(
+        file_stats["file"],
+        file_path.to_str().expect("path should be utf-8")
+    );
+    assert_eq!(file_stats["identities"], json!([identity]));
+    // The edited function, the module around it, and `helper`, which moved up.
+    let invalidated = file_stats["invalidated_identities"]
+        .as_array()
+        .expect("invalidated identities should be listed");
+    let helper = select_named_handle(&file_path, "helper");
+    assert!(invalidated.contains(&json!(identity)));
+    assert!(invalidated.contains(&helper["identity"]));
 
     let modified = fs::read_to_string(&file_path).expect("modified file should be readable");
     assert!(modified.contains("return value * 2"));
@@ -2,7 +2,7 @@ use super::*;
 
 #[cfg(unix)]
 #[test]
-fn apply_noop_on_read_only_file_returns_io_error() {
+fn apply_noop_on_read_only_file_returns_read_only_target_error() {
     let file_path = copy_fixture_to_temp_python("example.py");
     fs::set_permissions(&file_path, fs::Permissions::from_mode(0o444))
         .expect("fixture should be made read-only");
@@ -14,12 +14,174 @@ fn apply_noop_on_read_only_file_returns_io_error() {
     let output = run_identedit_with_stdin(&["apply"], &request.to_string());
     assert!(
         !output.status.success(),
-        "current contract requires write-open lock even for no-op apply"
+        "current contract requires a writable target even for no-op apply"
     );
 
     let response: Value =
         serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
-    assert_eq!(response["error"]["type"], "io_error");
+    assert_eq!(response["error"]["type"], "read_only_target");
+}
+
+#[cfg(unix)]
+fn helper_replacement_plan(file_path: &Path) -> String {
+    let handle = select_named_handle(file_path, "helper");
+    let plan_output = run_identedit(&[
+        "edit",
+        "--identity",
+        handle["identity"]
+            .as_str()
+            .expect("identity should be present"),
+        "--replace",
+        "def helper():\n    return 7",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        plan_output.status.success(),
+        "edit should succeed: {}",
+        String::from_utf8_lossy(&plan_output.stdout)
+    );
+    String::from_utf8(plan_output.stdout).expect("plan should be utf-8")
+}
+
+#[cfg(unix)]
+#[test]
+fn apply_chmod_writable_edits_read_only_file() {
+    let file_path = copy_fixture_to_temp_python("example.py");
+    let plan = helper_replacement_plan(&file_path);
+    fs::set_permissions(&file_path, fs::Permissions::from_mode(0o444))
+        .expect("fixture should be made read-only");
+    let original = fs::read_to_string(&file_path).expect("file should be readable");
+
+    let refused = run_identedit_with_stdin(&["apply"], &plan);
+    assert!(
+        !refused.status.success(),
+        "read-only target should be refused"
+    );
+    let response: Value =
+        serde_json::from_slice(&refused.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["error"]["type"], "read_only_target");
+    assert!(
+        response["error"]["suggestion"]
+            .as_str()
+            .is_some_and(|suggestion| suggestion.contains("--chmod-writable"))
+    );
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        original
+    );
+
+    let output = run_identedit_with_stdin(&["apply", "--chmod-writable"], &plan);
+    assert!(
+        output.status.success(),
+        "apply should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert!(
+        fs::read_to_string(&file_path)
+            .expect("file should be readable")
+            .contains("return 7")
+    );
+    assert_eq!(file_mode(&file_path), 0o444);
+}
+
+#[cfg(unix)]
+fn file_mode(file_path: &Path) -> u32 {
+    fs::metadata(file_path)
+        .expect("metadata should be readable")
+        .permissions()
+        .mode()
+        & 0o777
+}
+
+#[cfg(unix)]
+#[test]
+fn apply_chmod_writable_dry_run_leaves_read_only_file_untouched() {
+    let file_path = copy_fixture_to_temp_python("example.py");
+    let plan = helper_replacement_plan(&file_path);
+    fs::set_permissions(&file_path, fs::Permissions::from_mode(0o444))
+        .expect("fixture should be made read-only");
+    let original = fs::read_to_string(&file_path).expect("file should be readable");
+
+    let output = run_identedit_with_stdin(&["apply", "--dry-run", "--chmod-writable"], &plan);
+    assert!(
+        output.status.success(),
+        "dry-run should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        original
+    );
+    assert_eq!(file_mode(&file_path), 0o444);
+}
+
+#[cfg(unix)]
+#[test]
+fn apply_chmod_writable_keeps_permissions_when_a_later_file_fails_preflight() {
+    let file_path = copy_fixture_to_temp_python("example.py");
+    let mut plan: Value = serde_json::from_str(&helper_replacement_plan(&file_path))
+        .expect("plan should be valid JSON");
+    let mut missing = plan["files"][0].clone();
+    missing["file"] = json!(file_path.with_file_name("missing.py").to_string_lossy());
+    plan["files"]
+        .as_array_mut()
+        .expect("files should be an array")
+        .push(missing);
+    fs::set_permissions(&file_path, fs::Permissions::from_mode(0o444))
+        .expect("fixture should be made read-only");
+    let original = fs::read_to_string(&file_path).expect("file should be readable");
+
+    let output = run_identedit_with_stdin(&["apply", "--chmod-writable"], &plan.to_string());
+    assert!(
+        !output.status.success(),
+        "missing second file should fail preflight"
+    );
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        original
+    );
+    assert_eq!(file_mode(&file_path), 0o444);
+}
+
+#[cfg(unix)]
+#[test]
+fn apply_refuses_hard_linked_target_unless_force_unlink() {
+    let file_path = copy_fixture_to_temp_python("example.py");
+    let plan = helper_replacement_plan(&file_path);
+    let directory = tempdir().expect("tempdir should be created");
+    let other_link = directory.path().join("other.py");
+    fs::hard_link(&file_path, &other_link).expect("hard link should be created");
+    let original = fs::read_to_string(&file_path).expect("file should be readable");
+
+    let refused = run_identedit_with_stdin(&["apply"], &plan);
+    assert!(
+        !refused.status.success(),
+        "hard-linked target should be refused"
+    );
+    let response: Value =
+        serde_json::from_slice(&refused.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["error"]["type"], "hard_linked_target");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("2 hard links"))
+    );
+
+    let output = run_identedit_with_stdin(&["apply", "--force-unlink"], &plan);
+    assert!(
+        output.status.success(),
+        "apply should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert!(
+        fs::read_to_string(&file_path)
+            .expect("file should be readable")
+            .contains("return 7")
+    );
+    assert_eq!(
+        fs::read_to_string(&other_link).expect("other link should be readable"),
+        original
+    );
 }
 
 #[test]
@@ -475,3 +637,166 @@ fn apply_rejects_symbolic_link_target_path() {
         "expected symlink rejection message"
     );
 }
+
+#[cfg(unix)]
+#[test]
+fn apply_normalizes_verbatim_prefixed_plan_paths_and_reports_them_plainly() {
+    let file_path = copy_fixture_to_temp_python("example.py");
+    let handle = select_named_handle(&file_path, "helper");
+    let identity = handle["identity"]
+        .as_str()
+        .expect("identity should be present");
+    let plan_output = run_identedit(&[
+        "--path-style",
+        "posix",
+        "edit",
+        "--identity",
+        identity,
+        "--replace",
+        "def helper():\n    return 7",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        plan_output.status.success(),
+        "edit should succeed: {}",
+        String::from_utf8_lossy(&plan_output.stdout)
+    );
+    let mut plan: Value =
+        serde_json::from_slice(&plan_output.stdout).expect("plan should be valid JSON");
+    assert_eq!(
+        plan["files"][0]["file"],
+        file_path.to_string_lossy().as_ref()
+    );
+    // What `canonicalize` hands back on Windows; the prefix must not leak into the apply.
+    plan["files"][0]["file"] = json!(format!("//?/{}", file_path.display()));
+
+    let output = run_identedit_with_stdin(
+        &["apply", "--verbose", "--path-style", "posix"],
+        &plan.to_string(),
+    );
+    assert!(
+        output.status.success(),
+        "apply should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(
+        response["applied"][0]["file"],
+        file_path.to_string_lossy().as_ref()
+    );
+    assert!(
+        fs::read_to_string(&file_path)
+            .expect("file should be readable")
+            .contains("return 7")
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn apply_honors_every_durability_level_and_rejects_unknown_ones() {
+    for (level, replacement) in [
+        ("none", "return 1"),
+        ("file", "return 2"),
+        ("dir", "return 3"),
+    ] {
+        let file_path = copy_fixture_to_temp_python("example.py");
+        let plan = helper_replacement_plan(&file_path).replace("return 7", replacement);
+        let output = run_identedit_with_stdin(&["--durability", level, "apply"], &plan);
+        assert!(
+            output.status.success(),
+            "apply with --durability {level} should succeed: {}",
+            String::from_utf8_lossy(&output.stdout)
+        );
+        assert!(
+            fs::read_to_string(&file_path)
+                .expect("file should be readable")
+                .contains(replacement)
+        );
+    }
+
+    let output = run_identedit(&["--durability", "paranoid", "apply", "missing.json"]);
+    assert!(
+        !output.status.success(),
+        "unknown durability level should be rejected"
+    );
+}
+
+#[test]
+fn apply_streams_large_files_and_rolls_them_back_through_inverse_splices() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let large = workspace.path().join("a_large.txt");
+    let small = workspace.path().join("b_small.txt");
+    let filler = "0123456789abcdefghijklmnopqrstuvwxyz0123456789abcdefghijklmnop\n".repeat(300_000);
+    let before_large = format!("first\n{filler}last\n");
+    let last_line = before_large.lines().count();
+    fs::write(&large, &before_large).expect("large fixture should be written");
+    fs::write(&small, "uno\ndos\n").expect("small fixture should be written");
+
+    let anchor = |line: usize, text: &str| {
+        identedit::hashline::format_line_ref(line, &identedit::hashline::compute_line_hash(text))
+    };
+    let request = json!({
+        "command": "edit",
+        "files": [
+            {
+                "file": large.to_string_lossy(),
+                "operations": [
+                    {
+                        "target": {"type": "line", "anchor": anchor(1, "first")},
+                        "op": {"type": "set_line", "new_text": "FIRST LINE\n"}
+                    },
+                    {
+                        "target": {"type": "line", "anchor": anchor(last_line, "last")},
+                        "op": {"type": "set_line", "new_text": "LAST\n"}
+                    }
+                ]
+            },
+            {
+                "file": small.to_string_lossy(),
+                "operations": [
+                    {
+                        "target": {"type": "line", "anchor": anchor(2, "dos")},
+                        "op": {"type": "set_line", "new_text": "DOS\n"}
+                    }
+                ]
+            }
+        ]
+    });
+    let plan = run_identedit_with_stdin(&["edit", "--json"], &request.to_string());
+    assert!(
+        plan.status.success(),
+        "edit should build plan: {}",
+        String::from_utf8_lossy(&plan.stdout)
+    );
+
+    let rehearsal = run_identedit_with_raw_stdin_and_env(
+        &["apply", "--inject-failure-after-writes", "1"],
+        &plan.stdout,
+        &[("IDENTEDIT_EXPERIMENTAL", "1")],
+    );
+    assert!(
+        !rehearsal.status.success(),
+        "injected failure should roll back the streamed write"
+    );
+    assert!(
+        fs::read_to_string(&large).expect("large file should be readable") == before_large,
+        "rollback should restore the large file byte for byte"
+    );
+
+    let output = run_identedit_with_raw_stdin(&["apply"], &plan.stdout);
+    assert!(
+        output.status.success(),
+        "apply should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let after_large = fs::read_to_string(&large).expect("large file should be readable");
+    assert!(
+        after_large == format!("FIRST LINE\n{filler}LAST\n"),
+        "streamed rewrite should splice only the edited lines"
+    );
+    assert_eq!(
+        fs::read_to_string(&small).expect("small file should be readable"),
+        "uno\nDOS\n"
+    );
+}
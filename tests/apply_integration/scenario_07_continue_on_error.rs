@@ -0,0 +1,360 @@
+use super::*;
+
+fn line_anchor(file: &Path, line: usize) -> String {
+    let output = run_identedit(&[
+        "read",
+        "--mode",
+        "line",
+        "--json",
+        file.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(output.status.success(), "line read should succeed");
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    response["handles"][line - 1]["anchor"]
+        .as_str()
+        .expect("anchor should be string")
+        .to_string()
+}
+
+fn build_plan(request: &Value) -> Vec<u8> {
+    let output = run_identedit_with_stdin(&["edit", "--json"], &request.to_string());
+    assert!(
+        output.status.success(),
+        "edit should build plan: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    output.stdout
+}
+
+#[test]
+fn apply_continue_on_error_commits_operations_that_still_resolve() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let first = workspace.path().join("first.txt");
+    let second = workspace.path().join("second.txt");
+    fs::write(&first, "one\ntwo\nthree\n").expect("fixture should be written");
+    fs::write(&second, "uno\ndos\n").expect("fixture should be written");
+
+    let request = json!({
+        "command": "edit",
+        "files": [
+            {
+                "file": first.to_string_lossy(),
+                "operations": [
+                    {
+                        "target": {"type": "line", "anchor": line_anchor(&first, 1)},
+                        "op": {"type": "set_line", "new_text": "ONE\n"}
+                    },
+                    {
+                        "target": {"type": "line", "anchor": line_anchor(&first, 3)},
+                        "op": {"type": "set_line", "new_text": "THREE\n"}
+                    }
+                ]
+            },
+            {
+                "file": second.to_string_lossy(),
+                "operations": [
+                    {
+                        "target": {"type": "line", "anchor": line_anchor(&second, 2)},
+                        "op": {"type": "set_line", "new_text": "DOS\n"}
+                    }
+                ]
+            }
+        ]
+    });
+    let plan = build_plan(&request);
+    fs::write(&first, "one\ntwo\n3\n").expect("fixture should be rewritten");
+
+    let output = run_identedit_with_raw_stdin(&["apply"], &plan);
+    assert!(!output.status.success(), "default mode should stay atomic");
+    assert_eq!(
+        fs::read_to_string(&second).expect("second should be readable"),
+        "uno\ndos\n"
+    );
+
+    let output =
+        run_identedit_with_raw_stdin(&["apply", "--continue-on-error", "--verbose"], &plan);
+    assert!(
+        output.status.success(),
+        "continue-on-error apply should report per-operation results: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["transaction"]["mode"], "continue_on_error");
+    assert_eq!(response["transaction"]["status"], "partial");
+    assert_eq!(response["summary"]["operations_applied"], 2);
+    assert_eq!(response["summary"]["operations_failed"], 1);
+    assert_eq!(
+        response["operations"]["applied"].as_array().map(Vec::len),
+        Some(2)
+    );
+    assert_eq!(response["operations"]["skipped"], json!([]));
+    let failed = &response["operations"]["failed"];
+    assert_eq!(failed.as_array().map(Vec::len), Some(1));
+    assert_eq!(failed[0]["file"], first.to_string_lossy().as_ref());
+    assert_eq!(failed[0]["operation"], 1);
+    assert_eq!(failed[0]["error"]["type"], "precondition_failed");
+    assert_eq!(response["applied"][0]["operations_applied"], 1);
+
+    assert_eq!(
+        fs::read_to_string(&first).expect("first should be readable"),
+        "ONE\ntwo\n3\n"
+    );
+    assert_eq!(
+        fs::read_to_string(&second).expect("second should be readable"),
+        "uno\nDOS\n"
+    );
+}
+
+#[test]
+fn apply_continue_on_error_skips_source_delete_when_cross_file_move_insert_fails() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let source_file = workspace.path().join("source.py");
+    let destination_file = workspace.path().join("destination.py");
+    fs::write(
+        &source_file,
+        "def source_fn():\n    return 1\n\n\ndef keep_fn():\n    return 2\n",
+    )
+    .expect("source fixture write should succeed");
+    fs::write(
+        &destination_file,
+        "def destination_anchor(value):\n    return value * 2\n",
+    )
+    .expect("destination fixture write should succeed");
+
+    let source_handle = select_first_handle(&source_file, "function_definition", Some("source_fn"));
+    let destination_handle = select_first_handle(
+        &destination_file,
+        "function_definition",
+        Some("destination_anchor"),
+    );
+    let request = json!({
+        "command": "edit",
+        "file": source_file.to_string_lossy(),
+        "operations": [
+            {
+                "target": {
+                    "identity": source_handle["identity"],
+                    "kind": source_handle["kind"],
+                    "span_hint": source_handle["span"],
+                    "expected_old_hash": identedit::changeset::hash_text(
+                        source_handle["text"].as_str().expect("source text should be present")
+                    )
+                },
+                "op": {
+                    "type": "move_to_after",
+                    "destination_file": destination_file.to_string_lossy(),
+                    "destination": {
+                        "identity": destination_handle["identity"],
+                        "kind": destination_handle["kind"],
+                        "span_hint": destination_handle["span"],
+                        "expected_old_hash": identedit::changeset::hash_text(
+                            destination_handle["text"].as_str().expect("destination text should be present")
+                        )
+                    }
+                }
+            }
+        ]
+    });
+    let plan = build_plan(&request);
+    let before_source = fs::read_to_string(&source_file).expect("source should be readable");
+    fs::write(
+        &destination_file,
+        "def destination_anchor(value):\n    return value * 3\n",
+    )
+    .expect("destination fixture should be rewritten");
+
+    let output = run_identedit_with_raw_stdin(&["apply", "--continue-on-error"], &plan);
+    assert!(output.status.success(), "partial apply should succeed");
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["operations"]["applied"], json!([]));
+    assert_eq!(
+        response["operations"]["failed"][0]["file"],
+        destination_file.to_string_lossy().as_ref()
+    );
+    let skipped = &response["operations"]["skipped"];
+    assert_eq!(skipped[0]["file"], source_file.to_string_lossy().as_ref());
+    assert_eq!(
+        skipped[0]["depends_on"]["file"],
+        destination_file.to_string_lossy().as_ref()
+    );
+    assert_eq!(
+        fs::read_to_string(&source_file).expect("source should be readable"),
+        before_source,
+        "moved function must not be deleted when its insert failed"
+    );
+}
+
+#[test]
+fn apply_continue_on_error_reads_mode_from_plan_and_rejects_failure_injection() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("notes.txt");
+    fs::write(&file, "alpha\nbeta\n").expect("fixture should be written");
+    let request = json!({
+        "command": "edit",
+        "file": file.to_string_lossy(),
+        "operations": [
+            {
+                "target": {"type": "line", "anchor": line_anchor(&file, 2)},
+                "op": {"type": "set_line", "new_text": "BETA\n"}
+            }
+        ]
+    });
+    let mut plan: Value = serde_json::from_slice(&build_plan(&request)).expect("plan is JSON");
+    plan["transaction"]["mode"] = json!("continue_on_error");
+
+    let output = run_identedit_with_raw_stdin_and_env(
+        &["apply", "--inject-failure-after-writes", "1"],
+        plan.to_string().as_bytes(),
+        &[("IDENTEDIT_EXPERIMENTAL", "1")],
+    );
+    assert!(!output.status.success(), "injection should be rejected");
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("continue_on_error")),
+        "rejection should name the mode"
+    );
+
+    let output = run_identedit_with_raw_stdin(&["apply"], plan.to_string().as_bytes());
+    assert!(output.status.success(), "plan mode should be honored");
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["transaction"]["mode"], "continue_on_error");
+    assert_eq!(response["transaction"]["status"], "committed");
+    assert_eq!(
+        fs::read_to_string(&file).expect("file should be readable"),
+        "alpha\nBETA\n"
+    );
+}
+
+#[test]
+fn apply_continue_on_error_skips_operations_declared_dependent_on_a_failure() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let module = workspace.path().join("module.txt");
+    let caller = workspace.path().join("caller.txt");
+    fs::write(&module, "helper\nother\n").expect("fixture should be written");
+    fs::write(&caller, "import old\nbody\n").expect("fixture should be written");
+
+    let request = json!({
+        "command": "edit",
+        "files": [
+            {
+                "file": caller.to_string_lossy(),
+                "operations": [
+                    {
+                        "target": {"type": "line", "anchor": line_anchor(&caller, 1)},
+                        "op": {"type": "set_line", "new_text": "import helper\n"}
+                    },
+                    {
+                        "target": {"type": "line", "anchor": line_anchor(&caller, 2)},
+                        "op": {"type": "set_line", "new_text": "BODY\n"}
+                    }
+                ]
+            },
+            {
+                "file": module.to_string_lossy(),
+                "operations": [
+                    {
+                        "target": {"type": "line", "anchor": line_anchor(&module, 1)},
+                        "op": {"type": "set_line", "new_text": "def helper\n"}
+                    }
+                ]
+            }
+        ]
+    });
+    let mut plan: Value = serde_json::from_slice(&build_plan(&request)).expect("plan is JSON");
+    plan["files"][1]["operations"][0]["id"] = json!("define-helper");
+    plan["files"][0]["operations"][0]["depends_on"] = json!(["define-helper"]);
+    fs::write(&module, "HELPER\nother\n").expect("fixture should be rewritten");
+
+    let output = run_identedit_with_raw_stdin(
+        &["apply", "--continue-on-error"],
+        plan.to_string().as_bytes(),
+    );
+    assert!(output.status.success(), "partial apply should succeed");
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["transaction"]["status"], "partial");
+    assert_eq!(
+        response["operations"]["applied"],
+        json!([{"file": caller.to_string_lossy(), "operation": 1}])
+    );
+    assert_eq!(
+        response["operations"]["skipped"],
+        json!([{
+            "file": caller.to_string_lossy(),
+            "operation": 0,
+            "depends_on": {
+                "file": module.to_string_lossy(),
+                "operation": 0,
+                "id": "define-helper"
+            }
+        }])
+    );
+    assert_eq!(
+        fs::read_to_string(&caller).expect("caller should be readable"),
+        "import old\nBODY\n",
+        "the import must not land without the definition it refers to"
+    );
+}
+
+#[test]
+fn apply_rejects_unknown_and_cyclic_operation_dependencies() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("notes.txt");
+    fs::write(&file, "alpha\nbeta\n").expect("fixture should be written");
+    let request = json!({
+        "command": "edit",
+        "file": file.to_string_lossy(),
+        "operations": [
+            {
+                "target": {"type": "line", "anchor": line_anchor(&file, 1)},
+                "op": {"type": "set_line", "new_text": "ALPHA\n"}
+            },
+            {
+                "target": {"type": "line", "anchor": line_anchor(&file, 2)},
+                "op": {"type": "set_line", "new_text": "BETA\n"}
+            }
+        ]
+    });
+    let mut plan: Value = serde_json::from_slice(&build_plan(&request)).expect("plan is JSON");
+    plan["files"][0]["operations"][0]["id"] = json!("first");
+    plan["files"][0]["operations"][1]["id"] = json!("second");
+    plan["files"][0]["operations"][1]["depends_on"] = json!(["missing"]);
+
+    let output = run_identedit_with_raw_stdin(&["apply"], plan.to_string().as_bytes());
+    assert!(!output.status.success(), "unknown dependency should fail");
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("unknown operation id 'missing'")),
+        "unknown id should be named"
+    );
+
+    plan["files"][0]["operations"][0]["depends_on"] = json!(["second"]);
+    plan["files"][0]["operations"][1]["depends_on"] = json!(["first"]);
+    for args in [&["apply"][..], &["apply", "--continue-on-error"][..]] {
+        let output = run_identedit_with_raw_stdin(args, plan.to_string().as_bytes());
+        assert!(!output.status.success(), "cycle should fail for {args:?}");
+        let response: Value =
+            serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+        assert!(
+            response["error"]["message"]
+                .as_str()
+                .is_some_and(|message| message.contains("'first' -> 'second' -> 'first'")),
+            "cycle should be spelled out: {response}"
+        );
+    }
+    assert_eq!(
+        fs::read_to_string(&file).expect("file should be readable"),
+        "alpha\nbeta\n"
+    );
+}
@@ -0,0 +1,141 @@
+use super::*;
+
+/// A plan deleting `legacy` from both files, with every target marked optional, after
+/// `second` lost its copy some other way.
+fn cleanup_plan(first: &Path, second: &Path) -> Value {
+    let files = [first, second].map(|file| {
+        let handle = select_named_handle(file, "legacy");
+        json!({
+            "file": file.to_string_lossy(),
+            "operations": [{
+                "target": {
+                    "identity": handle["identity"],
+                    "kind": handle["kind"],
+                    "expected_old_hash": handle["expected_old_hash"]
+                },
+                "op": {"type": "delete"}
+            }]
+        })
+    });
+    let request = json!({"command": "edit", "files": files});
+    let output = run_identedit_with_stdin(&["edit", "--json"], &request.to_string());
+    assert!(
+        output.status.success(),
+        "edit should build plan: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let mut plan: Value = serde_json::from_slice(&output.stdout).expect("plan is JSON");
+    fs::write(second, "def keep():\n    return 2\n").expect("fixture should be rewritten");
+    for file in plan["files"]
+        .as_array_mut()
+        .expect("files should be an array")
+    {
+        file["operations"][0]["target"]["optional"] = json!(true);
+    }
+    plan
+}
+
+fn write_cleanup_fixtures(workspace: &Path) -> (PathBuf, PathBuf) {
+    let first = workspace.join("first.py");
+    let second = workspace.join("second.py");
+    let source = "def legacy():\n    return 1\n\n\ndef keep():\n    return 2\n";
+    fs::write(&first, source).expect("fixture should be written");
+    fs::write(&second, source).expect("fixture should be written");
+    (first, second)
+}
+
+#[test]
+fn apply_records_missing_optional_targets_as_ignored_no_ops() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let (first, second) = write_cleanup_fixtures(workspace.path());
+    let mut plan = cleanup_plan(&first, &second);
+
+    let output = run_identedit_with_raw_stdin(&["verify"], plan.to_string().as_bytes());
+    assert!(
+        output.status.success(),
+        "verify should pass: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["applicable"], true);
+    assert_eq!(response["summary"]["target_missing_ignored"], 1);
+    assert_eq!(
+        response["operations"][1]["status"],
+        "target_missing_ignored"
+    );
+
+    let output = run_identedit_with_raw_stdin(&["apply"], plan.to_string().as_bytes());
+    assert!(
+        output.status.success(),
+        "apply should ignore the missing optional target: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["summary"]["operations_applied"], 1);
+    assert_eq!(response["summary"]["operations_failed"], 0);
+    assert_eq!(
+        response["targets_missing_ignored"],
+        json!([{"file": second.to_string_lossy(), "operation": 0}])
+    );
+    assert_eq!(
+        fs::read_to_string(&first).expect("first should be readable"),
+        "\n\n\ndef keep():\n    return 2\n"
+    );
+    assert_eq!(
+        fs::read_to_string(&second).expect("second should be readable"),
+        "def keep():\n    return 2\n"
+    );
+
+    let (first, second) = write_cleanup_fixtures(workspace.path());
+    fs::write(&second, "def keep():\n    return 2\n").expect("fixture should be rewritten");
+    plan["files"][1]["operations"][0]["target"]
+        .as_object_mut()
+        .expect("target should be an object")
+        .remove("optional");
+    let output = run_identedit_with_raw_stdin(&["apply"], plan.to_string().as_bytes());
+    assert!(
+        !output.status.success(),
+        "a required target must still fail"
+    );
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["error"]["type"], "target_missing");
+    assert!(
+        fs::read_to_string(&first)
+            .expect("first should be readable")
+            .starts_with("def legacy")
+    );
+}
+
+#[test]
+fn apply_ignores_missing_files_whose_operations_are_all_optional() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let (first, second) = write_cleanup_fixtures(workspace.path());
+    let plan = cleanup_plan(&first, &second);
+    fs::remove_file(&second).expect("second should be removed");
+
+    for arguments in [vec!["apply", "--continue-on-error"], vec!["apply"]] {
+        fs::write(
+            &first,
+            "def legacy():\n    return 1\n\n\ndef keep():\n    return 2\n",
+        )
+        .expect("fixture should be rewritten");
+        let output = run_identedit_with_raw_stdin(&arguments, plan.to_string().as_bytes());
+        assert!(
+            output.status.success(),
+            "{arguments:?} should ignore the missing file: {}",
+            String::from_utf8_lossy(&output.stdout)
+        );
+        let response: Value =
+            serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+        assert_eq!(response["transaction"]["status"], "committed");
+        assert_eq!(response["summary"]["operations_failed"], 0);
+        assert_eq!(
+            response["targets_missing_ignored"][0]["file"],
+            second.to_string_lossy().as_ref()
+        );
+        assert!(!second.exists(), "a missing file must not be created");
+    }
+}
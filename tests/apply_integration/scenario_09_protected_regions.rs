@@ -0,0 +1,102 @@
+use super::*;
+
+/// A plan deleting the function `name` from `file`.
+fn delete_plan(file: &Path, name: &str) -> Value {
+    let handle = select_named_handle(file, name);
+    let request = json!({
+        "command": "edit",
+        "file": file.to_string_lossy(),
+        "operations": [{
+            "target": {
+                "identity": handle["identity"],
+                "kind": handle["kind"],
+                "expected_old_hash": handle["expected_old_hash"]
+            },
+            "op": {"type": "delete"}
+        }]
+    });
+    let output = run_identedit_with_stdin(&["edit", "--json"], &request.to_string());
+    assert!(
+        output.status.success(),
+        "edit should build plan: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    serde_json::from_slice(&output.stdout).expect("plan is JSON")
+}
+
+#[test]
+fn apply_refuses_edits_inside_marked_protected_regions() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("guards.py");
+    let source = "def legacy():\n    return 1\n\n\n# identedit:protected-begin\ndef check_token(token):\n    return token.valid\n# identedit:protected-end\n";
+    fs::write(&file, source).expect("fixture should be written");
+
+    let plan = delete_plan(&file, "check_token");
+    let output = run_identedit_with_stdin(&["apply"], &plan.to_string());
+    assert!(!output.status.success(), "a protected edit must fail");
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["error"]["type"], "protected_region");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("lines 5-8")),
+        "unexpected error: {response}"
+    );
+    assert_eq!(
+        fs::read_to_string(&file).expect("file should be readable"),
+        source
+    );
+
+    let output = run_identedit_with_raw_stdin(&["verify"], plan.to_string().as_bytes());
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["applicable"], false);
+    assert_eq!(response["operations"][0]["status"], "protected");
+
+    let plan = delete_plan(&file, "legacy");
+    let output = run_identedit_with_stdin(&["apply"], &plan.to_string());
+    assert!(
+        output.status.success(),
+        "an edit outside the region should apply: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert!(
+        fs::read_to_string(&file)
+            .expect("file should be readable")
+            .ends_with("# identedit:protected-begin\ndef check_token(token):\n    return token.valid\n# identedit:protected-end\n")
+    );
+}
+
+#[test]
+fn apply_refuses_edits_to_lines_protected_by_the_project_config() {
+    let workspace = tempdir().expect("tempdir should be created");
+    fs::create_dir(workspace.path().join(".identedit")).expect("config dir should be created");
+    fs::write(
+        workspace.path().join(".identedit/config.json"),
+        r#"{"protected_regions": [{"path": "*.py", "lines": [1, 2]}]}"#,
+    )
+    .expect("config should be written");
+    let file = workspace.path().join("licensed.py");
+    let source = "def license():\n    return \"MIT\"\n\n\ndef body():\n    return 1\n";
+    fs::write(&file, source).expect("fixture should be written");
+
+    let plan = delete_plan(&file, "license");
+    let output = run_identedit_with_stdin_in_dir(workspace.path(), &["apply"], &plan.to_string());
+    assert!(!output.status.success(), "a protected edit must fail");
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["error"]["type"], "protected_region");
+    assert_eq!(
+        fs::read_to_string(&file).expect("file should be readable"),
+        source
+    );
+
+    let plan = delete_plan(&file, "body");
+    let output = run_identedit_with_stdin_in_dir(workspace.path(), &["apply"], &plan.to_string());
+    assert!(
+        output.status.success(),
+        "an edit below the protected lines should apply: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+}
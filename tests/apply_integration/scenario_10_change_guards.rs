@@ -0,0 +1,123 @@
+use super::*;
+
+/// A module with a 25-line `build` function and a short `keep` function.
+fn write_module(file: &Path) -> String {
+    let body = (0..23)
+        .map(|index| format!("    step_{index} = {index}\n"))
+        .collect::<String>();
+    let source = format!("def build():\n{body}    return 0\n\n\ndef keep():\n    return 1\n");
+    fs::write(file, &source).expect("fixture should be written");
+    source
+}
+
+/// A plan replacing `build` with a stub, as a truncated agent replacement would.
+fn stub_plan(file: &Path) -> Value {
+    let handle = select_named_handle(file, "build");
+    let request = json!({
+        "command": "edit",
+        "file": file.to_string_lossy(),
+        "operations": [{
+            "target": {
+                "identity": handle["identity"],
+                "kind": handle["kind"],
+                "expected_old_hash": handle["expected_old_hash"]
+            },
+            "op": {"type": "replace", "new_text": "def build():\n    pass"}
+        }]
+    });
+    let output = run_identedit_with_stdin(&["edit", "--json"], &request.to_string());
+    assert!(
+        output.status.success(),
+        "edit should build plan: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    serde_json::from_slice(&output.stdout).expect("plan is JSON")
+}
+
+#[test]
+fn apply_stops_edits_that_change_most_of_a_file_unless_forced() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("pipeline.py");
+    let source = write_module(&file);
+    let plan = stub_plan(&file);
+
+    let output = run_identedit_with_stdin(&["apply"], &plan.to_string());
+    assert!(!output.status.success(), "a destructive edit must fail");
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["error"]["type"], "destructive_change");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("max_file_fraction_changed is 0.8")),
+        "unexpected error: {response}"
+    );
+    assert_eq!(
+        fs::read_to_string(&file).expect("file should be readable"),
+        source
+    );
+
+    let output = run_identedit_with_stdin(&["apply", "--force"], &plan.to_string());
+    assert!(
+        output.status.success(),
+        "--force should apply the edit: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert!(
+        fs::read_to_string(&file)
+            .expect("file should be readable")
+            .starts_with("def build():\n    pass\n")
+    );
+}
+
+#[test]
+fn apply_enforces_max_deleted_lines_per_op_from_the_project_config() {
+    let workspace = tempdir().expect("tempdir should be created");
+    fs::create_dir(workspace.path().join(".identedit")).expect("config dir should be created");
+    fs::write(
+        workspace.path().join(".identedit/config.json"),
+        r#"{"guards": {"max_deleted_lines_per_op": 10, "max_file_fraction_changed": null}}"#,
+    )
+    .expect("config should be written");
+    let file = workspace.path().join("pipeline.py");
+    write_module(&file);
+    let plan = stub_plan(&file);
+
+    let output = run_identedit_with_stdin_in_dir(workspace.path(), &["apply"], &plan.to_string());
+    assert!(!output.status.success(), "a destructive edit must fail");
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["error"]["type"], "destructive_change");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("deletes 23 lines")),
+        "unexpected error: {response}"
+    );
+}
+
+#[test]
+fn whole_file_writes_are_exempt_from_the_fraction_guard() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("pipeline.py");
+    let source = write_module(&file);
+    let file_hash = identedit::changeset::hash_text(&source);
+
+    let output = run_identedit(&[
+        "patch",
+        "--write-file",
+        "def build():\n    pass\n",
+        "--expected-file-hash",
+        &file_hash,
+        file.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "a whole-file write should not need --force: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert_eq!(
+        fs::read_to_string(&file).expect("file should be readable"),
+        "def build():\n    pass\n"
+    );
+}
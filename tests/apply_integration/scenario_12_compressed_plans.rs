@@ -0,0 +1,92 @@
+use super::*;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use identedit::compression::decompress;
+
+/// A plan rewriting `keep` in `file` to return 2.
+fn rewrite_plan(file: &Path) -> String {
+    let handle = select_named_handle(file, "keep");
+    let request = json!({
+        "command": "edit",
+        "file": file.to_string_lossy(),
+        "operations": [{
+            "target": {
+                "identity": handle["identity"],
+                "kind": handle["kind"],
+                "expected_old_hash": handle["expected_old_hash"]
+            },
+            "op": {"type": "replace", "new_text": "def keep():\n    return 2"}
+        }]
+    });
+    let output = run_identedit_with_stdin(&["edit", "--json"], &request.to_string());
+    assert!(
+        output.status.success(),
+        "edit should build plan: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    String::from_utf8(output.stdout).expect("plan is UTF-8")
+}
+
+#[test]
+fn apply_reads_gzip_plans_from_stdin_and_compresses_the_response() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("keep.py");
+    fs::write(&file, "def keep():\n    return 1\n").expect("fixture should be written");
+    let plan = rewrite_plan(&file);
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(plan.as_bytes())
+        .expect("plan should compress");
+    let compressed = encoder.finish().expect("plan should compress");
+
+    let output = run_identedit_with_raw_stdin(&["apply", "--accept-encoding", "zstd"], &compressed);
+    assert!(
+        output.status.success(),
+        "compressed plan should apply: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let envelope: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(envelope["encoding"], "zstd");
+    let body = STANDARD
+        .decode(envelope["body"].as_str().expect("body should be a string"))
+        .expect("body should be base64");
+    let response: Value = serde_json::from_slice(&decompress(body).expect("body decodes"))
+        .expect("body should be a JSON response");
+    assert_eq!(response["summary"]["files_modified"], 1);
+    assert_eq!(
+        fs::read_to_string(&file).expect("file should be readable"),
+        "def keep():\n    return 2\n"
+    );
+}
+
+#[test]
+fn apply_reads_zstd_plan_files_and_rejects_corrupt_ones() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("keep.py");
+    fs::write(&file, "def keep():\n    return 1\n").expect("fixture should be written");
+    let plan = rewrite_plan(&file);
+
+    let plan_path = workspace.path().join("plan.json.zst");
+    let compressed = ruzstd::encoding::compress_to_vec(
+        plan.as_bytes(),
+        ruzstd::encoding::CompressionLevel::Fastest,
+    );
+    fs::write(&plan_path, &compressed).expect("plan should be written");
+    let output = run_identedit(&["apply", plan_path.to_str().expect("utf-8 path")]);
+    assert!(
+        output.status.success(),
+        "compressed plan file should apply: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    fs::write(&plan_path, &compressed[..compressed.len() / 2]).expect("plan should be written");
+    let output = run_identedit(&["apply", plan_path.to_str().expect("utf-8 path")]);
+    assert!(!output.status.success(), "a truncated plan must be refused");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("errors stay plain JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+}
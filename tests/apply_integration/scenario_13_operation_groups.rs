@@ -0,0 +1,97 @@
+use super::*;
+
+const SOURCE: &str =
+    "def load():\n    return 1\n\n\ndef save():\n    return 2\n\n\ndef docs():\n    return 3\n";
+
+/// A plan making each of `load`, `save`, and `docs` return 0, with the first two in the
+/// "rename" group and the last in the "docs" group.
+fn grouped_plan(file: &Path) -> Value {
+    let operations = ["load", "save", "docs"]
+        .iter()
+        .map(|name| {
+            let handle = select_named_handle(file, name);
+            json!({
+                "target": {
+                    "identity": handle["identity"],
+                    "kind": handle["kind"],
+                    "expected_old_hash": handle["expected_old_hash"]
+                },
+                "op": {"type": "replace", "new_text": format!("def {name}():\n    return 0")}
+            })
+        })
+        .collect::<Vec<_>>();
+    let request = json!({
+        "command": "edit",
+        "file": file.to_string_lossy(),
+        "operations": operations
+    });
+    let output = run_identedit_with_stdin(&["edit", "--json"], &request.to_string());
+    assert!(
+        output.status.success(),
+        "edit should build plan: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let mut plan: Value = serde_json::from_slice(&output.stdout).expect("plan is JSON");
+    for (operation, group) in ["rename", "rename", "docs"].iter().enumerate() {
+        plan["files"][0]["operations"][operation]["group"] = json!(group);
+    }
+    plan
+}
+
+#[test]
+fn apply_summary_counts_results_per_group() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("io.py");
+    fs::write(&file, SOURCE).expect("fixture should be written");
+    let mut plan = grouped_plan(&file);
+    plan["files"][0]["operations"][2]["target"]["expected_old_hash"] = json!("0000000000000000");
+
+    let output = run_identedit_with_stdin(&["apply", "--continue-on-error"], &plan.to_string());
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(
+        response["summary"]["groups"],
+        json!({
+            "docs": {"applied": 0, "failed": 1, "skipped": 0},
+            "rename": {"applied": 2, "failed": 0, "skipped": 0}
+        })
+    );
+    assert_eq!(response["operations"]["failed"][0]["group"], "docs");
+    assert_eq!(
+        fs::read_to_string(&file).expect("file should be readable"),
+        SOURCE
+            .replace("return 1", "return 0")
+            .replace("return 2", "return 0")
+    );
+}
+
+#[test]
+fn apply_group_filter_applies_one_part_of_a_plan() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("io.py");
+    fs::write(&file, SOURCE).expect("fixture should be written");
+    let plan = grouped_plan(&file);
+
+    let output = run_identedit_with_stdin(&["apply", "--group", "docs"], &plan.to_string());
+    assert!(
+        output.status.success(),
+        "the docs group should apply: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(
+        response["summary"]["groups"],
+        json!({"docs": {"applied": 1, "failed": 0, "skipped": 0}})
+    );
+    assert_eq!(
+        fs::read_to_string(&file).expect("file should be readable"),
+        SOURCE.replace("return 3", "return 0")
+    );
+
+    let output = run_identedit_with_stdin(&["apply", "--group", "tests"], &plan.to_string());
+    assert!(!output.status.success(), "an unknown group must be refused");
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+}
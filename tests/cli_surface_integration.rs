@@ -264,6 +264,58 @@ fn apply_repair_remaps_stale_line_anchors() {
     assert!(updated.contains("result = value + 123"));
 }
 
+#[test]
+fn apply_repair_uses_the_planned_range_to_pick_among_duplicate_lines() {
+    let source = "x = 1\ny = 2\nx = 1\nz = 3\n";
+    let file = std::env::temp_dir().join(format!(
+        "identedit-repair-duplicates-{}.txt",
+        std::process::id()
+    ));
+    fs::write(&file, source).expect("fixture write should succeed");
+    let line_ref = |line: usize, text: &str| {
+        format!("{line}:{}", identedit::hashline::compute_line_hash(text))
+    };
+    let plan_for = |target: Value| {
+        let request = json!({
+            "command": "edit",
+            "file": file.to_string_lossy().to_string(),
+            "operations": [{"target": target, "op": {"type": "replace", "new_text": "w = 0\n"}}]
+        });
+        let output = run_identedit_with_stdin(&["edit", "--json"], &request.to_string());
+        assert!(
+            output.status.success(),
+            "edit json should succeed: {}",
+            String::from_utf8_lossy(&output.stdout)
+        );
+        String::from_utf8(output.stdout).expect("stdout should be utf-8")
+    };
+    let single_line = plan_for(json!({"type": "line", "anchor": line_ref(3, "x = 1")}));
+    let range = plan_for(json!({
+        "type": "line",
+        "anchor": line_ref(3, "x = 1"),
+        "end_anchor": line_ref(4, "z = 3")
+    }));
+
+    fs::write(&file, format!("# header\n{source}")).expect("file rewrite should succeed");
+    let output = run_identedit_with_stdin(&["apply", "--repair"], &single_line);
+    assert!(
+        !output.status.success(),
+        "a lone duplicated line is still ambiguous"
+    );
+
+    let output = run_identedit_with_stdin(&["apply", "--repair"], &range);
+    assert!(
+        output.status.success(),
+        "the range should settle which duplicate the anchor meant: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert_eq!(
+        fs::read_to_string(&file).expect("file should be readable"),
+        "# header\nx = 1\ny = 2\nw = 0\n"
+    );
+    fs::remove_file(&file).expect("temp file should be removed");
+}
+
 #[test]
 fn legacy_subcommands_are_no_longer_available() {
     let output = run_identedit(&["transform", "--json"]);
@@ -282,3 +334,380 @@ fn legacy_subcommands_are_no_longer_available() {
     let stderr = String::from_utf8_lossy(&output.stderr);
     assert!(stderr.contains("unrecognized subcommand 'hashline'"));
 }
+
+/// Asserts each key's first `"key":` occurrence in the raw JSON comes after the previous one.
+fn assert_keys_in_order(raw: &str, keys: &[&str]) {
+    let mut previous = 0;
+    for key in keys {
+        let needle = format!("\"{key}\":");
+        let position = raw[previous..]
+            .find(&needle)
+            .unwrap_or_else(|| panic!("key {key} should follow the previous keys in {raw}"));
+        previous += position + needle.len();
+    }
+}
+
+#[test]
+fn capabilities_reports_the_pinned_hash_contract() {
+    let output = run_identedit(&["capabilities"]);
+    assert!(output.status.success(), "capabilities should succeed");
+    let raw = String::from_utf8(output.stdout).expect("stdout should be utf-8");
+    assert_keys_in_order(
+        &raw,
+        &[
+            "version",
+            "contract_version",
+            "field_order",
+            "hashes",
+            "identity",
+            "algorithm",
+            "hex_length",
+            "input",
+            "used_by",
+            "text",
+            "line",
+            "schema_versions",
+            "features",
+            "commands",
+            "op_types",
+            "target_types",
+            "providers",
+            "limits",
+        ],
+    );
+
+    let response: Value = serde_json::from_str(&raw).expect("stdout should be valid JSON");
+    assert_eq!(response["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(response["contract_version"], 1);
+    assert_eq!(response["field_order"], "stable");
+    assert_eq!(
+        response["hashes"],
+        json!({
+            "identity": {
+                "algorithm": "blake3",
+                "hex_length": 16,
+                "input": "kind + \"\\n\" + name (empty if none) + \"\\n\" + node text",
+                "used_by": ["identity"]
+            },
+            "text": {
+                "algorithm": "blake3",
+                "hex_length": 16,
+                "input": "UTF-8 bytes of the text",
+                "used_by": ["expected_old_hash", "old_hash", "expected_file_hash"]
+            },
+            "line": {
+                "algorithm": "blake3",
+                "hex_length": 12,
+                "input": "line content without its line terminator",
+                "used_by": ["anchor", "end_anchor"]
+            }
+        })
+    );
+}
+
+#[test]
+fn capabilities_advertises_commands_ops_providers_and_limits() {
+    let output = run_identedit(&["capabilities"]);
+    assert!(output.status.success(), "capabilities should succeed");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+
+    assert_eq!(
+        response["schema_versions"],
+        json!({"contract": 1, "plan": 1})
+    );
+    assert_eq!(
+        response["features"],
+        json!({"script": cfg!(feature = "script"), "wasm": cfg!(feature = "wasm")})
+    );
+
+    let commands: Vec<&str> = response["commands"]
+        .as_array()
+        .expect("commands should be an array")
+        .iter()
+        .map(|command| command["name"].as_str().expect("name should be a string"))
+        .collect();
+    for expected in ["read", "edit", "apply", "patch", "capabilities"] {
+        assert!(commands.contains(&expected), "missing command {expected}");
+    }
+    for command in &commands {
+        let help = run_identedit(&[command, "--help"]);
+        assert!(help.status.success(), "{command} --help should succeed");
+    }
+
+    // Every advertised tag must be one the plan parser knows.
+    for op_type in response["op_types"].as_array().expect("op_types") {
+        let result =
+            serde_json::from_value::<identedit::changeset::OpKind>(json!({"type": op_type}));
+        if let Err(error) = result {
+            assert!(
+                !error.to_string().contains("unknown variant"),
+                "{op_type} is not an op: {error}"
+            );
+        }
+    }
+    for target_type in response["target_types"].as_array().expect("target_types") {
+        let result = serde_json::from_value::<identedit::changeset::TransformTarget>(
+            json!({"type": target_type}),
+        );
+        if let Err(error) = result {
+            assert!(
+                !error.to_string().contains("unknown variant"),
+                "{target_type} is not a target: {error}"
+            );
+        }
+    }
+
+    let providers = response["providers"].as_array().expect("providers");
+    let python = providers
+        .iter()
+        .find(|provider| provider["name"] == "tree-sitter-python")
+        .expect("python provider should be listed");
+    assert_eq!(python["extensions"], json!(["py"]));
+    assert_eq!(
+        providers.last().expect("at least one provider"),
+        &json!({"name": "fallback", "extensions": []})
+    );
+
+    assert_eq!(
+        response["limits"]["streaming_apply_min_bytes"],
+        16 * 1024 * 1024
+    );
+    assert_eq!(
+        response["limits"]["script_max_operations"].is_null(),
+        !cfg!(feature = "script")
+    );
+    assert_eq!(
+        response["limits"]["wasm_fuel_per_node"].is_null(),
+        !cfg!(feature = "wasm")
+    );
+}
+
+#[test]
+fn hash_values_stay_pinned_across_releases() {
+    // Changing any of these digests breaks caches keyed on them; bump the contract version.
+    assert_eq!(
+        identedit::handle::compute_identity(
+            "function_definition",
+            Some("process_data"),
+            "def process_data(value):\n    result = value + 1\n    return result"
+        ),
+        "ca465ff14ddcaa3f"
+    );
+    assert_eq!(
+        identedit::changeset::hash_text(
+            "def process_data(value):\n    result = value + 1\n    return result"
+        ),
+        "69879e5563550ef7"
+    );
+    assert_eq!(
+        identedit::hashline::compute_line_hash("def process_data(value):"),
+        "e49e9110121e"
+    );
+
+    let response = read_json(Path::new("tests/fixtures/example.py"));
+    let handle = &response["handles"][0];
+    assert_eq!(handle["identity"], "ca465ff14ddcaa3f");
+    assert_eq!(handle["expected_old_hash"], "69879e5563550ef7");
+}
+
+#[test]
+fn response_field_order_is_stable() {
+    let output = run_identedit(&[
+        "read",
+        "--mode",
+        "ast",
+        "--kind",
+        "function_definition",
+        "--verbose",
+        "--json",
+        "tests/fixtures/example.py",
+    ]);
+    assert!(output.status.success(), "read should succeed");
+    assert_keys_in_order(
+        &String::from_utf8_lossy(&output.stdout),
+        &[
+            "handles",
+            "target_type",
+            "file",
+            "span",
+            "start",
+            "end",
+            "start_position",
+            "line",
+            "column",
+            "utf16_column",
+            "end_position",
+            "kind",
+            "name",
+            "identity",
+            "expected_old_hash",
+            "text",
+        ],
+    );
+
+    let file = copy_fixture_to_temp_python("example.py");
+    let output = run_identedit(&[
+        "patch",
+        "--verbose",
+        "--identity",
+        "ca465ff14ddcaa3f",
+        "--replace",
+        "def process_data(value):\n    return value",
+        file.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "patch should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert_keys_in_order(
+        &String::from_utf8_lossy(&output.stdout),
+        &[
+            "applied",
+            "file",
+            "operations_applied",
+            "operations_total",
+            "status",
+            "summary",
+            "files_modified",
+            "operations_applied",
+            "operations_failed",
+            "transaction",
+            "mode",
+            "status",
+        ],
+    );
+
+    let output = run_identedit(&["read", "--json", "missing.py"]);
+    assert!(
+        !output.status.success(),
+        "read of a missing file should fail"
+    );
+    assert_keys_in_order(
+        &String::from_utf8_lossy(&output.stdout),
+        &["error", "type", "message"],
+    );
+}
+
+#[test]
+fn completions_cover_every_command_for_each_shell() {
+    for shell in ["bash", "zsh", "fish", "powershell", "elvish"] {
+        let output = run_identedit(&["completions", shell]);
+        assert!(
+            output.status.success(),
+            "{shell} completions should succeed"
+        );
+        let script = String::from_utf8(output.stdout).expect("script should be utf-8");
+        for command in ["read", "apply", "patch", "capabilities", "toolspec"] {
+            assert!(script.contains(command), "{shell} script misses {command}");
+        }
+    }
+
+    let unknown = run_identedit(&["completions", "tcsh"]);
+    assert!(
+        !unknown.status.success(),
+        "unknown shells should be rejected"
+    );
+}
+
+#[test]
+fn toolspec_matches_the_command_tree_in_both_formats() {
+    let openai = run_identedit(&["toolspec", "--format", "openai"]);
+    assert!(openai.status.success(), "openai toolspec should succeed");
+    let openai: Value = serde_json::from_slice(&openai.stdout).expect("stdout should be JSON");
+    let anthropic = run_identedit(&["toolspec", "--format", "anthropic"]);
+    assert!(
+        anthropic.status.success(),
+        "anthropic toolspec should succeed"
+    );
+    let anthropic: Value =
+        serde_json::from_slice(&anthropic.stdout).expect("stdout should be JSON");
+
+    let openai = openai
+        .as_array()
+        .expect("openai toolspec should be an array");
+    let anthropic = anthropic
+        .as_array()
+        .expect("anthropic toolspec should be an array");
+    assert_eq!(openai.len(), anthropic.len());
+    for (openai_tool, anthropic_tool) in openai.iter().zip(anthropic) {
+        assert_eq!(openai_tool["type"], "function");
+        assert_eq!(openai_tool["function"]["name"], anthropic_tool["name"]);
+        assert_eq!(
+            openai_tool["function"]["description"],
+            anthropic_tool["description"]
+        );
+        assert_eq!(
+            openai_tool["function"]["parameters"],
+            anthropic_tool["input_schema"]
+        );
+    }
+
+    let names: Vec<&str> = anthropic
+        .iter()
+        .map(|tool| tool["name"].as_str().expect("name should be a string"))
+        .collect();
+    for expected in [
+        "identedit_read",
+        "identedit_apply",
+        "identedit_patch",
+        "identedit_grammar_install",
+        "identedit_pin_add",
+    ] {
+        assert!(names.contains(&expected), "missing tool {expected}");
+    }
+    assert!(!names.contains(&"identedit_toolspec"));
+    assert!(!names.contains(&"identedit_completions"));
+
+    let apply = anthropic
+        .iter()
+        .find(|tool| tool["name"] == "identedit_apply")
+        .expect("apply tool should exist");
+    let schema = &apply["input_schema"];
+    assert_eq!(schema["type"], "object");
+    assert_eq!(schema["additionalProperties"], false);
+    assert_eq!(schema["properties"]["dry_run"]["type"], "boolean");
+    assert_eq!(schema["properties"]["input"]["type"], "string");
+    assert_eq!(schema["properties"]["stdin"]["type"], "string");
+    assert!(
+        schema["properties"]
+            .get("inject_failure_after_writes")
+            .is_none(),
+        "hidden flags stay out of tool specs"
+    );
+
+    // Every non-positional property is a real flag of its command.
+    for tool in anthropic {
+        let name = tool["name"].as_str().expect("name should be a string");
+        let mut args: Vec<&str> = name
+            .trim_start_matches("identedit_")
+            .splitn(2, '_')
+            .collect();
+        if ![
+            "analyze", "grammar", "hook", "lease", "manifest", "pin", "plan", "refactor",
+        ]
+        .contains(&args[0])
+        {
+            args = vec![name.trim_start_matches("identedit_")];
+        }
+        let command: Vec<String> = args.iter().map(|part| part.replace('_', "-")).collect();
+        let mut help_args: Vec<&str> = command.iter().map(String::as_str).collect();
+        help_args.push("--help");
+        let help = run_identedit(&help_args);
+        assert!(help.status.success(), "{name} help should succeed");
+        let help = String::from_utf8_lossy(&help.stdout);
+        for (property, schema) in tool["input_schema"]["properties"]
+            .as_object()
+            .expect("properties should be an object")
+        {
+            let positional = schema["description"]
+                .as_str()
+                .is_some_and(|description| description.ends_with("(positional)"));
+            if property == "stdin" || positional {
+                continue;
+            }
+            let flag = format!("--{}", property.replace('_', "-"));
+            assert!(help.contains(&flag), "{name} has no {flag}");
+        }
+    }
+}
@@ -6,7 +6,7 @@ use std::path::{Path, PathBuf};
 use std::process::{Command, Output, Stdio};
 
 use serde_json::Value;
-use tempfile::Builder;
+use tempfile::{Builder, TempDir};
 
 pub fn fixture_path(name: &str) -> PathBuf {
     PathBuf::from(env!("CARGO_MANIFEST_DIR"))
@@ -64,6 +64,82 @@ pub fn run_identedit_with_stdin(args: &[&str], input: &str) -> Output {
         .expect("failed to read process output")
 }
 
+/// Runs identedit in `workspace`, with `IDENTEDIT_RECORD_STATS` cleared so the caller's
+/// environment cannot turn on stats recording.
+pub fn run_in(workspace: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_identedit"))
+        .args(args)
+        .current_dir(workspace)
+        .env_remove("IDENTEDIT_RECORD_STATS")
+        .output()
+        .expect("failed to run identedit binary")
+}
+
+/// Runs identedit in `workspace` with `input` on stdin.
+pub fn run_in_with_stdin(workspace: &Path, args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_identedit"))
+        .args(args)
+        .current_dir(workspace)
+        .env_remove("IDENTEDIT_RECORD_STATS")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn identedit binary");
+    child
+        .stdin
+        .take()
+        .expect("stdin should be piped")
+        .write_all(input.as_bytes())
+        .expect("stdin write should succeed");
+    child
+        .wait_with_output()
+        .expect("failed to read process output")
+}
+
+/// Runs identedit, which must succeed, and parses its JSON response.
+pub fn run_json(args: &[&str]) -> Value {
+    let output = run_identedit(args);
+    assert!(
+        output.status.success(),
+        "{args:?} should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    stdout_json(&output)
+}
+
+pub fn stdout_json(output: &Output) -> Value {
+    serde_json::from_slice(&output.stdout).expect("stdout should be JSON")
+}
+
+/// Runs git in `root` under a fixed identity and returns its stdout.
+pub fn git(root: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(root)
+        .env("GIT_AUTHOR_NAME", "Ada")
+        .env("GIT_AUTHOR_EMAIL", "ada@example.com")
+        .env("GIT_COMMITTER_NAME", "Ada")
+        .env("GIT_COMMITTER_EMAIL", "ada@example.com")
+        .output()
+        .expect("git should run");
+    assert!(output.status.success(), "git {args:?} should succeed");
+    String::from_utf8(output.stdout).expect("git output should be utf-8")
+}
+
+/// A fresh git repository with `files`, given as path and contents, in one commit.
+pub fn committed_repo(files: &[(&str, &str)]) -> TempDir {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let root = workspace.path();
+    for (path, contents) in files {
+        fs::write(root.join(path), contents).expect("fixture should be written");
+    }
+    git(root, &["init", "--quiet"]);
+    git(root, &["add", "."]);
+    git(root, &["commit", "--quiet", "-m", "first"]);
+    workspace
+}
+
 #[cfg(unix)]
 pub fn run_shell_script(script: &str, root: &Path, identity: Option<&str>) -> Output {
     let mut command = Command::new("sh");
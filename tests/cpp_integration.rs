@@ -176,6 +176,70 @@ fn select_reports_parse_failure_for_syntax_invalid_h_when_both_parsers_fail() {
     assert!(message.contains("Syntax errors detected in C/C++ header source"));
 }
 
+fn read_header_language(arguments: &[&str], file: &Path) -> Value {
+    let mut arguments = arguments.to_vec();
+    arguments.extend([
+        "read",
+        "--json",
+        file.to_str().expect("path should be utf-8"),
+    ]);
+    let output = run_identedit(&arguments);
+    assert!(
+        output.status.success(),
+        "read failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    response["file_preconditions"][0]["header_language"].clone()
+}
+
+#[test]
+fn read_reports_h_header_language_decision() {
+    let knr = write_temp_source(
+        ".h",
+        "int configure(value)\nint value;\n{\n    return value;\n}\n",
+    );
+    let decision = read_header_language(&[], &knr);
+    assert_eq!(decision["language"], "c");
+    assert_eq!(decision["reason"], "only_c_parses");
+    assert!(decision["cpp_errors"].as_u64().expect("cpp_errors") > 0);
+    assert_eq!(decision["c_errors"], 0);
+
+    let shared = write_temp_source(".h", "int add(int left, int right);\n");
+    let decision = read_header_language(&[], &shared);
+    assert_eq!(decision["language"], "cpp");
+    assert_eq!(decision["reason"], "both_parse");
+
+    let plain_c = write_temp_source(".c", "int add(int left, int right);\n");
+    assert!(read_header_language(&[], &plain_c).is_null());
+}
+
+#[test]
+fn header_lang_forces_h_dialect() {
+    let shared = write_temp_source(".h", "int add(int left, int right);\n");
+    let decision = read_header_language(&["--header-lang", "c"], &shared);
+    assert_eq!(decision["language"], "c");
+    assert_eq!(decision["reason"], "forced");
+    assert!(decision.get("cpp_errors").is_none());
+
+    let knr = write_temp_source(
+        ".h",
+        "int configure(value)\nint value;\n{\n    return value;\n}\n",
+    );
+    let output = run_identedit(&[
+        "--header-lang",
+        "cpp",
+        "read",
+        "--json",
+        knr.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(!output.status.success(), "forced C++ should reject K&R C");
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["error"]["type"], "parse_failure");
+}
+
 #[test]
 fn select_reports_parse_failure_for_syntax_invalid_cpp() {
     let file_path = write_temp_source(".cpp", "int broken( {\n    return 1;\n}\n");
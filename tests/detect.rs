@@ -0,0 +1,59 @@
+use std::fs;
+
+mod common;
+
+#[test]
+fn detect_reports_provider_route_and_content_evidence() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let module = workspace.path().join("Module.PY");
+    let script = workspace.path().join("deploy");
+    let blob = workspace.path().join("logo.bin");
+    fs::write(&module, "def run():\n    return 1\n").expect("fixture should be written");
+    fs::write(
+        &script,
+        "#!/usr/bin/env python3\n# vim: set ft=python:\nprint('hi')\n",
+    )
+    .expect("fixture should be written");
+    fs::write(&blob, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").expect("fixture should be written");
+
+    let response = common::run_json(&[
+        "detect",
+        module.to_str().expect("path should be utf-8"),
+        script.to_str().expect("path should be utf-8"),
+        blob.to_str().expect("path should be utf-8"),
+    ]);
+    let files = response["files"]
+        .as_array()
+        .expect("files should be an array");
+
+    assert_eq!(files[0]["extension"], "py");
+    assert_eq!(files[0]["provider"], "tree-sitter-python");
+    assert_eq!(files[0]["route"], "extension");
+    assert_eq!(files[0]["binary"], false);
+
+    assert!(files[1].get("extension").is_none());
+    assert_eq!(files[1]["provider"], "fallback");
+    assert_eq!(files[1]["route"], "fallback");
+    assert_eq!(files[1]["shebang"]["interpreter"], "python3");
+    assert_eq!(files[1]["modeline"]["line"], 2);
+    assert_eq!(files[1]["modeline"]["language"], "python");
+
+    assert_eq!(files[2]["binary"], true);
+    assert!(files[2].get("shebang").is_none());
+}
+
+#[test]
+fn detect_reports_preferred_routes() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let notes = workspace.path().join("notes.txt");
+    fs::write(&notes, "def run():\n    return 1\n").expect("fixture should be written");
+
+    let response = common::run_json(&[
+        "--prefer",
+        "txt=python",
+        "detect",
+        notes.to_str().expect("path should be utf-8"),
+    ]);
+    assert_eq!(response["files"][0]["provider"], "tree-sitter-python");
+    assert_eq!(response["files"][0]["route"], "preferred");
+}
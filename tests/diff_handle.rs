@@ -0,0 +1,100 @@
+use std::fs;
+use std::path::Path;
+
+use serde_json::Value;
+
+mod common;
+
+fn diff_handle(file: &Path, handle: &Value, old_text: Option<&str>) -> Value {
+    let mut args = vec![
+        "diff-handle",
+        "--identity",
+        handle["identity"]
+            .as_str()
+            .expect("identity should be a string"),
+        "--expected-hash",
+        handle["expected_old_hash"]
+            .as_str()
+            .expect("hash should be a string"),
+    ];
+    if let Some(old_text) = old_text {
+        args.extend(["--old-text", old_text]);
+    }
+    args.push(file.to_str().expect("path should be utf-8"));
+    let output = common::run_identedit(&args);
+    assert!(
+        output.status.success(),
+        "diff-handle failed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON")
+}
+
+#[test]
+fn diff_handle_reports_unchanged_changed_and_stale_nodes() {
+    let file = common::copy_fixture_to_temp_python("example.py");
+    let handle = common::select_first_handle(&file, "function_definition", Some("process_data"));
+    let old_text = handle["text"].as_str().expect("text should be a string");
+
+    let response = diff_handle(&file, &handle, None);
+    assert_eq!(response["status"], "unchanged");
+    assert_eq!(response["current"]["identity"], handle["identity"]);
+    assert!(response.get("diff").is_none());
+
+    let source = fs::read_to_string(&file).expect("fixture should be readable");
+    fs::write(&file, source.replace("value + 1", "value + 2")).expect("edit should be written");
+
+    let response = diff_handle(&file, &handle, None);
+    assert_eq!(
+        response["status"], "stale",
+        "no successor lookup without old text"
+    );
+    assert!(response.get("current").is_none());
+
+    let response = diff_handle(&file, &handle, Some(old_text));
+    assert_eq!(response["status"], "changed");
+    assert_eq!(response["current"]["name"], "process_data");
+    assert_eq!(
+        response["current"]["text"],
+        "def process_data(value):\n    result = value + 2\n    return result"
+    );
+    let diff = response["diff"].as_str().expect("diff should be a string");
+    assert!(diff.contains("-    result = value + 1\n"), "diff: {diff}");
+    assert!(diff.contains("+    result = value + 2\n"), "diff: {diff}");
+    assert!(diff.contains("@@ -1,3 +1,3 @@"), "diff: {diff}");
+
+    fs::write(&file, "def helper():\n    return \"helper\"\n").expect("edit should be written");
+    let response = diff_handle(&file, &handle, Some(old_text));
+    assert_eq!(
+        response["status"], "stale",
+        "a deleted node has no successor"
+    );
+
+    fs::remove_file(&file).expect("temp fixture should be removed");
+}
+
+#[test]
+fn diff_handle_rejects_old_text_that_does_not_match_the_hash() {
+    let file = common::copy_fixture_to_temp_python("example.py");
+    let handle = common::select_first_handle(&file, "function_definition", Some("process_data"));
+    let output = common::run_identedit(&[
+        "diff-handle",
+        "--identity",
+        handle["identity"]
+            .as_str()
+            .expect("identity should be a string"),
+        "--expected-hash",
+        handle["expected_old_hash"]
+            .as_str()
+            .expect("hash should be a string"),
+        "--old-text",
+        "def process_data(value):\n    pass",
+        file.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(!output.status.success());
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+
+    fs::remove_file(&file).expect("temp fixture should be removed");
+}
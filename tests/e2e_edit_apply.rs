@@ -476,6 +476,69 @@ fn transform_apply_pipeline_supports_mixed_node_and_line_targets() {
     assert!(modified.contains("return \"helper-updated\""));
 }
 
+#[test]
+fn apply_whitespace_policy_cleans_edited_lines_and_reports_it() {
+    let source = "a = 1  \nb = 2\nc = 3";
+    let mut temp_file = Builder::new()
+        .suffix(".txt")
+        .tempfile()
+        .expect("temp text file should be created");
+    temp_file
+        .write_all(source.as_bytes())
+        .expect("fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+
+    let transform_request = json!({
+        "command": "edit",
+        "file": file_path.to_string_lossy().to_string(),
+        "operations": [{
+            "target": {"type": "line", "anchor": line_ref(source, 2)},
+            "op": {"type": "set_line", "new_text": "b = 20 \t\n"}
+        }]
+    });
+    let transform_output =
+        run_identedit_with_stdin(&["edit", "--json"], &transform_request.to_string());
+    assert!(
+        transform_output.status.success(),
+        "edit failed: {}",
+        String::from_utf8_lossy(&transform_output.stdout)
+    );
+
+    let transform_json =
+        std::str::from_utf8(&transform_output.stdout).expect("edit output should be utf-8");
+    let apply_output = run_identedit_with_stdin(
+        &[
+            "apply",
+            "--ensure-final-newline",
+            "--strip-trailing-whitespace",
+            "edited_lines",
+        ],
+        transform_json,
+    );
+    assert!(
+        apply_output.status.success(),
+        "apply failed: {}",
+        String::from_utf8_lossy(&apply_output.stdout)
+    );
+    let response: Value =
+        serde_json::from_slice(&apply_output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(
+        response["normalized"],
+        json!([{
+            "file": file_path.to_string_lossy().to_string(),
+            "final_newline_added": true,
+            "trailing_whitespace_stripped": [2]
+        }])
+    );
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        "a = 1  \nb = 20\nc = 3\n",
+        "only the edited line loses its trailing whitespace"
+    );
+
+    fs::remove_file(&file_path).expect("temp file should be removed");
+}
+
 #[test]
 fn select_transform_apply_pipeline_supports_parent_segment_paths() {
     let workspace = tempdir().expect("tempdir should be created");
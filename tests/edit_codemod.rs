@@ -0,0 +1,312 @@
+#![cfg(unix)]
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use serde_json::Value;
+use tempfile::tempdir;
+
+mod common;
+
+const SOURCE: &str = "def add(a, b):\n    return a + b\n\n\ndef keep(a):\n    return a\n";
+
+#[test]
+fn codemod_receives_json_handles_and_its_stdout_becomes_the_replacement() {
+    let workspace = tempdir().expect("tempdir should be created");
+    fs::write(workspace.path().join("math.py"), SOURCE).expect("fixture should be written");
+
+    // Records each handle it is given, then rewrites `return X` to `return int(X)`.
+    let script = r#"input=$(cat); printf '%s\n' "$input" >> handles.jsonl; printf '%s' "$input" | sed -n 's/.*"text":"return \([^"]*\)".*/return int(\1)/p'"#;
+    let output = common::run_in(
+        workspace.path(),
+        &[
+            "edit",
+            "--codemod",
+            "sh",
+            "--codemod-arg",
+            "-c",
+            "--codemod-arg",
+            script,
+            "--kind",
+            "return_statement",
+            "math.py",
+        ],
+    );
+    assert!(
+        output.status.success(),
+        "edit --codemod should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let plan: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    let operations = plan["files"][0]["operations"]
+        .as_array()
+        .expect("operations should be an array");
+    assert_eq!(operations.len(), 2);
+    assert_eq!(operations[0]["op"]["new_text"], "return int(a + b)");
+    assert_eq!(operations[1]["op"]["new_text"], "return int(a)");
+
+    let handles = fs::read_to_string(workspace.path().join("handles.jsonl"))
+        .expect("codemod should have recorded its input");
+    let first: Value = serde_json::from_str(handles.lines().next().expect("first handle"))
+        .expect("codemod input should be a JSON handle");
+    assert_eq!(first["target_type"], "node");
+    assert_eq!(first["kind"], "return_statement");
+    assert_eq!(first["text"], "return a + b");
+    assert_eq!(first["start_position"]["line"], 2);
+
+    let applied = Command::new(env!("CARGO_BIN_EXE_identedit"))
+        .arg("apply")
+        .current_dir(workspace.path())
+        .stdin(fs::File::open(write_plan(workspace.path(), &output.stdout)).expect("plan"))
+        .output()
+        .expect("apply should run");
+    assert!(applied.status.success());
+    assert_eq!(
+        fs::read_to_string(workspace.path().join("math.py")).expect("file should be readable"),
+        "def add(a, b):\n    return int(a + b)\n\n\ndef keep(a):\n    return int(a)\n"
+    );
+}
+
+#[test]
+fn failing_codemod_reports_stderr_and_missing_selector_is_rejected() {
+    let workspace = tempdir().expect("tempdir should be created");
+    fs::write(workspace.path().join("math.py"), SOURCE).expect("fixture should be written");
+
+    let output = common::run_in(
+        workspace.path(),
+        &[
+            "edit",
+            "--codemod",
+            "sh",
+            "--codemod-arg",
+            "-c",
+            "--codemod-arg",
+            "echo 'cannot rewrite' >&2; exit 3",
+            "--name",
+            "add",
+            "math.py",
+        ],
+    );
+    assert!(!output.status.success());
+    let error: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(error["error"]["type"], "codemod_failed");
+    assert!(
+        error["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("cannot rewrite"))
+    );
+
+    let output = common::run_in(workspace.path(), &["edit", "--codemod", "cat", "math.py"]);
+    assert!(!output.status.success());
+    let error: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(error["error"]["type"], "invalid_request");
+}
+
+#[test]
+fn composed_selector_flags_pick_handle_functions_outside_legacy_router() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let source = "class LegacyRouter:\n    def handle_get(self):\n        return 1\n\n\nclass Router:\n    def handle_get(self):\n        return 2\n\n\ndef handle_post():\n    return 3\n\n\ndef helper():\n    return 4\n";
+    fs::write(workspace.path().join("routes.py"), source).expect("fixture should be written");
+
+    // Records the name of each handle it is given and stubs the function out.
+    let script = r#"input=$(cat); printf '%s\n' "$input" | sed -n 's/.*"name":"\([^"]*\)".*/\1/p' >> names.txt; echo 'pass'"#;
+    let output = common::run_in(
+        workspace.path(),
+        &[
+            "edit",
+            "--codemod",
+            "sh",
+            "--codemod-arg",
+            "-c",
+            "--codemod-arg",
+            script,
+            "--kind",
+            "function_definition",
+            "--name",
+            "handle_*",
+            "--not",
+            r#"{"within": {"kind": "class_definition", "name_pattern": "LegacyRouter"}}"#,
+            "routes.py",
+        ],
+    );
+    assert!(
+        output.status.success(),
+        "edit with --not should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let names = fs::read_to_string(workspace.path().join("names.txt"))
+        .expect("codemod should have recorded names");
+    assert_eq!(names, "handle_get\nhandle_post\n");
+    let plan: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    let spans = plan["files"][0]["operations"]
+        .as_array()
+        .expect("operations should be an array")
+        .iter()
+        .map(|operation| operation["target"]["span_hint"]["start"].as_u64())
+        .collect::<Vec<_>>();
+    let router_method = source
+        .find("    def handle_get(self):\n        return 2")
+        .unwrap()
+        + 4;
+    let top_level = source.find("def handle_post").unwrap();
+    assert_eq!(
+        spans,
+        vec![Some(router_method as u64), Some(top_level as u64)]
+    );
+
+    let output = common::run_in(
+        workspace.path(),
+        &[
+            "edit",
+            "--codemod",
+            "cat",
+            "--kind",
+            "function_definition",
+            "--or",
+            r#"{"kind": "class_definition"}"#,
+            "--and",
+            "not json",
+            "routes.py",
+        ],
+    );
+    assert!(!output.status.success());
+    let error: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(error["error"]["type"], "invalid_request");
+    assert!(
+        error["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("--and expects a JSON selector"))
+    );
+}
+
+fn write_plan(workspace: &Path, plan: &[u8]) -> std::path::PathBuf {
+    let path = workspace.join("plan.json");
+    fs::write(&path, plan).expect("plan should be written");
+    path
+}
+
+/// `transform` hands back its own input: `(ptr << 32) | len`.
+#[cfg(feature = "wasm")]
+const ECHO_MODULE: &[u8] = &[
+    0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+    0x01, 0x0c, 0x02, 0x60, 0x01, 0x7f, 0x01, 0x7f, 0x60, 0x02, 0x7f, 0x7f, 0x01,
+    0x7e, // types
+    0x03, 0x03, 0x02, 0x00, 0x01, // functions
+    0x05, 0x03, 0x01, 0x00, 0x01, // memory
+    0x07, 0x1e, 0x03, // exports
+    0x06, b'm', b'e', b'm', b'o', b'r', b'y', 0x02, 0x00, //
+    0x05, b'a', b'l', b'l', b'o', b'c', 0x00, 0x00, //
+    0x09, b't', b'r', b'a', b'n', b's', b'f', b'o', b'r', b'm', 0x00, 0x01, //
+    0x0a, 0x14, 0x02, // code
+    0x05, 0x00, 0x41, 0x80, 0x08, 0x0b, // alloc: i32.const 1024
+    0x0c, 0x00, 0x20, 0x00, 0xad, 0x42, 0x20, 0x86, 0x20, 0x01, 0xad, 0x84, 0x0b,
+];
+
+#[cfg(feature = "wasm")]
+#[test]
+fn wasm_codemod_receives_handle_and_args_and_returns_replacement() {
+    let workspace = tempdir().expect("tempdir should be created");
+    fs::write(workspace.path().join("math.py"), SOURCE).expect("fixture should be written");
+    fs::write(workspace.path().join("echo.wasm"), ECHO_MODULE).expect("module should be written");
+
+    let output = common::run_in(
+        workspace.path(),
+        &[
+            "edit",
+            "--codemod",
+            "echo.wasm",
+            "--codemod-arg",
+            "strict",
+            "--name",
+            "keep",
+            "math.py",
+        ],
+    );
+    assert!(
+        output.status.success(),
+        "wasm codemod should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let plan: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    let new_text = plan["files"][0]["operations"][0]["op"]["new_text"]
+        .as_str()
+        .expect("replacement should be a string");
+    let input: Value = serde_json::from_str(new_text).expect("echoed input should be JSON");
+    assert_eq!(input["args"], serde_json::json!(["strict"]));
+    assert_eq!(input["handle"]["name"], "keep");
+    assert_eq!(input["handle"]["text"], "def keep(a):\n    return a");
+
+    fs::write(workspace.path().join("broken.wasm"), b"not wasm").expect("module written");
+    let output = common::run_in(
+        workspace.path(),
+        &[
+            "edit",
+            "--codemod",
+            "broken.wasm",
+            "--name",
+            "keep",
+            "math.py",
+        ],
+    );
+    let error: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(error["error"]["type"], "codemod_failed");
+}
+
+#[cfg(not(feature = "wasm"))]
+#[test]
+fn wasm_codemod_requires_the_wasm_feature() {
+    let workspace = tempdir().expect("tempdir should be created");
+    fs::write(workspace.path().join("math.py"), SOURCE).expect("fixture should be written");
+    fs::write(workspace.path().join("echo.wasm"), b"\0asm").expect("module should be written");
+
+    let output = common::run_in(
+        workspace.path(),
+        &[
+            "edit",
+            "--codemod",
+            "echo.wasm",
+            "--name",
+            "keep",
+            "math.py",
+        ],
+    );
+    assert!(!output.status.success());
+    let error: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(error["error"]["type"], "invalid_request");
+}
+
+#[cfg(feature = "script")]
+#[test]
+fn edit_script_rewrites_selected_nodes_and_keeps_those_it_returns_unit_for() {
+    let workspace = tempdir().expect("tempdir should be created");
+    fs::write(workspace.path().join("math.py"), SOURCE).expect("fixture should be written");
+    fs::write(
+        workspace.path().join("wrap.rhai"),
+        r#"let text = handle.text; if text.contains("+") { text.replace("return ", "return int("); text + ")" }"#,
+    )
+    .expect("script should be written");
+
+    let output = common::run_in(
+        workspace.path(),
+        &[
+            "edit",
+            "--script",
+            "wrap.rhai",
+            "--kind",
+            "return_statement",
+            "math.py",
+        ],
+    );
+    assert!(
+        output.status.success(),
+        "edit --script should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let plan: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    let operations = plan["files"][0]["operations"]
+        .as_array()
+        .expect("operations should be an array");
+    assert_eq!(operations.len(), 1);
+    assert_eq!(operations[0]["op"]["new_text"], "return int(a + b)");
+}
@@ -57,3 +57,7 @@ mod scenario_03_preview_and_file_targets;
 mod scenario_04_boundary_conflicts;
 #[path = "edit_integration/scenario_05_fallback_and_misc.rs"]
 mod scenario_05_fallback_and_misc;
+#[path = "edit_integration/scenario_06_fan_out.rs"]
+mod scenario_06_fan_out;
+#[path = "edit_integration/scenario_07_position_targets.rs"]
+mod scenario_07_position_targets;
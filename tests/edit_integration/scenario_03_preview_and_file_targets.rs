@@ -1976,3 +1976,69 @@ fn transform_json_mode_cross_file_move_rejects_same_file_destination() {
         "expected same-file destination diagnostic with guidance"
     );
 }
+
+#[test]
+fn node_target_position_hint_resolves_to_the_read_span() {
+    let file_path = copy_fixture_to_temp_python("example.py");
+    let handle = select_first_handle(&file_path, "function_definition", Some("helper"));
+    assert_eq!(handle["start_position"]["line"], 6);
+    assert_eq!(handle["start_position"]["column"], 1);
+
+    let position = |key: &str| {
+        json!({
+            "line": handle[key]["line"],
+            "utf16_column": handle[key]["utf16_column"]
+        })
+    };
+    let request = |position_hint: Value| {
+        json!({
+            "command": "edit",
+            "file": file_path.to_string_lossy().to_string(),
+            "operations": [{
+                "target": {
+                    "type": "node",
+                    "identity": handle["identity"],
+                    "kind": handle["kind"],
+                    "expected_old_hash": handle["expected_old_hash"],
+                    "position_hint": position_hint
+                },
+                "op": { "type": "replace", "new_text": "def helper():\n    return 3" }
+            }]
+        })
+    };
+
+    let output = run_identedit_with_stdin(
+        &["edit", "--json"],
+        &request(json!({ "start": position("start_position"), "end": position("end_position") }))
+            .to_string(),
+    );
+    assert!(
+        output.status.success(),
+        "edit should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    let target = &response["files"][0]["operations"][0]["target"];
+    assert_eq!(target["span_hint"], handle["span"]);
+    assert!(target.get("position_hint").is_none());
+
+    let output = run_identedit_with_stdin(
+        &["edit", "--json"],
+        &request(json!({
+            "start": { "line": 6, "column": 1 },
+            "end": { "line": 99, "column": 1 }
+        }))
+        .to_string(),
+    );
+    assert!(
+        !output.status.success(),
+        "out-of-range position should fail"
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("position_hint"))
+    );
+}
@@ -0,0 +1,137 @@
+use super::*;
+
+fn write_test_modules(workspace: &Path) -> (PathBuf, PathBuf) {
+    let first = workspace.join("test_first.py");
+    let second = workspace.join("test_second.py");
+    fs::write(
+        &first,
+        "def test_add():\n    assert 1 + 1 == 2\n\n\ndef helper():\n    return 1\n",
+    )
+    .expect("fixture should be written");
+    fs::write(
+        &second,
+        "class TestSuite:\n    def test_sub(self):\n        assert 2 - 1 == 1\n",
+    )
+    .expect("fixture should be written");
+    (first, second)
+}
+
+fn fan_out_request(first: &Path, second: &Path, expected_target_count: usize) -> Value {
+    json!({
+        "command": "edit",
+        "fan_out": {
+            "files": [first.to_string_lossy(), second.to_string_lossy()],
+            "selector": {"kind": "function_definition", "name_pattern": "test_*"},
+            "op": {"type": "insert_before", "new_text": "@pytest.mark.slow\n"},
+            "expected_target_count": expected_target_count
+        }
+    })
+}
+
+#[test]
+fn edit_fan_out_applies_one_op_to_every_matching_node_across_files() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let (first, second) = write_test_modules(workspace.path());
+
+    let output = run_identedit_with_stdin(
+        &["edit", "--json"],
+        &fan_out_request(&first, &second, 2).to_string(),
+    );
+    assert!(
+        output.status.success(),
+        "fan_out should build a plan: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let plan: Value = serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(
+        plan["files"][0]["operations"].as_array().map(Vec::len),
+        Some(1)
+    );
+    assert_eq!(
+        plan["files"][1]["operations"].as_array().map(Vec::len),
+        Some(1)
+    );
+
+    let output = run_identedit_with_stdin(&["apply"], &plan.to_string());
+    assert!(
+        output.status.success(),
+        "apply should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert_eq!(
+        fs::read_to_string(&first).expect("first should be readable"),
+        "@pytest.mark.slow\ndef test_add():\n    assert 1 + 1 == 2\n\n\ndef helper():\n    return 1\n"
+    );
+    assert_eq!(
+        fs::read_to_string(&second).expect("second should be readable"),
+        "class TestSuite:\n    @pytest.mark.slow\n    def test_sub(self):\n        assert 2 - 1 == 1\n"
+    );
+}
+
+#[test]
+fn edit_fan_out_rejects_a_target_count_mismatch() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let (first, second) = write_test_modules(workspace.path());
+
+    let output = run_identedit_with_stdin(
+        &["edit", "--json"],
+        &fan_out_request(&first, &second, 3).to_string(),
+    );
+    assert!(!output.status.success(), "a count mismatch must fail");
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+    let message = response["error"]["message"]
+        .as_str()
+        .expect("message should be a string");
+    assert!(
+        message.contains("matched 2 node(s)") && message.contains("expected_target_count is 3"),
+        "unexpected message: {message}"
+    );
+}
+
+#[test]
+fn edit_fan_out_selects_query_targets() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let (first, second) = write_test_modules(workspace.path());
+    let request = json!({
+        "command": "edit",
+        "fan_out": {
+            "files": [first.to_string_lossy(), second.to_string_lossy()],
+            "query": "(class_definition body: (block (function_definition) @target))",
+            "op": {"type": "delete"},
+            "expected_target_count": 1
+        }
+    });
+
+    let output = run_identedit_with_stdin(&["edit", "--json"], &request.to_string());
+    assert!(
+        output.status.success(),
+        "query fan_out should build a plan: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let plan: Value = serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(plan["files"][0]["operations"], json!([]));
+    assert_eq!(plan["files"][1]["operations"][0]["op"]["type"], "delete");
+
+    let request = json!({
+        "command": "edit",
+        "fan_out": {
+            "files": [first.to_string_lossy()],
+            "query": "(function_definition) @node",
+            "op": {"type": "delete"}
+        }
+    });
+    let output = run_identedit_with_stdin(&["edit", "--json"], &request.to_string());
+    assert!(
+        !output.status.success(),
+        "a query without @target must fail"
+    );
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("@target"))
+    );
+}
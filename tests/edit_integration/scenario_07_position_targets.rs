@@ -0,0 +1,100 @@
+use super::*;
+
+const MODULE: &str = "import os\nimport sys\n\n\nclass Router:\n    def get(self):\n        return 1\n\n    def post(self):\n        return 2\n\n\ndef main():\n    pass\n";
+
+fn edit_and_apply(file: &Path, operations: Value) -> String {
+    let request = json!({
+        "command": "edit",
+        "file": file.to_string_lossy(),
+        "operations": operations
+    });
+    let output = run_identedit_with_stdin(&["edit", "--json"], &request.to_string());
+    assert!(
+        output.status.success(),
+        "edit should build a plan: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let output = run_identedit_with_stdin(&["apply"], &String::from_utf8_lossy(&output.stdout));
+    assert!(
+        output.status.success(),
+        "apply should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    fs::read_to_string(file).expect("file should be readable")
+}
+
+#[test]
+fn edit_position_targets_insert_relative_to_first_and_last_matches() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("app.py");
+    fs::write(&file, MODULE).expect("fixture should be written");
+
+    let updated = edit_and_apply(
+        &file,
+        json!([
+            {
+                "target": {
+                    "type": "position",
+                    "position": "last",
+                    "selector": {"kind": "import_statement"}
+                },
+                "op": {"type": "insert_after", "new_text": "\nimport json"}
+            },
+            {
+                "target": {
+                    "type": "position",
+                    "position": "last",
+                    "selector": {
+                        "kind": "function_definition",
+                        "within": {"kind": "class_definition", "name_pattern": "Router"}
+                    }
+                },
+                "op": {"type": "insert_after", "new_text": "\n\n    def delete(self):\n        return 3"}
+            },
+            {
+                "target": {
+                    "type": "position",
+                    "position": "first",
+                    "selector": {"kind": "function_definition"}
+                },
+                "op": {"type": "insert_before", "new_text": "@route\n    "}
+            }
+        ]),
+    );
+    assert_eq!(
+        updated,
+        "import os\nimport sys\nimport json\n\n\nclass Router:\n    @route\n    def get(self):\n        return 1\n\n    def post(self):\n        return 2\n\n    def delete(self):\n        return 3\n\n\ndef main():\n    pass\n"
+    );
+}
+
+#[test]
+fn edit_position_target_without_a_match_is_rejected() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("app.py");
+    fs::write(&file, MODULE).expect("fixture should be written");
+    let request = json!({
+        "command": "edit",
+        "file": file.to_string_lossy(),
+        "operations": [{
+            "target": {
+                "type": "position",
+                "position": "last",
+                "selector": {"kind": "import_from_statement"}
+            },
+            "op": {"type": "insert_after", "new_text": "\nfrom a import b"}
+        }]
+    });
+
+    let output = run_identedit_with_stdin(&["edit", "--json"], &request.to_string());
+    assert!(
+        !output.status.success(),
+        "a position without a match must fail"
+    );
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+    assert_eq!(
+        fs::read_to_string(&file).expect("file should be readable"),
+        MODULE
+    );
+}
@@ -13,6 +13,16 @@ fn run_identedit(arguments: &[&str], kanna_home: &Path) -> Output {
     command.output().expect("failed to run identedit binary")
 }
 
+fn providers_token(kanna_home: &Path) -> String {
+    let output = run_identedit(&["capabilities"], kanna_home);
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("capabilities should be valid JSON");
+    response["providers_token"]
+        .as_str()
+        .expect("providers_token should be a string")
+        .to_string()
+}
+
 fn command_available(name: &str) -> bool {
     Command::new(name).arg("--version").output().is_ok()
 }
@@ -205,6 +215,7 @@ fn grammar_install_with_local_repo_enables_runtime_selection() {
     initialize_git_repository(grammar_repo_dir.path());
 
     let kanna_home = tempdir().expect("identedit home tempdir should be created");
+    let token_before = providers_token(kanna_home.path());
 
     let install_output = run_identedit(
         &[
@@ -234,6 +245,11 @@ fn grammar_install_with_local_repo_enables_runtime_selection() {
     assert_eq!(install_response["installed"]["lang"], "jsonlocal");
     assert_eq!(install_response["installed"]["symbol"], "tree_sitter_json");
     assert_eq!(install_response["installed"]["extensions"][0], "jlocal");
+    assert_ne!(
+        providers_token(kanna_home.path()),
+        token_before,
+        "capabilities should announce the new extension set"
+    );
 
     let workspace = tempdir().expect("workspace tempdir should be created");
     let target_file = workspace.path().join("fixture.jlocal");
@@ -489,3 +505,310 @@ fn missing_dynamic_library_entry_in_manifest_is_ignored_at_runtime() {
         serde_json::from_slice(&select_output.stdout).expect("select output should be valid JSON");
     assert_eq!(response["summary"]["matches"], 1);
 }
+
+#[cfg(unix)]
+#[test]
+fn grammar_install_from_local_path_registers_kind_map_and_abi_version() {
+    if !command_available("cc") {
+        return;
+    }
+
+    let grammar_dir = tempdir().expect("grammar checkout tempdir should be created");
+    prepare_local_grammar_repo(grammar_dir.path());
+
+    let kanna_home = tempdir().expect("identedit home tempdir should be created");
+    let install_output = run_identedit(
+        &[
+            "grammar",
+            "install",
+            "jsonpath",
+            "--path",
+            grammar_dir.path().to_str().expect("path should be utf-8"),
+            "--symbol",
+            "tree_sitter_json",
+            "--ext",
+            "jpath",
+            "--kind-map",
+            "object=mapping",
+        ],
+        kanna_home.path(),
+    );
+    assert!(
+        install_output.status.success(),
+        "grammar install failed: {}",
+        String::from_utf8_lossy(&install_output.stdout)
+    );
+
+    let response: Value = serde_json::from_slice(&install_output.stdout)
+        .expect("install output should be valid JSON");
+    assert_eq!(response["installed"]["kind_map"]["object"], "mapping");
+    assert!(response["installed"]["abi_version"].as_u64().is_some());
+    let manifest: Value = serde_json::from_str(
+        &fs::read_to_string(kanna_home.path().join("grammars").join("manifest.json"))
+            .expect("manifest should be written"),
+    )
+    .expect("manifest should be valid JSON");
+    assert_eq!(manifest["grammars"][0]["kind_map"]["object"], "mapping");
+    let leftovers = fs::read_dir(kanna_home.path().join("grammars"))
+        .expect("grammars directory should be readable")
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name().to_string_lossy().ends_with(".tmp"))
+        .count();
+    assert_eq!(leftovers, 0, "staged files must not be left behind");
+
+    let workspace = tempdir().expect("workspace tempdir should be created");
+    let target_file = workspace.path().join("fixture.jpath");
+    fs::write(&target_file, "{\"enabled\": true}\n").expect("fixture file should be written");
+    let select_output = run_identedit(
+        &[
+            "read",
+            "--json",
+            "--kind",
+            "mapping",
+            target_file.to_str().expect("path should be utf-8"),
+        ],
+        kanna_home.path(),
+    );
+    let select_response: Value =
+        serde_json::from_slice(&select_output.stdout).expect("select output should be valid JSON");
+    assert_eq!(select_response["summary"]["matches"], 1);
+    assert_eq!(select_response["handles"][0]["kind"], "mapping");
+}
+
+#[cfg(unix)]
+#[test]
+fn grammar_install_rejects_kind_map_for_unknown_node_kind_without_registering() {
+    if !command_available("cc") {
+        return;
+    }
+
+    let grammar_dir = tempdir().expect("grammar checkout tempdir should be created");
+    prepare_local_grammar_repo(grammar_dir.path());
+
+    let kanna_home = tempdir().expect("identedit home tempdir should be created");
+    let install_output = run_identedit(
+        &[
+            "grammar",
+            "install",
+            "jsonpath",
+            "--path",
+            grammar_dir.path().to_str().expect("path should be utf-8"),
+            "--symbol",
+            "tree_sitter_json",
+            "--ext",
+            "jpath",
+            "--kind-map",
+            "function_item=function",
+        ],
+        kanna_home.path(),
+    );
+    assert!(!install_output.status.success());
+
+    let response: Value =
+        serde_json::from_slice(&install_output.stdout).expect("error output should be valid JSON");
+    assert_eq!(response["error"]["type"], "grammar_install_failed");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("function_item")),
+        "message should name the unknown node kind: {response}"
+    );
+    assert!(
+        !kanna_home
+            .path()
+            .join("grammars")
+            .join("manifest.json")
+            .exists(),
+        "manifest must not be created after failed install"
+    );
+}
+
+#[test]
+fn grammar_install_path_without_parser_suggests_generate() {
+    let grammar_dir = tempdir().expect("grammar checkout tempdir should be created");
+    fs::write(
+        grammar_dir.path().join("grammar.js"),
+        "module.exports = {};\n",
+    )
+    .expect("grammar.js should be written");
+
+    let kanna_home = tempdir().expect("identedit home tempdir should be created");
+    let output = run_identedit(
+        &[
+            "grammar",
+            "install",
+            "toml",
+            "--path",
+            grammar_dir.path().to_str().expect("path should be utf-8"),
+        ],
+        kanna_home.path(),
+    );
+    assert!(!output.status.success());
+
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("error output should be valid JSON");
+    assert_eq!(response["error"]["type"], "grammar_install_failed");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("tree-sitter generate")),
+        "message should suggest generating the parser: {response}"
+    );
+}
+
+#[test]
+fn grammar_install_rejects_path_together_with_repo() {
+    let kanna_home = tempdir().expect("identedit home tempdir should be created");
+    let output = run_identedit(
+        &[
+            "grammar",
+            "install",
+            "toml",
+            "--path",
+            ".",
+            "--repo",
+            "https://example.invalid/tree-sitter-toml.git",
+        ],
+        kanna_home.path(),
+    );
+    assert!(!output.status.success(), "--path and --repo must conflict");
+}
+
+#[cfg(unix)]
+#[test]
+fn grammar_remove_deletes_library_and_manifest_entry_after_dry_run_listing() {
+    if !command_available("cc") {
+        return;
+    }
+
+    let grammar_dir = tempdir().expect("grammar checkout tempdir should be created");
+    prepare_local_grammar_repo(grammar_dir.path());
+
+    let kanna_home = tempdir().expect("identedit home tempdir should be created");
+    let install_output = run_identedit(
+        &[
+            "grammar",
+            "install",
+            "jsongone",
+            "--path",
+            grammar_dir.path().to_str().expect("path should be utf-8"),
+            "--symbol",
+            "tree_sitter_json",
+            "--ext",
+            "jgone",
+        ],
+        kanna_home.path(),
+    );
+    assert!(
+        install_output.status.success(),
+        "grammar install failed: {}",
+        String::from_utf8_lossy(&install_output.stdout)
+    );
+    let library_path = kanna_home
+        .path()
+        .join("grammars")
+        .join(format!("jsongone.{}", shared_library_extension()));
+    let manifest_path = kanna_home.path().join("grammars").join("manifest.json");
+
+    let dry_run_output = run_identedit(
+        &["grammar", "remove", "jsongone", "--dry-run"],
+        kanna_home.path(),
+    );
+    assert!(dry_run_output.status.success());
+    let dry_run: Value =
+        serde_json::from_slice(&dry_run_output.stdout).expect("output should be valid JSON");
+    assert_eq!(dry_run["dry_run"], true);
+    assert_eq!(dry_run["removed"], json!(["jsongone"]));
+    assert_eq!(dry_run["deleted_paths"].as_array().map(Vec::len), Some(1));
+    assert!(
+        library_path.is_file(),
+        "dry run must not delete the library"
+    );
+
+    let remove_output = run_identedit(&["grammar", "remove", "jsongone"], kanna_home.path());
+    assert!(remove_output.status.success());
+    assert!(!library_path.exists(), "library should be deleted");
+    let manifest: Value = serde_json::from_str(
+        &fs::read_to_string(&manifest_path).expect("manifest should still exist"),
+    )
+    .expect("manifest should be valid JSON");
+    assert_eq!(manifest["grammars"], json!([]));
+
+    let again = run_identedit(&["grammar", "remove", "jsongone"], kanna_home.path());
+    assert!(!again.status.success(), "removing twice should fail");
+    let response: Value =
+        serde_json::from_slice(&again.stdout).expect("error output should be valid JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+}
+
+#[test]
+fn grammar_gc_drops_stale_entries_and_orphaned_files() {
+    let kanna_home = tempdir().expect("identedit home tempdir should be created");
+    let grammars_dir = kanna_home.path().join("grammars");
+    fs::create_dir_all(&grammars_dir).expect("grammars directory should be created");
+
+    let kept_library = grammars_dir.join(format!("kept.{}", shared_library_extension()));
+    let orphan_library = grammars_dir.join(format!("orphan.{}", shared_library_extension()));
+    let staged_leftover = grammars_dir.join(".manifest.json.42.tmp");
+    for path in [&kept_library, &orphan_library, &staged_leftover] {
+        fs::write(path, "").expect("fixture file should be written");
+    }
+    let manifest = json!({
+        "grammars": [
+            {
+                "lang": "ghost",
+                "repo": "https://example.invalid/tree-sitter-ghost.git",
+                "symbol": "tree_sitter_ghost",
+                "extensions": ["ghost"],
+                "library_path": grammars_dir.join(format!("ghost.{}", shared_library_extension()))
+            },
+            {
+                "lang": "kept",
+                "repo": "https://example.invalid/tree-sitter-kept.git",
+                "symbol": "tree_sitter_kept",
+                "extensions": ["kept"],
+                "library_path": kept_library
+            }
+        ]
+    });
+    let manifest_path = grammars_dir.join("manifest.json");
+    fs::write(
+        &manifest_path,
+        serde_json::to_string_pretty(&manifest).expect("manifest serialization should succeed"),
+    )
+    .expect("manifest should be written");
+
+    let dry_run_output = run_identedit(&["grammar", "gc", "--dry-run"], kanna_home.path());
+    assert!(dry_run_output.status.success());
+    let dry_run: Value =
+        serde_json::from_slice(&dry_run_output.stdout).expect("output should be valid JSON");
+    assert_eq!(dry_run["removed"], json!(["ghost"]));
+    let listed = dry_run["deleted_paths"]
+        .as_array()
+        .expect("deleted_paths should be an array")
+        .iter()
+        .filter_map(Value::as_str)
+        .map(PathBuf::from)
+        .collect::<Vec<_>>();
+    assert!(listed.contains(&orphan_library), "{listed:?}");
+    assert!(listed.contains(&staged_leftover), "{listed:?}");
+    assert!(!listed.contains(&kept_library), "{listed:?}");
+    assert!(orphan_library.exists(), "dry run must not delete files");
+
+    let gc_output = run_identedit(&["grammar", "gc"], kanna_home.path());
+    assert!(gc_output.status.success());
+    assert!(!orphan_library.exists());
+    assert!(!staged_leftover.exists());
+    assert!(kept_library.exists());
+    let manifest: Value = serde_json::from_str(
+        &fs::read_to_string(&manifest_path).expect("manifest should still exist"),
+    )
+    .expect("manifest should be valid JSON");
+    let langs = manifest["grammars"]
+        .as_array()
+        .expect("grammars should be an array")
+        .iter()
+        .map(|entry| entry["lang"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(langs, vec![json!("kept")]);
+}
@@ -0,0 +1,75 @@
+use std::fs;
+use std::path::Path;
+use std::process::Output;
+
+use serde_json::Value;
+
+mod common;
+
+const COMMITTED: &[(&str, &str)] = &[
+    ("app.py", "def run():\n    return 1\n"),
+    ("legacy.py", "def broken(:\n    pass\n"),
+];
+
+fn pre_commit(root: &Path, args: &[&str]) -> (Output, Value) {
+    let output = common::run_in(root, &[&["hook", "pre-commit"], args].concat());
+    let response = common::stdout_json(&output);
+    (output, response)
+}
+
+#[test]
+fn pre_commit_rejects_staged_files_with_new_syntax_errors_only() {
+    let workspace = common::committed_repo(COMMITTED);
+    let root = workspace.path();
+    fs::write(root.join("legacy.py"), "def broken(:\n    pass\n\nx = 1\n").expect("edit");
+    fs::write(root.join("app.py"), "def run():\n    return 2\n").expect("edit");
+    common::git(root, &["add", "."]);
+
+    let (output, response) = pre_commit(root, &[]);
+    assert!(output.status.success(), "pre-existing errors should pass");
+    assert_eq!(response["passed"], true);
+    assert_eq!(response["files"][0]["file"], "app.py");
+    assert_eq!(response["files"][0]["provider"], "tree-sitter-python");
+    assert_eq!(response["files"][1]["head_syntax_errors"], 1);
+
+    fs::write(root.join("app.py"), "def run(:\n    return 2\n").expect("break");
+    common::git(root, &["add", "app.py"]);
+    // Only the staged content counts, not a working tree fix that was never added.
+    fs::write(root.join("app.py"), "def run():\n    return 2\n").expect("fix unstaged");
+
+    let (output, response) = pre_commit(root, &[]);
+    assert!(!output.status.success(), "new syntax errors should fail");
+    assert_eq!(response["passed"], false);
+    assert_eq!(response["violations"][0]["rule"], "new_syntax_errors");
+    assert_eq!(response["violations"][0]["file"], "app.py");
+}
+
+#[test]
+fn pre_commit_enforces_the_project_policy_when_asked() {
+    let workspace = common::committed_repo(COMMITTED);
+    let root = workspace.path();
+    fs::create_dir_all(root.join(".identedit")).expect("config dir");
+    fs::write(
+        root.join(".identedit/config.json"),
+        r#"{ "policy": { "protected": ["app.py", "vendor/**"], "max_files": 1 } }"#,
+    )
+    .expect("config");
+    fs::create_dir_all(root.join("vendor/lib")).expect("vendor dir");
+    fs::write(root.join("vendor/lib/dep.py"), "x = 1\n").expect("vendored file");
+    fs::remove_file(root.join("app.py")).expect("delete");
+    common::git(root, &["add", "--all", "app.py", "vendor"]);
+
+    let (output, response) = pre_commit(root, &[]);
+    assert!(output.status.success(), "policy is only checked on request");
+    assert_eq!(response["files"][0]["change"], "deleted");
+
+    let (output, response) = pre_commit(root, &["--enforce-policy"]);
+    assert!(!output.status.success());
+    let rules = response["violations"]
+        .as_array()
+        .expect("violations should be an array")
+        .iter()
+        .map(|violation| violation["rule"].as_str().expect("rule"))
+        .collect::<Vec<_>>();
+    assert_eq!(rules, ["max_files", "protected", "protected"]);
+}
@@ -0,0 +1,106 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+use tempfile::tempdir;
+
+mod common;
+
+fn run_as(workspace: &Path, session: Option<&str>, args: &[&str]) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_identedit"));
+    command
+        .args(args)
+        .current_dir(workspace)
+        .env_remove("IDENTEDIT_SESSION");
+    if let Some(session) = session {
+        command.env("IDENTEDIT_SESSION", session);
+    }
+    command.output().expect("failed to run identedit binary")
+}
+
+fn set_port(workspace: &Path, session: Option<&str>, port: &str) -> Output {
+    run_as(
+        workspace,
+        session,
+        &[
+            "patch",
+            "--config-path",
+            "port",
+            "--set-value",
+            port,
+            "config.json",
+        ],
+    )
+}
+
+#[test]
+fn leased_files_reject_writes_from_other_sessions_until_released() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let workspace = workspace.path();
+    fs::write(workspace.join("config.json"), "{\"port\": 80}\n")
+        .expect("fixture should be written");
+
+    let acquired = run_as(
+        workspace,
+        Some("agent-a"),
+        &["lease", "acquire", "config.json", "--ttl", "600"],
+    );
+    assert!(
+        acquired.status.success(),
+        "lease acquire should succeed: {}",
+        String::from_utf8_lossy(&acquired.stdout)
+    );
+    assert_eq!(
+        common::stdout_json(&acquired)["acquired"][0]["session"],
+        "agent-a"
+    );
+
+    for session in [Some("agent-b"), None] {
+        let blocked = set_port(workspace, session, "81");
+        assert!(!blocked.status.success(), "{session:?} should be blocked");
+        let error = &common::stdout_json(&blocked)["error"];
+        assert_eq!(error["type"], "lock_held_by_other_session");
+        assert_eq!(error["lease"]["session"], "agent-a");
+    }
+    let contending = run_as(
+        workspace,
+        Some("agent-b"),
+        &["lease", "acquire", "config.json"],
+    );
+    assert_eq!(
+        common::stdout_json(&contending)["error"]["type"],
+        "lock_held_by_other_session"
+    );
+
+    assert!(set_port(workspace, Some("agent-a"), "82").status.success());
+    let listed = common::stdout_json(&run_as(workspace, None, &["lease", "list"]));
+    assert_eq!(listed["leases"].as_array().map(Vec::len), Some(1));
+
+    let released = run_as(
+        workspace,
+        Some("agent-a"),
+        &["lease", "release", "config.json"],
+    );
+    assert_eq!(
+        common::stdout_json(&released)["released"][0]["session"],
+        "agent-a"
+    );
+    assert!(set_port(workspace, Some("agent-b"), "83").status.success());
+    assert_eq!(
+        fs::read_to_string(workspace.join("config.json")).expect("file should be readable"),
+        "{\"port\": 83}\n"
+    );
+}
+
+#[test]
+fn lease_commands_need_a_session() {
+    let workspace = tempdir().expect("tempdir should be created");
+    fs::write(workspace.path().join("config.json"), "{}\n").expect("fixture should be written");
+
+    let output = run_as(workspace.path(), None, &["lease", "acquire", "config.json"]);
+    assert!(!output.status.success());
+    assert_eq!(
+        common::stdout_json(&output)["error"]["type"],
+        "invalid_request"
+    );
+}
@@ -0,0 +1,101 @@
+use std::fs;
+
+use serde_json::Value;
+use tempfile::tempdir;
+
+mod common;
+
+const PYTHON_SOURCE: &str =
+    "class Model:\n    def save(self):\n        value = compute()\n        return value\n";
+
+const RUST_SOURCE: &str =
+    "fn main() {\n    let total = add(1, 2);\n    println!(\"{total}\");\n}\n";
+
+#[test]
+fn locate_resolves_traceback_and_rustc_locations_to_enclosing_handles() {
+    let workspace = tempdir().expect("tempdir should be created");
+    fs::write(workspace.path().join("models.py"), PYTHON_SOURCE)
+        .expect("fixture should be written");
+    fs::write(workspace.path().join("main.rs"), RUST_SOURCE).expect("fixture should be written");
+
+    let trace = "Traceback (most recent call last):\n  File \"models.py\", line 3, in save\nNameError: name 'compute' is not defined\nerror[E0425]: cannot find function `add` in this scope\n --> main.rs:2:17\n";
+    let output = common::run_in_with_stdin(
+        workspace.path(),
+        &["locate", "--kind", "function_definition"],
+        trace,
+    );
+    assert!(
+        output.status.success(),
+        "locate should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["summary"]["locations"], 2);
+    assert_eq!(response["summary"]["resolved"], 1);
+
+    let python = &response["locations"][0];
+    assert_eq!(python["file"], "models.py");
+    assert_eq!(python["line"], 3);
+    assert!(
+        python["line_anchor"]
+            .as_str()
+            .is_some_and(|anchor| anchor.starts_with("3:"))
+    );
+    assert_eq!(python["handle"]["kind"], "function_definition");
+    assert_eq!(python["handle"]["name"], "save");
+
+    let rust = &response["locations"][1];
+    assert_eq!(rust["file"], "main.rs");
+    assert_eq!(rust["column"], 17);
+    assert!(rust["line_anchor"].is_string());
+    assert!(
+        rust.get("handle").is_none(),
+        "no function_definition encloses the Rust line"
+    );
+
+    let innermost = common::run_in(
+        workspace.path(),
+        &[
+            "locate",
+            "--diagnostic",
+            "main.rs:2:17: error: cannot find function",
+        ],
+    );
+    let response: Value = serde_json::from_slice(&innermost.stdout).expect("stdout should be JSON");
+    assert_eq!(
+        response["locations"][0]["handle"]["kind"],
+        "let_declaration"
+    );
+}
+
+#[test]
+fn locate_reports_unreadable_locations_and_rejects_input_without_locations() {
+    let workspace = tempdir().expect("tempdir should be created");
+    fs::write(workspace.path().join("models.py"), PYTHON_SOURCE)
+        .expect("fixture should be written");
+
+    let output = common::run_in(
+        workspace.path(),
+        &[
+            "locate",
+            "--diagnostic",
+            "missing.py:4: AssertionError",
+            "--diagnostic",
+            "models.py:40: IndexError",
+        ],
+    );
+    assert!(output.status.success());
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["summary"]["resolved"], 0);
+    assert_eq!(response["locations"][0]["error"]["type"], "io_error");
+    assert_eq!(response["locations"][1]["error"]["type"], "invalid_request");
+    assert!(response["locations"][1].get("line_anchor").is_none());
+
+    let rejected = common::run_in(
+        workspace.path(),
+        &["locate", "--diagnostic", "everything is fine"],
+    );
+    assert!(!rejected.status.success());
+    let error: Value = serde_json::from_slice(&rejected.stdout).expect("stdout should be JSON");
+    assert_eq!(error["error"]["type"], "invalid_request");
+}
@@ -0,0 +1,89 @@
+use std::fs;
+
+use serde_json::{Value, json};
+
+mod common;
+
+#[test]
+fn verify_reports_added_removed_and_changed_files_since_create() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let root = workspace.path();
+    fs::create_dir_all(root.join("src")).expect("src should be created");
+    fs::write(root.join("src/a.py"), "def a():\n    return 1\n")
+        .expect("fixture should be written");
+    fs::write(root.join("src/b.py"), "def b():\n    return 2\n")
+        .expect("fixture should be written");
+    fs::write(root.join("README.md"), "# demo\n").expect("fixture should be written");
+    let dir = root.to_str().expect("path should be utf-8");
+    let manifest = root.join("manifest.json");
+    let manifest_arg = manifest.to_str().expect("path should be utf-8");
+
+    let output = common::run_identedit(&["manifest", "create", dir, "--output", manifest_arg]);
+    assert!(
+        output.status.success(),
+        "manifest create should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let written: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(written["files"], 3);
+    let stored: Value =
+        serde_json::from_str(&fs::read_to_string(&manifest).expect("manifest should be written"))
+            .expect("manifest should be JSON");
+    assert_eq!(stored["version"], 1);
+    assert_eq!(stored["hash_algorithm"], "blake3");
+    assert!(stored["files"]["src/a.py"].is_string());
+
+    let verify = || common::run_identedit(&["manifest", "verify", dir, "--manifest", manifest_arg]);
+    let output = verify();
+    assert!(output.status.success(), "an untouched tree should verify");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["unchanged"], true);
+
+    fs::write(root.join("src/a.py"), "def a():\n    return 10\n")
+        .expect("fixture should be rewritten");
+    fs::remove_file(root.join("src/b.py")).expect("fixture should be removed");
+    fs::write(root.join("src/c.py"), "def c():\n    pass\n").expect("fixture should be written");
+    let output = verify();
+    assert!(
+        !output.status.success(),
+        "a drifted tree should fail verify"
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["unchanged"], false);
+    assert_eq!(response["added"], json!(["src/c.py"]));
+    assert_eq!(response["removed"], json!(["src/b.py"]));
+    assert_eq!(response["changed"][0]["file"], "src/a.py");
+    assert_eq!(
+        response["changed"][0]["expected_hash"],
+        stored["files"]["src/a.py"]
+    );
+    assert_ne!(
+        response["changed"][0]["actual_hash"],
+        response["changed"][0]["expected_hash"]
+    );
+    assert_eq!(
+        response["summary"],
+        json!({"files": 3, "added": 1, "removed": 1, "changed": 1})
+    );
+}
+
+#[test]
+fn verify_rejects_files_that_are_not_manifests() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let bogus = workspace.path().join("bogus.json");
+    fs::write(&bogus, "{\"files\": []}").expect("fixture should be written");
+
+    let output = common::run_identedit(&[
+        "manifest",
+        "verify",
+        workspace.path().to_str().expect("path should be utf-8"),
+        "--manifest",
+        bogus.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        !output.status.success(),
+        "a bogus manifest should be rejected"
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+}
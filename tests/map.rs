@@ -0,0 +1,132 @@
+use std::fs;
+
+use serde_json::Value;
+
+mod common;
+
+#[test]
+fn map_lists_files_with_sizes_hashes_and_outlines() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let root = workspace.path();
+    fs::create_dir_all(root.join("src")).expect("src should be created");
+    fs::create_dir_all(root.join("node_modules/dep")).expect("node_modules should be created");
+    let python = "class Box:\n    def size(self):\n        return 1\n\n\ndef main():\n    pass\n";
+    fs::write(root.join("src/app.py"), python).expect("fixture should be written");
+    fs::write(root.join("config.json"), "{\"name\": \"demo\"}\n")
+        .expect("fixture should be written");
+    fs::write(
+        root.join("node_modules/dep/index.js"),
+        "function dep() {}\n",
+    )
+    .expect("fixture should be written");
+    fs::write(root.join(".env"), "SECRET=1\n").expect("fixture should be written");
+
+    let map = common::run_json(&["map", root.to_str().expect("path should be utf-8")]);
+    let files = map["files"].as_array().expect("files should be an array");
+    let paths = files
+        .iter()
+        .map(|file| file["file"].as_str().expect("file should be a string"))
+        .collect::<Vec<_>>();
+    assert_eq!(paths, vec!["config.json", "src/app.py"]);
+    assert_eq!(map["hash_algorithm"], "blake3");
+
+    let app = &files[1];
+    assert_eq!(app["bytes"], python.len());
+    assert_eq!(app["lines"], 7);
+    let read = common::run_json(&[
+        "read",
+        "--mode",
+        "line",
+        "--json",
+        root.join("src/app.py")
+            .to_str()
+            .expect("path should be utf-8"),
+    ]);
+    assert_eq!(
+        app["hash"],
+        read["file_preconditions"][0]["expected_file_hash"]
+    );
+    let outline = app["outline"]
+        .as_array()
+        .expect("outline should be an array")
+        .iter()
+        .map(|entry| {
+            (
+                entry["kind"]
+                    .as_str()
+                    .expect("kind should exist")
+                    .to_string(),
+                entry["name"]
+                    .as_str()
+                    .expect("name should exist")
+                    .to_string(),
+                entry["line"].as_u64().expect("line should exist"),
+                entry["end_line"].as_u64().expect("end_line should exist"),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert!(outline.contains(&("class_definition".to_string(), "Box".to_string(), 1, 3)));
+    assert!(outline.contains(&("function_definition".to_string(), "size".to_string(), 2, 3)));
+    assert!(outline.contains(&("function_definition".to_string(), "main".to_string(), 6, 7)));
+    assert_eq!(map["summary"]["files"], 2);
+}
+
+#[test]
+fn map_output_writes_compact_json_and_prints_the_summary() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let root = workspace.path();
+    fs::write(root.join("main.py"), "def main():\n    pass\n").expect("fixture should be written");
+    let output = root.join("map.json");
+    let output_arg = output.to_str().expect("path should be utf-8");
+    let root_arg = root.to_str().expect("path should be utf-8");
+
+    let first = common::run_json(&["map", root_arg, "--output", output_arg]);
+    assert_eq!(first["summary"]["files"], 1);
+    assert!(first.get("files").is_none());
+
+    // Mapping again does not pick up the map written by the first run.
+    let second = common::run_json(&["map", root_arg, "--output", output_arg]);
+    assert_eq!(second["summary"]["files"], 1);
+    let written = fs::read_to_string(&output).expect("map should be written");
+    assert!(!written.contains('\n'), "map file should be compact JSON");
+    let map: Value = serde_json::from_str(&written).expect("map should be JSON");
+    assert_eq!(map["files"][0]["file"], "main.py");
+    assert_eq!(map["files"][0]["outline"][0]["name"], "main");
+}
+
+#[test]
+fn map_stops_with_a_timeout_error_once_the_deadline_passes() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    fs::write(workspace.path().join("main.py"), "def main():\n    pass\n")
+        .expect("fixture should be written");
+    let root_arg = workspace.path().to_str().expect("path should be utf-8");
+
+    let output = common::run_identedit(&["map", root_arg, "--timeout-ms", "0"]);
+    assert!(!output.status.success(), "an expired deadline should fail");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "timeout");
+
+    let finished = common::run_json(&["map", root_arg, "--timeout-ms", "60000"]);
+    assert_eq!(finished["summary"]["files"], 1);
+}
+
+#[test]
+fn map_keeps_file_order_whatever_the_concurrency() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    for index in 0..12 {
+        fs::write(
+            workspace.path().join(format!("module_{index:02}.py")),
+            format!("def run_{index}():\n    pass\n"),
+        )
+        .expect("fixture should be written");
+    }
+    let root_arg = workspace.path().to_str().expect("path should be utf-8");
+
+    let parallel = common::run_json(&["map", root_arg]);
+    let serial = common::run_json(&["map", root_arg, "--max-concurrency", "1"]);
+    let capped = common::run_json(&["map", root_arg, "--max-concurrency", "5"]);
+    assert_eq!(parallel["summary"]["files"], 12);
+    assert_eq!(parallel, serial);
+    assert_eq!(parallel, capped);
+    assert_eq!(parallel["files"][11]["file"], "module_11.py");
+}
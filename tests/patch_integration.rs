@@ -1,6 +1,6 @@
 use std::fs;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Output;
 
 use serde_json::{Value, json};
@@ -96,6 +96,76 @@ fn patch_replace_applies_change_in_single_command() {
     );
 }
 
+#[test]
+fn patch_replace_file_and_replace_stdin_write_text_verbatim() {
+    let file_path = copy_fixture_to_temp_python("example.py");
+    let handle = select_named_function_handle(&file_path, "process_*");
+    let identity = handle["identity"]
+        .as_str()
+        .expect("identity should be present");
+    let replacement =
+        "def process_data(value):\n    return f\"{value!r} \\\"quoted\\\" 'single' \\n\"";
+    let mut body_file = Builder::new()
+        .suffix(".txt")
+        .tempfile()
+        .expect("temp body file should be created");
+    body_file
+        .write_all(replacement.as_bytes())
+        .expect("body write should succeed");
+
+    let output = run_identedit(&[
+        "patch",
+        "--identity",
+        identity,
+        "--replace-file",
+        body_file.path().to_str().expect("path should be utf-8"),
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "patch --replace-file failed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let modified = fs::read_to_string(&file_path).expect("modified file should be readable");
+    assert!(modified.contains(replacement));
+
+    let handle = select_named_function_handle(&file_path, "process_*");
+    let identity = handle["identity"]
+        .as_str()
+        .expect("identity should be present");
+    let replacement = "def process_data(value):\n    return \"\\t$HOME `x`\"";
+    let output = run_identedit_with_stdin(
+        &[
+            "patch",
+            "--identity",
+            identity,
+            "--replace-stdin",
+            file_path.to_str().expect("path should be utf-8"),
+        ],
+        replacement,
+    );
+    assert!(
+        output.status.success(),
+        "patch --replace-stdin failed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let modified = fs::read_to_string(&file_path).expect("modified file should be readable");
+    assert!(modified.contains(replacement));
+
+    let output = run_identedit(&[
+        "patch",
+        "--identity",
+        identity,
+        "--replace-file",
+        "/definitely/missing/body.txt",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(!output.status.success());
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["error"]["type"], "io_error");
+}
+
 #[test]
 fn patch_scoped_regex_flag_mode_rewrites_only_inside_target_span() {
     let file_path = create_scoped_regex_fixture();
@@ -168,6 +238,130 @@ fn patch_scoped_regex_flag_mode_rejects_zero_matches() {
     );
 }
 
+#[test]
+fn patch_scoped_regex_count_only_reports_match_spans_without_modifying_file() {
+    let file_path = create_scoped_regex_fixture();
+    let before = fs::read_to_string(&file_path).expect("fixture should be readable");
+    let handle = select_named_function_handle(&file_path, "process_*");
+    let identity = handle["identity"]
+        .as_str()
+        .expect("identity should be present");
+
+    let output = run_identedit(&[
+        "patch",
+        "--identity",
+        identity,
+        "--scoped-regex",
+        "value",
+        "--count-only",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "patch scoped regex --count-only failed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["regex_matches"], 2);
+    assert_eq!(
+        response["matches"][0]["span"],
+        json!({"start": 17, "end": 22})
+    );
+    assert_eq!(response["matches"][1]["start_position"]["line"], 2);
+    assert_eq!(response["matches"][1]["text"], "value");
+    assert!(response.get("summary").is_none());
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("fixture should be readable"),
+        before
+    );
+
+    let output = run_identedit(&[
+        "patch",
+        "--identity",
+        identity,
+        "--scoped-regex",
+        "does_not_exist",
+        "--count-only",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "zero matches is a count, not an error"
+    );
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["regex_matches"], 0);
+}
+
+#[test]
+fn patch_scoped_regex_skip_flags_leave_strings_and_comments_but_rewrite_interpolations() {
+    let mut temp_file = Builder::new()
+        .suffix(".py")
+        .tempfile()
+        .expect("temp python file should be created");
+    temp_file
+        .write_all(
+            b"def greet(name):\n    # name is required\n    label = f\"name: {name} {'name'}\"\n    return label + \"name\"\n",
+        )
+        .expect("fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+    let handle = select_named_function_handle(&file_path, "greet");
+    let identity = handle["identity"]
+        .as_str()
+        .expect("identity should be present");
+
+    let output = run_identedit(&[
+        "patch",
+        "--identity",
+        identity,
+        "--scoped-regex",
+        r"\bname\b",
+        "--scoped-replacement",
+        "who",
+        "--skip-strings",
+        "--skip-comments",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "patch scoped regex with skip flags failed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["regex_replacements"], 2);
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("modified file should be readable"),
+        "def greet(who):\n    # name is required\n    label = f\"name: {who} {'name'}\"\n    return label + \"name\"\n"
+    );
+
+    let handle = select_named_function_handle(&file_path, "greet");
+    let identity = handle["identity"]
+        .as_str()
+        .expect("identity should be present");
+    let output = run_identedit(&[
+        "patch",
+        "--identity",
+        identity,
+        "--scoped-regex",
+        "required",
+        "--scoped-replacement",
+        "needed",
+        "--skip-comments",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(!output.status.success());
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("matched 0 occurrences")),
+        "matches only inside skipped comments should count as zero"
+    );
+}
+
 #[test]
 fn patch_delete_removes_target_node() {
     let file_path = copy_fixture_to_temp_python("example.py");
@@ -539,6 +733,14 @@ fn patch_line_flag_set_line_applies_change() {
     let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
     assert_eq!(response["applied_mode"], "strict");
     assert_eq!(response["operations_applied"], 1);
+    assert_eq!(
+        response["stats"],
+        json!({
+            "lines_added": 1,
+            "lines_removed": 1,
+            "bytes_delta": 0
+        })
+    );
 
     let modified = fs::read_to_string(&file_path).expect("modified file should be readable");
     assert_eq!(modified, "a\nB\n");
@@ -946,6 +1148,120 @@ fn patch_json_node_target_scoped_regex_rejects_zero_matches() {
     );
 }
 
+#[test]
+fn patch_json_node_target_scoped_regex_dry_reports_matches_and_rejects_batching() {
+    let file_path = create_scoped_regex_fixture();
+    let before = fs::read_to_string(&file_path).expect("fixture should be readable");
+    let handle = select_named_function_handle(&file_path, "process_*");
+    let target = json!({
+        "type": "node",
+        "identity": handle["identity"],
+        "kind": handle["kind"],
+        "span_hint": handle["span"],
+        "expected_old_hash": identedit::changeset::hash_text(
+            handle["text"].as_str().expect("text should be string")
+        )
+    });
+    let request = json!({
+        "command": "patch",
+        "file": file_path.to_string_lossy().to_string(),
+        "target": target,
+        "op": {"type": "scoped_regex", "pattern": "value \\+ \\d", "dry": true}
+    });
+
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
+    assert!(
+        output.status.success(),
+        "patch --json dry scoped regex failed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["regex_matches"], 1);
+    assert_eq!(response["matches"][0]["text"], "value + 1");
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("fixture should be readable"),
+        before
+    );
+
+    let request = json!({
+        "command": "patch",
+        "file": file_path.to_string_lossy().to_string(),
+        "target": target,
+        "op": {"type": "scoped_regex", "pattern": "value"}
+    });
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
+    assert!(!output.status.success());
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("needs a replacement"))
+    );
+
+    let request = json!({
+        "command": "patch",
+        "file": file_path.to_string_lossy().to_string(),
+        "operations": [
+            {"target": target, "op": {"type": "scoped_regex", "pattern": "value", "dry": true}}
+        ]
+    });
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
+    assert!(!output.status.success());
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("cannot be batched"))
+    );
+}
+
+#[test]
+fn patch_json_node_target_scoped_regex_skip_strings_respects_template_literals() {
+    let mut temp_file = Builder::new()
+        .suffix(".js")
+        .tempfile()
+        .expect("temp js file should be created");
+    temp_file
+        .write_all(
+            b"function greet(name) {\n  // name here\n  return `name=${name.trim()} ${\"name\"}`;\n}\n",
+        )
+        .expect("fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+    let handle = common::select_first_handle(&file_path, "function_declaration", Some("greet"));
+    let request = json!({
+        "command": "patch",
+        "file": file_path.to_string_lossy().to_string(),
+        "target": {
+            "type": "node",
+            "identity": handle["identity"],
+            "kind": handle["kind"],
+            "span_hint": handle["span"],
+            "expected_old_hash": identedit::changeset::hash_text(
+                handle["text"].as_str().expect("text should be string")
+            )
+        },
+        "op": {
+            "type": "scoped_regex",
+            "pattern": "\\bname\\b",
+            "replacement": "who",
+            "skip_strings": true
+        }
+    });
+
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
+    assert!(
+        output.status.success(),
+        "patch --json scoped regex with skip_strings failed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["regex_replacements"], 3);
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("modified file should be readable"),
+        "function greet(who) {\n  // who here\n  return `name=${who.trim()} ${\"name\"}`;\n}\n"
+    );
+}
+
 #[test]
 fn patch_json_node_target_scoped_regex_preserves_stale_precondition_behavior() {
     let file_path = copy_fixture_to_temp_python("example.py");
@@ -1112,47 +1428,430 @@ fn patch_json_line_target_can_auto_repair() {
 }
 
 #[test]
-fn patch_json_rejects_node_target_with_line_only_op() {
+fn patch_json_operations_apply_node_and_file_ops_in_one_pass() {
     let file_path = copy_fixture_to_temp_python("example.py");
-    let handle = select_named_function_handle(&file_path, "process_*");
-    let request = json!({
-        "command": "patch",
-        "file": file_path.to_string_lossy().to_string(),
-        "target": {
+    let original = fs::read_to_string(&file_path).expect("fixture should be readable");
+    let process = select_named_function_handle(&file_path, "process_data");
+    let helper = select_named_function_handle(&file_path, "helper");
+    let node_target = |handle: &Value| {
+        json!({
             "type": "node",
             "identity": handle["identity"],
             "kind": handle["kind"],
+            "span_hint": handle["span"],
             "expected_old_hash": identedit::changeset::hash_text(
-                handle["text"].as_str().expect("text should be string")
+                handle["text"].as_str().expect("handle text should be present")
             )
-        },
-        "op": {
-            "type": "set_line",
-            "new_text": "x"
-        }
+        })
+    };
+
+    let request = json!({
+        "command": "patch",
+        "file": file_path.to_string_lossy().to_string(),
+        "operations": [
+            {
+                "target": node_target(&process),
+                "op": {"type": "replace", "new_text": "def process_data(value):\n    return value + 2"}
+            },
+            {
+                "target": node_target(&helper),
+                "op": {"type": "scoped_regex", "pattern": "helper", "replacement": "assist"}
+            },
+            {
+                "target": {
+                    "type": "file_end",
+                    "expected_file_hash": identedit::hash::hash_text(&original)
+                },
+                "op": {"type": "insert", "new_text": "\n\nLIMIT = 3\n"}
+            }
+        ]
     });
 
     let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
     assert!(
-        !output.status.success(),
-        "node target should reject line-only operation payload"
+        output.status.success(),
+        "batched patch should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
     );
-
     let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
-    assert_eq!(response["error"]["type"], "invalid_request");
+    assert_eq!(response["summary"]["operations_applied"], 3);
+    assert_eq!(response["regex_replacements"], 2);
+
+    let modified = fs::read_to_string(&file_path).expect("modified file should be readable");
+    assert_eq!(
+        modified,
+        "def process_data(value):\n    return value + 2\n\n\ndef assist():\n    return \"assist\"\n\n\nLIMIT = 3\n"
+    );
 }
 
 #[test]
-fn patch_json_rejects_line_target_with_node_only_op() {
-    let source = "a\nb\n";
-    let mut temp_file = Builder::new()
-        .suffix(".txt")
-        .tempfile()
-        .expect("temp text file should be created");
-    temp_file
-        .write_all(source.as_bytes())
-        .expect("fixture write should succeed");
-    let file_path = temp_file.keep().expect("temp file should persist").1;
+fn patch_json_operations_reject_overlap_and_keep_file_unchanged() {
+    let file_path = copy_fixture_to_temp_python("example.py");
+    let original = fs::read_to_string(&file_path).expect("fixture should be readable");
+    let process = select_named_function_handle(&file_path, "process_data");
+    let target = json!({
+        "type": "node",
+        "identity": process["identity"],
+        "kind": process["kind"],
+        "span_hint": process["span"],
+        "expected_old_hash": identedit::changeset::hash_text(
+            process["text"].as_str().expect("handle text should be present")
+        )
+    });
+
+    let request = json!({
+        "command": "patch",
+        "file": file_path.to_string_lossy().to_string(),
+        "operations": [
+            {"target": target, "op": {"type": "replace", "new_text": "def process_data(value):\n    return 0"}},
+            {"target": target, "op": {"type": "delete"}}
+        ]
+    });
+
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
+    assert!(!output.status.success(), "overlapping operations must fail");
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        original
+    );
+}
+
+#[test]
+fn patch_json_operations_batch_line_edits_bottom_up() {
+    let source = "a\nb\nc\nd\ne\nf\n";
+    let mut temp_file = Builder::new()
+        .suffix(".txt")
+        .tempfile()
+        .expect("temp text file should be created");
+    temp_file
+        .write_all(source.as_bytes())
+        .expect("fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+
+    let request = json!({
+        "command": "patch",
+        "file": file_path.to_string_lossy().to_string(),
+        "operations": [
+            {
+                "target": {"type": "line", "anchor": line_ref(source, 1)},
+                "op": {"type": "insert_after", "text": "a2\na3"}
+            },
+            {
+                "target": {"type": "line", "anchor": line_ref(source, 3), "end_anchor": line_ref(source, 4)},
+                "op": {"type": "replace_lines", "new_text": "CD"}
+            },
+            {
+                "target": {"type": "line", "anchor": line_ref(source, 6)},
+                "op": {"type": "set_line", "new_text": "F"}
+            }
+        ]
+    });
+
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
+    assert!(
+        output.status.success(),
+        "batched line patch should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["operations_applied"], 3);
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("modified file should be readable"),
+        "a\na2\na3\nb\nCD\ne\nF\n"
+    );
+
+    let mixed = json!({
+        "command": "patch",
+        "file": file_path.to_string_lossy().to_string(),
+        "operations": [
+            {
+                "target": {"type": "line", "anchor": line_ref("a\n", 1)},
+                "op": {"type": "set_line", "new_text": "A"}
+            },
+            {
+                "target": {"type": "file_end", "expected_file_hash": "0000000000000000"},
+                "op": {"type": "insert", "new_text": "z\n"}
+            }
+        ]
+    });
+    let output = run_identedit_with_stdin(&["patch", "--json"], &mixed.to_string());
+    assert!(!output.status.success(), "mixed targets should be rejected");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("use `edit`")),
+        "mixed rejection should point at edit"
+    );
+}
+
+#[test]
+fn patch_json_line_target_edits_apply_together_in_one_write() {
+    let source = "a\nb\nc\nd\n";
+    let mut temp_file = Builder::new()
+        .suffix(".txt")
+        .tempfile()
+        .expect("temp text file should be created");
+    temp_file
+        .write_all(source.as_bytes())
+        .expect("fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+    let batch = |edits: Value| {
+        json!({
+            "command": "patch",
+            "file": file_path.to_string_lossy().to_string(),
+            "target": {"type": "line", "edits": edits}
+        })
+        .to_string()
+    };
+
+    let stale = batch(json!([
+        {"anchor": line_ref(source, 1), "op": {"type": "set_line", "new_text": "A"}},
+        {"anchor": line_ref("x\nx\n", 2), "op": {"type": "set_line", "new_text": "B"}}
+    ]));
+    let output = run_identedit_with_stdin(&["patch", "--json"], &stale);
+    assert!(
+        !output.status.success(),
+        "a stale anchor should reject the batch"
+    );
+    let overlapping = batch(json!([
+        {"anchor": line_ref(source, 2), "op": {"type": "set_line", "new_text": "B"}},
+        {"anchor": line_ref(source, 1), "end_anchor": line_ref(source, 3), "op": {"type": "replace_lines", "new_text": "ABC"}}
+    ]));
+    let output = run_identedit_with_stdin(&["patch", "--json"], &overlapping);
+    assert!(
+        !output.status.success(),
+        "overlapping edits should reject the batch"
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+    let output = run_identedit_with_stdin(&["patch", "--json"], &batch(json!([])));
+    assert!(
+        !output.status.success(),
+        "an empty batch should be rejected"
+    );
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        source,
+        "rejected batches must not write"
+    );
+
+    let request = batch(json!([
+        {"anchor": line_ref(source, 4), "op": {"type": "insert_after", "text": "e"}},
+        {"anchor": line_ref(source, 1), "op": {"type": "set_line", "new_text": "A"}},
+        {"anchor": line_ref(source, 2), "end_anchor": line_ref(source, 3), "op": {"type": "replace_lines", "new_text": "BC"}}
+    ]));
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request);
+    assert!(
+        output.status.success(),
+        "line target edits should apply: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["operations_total"], 3);
+    assert_eq!(response["operations_applied"], 3);
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("modified file should be readable"),
+        "A\nBC\nd\ne\n"
+    );
+}
+
+#[test]
+fn patch_offset_targets_a_line_relative_to_a_verified_anchor() {
+    let source = "# landmark\nstamp = 1\nvalue = 2\n";
+    let mut temp_file = Builder::new()
+        .suffix(".txt")
+        .tempfile()
+        .expect("temp text file should be created");
+    temp_file
+        .write_all(source.as_bytes())
+        .expect("fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+    let file_arg = file_path.to_str().expect("path should be utf-8");
+    let landmark = line_ref(source, 1);
+
+    let output = run_identedit(&[
+        "patch",
+        "--at",
+        &landmark,
+        "--offset",
+        "+1",
+        "--set-line",
+        "stamp = 7",
+        file_arg,
+    ]);
+    assert!(
+        output.status.success(),
+        "offset flag patch should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = "# landmark\nstamp = 7\nvalue = 2\n";
+    let request = json!({
+        "command": "patch",
+        "file": file_path.to_string_lossy().to_string(),
+        "target": {"type": "line", "anchor": line_ref(updated, 3), "offset": -1},
+        "op": {"type": "set_line", "new_text": "stamp = 8"}
+    });
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
+    assert!(
+        output.status.success(),
+        "offset JSON patch should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        "# landmark\nstamp = 8\nvalue = 2\n"
+    );
+
+    let output = run_identedit(&[
+        "patch",
+        "--at",
+        &landmark,
+        "--offset",
+        "-1",
+        "--set-line",
+        "x",
+        file_arg,
+    ]);
+    assert!(!output.status.success(), "offset before line 1 should fail");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+
+    let output = run_identedit(&[
+        "patch",
+        "--at",
+        &line_ref("# moved\n", 1),
+        "--offset",
+        "1",
+        "--set-line",
+        "x",
+        file_arg,
+    ]);
+    assert!(!output.status.success(), "a stale landmark should fail");
+
+    let output = run_identedit(&[
+        "patch", "--at", "file-end", "--offset", "1", "--insert", "x\n", file_arg,
+    ]);
+    assert!(
+        !output.status.success(),
+        "offset needs a line anchor target"
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        "# landmark\nstamp = 8\nvalue = 2\n"
+    );
+}
+
+#[test]
+fn patch_json_line_match_resolves_the_target_line_by_pattern() {
+    let source = "[package]\nversion = \"0.1.0\"\n\n[dev]\nversion = \"0.2.0\"\n";
+    let mut temp_file = Builder::new()
+        .suffix(".txt")
+        .tempfile()
+        .expect("temp text file should be created");
+    temp_file
+        .write_all(source.as_bytes())
+        .expect("fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+    let patch = |target: Value, new_text: &str| {
+        let request = json!({
+            "command": "patch",
+            "file": file_path.to_string_lossy().to_string(),
+            "target": target,
+            "op": {"type": "set_line", "new_text": new_text}
+        });
+        run_identedit_with_stdin(&["patch", "--json"], &request.to_string())
+    };
+
+    let output = patch(json!({"type": "line", "match": "^version"}), "x");
+    assert!(!output.status.success(), "two matches need an occurrence");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+    let output = patch(
+        json!({"type": "line", "match": "^version", "occurrence": 2, "expected_match_count": 3}),
+        "x",
+    );
+    assert!(!output.status.success(), "a wrong match count should fail");
+    let output = patch(
+        json!({"type": "line", "match": "^version", "anchor": line_ref(source, 2)}),
+        "x",
+    );
+    assert!(!output.status.success(), "anchor and match are exclusive");
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        source
+    );
+
+    let output = patch(
+        json!({"type": "line", "match": "^version", "occurrence": 2, "expected_match_count": 2}),
+        "version = \"0.3.0\"",
+    );
+    assert!(
+        output.status.success(),
+        "second occurrence should be patched: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let output = patch(
+        json!({"type": "line", "match": "^\\[dev\\]$", "offset": -1}),
+        "# dev dependencies",
+    );
+    assert!(
+        output.status.success(),
+        "match combined with offset should be patched: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        "[package]\nversion = \"0.1.0\"\n# dev dependencies\n[dev]\nversion = \"0.3.0\"\n"
+    );
+}
+
+#[test]
+fn patch_json_rejects_node_target_with_line_only_op() {
+    let file_path = copy_fixture_to_temp_python("example.py");
+    let handle = select_named_function_handle(&file_path, "process_*");
+    let request = json!({
+        "command": "patch",
+        "file": file_path.to_string_lossy().to_string(),
+        "target": {
+            "type": "node",
+            "identity": handle["identity"],
+            "kind": handle["kind"],
+            "expected_old_hash": identedit::changeset::hash_text(
+                handle["text"].as_str().expect("text should be string")
+            )
+        },
+        "op": {
+            "type": "set_line",
+            "new_text": "x"
+        }
+    });
+
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
+    assert!(
+        !output.status.success(),
+        "node target should reject line-only operation payload"
+    );
+
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+}
+
+#[test]
+fn patch_json_rejects_line_target_with_node_only_op() {
+    let source = "a\nb\n";
+    let mut temp_file = Builder::new()
+        .suffix(".txt")
+        .tempfile()
+        .expect("temp text file should be created");
+    temp_file
+        .write_all(source.as_bytes())
+        .expect("fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
 
     let request = json!({
         "command": "patch",
@@ -1553,7 +2252,7 @@ fn patch_json_config_path_set_create_missing_preserves_crlf_yaml_newlines() {
 }
 
 #[test]
-fn patch_json_config_path_set_create_missing_rejects_yaml_multi_document() {
+fn patch_json_config_path_set_create_missing_inserts_into_first_yaml_document() {
     let mut temp_file = Builder::new()
         .suffix(".yaml")
         .tempfile()
@@ -1579,22 +2278,20 @@ fn patch_json_config_path_set_create_missing_rejects_yaml_multi_document() {
 
     let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
     assert!(
-        !output.status.success(),
-        "yaml multi-document create-missing should be rejected"
+        output.status.success(),
+        "yaml multi-document create-missing should edit the first document: {}",
+        String::from_utf8_lossy(&output.stdout)
     );
 
-    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
-    assert_eq!(response["error"]["type"], "invalid_request");
-    assert!(
-        response["error"]["message"]
-            .as_str()
-            .is_some_and(|message| message.contains("multiple YAML documents")),
-        "error should explain multi-document create-missing limitation"
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(
+        updated,
+        "---\nservice:\n  name: identedit\n  sidecar:\n    port: 9000\n---\nmetadata:\n  owner: team\n"
     );
 }
 
 #[test]
-fn patch_json_config_path_set_create_missing_rejects_yaml_anchor_alias() {
+fn patch_json_config_path_set_create_missing_inserts_beside_yaml_anchor_alias() {
     let mut temp_file = Builder::new()
         .suffix(".yaml")
         .tempfile()
@@ -1622,17 +2319,15 @@ fn patch_json_config_path_set_create_missing_rejects_yaml_anchor_alias() {
 
     let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
     assert!(
-        !output.status.success(),
-        "yaml anchor/alias create-missing should be rejected"
+        output.status.success(),
+        "yaml anchor/alias create-missing should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
     );
 
-    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
-    assert_eq!(response["error"]["type"], "invalid_request");
-    assert!(
-        response["error"]["message"]
-            .as_str()
-            .is_some_and(|message| message.contains("anchor/alias")),
-        "error should explain anchor/alias create-missing limitation"
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(
+        updated,
+        "defaults: &defaults\n  retries: 2\nservice:\n  <<: *defaults\n  name: identedit\n  sidecar:\n    port: 9000\n"
     );
 }
 
@@ -1798,7 +2493,7 @@ fn patch_json_config_path_set_create_missing_existing_toml_path_preserves_commen
 }
 
 #[test]
-fn patch_json_config_path_set_create_missing_rejects_toml_comment_preserving_fallback() {
+fn patch_json_config_path_set_create_missing_missing_path_preserves_toml_comments() {
     let mut temp_file = Builder::new()
         .suffix(".toml")
         .tempfile()
@@ -1824,17 +2519,15 @@ fn patch_json_config_path_set_create_missing_rejects_toml_comment_preserving_fal
 
     let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
     assert!(
-        !output.status.success(),
-        "missing-path TOML create-missing with comments should be rejected for safety"
+        output.status.success(),
+        "missing-path TOML create-missing should keep comments: {}",
+        String::from_utf8_lossy(&output.stdout)
     );
 
-    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
-    assert_eq!(response["error"]["type"], "invalid_request");
-    assert!(
-        response["error"]["message"]
-            .as_str()
-            .is_some_and(|message| message.contains("TOML comments")),
-        "error should explain comment-preservation limitation"
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(
+        updated,
+        "# keep-this-comment\n[server]\nhost = \"127.0.0.1\"\nport = 9090\n"
     );
 }
 
@@ -1961,7 +2654,7 @@ fn patch_flag_config_path_create_missing_rejects_unrelated_line_flags() {
 }
 
 #[test]
-fn patch_json_config_path_set_create_missing_rejects_yaml_comment_preserving_fallback() {
+fn patch_json_config_path_set_create_missing_missing_path_preserves_yaml_comments() {
     let mut temp_file = Builder::new()
         .suffix(".yaml")
         .tempfile()
@@ -1987,17 +2680,15 @@ fn patch_json_config_path_set_create_missing_rejects_yaml_comment_preserving_fal
 
     let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
     assert!(
-        !output.status.success(),
-        "missing-path YAML create-missing with comments should be rejected for safety"
+        output.status.success(),
+        "missing-path YAML create-missing should keep comments: {}",
+        String::from_utf8_lossy(&output.stdout)
     );
 
-    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
-    assert_eq!(response["error"]["type"], "invalid_request");
-    assert!(
-        response["error"]["message"]
-            .as_str()
-            .is_some_and(|message| message.contains("YAML comments")),
-        "error should explain YAML comment-preservation limitation"
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(
+        updated,
+        "service:\n  # keep-this-comment\n  name: identedit\n  sidecar:\n    port: 9000\n"
     );
 }
 
@@ -2177,16 +2868,15 @@ fn patch_json_config_path_set_create_missing_array_oob_does_not_mutate_file() {
 }
 
 #[test]
-fn patch_json_config_path_yaml_comment_rejection_does_not_mutate_file() {
+fn patch_json_config_path_create_missing_extends_yaml_flow_mapping() {
     let mut temp_file = Builder::new()
         .suffix(".yaml")
         .tempfile()
         .expect("temp yaml file should be created");
     temp_file
-        .write_all(b"service:\n  # keep-this-comment\n  name: identedit\n")
+        .write_all(b"service: {name: identedit} # keep-this-comment\n")
         .expect("yaml fixture write should succeed");
     let file_path = temp_file.keep().expect("temp file should persist").1;
-    let before = fs::read_to_string(&file_path).expect("fixture should be readable");
 
     let request = json!({
         "command": "patch",
@@ -2203,19 +2893,27 @@ fn patch_json_config_path_yaml_comment_rejection_does_not_mutate_file() {
     });
 
     let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
-    assert!(!output.status.success(), "operation should fail");
-    let after = fs::read_to_string(&file_path).expect("fixture should be readable");
-    assert_eq!(before, after, "yaml rejection must not mutate file");
+    assert!(
+        output.status.success(),
+        "flow mapping create-missing should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(
+        updated,
+        "service: {name: identedit, sidecar: {port: 9000}} # keep-this-comment\n"
+    );
 }
 
 #[test]
-fn patch_json_config_path_toml_comment_rejection_does_not_mutate_file() {
+fn patch_json_config_path_create_missing_through_toml_scalar_does_not_mutate_file() {
     let mut temp_file = Builder::new()
         .suffix(".toml")
         .tempfile()
         .expect("temp toml file should be created");
     temp_file
-        .write_all(b"# keep-this-comment\n[server]\nhost = \"127.0.0.1\"\n")
+        .write_all(b"# keep-this-comment\n[server]\nport = 8080\n")
         .expect("toml fixture write should succeed");
     let file_path = temp_file.keep().expect("temp file should persist").1;
     let before = fs::read_to_string(&file_path).expect("fixture should be readable");
@@ -2225,29 +2923,38 @@ fn patch_json_config_path_toml_comment_rejection_does_not_mutate_file() {
         "file": file_path.to_string_lossy().to_string(),
         "target": {
             "type": "config_path",
-            "path": "server.port"
+            "path": "server.port.tls"
         },
         "op": {
             "type": "set",
-            "new_text": "9090",
+            "new_text": "true",
             "create_missing": true
         }
     });
 
     let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
     assert!(!output.status.success(), "operation should fail");
-    let after = fs::read_to_string(&file_path).expect("fixture should be readable");
-    assert_eq!(before, after, "toml rejection must not mutate file");
-}
 
-#[test]
-fn patch_json_config_path_yaml_multi_document_rejection_does_not_mutate_file() {
-    let mut temp_file = Builder::new()
-        .suffix(".yaml")
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("expected mapping/object")),
+        "error should explain the scalar blocking create-missing"
+    );
+    let after = fs::read_to_string(&file_path).expect("fixture should be readable");
+    assert_eq!(before, after, "rejected operation must not mutate file");
+}
+
+#[test]
+fn patch_json_config_path_create_missing_rejects_multiline_value_in_yaml_flow_mapping() {
+    let mut temp_file = Builder::new()
+        .suffix(".yaml")
         .tempfile()
         .expect("temp yaml file should be created");
     temp_file
-        .write_all(b"---\nservice:\n  name: identedit\n---\nmetadata:\n  owner: team\n")
+        .write_all(b"service: {name: identedit}\n")
         .expect("yaml fixture write should succeed");
     let file_path = temp_file.keep().expect("temp file should persist").1;
     let before = fs::read_to_string(&file_path).expect("fixture should be readable");
@@ -2257,34 +2964,38 @@ fn patch_json_config_path_yaml_multi_document_rejection_does_not_mutate_file() {
         "file": file_path.to_string_lossy().to_string(),
         "target": {
             "type": "config_path",
-            "path": "service.sidecar.port"
+            "path": "service.env"
         },
         "op": {
             "type": "set",
-            "new_text": "9000",
+            "new_text": "A: 1\nB: 2",
             "create_missing": true
         }
     });
 
     let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
     assert!(!output.status.success(), "operation should fail");
-    let after = fs::read_to_string(&file_path).expect("fixture should be readable");
-    assert_eq!(
-        before, after,
-        "multi-document rejection must not mutate file"
+
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("flow mapping")),
+        "error should mention the flow mapping limitation"
     );
+    let after = fs::read_to_string(&file_path).expect("fixture should be readable");
+    assert_eq!(before, after, "rejected operation must not mutate file");
 }
 
 #[test]
-fn patch_json_config_path_yaml_anchor_alias_rejection_does_not_mutate_file() {
+fn patch_json_config_path_create_missing_through_yaml_alias_does_not_mutate_file() {
     let mut temp_file = Builder::new()
         .suffix(".yaml")
         .tempfile()
         .expect("temp yaml file should be created");
     temp_file
-        .write_all(
-            b"defaults: &defaults\n  retries: 2\nservice:\n  <<: *defaults\n  name: identedit\n",
-        )
+        .write_all(b"defaults: &defaults\n  retries: 2\nservice: *defaults\n")
         .expect("yaml fixture write should succeed");
     let file_path = temp_file.keep().expect("temp file should persist").1;
     let before = fs::read_to_string(&file_path).expect("fixture should be readable");
@@ -2305,8 +3016,17 @@ fn patch_json_config_path_yaml_anchor_alias_rejection_does_not_mutate_file() {
 
     let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
     assert!(!output.status.success(), "operation should fail");
+
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("alias")),
+        "error should mention the alias blocking create-missing"
+    );
     let after = fs::read_to_string(&file_path).expect("fixture should be readable");
-    assert_eq!(before, after, "anchor/alias rejection must not mutate file");
+    assert_eq!(before, after, "rejected operation must not mutate file");
 }
 
 #[test]
@@ -2416,7 +3136,7 @@ fn patch_json_config_path_missing_path_without_create_missing_bypasses_toml_comm
 }
 
 #[test]
-fn patch_json_config_path_create_missing_existing_anchor_path_rejects_with_no_mutation() {
+fn patch_json_config_path_create_missing_existing_anchor_path_updates_value() {
     let mut temp_file = Builder::new()
         .suffix(".yaml")
         .tempfile()
@@ -2442,16 +3162,18 @@ fn patch_json_config_path_create_missing_existing_anchor_path_rejects_with_no_mu
         }
     });
 
-    let before = fs::read_to_string(&file_path).expect("fixture should be readable");
     let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
     assert!(
-        !output.status.success(),
-        "anchor/alias yaml should be rejected in create-missing mode"
+        output.status.success(),
+        "existing anchored path should be updated in place: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(
+        updated,
+        "defaults: &defaults\n  retries: 5\nservice:\n  <<: *defaults\n  name: identedit\n"
     );
-    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
-    assert_eq!(response["error"]["type"], "invalid_request");
-    let after = fs::read_to_string(&file_path).expect("fixture should be readable");
-    assert_eq!(before, after, "rejected operation must not mutate file");
 }
 
 #[test]
@@ -2543,7 +3265,7 @@ fn patch_json_config_path_create_missing_invalid_toml_value_does_not_mutate_file
 }
 
 #[test]
-fn patch_flag_config_path_create_missing_rejects_yaml_comment_fallback() {
+fn patch_flag_config_path_create_missing_preserves_yaml_comments() {
     let mut temp_file = Builder::new()
         .suffix(".yaml")
         .tempfile()
@@ -2563,13 +3285,20 @@ fn patch_flag_config_path_create_missing_rejects_yaml_comment_fallback() {
         file_path.to_str().expect("path should be utf-8"),
     ]);
     assert!(
-        !output.status.success(),
-        "flag-mode YAML fallback with comments should be rejected"
+        output.status.success(),
+        "flag-mode YAML create-missing should keep comments: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(
+        updated,
+        "service:\n  # keep-this-comment\n  name: identedit\n  sidecar:\n    port: 9000\n"
     );
 }
 
 #[test]
-fn patch_flag_config_path_create_missing_rejects_toml_comment_fallback() {
+fn patch_flag_config_path_create_missing_preserves_toml_comments() {
     let mut temp_file = Builder::new()
         .suffix(".toml")
         .tempfile()
@@ -2589,13 +3318,53 @@ fn patch_flag_config_path_create_missing_rejects_toml_comment_fallback() {
         file_path.to_str().expect("path should be utf-8"),
     ]);
     assert!(
-        !output.status.success(),
-        "flag-mode TOML fallback with comments should be rejected"
+        output.status.success(),
+        "flag-mode TOML create-missing should keep comments: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(
+        updated,
+        "# keep-this-comment\n[server]\nhost = \"127.0.0.1\"\nport = 9090\n"
+    );
+}
+
+#[test]
+fn patch_flag_config_path_create_missing_inserts_into_sequence_item_below_leading_comment() {
+    let mut temp_file = Builder::new()
+        .suffix(".yaml")
+        .tempfile()
+        .expect("temp yaml file should be created");
+    temp_file
+        .write_all(b"# deployment targets\ntargets:\n  - name: api\n")
+        .expect("yaml fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "targets[0].port",
+        "--set-value",
+        "8080",
+        "--create-missing",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "flag-mode YAML create-missing should address sequence items: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(
+        updated,
+        "# deployment targets\ntargets:\n  - name: api\n    port: 8080\n"
     );
 }
 
 #[test]
-fn patch_flag_config_path_create_missing_rejects_yaml_multi_document() {
+fn patch_flag_config_path_create_missing_inserts_into_first_yaml_document() {
     let mut temp_file = Builder::new()
         .suffix(".yaml")
         .tempfile()
@@ -2615,13 +3384,20 @@ fn patch_flag_config_path_create_missing_rejects_yaml_multi_document() {
         file_path.to_str().expect("path should be utf-8"),
     ]);
     assert!(
-        !output.status.success(),
-        "flag-mode YAML multi-document fallback should be rejected"
+        output.status.success(),
+        "flag-mode YAML create-missing should edit the first document: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(
+        updated,
+        "---\nservice:\n  name: identedit\n  sidecar:\n    port: 9000\n---\nmetadata:\n  owner: team\n"
     );
 }
 
 #[test]
-fn patch_flag_config_path_create_missing_rejects_yaml_anchor_alias() {
+fn patch_flag_config_path_create_missing_inserts_beside_yaml_anchor_alias() {
     let mut temp_file = Builder::new()
         .suffix(".yaml")
         .tempfile()
@@ -2643,8 +3419,15 @@ fn patch_flag_config_path_create_missing_rejects_yaml_anchor_alias() {
         file_path.to_str().expect("path should be utf-8"),
     ]);
     assert!(
-        !output.status.success(),
-        "flag-mode YAML anchor/alias fallback should be rejected"
+        output.status.success(),
+        "flag-mode YAML create-missing should keep anchors and aliases: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(
+        updated,
+        "defaults: &defaults\n  retries: 2\nservice:\n  <<: *defaults\n  name: identedit\n  sidecar:\n    port: 9000\n"
     );
 }
 
@@ -2703,15 +3486,16 @@ fn patch_flag_config_path_create_missing_existing_yaml_path_preserves_comment()
 }
 
 #[test]
-fn patch_flag_config_path_create_missing_yaml_comment_error_mentions_comment() {
+fn patch_flag_config_path_create_missing_through_yaml_alias_is_rejected() {
     let mut temp_file = Builder::new()
         .suffix(".yaml")
         .tempfile()
         .expect("temp yaml file should be created");
     temp_file
-        .write_all(b"service:\n  # keep-this-comment\n  name: identedit\n")
+        .write_all(b"defaults: &defaults\n  retries: 2\nservice: *defaults\n")
         .expect("yaml fixture write should succeed");
     let file_path = temp_file.keep().expect("temp file should persist").1;
+    let before = fs::read_to_string(&file_path).expect("fixture should be readable");
 
     let output = run_identedit(&[
         "patch",
@@ -2729,40 +3513,45 @@ fn patch_flag_config_path_create_missing_yaml_comment_error_mentions_comment() {
     assert!(
         response["error"]["message"]
             .as_str()
-            .is_some_and(|message| message.contains("YAML comments")),
-        "error should mention YAML comments limitation"
+            .is_some_and(|message| message.contains("alias")),
+        "error should mention the alias blocking create-missing"
     );
+    let after = fs::read_to_string(&file_path).expect("fixture should be readable");
+    assert_eq!(before, after, "rejected operation must not mutate file");
 }
 
 #[test]
-fn patch_flag_config_path_create_missing_toml_comment_error_mentions_comment() {
+fn patch_flag_config_path_create_missing_adds_toml_subtable_next_to_parent() {
     let mut temp_file = Builder::new()
         .suffix(".toml")
         .tempfile()
         .expect("temp toml file should be created");
     temp_file
-        .write_all(b"# keep-this-comment\n[server]\nhost = \"127.0.0.1\"\n")
+        .write_all(
+            b"# keep-this-comment\n[server]\nhost = \"127.0.0.1\" # loopback\n\n[logging]\nlevel = \"info\"\n",
+        )
         .expect("toml fixture write should succeed");
     let file_path = temp_file.keep().expect("temp file should persist").1;
 
     let output = run_identedit(&[
         "patch",
         "--config-path",
-        "server.port",
+        "server.tls.enabled",
         "--set-value",
-        "9090",
+        "true",
         "--create-missing",
         file_path.to_str().expect("path should be utf-8"),
     ]);
-    assert!(!output.status.success(), "operation should fail");
-
-    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
-    assert_eq!(response["error"]["type"], "invalid_request");
     assert!(
-        response["error"]["message"]
-            .as_str()
-            .is_some_and(|message| message.contains("TOML comments")),
-        "error should mention TOML comments limitation"
+        output.status.success(),
+        "flag-mode TOML create-missing should add the sub-table after its parent: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(
+        updated,
+        "# keep-this-comment\n[server]\nhost = \"127.0.0.1\" # loopback\n\n[server.tls]\nenabled = true\n\n[logging]\nlevel = \"info\"\n"
     );
 }
 
@@ -2894,81 +3683,73 @@ fn patch_json_config_path_create_missing_existing_multi_document_path_with_hash_
 }
 
 #[test]
-fn patch_flag_yaml_comment_rejection_keeps_file_unchanged() {
+fn patch_flag_yaml_create_missing_keeps_trailing_comment_on_last_pair() {
     let mut temp_file = Builder::new()
         .suffix(".yaml")
         .tempfile()
         .expect("temp yaml file should be created");
     temp_file
-        .write_all(b"service:\n  # keep-this-comment\n  name: identedit\n")
+        .write_all(b"service:\n  name: identedit # keep-this-comment\n")
         .expect("yaml fixture write should succeed");
     let file_path = temp_file.keep().expect("temp file should persist").1;
-    let before = fs::read_to_string(&file_path).expect("fixture should be readable");
 
     let output = run_identedit(&[
         "patch",
         "--config-path",
-        "service.sidecar.port",
+        "service.port",
         "--set-value",
         "9000",
         "--create-missing",
         file_path.to_str().expect("path should be utf-8"),
     ]);
-    assert!(!output.status.success(), "operation should fail");
-    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
-    assert_eq!(response["error"]["type"], "invalid_request");
     assert!(
-        response["error"]["message"]
-            .as_str()
-            .is_some_and(|message| message.contains("YAML comments")),
-        "error should mention YAML comments limitation"
+        output.status.success(),
+        "flag-mode YAML create-missing should insert after trailing comments: {}",
+        String::from_utf8_lossy(&output.stdout)
     );
-    let after = fs::read_to_string(&file_path).expect("fixture should be readable");
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
     assert_eq!(
-        before, after,
-        "rejected flag-mode operation must not mutate file"
+        updated,
+        "service:\n  name: identedit # keep-this-comment\n  port: 9000\n"
     );
 }
 
 #[test]
-fn patch_flag_toml_comment_rejection_keeps_file_unchanged() {
+fn patch_flag_toml_create_missing_extends_inline_table() {
     let mut temp_file = Builder::new()
         .suffix(".toml")
         .tempfile()
         .expect("temp toml file should be created");
     temp_file
-        .write_all(b"# keep-this-comment\n[server]\nhost = \"127.0.0.1\"\n")
+        .write_all(b"# keep-this-comment\nserver = { host = \"127.0.0.1\" }\n")
         .expect("toml fixture write should succeed");
     let file_path = temp_file.keep().expect("temp file should persist").1;
-    let before = fs::read_to_string(&file_path).expect("fixture should be readable");
 
     let output = run_identedit(&[
         "patch",
         "--config-path",
-        "server.port",
+        "server.tls.enabled",
         "--set-value",
-        "9090",
+        "true",
         "--create-missing",
         file_path.to_str().expect("path should be utf-8"),
     ]);
-    assert!(!output.status.success(), "operation should fail");
-    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
-    assert_eq!(response["error"]["type"], "invalid_request");
     assert!(
-        response["error"]["message"]
-            .as_str()
-            .is_some_and(|message| message.contains("TOML comments")),
-        "error should mention TOML comments limitation"
+        output.status.success(),
+        "flag-mode TOML create-missing should keep inline tables inline: {}",
+        String::from_utf8_lossy(&output.stdout)
     );
-    let after = fs::read_to_string(&file_path).expect("fixture should be readable");
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
     assert_eq!(
-        before, after,
-        "rejected flag-mode operation must not mutate file"
+        updated,
+        "# keep-this-comment\nserver = { host = \"127.0.0.1\", tls = { enabled = true } }\n"
     );
 }
 
 #[test]
-fn patch_flag_yaml_multidoc_rejection_keeps_file_unchanged() {
+fn patch_flag_yaml_multidoc_create_missing_leaves_later_documents_unchanged() {
     let mut temp_file = Builder::new()
         .suffix(".yaml")
         .tempfile()
@@ -2977,29 +3758,31 @@ fn patch_flag_yaml_multidoc_rejection_keeps_file_unchanged() {
         .write_all(b"---\nservice:\n  name: identedit\n---\nmetadata:\n  owner: team\n")
         .expect("yaml fixture write should succeed");
     let file_path = temp_file.keep().expect("temp file should persist").1;
-    let before = fs::read_to_string(&file_path).expect("fixture should be readable");
 
     let output = run_identedit(&[
         "patch",
         "--config-path",
-        "service.sidecar.port",
+        "owner",
         "--set-value",
-        "9000",
+        "team",
         "--create-missing",
         file_path.to_str().expect("path should be utf-8"),
     ]);
-    assert!(!output.status.success(), "operation should fail");
-    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
-    assert_eq!(response["error"]["type"], "invalid_request");
-    let after = fs::read_to_string(&file_path).expect("fixture should be readable");
+    assert!(
+        output.status.success(),
+        "flag-mode YAML create-missing should add a top-level key to the first document: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
     assert_eq!(
-        before, after,
-        "rejected flag-mode operation must not mutate file"
+        updated,
+        "---\nservice:\n  name: identedit\nowner: team\n---\nmetadata:\n  owner: team\n"
     );
 }
 
 #[test]
-fn patch_flag_yaml_anchor_alias_rejection_keeps_file_unchanged() {
+fn patch_flag_yaml_create_missing_extends_anchored_mapping() {
     let mut temp_file = Builder::new()
         .suffix(".yaml")
         .tempfile()
@@ -3010,24 +3793,26 @@ fn patch_flag_yaml_anchor_alias_rejection_keeps_file_unchanged() {
         )
         .expect("yaml fixture write should succeed");
     let file_path = temp_file.keep().expect("temp file should persist").1;
-    let before = fs::read_to_string(&file_path).expect("fixture should be readable");
 
     let output = run_identedit(&[
         "patch",
         "--config-path",
-        "service.sidecar.port",
+        "defaults.timeout",
         "--set-value",
-        "9000",
+        "30",
         "--create-missing",
         file_path.to_str().expect("path should be utf-8"),
     ]);
-    assert!(!output.status.success(), "operation should fail");
-    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
-    assert_eq!(response["error"]["type"], "invalid_request");
-    let after = fs::read_to_string(&file_path).expect("fixture should be readable");
+    assert!(
+        output.status.success(),
+        "flag-mode YAML create-missing should extend the anchored mapping: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
     assert_eq!(
-        before, after,
-        "rejected flag-mode operation must not mutate file"
+        updated,
+        "defaults: &defaults\n  retries: 2\n  timeout: 30\nservice:\n  <<: *defaults\n  name: identedit\n"
     );
 }
 
@@ -3816,3 +4601,2219 @@ fn patch_json_config_path_append_rejects_index_targeting_scalar() {
     let after = fs::read_to_string(&file_path).expect("fixture should be readable");
     assert_eq!(before, after, "failed scalar append must not mutate file");
 }
+
+#[test]
+fn patch_json_config_path_insert_at_shifts_json_array_elements() {
+    let file_path = copy_fixture_to_temp_with_suffix("example.json", ".json");
+    let request = json!({
+        "command": "patch",
+        "file": file_path.to_string_lossy().to_string(),
+        "target": {
+            "type": "config_path",
+            "path": "items"
+        },
+        "op": {
+            "type": "insert_at",
+            "index": 1,
+            "new_text": "9"
+        }
+    });
+
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
+    assert!(
+        output.status.success(),
+        "json insert_at should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("updated JSON should be readable");
+    assert!(updated.contains("\"items\": [1, 9, 2, 3]"));
+}
+
+#[test]
+fn patch_json_config_path_insert_at_len_appends_and_past_len_is_rejected() {
+    let file_path = copy_fixture_to_temp_with_suffix("example.json", ".json");
+    let request_for = |index: usize| {
+        json!({
+            "command": "patch",
+            "file": file_path.to_string_lossy().to_string(),
+            "target": {
+                "type": "config_path",
+                "path": "items"
+            },
+            "op": {
+                "type": "insert_at",
+                "index": index,
+                "new_text": "4"
+            }
+        })
+        .to_string()
+    };
+
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request_for(3));
+    assert!(output.status.success(), "insert_at len should append");
+    let appended = fs::read_to_string(&file_path).expect("updated JSON should be readable");
+    assert!(appended.contains("\"items\": [1, 2, 3, 4]"));
+
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request_for(9));
+    assert!(!output.status.success(), "insert_at past len should fail");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("(len=4)") && message.contains("0..=4")),
+        "out-of-range insert_at should report bounds"
+    );
+    let after = fs::read_to_string(&file_path).expect("fixture should be readable");
+    assert_eq!(appended, after, "rejected insert_at must not mutate file");
+}
+
+#[test]
+fn patch_json_config_path_remove_at_rejects_out_of_range_index() {
+    let file_path = copy_fixture_to_temp_with_suffix("example.json", ".json");
+    let before = fs::read_to_string(&file_path).expect("fixture should be readable");
+    let request = json!({
+        "command": "patch",
+        "file": file_path.to_string_lossy().to_string(),
+        "target": {
+            "type": "config_path",
+            "path": "items"
+        },
+        "op": {
+            "type": "remove_at",
+            "index": 3
+        }
+    });
+
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
+    assert!(!output.status.success(), "remove_at past end should fail");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("(len=3)") && message.contains("0..=2")),
+        "out-of-range remove_at should report bounds"
+    );
+    let after = fs::read_to_string(&file_path).expect("fixture should be readable");
+    assert_eq!(before, after, "rejected remove_at must not mutate file");
+}
+
+#[test]
+fn patch_json_config_path_remove_at_rejects_non_array_target() {
+    let file_path = copy_fixture_to_temp_with_suffix("example.json", ".json");
+    let request = json!({
+        "command": "patch",
+        "file": file_path.to_string_lossy().to_string(),
+        "target": {
+            "type": "config_path",
+            "path": "config.retries"
+        },
+        "op": {
+            "type": "remove_at",
+            "index": 0
+        }
+    });
+
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
+    assert!(!output.status.success(), "remove_at on scalar should fail");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("remove_at requires an array/sequence")),
+        "scalar remove_at should report array-target diagnostic"
+    );
+}
+
+#[test]
+fn patch_flag_config_path_insert_at_adds_yaml_block_sequence_item() {
+    let mut temp_file = Builder::new()
+        .suffix(".yaml")
+        .tempfile()
+        .expect("temp yaml file should be created");
+    temp_file
+        .write_all(b"steps:\n  - fmt # first\n  - test\n")
+        .expect("yaml fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "steps",
+        "--insert-at",
+        "1",
+        "--insert-value",
+        "clippy",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "flag-mode insert_at should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(updated, "steps:\n  - fmt # first\n  - clippy\n  - test\n");
+}
+
+#[test]
+fn patch_flag_config_path_remove_at_first_yaml_item_keeps_indentation() {
+    let mut temp_file = Builder::new()
+        .suffix(".yaml")
+        .tempfile()
+        .expect("temp yaml file should be created");
+    temp_file
+        .write_all(b"steps:\n  - fmt\n  - test\n")
+        .expect("yaml fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "steps",
+        "--remove-at",
+        "0",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "flag-mode remove_at should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(updated, "steps:\n  - test\n");
+}
+
+#[test]
+fn patch_flag_config_path_remove_at_keeps_multiline_toml_array_comments() {
+    let mut temp_file = Builder::new()
+        .suffix(".toml")
+        .tempfile()
+        .expect("temp toml file should be created");
+    temp_file
+        .write_all(b"[ci]\nsteps = [\n  \"fmt\",\n  \"test\", # slow\n]\n")
+        .expect("toml fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "ci.steps",
+        "--remove-at",
+        "0",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "flag-mode toml remove_at should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(updated, "[ci]\nsteps = [\n  \"test\", # slow\n]\n");
+}
+
+#[test]
+fn patch_flag_config_path_insert_at_requires_insert_value() {
+    let file_path = copy_fixture_to_temp_with_suffix("example.json", ".json");
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "items",
+        "--insert-at",
+        "0",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        !output.status.success(),
+        "--insert-at without --insert-value should be rejected"
+    );
+}
+
+#[test]
+fn patch_flag_config_path_merge_object_keeps_yaml_comments_and_creates_nested_keys() {
+    let mut temp_file = Builder::new()
+        .suffix(".yaml")
+        .tempfile()
+        .expect("temp yaml file should be created");
+    temp_file
+        .write_all(b"# app\nserver:\n  port: 80 # http\n  host: a\nname: x\n")
+        .expect("yaml fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "server",
+        "--merge-object",
+        r#"{"port": 81, "tls": {"enabled": true}}"#,
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "flag-mode merge_object should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(
+        updated,
+        "# app\nserver:\n  port: 81 # http\n  host: a\n  tls:\n    enabled: true\nname: x\n"
+    );
+}
+
+#[test]
+fn patch_flag_config_path_merge_object_keep_policy_preserves_existing_values() {
+    let mut temp_file = Builder::new()
+        .suffix(".yaml")
+        .tempfile()
+        .expect("temp yaml file should be created");
+    temp_file
+        .write_all(b"server:\n  port: 80 # http\n")
+        .expect("yaml fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "server",
+        "--merge-object",
+        r#"{"port": 81, "host": "h"}"#,
+        "--on-conflict",
+        "keep",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "merge_object with keep policy should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(updated, "server:\n  port: 80 # http\n  host: \"h\"\n");
+}
+
+#[test]
+fn patch_flag_config_path_merge_object_error_policy_rejects_conflict_without_writing() {
+    let file_path = copy_fixture_to_temp_with_suffix("example.json", ".json");
+    let before = fs::read_to_string(&file_path).expect("fixture should be readable");
+
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "config",
+        "--merge-object",
+        r#"{"debug": true, "retries": 5}"#,
+        "--on-conflict",
+        "error",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(!output.status.success(), "conflicting merge should fail");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("config.retries")),
+        "conflict error should name the conflicting path"
+    );
+    let after = fs::read_to_string(&file_path).expect("fixture should be readable");
+    assert_eq!(before, after, "rejected merge must not mutate file");
+}
+
+#[test]
+fn patch_flag_config_path_merge_object_adds_toml_subtable_and_keeps_comments() {
+    let mut temp_file = Builder::new()
+        .suffix(".toml")
+        .tempfile()
+        .expect("temp toml file should be created");
+    temp_file
+        .write_all(b"[server]\nport = 80 # http\n\n[other]\nx = 1\n")
+        .expect("toml fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "server",
+        "--merge-object",
+        r#"{"tls": {"enabled": true}, "hosts": ["a"]}"#,
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "toml merge_object should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(
+        updated,
+        "[server]\nport = 80 # http\nhosts = [\"a\"]\n\n[server.tls]\nenabled = true\n\n[other]\nx = 1\n"
+    );
+}
+
+#[test]
+fn patch_json_config_path_merge_object_merges_into_json_mapping() {
+    let file_path = copy_fixture_to_temp_with_suffix("example.json", ".json");
+    let request = json!({
+        "command": "patch",
+        "file": file_path.to_string_lossy().to_string(),
+        "target": {
+            "type": "config_path",
+            "path": "config"
+        },
+        "op": {
+            "type": "merge_object",
+            "object": {"retries": 5, "backoff": {"max": 10}}
+        }
+    });
+
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
+    assert!(
+        output.status.success(),
+        "json-mode merge_object should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    let parsed: Value = serde_json::from_str(&updated).expect("merged file should stay JSON");
+    assert_eq!(
+        parsed["config"],
+        json!({"enabled": true, "retries": 5, "backoff": {"max": 10}})
+    );
+    assert_eq!(parsed["items"], json!([1, 2, 3]));
+}
+
+#[test]
+fn patch_config_path_merge_object_writes_new_keys_in_request_order() {
+    let flag_file = copy_fixture_to_temp_with_suffix("example.json", ".json");
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "config",
+        "--merge-object",
+        r#"{"zeta": 1, "alpha": {"y": 2, "x": 3}}"#,
+        flag_file.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "flag-mode merge_object should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let json_file = copy_fixture_to_temp_with_suffix("example.json", ".json");
+    let request = r#"{"command": "patch", "file": FILE, "target": {"type": "config_path", "path": "config"}, "op": {"type": "merge_object", "object": {"zeta": 1, "alpha": {"y": 2, "x": 3}}}}"#
+        .replace("FILE", &Value::from(json_file.to_string_lossy()).to_string());
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request);
+    assert!(
+        output.status.success(),
+        "json-mode merge_object should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    for file_path in [flag_file, json_file] {
+        assert_eq!(
+            fs::read_to_string(&file_path).expect("file should be readable"),
+            "{\n  \"name\": \"identedit\",\n  \"config\": {\n    \"enabled\": true,\n    \"retries\": 3,\n    \"zeta\": 1,\n    \"alpha\": {\n      \"y\": 2,\n      \"x\": 3\n    }\n  },\n  \"items\": [1, 2, 3]\n}\n"
+        );
+    }
+}
+
+#[test]
+fn patch_flag_config_path_merge_object_rejects_integers_beyond_toml_range() {
+    let mut temp_file = Builder::new()
+        .suffix(".toml")
+        .tempfile()
+        .expect("temp toml file should be created");
+    temp_file
+        .write_all(b"[server]\nport = 80\n")
+        .expect("toml fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "server",
+        "--merge-object",
+        r#"{"limit": 18446744073709551615}"#,
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        !output.status.success(),
+        "u64 beyond i64 should be rejected"
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("server.limit")),
+        "range error should name the path"
+    );
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        "[server]\nport = 80\n"
+    );
+}
+
+#[test]
+fn patch_flag_config_path_merge_object_that_changes_nothing_leaves_file_untouched() {
+    let file_path = copy_fixture_to_temp_with_suffix("example.json", ".json");
+    let old_mtime = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+    fs::File::options()
+        .write(true)
+        .open(&file_path)
+        .expect("fixture should open")
+        .set_modified(old_mtime)
+        .expect("mtime should be settable");
+    let before = fs::read_to_string(&file_path).expect("fixture should be readable");
+
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "config",
+        "--merge-object",
+        r#"{"enabled": true}"#,
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "no-op merge_object should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["summary"]["files_modified"], 0);
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        before
+    );
+    assert_eq!(
+        fs::metadata(&file_path)
+            .and_then(|metadata| metadata.modified())
+            .expect("mtime should be readable"),
+        old_mtime,
+        "no-op merge must not rewrite the file"
+    );
+}
+
+#[test]
+fn patch_flag_config_path_merge_object_rejects_non_object_value() {
+    let file_path = copy_fixture_to_temp_with_suffix("example.json", ".json");
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "config",
+        "--merge-object",
+        "[1, 2]",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        !output.status.success(),
+        "non-object merge value should fail"
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+}
+
+#[test]
+fn patch_flag_config_path_rename_key_keeps_yaml_position_and_comment() {
+    let mut temp_file = Builder::new()
+        .suffix(".yaml")
+        .tempfile()
+        .expect("temp yaml file should be created");
+    temp_file
+        .write_all(b"server:\n  port: 80 # http\n  host: a\n")
+        .expect("yaml fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "server.port",
+        "--rename-key",
+        "listen_port",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "flag-mode rename_key should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(updated, "server:\n  listen_port: 80 # http\n  host: a\n");
+}
+
+#[test]
+fn patch_flag_config_path_rename_key_quotes_non_bare_toml_key() {
+    let mut temp_file = Builder::new()
+        .suffix(".toml")
+        .tempfile()
+        .expect("temp toml file should be created");
+    temp_file
+        .write_all(b"[server]\nport = 80 # http\n")
+        .expect("toml fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "server.port",
+        "--rename-key",
+        "listen.port",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "toml rename_key should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(updated, "[server]\n\"listen.port\" = 80 # http\n");
+}
+
+#[test]
+fn patch_json_config_path_rename_key_rejects_existing_sibling() {
+    let file_path = copy_fixture_to_temp_with_suffix("example.json", ".json");
+    let before = fs::read_to_string(&file_path).expect("fixture should be readable");
+    let request = json!({
+        "command": "patch",
+        "file": file_path.to_string_lossy().to_string(),
+        "target": {
+            "type": "config_path",
+            "path": "config.enabled"
+        },
+        "op": {
+            "type": "rename_key",
+            "new_key": "retries"
+        }
+    });
+
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
+    assert!(
+        !output.status.success(),
+        "rename onto an existing key should fail"
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("already exists")),
+        "rename conflict should be explained"
+    );
+    let after = fs::read_to_string(&file_path).expect("fixture should be readable");
+    assert_eq!(before, after, "rejected rename_key must not mutate file");
+}
+
+#[test]
+fn patch_json_config_path_move_keeps_json_value_formatting() {
+    let mut temp_file = Builder::new()
+        .suffix(".json")
+        .tempfile()
+        .expect("temp json file should be created");
+    temp_file
+        .write_all(b"{\n  \"a\": {\n    \"x\": [\n      1,\n      2\n    ],\n    \"y\": 1\n  },\n  \"b\": {\n    \"z\": true\n  }\n}\n")
+        .expect("json fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+    let request = json!({
+        "command": "patch",
+        "file": file_path.to_string_lossy().to_string(),
+        "target": {
+            "type": "config_path",
+            "path": "a.x"
+        },
+        "op": {
+            "type": "move",
+            "to": "b.x"
+        }
+    });
+
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
+    assert!(
+        output.status.success(),
+        "json-mode move should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(
+        updated,
+        "{\n  \"a\": {\n    \"y\": 1\n  },\n  \"b\": {\n    \"z\": true,\n    \"x\": [\n      1,\n      2\n    ]\n  }\n}\n"
+    );
+}
+
+#[test]
+fn patch_flag_config_path_move_reindents_yaml_block_under_new_parent() {
+    let mut temp_file = Builder::new()
+        .suffix(".yaml")
+        .tempfile()
+        .expect("temp yaml file should be created");
+    temp_file
+        .write_all(b"# app\nserver:\n  port: 80 # http\n  host: a\nname: x\n")
+        .expect("yaml fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "server",
+        "--move-to",
+        "app.server",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "flag-mode move should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(
+        updated,
+        "# app\nname: x\napp:\n  server:\n    port: 80 # http\n    host: a\n"
+    );
+}
+
+#[test]
+fn patch_flag_config_path_move_between_toml_tables() {
+    let mut temp_file = Builder::new()
+        .suffix(".toml")
+        .tempfile()
+        .expect("temp toml file should be created");
+    temp_file
+        .write_all(b"[server]\nport = 80\nhosts = [\"a\", \"b\"]\n\n[other]\nx = 1\n")
+        .expect("toml fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "server.hosts",
+        "--move-to",
+        "other.hosts",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "toml move should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(
+        updated,
+        "[server]\nport = 80\n\n[other]\nx = 1\nhosts = [\"a\", \"b\"]\n"
+    );
+}
+
+#[test]
+fn patch_flag_config_path_move_rejects_existing_destination_and_self_nesting() {
+    let file_path = copy_fixture_to_temp_with_suffix("example.json", ".json");
+    let before = fs::read_to_string(&file_path).expect("fixture should be readable");
+
+    for destination in ["name", "config.nested"] {
+        let output = run_identedit(&[
+            "patch",
+            "--config-path",
+            if destination == "name" {
+                "items"
+            } else {
+                "config"
+            },
+            "--move-to",
+            destination,
+            file_path.to_str().expect("path should be utf-8"),
+        ]);
+        assert!(
+            !output.status.success(),
+            "move to '{destination}' should be rejected"
+        );
+        let response: Value =
+            serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+        assert_eq!(response["error"]["type"], "invalid_request");
+    }
+
+    let after = fs::read_to_string(&file_path).expect("fixture should be readable");
+    assert_eq!(before, after, "rejected moves must not mutate file");
+}
+
+#[test]
+fn patch_flag_config_path_wildcard_set_updates_every_match_and_keeps_comments() {
+    let mut temp_file = Builder::new()
+        .suffix(".yaml")
+        .tempfile()
+        .expect("temp yaml file should be created");
+    temp_file
+        .write_all(b"services:\n  api:\n    image: api:1 # pinned\n  db:\n    image: pg:15\n  cache:\n    port: 1\n")
+        .expect("yaml fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "services.*.image",
+        "--set-value",
+        "registry/app:2",
+        "--expected-match-count",
+        "2",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "wildcard set should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(
+        updated,
+        "services:\n  api:\n    image: registry/app:2 # pinned\n  db:\n    image: registry/app:2\n  cache:\n    port: 1\n"
+    );
+}
+
+#[test]
+fn patch_flag_config_path_wildcard_rejects_unexpected_match_count() {
+    let mut temp_file = Builder::new()
+        .suffix(".yaml")
+        .tempfile()
+        .expect("temp yaml file should be created");
+    temp_file
+        .write_all(b"services:\n  api:\n    image: a\n  db:\n    image: b\n")
+        .expect("yaml fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+    let before = fs::read_to_string(&file_path).expect("fixture should be readable");
+
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "services.*.image",
+        "--set-value",
+        "c",
+        "--expected-match-count",
+        "3",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(!output.status.success(), "count mismatch should fail");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| {
+                message.contains("matched 2 target(s)")
+                    && message.contains("services.api.image")
+                    && message.contains("services.db.image")
+            }),
+        "count mismatch should list the matched paths"
+    );
+    let after = fs::read_to_string(&file_path).expect("fixture should be readable");
+    assert_eq!(
+        before, after,
+        "rejected wildcard patch must not mutate file"
+    );
+}
+
+#[test]
+fn patch_json_config_path_index_wildcard_updates_each_array_element() {
+    let file_path = copy_fixture_to_temp_with_suffix("example.json", ".json");
+    let request = json!({
+        "command": "patch",
+        "file": file_path.to_string_lossy().to_string(),
+        "target": {
+            "type": "config_path",
+            "path": "items[*]",
+            "expected_match_count": 3
+        },
+        "op": {
+            "type": "set",
+            "new_text": "0"
+        }
+    });
+
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
+    assert!(
+        output.status.success(),
+        "json-mode wildcard set should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert!(
+        updated.contains("\"items\": [0, 0, 0]"),
+        "every element should be replaced in place: {updated}"
+    );
+}
+
+#[test]
+fn patch_json_config_path_wildcard_delete_removes_every_matching_key() {
+    let mut temp_file = Builder::new()
+        .suffix(".json")
+        .tempfile()
+        .expect("temp json file should be created");
+    temp_file
+        .write_all(b"{\n  \"items\": [\n    {\"name\": \"a\", \"debug\": true},\n    {\"name\": \"b\", \"debug\": false}\n  ]\n}\n")
+        .expect("json fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+    let request = json!({
+        "command": "patch",
+        "file": file_path.to_string_lossy().to_string(),
+        "target": {
+            "type": "config_path",
+            "path": "items[*].debug"
+        },
+        "op": {
+            "type": "delete"
+        }
+    });
+
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
+    assert!(
+        output.status.success(),
+        "wildcard delete should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(
+        updated,
+        "{\n  \"items\": [\n    {\"name\": \"a\"},\n    {\"name\": \"b\"}\n  ]\n}\n"
+    );
+}
+
+#[test]
+fn patch_flag_config_path_wildcard_without_matches_is_rejected() {
+    let file_path = copy_fixture_to_temp_with_suffix("example.json", ".json");
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "config.*.missing",
+        "--set-value",
+        "1",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(!output.status.success(), "unmatched wildcard should fail");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("matched no targets")),
+        "unmatched wildcard should be explained"
+    );
+}
+
+#[test]
+fn patch_flag_config_path_wildcard_is_rejected_for_single_target_operations() {
+    let file_path = copy_fixture_to_temp_with_suffix("example.json", ".json");
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "*",
+        "--merge-object",
+        "{}",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(!output.status.success(), "wildcard merge should fail");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("wildcard")),
+        "wildcard rejection should be explained"
+    );
+}
+
+#[test]
+fn patch_flag_config_path_set_string_quotes_numeric_text_in_yaml() {
+    let mut temp_file = Builder::new()
+        .suffix(".yaml")
+        .tempfile()
+        .expect("temp yaml file should be created");
+    temp_file
+        .write_all(b"server:\n  port: 80 # public\n")
+        .expect("yaml fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "server.port",
+        "--set-string",
+        "8080",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "typed string set should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(updated, "server:\n  port: \"8080\" # public\n");
+}
+
+#[test]
+fn patch_flag_config_path_set_int_and_string_write_toml_literals() {
+    let mut temp_file = Builder::new()
+        .suffix(".toml")
+        .tempfile()
+        .expect("temp toml file should be created");
+    temp_file
+        .write_all(b"[server]\nport = 80\nhost = \"a\"\n")
+        .expect("toml fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+    let path_arg = file_path.to_str().expect("path should be utf-8");
+
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "server.port",
+        "--set-int",
+        "-1",
+        path_arg,
+    ]);
+    assert!(
+        output.status.success(),
+        "typed int set should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "server.host",
+        "--set-string",
+        "example.com",
+        path_arg,
+    ]);
+    assert!(
+        output.status.success(),
+        "typed string set should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(updated, "[server]\nport = -1\nhost = \"example.com\"\n");
+}
+
+#[test]
+fn patch_flag_config_path_set_bool_rejects_non_boolean_text() {
+    let file_path = copy_fixture_to_temp_with_suffix("example.json", ".json");
+    let before = fs::read_to_string(&file_path).expect("file should be readable");
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "config.enabled",
+        "--set-bool",
+        "yes",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(!output.status.success(), "non-boolean text should fail");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("not a valid bool value")),
+        "invalid bool should be explained"
+    );
+    let after = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(before, after);
+}
+
+#[test]
+fn patch_flag_config_path_rejects_multiple_set_flags() {
+    let file_path = copy_fixture_to_temp_with_suffix("example.json", ".json");
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "config.retries",
+        "--set-value",
+        "4",
+        "--set-int",
+        "5",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(!output.status.success(), "two set flags should fail");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+}
+
+#[test]
+fn patch_json_config_path_value_type_string_escapes_json_text() {
+    let file_path = copy_fixture_to_temp_with_suffix("example.json", ".json");
+    let request = json!({
+        "command": "patch",
+        "file": file_path.to_string_lossy().to_string(),
+        "target": {
+            "type": "config_path",
+            "path": "config.label"
+        },
+        "op": {
+            "type": "set",
+            "new_text": "say \"hi\"",
+            "value_type": "string",
+            "create_missing": true
+        }
+    });
+
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
+    assert!(
+        output.status.success(),
+        "typed JSON-mode set should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    let document: Value = serde_json::from_str(&updated).expect("output should stay valid JSON");
+    assert_eq!(document["config"]["label"], "say \"hi\"");
+}
+
+#[test]
+fn patch_json_config_path_value_type_int_applies_to_append() {
+    let file_path = copy_fixture_to_temp_with_suffix("example.json", ".json");
+    let request = json!({
+        "command": "patch",
+        "file": file_path.to_string_lossy().to_string(),
+        "target": {
+            "type": "config_path",
+            "path": "items"
+        },
+        "op": {
+            "type": "append",
+            "new_text": "four",
+            "value_type": "int"
+        }
+    });
+
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
+    assert!(!output.status.success(), "non-integer append should fail");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("not a valid int value")),
+        "invalid int should be explained"
+    );
+}
+
+const K8S_MANIFEST: &str = "apiVersion: v1\nkind: Service\nmetadata:\n  name: web\nspec:\n  port: 80\n---\napiVersion: apps/v1\nkind: Deployment\nmetadata:\n  name: web\nspec:\n  replicas: 1 # scaled by hpa\n---\nkind: Deployment\nmetadata:\n  name: api\nspec:\n  replicas: 1\n";
+
+fn write_k8s_manifest() -> PathBuf {
+    let mut temp_file = Builder::new()
+        .suffix(".yaml")
+        .tempfile()
+        .expect("temp yaml file should be created");
+    temp_file
+        .write_all(K8S_MANIFEST.as_bytes())
+        .expect("yaml fixture write should succeed");
+    temp_file.keep().expect("temp file should persist").1
+}
+
+#[test]
+fn patch_flag_config_path_doc_selects_document_by_kind_and_name() {
+    let file_path = write_k8s_manifest();
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "spec.replicas",
+        "--doc",
+        "Deployment/web",
+        "--set-int",
+        "3",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "document-selected set should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(
+        updated,
+        K8S_MANIFEST.replace("replicas: 1 # scaled", "replicas: 3 # scaled")
+    );
+}
+
+#[test]
+fn patch_json_config_path_document_applies_create_missing_inside_the_document() {
+    let file_path = write_k8s_manifest();
+    let request = json!({
+        "command": "patch",
+        "file": file_path.to_string_lossy().to_string(),
+        "target": {
+            "type": "config_path",
+            "path": "spec.strategy.type",
+            "document": "Deployment/api"
+        },
+        "op": {
+            "type": "set",
+            "new_text": "Recreate",
+            "create_missing": true
+        }
+    });
+
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
+    assert!(
+        output.status.success(),
+        "document-selected create-missing should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(
+        updated,
+        format!("{K8S_MANIFEST}  strategy:\n    type: Recreate\n")
+    );
+}
+
+#[test]
+fn patch_flag_config_path_doc_without_match_lists_documents() {
+    let file_path = write_k8s_manifest();
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "spec.replicas",
+        "--doc",
+        "Deployment/worker",
+        "--set-value",
+        "2",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(!output.status.success(), "unknown document should fail");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message
+                .contains("documents: Service/web, Deployment/web, Deployment/api")),
+        "available documents should be listed"
+    );
+    let after = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(after, K8S_MANIFEST);
+}
+
+#[test]
+fn patch_flag_config_path_doc_is_rejected_for_non_yaml_files() {
+    let file_path = copy_fixture_to_temp_with_suffix("example.json", ".json");
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "config.retries",
+        "--doc",
+        "Deployment/web",
+        "--set-value",
+        "4",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(!output.status.success(), "--doc on JSON should fail");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("only applies to YAML")),
+        "non-YAML rejection should be explained"
+    );
+}
+
+fn write_manifest(name: &str, source: &str) -> PathBuf {
+    let directory = tempfile::tempdir().expect("tempdir should be created");
+    let file_path = directory.keep().join(name);
+    fs::write(&file_path, source).expect("manifest write should succeed");
+    file_path
+}
+
+#[test]
+fn patch_flag_cargo_add_dependency_keeps_sorted_order_and_comments() {
+    let file_path = write_manifest(
+        "Cargo.toml",
+        "[package]\nname = \"demo\"\n\n[dependencies]\n# runtime\nanyhow = \"1\" # errors\ntokio = { version = \"1\", features = [\"rt\"] }\n",
+    );
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "dependencies",
+        "--add-dependency",
+        "serde",
+        "--dependency-version",
+        "1.0",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "add dependency should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(
+        updated,
+        "[package]\nname = \"demo\"\n\n[dependencies]\n# runtime\nanyhow = \"1\" # errors\nserde = \"1.0\"\ntokio = { version = \"1\", features = [\"rt\"] }\n"
+    );
+}
+
+#[test]
+fn patch_flag_cargo_set_dependency_version_updates_inline_tables_and_rejects_workspace() {
+    let file_path = write_manifest(
+        "Cargo.toml",
+        "[dependencies]\nlocal = { path = \"../local\" }\nshared = { workspace = true }\n",
+    );
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "dependencies",
+        "--set-dependency-version",
+        "local",
+        "--dependency-version",
+        "0.2",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "set version should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        "[dependencies]\nlocal = { path = \"../local\", version = \"0.2\" }\nshared = { workspace = true }\n"
+    );
+
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "dependencies",
+        "--set-dependency-version",
+        "shared",
+        "--dependency-version",
+        "2",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        !output.status.success(),
+        "workspace-inherited version should be rejected"
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+}
+
+#[test]
+fn patch_json_package_json_dependency_operations_edit_sections() {
+    let file_path = write_manifest(
+        "package.json",
+        "{\n  \"name\": \"demo\",\n  \"dependencies\": {\n    \"express\": \"^4.0.0\",\n    \"zod\": \"^3.0.0\"\n  }\n}\n",
+    );
+    let request = |op: Value, path: &str| {
+        json!({
+            "command": "patch",
+            "file": file_path.to_string_lossy().to_string(),
+            "target": {
+                "type": "config_path",
+                "path": path
+            },
+            "op": op
+        })
+        .to_string()
+    };
+
+    for (op, path) in [
+        (
+            json!({"type": "add_dependency", "name": "lodash", "version": "^4.17.0"}),
+            "dependencies",
+        ),
+        (
+            json!({"type": "set_dependency_version", "name": "zod", "version": "^3.22.0"}),
+            "dependencies",
+        ),
+        (
+            json!({"type": "remove_dependency", "name": "express"}),
+            "dependencies",
+        ),
+        (
+            json!({"type": "add_dependency", "name": "vitest", "version": "^1.0.0"}),
+            "devDependencies",
+        ),
+    ] {
+        let output = run_identedit_with_stdin(&["patch", "--json"], &request(op, path));
+        assert!(
+            output.status.success(),
+            "dependency operation should succeed: {}",
+            String::from_utf8_lossy(&output.stdout)
+        );
+    }
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(
+        updated,
+        "{\n  \"name\": \"demo\",\n  \"dependencies\": {\n    \"lodash\": \"^4.17.0\",\n    \"zod\": \"^3.22.0\"\n  },\n  \"devDependencies\": {\n    \"vitest\": \"^1.0.0\"\n  }\n}\n"
+    );
+
+    let output = run_identedit_with_stdin(
+        &["patch", "--json"],
+        &request(
+            json!({"type": "add_dependency", "name": "zod", "version": "^4.0.0"}),
+            "dependencies",
+        ),
+    );
+    assert!(!output.status.success(), "duplicate add should fail");
+    let after = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(after, updated);
+}
+
+#[test]
+fn patch_flag_dependency_operation_rejects_non_manifest_files() {
+    let file_path = copy_fixture_to_temp_with_suffix("example.json", ".json");
+    let output = run_identedit(&[
+        "patch",
+        "--config-path",
+        "dependencies",
+        "--add-dependency",
+        "serde",
+        "--dependency-version",
+        "1",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(!output.status.success(), "non-manifest file should fail");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+}
+
+#[test]
+fn patch_annotate_inserts_marker_comment_that_read_annotations_lists() {
+    let file_path = copy_fixture_to_temp_python("example.py");
+    let handle = select_named_function_handle(&file_path, "process_*");
+    let request = json!({
+        "command": "patch",
+        "file": file_path.to_string_lossy().to_string(),
+        "target": {
+            "type": "node",
+            "identity": handle["identity"],
+            "kind": handle["kind"],
+            "expected_old_hash": identedit::changeset::hash_text(
+                handle["text"].as_str().expect("text should be string")
+            )
+        },
+        "op": {
+            "type": "annotate",
+            "marker": "fixme",
+            "ticket": "DATA-7",
+            "message": "validate negative values"
+        }
+    });
+
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
+    assert!(
+        output.status.success(),
+        "patch --json annotate failed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let modified = fs::read_to_string(&file_path).expect("modified file should be readable");
+    assert!(modified.contains("# FIXME(DATA-7): validate negative values\ndef process_data("));
+
+    let output = run_identedit(&[
+        "read",
+        "--annotations",
+        "--json",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(output.status.success(), "read --annotations should succeed");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    let annotation = response["handles"]
+        .as_array()
+        .expect("handles should be an array")
+        .iter()
+        .find(|handle| handle["ticket"] == "DATA-7")
+        .expect("inserted marker should be listed");
+    assert_eq!(annotation["target_type"], "annotation");
+    assert_eq!(annotation["marker"], "FIXME");
+    assert_eq!(annotation["message"], "validate negative values");
+    assert_eq!(
+        annotation["anchor"],
+        line_ref(
+            &modified,
+            annotation["line"].as_u64().expect("line") as usize
+        )
+    );
+}
+
+#[test]
+fn patch_annotate_flag_mode_uses_language_comment_syntax_and_rejects_inline_targets() {
+    let mut temp_file = Builder::new()
+        .suffix(".rs")
+        .tempfile()
+        .expect("temp rust file should be created");
+    temp_file
+        .write_all(b"fn main() {\n    let total = 1;\n}\n")
+        .expect("fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+    let handle = common::select_first_handle(&file_path, "let_declaration", None);
+    let output = run_identedit(&[
+        "patch",
+        "--identity",
+        handle["identity"].as_str().expect("identity"),
+        "--annotate",
+        "overflow check",
+        "--timestamp",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "patch --annotate failed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let modified = fs::read_to_string(&file_path).expect("modified file should be readable");
+    let marker_line = modified.lines().nth(1).expect("marker line");
+    assert!(marker_line.starts_with("    // TODO 20"), "{marker_line}");
+    assert!(marker_line.ends_with(": overflow check"));
+    assert_eq!(modified.lines().nth(2), Some("    let total = 1;"));
+
+    let integer = common::select_first_handle(&file_path, "integer_literal", None);
+    let output = run_identedit(&[
+        "patch",
+        "--identity",
+        integer["identity"].as_str().expect("identity"),
+        "--annotate",
+        "inline",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(!output.status.success(), "inline target should be rejected");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+}
+
+#[cfg(feature = "script")]
+#[test]
+fn patch_script_computes_replacements_for_node_and_scoped_regex_matches() {
+    let file_path = create_scoped_regex_fixture();
+    let handle = select_named_function_handle(&file_path, "helper");
+    let identity = handle["identity"]
+        .as_str()
+        .expect("identity should be present");
+    let mut script = Builder::new()
+        .suffix(".rhai")
+        .tempfile()
+        .expect("temp script should be created");
+    script
+        .write_all(
+            br#"let n = parse_int(captures.amount);
+if n > 1 { `value * ${n}` }"#,
+        )
+        .expect("script write should succeed");
+
+    let output = run_identedit(&[
+        "patch",
+        "--identity",
+        identity,
+        "--scoped-regex",
+        r"value \+ (?<amount>\d+)",
+        "--script",
+        script.path().to_str().expect("path should be utf-8"),
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "patch --script failed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let modified = fs::read_to_string(&file_path).expect("modified file should be readable");
+    assert!(modified.contains("return value + 1\n"));
+    assert!(modified.contains("return value * 2\n"));
+
+    let mut node_script = Builder::new()
+        .suffix(".rhai")
+        .tempfile()
+        .expect("temp script should be created");
+    node_script
+        .write_all(br#"let text = handle.text; if handle.name == "process_data" { text.replace("value", "x"); text }"#)
+        .expect("script write should succeed");
+    let process = select_named_function_handle(&file_path, "process_data");
+    let output = run_identedit(&[
+        "patch",
+        "--identity",
+        process["identity"].as_str().expect("identity"),
+        "--script",
+        node_script.path().to_str().expect("path should be utf-8"),
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(output.status.success());
+    let modified = fs::read_to_string(&file_path).expect("modified file should be readable");
+    assert!(modified.starts_with("def process_data(x):\n    return x + 1\n"));
+
+    let mut bad_script = Builder::new()
+        .suffix(".rhai")
+        .tempfile()
+        .expect("temp script should be created");
+    bad_script.write_all(b"40 + 2").expect("script write");
+    let helper = select_named_function_handle(&file_path, "helper");
+    let output = run_identedit(&[
+        "patch",
+        "--identity",
+        helper["identity"].as_str().expect("identity"),
+        "--script",
+        bad_script.path().to_str().expect("path should be utf-8"),
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(!output.status.success());
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "script_failed");
+}
+
+#[test]
+fn patch_retry_on_stale_re_resolves_a_node_edited_since_read() {
+    let file_path = copy_fixture_to_temp_python("example.py");
+    let handle = select_named_function_handle(&file_path, "process_data");
+    let source = fs::read_to_string(&file_path).expect("file should be readable");
+    fs::write(&file_path, source.replace("value + 1", "value + 9"))
+        .expect("concurrent edit should be written");
+
+    let request = json!({
+        "command": "patch",
+        "file": file_path.to_string_lossy().to_string(),
+        "target": {
+            "type": "node",
+            "identity": handle["identity"],
+            "kind": handle["kind"],
+            "span_hint": handle["span"],
+            "expected_old_hash": handle["expected_old_hash"]
+        },
+        "op": {
+            "type": "replace",
+            "new_text": "def process_data(value):\n    return value"
+        }
+    });
+
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
+    assert!(
+        !output.status.success(),
+        "stale hash should fail without retries"
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "precondition_failed");
+
+    let output = run_identedit_with_stdin(
+        &["patch", "--json", "--retry-on-stale", "2"],
+        &request.to_string(),
+    );
+    assert!(
+        output.status.success(),
+        "retry should re-resolve the edited node: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    let attempts = response["attempts"]
+        .as_array()
+        .expect("attempts should be reported");
+    assert_eq!(attempts.len(), 2);
+    assert_eq!(attempts[0]["outcome"], "precondition_failed");
+    assert_eq!(attempts[0]["identity"], handle["identity"]);
+    assert_eq!(
+        attempts[0]["expected_old_hash"],
+        handle["expected_old_hash"]
+    );
+    assert_eq!(attempts[1]["outcome"], "applied");
+    assert_eq!(attempts[1]["expected_old_hash"], attempts[0]["actual_hash"]);
+    assert_ne!(attempts[1]["identity"], handle["identity"]);
+
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert!(updated.starts_with("def process_data(value):\n    return value\n"));
+    assert!(!updated.contains("value + 9"));
+}
+
+#[test]
+fn patch_retry_on_stale_is_rejected_for_non_node_targets() {
+    let file_path = copy_fixture_to_temp_python("example.py");
+    let output = run_identedit(&[
+        "patch",
+        "--at",
+        "file-end",
+        "--insert",
+        "# tail\n",
+        "--retry-on-stale",
+        "1",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(!output.status.success());
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+
+    let identity = select_named_function_handle(&file_path, "helper")["identity"]
+        .as_str()
+        .expect("identity should be a string")
+        .to_string();
+    let output = run_identedit(&[
+        "patch",
+        "--identity",
+        &identity,
+        "--replace",
+        "def helper():\n    return \"patched\"",
+        "--retry-on-stale",
+        "3",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "fresh flag-mode patch should apply"
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    let attempts = response["attempts"]
+        .as_array()
+        .expect("attempts should be reported");
+    assert_eq!(attempts.len(), 1);
+    assert_eq!(attempts[0]["outcome"], "applied");
+}
+
+#[test]
+fn patch_write_file_creates_then_replaces_under_file_preconditions() {
+    let directory = tempfile::tempdir().expect("temp directory should be created");
+    let file_path = directory.path().join("settings.txt");
+    let file_arg = file_path.to_str().expect("path should be utf-8");
+
+    let output = run_identedit(&[
+        "patch",
+        "--write-file",
+        "mode = fast\n",
+        "--expect-absent",
+        file_arg,
+    ]);
+    assert!(
+        output.status.success(),
+        "create should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        "mode = fast\n"
+    );
+
+    let output = run_identedit(&[
+        "patch",
+        "--write-file",
+        "mode = slow\n",
+        "--expect-absent",
+        file_arg,
+    ]);
+    assert!(
+        !output.status.success(),
+        "an existing file is never clobbered"
+    );
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["error"]["type"], "precondition_failed");
+
+    let stale_hash = identedit::changeset::hash_text("mode = old\n");
+    let output = run_identedit(&[
+        "patch",
+        "--write-file",
+        "mode = slow\n",
+        "--expected-file-hash",
+        &stale_hash,
+        file_arg,
+    ]);
+    assert!(
+        !output.status.success(),
+        "a stale file hash should be rejected"
+    );
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        "mode = fast\n"
+    );
+
+    let current_hash = identedit::changeset::hash_text("mode = fast\n");
+    let output = run_identedit(&[
+        "patch",
+        "--write-file",
+        "mode = slow\n",
+        "--expected-file-hash",
+        &current_hash,
+        file_arg,
+    ]);
+    assert!(
+        output.status.success(),
+        "replace should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["summary"]["files_modified"], 1);
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        "mode = slow\n"
+    );
+    let leftovers = fs::read_dir(directory.path())
+        .expect("directory should be readable")
+        .count();
+    assert_eq!(leftovers, 1, "no temporary files should remain");
+
+    let output = run_identedit(&["patch", "--write-file", "x\n", file_arg]);
+    assert!(
+        !output.status.success(),
+        "--write-file needs a precondition"
+    );
+}
+
+#[test]
+fn patch_json_append_and_prepend_file_keep_line_endings() {
+    let source = "\u{feff}first\r\nlast";
+    let mut temp_file = Builder::new()
+        .suffix(".log")
+        .tempfile()
+        .expect("temp log file should be created");
+    temp_file
+        .write_all(source.as_bytes())
+        .expect("fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+
+    let request = json!({
+        "command": "patch",
+        "file": file_path.to_string_lossy().to_string(),
+        "operations": [
+            {
+                "target": {
+                    "type": "file",
+                    "expected_file_hash": identedit::changeset::hash_text(source)
+                },
+                "op": {"type": "append_file", "new_text": "appended"}
+            },
+            {
+                "target": {
+                    "type": "file",
+                    "expected_file_hash": identedit::changeset::hash_text(source)
+                },
+                "op": {"type": "prepend_file", "new_text": "header"}
+            }
+        ]
+    });
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
+    assert!(
+        output.status.success(),
+        "append/prepend should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        "\u{feff}header\r\nfirst\r\nlast\r\nappended"
+    );
+
+    let request = json!({
+        "command": "patch",
+        "file": file_path.to_string_lossy().to_string(),
+        "target": {"type": "file", "expected_file_hash": identedit::changeset::hash_text(source)},
+        "op": {"type": "append_file", "new_text": "stale"}
+    });
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
+    assert!(
+        !output.status.success(),
+        "a stale file hash should be rejected"
+    );
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["error"]["type"], "precondition_failed");
+
+    fs::remove_file(&file_path).expect("temp file should be removed");
+}
+
+#[test]
+fn patch_json_upsert_inserts_once_then_replaces_in_place() {
+    let directory = tempfile::tempdir().expect("temp directory should be created");
+    let file_path = directory.path().join("greeter.py");
+    fs::write(
+        &file_path,
+        "class Greeter:\n    def hello(self):\n        return \"hi\"\n",
+    )
+    .expect("fixture write should succeed");
+    let upsert = |new_text: &str| {
+        let source = fs::read_to_string(&file_path).expect("file should be readable");
+        let request = json!({
+            "command": "patch",
+            "file": file_path.to_string_lossy().to_string(),
+            "target": {"type": "file", "expected_file_hash": identedit::changeset::hash_text(&source)},
+            "op": {
+                "type": "upsert",
+                "kind": "function_definition",
+                "name": "bye",
+                "new_text": new_text,
+                "within": {"kind": "class_definition", "name": "Greeter"}
+            }
+        });
+        let output = run_identedit_with_stdin(&["patch", "--json"], &request.to_string());
+        assert!(
+            output.status.success(),
+            "upsert should succeed: {}",
+            String::from_utf8_lossy(&output.stdout)
+        );
+        let response: Value =
+            serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+        response["upsert"].clone()
+    };
+
+    assert_eq!(upsert("def bye(self):\n    return \"bye\"\n"), "inserted");
+    let inserted = "class Greeter:\n    def hello(self):\n        return \"hi\"\n\n    def bye(self):\n        return \"bye\"\n";
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        inserted
+    );
+    assert_eq!(upsert("def bye(self):\n    return \"bye\"\n"), "unchanged");
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        inserted
+    );
+    assert_eq!(upsert("def bye(self):\n    return \"later\"\n"), "replaced");
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        inserted.replace("\"bye\"", "\"later\"")
+    );
+}
+
+#[test]
+fn patch_json_upsert_rejects_ambiguous_matches_and_stale_hashes() {
+    let directory = tempfile::tempdir().expect("temp directory should be created");
+    let file_path = directory.path().join("twice.py");
+    let source = "class A:\n    def run(self):\n        pass\n\nclass B:\n    def run(self):\n        pass\n";
+    fs::write(&file_path, source).expect("fixture write should succeed");
+    let request = |expected_file_hash: String| {
+        json!({
+            "command": "patch",
+            "file": file_path.to_string_lossy().to_string(),
+            "target": {"type": "file", "expected_file_hash": expected_file_hash},
+            "op": {
+                "type": "upsert",
+                "kind": "function_definition",
+                "name": "run",
+                "new_text": "def run(self):\n    return 1\n"
+            }
+        })
+        .to_string()
+    };
+
+    let output = run_identedit_with_stdin(
+        &["patch", "--json"],
+        &request(identedit::changeset::hash_text(source)),
+    );
+    assert!(!output.status.success(), "two matches should be rejected");
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("within"))
+    );
+
+    let output = run_identedit_with_stdin(&["patch", "--json"], &request("0".repeat(16)));
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["error"]["type"], "precondition_failed");
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        source
+    );
+}
+
+#[test]
+fn patch_respect_editorconfig_restyles_inserted_text() {
+    let directory = tempfile::tempdir().expect("temp directory should be created");
+    fs::write(
+        directory.path().join(".editorconfig"),
+        "root = true\n\n[*.py]\nindent_style = space\nindent_size = 4\ninsert_final_newline = true\n",
+    )
+    .expect("editorconfig should be written");
+    let source = "def f():\n    return 1\n";
+    let file_path = directory.path().join("module.py");
+    fs::write(&file_path, source).expect("fixture write should succeed");
+    let file_arg = file_path.to_str().expect("path should be utf-8");
+
+    let output = run_identedit(&[
+        "patch",
+        "--respect-editorconfig",
+        "--at",
+        "file-end",
+        "--insert",
+        "\ndef g():\n\treturn 2",
+        file_arg,
+    ]);
+    assert!(
+        output.status.success(),
+        "file-end insert should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let updated = "def f():\n    return 1\n\ndef g():\n    return 2\n";
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        updated
+    );
+
+    let request = json!({
+        "command": "patch",
+        "file": file_arg,
+        "target": {"type": "line", "anchor": line_ref(updated, 2)},
+        "op": {"type": "set_line", "new_text": "\treturn 10"}
+    });
+    let output = run_identedit_with_stdin(
+        &["patch", "--json", "--respect-editorconfig"],
+        &request.to_string(),
+    );
+    assert!(
+        output.status.success(),
+        "line patch should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        "def f():\n    return 10\n\ndef g():\n    return 2\n"
+    );
+}
+
+#[test]
+fn patch_reindent_target_matches_the_insertion_depth() {
+    let source = "class A:\n    def f(self):\n        return 1\n";
+    let mut temp_file = Builder::new()
+        .suffix(".py")
+        .tempfile()
+        .expect("temp file should be created");
+    temp_file
+        .write_all(source.as_bytes())
+        .expect("fixture write should succeed");
+    let file_path = temp_file.path().to_path_buf();
+    let file_arg = file_path.to_str().expect("path should be utf-8");
+
+    let output = run_identedit(&["read", "--json", "--mode", "line", file_arg]);
+    assert!(output.status.success(), "read should succeed");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(
+        response["file_preconditions"][0]["indentation"],
+        json!({"style": "spaces", "width": 4})
+    );
+
+    let output = run_identedit(&[
+        "patch",
+        "--anchor",
+        &line_ref(source, 2),
+        "--insert-after-line",
+        "if self.x:\n  return 0",
+        "--reindent",
+        "target",
+        file_arg,
+    ]);
+    assert!(
+        output.status.success(),
+        "line insert should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let updated = fs::read_to_string(&file_path).expect("file should be readable");
+    assert_eq!(
+        updated,
+        "class A:\n    def f(self):\n        if self.x:\n            return 0\n        return 1\n"
+    );
+
+    let output = run_identedit(&["read", "--json", "--kind", "function_definition", file_arg]);
+    assert!(output.status.success(), "read should succeed");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    let identity = response["handles"][0]["identity"]
+        .as_str()
+        .expect("function identity should exist")
+        .to_string();
+    let output = run_identedit(&[
+        "patch",
+        "--identity",
+        &identity,
+        "--insert-before",
+        "def g(self):\n\treturn 2\n\n",
+        "--reindent",
+        "target",
+        file_arg,
+    ]);
+    assert!(
+        output.status.success(),
+        "node insert should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        "class A:\n    def g(self):\n        return 2\n\n    def f(self):\n        if self.x:\n            return 0\n        return 1\n"
+    );
+
+    let output = run_identedit(&[
+        "patch",
+        "--at",
+        "file-end",
+        "--insert",
+        "x = 1\n",
+        "--reindent",
+        "target",
+        file_arg,
+    ]);
+    assert!(
+        !output.status.success(),
+        "--reindent should need an insert op"
+    );
+}
+
+#[test]
+fn patch_json_echoes_correlation_id_in_responses_and_errors() {
+    let temp_dir = tempfile::tempdir().expect("tempdir should be created");
+    let file_path = temp_dir.path().join("notes.txt");
+    fs::write(&file_path, "one\n").expect("fixture should be written");
+    let request = |expected_file_hash: &str| {
+        json!({
+            "command": "patch",
+            "file": file_path.to_string_lossy().to_string(),
+            "target": {"type": "file_end", "expected_file_hash": expected_file_hash},
+            "op": {"type": "insert", "new_text": "two\n"},
+            "correlation_id": "intent-42"
+        })
+    };
+
+    let output = run_identedit_with_stdin(
+        &["patch", "--json"],
+        &request(&identedit::changeset::hash_text("one\n")).to_string(),
+    );
+    assert!(output.status.success(), "patch --json should succeed");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["correlation_id"], "intent-42");
+
+    let output = run_identedit_with_stdin(
+        &["patch", "--json"],
+        &request("0000000000000000").to_string(),
+    );
+    assert!(!output.status.success(), "stale file hash should fail");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "precondition_failed");
+    assert_eq!(response["correlation_id"], "intent-42");
+}
+
+#[test]
+fn patch_json_marker_region_create_append_and_replace_touch_only_the_region() {
+    let directory = tempfile::tempdir().expect("temp directory should be created");
+    let file_path = directory.path().join("routes.py");
+    let header = "import app\n\n\ndef manual():\n    pass\n";
+    fs::write(&file_path, header).expect("fixture write should succeed");
+    let patch = |target: Value, op: Value| {
+        let request = json!({
+            "command": "patch",
+            "file": file_path.to_string_lossy().to_string(),
+            "target": target,
+            "op": op
+        });
+        run_identedit_with_stdin(&["patch", "--json"], &request.to_string())
+    };
+    let region = json!({"type": "marker_region", "name": "routes"});
+
+    let output = patch(
+        json!({"type": "file", "expected_file_hash": identedit::changeset::hash_text(header)}),
+        json!({"type": "create_marker_region", "name": "routes", "new_text": "app.route(\"/a\")"}),
+    );
+    assert!(
+        output.status.success(),
+        "create_marker_region should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let output = patch(
+        region.clone(),
+        json!({"type": "append", "new_text": "app.route(\"/b\")"}),
+    );
+    assert!(output.status.success(), "append should succeed");
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        format!(
+            "{header}# identedit:begin routes\napp.route(\"/a\")\napp.route(\"/b\")\n# identedit:end routes\n"
+        )
+    );
+
+    let stale = json!({
+        "type": "marker_region",
+        "name": "routes",
+        "expected_region_hash": identedit::changeset::hash_text("app.route(\"/a\")\n")
+    });
+    let output = patch(stale, json!({"type": "replace", "new_text": ""}));
+    assert!(!output.status.success(), "a stale region hash must fail");
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["error"]["type"], "precondition_failed");
+
+    let output = patch(
+        region.clone(),
+        json!({"type": "replace", "new_text": "app.route(\"/c\")\n"}),
+    );
+    assert!(output.status.success(), "replace should succeed");
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        format!("{header}# identedit:begin routes\napp.route(\"/c\")\n# identedit:end routes\n")
+    );
+
+    let output = patch(
+        json!({"type": "marker_region", "name": "models"}),
+        json!({"type": "append", "new_text": "x"}),
+    );
+    assert!(!output.status.success(), "a missing region must fail");
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["error"]["type"], "target_missing");
+}
+
+#[test]
+fn patch_byte_span_replaces_raw_bytes_of_a_non_utf8_file() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("image.bin");
+    let original = [0xff, 0xfe, b'a', b'b', b'c', 0x00, 0x80];
+    fs::write(&file, original).expect("fixture should be written");
+    let blob = workspace.path().join("blob");
+    fs::write(&blob, [0xc0, 0x01]).expect("blob should be written");
+    let file_arg = file.to_str().expect("path should be utf-8");
+    let blob_arg = blob.to_str().expect("path should be utf-8");
+
+    let output = run_identedit(&[
+        "patch",
+        "--byte-span",
+        "2:5",
+        "--expected-bytes-hash",
+        &identedit::changeset::hash_bytes(b"abd"),
+        "--replace-bytes-file",
+        blob_arg,
+        file_arg,
+    ]);
+    assert!(!output.status.success(), "a stale hash must fail");
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["error"]["type"], "precondition_failed");
+    assert_eq!(fs::read(&file).expect("file should be readable"), original);
+
+    let output = run_identedit(&[
+        "patch",
+        "--byte-span",
+        "2:5",
+        "--expected-bytes-hash",
+        &identedit::changeset::hash_bytes(b"abc"),
+        "--replace-bytes-file",
+        blob_arg,
+        file_arg,
+    ]);
+    assert!(
+        output.status.success(),
+        "byte-span patch should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert_eq!(
+        fs::read(&file).expect("file should be readable"),
+        [0xff, 0xfe, 0xc0, 0x01, 0x00, 0x80]
+    );
+
+    let output = run_identedit(&[
+        "patch",
+        "--byte-span",
+        "4:9",
+        "--expected-bytes-hash",
+        "0000",
+        "--replace-bytes-file",
+        blob_arg,
+        file_arg,
+    ]);
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+}
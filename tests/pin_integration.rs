@@ -0,0 +1,175 @@
+use std::fs;
+use std::path::Path;
+
+use serde_json::Value;
+use tempfile::tempdir;
+
+mod common;
+
+const SOURCE: &str =
+    "def process_data(value):\n    return value + 1\n\n\ndef helper():\n    return 2\n";
+
+fn function_identity(workspace: &Path, name: &str) -> String {
+    let output = common::run_in(
+        workspace,
+        &[
+            "read",
+            "--json",
+            "--kind",
+            "function_definition",
+            "--name",
+            name,
+            "example.py",
+        ],
+    );
+    assert!(output.status.success(), "read should succeed");
+    common::stdout_json(&output)["handles"][0]["identity"]
+        .as_str()
+        .expect("identity should be string")
+        .to_string()
+}
+
+fn pin_process_data(workspace: &Path) {
+    fs::write(workspace.join("example.py"), SOURCE).expect("fixture should be written");
+    let identity = function_identity(workspace, "process_data");
+    let output = common::run_in(
+        workspace,
+        &["pin", "add", "process", "--at", &identity, "example.py"],
+    );
+    assert!(
+        output.status.success(),
+        "pin add should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+}
+
+#[test]
+fn pin_add_persists_and_resolves_across_invocations() {
+    let workspace = tempdir().expect("tempdir should be created");
+    pin_process_data(workspace.path());
+    assert!(workspace.path().join(".identedit/pins.json").is_file());
+
+    let output = common::run_in(
+        workspace.path(),
+        &["pin", "resolve", "process", "--verbose"],
+    );
+    assert!(output.status.success(), "pin resolve should succeed");
+    let response = common::stdout_json(&output);
+    assert_eq!(response["pin"]["kind"], "function_definition");
+    assert_eq!(response["pin"]["name"], "process_data");
+    assert_eq!(
+        response["handle"]["text"],
+        "def process_data(value):\n    return value + 1"
+    );
+
+    let output = common::run_in(workspace.path(), &["pin", "list"]);
+    let response = common::stdout_json(&output);
+    assert_eq!(response["pins"][0]["alias"], "process");
+    assert_eq!(response["pins"][0]["status"], "current");
+}
+
+#[test]
+fn patch_pin_replaces_node_and_moves_pin_to_new_identity() {
+    let workspace = tempdir().expect("tempdir should be created");
+    pin_process_data(workspace.path());
+
+    let output = common::run_in(
+        workspace.path(),
+        &[
+            "patch",
+            "--pin",
+            "process",
+            "--replace",
+            "def process_data(value):\n    return value + 2",
+        ],
+    );
+    assert!(
+        output.status.success(),
+        "patch --pin should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let new_identity = function_identity(workspace.path(), "process_data");
+    assert_eq!(
+        common::stdout_json(&output)["pin"]["identity"],
+        new_identity
+    );
+
+    let output = common::run_in(
+        workspace.path(),
+        &[
+            "patch",
+            "--pin",
+            "process",
+            "--replace",
+            "def process_data(value):\n    return value + 3",
+        ],
+    );
+    assert!(
+        output.status.success(),
+        "pin should survive the first patch"
+    );
+    let updated =
+        fs::read_to_string(workspace.path().join("example.py")).expect("file should be readable");
+    assert!(updated.contains("return value + 3"));
+    assert!(updated.contains("def helper():"));
+}
+
+#[test]
+fn pin_goes_stale_when_node_changes_outside_identedit() {
+    let workspace = tempdir().expect("tempdir should be created");
+    pin_process_data(workspace.path());
+    fs::write(
+        workspace.path().join("example.py"),
+        SOURCE.replace("value + 1", "value + 5"),
+    )
+    .expect("fixture should be rewritten");
+
+    let output = common::run_in(workspace.path(), &["pin", "list"]);
+    assert_eq!(common::stdout_json(&output)["pins"][0]["status"], "stale");
+
+    let output = common::run_in(workspace.path(), &["patch", "--pin", "process", "--delete"]);
+    assert!(!output.status.success(), "stale pin should not patch");
+    assert_eq!(
+        common::stdout_json(&output)["error"]["type"],
+        "target_missing"
+    );
+}
+
+#[test]
+fn pin_rejects_unknown_alias_and_conflicting_targets() {
+    let workspace = tempdir().expect("tempdir should be created");
+    pin_process_data(workspace.path());
+
+    let output = common::run_in(workspace.path(), &["pin", "resolve", "missing"]);
+    assert!(!output.status.success(), "unknown alias should fail");
+    let response = common::stdout_json(&output);
+    assert_eq!(response["error"]["type"], "invalid_request");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("pins: process")),
+        "known aliases should be listed"
+    );
+
+    let output = common::run_in(
+        workspace.path(),
+        &[
+            "patch",
+            "--pin",
+            "process",
+            "--at",
+            "file-start",
+            "--delete",
+            "example.py",
+        ],
+    );
+    assert!(!output.status.success(), "--pin with --at should fail");
+
+    let output = common::run_in(workspace.path(), &["pin", "remove", "process"]);
+    assert!(output.status.success(), "pin remove should succeed");
+    let output = common::run_in(workspace.path(), &["pin", "list"]);
+    assert_eq!(
+        common::stdout_json(&output)["pins"],
+        Value::Array(Vec::new())
+    );
+}
@@ -0,0 +1,161 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+use serde_json::Value;
+use tempfile::tempdir;
+
+mod common;
+
+const PYTHON_SOURCE: &str =
+    "def process_data(value):\n    return value + 1\n\n\ndef helper():\n    return 2\n";
+
+fn stdout_text(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).expect("stdout should be UTF-8")
+}
+
+/// Runs an exported shell script with the test binary first on PATH.
+fn run_shell_script(workspace: &Path, script: &str) -> Output {
+    fs::write(workspace.join("plan.sh"), script).expect("script should be written");
+    let binary_dir = Path::new(env!("CARGO_BIN_EXE_identedit"))
+        .parent()
+        .expect("binary should have a parent directory");
+    let path = format!(
+        "{}:{}",
+        binary_dir.display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    Command::new("sh")
+        .arg("plan.sh")
+        .env("PATH", path)
+        .current_dir(workspace)
+        .output()
+        .expect("failed to run sh")
+}
+
+#[test]
+fn plan_export_shell_script_reproduces_plan_from_diff() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let source = "alpha\nbeta\ngamma\ndelta\n";
+    fs::write(workspace.path().join("notes.txt"), source).expect("fixture should be written");
+    let diff = "--- notes.txt\n+++ notes.txt\n\
+@@ -0,0 +1 @@\n+# it's\n\
+@@ -2,2 +3,2 @@\n-beta\n+-BETA\n gamma\n\
+@@ -4 +5,2 @@\n delta\n+end\n";
+    fs::write(workspace.path().join("change.diff"), diff).expect("diff should be written");
+
+    let output = common::run_in(workspace.path(), &["plan", "from-diff", "change.diff"]);
+    assert!(output.status.success(), "plan from-diff should succeed");
+    fs::write(workspace.path().join("plan.json"), &output.stdout).expect("plan should be written");
+
+    let output = common::run_in(workspace.path(), &["plan", "export", "plan.json"]);
+    assert!(
+        output.status.success(),
+        "plan export should succeed: {}",
+        stdout_text(&output)
+    );
+    let script = stdout_text(&output);
+    let commands = script
+        .lines()
+        .filter(|line| line.starts_with("identedit patch"))
+        .collect::<Vec<_>>();
+    assert_eq!(commands.len(), 3, "one patch command per plan operation");
+    assert!(
+        commands[0].contains("--at 4:"),
+        "steps run bottom-up so earlier anchors stay valid: {script}"
+    );
+    assert!(commands[2].contains("--at file-start --insert"));
+
+    let output = run_shell_script(workspace.path(), &script);
+    assert!(
+        output.status.success(),
+        "exported script should run: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        fs::read_to_string(workspace.path().join("notes.txt")).expect("file should be readable"),
+        "# it's\nalpha\n-BETA\ngamma\ndelta\nend\n"
+    );
+}
+
+#[test]
+fn plan_export_python_renders_json_patch_requests_for_node_plans() {
+    let workspace = tempdir().expect("tempdir should be created");
+    fs::write(workspace.path().join("example.py"), PYTHON_SOURCE)
+        .expect("fixture should be written");
+    let output = common::run_in(
+        workspace.path(),
+        &[
+            "read",
+            "--json",
+            "--kind",
+            "function_definition",
+            "--name",
+            "helper",
+            "example.py",
+        ],
+    );
+    let read: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    let identity = read["handles"][0]["identity"]
+        .as_str()
+        .expect("identity should be string")
+        .to_string();
+    let output = common::run_in(
+        workspace.path(),
+        &["edit", "--identity", &identity, "--delete", "example.py"],
+    );
+    assert!(output.status.success(), "edit should succeed");
+    fs::write(workspace.path().join("plan.json"), &output.stdout).expect("plan should be written");
+
+    let output = common::run_in(
+        workspace.path(),
+        &["plan", "export", "--format", "python", "plan.json"],
+    );
+    assert!(output.status.success(), "plan export should succeed");
+    let script = stdout_text(&output);
+    assert!(script.starts_with("#!/usr/bin/env python3\n"));
+    assert!(script.contains("[IDENTEDIT, \"patch\", \"--json\"]"));
+    let request_start = script.find("patch({").expect("script should call patch") + "patch(".len();
+    let request_end = script.rfind("})").expect("request should be closed") + 1;
+    let request: Value = serde_json::from_str(&script[request_start..request_end])
+        .expect("request literal should be JSON");
+    assert_eq!(request["command"], "patch");
+    assert_eq!(request["file"], "example.py");
+    assert_eq!(request["target"]["type"], "node");
+    assert_eq!(request["target"]["identity"], identity);
+    assert!(request["target"]["expected_old_hash"].is_string());
+    assert_eq!(request["op"]["type"], "delete");
+}
+
+#[test]
+fn plan_export_rejects_move_operations() {
+    let workspace = tempdir().expect("tempdir should be created");
+    fs::write(workspace.path().join("notes.txt"), "alpha\nbeta\n")
+        .expect("fixture should be written");
+    let plan = serde_json::json!({
+        "files": [{
+            "file": "notes.txt",
+            "operations": [{
+                "target": {"type": "line", "anchor": "1:000000000000"},
+                "op": {
+                    "type": "move_after",
+                    "destination": {"type": "line", "anchor": "2:000000000000"}
+                },
+                "preview": {"new_text": "", "matched_span": {"start": 0, "end": 6}}
+            }]
+        }]
+    });
+    fs::write(workspace.path().join("plan.json"), plan.to_string())
+        .expect("plan should be written");
+
+    let output = common::run_in(workspace.path(), &["plan", "export", "plan.json"]);
+    assert!(!output.status.success(), "move export should fail");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("move_after")),
+        "unsupported operation should be named"
+    );
+}
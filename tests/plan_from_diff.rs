@@ -0,0 +1,134 @@
+use std::fs;
+use std::path::Path;
+use std::process::Output;
+
+use serde_json::Value;
+use tempfile::tempdir;
+
+mod common;
+
+const SOURCE: &str = "alpha\nbeta\ngamma\ndelta\nepsilon\nzeta\neta\ntheta\n";
+
+fn plan_and_apply(workspace: &Path, diff: &str) -> Output {
+    fs::write(workspace.join("change.diff"), diff).expect("diff should be written");
+    let output = common::run_in(workspace, &["plan", "from-diff", "change.diff"]);
+    assert!(
+        output.status.success(),
+        "plan from-diff should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    fs::write(workspace.join("plan.json"), &output.stdout).expect("plan should be written");
+    common::run_in(workspace, &["apply", "plan.json"])
+}
+
+#[test]
+fn plan_from_diff_converts_git_hunks_into_line_anchored_replacements() {
+    let workspace = tempdir().expect("tempdir should be created");
+    fs::write(workspace.path().join("notes.txt"), SOURCE).expect("fixture should be written");
+    let diff = "diff --git a/notes.txt b/notes.txt\n\
+index 1111111..2222222 100644\n\
+--- a/notes.txt\n\
++++ b/notes.txt\n\
+@@ -1,3 +1,3 @@\n alpha\n-beta\n+BETA\n gamma\n\
+@@ -6,3 +6,3 @@ epsilon\n zeta\n eta\n-theta\n+THETA\n\\ No newline at end of file\n";
+
+    fs::write(workspace.path().join("change.diff"), diff).expect("diff should be written");
+    let output = common::run_in(workspace.path(), &["plan", "from-diff", "change.diff"]);
+    assert!(output.status.success(), "plan from-diff should succeed");
+    let plan: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    let operations = plan["files"][0]["operations"]
+        .as_array()
+        .expect("operations should be array");
+    assert_eq!(operations.len(), 2);
+    assert_eq!(operations[0]["target"]["type"], "line");
+    assert!(
+        operations[0]["target"]["anchor"]
+            .as_str()
+            .is_some_and(|anchor| anchor.starts_with("1:"))
+    );
+    assert!(
+        operations[0]["target"]["end_anchor"]
+            .as_str()
+            .is_some_and(|anchor| anchor.starts_with("3:")),
+        "context lines bound the replaced range"
+    );
+    assert!(operations[0]["preview"]["old_hash"].is_string());
+
+    fs::write(workspace.path().join("plan.json"), &output.stdout).expect("plan should be written");
+    let output = common::run_in(workspace.path(), &["apply", "plan.json"]);
+    assert!(output.status.success(), "apply should succeed");
+    assert_eq!(
+        fs::read_to_string(workspace.path().join("notes.txt")).expect("file should be readable"),
+        "alpha\nBETA\ngamma\ndelta\nepsilon\nzeta\neta\nTHETA"
+    );
+}
+
+#[test]
+fn plan_from_diff_finds_hunks_at_an_offset_and_handles_zero_context_inserts() {
+    let workspace = tempdir().expect("tempdir should be created");
+    fs::write(
+        workspace.path().join("notes.txt"),
+        format!("header 1\nheader 2\n{SOURCE}"),
+    )
+    .expect("fixture should be written");
+    let diff = "--- notes.txt\n+++ notes.txt\n\
+@@ -0,0 +1 @@\n+# title\n\
+@@ -4,2 +5,3 @@\n delta\n+inserted\n epsilon\n";
+
+    let output = plan_and_apply(workspace.path(), diff);
+    assert!(
+        output.status.success(),
+        "apply should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert_eq!(
+        fs::read_to_string(workspace.path().join("notes.txt")).expect("file should be readable"),
+        "# title\nheader 1\nheader 2\nalpha\nbeta\ngamma\ndelta\ninserted\nepsilon\nzeta\neta\ntheta\n"
+    );
+}
+
+#[test]
+fn plan_from_diff_reads_stdin_and_plan_fails_when_context_changes_before_apply() {
+    let workspace = tempdir().expect("tempdir should be created");
+    fs::write(workspace.path().join("notes.txt"), SOURCE).expect("fixture should be written");
+    let diff = "--- a/notes.txt\n+++ b/notes.txt\n@@ -2,3 +2,3 @@\n beta\n-gamma\n+GAMMA\n delta\n";
+
+    let output = common::run_in_with_stdin(workspace.path(), &["plan", "from-diff", "-"], diff);
+    assert!(output.status.success(), "stdin diff should be accepted");
+    fs::write(workspace.path().join("plan.json"), &output.stdout).expect("plan should be written");
+
+    let drifted = SOURCE.replace("delta", "DELTA");
+    fs::write(workspace.path().join("notes.txt"), &drifted).expect("fixture should be rewritten");
+    let output = common::run_in(workspace.path(), &["apply", "plan.json"]);
+    assert!(
+        !output.status.success(),
+        "changed context line should fail the plan"
+    );
+    assert_eq!(
+        fs::read_to_string(workspace.path().join("notes.txt")).expect("file should be readable"),
+        drifted
+    );
+}
+
+#[test]
+fn plan_from_diff_rejects_hunks_that_do_not_match_and_new_files() {
+    let workspace = tempdir().expect("tempdir should be created");
+    fs::write(workspace.path().join("notes.txt"), SOURCE).expect("fixture should be written");
+
+    let mismatched = "--- a/notes.txt\n+++ b/notes.txt\n@@ -2,2 +2,2 @@\n beta\n-missing\n+x\n";
+    let output =
+        common::run_in_with_stdin(workspace.path(), &["plan", "from-diff", "-"], mismatched);
+    assert!(!output.status.success(), "unmatched hunk should fail");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("does not match")),
+        "mismatch should be explained"
+    );
+
+    let creation = "--- /dev/null\n+++ b/new.txt\n@@ -0,0 +1 @@\n+hello\n";
+    let output = common::run_in_with_stdin(workspace.path(), &["plan", "from-diff", "-"], creation);
+    assert!(!output.status.success(), "file creation should fail");
+}
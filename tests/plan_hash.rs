@@ -0,0 +1,67 @@
+use std::fs;
+
+use serde_json::Value;
+
+mod common;
+
+#[test]
+fn plan_hash_ignores_layout_and_matches_the_apply_response() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("main.py");
+    fs::write(&file, "def a():\n    return 1\n").expect("fixture should be written");
+    let file_arg = file.to_str().expect("path should be utf-8");
+
+    let output =
+        common::run_identedit(&["read", "--json", "--kind", "function_definition", file_arg]);
+    assert!(output.status.success(), "read should succeed");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    let identity = response["handles"][0]["identity"]
+        .as_str()
+        .expect("identity should exist");
+
+    let output = common::run_identedit(&[
+        "edit",
+        "--identity",
+        identity,
+        "--replace",
+        "def a():\n    return 2",
+        file_arg,
+    ]);
+    assert!(
+        output.status.success(),
+        "edit should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let pretty = String::from_utf8(output.stdout).expect("plan should be utf-8");
+    let mut compact: Value = serde_json::from_str(&pretty).expect("plan should be JSON");
+    compact["correlation_id"] = Value::from("run-1");
+    let compact = compact.to_string();
+
+    let hash_of = |plan: &str| {
+        let output = common::run_identedit_with_stdin(&["plan", "hash"], plan);
+        assert!(
+            output.status.success(),
+            "plan hash should succeed: {}",
+            String::from_utf8_lossy(&output.stdout)
+        );
+        let response: Value =
+            serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+        assert_eq!(response["algorithm"], "blake3");
+        assert_eq!(response["operations"], 1);
+        response["plan_hash"]
+            .as_str()
+            .expect("plan_hash should be a string")
+            .to_string()
+    };
+    let plan_hash = hash_of(&pretty);
+    assert_eq!(hash_of(&compact), plan_hash);
+
+    let output = common::run_identedit_with_stdin(&["apply"], &compact);
+    assert!(
+        output.status.success(),
+        "apply should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["plan_hash"], plan_hash.as_str());
+}
@@ -0,0 +1,98 @@
+use std::fs;
+use std::path::Path;
+
+use serde_json::{Value, json};
+
+mod common;
+
+/// The single operation of an `edit` plan against the function named `name`.
+fn edit_operation(file: &Path, name: &str, edit: &[&str]) -> Value {
+    let file_arg = file.to_str().expect("path should be utf-8");
+    let output =
+        common::run_identedit(&["read", "--json", "--kind", "function_definition", file_arg]);
+    assert!(output.status.success(), "read should succeed");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    let handle = response["handles"]
+        .as_array()
+        .expect("handles should be an array")
+        .iter()
+        .find(|handle| handle["name"] == name)
+        .expect("handle should exist");
+
+    let mut args = vec![
+        "edit",
+        "--identity",
+        handle["identity"].as_str().expect("identity should exist"),
+    ];
+    args.extend(edit);
+    args.push(file_arg);
+    let output = common::run_identedit(&args);
+    assert!(output.status.success(), "edit should succeed");
+    let plan: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    plan["files"][0]["operations"][0].clone()
+}
+
+#[test]
+fn plan_rebase_refreshes_moved_targets_and_drops_changed_ones() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("main.py");
+    fs::write(
+        &file,
+        "def a():\n    return 1\n\n\ndef b():\n    return 2\n\n\ndef c():\n    return 3\n",
+    )
+    .expect("fixture should be written");
+
+    let mut replace_b = edit_operation(&file, "b", &["--replace", "def b():\n    return 22"]);
+    replace_b["id"] = json!("replace-b");
+    let mut delete_c = edit_operation(&file, "c", &["--delete"]);
+    delete_c["id"] = json!("delete-c");
+    delete_c["depends_on"] = json!(["replace-a"]);
+    let mut replace_a = edit_operation(&file, "a", &["--replace", "def a():\n    return 11"]);
+    replace_a["id"] = json!("replace-a");
+    let plan = json!({
+        "files": [{"file": file, "operations": [replace_b, delete_c, replace_a]}]
+    });
+
+    // Unrelated churn moves `b` and `c`; `a` itself changes.
+    fs::write(
+        &file,
+        "import os\n\n\ndef a():\n    return 100\n\n\ndef b():\n    return 2\n\n\ndef c():\n    return 3\n",
+    )
+    .expect("fixture should be rewritten");
+
+    let output = common::run_identedit_with_stdin(&["plan", "rebase"], &plan.to_string());
+    assert!(
+        output.status.success(),
+        "plan rebase should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(
+        response["summary"],
+        json!({"operations_total": 3, "unchanged": 0, "rebased": 1, "dropped": 2})
+    );
+    assert_eq!(response["rebased"], json!([{"file": file, "operation": 0, "id": "replace-b"}]));
+    let dropped = response["dropped"]
+        .as_array()
+        .expect("dropped should be an array");
+    assert_eq!(dropped[0]["operation"], 1);
+    assert_eq!(dropped[1]["operation"], 2);
+    assert_eq!(dropped[1]["error"]["type"], "target_missing");
+
+    let operations = response["changeset"]["files"][0]["operations"]
+        .as_array()
+        .expect("operations should be an array");
+    assert_eq!(operations.len(), 1);
+    assert_eq!(operations[0]["target"]["span_hint"]["start"], 38);
+
+    let output = common::run_identedit_with_stdin(&["apply"], &response["changeset"].to_string());
+    assert!(
+        output.status.success(),
+        "rebased plan should apply: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert_eq!(
+        fs::read_to_string(&file).expect("file should be readable"),
+        "import os\n\n\ndef a():\n    return 100\n\n\ndef b():\n    return 22\n\n\ndef c():\n    return 3\n"
+    );
+}
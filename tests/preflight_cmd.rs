@@ -0,0 +1,121 @@
+#![cfg(unix)]
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+mod common;
+
+fn write_script(dir: &Path, name: &str, body: &str) -> PathBuf {
+    let script = dir.join(name);
+    fs::write(&script, format!("#!/bin/sh\n{body}\n")).expect("script should be written");
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755))
+        .expect("script should be made executable");
+    script
+}
+
+#[test]
+fn passing_preflight_output_is_attached_to_the_patch_response() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("main.py");
+    fs::write(&file, "def a():\n    return 1\n").expect("fixture should be written");
+    let file_arg = file.to_str().expect("path should be utf-8");
+    let input_log = workspace.path().join("input.json");
+    let script = write_script(
+        workspace.path(),
+        "check.sh",
+        &format!(
+            "cat > '{}'\necho '{{\"daemon\": \"idle\"}}'",
+            input_log.display()
+        ),
+    );
+    let script_arg = script.to_str().expect("path should be utf-8");
+
+    let read =
+        common::run_identedit(&["read", "--json", "--kind", "function_definition", file_arg]);
+    let read: Value = serde_json::from_slice(&read.stdout).expect("stdout should be JSON");
+    let identity = read["handles"][0]["identity"]
+        .as_str()
+        .expect("identity should exist");
+
+    let output = common::run_identedit(&[
+        "patch",
+        file_arg,
+        "--identity",
+        identity,
+        "--replace",
+        "def a():\n    return 2",
+        "--preflight-cmd",
+        script_arg,
+    ]);
+    assert!(
+        output.status.success(),
+        "patch should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["preflight"][0]["program"], script_arg);
+    assert_eq!(response["preflight"][0]["output"]["daemon"], "idle");
+
+    let input: Value = serde_json::from_str(
+        &fs::read_to_string(&input_log).expect("preflight should have recorded its input"),
+    )
+    .expect("preflight input should be JSON");
+    assert_eq!(input["command"], "patch");
+    assert_eq!(input["files"][0], file_arg);
+    assert!(
+        fs::read_to_string(&file)
+            .expect("file should be readable")
+            .contains("return 2")
+    );
+}
+
+#[test]
+fn failing_preflight_aborts_apply_before_any_write() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("main.py");
+    let source = "def a():\n    return 1\n";
+    fs::write(&file, source).expect("fixture should be written");
+    let file_arg = file.to_str().expect("path should be utf-8");
+    let script = write_script(
+        workspace.path(),
+        "refuse.sh",
+        "echo 'formatter daemon is rewriting main.py' >&2\nexit 3",
+    );
+
+    let read =
+        common::run_identedit(&["read", "--json", "--kind", "function_definition", file_arg]);
+    let read: Value = serde_json::from_slice(&read.stdout).expect("stdout should be JSON");
+    let plan = common::run_identedit(&[
+        "edit",
+        "--identity",
+        read["handles"][0]["identity"]
+            .as_str()
+            .expect("identity should exist"),
+        "--replace",
+        "def a():\n    return 2",
+        file_arg,
+    ]);
+    let plan_file = workspace.path().join("plan.json");
+    fs::write(&plan_file, &plan.stdout).expect("plan should be written");
+
+    let output = common::run_identedit(&[
+        "apply",
+        "--preflight-cmd",
+        script.to_str().expect("path should be utf-8"),
+        plan_file.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(!output.status.success());
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "preflight_failed");
+    let message = response["error"]["message"]
+        .as_str()
+        .expect("message should be a string");
+    assert!(message.contains("formatter daemon is rewriting main.py"));
+    assert_eq!(
+        fs::read_to_string(&file).expect("file should be readable"),
+        source
+    );
+}
@@ -0,0 +1,143 @@
+use std::fs;
+use std::process::Command;
+
+use serde_json::Value;
+
+mod common;
+
+fn handle_kinds(response: &Value) -> Vec<&str> {
+    response["handles"]
+        .as_array()
+        .expect("handles should be an array")
+        .iter()
+        .map(|handle| handle["kind"].as_str().expect("kind should be a string"))
+        .collect()
+}
+
+#[test]
+fn prefer_routes_an_extension_to_the_named_provider() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let script = workspace.path().join("build.txt");
+    fs::write(&script, "def build():\n    return 1\n").expect("fixture should be written");
+    let script_arg = script.to_str().expect("path should be utf-8");
+
+    // The fallback provider only recognizes definition lines, never their bodies.
+    let plain = common::run_json(&["read", "--json", script_arg]);
+    assert!(!handle_kinds(&plain).contains(&"return_statement"));
+
+    let routed = common::run_json(&[
+        "read",
+        "--json",
+        "--prefer",
+        ".TXT=tree-sitter-python",
+        script_arg,
+    ]);
+    assert_eq!(handle_kinds(&routed)[0], "module");
+    assert!(handle_kinds(&routed).contains(&"return_statement"));
+}
+
+#[test]
+fn disabled_providers_drop_out_of_lookup_and_capabilities() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let query = workspace.path().join("report.sql");
+    fs::write(&query, "SELECT 1;\n").expect("fixture should be written");
+    let query_arg = query.to_str().expect("path should be utf-8");
+
+    assert!(!handle_kinds(&common::run_json(&["read", "--json", query_arg])).is_empty());
+    let disabled = common::run_json(&[
+        "read",
+        "--json",
+        "--disable-provider",
+        "tree-sitter-sql",
+        query_arg,
+    ]);
+    assert!(handle_kinds(&disabled).is_empty());
+
+    let capabilities = common::run_json(&["capabilities", "--disable-provider", "tree-sitter-sql"]);
+    let names = capabilities["providers"]
+        .as_array()
+        .expect("providers should be an array")
+        .iter()
+        .map(|provider| provider["name"].as_str().expect("name should be a string"))
+        .collect::<Vec<_>>();
+    assert!(!names.contains(&"tree-sitter-sql"));
+    assert!(names.contains(&"fallback"));
+
+    for args in [
+        ["--disable-provider", "tree-sitter-nope"],
+        ["--prefer", "sql=tree-sitter-nope"],
+    ] {
+        let output = common::run_identedit(&["read", "--json", args[0], args[1], query_arg]);
+        let error = common::stdout_json(&output);
+        assert_eq!(error["error"]["type"], "invalid_request", "{args:?}");
+    }
+}
+
+#[test]
+fn project_config_maps_extensions_that_would_reach_the_fallback() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let root = workspace.path();
+    fs::create_dir(root.join(".identedit")).expect("config dir should be created");
+    fs::write(
+        root.join(".identedit/config.json"),
+        r#"{"extensions": {".pyi": "python", "py": "javascript"}}"#,
+    )
+    .expect("config should be written");
+    fs::write(root.join("stub.pyi"), "def build() -> int: ...\n")
+        .expect("fixture should be written");
+    fs::write(root.join("real.py"), "def build():\n    return 1\n")
+        .expect("fixture should be written");
+
+    let read = |file: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_identedit"))
+            .args(["read", "--json", file])
+            .current_dir(root)
+            .output()
+            .expect("failed to run identedit binary");
+        serde_json::from_slice::<Value>(&output.stdout).expect("stdout should be JSON")
+    };
+    assert_eq!(handle_kinds(&read("stub.pyi"))[0], "module");
+    // Mappings never take an extension away from the provider that claims it.
+    assert!(handle_kinds(&read("real.py")).contains(&"return_statement"));
+
+    fs::write(
+        root.join(".identedit/config.json"),
+        r#"{"extensions": {"pyi": "cobol"}}"#,
+    )
+    .expect("config should be written");
+    assert_eq!(read("stub.pyi")["error"]["type"], "invalid_request");
+}
+
+#[test]
+fn malformed_project_config_fails_only_the_commands_it_governs() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let root = workspace.path();
+    fs::create_dir(root.join(".identedit")).expect("config dir should be created");
+    fs::write(root.join(".identedit/config.json"), "{not json").expect("config should be written");
+    fs::write(root.join("real.py"), "def build():\n    return 1\n")
+        .expect("fixture should be written");
+
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_identedit"))
+            .args(args)
+            .current_dir(root)
+            .output()
+            .expect("failed to run identedit binary")
+    };
+    let read = run(&["read", "--json", "real.py"]);
+    assert!(read.status.success());
+    let response: Value = serde_json::from_slice(&read.stdout).expect("stdout should be JSON");
+    assert!(handle_kinds(&response).contains(&"return_statement"));
+    assert!(String::from_utf8_lossy(&read.stderr).contains("project config"));
+
+    let apply = run(&["apply", "missing-plan.json"]);
+    assert!(!apply.status.success());
+    let response: Value = serde_json::from_slice(&apply.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .expect("message should be a string")
+            .contains("Failed to parse project config")
+    );
+}
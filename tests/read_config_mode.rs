@@ -0,0 +1,175 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde_json::{Value, json};
+use tempfile::Builder;
+
+mod common;
+
+fn write_temp_config(suffix: &str, source: &str) -> PathBuf {
+    let mut temp_file = Builder::new()
+        .suffix(suffix)
+        .tempfile()
+        .expect("temp config file should be created");
+    temp_file
+        .write_all(source.as_bytes())
+        .expect("fixture write should succeed");
+    temp_file.keep().expect("temp file should persist").1
+}
+
+fn read_config_handles(file_path: &Path) -> Vec<Value> {
+    let output = common::run_identedit(&[
+        "read",
+        "--json",
+        "--mode",
+        "config",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        output.status.success(),
+        "read --mode config should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    response["handles"]
+        .as_array()
+        .expect("handles should be array")
+        .clone()
+}
+
+#[test]
+fn read_mode_config_lists_json_paths_in_document_order() {
+    let file_path = common::copy_fixture_to_temp_json("example.json");
+    let handles = read_config_handles(&file_path);
+
+    let paths = handles
+        .iter()
+        .map(|handle| handle["path"].as_str().expect("path should be string"))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        vec![
+            "name",
+            "config",
+            "config.enabled",
+            "config.retries",
+            "items",
+            "items[0]",
+            "items[1]",
+            "items[2]"
+        ]
+    );
+
+    let retries = handles
+        .iter()
+        .find(|handle| handle["path"] == "config.retries")
+        .expect("config.retries should be listed");
+    assert_eq!(retries["target_type"], "config");
+    assert_eq!(retries["value_type"], "number");
+    assert_eq!(retries["preview"], "3");
+    assert_eq!(
+        retries["expected_value_hash"],
+        identedit::hash::hash_bytes(b"3")
+    );
+    let items = handles
+        .iter()
+        .find(|handle| handle["path"] == "items")
+        .expect("items should be listed");
+    assert_eq!(items["value_type"], "array");
+    assert_eq!(items["preview"], "[3 item(s)]");
+}
+
+#[test]
+fn read_mode_config_text_output_lists_yaml_and_toml_values() {
+    let yaml_path = write_temp_config(".yaml", "server:\n  port: 80 # public\n  hosts:\n    - a\n");
+    let toml_path = write_temp_config(".toml", "[server]\nport = 80\n");
+    let output = common::run_identedit(&[
+        "read",
+        "--mode",
+        "config",
+        yaml_path.to_str().expect("path should be utf-8"),
+        toml_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(output.status.success(), "text config read should succeed");
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout should be utf-8");
+    let port_hash = identedit::hash::hash_bytes(b"80");
+    assert!(stdout.contains(&format!("server.port number {port_hash} 80")));
+    assert!(stdout.contains("server.hosts array "));
+    assert!(stdout.contains("server.hosts[0] string "));
+    let toml_section = stdout
+        .split("\n\n")
+        .find(|section| section.starts_with(&format!("## {}", toml_path.display())))
+        .expect("TOML file should have its own section");
+    assert!(toml_section.contains(&format!("server.port number {port_hash} 80")));
+    assert!(
+        !toml_section.lines().any(|line| line.starts_with("server ")),
+        "TOML table headers have no value node to list"
+    );
+}
+
+#[test]
+fn read_mode_config_rejects_selector_flags() {
+    let file_path = common::copy_fixture_to_temp_json("example.json");
+    let output = common::run_identedit(&[
+        "read",
+        "--json",
+        "--mode",
+        "config",
+        "--kind",
+        "pair",
+        file_path.to_str().expect("path should be utf-8"),
+    ]);
+    assert!(
+        !output.status.success(),
+        "selector flags should be rejected"
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+}
+
+#[test]
+fn read_mode_config_value_hash_guards_config_path_patch() {
+    let file_path = write_temp_config(".yaml", "server:\n  port: 80\n  host: a\n");
+    let handles = read_config_handles(&file_path);
+    let port_hash = handles
+        .iter()
+        .find(|handle| handle["path"] == "server.port")
+        .and_then(|handle| handle["expected_value_hash"].as_str())
+        .expect("server.port should be listed")
+        .to_string();
+
+    let request = |new_text: &str| {
+        json!({
+            "command": "patch",
+            "file": file_path.to_string_lossy().to_string(),
+            "target": {
+                "type": "config_path",
+                "path": "server.port",
+                "expected_value_hash": port_hash
+            },
+            "op": {
+                "type": "set",
+                "new_text": new_text
+            }
+        })
+        .to_string()
+    };
+
+    let output = common::run_identedit_with_stdin(&["patch", "--json"], &request("8080"));
+    assert!(
+        output.status.success(),
+        "matching value hash should allow the patch: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let output = common::run_identedit_with_stdin(&["patch", "--json"], &request("9090"));
+    assert!(!output.status.success(), "stale value hash should fail");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "precondition_failed");
+    assert_eq!(
+        fs::read_to_string(&file_path).expect("file should be readable"),
+        "server:\n  port: 8080\n  host: a\n"
+    );
+}
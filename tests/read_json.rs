@@ -259,3 +259,100 @@ fn unsupported_extension_routes_to_fallback_provider() {
         serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
     assert_eq!(response["summary"]["matches"], 0);
 }
+
+#[test]
+fn names_json_handles_by_config_path() {
+    let fixture = fixture_path("example.json");
+    let output = run_read(&["--verbose", "--name", "config.*"], &fixture);
+
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    let handles = response["handles"]
+        .as_array()
+        .expect("handles should be an array");
+    let selected = handles
+        .iter()
+        .map(|handle| {
+            (
+                handle["kind"].as_str().expect("kind should be string"),
+                handle["name"].as_str().expect("name should be string"),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        selected,
+        vec![
+            ("key", "config.enabled"),
+            ("boolean", "config.enabled"),
+            ("key", "config.retries"),
+            ("number", "config.retries"),
+        ]
+    );
+    let retries = handles
+        .iter()
+        .find(|handle| handle["kind"] == "number")
+        .expect("number handle should exist");
+    assert_eq!(retries["text"], "3");
+}
+
+#[test]
+fn max_response_bytes_cuts_texts_and_points_at_the_rest() {
+    let temp_dir = tempdir().expect("tempdir should be created");
+    let file_path = temp_dir.path().join("generated.py");
+    let body = (0..60)
+        .map(|index| format!("    value_{index} = x + {index}\n"))
+        .collect::<String>();
+    let source = (0..4)
+        .map(|index| format!("def f{index}(x):\n{body}    return x\n\n\n"))
+        .collect::<String>();
+    fs::write(&file_path, &source).expect("fixture should be written");
+
+    let read = |extra: &[&str]| {
+        let mut arguments = vec!["--verbose", "--kind", "function_definition"];
+        arguments.extend(extra);
+        let output = run_read(&arguments, &file_path);
+        assert!(output.status.success(), "read should succeed");
+        (
+            output.stdout.len(),
+            serde_json::from_slice::<Value>(&output.stdout).expect("stdout should be JSON"),
+        )
+    };
+    let (full_len, full) = read(&[]);
+    let limit = full_len * 3 / 4;
+    let limit_arg = limit.to_string();
+
+    let (len, response) = read(&["--max-response-bytes", &limit_arg]);
+    assert!(len <= limit, "{len} bytes should fit in {limit}");
+    let handles = response["handles"]
+        .as_array()
+        .expect("handles should be an array");
+    assert_eq!(handles.len(), 4, "metadata is never cut");
+    assert_eq!(handles[0]["text"], full["handles"][0]["text"]);
+    let truncation = &response["summary"]["truncation"];
+    let next_offset = truncation["next_offset"]
+        .as_u64()
+        .expect("next_offset should be set") as usize;
+    assert!(next_offset > 0 && next_offset < 4);
+    assert_eq!(handles[next_offset]["text_truncated"], true);
+    assert!(handles[next_offset].get("text").is_none());
+    assert_eq!(truncation["truncated_handles"], 4 - next_offset);
+
+    let offset_arg = next_offset.to_string();
+    let (_, rest) = read(&["--max-response-bytes", &limit_arg, "--offset", &offset_arg]);
+    assert_eq!(
+        rest["handles"][0]["text"],
+        full["handles"][next_offset]["text"]
+    );
+
+    // A text that can never fit is cut, and the cursor still moves past it.
+    let (_, tiny) = read(&["--max-response-bytes", "1"]);
+    assert_eq!(tiny["handles"][0]["text_truncated"], true);
+    assert_eq!(tiny["summary"]["truncation"]["next_offset"], 1);
+}
@@ -44,6 +44,58 @@ fn select_mode_line_returns_line_handles_with_anchors() {
     );
 }
 
+#[test]
+fn hash_algo_blake3_24_emits_long_anchors_that_patch_accepts() {
+    let source = "alpha\nbeta\n";
+    let mut temp_file = Builder::new()
+        .suffix(".txt")
+        .tempfile()
+        .expect("temp text file should be created");
+    temp_file
+        .write_all(source.as_bytes())
+        .expect("fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+    let file_arg = file_path.to_str().expect("path should be utf-8");
+
+    let output = common::run_identedit(&["read", "--json", "--mode", "line", file_arg]);
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["handles"][0]["hash_algo"], "blake3-12");
+
+    let output = common::run_identedit(&[
+        "read",
+        "--json",
+        "--mode",
+        "line",
+        "--hash-algo",
+        "blake3-24",
+        file_arg,
+    ]);
+    assert!(output.status.success(), "blake3-24 read should succeed");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    let handle = &response["handles"][1];
+    assert_eq!(handle["hash_algo"], "blake3-24");
+    let hash = handle["hash"].as_str().expect("hash should be a string");
+    assert_eq!(hash.len(), 24);
+    assert!(hash.starts_with(&identedit::hashline::compute_line_hash("beta")));
+    let anchor = handle["anchor"]
+        .as_str()
+        .expect("anchor should be a string");
+
+    let output = common::run_identedit(&["patch", "--at", anchor, "--set-line", "gamma", file_arg]);
+    assert!(
+        output.status.success(),
+        "a blake3-24 anchor should patch: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert_eq!(
+        std::fs::read_to_string(&file_path).expect("file should be readable"),
+        "alpha\ngamma\n"
+    );
+
+    let output = common::run_identedit(&["read", "--hash-algo", "blake3-24", file_arg]);
+    assert!(!output.status.success(), "--hash-algo needs --mode line");
+}
+
 #[test]
 fn select_mode_line_rejects_selector_flags() {
     let fixture = common::fixture_path("example.py");
@@ -109,3 +161,122 @@ fn select_json_mode_rejects_mode_line() {
     let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
     assert_eq!(response["error"]["type"], "invalid_request");
 }
+
+#[test]
+fn select_mode_line_honors_line_window_and_rejects_containing_line() {
+    let source = "alpha\nbeta\ngamma\ndelta\n";
+    let mut temp_file = Builder::new()
+        .suffix(".txt")
+        .tempfile()
+        .expect("temp text file should be created");
+    temp_file
+        .write_all(source.as_bytes())
+        .expect("fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+    let file = file_path.to_str().expect("path should be utf-8");
+
+    let output = common::run_identedit(&["read", "--mode", "line", "--lines", "2:3", file]);
+    assert!(output.status.success(), "line window should succeed");
+    let text = String::from_utf8(output.stdout).expect("stdout should be UTF-8");
+    let lines = text.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("2:") && lines[0].ends_with("|beta"));
+    assert!(lines[1].starts_with("3:") && lines[1].ends_with("|gamma"));
+
+    let output = common::run_identedit(&[
+        "read",
+        "--json",
+        "--mode",
+        "line",
+        "--byte-range",
+        "11:12",
+        file,
+    ]);
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["handles"][0]["line"], 3);
+    assert_eq!(response["summary"]["matches"], 1);
+
+    let output = common::run_identedit(&["read", "--mode", "line", "--containing-line", "2", file]);
+    assert!(!output.status.success(), "containing-line is ast only");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+}
+
+#[test]
+fn base64_text_encoding_round_trips_control_characters_through_read_and_patch() {
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+
+    let source = "plain\ncolor = \"\u{1b}[31m\u{7}\"\n";
+    let mut temp_file = Builder::new()
+        .suffix(".txt")
+        .tempfile()
+        .expect("temp text file should be created");
+    temp_file
+        .write_all(source.as_bytes())
+        .expect("fixture write should succeed");
+    let file_path = temp_file.keep().expect("temp file should persist").1;
+    let path = file_path.to_str().expect("path should be utf-8");
+
+    let output = common::run_identedit(&[
+        "read",
+        "--json",
+        "--mode",
+        "line",
+        "--text-encoding",
+        "base64",
+        path,
+    ]);
+    assert!(
+        output.status.success(),
+        "read --text-encoding base64 should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["text_encoding"], "base64");
+    let handle = &response["handles"][1];
+    let encoded = handle["text"].as_str().expect("text should be a string");
+    assert!(encoded.is_ascii());
+    assert_eq!(
+        STANDARD.decode(encoded).expect("text should be base64"),
+        "color = \"\u{1b}[31m\u{7}\"".as_bytes()
+    );
+
+    let replacement = STANDARD.encode("color = \"\u{1b}[0m\"");
+    let anchor = handle["anchor"]
+        .as_str()
+        .expect("anchor should be a string");
+    let output = common::run_identedit(&[
+        "patch",
+        "--anchor",
+        anchor,
+        "--set-line",
+        &replacement,
+        "--text-encoding",
+        "base64",
+        path,
+    ]);
+    assert!(
+        output.status.success(),
+        "patch --text-encoding base64 should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        std::fs::read_to_string(&file_path).expect("file should be readable"),
+        "plain\ncolor = \"\u{1b}[0m\"\n"
+    );
+
+    let rejected = common::run_identedit(&[
+        "patch",
+        "--anchor",
+        anchor,
+        "--set-line",
+        "not base64!",
+        "--text-encoding",
+        "base64",
+        path,
+    ]);
+    assert!(!rejected.status.success());
+    let error: Value = serde_json::from_slice(&rejected.stdout).expect("stdout should be JSON");
+    assert_eq!(error["error"]["type"], "invalid_request");
+}
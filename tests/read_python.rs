@@ -73,3 +73,335 @@ fn applies_name_pattern_filter_for_python_functions() {
     assert_eq!(handles.len(), 1, "expected one function matching process_*");
     assert_eq!(handles[0]["name"], "process_data");
 }
+
+#[test]
+fn line_window_keeps_only_handles_intersecting_the_lines() {
+    let fixture = fixture_path("example.py");
+    let output = run_read(
+        &["--kind", "function_definition", "--lines", "6:7"],
+        &fixture,
+    );
+    assert!(output.status.success(), "windowed read should succeed");
+
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    let names = response["handles"]
+        .as_array()
+        .expect("handles should be an array")
+        .iter()
+        .map(|handle| handle["name"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["helper"]);
+
+    let output = run_read(&["--byte-range", "0:4"], &fixture);
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert!(
+        response["handles"]
+            .as_array()
+            .expect("handles should be an array")
+            .iter()
+            .all(|handle| handle["span"]["start"].as_u64() < Some(4)),
+        "byte window should drop handles starting after it"
+    );
+}
+
+#[test]
+fn containing_line_returns_innermost_covering_node() {
+    let fixture = fixture_path("example.py");
+    let output = run_read(&["--containing-line", "3"], &fixture);
+    assert!(
+        output.status.success(),
+        "containing-line read should succeed"
+    );
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["summary"]["matches"], 1);
+    assert_eq!(response["handles"][0]["kind"], "return_statement");
+
+    let output = run_read(
+        &["--containing-line", "3", "--kind", "function_definition"],
+        &fixture,
+    );
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["handles"][0]["name"], "process_data");
+
+    let output = run_read(&["--containing-line", "3", "--lines", "1:3"], &fixture);
+    assert!(!output.status.success(), "window flags should be exclusive");
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+}
+
+#[test]
+fn sorted_pages_walk_every_handle_once() {
+    let fixture = fixture_path("example.py");
+    let output = run_read(&["--sort", "name"], &fixture);
+    assert!(output.status.success(), "sorted read should succeed");
+    let full: Value = serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    let total = full["summary"]["matches"]
+        .as_u64()
+        .expect("matches should be a number");
+    assert!(full["summary"].get("page").is_none());
+    assert_eq!(full["handles"][0]["name"], "helper");
+    assert_eq!(full["handles"][1]["name"], "process_data");
+
+    let mut paged = Vec::new();
+    let mut offset = 0;
+    loop {
+        let offset_arg = offset.to_string();
+        let output = run_read(
+            &["--sort", "name", "--offset", &offset_arg, "--limit", "3"],
+            &fixture,
+        );
+        let page: Value =
+            serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+        assert_eq!(page["summary"]["matches"], total);
+        paged.extend(page["handles"].as_array().expect("handles array").clone());
+        match page["summary"]["page"]["next_offset"].as_u64() {
+            Some(next_offset) => offset = next_offset,
+            None => break,
+        }
+    }
+    assert_eq!(&paged, full["handles"].as_array().expect("handles array"));
+
+    let output = run_read(&["--limit", "0"], &fixture);
+    assert!(!output.status.success(), "--limit 0 should be rejected");
+}
+
+#[test]
+fn repeated_kinds_name_regex_and_negative_filters_combine() {
+    let fixture = fixture_path("example.py");
+    let names = |arguments: &[&str]| {
+        let output = run_read(arguments, &fixture);
+        assert!(output.status.success(), "filtered read should succeed");
+        let response: Value =
+            serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+        response["handles"]
+            .as_array()
+            .expect("handles should be an array")
+            .iter()
+            .map(|handle| {
+                format!(
+                    "{}:{}",
+                    handle["kind"].as_str().unwrap_or_default(),
+                    handle["name"].as_str().unwrap_or("-")
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        names(&["--kind", "function_definition", "--name-regex", "^help"]),
+        ["function_definition:helper"]
+    );
+    assert_eq!(
+        names(&[
+            "--kind",
+            "function_definition",
+            "--kind",
+            "return_statement",
+            "--not-kind",
+            "return_statement",
+            "--not-name",
+            "help*",
+        ]),
+        ["function_definition:process_data"]
+    );
+
+    let output = run_read(&["--name-regex", "("], &fixture);
+    assert!(!output.status.success(), "invalid regex should be rejected");
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+}
+
+#[test]
+fn name_mode_matches_decomposed_identifiers_and_ignores_case() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("names.py");
+    std::fs::write(
+        &file,
+        "def cafe\u{301}_total(x):\n    return x\n\n\ndef LoadUser():\n    return 1\n",
+    )
+    .expect("fixture should be written");
+    let matches = |arguments: &[&str]| {
+        let output = run_read(arguments, &file);
+        assert!(output.status.success(), "read should succeed");
+        let response: Value =
+            serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+        response["summary"]["matches"].clone()
+    };
+
+    assert_eq!(matches(&["--name", "caf\u{e9}_*"]), 0);
+    assert_eq!(matches(&["--name", "caf\u{e9}_*", "--name-mode", "nfc"]), 1);
+    assert_eq!(matches(&["--name", "loaduser", "--name-mode", "ci"]), 1);
+    assert_eq!(
+        matches(&[
+            "--kind",
+            "function_definition",
+            "--not-name",
+            "LOAD*",
+            "--name-mode",
+            "ci"
+        ]),
+        1
+    );
+}
+
+#[test]
+fn context_lines_surround_each_handle() {
+    let fixture = fixture_path("example.py");
+    let output = run_read(&["--kind", "return_statement", "--context", "1"], &fixture);
+    assert!(output.status.success(), "read --context should succeed");
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    let first = &response["handles"][0];
+    assert_eq!(
+        first["context_before"],
+        serde_json::json!(["    result = value + 1"])
+    );
+    assert_eq!(first["context_after"], serde_json::json!([""]));
+    let last = &response["handles"][1];
+    assert_eq!(last["context_before"], serde_json::json!(["def helper():"]));
+    assert_eq!(last["context_after"], serde_json::json!([]));
+
+    let output = run_read(&["--kind", "return_statement"], &fixture);
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert!(response["handles"][0].get("context_before").is_none());
+}
+
+#[test]
+fn resolve_returns_current_nodes_for_identities_across_files() {
+    let example = fixture_path("example.py");
+    let ambiguous = fixture_path("ambiguous.py");
+    let identity_of = |file: &PathBuf, name: &str| {
+        let output = run_read(&["--kind", "function_definition", "--name", name], file);
+        let response: Value =
+            serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+        response["handles"][0]["identity"]
+            .as_str()
+            .expect("identity should be a string")
+            .to_string()
+    };
+    let process_data = identity_of(&example, "process_data");
+    let duplicate = identity_of(&ambiguous, "duplicate");
+
+    let list = tempfile::NamedTempFile::new().expect("temp file should be created");
+    std::fs::write(
+        list.path(),
+        format!("# working set\n{duplicate}\n\n{process_data}\nffffffffffffffff\n"),
+    )
+    .expect("identity list should be written");
+    let output = Command::new(env!("CARGO_BIN_EXE_identedit"))
+        .args([
+            "read",
+            "--json",
+            "--resolve",
+            &process_data,
+            "--resolve-file",
+        ])
+        .arg(list.path())
+        .arg(&example)
+        .arg(&ambiguous)
+        .output()
+        .expect("failed to run identedit binary");
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+
+    let handles = response["handles"]
+        .as_array()
+        .expect("handles should be an array");
+    assert_eq!(handles.len(), 3, "both duplicates and process_data resolve");
+    assert_eq!(handles[0]["identity"], process_data.as_str());
+    assert_eq!(
+        handles[0]["text"],
+        "def process_data(value):\n    result = value + 1\n    return result"
+    );
+    assert!(handles[0]["expected_old_hash"].is_string());
+    assert!(
+        handles[1..]
+            .iter()
+            .all(|handle| handle["identity"] == duplicate.as_str())
+    );
+    assert_eq!(
+        response["unresolved"],
+        serde_json::json!(["ffffffffffffffff"])
+    );
+    assert_eq!(response["summary"]["files_scanned"], 2);
+
+    let plain = run_read(&["--kind", "function_definition"], &example);
+    let plain: Value = serde_json::from_slice(&plain.stdout).expect("stdout should be valid JSON");
+    assert!(plain.get("unresolved").is_none());
+
+    let filtered = run_read(&["--resolve", &process_data, "--kind", "call"], &example);
+    assert!(!filtered.status.success(), "--resolve rejects node filters");
+}
+
+#[test]
+fn if_none_match_skips_handles_until_the_file_changes() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("poll.py");
+    std::fs::write(&file, "def a():\n    return 1\n").expect("fixture should be written");
+
+    let output = run_read(&[], &file);
+    assert!(output.status.success(), "read should succeed");
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    let token = response["state_token"]
+        .as_str()
+        .expect("state_token should be a string")
+        .to_string();
+
+    let output = run_read(&["--if-none-match", &token], &file);
+    assert!(output.status.success(), "conditional read should succeed");
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(
+        response,
+        serde_json::json!({"status": "not_modified", "state_token": token})
+    );
+
+    std::fs::write(&file, "def a():\n    return 2\n").expect("fixture should be rewritten");
+    let output = run_read(&["--if-none-match", &token], &file);
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert!(
+        response["handles"].is_array(),
+        "changed file should re-read"
+    );
+    assert_ne!(response["state_token"], token.as_str());
+}
+
+#[test]
+fn with_metrics_reports_size_and_complexity_per_handle() {
+    let fixture = fixture_path("example.py");
+    let output = run_read(
+        &["--kind", "function_definition", "--with-metrics"],
+        &fixture,
+    );
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(
+        response["handles"][0]["metrics"],
+        serde_json::json!({"lines": 3, "bytes": 65, "nesting_depth": 0, "cyclomatic": 1})
+    );
+
+    let output = run_read(&["--with-metrics", "--annotations"], &fixture);
+    assert!(!output.status.success(), "--annotations should be rejected");
+    let response: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+}
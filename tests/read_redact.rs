@@ -0,0 +1,107 @@
+use std::fs;
+
+use serde_json::Value;
+
+mod common;
+
+const SOURCE: &str =
+    "def connect():\n    token = \"sk-live-4242424242\"\n    return open_session(token)\n";
+
+fn read_json(args: &[&str]) -> Value {
+    let output = common::run_identedit(args);
+    assert!(
+        output.status.success(),
+        "read should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    serde_json::from_slice(&output.stdout).expect("stdout should be JSON")
+}
+
+#[test]
+fn redact_masks_texts_but_keeps_hashes_of_the_real_content() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("client.py");
+    fs::write(&file, SOURCE).expect("fixture should be written");
+    let file_arg = file.to_str().expect("path should be utf-8");
+
+    let plain = read_json(&["read", "--json", "--verbose", file_arg]);
+    let redacted = read_json(&[
+        "read",
+        "--json",
+        "--verbose",
+        "--redact",
+        "sk-live-[0-9]+",
+        file_arg,
+    ]);
+    let text = redacted["handles"][0]["text"]
+        .as_str()
+        .expect("text should be a string");
+    assert!(
+        text.contains("token = \"[REDACTED]\"") && !text.contains("sk-live"),
+        "unexpected text: {text}"
+    );
+    assert!(
+        !redacted.to_string().contains("sk-live"),
+        "no returned text may hold the secret"
+    );
+    assert!(redacted["summary"]["redactions"].as_u64().unwrap_or(0) >= 1);
+    assert!(plain["summary"].get("redactions").is_none());
+    assert_eq!(
+        redacted["handles"][0]["expected_old_hash"],
+        plain["handles"][0]["expected_old_hash"]
+    );
+    assert_eq!(redacted["handles"][0]["span"], plain["handles"][0]["span"]);
+
+    let lines = read_json(&[
+        "read",
+        "--json",
+        "--mode",
+        "line",
+        "--redact",
+        "sk-live-[0-9]+",
+        file_arg,
+    ]);
+    assert_eq!(lines["handles"][1]["text"], "    token = \"[REDACTED]\"");
+
+    // A plan built from the redacted handle still applies to the real file.
+    let identity = redacted["handles"][0]["identity"]
+        .as_str()
+        .expect("identity should be a string");
+    let output = common::run_identedit(&[
+        "patch",
+        "--identity",
+        identity,
+        "--replace",
+        "def connect():\n    return open_session(load_token())",
+        file_arg,
+    ]);
+    assert!(
+        output.status.success(),
+        "patch should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let rewritten = fs::read_to_string(&file).expect("file should be readable");
+    assert!(
+        rewritten.contains("open_session(load_token())") && !rewritten.contains("token ="),
+        "unexpected file: {rewritten}"
+    );
+}
+
+#[test]
+fn redact_rejects_patterns_matching_the_empty_string() {
+    let output = common::run_identedit(&[
+        "read",
+        "--json",
+        "--redact",
+        "x*",
+        common::fixture_path("example.py")
+            .to_str()
+            .expect("path should be utf-8"),
+    ]);
+    assert!(
+        !output.status.success(),
+        "an empty-matching pattern must fail"
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+}
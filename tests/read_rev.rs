@@ -0,0 +1,92 @@
+use std::fs;
+
+use serde_json::Value;
+
+mod common;
+
+fn read_json(args: &[&str]) -> Value {
+    let output = common::run_identedit(args);
+    serde_json::from_slice(&output.stdout).expect("stdout should be JSON")
+}
+
+fn function_names(response: &Value) -> Vec<&str> {
+    response["handles"]
+        .as_array()
+        .expect("handles should be an array")
+        .iter()
+        .filter_map(|handle| handle["name"].as_str())
+        .collect()
+}
+
+#[test]
+fn rev_reads_committed_content_instead_of_the_working_tree() {
+    let workspace = common::committed_repo(&[
+        ("module.py", "def committed():\n    return 1\n"),
+        ("removed.py", "def gone():\n    return 2\n"),
+    ]);
+    let root = workspace.path();
+    let module = root.join("module.py");
+    let removed = root.join("removed.py");
+    fs::write(&module, "def edited():\n    return 3\n").expect("fixture should be rewritten");
+    fs::remove_file(&removed).expect("fixture should be removed");
+
+    let module_arg = module.to_str().expect("path should be utf-8");
+    let removed_arg = removed.to_str().expect("path should be utf-8");
+    let working = read_json(&[
+        "read",
+        "--json",
+        "--kind",
+        "function_definition",
+        module_arg,
+    ]);
+    assert_eq!(function_names(&working), ["edited"]);
+    assert!(working["file_preconditions"][0].get("revision").is_none());
+
+    let committed = read_json(&[
+        "read",
+        "--json",
+        "--kind",
+        "function_definition",
+        "--rev",
+        "HEAD",
+        module_arg,
+        removed_arg,
+    ]);
+    assert_eq!(function_names(&committed), ["committed", "gone"]);
+    let revision = committed["file_preconditions"][0]["revision"]
+        .as_str()
+        .expect("revision should be a commit id");
+    assert_eq!(revision.len(), 40);
+    assert_ne!(
+        committed["file_preconditions"][0]["expected_file_hash"],
+        working["file_preconditions"][0]["expected_file_hash"]
+    );
+}
+
+#[test]
+fn rev_rejects_unknown_revisions_and_paths() {
+    let workspace = common::committed_repo(&[("module.py", "def committed():\n    return 1\n")]);
+    let root = workspace.path();
+    let module = root.join("module.py");
+    let untracked = root.join("untracked.py");
+    fs::write(&untracked, "x = 1\n").expect("fixture should be written");
+
+    let module_arg = module.to_str().expect("path should be utf-8");
+    let unknown = read_json(&["read", "--json", "--rev", "no-such-branch", module_arg]);
+    assert_eq!(unknown["error"]["type"], "invalid_request");
+    assert!(
+        unknown["error"]["message"]
+            .as_str()
+            .expect("message should be a string")
+            .contains("no-such-branch")
+    );
+
+    let missing = read_json(&[
+        "read",
+        "--json",
+        "--rev",
+        "HEAD",
+        untracked.to_str().expect("path should be utf-8"),
+    ]);
+    assert_eq!(missing["error"]["type"], "invalid_request");
+}
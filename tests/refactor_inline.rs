@@ -0,0 +1,96 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+use serde_json::Value;
+use tempfile::tempdir;
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_identedit"))
+        .args(args)
+        .output()
+        .expect("failed to run identedit binary")
+}
+
+fn function_identity(file: &Path, name: &str) -> String {
+    let output = run(&[
+        "read",
+        "--json",
+        "--kind",
+        "function_definition",
+        "--name",
+        name,
+        file.to_str().expect("path should be utf-8"),
+    ]);
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    response["handles"][0]["identity"]
+        .as_str()
+        .expect("function should be found")
+        .to_string()
+}
+
+fn inline(file: &Path, function: &str, variable: &str, apply: bool) -> Output {
+    let identity = function_identity(file, function);
+    let mut args = vec![
+        "refactor",
+        "inline-variable",
+        "--identity",
+        &identity,
+        "--variable",
+        variable,
+    ];
+    if apply {
+        args.push("--apply");
+    }
+    args.push(file.to_str().expect("path should be utf-8"));
+    run(&args)
+}
+
+#[test]
+fn inline_variable_replaces_uses_and_removes_the_declaration_line() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("pricing.py");
+    let source = "def price(item, qty):\n    subtotal = item.cost * qty\n    tax = subtotal * 0.2\n    return subtotal + tax\n\n\ndef label(item):\n    name = item.name\n    return name.upper()\n";
+    fs::write(&file, source).expect("fixture should be written");
+
+    let plan = inline(&file, "price", "subtotal", false);
+    assert!(
+        plan.status.success(),
+        "inline plan should succeed: {}",
+        String::from_utf8_lossy(&plan.stdout)
+    );
+    let plan: Value = serde_json::from_slice(&plan.stdout).expect("stdout should be JSON");
+    assert_eq!(
+        plan["files"][0]["operations"].as_array().map(Vec::len),
+        Some(3)
+    );
+    assert_eq!(fs::read_to_string(&file).expect("readable"), source);
+
+    let applied = inline(&file, "price", "subtotal", true);
+    assert!(applied.status.success(), "inline apply should succeed");
+    let applied = inline(&file, "label", "name", true);
+    assert!(applied.status.success(), "inline apply should succeed");
+    assert_eq!(
+        fs::read_to_string(&file).expect("readable"),
+        "def price(item, qty):\n    tax = (item.cost * qty) * 0.2\n    return (item.cost * qty) + tax\n\n\ndef label(item):\n    return item.name.upper()\n"
+    );
+}
+
+#[test]
+fn inline_variable_requires_a_single_assignment() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("loop.py");
+    let source = "def total(items, start):\n    acc = 0\n    for item in items:\n        acc = acc + item\n    return acc + start\n";
+    fs::write(&file, source).expect("fixture should be written");
+
+    for variable in ["acc", "start", "item", "missing"] {
+        let output = inline(&file, "total", variable, true);
+        assert!(
+            !output.status.success(),
+            "inlining '{variable}' should be rejected"
+        );
+        let error: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+        assert_eq!(error["error"]["type"], "invalid_request");
+    }
+    assert_eq!(fs::read_to_string(&file).expect("readable"), source);
+}
@@ -0,0 +1,106 @@
+use std::fs;
+
+use serde_json::Value;
+use tempfile::tempdir;
+
+mod common;
+
+#[test]
+fn rename_plans_and_applies_identifier_renames_across_files() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let root = workspace.path();
+    fs::create_dir_all(root.join("src/.cache")).expect("dirs should be created");
+    fs::write(
+        root.join("src/models.py"),
+        "def load_user(user_id):\n    # load_user reads from the cache\n    return {\"name\": \"load_user\"}\n",
+    )
+    .expect("fixture should be written");
+    fs::write(
+        root.join("src/views.py"),
+        "from models import load_user\n\n\ndef show(user_id):\n    return load_user(user_id)\n",
+    )
+    .expect("fixture should be written");
+    fs::write(root.join("src/notes.txt"), "load_user\n").expect("fixture should be written");
+    fs::write(root.join("src/.cache/stale.py"), "load_user()\n")
+        .expect("fixture should be written");
+
+    let plan = common::run_in(
+        root,
+        &[
+            "refactor",
+            "rename",
+            "--symbol",
+            "load_user",
+            "--to",
+            "fetch_user",
+            "--dir",
+            "src",
+        ],
+    );
+    assert!(
+        plan.status.success(),
+        "rename plan should succeed: {}",
+        String::from_utf8_lossy(&plan.stdout)
+    );
+    let plan: Value = serde_json::from_slice(&plan.stdout).expect("stdout should be JSON");
+    let files = plan["files"].as_array().expect("files should be an array");
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[0]["operations"].as_array().map(Vec::len), Some(1));
+    assert_eq!(files[1]["operations"].as_array().map(Vec::len), Some(2));
+    assert_eq!(
+        fs::read_to_string(root.join("src/models.py")).expect("file should be readable"),
+        "def load_user(user_id):\n    # load_user reads from the cache\n    return {\"name\": \"load_user\"}\n",
+        "planning must not write"
+    );
+
+    let applied = common::run_in(
+        root,
+        &[
+            "refactor",
+            "rename",
+            "--symbol",
+            "load_user",
+            "--to",
+            "fetch_user",
+            "--dir",
+            "src",
+            "--apply",
+        ],
+    );
+    assert!(
+        applied.status.success(),
+        "rename apply should succeed: {}",
+        String::from_utf8_lossy(&applied.stdout)
+    );
+    let response: Value = serde_json::from_slice(&applied.stdout).expect("stdout should be JSON");
+    assert_eq!(response["summary"]["files_modified"], 2);
+    assert_eq!(
+        fs::read_to_string(root.join("src/models.py")).expect("file should be readable"),
+        "def fetch_user(user_id):\n    # load_user reads from the cache\n    return {\"name\": \"load_user\"}\n"
+    );
+    assert_eq!(
+        fs::read_to_string(root.join("src/views.py")).expect("file should be readable"),
+        "from models import fetch_user\n\n\ndef show(user_id):\n    return fetch_user(user_id)\n"
+    );
+    assert_eq!(
+        fs::read_to_string(root.join("src/.cache/stale.py")).expect("file should be readable"),
+        "load_user()\n"
+    );
+
+    let missing = common::run_in(
+        root,
+        &[
+            "refactor",
+            "rename",
+            "--symbol",
+            "load_user",
+            "--to",
+            "fetch_user",
+            "--dir",
+            "src",
+        ],
+    );
+    assert!(!missing.status.success());
+    let error: Value = serde_json::from_slice(&missing.stdout).expect("stdout should be JSON");
+    assert_eq!(error["error"]["type"], "invalid_request");
+}
@@ -0,0 +1,149 @@
+use std::fs;
+
+use serde_json::Value;
+
+mod common;
+
+fn run_json_with_stdin(args: &[&str], stdin: &str) -> Value {
+    let output = common::run_identedit_with_stdin(args, stdin);
+    assert!(
+        output.status.success(),
+        "{args:?} should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    serde_json::from_slice(&output.stdout).expect("stdout should be JSON")
+}
+
+#[test]
+fn apply_reread_after_returns_the_handles_the_edit_created() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("main.py");
+    fs::write(
+        &file,
+        "def a():\n    return 1\n\n\ndef b():\n    return 2\n",
+    )
+    .expect("fixture should be written");
+    let file_arg = file.to_str().expect("path should be utf-8");
+
+    let read = common::run_json(&["read", "--json", "--kind", "function_definition", file_arg]);
+    let handle_b = read["handles"]
+        .as_array()
+        .expect("handles should be an array")
+        .iter()
+        .find(|handle| handle["name"] == "b")
+        .expect("handle for b should exist");
+    let plan = common::run_json(&[
+        "edit",
+        "--identity",
+        handle_b["identity"]
+            .as_str()
+            .expect("identity should exist"),
+        "--replace",
+        "def b():\n    return 22",
+        file_arg,
+    ]);
+    let plan_file = workspace.path().join("plan.json");
+    fs::write(&plan_file, plan.to_string()).expect("plan should be written");
+
+    let response = common::run_json(&[
+        "apply",
+        "--reread-after",
+        plan_file.to_str().expect("path should be utf-8"),
+    ]);
+    let reread = response["reread"]
+        .as_array()
+        .expect("reread should be an array");
+    let fresh_b = reread
+        .iter()
+        .find(|handle| handle["kind"] == "function_definition")
+        .expect("the rewritten function should be reread");
+    assert_eq!(fresh_b["name"], "b");
+    assert_ne!(fresh_b["identity"], handle_b["identity"]);
+
+    // Fresh handles resolve without another read; `a` did not move, so it is not listed.
+    assert!(!reread.iter().any(|handle| handle["name"] == "a"));
+    let second = common::run_json(&[
+        "patch",
+        file_arg,
+        "--identity",
+        fresh_b["identity"].as_str().expect("identity should exist"),
+        "--replace",
+        "def b():\n    return 222",
+    ]);
+    assert_eq!(second["summary"]["operations_applied"], 1);
+}
+
+#[test]
+fn patch_reread_after_returns_anchors_around_changed_lines() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("notes.txt");
+    fs::write(&file, "one\ntwo\nthree\nfour\nfive\n").expect("fixture should be written");
+    let file_arg = file.to_str().expect("path should be utf-8");
+
+    let lines = common::run_json(&["read", "--mode", "line", "--json", file_arg]);
+    let anchor = lines["handles"][2]["anchor"]
+        .as_str()
+        .expect("anchor should exist")
+        .to_string();
+
+    let flag_response = common::run_json(&[
+        "patch",
+        file_arg,
+        "--at",
+        &anchor,
+        "--set-line",
+        "THREE",
+        "--reread-after",
+    ]);
+    let texts = |response: &Value| {
+        response["reread"]
+            .as_array()
+            .expect("reread should be an array")
+            .iter()
+            .map(|handle| {
+                (
+                    handle["line"].as_u64().expect("line should exist"),
+                    handle["text"]
+                        .as_str()
+                        .expect("text should exist")
+                        .to_string(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        texts(&flag_response),
+        vec![
+            (2, "two".to_string()),
+            (3, "THREE".to_string()),
+            (4, "four".to_string())
+        ]
+    );
+
+    let fresh_anchor = flag_response["reread"][1]["anchor"]
+        .as_str()
+        .expect("anchor should exist");
+    let request = serde_json::json!({
+        "command": "patch",
+        "file": file_arg,
+        "target": {"type": "line", "anchor": fresh_anchor},
+        "op": {"type": "insert_after", "text": "three and a half"}
+    });
+    let json_response =
+        run_json_with_stdin(&["patch", "--json", "--reread-after"], &request.to_string());
+    assert_eq!(
+        texts(&json_response),
+        vec![
+            (3, "THREE".to_string()),
+            (4, "three and a half".to_string()),
+            (5, "four".to_string())
+        ]
+    );
+}
+
+#[test]
+fn apply_reread_after_is_rejected_with_dry_run() {
+    let output = common::run_identedit(&["apply", "--dry-run", "--reread-after"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("--reread-after"));
+}
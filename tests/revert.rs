@@ -0,0 +1,118 @@
+use std::fs;
+use std::path::Path;
+
+use serde_json::{Value, json};
+
+mod common;
+
+const SOURCE: &str =
+    "def a():\n    return 1\n\n\ndef b():\n    return 2\n\n\ndef c():\n    return 3\n";
+
+/// The single operation of an `edit` plan against the function named `name`.
+fn edit_operation(file: &Path, name: &str, edit: &[&str]) -> Value {
+    let file_arg = file.to_str().expect("path should be utf-8");
+    let output =
+        common::run_identedit(&["read", "--json", "--kind", "function_definition", file_arg]);
+    assert!(output.status.success(), "read should succeed");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    let handle = response["handles"]
+        .as_array()
+        .expect("handles should be an array")
+        .iter()
+        .find(|handle| handle["name"] == name)
+        .expect("handle should exist");
+
+    let mut args = vec![
+        "edit",
+        "--identity",
+        handle["identity"].as_str().expect("identity should exist"),
+    ];
+    args.extend(edit);
+    args.push(file_arg);
+    let output = common::run_identedit(&args);
+    assert!(output.status.success(), "edit should succeed");
+    let plan: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    plan["files"][0]["operations"][0].clone()
+}
+
+fn forward_plan(file: &Path) -> Value {
+    let replace_a = edit_operation(
+        file,
+        "a",
+        &["--replace", "def a():\n    value = 10\n    return value"],
+    );
+    let delete_c = edit_operation(file, "c", &["--delete"]);
+    json!({"files": [{"file": file, "operations": [replace_a, delete_c]}]})
+}
+
+#[test]
+fn revert_restores_the_files_an_apply_changed() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("main.py");
+    fs::write(&file, SOURCE).expect("fixture should be written");
+    let plan = forward_plan(&file);
+    let inverse_path = workspace.path().join("inverse.json");
+    let inverse_arg = inverse_path.to_str().expect("path should be utf-8");
+
+    let output = common::run_identedit_with_stdin(
+        &["apply", "--inverse-out", inverse_arg],
+        &plan.to_string(),
+    );
+    assert!(
+        output.status.success(),
+        "apply should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert_ne!(
+        fs::read_to_string(&file).expect("file should exist"),
+        SOURCE
+    );
+
+    let output = common::run_identedit(&["revert", inverse_arg]);
+    assert!(
+        output.status.success(),
+        "revert should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["summary"]["files_modified"], 1);
+    assert_eq!(
+        fs::read_to_string(&file).expect("file should exist"),
+        SOURCE
+    );
+}
+
+#[test]
+fn revert_refuses_lines_edited_since_and_forward_plans() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("main.py");
+    fs::write(&file, SOURCE).expect("fixture should be written");
+    let plan = forward_plan(&file);
+
+    let output = common::run_identedit_with_stdin(&["apply", "--emit-inverse"], &plan.to_string());
+    assert!(
+        output.status.success(),
+        "apply should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    let inverse = response["inverse"].to_string();
+
+    let output = common::run_identedit_with_stdin(&["revert"], &plan.to_string());
+    assert!(!output.status.success(), "a forward plan must be refused");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "invalid_request");
+
+    let edited = fs::read_to_string(&file)
+        .expect("file should exist")
+        .replace("value = 10", "value = 11");
+    fs::write(&file, &edited).expect("file should be rewritten");
+    let output = common::run_identedit_with_stdin(&["revert"], &inverse);
+    assert!(!output.status.success(), "a stale inverse must be refused");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "precondition_failed");
+    assert_eq!(
+        fs::read_to_string(&file).expect("file should exist"),
+        edited
+    );
+}
@@ -0,0 +1,87 @@
+use std::fs;
+
+use serde_json::Value;
+use tempfile::tempdir;
+
+mod common;
+
+const PYTHON_SOURCE: &str =
+    "def process_data(value):\n    return value + 1\n\n\ndef helper():\n    return 2\n";
+
+fn check_statuses(report: &Value) -> Vec<(String, String)> {
+    report["checks"]
+        .as_array()
+        .expect("checks should be an array")
+        .iter()
+        .map(|check| {
+            (
+                check["name"].as_str().unwrap_or_default().to_string(),
+                check["status"].as_str().unwrap_or_default().to_string(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn selftest_passes_on_sample_files_and_leaves_them_untouched() {
+    let workspace = tempdir().expect("tempdir should be created");
+    fs::write(workspace.path().join("example.py"), PYTHON_SOURCE)
+        .expect("fixture should be written");
+    fs::write(workspace.path().join("notes.txt"), "alpha\nbeta\ngamma\n")
+        .expect("fixture should be written");
+
+    let output = common::run_in(
+        workspace.path(),
+        &["selftest", "--max-nodes", "4", "example.py", "notes.txt"],
+    );
+    assert!(
+        output.status.success(),
+        "selftest should pass: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["passed"], true);
+    assert_eq!(response["summary"]["files"], 2);
+    assert_eq!(response["summary"]["checks_failed"], 0);
+
+    let python = check_statuses(&response["files"][0]);
+    assert_eq!(
+        python,
+        [
+            ("plan_apply_roundtrip", "passed"),
+            ("idempotent_reapply", "passed"),
+            ("line_ending_roundtrip", "passed"),
+        ]
+        .map(|(name, status)| (name.to_string(), status.to_string()))
+    );
+    assert_eq!(
+        response["files"][0]["checks"][0]["detail"], "4 nodes edited",
+        "--max-nodes should cap the sample"
+    );
+    let text = check_statuses(&response["files"][1]);
+    assert_eq!(
+        text[2],
+        ("line_ending_roundtrip".to_string(), "passed".to_string())
+    );
+
+    assert_eq!(
+        fs::read_to_string(workspace.path().join("example.py")).expect("file should be readable"),
+        PYTHON_SOURCE
+    );
+    assert_eq!(
+        fs::read_dir(workspace.path())
+            .expect("workspace should be listable")
+            .count(),
+        2,
+        "selftest must not leave files next to the samples"
+    );
+}
+
+#[test]
+fn selftest_reports_missing_files_as_io_errors() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let output = common::run_in(workspace.path(), &["selftest", "missing.py"]);
+    assert!(!output.status.success(), "missing sample should fail");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["error"]["type"], "io_error");
+}
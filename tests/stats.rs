@@ -0,0 +1,75 @@
+use std::fs;
+
+use tempfile::tempdir;
+
+mod common;
+
+#[test]
+fn recorded_commands_add_up_per_command_and_error_type() {
+    let workspace = tempdir().expect("tempdir should be created");
+    let workspace = workspace.path();
+    fs::write(workspace.join("config.json"), "{\"port\": 80}\n")
+        .expect("fixture should be written");
+
+    // Unrecorded runs leave no counters behind.
+    common::run_in(workspace, &["read", "--json", "config.json"]);
+    assert_eq!(
+        common::stdout_json(&common::run_in(workspace, &["stats"]))["commands"],
+        serde_json::json!({})
+    );
+
+    let read = common::stdout_json(&common::run_in(
+        workspace,
+        &["read", "--json", "--record-stats", "config.json"],
+    ));
+    let token = read["state_token"]
+        .as_str()
+        .expect("state_token should be a string");
+    common::run_in(
+        workspace,
+        &[
+            "read",
+            "--json",
+            "--record-stats",
+            "--if-none-match",
+            token,
+            "config.json",
+        ],
+    );
+    let failed = common::run_in(
+        workspace,
+        &[
+            "patch",
+            "--record-stats",
+            "--config-path",
+            "missing",
+            "--set-value",
+            "1",
+            "config.json",
+        ],
+    );
+    let error_type = common::stdout_json(&failed)["error"]["type"]
+        .as_str()
+        .expect("error type should be a string")
+        .to_string();
+
+    let stats = common::stdout_json(&common::run_in(workspace, &["stats", "--record-stats"]));
+    assert_eq!(stats["commands"]["read"]["requests"], 2);
+    assert_eq!(stats["commands"]["read"]["failures"], 0);
+    assert_eq!(stats["commands"]["patch"]["failures"], 1);
+    assert_eq!(stats["commands"]["patch"]["errors"][&error_type], 1);
+    assert!(stats["commands"].get("stats").is_none());
+    assert_eq!(stats["read_cache"]["hits"], 1);
+    assert_eq!(stats["read_cache"]["misses"], 0);
+
+    let prometheus = common::run_in(workspace, &["stats", "--format", "prometheus", "--reset"]);
+    let text = String::from_utf8(prometheus.stdout).expect("stdout should be utf-8");
+    assert!(text.contains("identedit_requests_total{command=\"read\"} 2\n"));
+    assert!(text.contains(&format!(
+        "identedit_errors_total{{command=\"patch\",type=\"{error_type}\"}} 1\n"
+    )));
+    assert_eq!(
+        common::stdout_json(&common::run_in(workspace, &["stats"]))["commands"],
+        serde_json::json!({})
+    );
+}
@@ -0,0 +1,207 @@
+use std::fs;
+use std::path::Path;
+
+use serde_json::{Value, json};
+
+mod common;
+
+/// The single operation of an `edit` plan against the `kind` node that `matches` picks.
+fn edit_operation(
+    file: &Path,
+    kind: &str,
+    matches: impl Fn(&Value) -> bool,
+    edit: &[&str],
+) -> Value {
+    let file_arg = file.to_str().expect("path should be utf-8");
+    let output = common::run_identedit(&["read", "--json", "--kind", kind, file_arg]);
+    assert!(output.status.success(), "read should succeed");
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    let handle = response["handles"]
+        .as_array()
+        .expect("handles should be an array")
+        .iter()
+        .find(|handle| matches(handle))
+        .expect("handle should exist");
+
+    let mut args = vec![
+        "edit",
+        "--identity",
+        handle["identity"].as_str().expect("identity should exist"),
+    ];
+    args.extend(edit);
+    args.push(file_arg);
+    let output = common::run_identedit(&args);
+    assert!(
+        output.status.success(),
+        "edit should succeed: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let plan: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    plan["files"][0]["operations"][0].clone()
+}
+
+fn named(name: &'static str) -> impl Fn(&Value) -> bool {
+    move |handle| handle["name"] == name
+}
+
+fn verify(plan: &Value) -> (bool, Value) {
+    let output = common::run_identedit_with_stdin(&["verify"], &plan.to_string());
+    let response = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    (output.status.success(), response)
+}
+
+#[test]
+fn verify_reports_each_operation_without_touching_files() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let main_file = workspace.path().join("main.py");
+    let other_file = workspace.path().join("other.py");
+    let main_source =
+        "def a():\n    return 1\n\n\ndef b():\n    return 2\n\n\ndef c():\n    return 3\n";
+    let other_source = "def d():\n    return 4\n";
+    fs::write(&main_file, main_source).expect("fixture should be written");
+    fs::write(&other_file, other_source).expect("fixture should be written");
+
+    let replace_b = edit_operation(
+        &main_file,
+        "function_definition",
+        named("b"),
+        &["--replace", "def b():\n    return 22"],
+    );
+    let delete_c = edit_operation(&main_file, "function_definition", named("c"), &["--delete"]);
+    let mut delete_d = edit_operation(
+        &other_file,
+        "function_definition",
+        named("d"),
+        &["--delete"],
+    );
+    let plan = json!({
+        "files": [
+            {"file": main_file, "operations": [replace_b, delete_c]},
+            {"file": other_file, "operations": [delete_d]},
+        ]
+    });
+
+    let (succeeded, response) = verify(&plan);
+    assert!(succeeded, "fresh plan should verify: {response}");
+    assert_eq!(response["applicable"], true);
+    assert_eq!(response["summary"]["applicable"], 3);
+
+    let replace_a = edit_operation(
+        &main_file,
+        "function_definition",
+        named("a"),
+        &["--replace", "def a():\n    return 11"],
+    );
+    let replace_return = edit_operation(
+        &main_file,
+        "return_statement",
+        |handle| handle["span"]["start"] == 13,
+        &["--replace", "return 0"],
+    );
+    fs::write(
+        &main_file,
+        "def a():\n    return 1\n\n\ndef b():\n    return 20\n",
+    )
+    .expect("fixture should be rewritten");
+    // Without a span hint, a duplicated node cannot be told apart from its copy.
+    delete_d["target"]
+        .as_object_mut()
+        .expect("target should be an object")
+        .remove("span_hint");
+    fs::write(&other_file, format!("{other_source}\n\n{other_source}"))
+        .expect("fixture should be rewritten");
+    let plan = json!({
+        "files": [
+            {"file": main_file, "operations": [replace_b, delete_c, replace_a, replace_return]},
+            {"file": other_file, "operations": [delete_d]},
+        ]
+    });
+
+    let (succeeded, response) = verify(&plan);
+    assert!(!succeeded, "stale plan should fail verification");
+    assert_eq!(response["applicable"], false);
+    let statuses = response["operations"]
+        .as_array()
+        .expect("operations should be an array")
+        .iter()
+        .map(|operation| {
+            (
+                operation["operation"].as_u64().expect("index should exist"),
+                operation["status"].as_str().expect("status should exist"),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        statuses,
+        vec![
+            (0, "stale"),
+            (1, "missing"),
+            (2, "conflict"),
+            (3, "conflict"),
+            (0, "ambiguous"),
+        ]
+    );
+    assert_eq!(response["operations"][1]["error"]["type"], "target_missing");
+    assert!(response["operations"][0].get("error").is_some());
+    assert_eq!(response["summary"]["conflict"], 2);
+    assert_eq!(
+        fs::read_to_string(&main_file).expect("file should be readable"),
+        "def a():\n    return 1\n\n\ndef b():\n    return 20\n"
+    );
+}
+
+#[test]
+fn operation_ids_and_correlation_ids_are_echoed_in_results_and_errors() {
+    let workspace = tempfile::tempdir().expect("tempdir should be created");
+    let file = workspace.path().join("main.py");
+    fs::write(
+        &file,
+        "def a():\n    return 1\n\n\ndef b():\n    return 2\n",
+    )
+    .expect("fixture should be written");
+
+    let mut replace_a = edit_operation(
+        &file,
+        "function_definition",
+        named("a"),
+        &["--replace", "def a():\n    return 11"],
+    );
+    replace_a["id"] = json!("replace-a");
+    replace_a["correlation_id"] = json!("intent-1");
+    let mut delete_b = edit_operation(&file, "function_definition", named("b"), &["--delete"]);
+    delete_b["correlation_id"] = json!("intent-2");
+    delete_b["target"]["expected_old_hash"] = json!("0000000000000000");
+    let plan = json!({
+        "files": [{"file": file, "operations": [replace_a, delete_b]}],
+        "correlation_id": "request-7"
+    });
+
+    let (applicable, response) = verify(&plan);
+    assert!(!applicable);
+    assert_eq!(response["correlation_id"], "request-7");
+    assert_eq!(response["operations"][0]["id"], "replace-a");
+    assert_eq!(response["operations"][0]["correlation_id"], "intent-1");
+    assert!(response["operations"][1].get("id").is_none());
+    assert_eq!(response["operations"][1]["correlation_id"], "intent-2");
+
+    let output =
+        common::run_identedit_with_stdin(&["apply", "--continue-on-error"], &plan.to_string());
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(response["correlation_id"], "request-7");
+    assert_eq!(response["operations"]["applied"][0]["id"], "replace-a");
+    assert_eq!(
+        response["operations"]["applied"][0]["correlation_id"],
+        "intent-1"
+    );
+    assert_eq!(
+        response["operations"]["failed"][0]["correlation_id"],
+        "intent-2"
+    );
+
+    // A failed request still names the intent it belonged to.
+    let output = common::run_identedit_with_stdin(&["apply"], &plan.to_string());
+    assert!(!output.status.success());
+    let response: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert!(response["error"]["type"].is_string());
+    assert_eq!(response["correlation_id"], "request-7");
+}